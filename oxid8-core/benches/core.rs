@@ -0,0 +1,55 @@
+//! Core benchmarks.
+//!
+//! Measures the paths that move the most memory: a frame of a game
+//! drawing every cycle, `reset`, savestates, and building a core, both
+//! on the stack and boxed. Run with `cargo bench -p oxid8-core --bench
+//! core` before and after changing the layout of `Oxid8`.
+
+use criterion::{Criterion, criterion_group, criterion_main};
+use oxid8_core::Oxid8;
+use std::hint::black_box;
+
+/// RND V0, FF; LD F, V0; DRW V0, V1, 5; ADD V1, 1; LD B, V0 into 300;
+/// JP 200, drawing and writing memory every frame.
+const ROM: [u8; 14] = [
+    0xC0, 0xFF, 0xF0, 0x29, 0xD0, 0x15, 0x71, 0x01, 0xA3, 0x00, 0xF0, 0x33, 0x12, 0x00,
+];
+
+fn core() -> Oxid8 {
+    let mut emu = Oxid8::new();
+    emu.load_font();
+    emu.load_rom_bytes(&ROM).unwrap();
+    emu
+}
+
+fn frames(c: &mut Criterion) {
+    let mut emu = core();
+    emu.set_cycles_per_frame(1000);
+    c.bench_function("next_frame", |b| b.iter(|| emu.next_frame().unwrap()));
+}
+
+fn reset(c: &mut Criterion) {
+    let mut emu = core();
+    c.bench_function("reset", |b| {
+        b.iter(|| {
+            emu.reset();
+            black_box(&emu);
+        })
+    });
+}
+
+fn savestates(c: &mut Criterion) {
+    let mut emu = core();
+    emu.next_frame().unwrap();
+    let state = emu.save_state();
+    c.bench_function("save_state", |b| b.iter(|| black_box(emu.save_state())));
+    c.bench_function("restore_state", |b| b.iter(|| emu.restore_state(&state)));
+}
+
+fn construct(c: &mut Criterion) {
+    c.bench_function("new", |b| b.iter(|| black_box(Oxid8::new())));
+    c.bench_function("boxed", |b| b.iter(|| black_box(Box::new(Oxid8::new()))));
+}
+
+criterion_group!(benches, frames, reset, savestates, construct);
+criterion_main!(benches);
@@ -0,0 +1,47 @@
+//! Generates an Octo program, assembles it, and runs it.
+//!
+//! ```text
+//! cargo run -p oxid8-core --example assemble --features octo
+//! ```
+//!
+//! The program draws the sixteen font digits in a grid. Generating
+//! source and assembling it with [`octo::assemble`] is easier than
+//! writing opcodes by hand, e.g. for test ROMs.
+
+use oxid8_core::{Oxid8, SCREEN_HEIGHT, SCREEN_WIDTH, octo};
+
+fn main() -> Result<(), String> {
+    let mut source = String::from(": main\n");
+    for digit in 0..16 {
+        let (x, y) = (2 + (digit % 8) * 8, 4 + (digit / 8) * 12);
+        source.push_str(&format!(
+            "  v0 := {digit}  v1 := {x}  v2 := {y}  i := hex v0  sprite v1 v2 5\n"
+        ));
+    }
+    source.push_str("  loop again\n");
+
+    let rom = octo::assemble(&source)?;
+    println!("{} bytes assembled from:\n{source}", rom.len());
+
+    let mut emu = Oxid8::new();
+    emu.load_font();
+    emu.load_rom_bytes(&rom).map_err(|e| e.to_string())?;
+    for _ in 0..10 {
+        emu.next_frame()?;
+    }
+
+    let screen = emu.screen_ref();
+    for y in 0..SCREEN_HEIGHT {
+        let row: String = (0..SCREEN_WIDTH)
+            .map(|x| {
+                if screen[y * SCREEN_WIDTH + x] {
+                    '█'
+                } else {
+                    ' '
+                }
+            })
+            .collect();
+        println!("{}", row.trim_end());
+    }
+    Ok(())
+}
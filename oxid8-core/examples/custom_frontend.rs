@@ -0,0 +1,78 @@
+//! A custom frontend on the async driver.
+//!
+//! ```text
+//! cargo run -p oxid8-core --example custom_frontend --features async -- <rom>
+//! ```
+//!
+//! The frontend prints frames to the terminal. It declares its
+//! [`Capabilities`]: like a plain terminal it never sees key releases
+//! and only keeps up with a few frames per second, so the driver times
+//! out keys and skips frames for it. Keys come from a script instead of
+//! the keyboard.
+
+use futures::{StreamExt, channel::mpsc, executor::block_on, join, stream};
+use oxid8_core::{
+    Oxid8, SCREEN_HEIGHT, SCREEN_WIDTH,
+    driver::{self, Frame, KeyEvent},
+    frontend::Capabilities,
+};
+use std::env;
+
+/// Frames printed before quitting.
+const FRAMES: usize = 20;
+
+/// Prints `frame` with half blocks, two rows per line.
+fn print_frame(n: usize, frame: &Frame) {
+    println!("frame {n}{}", if frame.sound { " (beep)" } else { "" });
+    for y in (0..SCREEN_HEIGHT).step_by(2) {
+        let line: String = (0..SCREEN_WIDTH)
+            .map(|x| {
+                let top = frame.screen[y * SCREEN_WIDTH + x];
+                let bottom = frame.screen[(y + 1) * SCREEN_WIDTH + x];
+                match (top, bottom) {
+                    (true, true) => '█',
+                    (true, false) => '▀',
+                    (false, true) => '▄',
+                    (false, false) => ' ',
+                }
+            })
+            .collect();
+        println!("{}", line.trim_end());
+    }
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let rom = env::args().nth(1).ok_or("usage: custom_frontend <rom>")?;
+
+    let mut core = Oxid8::new();
+    core.load_font();
+    core.load_rom(&rom)?;
+
+    let capabilities = Capabilities {
+        key_release: false,
+        audio: true,
+        max_frame_rate: Some(10),
+    };
+
+    // Presses only, the driver releases keys after a few frames.
+    let script = [0x5, 0x5, 0x6].map(|key| KeyEvent { key, pressed: true });
+    // Keep the input open after the script, the driver stops when it ends.
+    let input = stream::iter(script).chain(stream::pending());
+
+    let (frame_tx, frame_rx) = mpsc::channel(1);
+    block_on(async {
+        let render = async move {
+            // Dropping the receiver after `FRAMES` stops the driver.
+            let mut frames = frame_rx.take(FRAMES).enumerate();
+            while let Some((n, frame)) = frames.next().await {
+                print_frame(n, &frame);
+            }
+        };
+        let (result, ()) = join!(
+            driver::run_with_capabilities(&mut core, frame_tx, input, capabilities),
+            render
+        );
+        result
+    })?;
+    Ok(())
+}
@@ -0,0 +1,42 @@
+//! Runs a ROM without a window and saves the screen as a PNG.
+//!
+//! ```text
+//! cargo run -p oxid8-core --example headless_png -- <rom> [frames] [out.png]
+//! ```
+//!
+//! Handy for thumbnails and for checking a ROM boots in CI.
+
+use oxid8_core::Oxid8;
+use std::{env, error::Error, fs::File, io::BufWriter};
+
+/// Size of a CHIP-8 pixel in the image.
+const SCALE: usize = 8;
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let mut args = env::args().skip(1);
+    let rom = args
+        .next()
+        .ok_or("usage: headless_png <rom> [frames] [out.png]")?;
+    let frames: u32 = args.next().map(|n| n.parse()).transpose()?.unwrap_or(120);
+    let out = args.next().unwrap_or_else(|| "screen.png".into());
+
+    let mut emu = Oxid8::new();
+    emu.load_font();
+    emu.load_rom(&rom)?;
+    for _ in 0..frames {
+        emu.next_frame()?;
+    }
+
+    let image = emu.render_scaled(SCALE, false);
+    let mut encoder = png::Encoder::new(
+        BufWriter::new(File::create(&out)?),
+        image.width() as u32,
+        image.height() as u32,
+    );
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.write_header()?.write_image_data(image.as_bytes())?;
+
+    println!("{frames} frames, wrote {out}");
+    Ok(())
+}
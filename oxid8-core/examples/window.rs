@@ -0,0 +1,126 @@
+//! A minimal window frontend drawing with the CPU.
+//!
+//! ```text
+//! cargo run -p oxid8-core --example window -- <rom>
+//! ```
+//!
+//! Frames are scaled into a `softbuffer` surface, no GPU needed. The
+//! `pixels` crate works the same way but pins an older wgpu that can't
+//! share a build with `oxid8-wgpu`. Keys use the standard [`Keymap`].
+
+use oxid8_core::{Oxid8, SCREEN_HEIGHT, SCREEN_WIDTH, keymap::Keymap};
+use softbuffer::{Context, Surface};
+use std::{env, error::Error, num::NonZeroU32, rc::Rc, time::Instant};
+use winit::{
+    application::ApplicationHandler,
+    dpi::LogicalSize,
+    event::WindowEvent,
+    event_loop::{ActiveEventLoop, ControlFlow, EventLoop},
+    keyboard::Key,
+    window::{Window, WindowId},
+};
+
+const FOREGROUND: u32 = 0x00FF_FFFF;
+const BACKGROUND: u32 = 0x0000_0000;
+
+struct App {
+    emu: Oxid8,
+    window: Option<Rc<Window>>,
+    surface: Option<Surface<Rc<Window>, Rc<Window>>>,
+    next_frame: Instant,
+}
+
+impl App {
+    /// Scales the screen to the window, nearest neighbour.
+    fn draw(&mut self) {
+        let (Some(window), Some(surface)) = (&self.window, &mut self.surface) else {
+            return;
+        };
+        let size = window.inner_size();
+        let (Some(width), Some(height)) =
+            (NonZeroU32::new(size.width), NonZeroU32::new(size.height))
+        else {
+            return;
+        };
+        surface.resize(width, height).unwrap();
+
+        let (width, height) = (size.width as usize, size.height as usize);
+        let screen = self.emu.screen_ref();
+        let mut buffer = surface.buffer_mut().unwrap();
+        for (y, row) in buffer.chunks_exact_mut(width).enumerate() {
+            let sy = y * SCREEN_HEIGHT / height;
+            for (x, px) in row.iter_mut().enumerate() {
+                let sx = x * SCREEN_WIDTH / width;
+                *px = if screen[sy * SCREEN_WIDTH + sx] {
+                    FOREGROUND
+                } else {
+                    BACKGROUND
+                };
+            }
+        }
+        buffer.present().unwrap();
+    }
+}
+
+impl ApplicationHandler for App {
+    fn resumed(&mut self, event_loop: &ActiveEventLoop) {
+        if self.window.is_some() {
+            return;
+        }
+        let attributes = Window::default_attributes()
+            .with_title("Oxid8")
+            .with_inner_size(LogicalSize::new(640, 320));
+        let window = Rc::new(event_loop.create_window(attributes).unwrap());
+        let context = Context::new(window.clone()).unwrap();
+        self.surface = Some(Surface::new(&context, window.clone()).unwrap());
+        self.window = Some(window);
+    }
+
+    fn window_event(&mut self, event_loop: &ActiveEventLoop, _: WindowId, event: WindowEvent) {
+        match event {
+            WindowEvent::CloseRequested => event_loop.exit(),
+            WindowEvent::KeyboardInput { event, .. } => {
+                if let Key::Character(s) = &event.logical_key
+                    && let Some(c) = s.chars().next()
+                    && let Some(k) = Keymap::Standard.key(c.to_ascii_lowercase())
+                {
+                    self.emu.set_key(k as usize, event.state.is_pressed());
+                }
+            }
+            WindowEvent::RedrawRequested => self.draw(),
+            _ => {}
+        }
+    }
+
+    fn about_to_wait(&mut self, event_loop: &ActiveEventLoop) {
+        if Instant::now() >= self.next_frame {
+            if let Err(e) = self.emu.next_frame() {
+                eprintln!("{e}");
+                event_loop.exit();
+                return;
+            }
+            self.next_frame += self.emu.timer_rate().tick();
+            if let Some(window) = &self.window {
+                window.request_redraw();
+            }
+        }
+        event_loop.set_control_flow(ControlFlow::WaitUntil(self.next_frame));
+    }
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let rom = env::args().nth(1).ok_or("usage: window <rom>")?;
+
+    let mut emu = Oxid8::new();
+    emu.load_font();
+    emu.load_rom(&rom)?;
+
+    let mut app = App {
+        emu,
+        window: None,
+        surface: None,
+        next_frame: Instant::now(),
+    };
+    EventLoop::new()?.run_app(&mut app)?;
+    Ok(())
+}
@@ -0,0 +1,152 @@
+//! Extract ROMs from zipped archives.
+//!
+//! Many ROM collections ship zipped. An archive is accepted when it
+//! contains exactly one `.ch8` file, which is extracted in memory.
+//! Directories and the `__MACOSX/` and `._` resource forks macOS adds
+//! when zipping are not counted.
+
+use std::io::{self, Cursor, Read};
+
+use crate::layout::MAX_ROM_SIZE;
+
+/// Local file header signature found at the start of every zip archive.
+const ZIP_MAGIC: [u8; 4] = [b'P', b'K', 0x03, 0x04];
+
+/// Returns true if `data` looks like a zip archive.
+///
+/// The signature decodes to `5xyB` which is not a valid Chip-8
+/// instruction, so a raw ROM is never mistaken for an archive.
+#[must_use]
+pub fn is_zip(data: &[u8]) -> bool {
+    data.starts_with(&ZIP_MAGIC)
+}
+
+/// Extracts the single `.ch8` ROM contained in a zip archive.
+///
+/// # Errors
+///
+/// Returns an `InvalidData` error if the archive cannot be read or does
+/// not contain exactly one `.ch8` file, and a `FileTooLarge` error if the
+/// ROM would not fit in memory.
+pub fn extract_rom(data: &[u8]) -> io::Result<Vec<u8>> {
+    let mut archive = zip::ZipArchive::new(Cursor::new(data)).map_err(invalid)?;
+
+    let mut roms = (0..archive.len()).filter(|&i| archive.name_for_index(i).is_some_and(is_rom));
+
+    let index = match (roms.next(), roms.next()) {
+        (Some(index), None) => index,
+        (None, _) => return Err(invalid("no .ch8 file in archive")),
+        (Some(_), Some(_)) => return Err(invalid("more than one .ch8 file in archive")),
+    };
+
+    let file = archive.by_index(index).map_err(invalid)?;
+
+    // Never decompress more than fits in memory.
+    let limit = MAX_ROM_SIZE as u64;
+    let mut rom = Vec::new();
+    file.take(limit + 1).read_to_end(&mut rom)?;
+
+    if rom.len() as u64 > limit {
+        return Err(io::Error::new(
+            io::ErrorKind::FileTooLarge,
+            "ROM in archive too large",
+        ));
+    }
+
+    Ok(rom)
+}
+
+/// Returns true if the entry `name` is a `.ch8` file, not a directory
+/// or a macOS resource fork.
+fn is_rom(name: &str) -> bool {
+    let file = name.rsplit('/').next().unwrap_or(name);
+    !name.ends_with('/')
+        && !name.starts_with("__MACOSX/")
+        && !file.starts_with("._")
+        && file.to_ascii_lowercase().ends_with(".ch8")
+}
+
+fn invalid(err: impl Into<Box<dyn std::error::Error + Send + Sync>>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, err)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layout::{RAM_SIZE, START_ADDR};
+    use std::io::Write;
+    use zip::{ZipWriter, write::SimpleFileOptions};
+
+    fn make_zip(files: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+        for (name, data) in files {
+            writer
+                .start_file(*name, SimpleFileOptions::default())
+                .unwrap();
+            writer.write_all(data).unwrap();
+        }
+        writer.finish().unwrap().into_inner()
+    }
+
+    #[test]
+    fn detect_zip() {
+        assert!(is_zip(&make_zip(&[("a.ch8", &[0x00, 0xE0])])));
+        assert!(!is_zip(&[0x00, 0xE0, 0x12, 0x00]));
+        assert!(!is_zip(&[]));
+    }
+
+    #[test]
+    fn extract_single_rom() {
+        let data = make_zip(&[("README.txt", b"hello"), ("GAME.CH8", &[0x00, 0xE0])]);
+        assert_eq!(extract_rom(&data).unwrap(), vec![0x00, 0xE0]);
+    }
+
+    #[test]
+    fn extract_from_macos_zip() {
+        let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+        let options = SimpleFileOptions::default();
+        writer.add_directory("roms.ch8/", options).unwrap();
+        writer.add_directory("__MACOSX/", options).unwrap();
+        for (name, data) in [
+            ("roms.ch8/GAME.ch8", &[0x00, 0xE0]),
+            ("__MACOSX/roms.ch8/._GAME.ch8", &[0x00, 0x05]),
+            ("roms.ch8/._GAME.ch8", &[0x00, 0x05]),
+        ] {
+            writer.start_file(name, options).unwrap();
+            writer.write_all(data).unwrap();
+        }
+        let data = writer.finish().unwrap().into_inner();
+        assert_eq!(extract_rom(&data).unwrap(), vec![0x00, 0xE0]);
+    }
+
+    #[test]
+    fn extract_no_rom() {
+        let data = make_zip(&[("README.txt", b"hello")]);
+        let err = extract_rom(&data).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn extract_many_roms() {
+        let data = make_zip(&[("a.ch8", &[0x00]), ("b.ch8", &[0x00])]);
+        let err = extract_rom(&data).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn extract_too_large() {
+        let big = vec![0; RAM_SIZE];
+        let data = make_zip(&[("big.ch8", &big)]);
+        let err = extract_rom(&data).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::FileTooLarge);
+    }
+
+    #[test]
+    fn load_zipped_rom() {
+        let data = make_zip(&[("a.ch8", &[0x12, 0x34])]);
+        let mut emu = crate::Oxid8::new();
+        emu.load_rom_bytes(&data).unwrap();
+        assert_eq!(emu.ram[START_ADDR.index()], 0x12);
+        assert_eq!(emu.ram[START_ADDR.index() + 1], 0x34);
+    }
+}
@@ -0,0 +1,143 @@
+//! Deterministic audio rendering.
+//!
+//! CHIP-8 has a single beeper that sounds while the sound timer is
+//! nonzero. [`Beeper`] renders it as a square wave with a whole number
+//! of samples per frame, so the same frames always produce the same
+//! samples. [`write_wav`] saves them as a 16-bit mono WAV file.
+
+use std::io::{self, Write};
+
+use crate::TimerRate;
+
+/// Default sample rate in Hz, divisible by both 60 and 50.
+pub const SAMPLE_RATE: u32 = 44_100;
+
+/// Pitch of the beep in Hz.
+pub const TONE: u32 = 440;
+
+/// Peak amplitude of the square wave.
+const AMPLITUDE: i16 = 8_000;
+
+/// Square wave generator for the beeper.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Beeper {
+    sample_rate: u32,
+    phase: u32,
+}
+
+impl Beeper {
+    /// New beeper rendering at `sample_rate`.
+    #[must_use]
+    pub fn new(sample_rate: u32) -> Self {
+        Self {
+            sample_rate,
+            phase: 0,
+        }
+    }
+
+    /// Samples rendered per frame at `rate`.
+    #[must_use]
+    pub fn samples_per_frame(&self, rate: TimerRate) -> usize {
+        (self.sample_rate / rate.hz()) as usize
+    }
+
+    /// Appends one frame of samples to `out`, a tone while `on` and
+    /// silence otherwise. The phase carries over between frames so
+    /// consecutive beeps are continuous.
+    pub fn render_frame(&mut self, on: bool, rate: TimerRate, out: &mut Vec<i16>) {
+        for _ in 0..self.samples_per_frame(rate) {
+            if !on {
+                out.push(0);
+                continue;
+            }
+            out.push(if self.phase < self.sample_rate / 2 {
+                AMPLITUDE
+            } else {
+                -AMPLITUDE
+            });
+            self.phase = (self.phase + TONE) % self.sample_rate;
+        }
+    }
+}
+
+impl Default for Beeper {
+    fn default() -> Self {
+        Self::new(SAMPLE_RATE)
+    }
+}
+
+/// Writes `samples` as a 16-bit PCM mono WAV file.
+///
+/// # Errors
+///
+/// Returns an error if writing fails.
+pub fn write_wav(mut w: impl Write, sample_rate: u32, samples: &[i16]) -> io::Result<()> {
+    let data_len = (samples.len() * 2) as u32;
+
+    w.write_all(b"RIFF")?;
+    w.write_all(&(36 + data_len).to_le_bytes())?;
+    w.write_all(b"WAVE")?;
+
+    w.write_all(b"fmt ")?;
+    w.write_all(&16u32.to_le_bytes())?; // chunk size
+    w.write_all(&1u16.to_le_bytes())?; // PCM
+    w.write_all(&1u16.to_le_bytes())?; // mono
+    w.write_all(&sample_rate.to_le_bytes())?;
+    w.write_all(&(sample_rate * 2).to_le_bytes())?; // byte rate
+    w.write_all(&2u16.to_le_bytes())?; // block align
+    w.write_all(&16u16.to_le_bytes())?; // bits per sample
+
+    w.write_all(b"data")?;
+    w.write_all(&data_len.to_le_bytes())?;
+    for sample in samples {
+        w.write_all(&sample.to_le_bytes())?;
+    }
+    w.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frame_length() {
+        let mut beeper = Beeper::default();
+        let mut samples = Vec::new();
+        beeper.render_frame(false, TimerRate::Ntsc, &mut samples);
+        assert_eq!(samples.len(), 735);
+        assert!(samples.iter().all(|&s| s == 0));
+
+        beeper.render_frame(true, TimerRate::Pal, &mut samples);
+        assert_eq!(samples.len(), 735 + 882);
+        assert!(samples[735..].contains(&AMPLITUDE));
+        assert!(samples[735..].contains(&-AMPLITUDE));
+    }
+
+    #[test]
+    fn deterministic() {
+        let render = || {
+            let mut beeper = Beeper::default();
+            let mut samples = Vec::new();
+            for on in [true, false, true, true] {
+                beeper.render_frame(on, TimerRate::Ntsc, &mut samples);
+            }
+            samples
+        };
+        assert_eq!(render(), render());
+    }
+
+    #[test]
+    fn wav_header() {
+        let mut wav = Vec::new();
+        write_wav(&mut wav, SAMPLE_RATE, &[1, -1]).unwrap();
+        assert_eq!(wav.len(), 44 + 4);
+        assert_eq!(&wav[0..4], b"RIFF");
+        assert_eq!(&wav[8..16], b"WAVEfmt ");
+        assert_eq!(
+            u32::from_le_bytes(wav[24..28].try_into().unwrap()),
+            SAMPLE_RATE
+        );
+        assert_eq!(&wav[36..40], b"data");
+        assert_eq!(&wav[44..], &[1, 0, 0xFF, 0xFF]);
+    }
+}
@@ -0,0 +1,230 @@
+//! Breakpoints on the program counter for debuggers.
+//!
+//! [`Oxid8::next_frame_until`] emulates a frame like
+//! [`Oxid8::next_frame_with_input`] but stops before an instruction at a
+//! breakpoint. To resume, step over it with [`Oxid8::run_cycle`] and
+//! carry on with the next frame. [`Oxid8::step_over`] runs a `CALL`
+//! through to its return, for stepping through a game a line at a time.
+//!
+//! ```
+//! use oxid8_core::{Oxid8, breakpoint::Breakpoints};
+//!
+//! let mut emu = Oxid8::new();
+//! // ADD V0, 1; JP 200
+//! emu.load_rom_bytes(&[0x70, 0x01, 0x12, 0x00]).unwrap();
+//!
+//! let mut breakpoints = Breakpoints::default();
+//! breakpoints.toggle(0x202);
+//! assert_eq!(emu.next_frame_until(&[], &breakpoints), Ok(Some(0x202)));
+//!
+//! emu.run_cycle().unwrap();
+//! assert_eq!(emu.pc(), 0x200);
+//! ```
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::{Oxid8, input::InputEvent};
+
+/// A set of instruction addresses to stop at.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Breakpoints {
+    /// Sorted and without duplicates.
+    addrs: Vec<u16>,
+}
+
+impl Breakpoints {
+    /// Adds a breakpoint at `addr`, or removes it if there is one.
+    /// Returns true if it was added.
+    pub fn toggle(&mut self, addr: u16) -> bool {
+        match self.addrs.binary_search(&addr) {
+            Ok(i) => {
+                self.addrs.remove(i);
+                false
+            }
+            Err(i) => {
+                self.addrs.insert(i, addr);
+                true
+            }
+        }
+    }
+
+    /// Returns true if there is a breakpoint at `addr`.
+    #[must_use]
+    pub fn contains(&self, addr: u16) -> bool {
+        self.addrs.binary_search(&addr).is_ok()
+    }
+
+    /// Removes every breakpoint.
+    pub fn clear(&mut self) {
+        self.addrs.clear();
+    }
+
+    /// Returns true if there are no breakpoints.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.addrs.is_empty()
+    }
+
+    /// Breakpoint addresses in ascending order.
+    pub fn iter(&self) -> impl Iterator<Item = u16> + '_ {
+        self.addrs.iter().copied()
+    }
+}
+
+/// Serialized as a list of hex addresses like `["0x2A4"]`.
+#[cfg(feature = "serde")]
+impl Serialize for Breakpoints {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.iter().map(|addr| format!("{addr:#05X}")))
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for Breakpoints {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let mut breakpoints = Breakpoints::default();
+        for addr in Vec::<String>::deserialize(deserializer)? {
+            let hex = addr.trim_start_matches("0x").trim_start_matches("0X");
+            let addr = u16::from_str_radix(hex, 16)
+                .map_err(|_| serde::de::Error::custom(format!("Invalid address: {addr}")))?;
+            if !breakpoints.contains(addr) {
+                breakpoints.toggle(addr);
+            }
+        }
+        Ok(breakpoints)
+    }
+}
+
+impl Oxid8 {
+    /// Emulates a frame like `next_frame_with_input`, stopping before
+    /// an instruction at any of `breakpoints`.
+    ///
+    /// Returns the address stopped at, if any. The rest of the frame is
+    /// skipped: events not yet applied are applied and the timers are
+    /// not decremented.
+    ///
+    /// # Errors
+    ///
+    /// See `next_frame`.
+    ///
+    /// # Panics
+    ///
+    /// See `next_frame_with_input`.
+    pub fn next_frame_until(
+        &mut self,
+        events: &[InputEvent],
+        breakpoints: &Breakpoints,
+    ) -> Result<Option<u16>, String> {
+        self.run_frame(events, |core| {
+            breakpoints.addrs.binary_search(&core.pc.get()).is_ok()
+        })
+    }
+
+    /// Runs one instruction, or if it is a `CALL`, the whole subroutine
+    /// up to its return, with the timers decremented every
+    /// `cycles_per_frame` cycles so delays end. Returns true once back
+    /// after the `CALL`, or false if it stopped first before an
+    /// instruction at one of `breakpoints` or after `max_cycles`.
+    ///
+    /// # Errors
+    ///
+    /// See `run_cycle`.
+    pub fn step_over(
+        &mut self,
+        breakpoints: &Breakpoints,
+        max_cycles: u32,
+    ) -> Result<bool, String> {
+        let opcode = self.ram[self.pc.index()];
+        let (ret, sp) = (self.pc.get() + 2, self.sp);
+        self.run_cycle()?;
+        if opcode >> 4 != 0x2 {
+            return Ok(true);
+        }
+        for cycle in 1..max_cycles {
+            if self.pc.get() == ret && self.sp == sp {
+                return Ok(true);
+            }
+            if breakpoints.contains(self.pc.get()) {
+                return Ok(false);
+            }
+            if cycle.is_multiple_of(self.cycles_per_frame()) {
+                self.dec_timers();
+            }
+            self.run_cycle()?;
+        }
+        Ok(self.pc.get() == ret && self.sp == sp)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layout::Addr;
+
+    #[test]
+    fn toggle() {
+        let mut breakpoints = Breakpoints::default();
+        assert!(breakpoints.toggle(0x230));
+        assert!(breakpoints.toggle(0x200));
+        assert!(breakpoints.contains(0x230));
+        assert_eq!(breakpoints.iter().collect::<Vec<_>>(), [0x200, 0x230]);
+
+        assert!(!breakpoints.toggle(0x230));
+        assert!(!breakpoints.contains(0x230));
+        breakpoints.clear();
+        assert!(breakpoints.is_empty());
+    }
+
+    #[test]
+    fn stops_before_instruction() {
+        let mut emu = Oxid8::new();
+        // ADD V0, 1; LD DT, V0; JP 200
+        emu.load_rom_bytes(&[0x70, 0x01, 0xF0, 0x15, 0x12, 0x00])
+            .unwrap();
+        emu.set_cycles_per_frame(10);
+        let mut breakpoints = Breakpoints::default();
+        breakpoints.toggle(0x204);
+
+        assert_eq!(emu.next_frame_until(&[], &breakpoints), Ok(Some(0x204)));
+        assert_eq!(emu.v_reg[0], 1);
+        // Stopped mid-frame, the timers were not decremented.
+        assert_eq!(emu.dt, 1);
+
+        // Stepping over resumes, then stops again on the next loop.
+        emu.run_cycle().unwrap();
+        assert_eq!(emu.next_frame_until(&[], &breakpoints), Ok(Some(0x204)));
+        assert_eq!(emu.v_reg[0], 2);
+
+        breakpoints.clear();
+        assert_eq!(emu.next_frame_until(&[], &breakpoints), Ok(None));
+    }
+
+    #[test]
+    fn step_over() {
+        let mut emu = Oxid8::new();
+        // CALL 206; ADD V1, 1; JP 202;
+        // 206: LD V0, 3; LD DT, V0; LD V0, DT; SE V0, 0; JP 20A; RET
+        emu.load_rom_bytes(&[
+            0x22, 0x06, 0x71, 0x01, 0x12, 0x02, 0x60, 0x03, 0xF0, 0x15, 0xF0, 0x07, 0x30, 0x00,
+            0x12, 0x0A, 0x00, 0xEE,
+        ])
+        .unwrap();
+        let mut breakpoints = Breakpoints::default();
+
+        // Waits out the delay timer in the subroutine.
+        assert_eq!(emu.step_over(&breakpoints, 1000), Ok(true));
+        assert_eq!(emu.pc(), 0x202);
+        assert_eq!(emu.dt, 0);
+        // Other instructions run alone.
+        assert_eq!(emu.step_over(&breakpoints, 1000), Ok(true));
+        assert_eq!(emu.v_reg[1], 1);
+
+        emu.pc = Addr::new(0x200);
+        assert_eq!(emu.step_over(&breakpoints, 10), Ok(false));
+        emu.pc = Addr::new(0x200);
+        breakpoints.toggle(0x20C);
+        assert_eq!(emu.step_over(&breakpoints, 1000), Ok(false));
+        assert_eq!(emu.pc(), 0x20C);
+    }
+}
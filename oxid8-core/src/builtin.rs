@@ -0,0 +1,396 @@
+//! Roms built into the interpreter, assembled from Octo source at
+//! runtime.
+//!
+//! The controls test draws the keypad in its original layout, lights
+//! each key while it is held, and echoes the last key pressed on the
+//! right. It needs no rom files, so frontends can offer it as a
+//! "Controls test" to check input handling on a new platform or
+//! terminal.
+//!
+//! The splash shows the title and version for [`SPLASH_FRAMES`] when a
+//! frontend starts, drawn by the core running a rom like any other. A
+//! [`Splash`] checks the screen it leaves, so every launch doubles as
+//! a smoke test of the core, and frontends boot the same way. Players
+//! skip it with `splash = false` in `defaults.toml`, see
+//! [`config`](crate::config).
+//!
+//! ```
+//! use oxid8_core::{Oxid8, builtin};
+//!
+//! let mut emu = Oxid8::new();
+//! emu.load_font();
+//! emu.load_rom_bytes(&builtin::controls_test()).unwrap();
+//!
+//! let mut splash = builtin::Splash::new("0.2.0");
+//! while !splash.is_done() {
+//!     splash.frame().unwrap();
+//! }
+//! ```
+
+use std::fmt::Write;
+
+use crate::{FONTSET, Oxid8, SCREEN_AREA, SCREEN_WIDTH, layout::GLYPH_SIZE, octo};
+
+/// Name frontends show for [`controls_test`].
+pub const CONTROLS_TEST_NAME: &str = "Controls test";
+
+/// Octo source of [`controls_test`].
+pub const CONTROLS_TEST_SOURCE: &str = "\
+# Draws the keypad, lights held keys, and echoes the last key pressed.
+
+:alias digit v2
+:alias held v3
+:alias last v4
+
+: main
+  clear
+  last := 255
+  digit := 0
+  loop
+    position
+    v0 += 1
+    v1 += 1
+    i := hex digit
+    sprite v0 v1 5
+    digit += 1
+    while digit != 16
+  again
+
+  loop
+    digit := 0
+    loop
+      held := 0
+      if digit key then held := 1
+      i := pressed
+      i += digit
+      load v0
+      if v0 != held begin
+        v0 := held
+        i := pressed
+        i += digit
+        save v0
+        # Held keys are drawn inverted.
+        position
+        i := highlight
+        sprite v0 v1 7
+        if held == 1 begin
+          echo
+          last := digit
+          echo
+        end
+      end
+      digit += 1
+      while digit != 16
+    again
+  again
+
+# v0, v1 := the top left of the key `digit` on the keypad.
+: position
+  i := positions
+  i += digit
+  i += digit
+  load v1
+;
+
+# Draws or erases the last key pressed.
+: echo
+  if last == 255 then return
+  v0 := 48
+  v1 := 13
+  i := hex last
+  sprite v0 v1 5
+;
+
+# Key 0 to F, laid out like the COSMAC VIP keypad.
+: positions
+  12 25   4  1  12  1  20  1
+   4  9  12  9  20  9   4 17
+  12 17  20 17   4 25  20 25
+  28  1  28  9  28 17  28 25
+
+: highlight
+  0xFC 0xFC 0xFC 0xFC 0xFC 0xFC 0xFC
+
+: pressed
+  0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0
+";
+
+/// The controls test rom, see the [module docs](self).
+///
+/// # Panics
+///
+/// Never, the source is assembled in the tests.
+#[must_use]
+pub fn controls_test() -> Vec<u8> {
+    octo::assemble(CONTROLS_TEST_SOURCE).expect("controls test assembles")
+}
+
+/// Frames the splash is shown for, a second at 60Hz.
+pub const SPLASH_FRAMES: u32 = 60;
+
+/// "OXID-8", 5x7 pixels a letter.
+const TITLE: [[u8; 7]; 6] = [
+    [0x70, 0x88, 0x88, 0x88, 0x88, 0x88, 0x70],
+    [0x88, 0x88, 0x50, 0x20, 0x50, 0x88, 0x88],
+    [0xF8, 0x20, 0x20, 0x20, 0x20, 0x20, 0xF8],
+    [0xF0, 0x88, 0x88, 0x88, 0x88, 0x88, 0xF0],
+    [0x00, 0x00, 0x00, 0xF8, 0x00, 0x00, 0x00],
+    [0x70, 0x88, 0x88, 0x70, 0x88, 0x88, 0x70],
+];
+
+/// A dot between version numbers, as tall as a digit.
+const DOT: [u8; GLYPH_SIZE] = [0, 0, 0, 0, 0x80];
+
+/// The top of the title and of the version.
+const TITLE_Y: usize = 8;
+const VERSION_Y: usize = 20;
+
+/// Letters of the title are a pixel apart.
+const LETTER_WIDTH: usize = 6;
+
+/// The digits of `version` drawn, `None` for dots, up to anything
+/// else, e.g. `0.2.0` of `0.2.0-beta`. Nothing if they don't fit.
+fn version_glyphs(version: &str) -> Vec<Option<u32>> {
+    let glyphs: Vec<_> = version
+        .chars()
+        .take_while(|c| c.is_ascii_digit() || *c == '.')
+        .map(|c| c.to_digit(10))
+        .collect();
+    match glyphs
+        .iter()
+        .map(|&glyph| glyph_width(glyph))
+        .sum::<usize>()
+        <= SCREEN_WIDTH
+    {
+        true => glyphs,
+        false => Vec::new(),
+    }
+}
+
+/// Digits are a pixel apart, as are dots.
+fn glyph_width(glyph: Option<u32>) -> usize {
+    match glyph {
+        Some(_) => 5,
+        None => 2,
+    }
+}
+
+/// Octo source of the splash for `version`, see the
+/// [module docs](self).
+#[must_use]
+pub fn splash_source(version: &str) -> String {
+    let mut source =
+        String::from("# The title and version, shown for a second.\n\n: main\n  clear\n");
+    let mut x = (SCREEN_WIDTH - (TITLE.len() * LETTER_WIDTH - 1)) / 2;
+    let _ = writeln!(source, "  v1 := {TITLE_Y}");
+    for letter in 0..TITLE.len() {
+        let _ = writeln!(source, "  v0 := {x}  i := title{letter}  sprite v0 v1 7");
+        x += LETTER_WIDTH;
+    }
+
+    let glyphs = version_glyphs(version);
+    let mut x = (SCREEN_WIDTH
+        - glyphs
+            .iter()
+            .map(|&glyph| glyph_width(glyph))
+            .sum::<usize>())
+        / 2;
+    let _ = writeln!(source, "  v1 := {VERSION_Y}");
+    for glyph in glyphs {
+        let _ = match glyph {
+            Some(digit) => writeln!(
+                source,
+                "  v0 := {x}  v2 := {digit}  i := hex v2  sprite v0 v1 5"
+            ),
+            None => writeln!(source, "  v0 := {x}  i := dot  sprite v0 v1 5"),
+        };
+        x += glyph_width(glyph);
+    }
+
+    let _ = write!(
+        source,
+        "  v0 := {SPLASH_FRAMES}
+  delay := v0
+  loop
+    v0 := delay
+    while v0 != 0
+  again
+  loop again
+"
+    );
+    for (letter, rows) in TITLE.iter().enumerate() {
+        let rows: Vec<_> = rows.iter().map(|row| format!("{row:#04X}")).collect();
+        let _ = write!(source, "\n: title{letter}\n  {}\n", rows.join(" "));
+    }
+    let dot: Vec<_> = DOT.iter().map(u8::to_string).collect();
+    let _ = write!(source, "\n: dot\n  {}\n", dot.join(" "));
+    source
+}
+
+/// The splash rom for `version`, see the [module docs](self).
+///
+/// # Panics
+///
+/// Never, the source is assembled in the tests.
+#[must_use]
+pub fn splash(version: &str) -> Vec<u8> {
+    octo::assemble(&splash_source(version)).expect("splash assembles")
+}
+
+/// Runs the splash for [`SPLASH_FRAMES`] and checks what it drew.
+#[derive(Debug)]
+pub struct Splash {
+    emu: Box<Oxid8>,
+    frames: u32,
+    /// Pixels lit once the title and version are drawn.
+    lit: usize,
+}
+
+impl Splash {
+    /// Loads the splash for `version`, the frontend's.
+    ///
+    /// # Panics
+    ///
+    /// Never, the rom always fits.
+    #[must_use]
+    pub fn new(version: &str) -> Self {
+        let mut emu = Oxid8::boxed();
+        emu.load_font();
+        emu.load_rom_bytes(&splash(version))
+            .expect("splash fits in memory");
+        let ones = |rows: &[u8]| {
+            rows.iter()
+                .map(|row| row.count_ones() as usize)
+                .sum::<usize>()
+        };
+        let title: usize = TITLE.iter().map(|letter| ones(letter)).sum();
+        let version: usize = version_glyphs(version)
+            .into_iter()
+            .map(|glyph| match glyph {
+                Some(digit) => {
+                    let start = digit as usize * GLYPH_SIZE;
+                    ones(&FONTSET[start..start + GLYPH_SIZE])
+                }
+                None => ones(&DOT),
+            })
+            .sum();
+        Splash {
+            emu,
+            frames: 0,
+            lit: title + version,
+        }
+    }
+
+    /// Runs the next frame, nothing once [`Splash::is_done`].
+    ///
+    /// # Errors
+    ///
+    /// If the core fails to run the splash, or, after the last frame,
+    /// if the screen isn't what the splash draws. Either is a bug in
+    /// the core.
+    pub fn frame(&mut self) -> Result<(), String> {
+        if self.is_done() {
+            return Ok(());
+        }
+        self.emu.next_frame()?;
+        self.frames += 1;
+        if self.is_done() {
+            self.check()?;
+        }
+        Ok(())
+    }
+
+    /// Returns true once [`SPLASH_FRAMES`] have run.
+    #[must_use]
+    pub fn is_done(&self) -> bool {
+        self.frames >= SPLASH_FRAMES
+    }
+
+    /// The splash's screen.
+    #[must_use]
+    pub fn screen_ref(&self) -> &[bool; SCREEN_AREA] {
+        self.emu.screen_ref()
+    }
+
+    /// Checks the title and version were drawn, without collisions.
+    fn check(&self) -> Result<(), String> {
+        let lit = self.screen_ref().iter().filter(|&&px| px).count();
+        match lit == self.lit {
+            true => Ok(()),
+            false => Err(format!("Splash drew {lit} pixels instead of {}", self.lit)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lit(emu: &Oxid8, x: usize, y: usize, width: usize, height: usize) -> usize {
+        let screen = emu.screen_ref();
+        (y..y + height)
+            .flat_map(|y| (x..x + width).map(move |x| screen[x + y * SCREEN_WIDTH]))
+            .filter(|&px| px)
+            .count()
+    }
+
+    /// Long enough to draw the keypad or check every key.
+    fn run(emu: &mut Oxid8) {
+        for _ in 0..60 {
+            emu.next_frame().unwrap();
+        }
+    }
+
+    #[test]
+    fn controls_test_echoes_keys() {
+        let mut emu = Oxid8::new();
+        emu.load_font();
+        emu.load_rom_bytes(&controls_test()).unwrap();
+        run(&mut emu);
+
+        // Key 1 is drawn in the top left, nothing is echoed yet.
+        let one_key = lit(&emu, 4, 1, 6, 7);
+        assert!(one_key > 0);
+        assert_eq!(lit(&emu, 48, 13, 4, 5), 0);
+
+        emu.set_key(0x1, true);
+        run(&mut emu);
+        // Inverted while held.
+        assert_eq!(lit(&emu, 4, 1, 6, 7), 6 * 7 - one_key);
+        let one = lit(&emu, 48, 13, 4, 5);
+        assert!(one > 0);
+
+        emu.set_key(0x1, false);
+        emu.set_key(0xF, true);
+        run(&mut emu);
+        assert_eq!(lit(&emu, 4, 1, 6, 7), one_key);
+        assert!(lit(&emu, 28, 25, 6, 7) > 0);
+        // F replaces 1 in the echo.
+        assert_ne!(lit(&emu, 48, 13, 4, 5), one);
+    }
+
+    fn run_splash(version: &str) -> Splash {
+        let mut splash = Splash::new(version);
+        for _ in 0..SPLASH_FRAMES + 5 {
+            splash.frame().unwrap();
+        }
+        assert!(splash.is_done());
+        splash
+    }
+
+    #[test]
+    fn splash_draws_title_and_version() {
+        let splash = run_splash("0.2.0");
+        // "O" in the top left of the title, the dot in the version.
+        let title_x = (SCREEN_WIDTH - (TITLE.len() * LETTER_WIDTH - 1)) / 2;
+        let screen = splash.screen_ref();
+        assert!(screen[title_x + 1 + TITLE_Y * SCREEN_WIDTH]);
+        assert!(lit(&splash.emu, 0, VERSION_Y, SCREEN_WIDTH, 5) > 0);
+        assert_eq!(splash.lit, lit(&splash.emu, 0, 0, SCREEN_WIDTH, 32));
+
+        // Only numbers and dots are drawn, nothing too long to fit.
+        assert_eq!(run_splash("0.2.0-beta").screen_ref(), screen);
+        let long = run_splash(&"1.".repeat(20));
+        assert_eq!(lit(&long.emu, 0, VERSION_Y, SCREEN_WIDTH, 5), 0);
+    }
+}
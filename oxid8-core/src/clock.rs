@@ -0,0 +1,478 @@
+//! Clocks that decide when frames are due.
+//!
+//! Pacing emulation off wall-clock time and playing audio off the sound
+//! card's clock lets the two drift apart over a long session, ending in
+//! crackles or growing latency. With audio output, pace frames off the
+//! samples the audio callback has consumed instead: [`AudioClock`] asks
+//! for a frame whenever a frame's worth of samples has been played, so
+//! the emulator produces exactly as much audio as is played.
+//!
+//! Frontends that run cycles and timers at their own rates rather than
+//! whole frames use [`FrameClock`], which counts out both from the time
+//! since it started so neither drifts.
+//!
+//! ```
+//! use oxid8_core::{TimerRate, clock::{AudioClock, Clock}};
+//!
+//! let mut clock = AudioClock::new(44_100);
+//! let played = clock.counter();
+//!
+//! // In the audio callback, after copying samples to the device:
+//! played.add(735);
+//!
+//! // In the frame loop:
+//! assert_eq!(clock.frames_due(TimerRate::Ntsc), 1);
+//! assert_eq!(clock.frames_due(TimerRate::Ntsc), 0);
+//! ```
+
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::Duration,
+};
+
+use crate::{CPU_HZ, TimerRate};
+
+/// Most frames a clock asks for at once. Any more are dropped, so a
+/// stalled host skips ahead instead of fast forwarding to catch up.
+pub const MAX_FRAMES_DUE: u32 = 4;
+
+/// A source of time for pacing frames.
+pub trait Clock {
+    /// Number of frames to emulate now at `rate`. Frames returned are
+    /// counted as emulated.
+    fn frames_due(&mut self, rate: TimerRate) -> u32;
+}
+
+/// Paces frames off elapsed time reported by the frontend.
+///
+/// The core has no time source of its own, `std::time::Instant` panics
+/// in the web, so frontends pass in the time since their last call.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WallClock {
+    elapsed: Duration,
+}
+
+impl WallClock {
+    /// Adds time that has passed.
+    pub fn advance(&mut self, elapsed: Duration) {
+        self.elapsed += elapsed;
+    }
+}
+
+impl Clock for WallClock {
+    fn frames_due(&mut self, rate: TimerRate) -> u32 {
+        let tick = rate.tick();
+        let due = (self.elapsed.as_nanos() / tick.as_nanos()) as u32;
+        self.elapsed -= tick * due;
+        if due > MAX_FRAMES_DUE {
+            self.elapsed = Duration::ZERO;
+        }
+        due.min(MAX_FRAMES_DUE)
+    }
+}
+
+/// What [`FrameClock`] does with time missed while the host stalled,
+/// e.g. a garbage collection pause in the browser or a suspended
+/// terminal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CatchUp {
+    /// Run every missed tick at once, fast forwarding the game.
+    Burst,
+    /// Run at most this many timer decrements at once, at least 1, with
+    /// their cycles. The rest are dropped.
+    Clamp(u32),
+    /// Drop stalls of more than this many timer decrements, at least 1,
+    /// entirely. The game carries on where it paused.
+    Drop(u32),
+}
+
+/// Short hiccups are caught up and long stalls skipped.
+impl Default for CatchUp {
+    fn default() -> Self {
+        CatchUp::Clamp(MAX_FRAMES_DUE)
+    }
+}
+
+/// Cycles and timer decrements due, see [`FrameClock::tick`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Ticks {
+    pub cycles: u32,
+    pub timers: u32,
+}
+
+/// Paces cpu cycles and timer decrements at their own rates off time
+/// reported by the frontend.
+///
+/// Counts are worked out from the total time since the first tick, so
+/// rounding never accumulates. Frontends that emulate whole frames with
+/// `next_frame` run a frame per timer decrement and ignore the cycles.
+///
+/// ```
+/// use std::time::Duration;
+/// use oxid8_core::clock::{FrameClock, Ticks};
+///
+/// let mut clock = FrameClock::new(700, 60);
+/// assert_eq!(clock.tick(Duration::ZERO), Ticks::default());
+///
+/// let ticks = clock.tick(Duration::from_millis(50));
+/// assert_eq!(ticks, Ticks { cycles: 35, timers: 3 });
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FrameClock {
+    cpu_hz: u32,
+    timer_hz: u32,
+    catch_up: CatchUp,
+    /// Time of the first tick, the counts below are since.
+    start: Option<Duration>,
+    cycles: u64,
+    timers: u64,
+}
+
+impl Default for FrameClock {
+    /// `CPU_HZ` cycles and 60Hz timers.
+    fn default() -> Self {
+        Self::new(CPU_HZ, TimerRate::default().hz())
+    }
+}
+
+impl FrameClock {
+    /// New clock running `cpu_hz` cycles and `timer_hz` timer
+    /// decrements a second, each at least 1.
+    #[must_use]
+    pub fn new(cpu_hz: u32, timer_hz: u32) -> Self {
+        Self {
+            cpu_hz: cpu_hz.max(1),
+            timer_hz: timer_hz.max(1),
+            catch_up: CatchUp::default(),
+            start: None,
+            cycles: 0,
+            timers: 0,
+        }
+    }
+
+    /// Cycles and timer decrements to run at `now`, the time since any
+    /// fixed point, e.g. an `Instant` taken at startup. Ticks returned
+    /// are counted as run.
+    ///
+    /// The first tick starts the clock and returns none. Stalls are
+    /// handled by the [`CatchUp`] policy.
+    pub fn tick(&mut self, now: Duration) -> Ticks {
+        let start = *self.start.get_or_insert(now);
+        let elapsed = now.saturating_sub(start);
+        let cycles = count(elapsed, self.cpu_hz).saturating_sub(self.cycles);
+        let timers = count(elapsed, self.timer_hz).saturating_sub(self.timers);
+        let per_timer = u64::from(self.cpu_hz.div_ceil(self.timer_hz));
+        let (cycles, timers) = match self.catch_up {
+            CatchUp::Clamp(max) if timers > u64::from(max.max(1)) => {
+                self.skip_to(now);
+                let max = u64::from(max.max(1));
+                (cycles.min(per_timer * max), max)
+            }
+            CatchUp::Drop(max) if timers > u64::from(max.max(1)) => {
+                self.skip_to(now);
+                (0, 0)
+            }
+            _ => {
+                self.cycles += cycles;
+                self.timers += timers;
+                (cycles, timers)
+            }
+        };
+        Ticks {
+            cycles: u32::try_from(cycles).unwrap_or(u32::MAX),
+            timers: u32::try_from(timers).unwrap_or(u32::MAX),
+        }
+    }
+
+    /// Time from `now` until the next cycle is due, for sleeping.
+    #[must_use]
+    pub fn until_cycle(&self, now: Duration) -> Duration {
+        self.until(now, self.cpu_hz, self.cycles)
+    }
+
+    /// Time from `now` until the next timer decrement is due.
+    #[must_use]
+    pub fn until_timer(&self, now: Duration) -> Duration {
+        self.until(now, self.timer_hz, self.timers)
+    }
+
+    /// Time from the last timer decrement until `now`, e.g. to
+    /// timestamp input within a frame.
+    #[must_use]
+    pub fn since_timer(&self, now: Duration) -> Duration {
+        let Some(start) = self.start else {
+            return Duration::ZERO;
+        };
+        let last = u128::from(self.timers) * NANOS_PER_SEC / u128::from(self.timer_hz);
+        now.saturating_sub(start + Duration::from_nanos(last as u64))
+    }
+
+    #[must_use]
+    pub fn catch_up(&self) -> CatchUp {
+        self.catch_up
+    }
+
+    pub fn set_catch_up(&mut self, catch_up: CatchUp) {
+        self.catch_up = catch_up;
+    }
+
+    #[must_use]
+    pub fn cpu_hz(&self) -> u32 {
+        self.cpu_hz
+    }
+
+    /// Changes the cpu rate, counting from the next tick.
+    pub fn set_cpu_hz(&mut self, hz: u32) {
+        if self.cpu_hz != hz.max(1) {
+            self.cpu_hz = hz.max(1);
+            self.restart();
+        }
+    }
+
+    #[must_use]
+    pub fn timer_hz(&self) -> u32 {
+        self.timer_hz
+    }
+
+    /// Changes the timer rate, counting from the next tick.
+    pub fn set_timer_hz(&mut self, hz: u32) {
+        if self.timer_hz != hz.max(1) {
+            self.timer_hz = hz.max(1);
+            self.restart();
+        }
+    }
+
+    /// Forgets time already passed, e.g. after a pause, so it isn't
+    /// caught up.
+    pub fn restart(&mut self) {
+        self.start = None;
+        self.cycles = 0;
+        self.timers = 0;
+    }
+
+    /// Counts from `now`, dropping the time before.
+    fn skip_to(&mut self, now: Duration) {
+        self.start = Some(now);
+        self.cycles = 0;
+        self.timers = 0;
+    }
+
+    fn until(&self, now: Duration, hz: u32, run: u64) -> Duration {
+        let Some(start) = self.start else {
+            return Duration::ZERO;
+        };
+        let next = (u128::from(run) + 1) * NANOS_PER_SEC;
+        let next = start + Duration::from_nanos(next.div_ceil(u128::from(hz)) as u64);
+        next.saturating_sub(now)
+    }
+}
+
+const NANOS_PER_SEC: u128 = 1_000_000_000;
+
+/// Ticks at `hz` in `elapsed`.
+fn count(elapsed: Duration, hz: u32) -> u64 {
+    (elapsed.as_nanos() * u128::from(hz) / NANOS_PER_SEC) as u64
+}
+
+/// Number of samples played, shared with the audio callback.
+#[derive(Debug, Clone, Default)]
+pub struct SampleCounter(Arc<AtomicU64>);
+
+impl SampleCounter {
+    /// Counts `samples` more as played.
+    pub fn add(&self, samples: usize) {
+        self.0.fetch_add(samples as u64, Ordering::Relaxed);
+    }
+
+    /// Samples played so far.
+    #[must_use]
+    pub fn get(&self) -> u64 {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// Paces frames off samples consumed by the audio output.
+#[derive(Debug, Clone)]
+pub struct AudioClock {
+    sample_rate: u32,
+    played: SampleCounter,
+    /// Samples already paid for with frames.
+    used: u64,
+    /// The part of a frame left over, in samples times the timer rate.
+    carry: u64,
+}
+
+impl AudioClock {
+    /// New clock for audio output at `sample_rate`, see
+    /// `audio::SAMPLE_RATE`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a frame at the fastest timer rate is shorter than a
+    /// sample.
+    #[must_use]
+    pub fn new(sample_rate: u32) -> Self {
+        assert!(
+            sample_rate >= TimerRate::Ntsc.hz(),
+            "sample rate {sample_rate} has no samples per frame"
+        );
+        Self {
+            sample_rate,
+            played: SampleCounter::default(),
+            used: 0,
+            carry: 0,
+        }
+    }
+
+    /// A handle for the audio callback to count played samples with.
+    #[must_use]
+    pub fn counter(&self) -> SampleCounter {
+        self.played.clone()
+    }
+}
+
+impl Clock for AudioClock {
+    fn frames_due(&mut self, rate: TimerRate) -> u32 {
+        // Frames rarely take a whole number of samples, count in samples
+        // times the rate so the fractions add up.
+        let sample_rate = u64::from(self.sample_rate);
+        let played = self.played.get();
+        let units = played.saturating_sub(self.used) * u64::from(rate.hz()) + self.carry;
+        self.used = played;
+        self.carry = units % sample_rate;
+        (units / sample_rate).min(u64::from(MAX_FRAMES_DUE)) as u32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wall_clock() {
+        let mut clock = WallClock::default();
+        let tick = TimerRate::Pal.tick();
+        clock.advance(tick / 2);
+        assert_eq!(clock.frames_due(TimerRate::Pal), 0);
+        clock.advance(tick);
+        assert_eq!(clock.frames_due(TimerRate::Pal), 1);
+        // The leftover half tick carries over.
+        clock.advance(tick / 2);
+        assert_eq!(clock.frames_due(TimerRate::Pal), 1);
+
+        // A long stall is skipped, not caught up.
+        clock.advance(tick * 100);
+        assert_eq!(clock.frames_due(TimerRate::Pal), MAX_FRAMES_DUE);
+        assert_eq!(clock.frames_due(TimerRate::Pal), 0);
+    }
+
+    #[test]
+    fn frame_clock() {
+        let ms = Duration::from_millis;
+        let mut clock = FrameClock::new(700, 60);
+        assert_eq!(clock.until_timer(ms(5)), Duration::ZERO);
+        clock.tick(ms(5));
+        assert_eq!(clock.until_cycle(ms(5)), Duration::from_nanos(1_428_572));
+        assert_eq!(clock.until_timer(ms(15)), Duration::from_nanos(6_666_667));
+        assert_eq!(clock.since_timer(ms(15)), ms(10));
+
+        // A second in uneven steps runs exactly a second of ticks.
+        let mut total = Ticks::default();
+        for step in 1..=1000 {
+            let ticks = clock.tick(ms(5 + step));
+            total.cycles += ticks.cycles;
+            total.timers += ticks.timers;
+        }
+        assert_eq!(
+            total,
+            Ticks {
+                cycles: 700,
+                timers: 60
+            }
+        );
+
+        // A long stall is skipped, not caught up.
+        let ticks = clock.tick(ms(10_000));
+        assert_eq!(ticks.timers, MAX_FRAMES_DUE);
+        assert_eq!(ticks.cycles, 12 * MAX_FRAMES_DUE);
+        assert_eq!(clock.tick(ms(10_001)), Ticks::default());
+
+        // Changing a rate starts counting again.
+        clock.set_timer_hz(50);
+        assert_eq!(clock.tick(ms(10_100)), Ticks::default());
+        assert_eq!(clock.tick(ms(10_120)).timers, 1);
+    }
+
+    #[test]
+    fn catch_up() {
+        let stall = |catch_up| {
+            let mut clock = FrameClock::new(600, 60);
+            clock.set_catch_up(catch_up);
+            clock.tick(Duration::ZERO);
+            let ticks = clock.tick(Duration::from_secs(1));
+            // Counting carries on from the end of the stall.
+            let after = clock.tick(Duration::from_millis(1050));
+            (ticks, after.timers)
+        };
+        let ticks = |cycles, timers| Ticks { cycles, timers };
+
+        assert_eq!(stall(CatchUp::Burst), (ticks(600, 60), 3));
+        assert_eq!(stall(CatchUp::Clamp(4)), (ticks(40, 4), 3));
+        assert_eq!(stall(CatchUp::Drop(4)), (ticks(0, 0), 3));
+        // Within the limit, nothing is dropped.
+        assert_eq!(stall(CatchUp::Drop(60)), (ticks(600, 60), 3));
+        // At least one tick runs, the 3 after are clamped too.
+        assert_eq!(stall(CatchUp::Clamp(0)), (ticks(10, 1), 1));
+    }
+
+    #[test]
+    fn audio_clock() {
+        let mut clock = AudioClock::new(44_100);
+        let played = clock.counter();
+
+        played.add(700);
+        assert_eq!(clock.frames_due(TimerRate::Ntsc), 0);
+        played.add(735 * 2);
+        assert_eq!(clock.frames_due(TimerRate::Ntsc), 2);
+        // The first 700 samples count towards the next frame.
+        played.add(35);
+        assert_eq!(clock.frames_due(TimerRate::Ntsc), 1);
+        assert_eq!(played.get(), 735 * 3);
+
+        played.add(735 * 100);
+        assert_eq!(clock.frames_due(TimerRate::Ntsc), MAX_FRAMES_DUE);
+        assert_eq!(clock.frames_due(TimerRate::Ntsc), 0);
+    }
+
+    #[test]
+    fn audio_clock_fractions() {
+        // 367.5 samples a frame, a second of them is still 60 frames.
+        let mut clock = AudioClock::new(22_050);
+        let played = clock.counter();
+        let mut frames = 0;
+        for _ in 0..210 {
+            played.add(105);
+            frames += clock.frames_due(TimerRate::Ntsc);
+        }
+        assert_eq!(frames, 60);
+
+        // A sample a frame.
+        let mut clock = AudioClock::new(60);
+        clock.counter().add(3);
+        assert_eq!(clock.frames_due(TimerRate::Ntsc), 3);
+        clock.counter().add(5);
+        assert_eq!(clock.frames_due(TimerRate::Pal), 4);
+        // The sixth of a frame left over makes the next whole.
+        clock.counter().add(1);
+        assert_eq!(clock.frames_due(TimerRate::Pal), 1);
+    }
+
+    #[test]
+    #[should_panic = "no samples per frame"]
+    fn audio_clock_too_slow() {
+        let _ = AudioClock::new(59);
+    }
+}
@@ -0,0 +1,262 @@
+//! Colors and palettes shared by the frontends.
+//!
+//! [`Rgba`] is the one color type for the screen: the config writes it
+//! as `#rrggbb`, or `#rrggbbaa` when not opaque, exported images and
+//! the wgpu texture take its bytes, and the terminal draws it as is
+//! where truecolor is supported or as the nearest of the 256 ANSI
+//! colors elsewhere, see [`Rgba::ansi256`]. A [`Palette`] is the pair
+//! of colors lit and unlit pixels are drawn in, and [`PRESETS`] are the
+//! named palettes players cycle through, see
+//! [`palette`](crate::palette).
+//!
+//! ```
+//! use oxid8_core::color::{Preset, Rgba};
+//!
+//! let amber = Preset::named("amber").unwrap().palette;
+//! assert_eq!(amber.foreground.to_string(), "#ffb000");
+//! assert_eq!("#ffb000".parse(), Ok(amber.foreground));
+//! assert_eq!("#ffb00080".parse(), Ok(Rgba(0xFF, 0xB0, 0x00, 0x80)));
+//!
+//! // Orange in the 6x6x6 cube of 256 color terminals.
+//! assert_eq!(amber.foreground.ansi256(), 214);
+//! ```
+
+use std::{fmt, str::FromStr};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// A color with an alpha channel, written as `#rrggbb` or `#rrggbbaa`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(try_from = "String", into = "String")
+)]
+pub struct Rgba(pub u8, pub u8, pub u8, pub u8);
+
+/// Levels of each channel in the 6x6x6 cube of 256 color terminals.
+const CUBE_LEVELS: [u8; 6] = [0, 0x5F, 0x87, 0xAF, 0xD7, 0xFF];
+
+impl Rgba {
+    pub const WHITE: Rgba = Rgba::rgb(0xFF, 0xFF, 0xFF);
+    pub const BLACK: Rgba = Rgba::rgb(0, 0, 0);
+
+    /// An opaque color.
+    #[must_use]
+    pub const fn rgb(r: u8, g: u8, b: u8) -> Self {
+        Rgba(r, g, b, 0xFF)
+    }
+
+    /// The red, green, and blue channels.
+    #[must_use]
+    pub fn to_rgb(self) -> [u8; 3] {
+        [self.0, self.1, self.2]
+    }
+
+    /// RGBA8 bytes, as images and textures take them.
+    #[must_use]
+    pub fn to_bytes(self) -> [u8; 4] {
+        [self.0, self.1, self.2, self.3]
+    }
+
+    /// The color `level` of the way from `background` to this color,
+    /// from 0 for `background` to 255 for this color.
+    #[must_use]
+    pub fn mix(self, background: Rgba, level: u8) -> Rgba {
+        let [r, g, b, a] = std::array::from_fn(|i| {
+            let (fg, bg) = (self.to_bytes()[i], background.to_bytes()[i]);
+            ((u32::from(fg) * u32::from(level) + u32::from(bg) * u32::from(u8::MAX - level)) / 255)
+                as u8
+        });
+        Rgba(r, g, b, a)
+    }
+
+    /// Index of the nearest of the 256 ANSI colors, from the color cube
+    /// and the gray ramp. The 16 system colors are left out, terminals
+    /// theme them.
+    #[must_use]
+    pub fn ansi256(self) -> u8 {
+        let distance = |[r, g, b]: [u8; 3]| {
+            [(r, self.0), (g, self.1), (b, self.2)]
+                .iter()
+                .map(|&(a, b)| (i32::from(a) - i32::from(b)).pow(2))
+                .sum::<i32>()
+        };
+        let level = |c: u8| {
+            (0..CUBE_LEVELS.len())
+                .min_by_key(|&i| (i32::from(CUBE_LEVELS[i]) - i32::from(c)).abs())
+                .unwrap_or(0)
+        };
+        let (r, g, b) = (level(self.0), level(self.1), level(self.2));
+        let cube = [CUBE_LEVELS[r], CUBE_LEVELS[g], CUBE_LEVELS[b]];
+        // Grays run from 8 to 238 in steps of 10.
+        let average = (u32::from(self.0) + u32::from(self.1) + u32::from(self.2)) / 3;
+        let gray = (average.saturating_sub(3) / 10).min(23) as u8;
+        let level = 8 + 10 * gray;
+        match distance([level; 3]) < distance(cube) {
+            true => 232 + gray,
+            false => (16 + 36 * r + 6 * g + b) as u8,
+        }
+    }
+}
+
+/// Formatted like "#33ff66", with the alpha channel only if it isn't
+/// opaque.
+impl fmt::Display for Rgba {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "#{:02x}{:02x}{:02x}", self.0, self.1, self.2)?;
+        if self.3 != 0xFF {
+            write!(f, "{:02x}", self.3)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Rgba {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let hex = s
+            .strip_prefix('#')
+            .filter(|hex| matches!(hex.len(), 6 | 8) && hex.is_ascii())
+            .ok_or_else(|| format!("Invalid color: {s}"))?;
+        let channel = |i: usize| match hex.get(i..i + 2) {
+            Some(hex) => u8::from_str_radix(hex, 16).map_err(|_| format!("Invalid color: {s}")),
+            None => Ok(0xFF),
+        };
+        Ok(Rgba(channel(0)?, channel(2)?, channel(4)?, channel(6)?))
+    }
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<String> for Rgba {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+#[cfg(feature = "serde")]
+impl From<Rgba> for String {
+    fn from(color: Rgba) -> Self {
+        color.to_string()
+    }
+}
+
+/// Colors of lit and unlit pixels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Palette {
+    pub foreground: Rgba,
+    pub background: Rgba,
+}
+
+impl Default for Palette {
+    /// White on black, the first of the [`PRESETS`].
+    fn default() -> Self {
+        PRESETS[0].palette
+    }
+}
+
+/// Color of the grid lines between pixels in exported images.
+pub const GRID: Rgba = Rgba::rgb(0x30, 0x30, 0x30);
+
+/// A named palette.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Preset {
+    pub name: &'static str,
+    pub palette: Palette,
+}
+
+impl Preset {
+    /// The preset called `name`.
+    #[must_use]
+    pub fn named(name: &str) -> Option<&'static Preset> {
+        PRESETS.iter().find(|preset| preset.name == name)
+    }
+
+    const fn new(name: &'static str, foreground: Rgba, background: Rgba) -> Self {
+        Preset {
+            name,
+            palette: Palette {
+                foreground,
+                background,
+            },
+        }
+    }
+}
+
+/// Presets in the order they are cycled through.
+pub const PRESETS: [Preset; 5] = [
+    Preset::new("classic", Rgba::WHITE, Rgba::BLACK),
+    Preset::new(
+        "green phosphor",
+        Rgba::rgb(0x33, 0xFF, 0x66),
+        Rgba::rgb(0x10, 0x10, 0x10),
+    ),
+    Preset::new(
+        "amber",
+        Rgba::rgb(0xFF, 0xB0, 0x00),
+        Rgba::rgb(0x1A, 0x0F, 0x00),
+    ),
+    Preset::new(
+        "lcd",
+        Rgba::rgb(0x0F, 0x38, 0x0F),
+        Rgba::rgb(0x9B, 0xBC, 0x0F),
+    ),
+    Preset::new(
+        "octo",
+        Rgba::rgb(0xFF, 0xCC, 0x00),
+        Rgba::rgb(0x99, 0x66, 0x00),
+    ),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        assert_eq!("#33ff66".parse(), Ok(Rgba::rgb(0x33, 0xFF, 0x66)));
+        assert_eq!("#33FF6600".parse(), Ok(Rgba(0x33, 0xFF, 0x66, 0)));
+        for bad in ["33ff66", "#33ff6", "#33ff660", "#33gg66", "#ééé"] {
+            assert_eq!(
+                bad.parse::<Rgba>(),
+                Err(format!("Invalid color: {bad}")),
+                "{bad}"
+            );
+        }
+        assert_eq!(Rgba(1, 2, 3, 4).to_string(), "#01020304");
+    }
+
+    #[test]
+    fn ansi256() {
+        assert_eq!(Rgba::BLACK.ansi256(), 16);
+        assert_eq!(Rgba::WHITE.ansi256(), 231);
+        assert_eq!(Rgba::rgb(0xFF, 0, 0).ansi256(), 196);
+        // Closer to a gray than to the cube's.
+        assert_eq!(Rgba::rgb(0x80, 0x80, 0x80).ansi256(), 244);
+        assert_eq!(Rgba::rgb(0x10, 0x10, 0x10).ansi256(), 233);
+        assert_eq!(Rgba::rgb(0x33, 0xFF, 0x66).ansi256(), 83);
+    }
+
+    #[test]
+    fn mix() {
+        let (fg, bg) = (Rgba::rgb(255, 0, 100), Rgba::rgb(0, 255, 0));
+        assert_eq!(fg.mix(bg, 255), fg);
+        assert_eq!(fg.mix(bg, 0), bg);
+        assert_eq!(Rgba::WHITE.mix(Rgba::BLACK, 128), Rgba::rgb(128, 128, 128));
+    }
+
+    #[test]
+    fn presets() {
+        assert_eq!(
+            Palette::default(),
+            Preset::named("classic").unwrap().palette
+        );
+        assert_eq!(Preset::named("lcd").unwrap().palette.background.0, 0x9B);
+        assert!(Preset::named("sepia").is_none());
+    }
+}
@@ -0,0 +1,313 @@
+//! Checking how a collection of roms runs, for release notes.
+//!
+//! `oxid-compat <dir>` runs every rom in a directory under the default
+//! settings for a while, all at once on a pool of threads, and sorts
+//! each into a [`Verdict`]: it ran clean, hit an invalid opcode,
+//! overflowed or underflowed the stack, panicked, failed to load, or
+//! never lit a pixel. The [`Report`] lists them as a markdown or HTML
+//! table, with a thumbnail of each rom's final screen for the tool to
+//! write next to it, as a snapshot of compatibility at a release.
+//!
+//! No keys are pressed, so games waiting on a key count as clean if
+//! they drew something first.
+//!
+//! ```
+//! use oxid8_core::compat::{Report, Verdict};
+//!
+//! let roms = vec![
+//!     // LD F, V0; DRW V0, V0, 5; JP 204
+//!     ("font".to_string(), vec![0xF0, 0x29, 0xD0, 0x05, 0x12, 0x04]),
+//!     // JP 200
+//!     ("spin".to_string(), vec![0x12, 0x00]),
+//!     // RET
+//!     ("ret".to_string(), vec![0x00, 0xEE]),
+//! ];
+//! let report = Report::run(roms, 60);
+//! let verdicts: Vec<Verdict> = report.entries.iter().map(|entry| entry.verdict).collect();
+//! assert_eq!(verdicts, [Verdict::Clean, Verdict::Blank, Verdict::StackFault]);
+//! assert!(report.markdown("thumbs").contains("| ret | stack fault |"));
+//! ```
+
+use std::{
+    fmt,
+    num::NonZeroUsize,
+    panic::{self, AssertUnwindSafe},
+    thread,
+};
+
+use crate::{Oxid8, SCREEN_AREA, repro::panic_message};
+
+/// How a rom did.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Verdict {
+    /// Ran every frame and drew something.
+    Clean,
+    /// Ran every frame without ever lighting a pixel.
+    Blank,
+    /// Stopped on an instruction the core doesn't know.
+    InvalidOpcode,
+    /// Called too deep or returned with nothing to return to.
+    StackFault,
+    /// Panicked in some other way.
+    Crashed,
+    /// Couldn't be loaded, e.g. too large.
+    Unloadable,
+}
+
+impl Verdict {
+    /// Every verdict, best first.
+    pub const ALL: [Verdict; 6] = [
+        Verdict::Clean,
+        Verdict::Blank,
+        Verdict::InvalidOpcode,
+        Verdict::StackFault,
+        Verdict::Crashed,
+        Verdict::Unloadable,
+    ];
+}
+
+/// Formatted like "invalid opcode".
+impl fmt::Display for Verdict {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.pad(match self {
+            Verdict::Clean => "ran clean",
+            Verdict::Blank => "blank screen",
+            Verdict::InvalidOpcode => "invalid opcode",
+            Verdict::StackFault => "stack fault",
+            Verdict::Crashed => "crashed",
+            Verdict::Unloadable => "unloadable",
+        })
+    }
+}
+
+/// A rom in a [`Report`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    pub name: String,
+    pub verdict: Verdict,
+    /// Frames run in full.
+    pub frames: usize,
+    /// The error or panic that stopped the rom.
+    pub detail: Option<String>,
+    /// The screen when it stopped.
+    pub screen: [bool; SCREEN_AREA],
+}
+
+impl Entry {
+    /// Runs `rom` for `frames` frames on a new core.
+    #[must_use]
+    pub fn check(name: String, rom: &[u8], frames: usize) -> Self {
+        let mut emu = Oxid8::new();
+        emu.load_font();
+        let mut entry = Entry {
+            name,
+            verdict: Verdict::Clean,
+            frames: 0,
+            detail: None,
+            screen: [false; SCREEN_AREA],
+        };
+        if let Err(e) = emu.load_rom_bytes(rom) {
+            entry.verdict = Verdict::Unloadable;
+            entry.detail = Some(e.to_string());
+            return entry;
+        }
+
+        let mut lit = false;
+        let ran = panic::catch_unwind(AssertUnwindSafe(|| {
+            for _ in 0..frames {
+                emu.next_frame()?;
+                entry.frames += 1;
+                lit |= emu.screen_ref().contains(&true);
+            }
+            Ok::<_, String>(())
+        }));
+        (entry.verdict, entry.detail) = match ran {
+            Ok(Ok(())) if lit => (Verdict::Clean, None),
+            Ok(Ok(())) => (Verdict::Blank, None),
+            Ok(Err(e)) => (Verdict::InvalidOpcode, Some(e)),
+            Err(panic) => {
+                let message = panic_message(&*panic).to_string();
+                match message.contains("Stack") {
+                    true => (Verdict::StackFault, Some(message)),
+                    false => (Verdict::Crashed, Some(message)),
+                }
+            }
+        };
+        entry.screen = *emu.screen_ref();
+        entry
+    }
+
+    /// File name of the thumbnail of entry `index` in a report.
+    #[must_use]
+    pub fn thumbnail(index: usize) -> String {
+        format!("{index:03}.png")
+    }
+}
+
+/// Roms checked together, see the [module docs](self).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Report {
+    /// Frames each rom was run for.
+    pub frames: usize,
+    /// The roms, in the order given.
+    pub entries: Vec<Entry>,
+}
+
+impl Report {
+    /// Checks `roms`, named, for `frames` frames each, on as many
+    /// threads as the machine has.
+    ///
+    /// Panics are caught, but still reach the panic hook, which prints
+    /// them unless replaced.
+    #[must_use]
+    pub fn run(roms: Vec<(String, Vec<u8>)>, frames: usize) -> Self {
+        let threads = thread::available_parallelism().map_or(1, NonZeroUsize::get);
+        let chunk = roms.len().div_ceil(threads).max(1);
+        let entries = thread::scope(|scope| {
+            let handles: Vec<_> = roms
+                .chunks(chunk)
+                .map(|roms| {
+                    scope.spawn(move || {
+                        roms.iter()
+                            .map(|(name, rom)| Entry::check(name.clone(), rom, frames))
+                            .collect::<Vec<_>>()
+                    })
+                })
+                .collect();
+            handles
+                .into_iter()
+                .flat_map(|handle| match handle.join() {
+                    Ok(entries) => entries,
+                    Err(panic) => panic::resume_unwind(panic),
+                })
+                .collect()
+        });
+        Report { frames, entries }
+    }
+
+    /// Number of roms with `verdict`.
+    #[must_use]
+    pub fn count(&self, verdict: Verdict) -> usize {
+        self.entries
+            .iter()
+            .filter(|entry| entry.verdict == verdict)
+            .count()
+    }
+
+    /// The verdicts reached and how many roms got each, e.g. "12 ran
+    /// clean, 1 stack fault".
+    #[must_use]
+    pub fn summary(&self) -> String {
+        let counts: Vec<String> = Verdict::ALL
+            .iter()
+            .map(|&verdict| (verdict, self.count(verdict)))
+            .filter(|&(_, n)| n > 0)
+            .map(|(verdict, n)| format!("{n} {verdict}"))
+            .collect();
+        counts.join(", ")
+    }
+
+    /// The report as a markdown table, with thumbnails in `thumbs`.
+    #[must_use]
+    pub fn markdown(&self, thumbs: &str) -> String {
+        let mut out = format!(
+            "# Compatibility\n\n{} roms over {} frames: {}\n\n",
+            self.entries.len(),
+            self.frames,
+            self.summary()
+        );
+        out.push_str("| Rom | Verdict | Frames | Detail | Screen |\n");
+        out.push_str("|-----|---------|--------|--------|--------|\n");
+        for (i, entry) in self.entries.iter().enumerate() {
+            let cell = |text: &str| text.replace('|', "\\|");
+            out.push_str(&format!(
+                "| {} | {} | {} | {} | ![]({thumbs}/{}) |\n",
+                cell(&entry.name),
+                entry.verdict,
+                entry.frames,
+                cell(entry.detail.as_deref().unwrap_or("")),
+                Entry::thumbnail(i)
+            ));
+        }
+        out
+    }
+
+    /// The report as an HTML page, with thumbnails in `thumbs`.
+    #[must_use]
+    pub fn html(&self, thumbs: &str) -> String {
+        let mut out = format!(
+            "<!DOCTYPE html>\n<meta charset=\"utf-8\">\n<title>Compatibility</title>\n\
+             <h1>Compatibility</h1>\n<p>{} roms over {} frames: {}</p>\n<table>\n\
+             <tr><th>Rom<th>Verdict<th>Frames<th>Detail<th>Screen\n",
+            self.entries.len(),
+            self.frames,
+            self.summary()
+        );
+        for (i, entry) in self.entries.iter().enumerate() {
+            out.push_str(&format!(
+                "<tr><td>{}<td>{}<td>{}<td>{}<td><img src=\"{}/{}\" alt=\"\">\n",
+                escape_html(&entry.name),
+                entry.verdict,
+                entry.frames,
+                escape_html(entry.detail.as_deref().unwrap_or("")),
+                escape_html(thumbs),
+                Entry::thumbnail(i)
+            ));
+        }
+        out.push_str("</table>\n");
+        out
+    }
+}
+
+/// `text` with the characters HTML gives meaning escaped.
+fn escape_html(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn verdicts() {
+        let check = |rom: &[u8]| Entry::check("rom".into(), rom, 10);
+        let invalid = check(&[0xFF, 0xFF]);
+        assert_eq!(invalid.verdict, Verdict::InvalidOpcode);
+        assert_eq!(invalid.frames, 0);
+        assert!(invalid.detail.unwrap().contains("FFFF"));
+        // CALL 200
+        assert_eq!(check(&[0x22, 0x00]).verdict, Verdict::StackFault);
+        let unloadable = check(&[0; 8192]);
+        assert_eq!(unloadable.verdict, Verdict::Unloadable);
+        assert!(unloadable.detail.unwrap().contains("too large"));
+    }
+
+    #[test]
+    fn reports() {
+        let roms = vec![
+            ("a <b>".to_string(), vec![0x12, 0x00]),
+            ("c|d".to_string(), vec![0xFF, 0xFF]),
+        ];
+        let report = Report::run(roms, 5);
+        assert_eq!(report.summary(), "1 blank screen, 1 invalid opcode");
+        let markdown = report.markdown("thumbs");
+        assert!(markdown.contains("| a <b> | blank screen | 5 |  | ![](thumbs/000.png) |"));
+        assert!(markdown.contains("| c\\|d | invalid opcode | 0 |"));
+        let html = report.html("thumbs");
+        assert!(
+            html.contains(
+                "<td>a &lt;b&gt;<td>blank screen<td>5<td><td><img src=\"thumbs/000.png\""
+            )
+        );
+    }
+}
@@ -0,0 +1,811 @@
+//! Per-game configuration overrides.
+//!
+//! Settings known to work well for a game are saved as a small TOML
+//! file named after the ROM hash, so they are applied automatically
+//! the next time the same ROM is loaded in any frontend.
+//!
+//! ```toml
+//! # <dir>/<sha1>.toml
+//! cycles_per_frame = 15
+//! timer_rate = "50hz"
+//! foreground = "#33ff66"
+//! background = "#101010"
+//! border = "#000000"
+//! keymap = "two-player"
+//! rotation = 90
+//!
+//! [quirks]
+//! rng = "lfsr"
+//! wrap_x = true
+//! ```
+//!
+//! Quirks left out keep the frontend's, see [`QuirkOverrides`].
+//!
+//! With the `debugger` feature, breakpoints and watch expressions are
+//! kept beside it in `<sha1>.debug.toml`, see [`DebugConfig`].
+//!
+//! ```toml
+//! # <dir>/<sha1>.debug.toml
+//! breakpoints = ["0x2A4", "0x31E"]
+//! watches = ["V3 + V4", "ram[I..I + 3]"]
+//! ```
+//!
+//! Goals to reach in the game are read from `<sha1>.goals.toml`, see
+//! [`goals`](crate::goals), and with the `script` feature a script to
+//! run with it from `<sha1>.rhai`, see [`script`](crate::script).
+//!
+//! Settings for every game, like colors saved from the
+//! [`palette`](crate::palette) editor, are kept in `defaults.toml`.
+//! Frontends load them under the settings saved for the rom. Settings
+//! for the frontend itself, like `splash = false` to start without the
+//! splash, are only read from there.
+
+use serde::{Deserialize, Serialize};
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+#[cfg(feature = "script")]
+use crate::script::Script;
+use crate::{
+    Oxid8, TimerRate,
+    color::Rgba,
+    keymap::Keymap,
+    quirks::{KeyWait, Quirks, RngMode, Shift},
+    rotation::Rotation,
+    weights::CycleWeights,
+};
+#[cfg(feature = "debugger")]
+use crate::{breakpoint::Breakpoints, goals::Goals, watch::Watch};
+
+/// Settings overridden for a single game. Unset fields keep the
+/// frontend's defaults.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GameConfig {
+    /// See [`Oxid8::set_cycles_per_frame`].
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cycles_per_frame: Option<u32>,
+    /// See [`Oxid8::set_timer_rate`].
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timer_rate: Option<TimerRate>,
+    /// Color of lit pixels.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub foreground: Option<Rgba>,
+    /// Color of unlit pixels.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub background: Option<Rgba>,
+    /// Color around the screen, where the window is wider or taller
+    /// than the screen.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub border: Option<Rgba>,
+    /// Keyboard layout.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub keymap: Option<Keymap>,
+    /// Display rotation.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rotation: Option<Rotation>,
+    /// Show the splash when a frontend starts, see `builtin::Splash`.
+    /// Only read from `defaults.toml`, it shows before a rom is picked.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub splash: Option<bool>,
+    /// See [`Oxid8::set_quirks`].
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub quirks: Option<QuirkOverrides>,
+    /// See [`Oxid8::set_skip_invalid`].
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub skip_invalid: Option<bool>,
+    /// See [`Oxid8::set_cycle_weights`].
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cycle_weights: Option<CycleWeights>,
+}
+
+impl GameConfig {
+    /// Applies the core settings of this config to `core`.
+    pub fn apply(&self, core: &mut Oxid8) {
+        if let Some(cycles) = self.cycles_per_frame {
+            core.set_cycles_per_frame(cycles);
+        }
+        if let Some(rate) = self.timer_rate {
+            core.set_timer_rate(rate);
+        }
+        if let Some(quirks) = self.quirks {
+            core.set_quirks(quirks.apply(core.quirks()));
+        }
+        if let Some(skip) = self.skip_invalid {
+            core.set_skip_invalid(skip);
+        }
+        if let Some(weights) = &self.cycle_weights {
+            core.set_cycle_weights(weights.clone());
+        }
+    }
+
+    /// Captures the core settings of `core`. Skipping invalid opcodes
+    /// and cycle weights are only captured when set, so saved configs
+    /// don't fill up with the defaults.
+    #[must_use]
+    pub fn capture(core: &Oxid8) -> Self {
+        Self {
+            cycles_per_frame: Some(core.cycles_per_frame()),
+            timer_rate: Some(core.timer_rate()),
+            quirks: Some(core.quirks().into()),
+            skip_invalid: core.skip_invalid().then_some(true),
+            cycle_weights: (!core.cycle_weights().is_empty()).then(|| core.cycle_weights().clone()),
+            ..Default::default()
+        }
+    }
+
+    /// Overrides the fields of this config with the fields set in
+    /// `other`, quirks one at a time.
+    #[must_use]
+    pub fn merge(self, other: &GameConfig) -> Self {
+        let quirks = match (self.quirks, other.quirks) {
+            (Some(quirks), Some(other)) => Some(quirks.merge(&other)),
+            (quirks, other) => other.or(quirks),
+        };
+        Self {
+            cycles_per_frame: other.cycles_per_frame.or(self.cycles_per_frame),
+            timer_rate: other.timer_rate.or(self.timer_rate),
+            foreground: other.foreground.or(self.foreground),
+            background: other.background.or(self.background),
+            border: other.border.or(self.border),
+            keymap: other.keymap.or(self.keymap),
+            rotation: other.rotation.or(self.rotation),
+            splash: other.splash.or(self.splash),
+            quirks,
+            skip_invalid: other.skip_invalid.or(self.skip_invalid),
+            cycle_weights: other.cycle_weights.clone().or(self.cycle_weights),
+        }
+    }
+
+    /// This config with the fields still as `overrides` set them put
+    /// back to `base`'s, for saving settings changed on top of
+    /// overrides that aren't the player's, like a playlist's.
+    #[must_use]
+    pub fn without(self, overrides: &GameConfig, base: &GameConfig) -> Self {
+        let quirks = match (self.quirks, overrides.quirks) {
+            (Some(quirks), Some(over)) => {
+                let base = base.quirks.unwrap_or_default();
+                let quirks = quirks.without(&over, &base);
+                (quirks != QuirkOverrides::default()).then_some(quirks)
+            }
+            (quirks, _) => quirks,
+        };
+        Self {
+            cycles_per_frame: unless(
+                self.cycles_per_frame,
+                &overrides.cycles_per_frame,
+                &base.cycles_per_frame,
+            ),
+            timer_rate: unless(self.timer_rate, &overrides.timer_rate, &base.timer_rate),
+            foreground: unless(self.foreground, &overrides.foreground, &base.foreground),
+            background: unless(self.background, &overrides.background, &base.background),
+            border: unless(self.border, &overrides.border, &base.border),
+            keymap: unless(self.keymap, &overrides.keymap, &base.keymap),
+            rotation: unless(self.rotation, &overrides.rotation, &base.rotation),
+            splash: unless(self.splash, &overrides.splash, &base.splash),
+            quirks,
+            skip_invalid: unless(
+                self.skip_invalid,
+                &overrides.skip_invalid,
+                &base.skip_invalid,
+            ),
+            cycle_weights: unless(
+                self.cycle_weights,
+                &overrides.cycle_weights,
+                &base.cycle_weights,
+            ),
+        }
+    }
+
+    /// Parses a config from TOML.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error message if the TOML is malformed.
+    pub fn from_toml(s: &str) -> Result<Self, String> {
+        toml::from_str(s).map_err(|err| err.to_string())
+    }
+
+    /// Serializes the config as TOML.
+    #[must_use]
+    pub fn to_toml(&self) -> String {
+        toml::to_string(self).expect("GameConfig is always serializable")
+    }
+}
+
+/// `value`, or `base` if it is what `overrides` set.
+fn unless<T: Clone + PartialEq>(
+    value: Option<T>,
+    overrides: &Option<T>,
+    base: &Option<T>,
+) -> Option<T> {
+    match overrides {
+        Some(_) if value == *overrides => base.clone(),
+        _ => value,
+    }
+}
+
+/// Quirks set in a config, see [`Quirks`] for each. Unset quirks keep
+/// the core's.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct QuirkOverrides {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rng: Option<RngMode>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub wrap_x: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub wrap_y: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub key_wait: Option<KeyWait>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub i_overflow: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub jump_vx: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub shift: Option<Shift>,
+}
+
+impl QuirkOverrides {
+    /// `quirks` with the quirks set here changed.
+    #[must_use]
+    pub fn apply(&self, quirks: Quirks) -> Quirks {
+        Quirks {
+            rng: self.rng.unwrap_or(quirks.rng),
+            wrap_x: self.wrap_x.unwrap_or(quirks.wrap_x),
+            wrap_y: self.wrap_y.unwrap_or(quirks.wrap_y),
+            key_wait: self.key_wait.unwrap_or(quirks.key_wait),
+            i_overflow: self.i_overflow.unwrap_or(quirks.i_overflow),
+            jump_vx: self.jump_vx.unwrap_or(quirks.jump_vx),
+            shift: self.shift.unwrap_or(quirks.shift),
+        }
+    }
+
+    /// Overrides the quirks set here with those set in `other`.
+    #[must_use]
+    pub fn merge(self, other: &QuirkOverrides) -> Self {
+        Self {
+            rng: other.rng.or(self.rng),
+            wrap_x: other.wrap_x.or(self.wrap_x),
+            wrap_y: other.wrap_y.or(self.wrap_y),
+            key_wait: other.key_wait.or(self.key_wait),
+            i_overflow: other.i_overflow.or(self.i_overflow),
+            jump_vx: other.jump_vx.or(self.jump_vx),
+            shift: other.shift.or(self.shift),
+        }
+    }
+
+    /// See [`GameConfig::without`].
+    #[must_use]
+    fn without(self, overrides: &QuirkOverrides, base: &QuirkOverrides) -> Self {
+        Self {
+            rng: unless(self.rng, &overrides.rng, &base.rng),
+            wrap_x: unless(self.wrap_x, &overrides.wrap_x, &base.wrap_x),
+            wrap_y: unless(self.wrap_y, &overrides.wrap_y, &base.wrap_y),
+            key_wait: unless(self.key_wait, &overrides.key_wait, &base.key_wait),
+            i_overflow: unless(self.i_overflow, &overrides.i_overflow, &base.i_overflow),
+            jump_vx: unless(self.jump_vx, &overrides.jump_vx, &base.jump_vx),
+            shift: unless(self.shift, &overrides.shift, &base.shift),
+        }
+    }
+}
+
+/// Every quirk set.
+impl From<Quirks> for QuirkOverrides {
+    fn from(quirks: Quirks) -> Self {
+        Self {
+            rng: Some(quirks.rng),
+            wrap_x: Some(quirks.wrap_x),
+            wrap_y: Some(quirks.wrap_y),
+            key_wait: Some(quirks.key_wait),
+            i_overflow: Some(quirks.i_overflow),
+            jump_vx: Some(quirks.jump_vx),
+            shift: Some(quirks.shift),
+        }
+    }
+}
+
+/// Debugger state saved for a game, restored when it is loaded again.
+#[cfg(feature = "debugger")]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DebugConfig {
+    #[serde(skip_serializing_if = "Breakpoints::is_empty")]
+    pub breakpoints: Breakpoints,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub watches: Vec<Watch>,
+}
+
+#[cfg(feature = "debugger")]
+impl DebugConfig {
+    /// Returns true if there is nothing to save.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.breakpoints.is_empty() && self.watches.is_empty()
+    }
+
+    /// Parses a debug config from TOML.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error message if the TOML is malformed, or has an
+    /// invalid address or watch expression.
+    pub fn from_toml(s: &str) -> Result<Self, String> {
+        toml::from_str(s).map_err(|err| err.to_string())
+    }
+
+    /// Serializes the debug config as TOML.
+    #[must_use]
+    pub fn to_toml(&self) -> String {
+        toml::to_string(self).expect("DebugConfig is always serializable")
+    }
+}
+
+/// Directory of [`GameConfig`] files keyed by ROM hash.
+#[derive(Debug, Clone)]
+pub struct ConfigStore {
+    dir: PathBuf,
+}
+
+impl ConfigStore {
+    /// Store configs in `dir`, which is created on first save.
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// The directory configs are stored in.
+    #[must_use]
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Path of the config for the ROM with `hash`.
+    #[must_use]
+    pub fn path(&self, hash: &str) -> PathBuf {
+        self.dir.join(format!("{hash}.toml"))
+    }
+
+    /// Loads the config saved for `hash`, if any.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file exists but cannot be read or parsed.
+    pub fn load(&self, hash: &str) -> io::Result<Option<GameConfig>> {
+        load_config(&self.path(hash))
+    }
+
+    /// Saves `config` for `hash`, replacing any previous config.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be written.
+    pub fn save(&self, hash: &str, config: &GameConfig) -> io::Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        fs::write(self.path(hash), config.to_toml())
+    }
+
+    /// Path of the config for every game.
+    #[must_use]
+    pub fn defaults_path(&self) -> PathBuf {
+        self.dir.join("defaults.toml")
+    }
+
+    /// Loads the config saved for every game, if any.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file exists but cannot be read or parsed.
+    pub fn load_defaults(&self) -> io::Result<Option<GameConfig>> {
+        load_config(&self.defaults_path())
+    }
+
+    /// Saves `config` for every game, replacing any previous defaults.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be written.
+    pub fn save_defaults(&self, config: &GameConfig) -> io::Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        fs::write(self.defaults_path(), config.to_toml())
+    }
+
+    /// Loads the config saved for `hash` over the defaults for every
+    /// game, `None` if neither is saved.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if either file exists but cannot be read or
+    /// parsed.
+    pub fn load_merged(&self, hash: &str) -> io::Result<Option<GameConfig>> {
+        Ok(match (self.load_defaults()?, self.load(hash)?) {
+            (Some(defaults), Some(config)) => Some(defaults.merge(&config)),
+            (defaults, config) => config.or(defaults),
+        })
+    }
+
+    /// Removes the config saved for `hash`, if any.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file exists but cannot be removed.
+    pub fn remove(&self, hash: &str) -> io::Result<()> {
+        remove_file(&self.path(hash))
+    }
+
+    /// Path of the debug config for the ROM with `hash`.
+    #[cfg(feature = "debugger")]
+    #[must_use]
+    pub fn debug_path(&self, hash: &str) -> PathBuf {
+        self.dir.join(format!("{hash}.debug.toml"))
+    }
+
+    /// Loads the debug config saved for `hash`, if any.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file exists but cannot be read or parsed.
+    #[cfg(feature = "debugger")]
+    pub fn load_debug(&self, hash: &str) -> io::Result<Option<DebugConfig>> {
+        match fs::read_to_string(self.debug_path(hash)) {
+            Ok(s) => DebugConfig::from_toml(&s)
+                .map(Some)
+                .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err)),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Saves `config` for `hash`, or removes the file if it is empty.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be written or removed.
+    #[cfg(feature = "debugger")]
+    pub fn save_debug(&self, hash: &str, config: &DebugConfig) -> io::Result<()> {
+        if config.is_empty() {
+            return remove_file(&self.debug_path(hash));
+        }
+        fs::create_dir_all(&self.dir)?;
+        fs::write(self.debug_path(hash), config.to_toml())
+    }
+
+    /// Path of the goals for the ROM with `hash`.
+    #[cfg(feature = "debugger")]
+    #[must_use]
+    pub fn goals_path(&self, hash: &str) -> PathBuf {
+        self.dir.join(format!("{hash}.goals.toml"))
+    }
+
+    /// Loads the goals written for `hash`, if any. Goals are written by
+    /// hand, so there is no `save_goals`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file exists but cannot be read or parsed.
+    #[cfg(feature = "debugger")]
+    pub fn load_goals(&self, hash: &str) -> io::Result<Option<Goals>> {
+        match fs::read_to_string(self.goals_path(hash)) {
+            Ok(s) => Goals::from_toml(&s)
+                .map(Some)
+                .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err)),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Path of the script for the ROM with `hash`.
+    #[cfg(feature = "script")]
+    #[must_use]
+    pub fn script_path(&self, hash: &str) -> PathBuf {
+        self.dir.join(format!("{hash}.rhai"))
+    }
+
+    /// Loads the script written for `hash`, if any, see
+    /// [`script`](crate::script).
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file exists but cannot be read or
+    /// compiled.
+    #[cfg(feature = "script")]
+    pub fn load_script(&self, hash: &str) -> io::Result<Option<Script>> {
+        match fs::read_to_string(self.script_path(hash)) {
+            Ok(s) => Script::new(&s)
+                .map(Some)
+                .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err)),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+}
+
+/// Loads the config at `path`, if it exists.
+fn load_config(path: &Path) -> io::Result<Option<GameConfig>> {
+    match fs::read_to_string(path) {
+        Ok(s) => GameConfig::from_toml(&s)
+            .map(Some)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err)),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(err),
+    }
+}
+
+/// Removes `path`, if it exists.
+fn remove_file(path: &Path) -> io::Result<()> {
+    match fs::remove_file(path) {
+        Err(err) if err.kind() != io::ErrorKind::NotFound => Err(err),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rgb_parse() {
+        assert_eq!("#33ff66".parse::<Rgba>(), Ok(Rgba::rgb(0x33, 0xFF, 0x66)));
+        assert_eq!(Rgba::rgb(0x33, 0xFF, 0x66).to_string(), "#33ff66");
+        assert!("33ff66".parse::<Rgba>().is_err());
+        assert!("#33ff6".parse::<Rgba>().is_err());
+        assert!("#33ff6g".parse::<Rgba>().is_err());
+    }
+
+    #[test]
+    fn toml_roundtrip() {
+        let config = GameConfig {
+            cycles_per_frame: Some(15),
+            timer_rate: Some(TimerRate::Pal),
+            foreground: Some(Rgba::rgb(0x33, 0xFF, 0x66)),
+            background: None,
+            border: Some(Rgba::rgb(0, 0, 0)),
+            keymap: Some(Keymap::TwoPlayer),
+            rotation: Some(Rotation::Deg90),
+            splash: Some(false),
+            quirks: Some(
+                Quirks {
+                    rng: RngMode::Lfsr,
+                    wrap_x: true,
+                    ..Quirks::default()
+                }
+                .into(),
+            ),
+            skip_invalid: Some(true),
+            cycle_weights: Some("dxyn=4".parse().unwrap()),
+        };
+        let s = config.to_toml();
+        assert!(s.contains("cycles_per_frame = 15"));
+        assert!(s.contains("timer_rate = \"50hz\""));
+        assert!(s.contains("foreground = \"#33ff66\""));
+        assert!(!s.contains("background"));
+        assert!(s.contains("border = \"#000000\""));
+        assert!(s.contains("keymap = \"two-player\""));
+        assert!(s.contains("rotation = 90"));
+        assert!(s.contains("splash = false"));
+        assert!(s.contains("skip_invalid = true"));
+        assert!(s.contains("[cycle_weights]\nDxyn = 4"));
+        assert!(s.contains("[quirks]\nrng = \"lfsr\"\nwrap_x = true\nwrap_y = false"));
+        assert_eq!(GameConfig::from_toml(&s), Ok(config));
+        // Saved before the rng was renamed.
+        let old = GameConfig::from_toml("[quirks]\nrng = \"vip\"\n").unwrap();
+        assert_eq!(old.quirks.unwrap().rng, Some(RngMode::Lfsr));
+        assert_eq!(old.quirks.unwrap().wrap_x, None);
+    }
+
+    #[test]
+    fn apply_and_capture() {
+        let mut emu = Oxid8::new();
+        GameConfig::default().apply(&mut emu);
+        assert_eq!(emu.cycles_per_frame(), crate::DEFAULT_CYCLES_PER_FRAME);
+
+        let config = GameConfig {
+            cycles_per_frame: Some(20),
+            ..Default::default()
+        };
+        config.apply(&mut emu);
+        assert_eq!(emu.cycles_per_frame(), 20);
+        assert_eq!(GameConfig::capture(&emu).cycles_per_frame, Some(20));
+        assert_eq!(GameConfig::capture(&emu).skip_invalid, None);
+
+        GameConfig {
+            skip_invalid: Some(true),
+            ..Default::default()
+        }
+        .apply(&mut emu);
+        assert!(emu.skip_invalid());
+        assert_eq!(GameConfig::capture(&emu).skip_invalid, Some(true));
+
+        emu.reset();
+        assert_eq!(emu.cycles_per_frame(), 20);
+    }
+
+    #[test]
+    fn merge() {
+        let base = GameConfig {
+            cycles_per_frame: Some(15),
+            rotation: Some(Rotation::Deg90),
+            ..Default::default()
+        };
+        let over = GameConfig {
+            cycles_per_frame: Some(30),
+            keymap: Some(Keymap::TwoPlayer),
+            ..Default::default()
+        };
+        let merged = base.merge(&over);
+        assert_eq!(merged.cycles_per_frame, Some(30));
+        assert_eq!(merged.rotation, Some(Rotation::Deg90));
+        assert_eq!(merged.keymap, Some(Keymap::TwoPlayer));
+    }
+
+    #[test]
+    fn merge_quirks() {
+        let base = GameConfig::from_toml("[quirks]\nrng = \"lfsr\"\nwrap_x = true\n").unwrap();
+        let over = GameConfig::from_toml("[quirks]\nwrap_x = false\nshift = \"vy\"\n").unwrap();
+        let quirks = base.merge(&over).quirks.unwrap();
+        assert_eq!(quirks.rng, Some(RngMode::Lfsr));
+        assert_eq!(quirks.wrap_x, Some(false));
+        assert_eq!(quirks.shift, Some(Shift::Vy));
+        assert_eq!(quirks.key_wait, None);
+
+        let mut emu = Oxid8::new();
+        emu.set_quirks(Quirks {
+            jump_vx: true,
+            ..Quirks::default()
+        });
+        GameConfig {
+            quirks: Some(quirks),
+            ..Default::default()
+        }
+        .apply(&mut emu);
+        assert_eq!(
+            emu.quirks(),
+            Quirks {
+                rng: RngMode::Lfsr,
+                jump_vx: true,
+                shift: Shift::Vy,
+                ..Quirks::default()
+            }
+        );
+    }
+
+    #[test]
+    fn without_overrides() {
+        let base = GameConfig {
+            cycles_per_frame: Some(15),
+            ..Default::default()
+        };
+        let overrides = GameConfig::from_toml(
+            "cycles_per_frame = 30\nrotation = 90\nkeymap = \"two-player\"\n[quirks]\nwrap_x = true\n",
+        )
+        .unwrap();
+        let mut emu = Oxid8::new();
+        base.clone().merge(&overrides).apply(&mut emu);
+        // The player changed the speed, the rest is the playlist's.
+        emu.set_cycles_per_frame(20);
+        let current = GameConfig {
+            keymap: Some(Keymap::TwoPlayer),
+            rotation: Some(Rotation::Deg90),
+            ..GameConfig::capture(&emu)
+        };
+        let saved = current.without(&overrides, &base);
+        assert_eq!(saved.cycles_per_frame, Some(20));
+        assert_eq!(saved.rotation, None);
+        assert_eq!(saved.keymap, None);
+        let quirks = saved.quirks.unwrap();
+        assert_eq!(quirks.wrap_x, None);
+        assert_eq!(quirks.rng, Some(RngMode::Uniform));
+    }
+
+    #[test]
+    fn store_roundtrip() {
+        let dir = std::env::temp_dir().join(format!("oxid8-config-{}", std::process::id()));
+        let store = ConfigStore::new(&dir);
+        let hash = "da39a3ee5e6b4b0d3255bfef95601890afd80709";
+
+        assert_eq!(store.load(hash).unwrap(), None);
+
+        let config = GameConfig {
+            cycles_per_frame: Some(30),
+            ..Default::default()
+        };
+        store.save(hash, &config).unwrap();
+        assert_eq!(store.load(hash).unwrap(), Some(config));
+
+        store.remove(hash).unwrap();
+        assert_eq!(store.load(hash).unwrap(), None);
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn defaults_under_rom() {
+        let dir = std::env::temp_dir().join(format!("oxid8-defaults-{}", std::process::id()));
+        let store = ConfigStore::new(&dir);
+        let hash = "da39a3ee5e6b4b0d3255bfef95601890afd80709";
+        assert_eq!(store.load_merged(hash).unwrap(), None);
+
+        let defaults = GameConfig {
+            foreground: Some(Rgba::rgb(0x33, 0xFF, 0x66)),
+            background: Some(Rgba::rgb(0x10, 0x10, 0x10)),
+            ..Default::default()
+        };
+        store.save_defaults(&defaults).unwrap();
+        assert_eq!(store.load_merged(hash).unwrap(), Some(defaults.clone()));
+
+        let config = GameConfig {
+            foreground: Some(Rgba::rgb(0xFF, 0xB0, 0x00)),
+            ..Default::default()
+        };
+        store.save(hash, &config).unwrap();
+        let merged = store.load_merged(hash).unwrap().unwrap();
+        assert_eq!(merged.foreground, config.foreground);
+        assert_eq!(merged.background, defaults.background);
+        assert_eq!(store.load_defaults().unwrap(), Some(defaults));
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[cfg(feature = "debugger")]
+    #[test]
+    fn debug_roundtrip() {
+        let dir = std::env::temp_dir().join(format!("oxid8-debug-{}", std::process::id()));
+        let store = ConfigStore::new(&dir);
+        let hash = "da39a3ee5e6b4b0d3255bfef95601890afd80709";
+        assert_eq!(store.load_debug(hash).unwrap(), None);
+
+        let mut config = DebugConfig::default();
+        config.breakpoints.toggle(0x31E);
+        config.breakpoints.toggle(0x2A4);
+        config.watches.push("V3 + V4".parse().unwrap());
+        let s = config.to_toml();
+        assert!(s.contains("breakpoints = [\"0x2A4\", \"0x31E\"]"), "{s}");
+        assert!(s.contains("watches = [\"V3 + V4\"]"), "{s}");
+
+        store.save_debug(hash, &config).unwrap();
+        assert_eq!(store.load_debug(hash).unwrap(), Some(config));
+        // The game settings are separate.
+        assert_eq!(store.load(hash).unwrap(), None);
+
+        store.save_debug(hash, &DebugConfig::default()).unwrap();
+        assert_eq!(store.load_debug(hash).unwrap(), None);
+        assert!(DebugConfig::from_toml("watches = [\"V3 +\"]").is_err());
+        assert!(DebugConfig::from_toml("breakpoints = [\"zz\"]").is_err());
+        let _ = fs::remove_dir_all(dir);
+    }
+    #[cfg(feature = "debugger")]
+    #[test]
+    fn load_goals() {
+        let dir = std::env::temp_dir().join(format!("oxid8-goals-{}", std::process::id()));
+        let store = ConfigStore::new(&dir);
+        let hash = "da39a3ee5e6b4b0d3255bfef95601890afd80709";
+        assert_eq!(store.load_goals(hash).unwrap(), None);
+
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(store.goals_path(hash), "\"V0 >= 10\" = \"Ten\"\n").unwrap();
+        let goals = store.load_goals(hash).unwrap().unwrap();
+        assert_eq!(goals.goals()[0].name, "Ten");
+
+        fs::write(store.goals_path(hash), "\"V0 >=\" = \"Ten\"\n").unwrap();
+        assert!(store.load_goals(hash).is_err());
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[cfg(feature = "script")]
+    #[test]
+    fn load_script() {
+        let dir = std::env::temp_dir().join(format!("oxid8-script-{}", std::process::id()));
+        let store = ConfigStore::new(&dir);
+        let hash = "da39a3ee5e6b4b0d3255bfef95601890afd80709";
+        assert!(store.load_script(hash).unwrap().is_none());
+
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(store.script_path(hash), "announce(\"Ready\");").unwrap();
+        let mut script = store.load_script(hash).unwrap().unwrap();
+        script.run_frame(&mut Oxid8::new()).unwrap();
+        assert_eq!(script.announcements(), ["Ready"]);
+
+        fs::write(store.script_path(hash), "announce(").unwrap();
+        assert!(store.load_script(hash).is_err());
+        let _ = fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,219 @@
+//! Savestates stored as their changes from another.
+//!
+//! A [`SaveState`] is over 6KB, most of it memory and display that
+//! barely change from one frame to the next. A [`StateDelta`] keeps a
+//! state's registers as they are and its memory and packed display as
+//! the XOR against a base state, run-length encoded so the unchanged
+//! bytes, zero after the XOR, cost nothing. A frame of a typical game
+//! comes to a few hundred bytes.
+//!
+//! [`Rewind`](crate::rewind::Rewind) keeps every frame like this,
+//! against a full keyframe every second.
+//!
+//! ```
+//! use oxid8_core::Oxid8;
+//!
+//! let mut emu = Oxid8::new();
+//! emu.load_font();
+//! // LD F, V0; DRW V0, V0, 5; JP 204
+//! emu.load_rom_bytes(&[0xF0, 0x29, 0xD0, 0x05, 0x12, 0x04]).unwrap();
+//! let base = emu.save_state();
+//! emu.next_frame().unwrap();
+//!
+//! let state = emu.save_state();
+//! let delta = state.delta(&base);
+//! assert!(delta.size() < 512);
+//! assert_eq!(delta.apply(&base), state);
+//! ```
+
+use std::mem;
+
+use crate::{
+    NUM_KEYS, NUM_REGS, STACK_SIZE,
+    export::{pack_screen, unpack_screen},
+    rng::Rng,
+    savestate::SaveState,
+};
+
+/// Bytes a run of changed bytes costs besides the bytes, for its offset
+/// and length.
+const RUN_HEADER: usize = 4;
+
+/// A savestate as the changes from a base, see the [module docs](self).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StateDelta {
+    pc: u16,
+    v_reg: [u8; NUM_REGS],
+    i_reg: u16,
+    sp: u16,
+    stack: [u16; STACK_SIZE],
+    keys: [bool; NUM_KEYS],
+    stored_key: Option<usize>,
+    dt: u8,
+    st: u8,
+    rng: Rng,
+    cycle_remainder: u32,
+    rom_hash: Option<String>,
+    /// Runs of memory XORed with the base's.
+    ram: Vec<u8>,
+    /// Runs of the packed display XORed with the base's.
+    screen: Vec<u8>,
+}
+
+impl StateDelta {
+    /// The state, rebuilt from the base it was taken against.
+    #[must_use]
+    pub fn apply(&self, base: &SaveState) -> SaveState {
+        let mut ram = base.ram;
+        unpatch(&mut ram, &self.ram);
+        let mut screen = pack_screen(&base.screen);
+        unpatch(&mut screen, &self.screen);
+        SaveState {
+            pc: self.pc,
+            ram,
+            screen: unpack_screen(&screen),
+            v_reg: self.v_reg,
+            i_reg: self.i_reg,
+            sp: self.sp,
+            stack: self.stack,
+            keys: self.keys,
+            stored_key: self.stored_key,
+            dt: self.dt,
+            st: self.st,
+            rng: self.rng.clone(),
+            cycle_remainder: self.cycle_remainder,
+            rom_hash: self.rom_hash.clone(),
+        }
+    }
+
+    /// Bytes the delta takes, roughly.
+    #[must_use]
+    pub fn size(&self) -> usize {
+        mem::size_of::<Self>()
+            + self.ram.len()
+            + self.screen.len()
+            + self.rom_hash.as_ref().map_or(0, String::len)
+    }
+}
+
+impl SaveState {
+    /// This state as its changes from `base`, rebuilt with
+    /// [`StateDelta::apply`] on the same base.
+    #[must_use]
+    pub fn delta(&self, base: &SaveState) -> StateDelta {
+        let SaveState {
+            pc,
+            ref ram,
+            ref screen,
+            v_reg,
+            i_reg,
+            sp,
+            stack,
+            keys,
+            stored_key,
+            dt,
+            st,
+            ref rng,
+            cycle_remainder,
+            ref rom_hash,
+        } = *self;
+        StateDelta {
+            pc,
+            v_reg,
+            i_reg,
+            sp,
+            stack,
+            keys,
+            stored_key,
+            dt,
+            st,
+            rng: rng.clone(),
+            cycle_remainder,
+            rom_hash: rom_hash.clone(),
+            ram: patch(&base.ram, ram),
+            screen: patch(&pack_screen(&base.screen), &pack_screen(screen)),
+        }
+    }
+}
+
+/// Runs of `after` XORed with `before` where they differ, each a
+/// little-endian `u16` count of bytes to skip, one of bytes in the run,
+/// and the run. Runs less than a header apart are joined.
+fn patch(before: &[u8], after: &[u8]) -> Vec<u8> {
+    let mut out = Vec::new();
+    let mut pos = 0;
+    let changed = |i: usize| before[i] != after[i];
+    while let Some(start) = (pos..after.len()).find(|&i| changed(i)) {
+        let mut end = start + 1;
+        while let Some(next) = (end..after.len().min(end + RUN_HEADER)).find(|&i| changed(i)) {
+            end = next + 1;
+        }
+        out.extend_from_slice(&((start - pos) as u16).to_le_bytes());
+        out.extend_from_slice(&((end - start) as u16).to_le_bytes());
+        out.extend(
+            before[start..end]
+                .iter()
+                .zip(&after[start..end])
+                .map(|(a, b)| a ^ b),
+        );
+        pos = end;
+    }
+    out
+}
+
+/// Applies runs from [`patch`] to `bytes`.
+fn unpatch(bytes: &mut [u8], mut runs: &[u8]) {
+    let mut pos = 0;
+    while let [skip0, skip1, len0, len1, rest @ ..] = runs {
+        let start = pos + usize::from(u16::from_le_bytes([*skip0, *skip1]));
+        let len = usize::from(u16::from_le_bytes([*len0, *len1]));
+        for (byte, xor) in bytes[start..start + len].iter_mut().zip(&rest[..len]) {
+            *byte ^= xor;
+        }
+        pos = start + len;
+        runs = &rest[len..];
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn runs() {
+        let before = [0u8; 32];
+        let mut after = before;
+        after[3] = 1;
+        // Joined with the run before, closer than a header.
+        after[6] = 2;
+        after[20] = 3;
+        after[31] = 4;
+        let runs = patch(&before, &after);
+        assert_eq!(
+            runs,
+            [3, 0, 4, 0, 1, 0, 0, 2, 13, 0, 1, 0, 3, 10, 0, 1, 0, 4]
+        );
+        let mut bytes = before;
+        unpatch(&mut bytes, &runs);
+        assert_eq!(bytes, after);
+        assert!(patch(&after, &after).is_empty());
+    }
+
+    #[test]
+    fn rebuilds_states() {
+        let mut emu = crate::Oxid8::new();
+        emu.load_font();
+        // RND V0, FF; LD I, 300; DRW V0, V0, 5; LD B, V0; JP 200
+        emu.load_rom_bytes(&[0xC0, 0xFF, 0xA3, 0x00, 0xD0, 0x05, 0xF0, 0x33, 0x12, 0x00])
+            .unwrap();
+        emu.set_key(0x7, true);
+        let base = emu.save_state();
+        for _ in 0..30 {
+            emu.run_cycle().unwrap();
+            let state = emu.save_state();
+            let delta = state.delta(&base);
+            assert_eq!(delta.apply(&base), state);
+            assert!(delta.size() < mem::size_of::<SaveState>() / 4);
+        }
+    }
+}
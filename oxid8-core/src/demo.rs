@@ -0,0 +1,182 @@
+//! Input scripts for scripted demos.
+//!
+//! Movies are recorded by playing, and list the keys of every frame. A
+//! demo is written by hand instead: a line per frame something happens
+//! on, with the keys pressed and released then, so documentation
+//! captures and conformance tests can drive a game without a person at
+//! the keyboard.
+//!
+//! ```text
+//! # Start the game, then walk right for a second.
+//! seed 7
+//! 60 5
+//! 90 6+
+//! 150 6-
+//! end 200
+//! ```
+//!
+//! A line starts with its frame, counted from 0, followed by keys: `6+`
+//! presses 6, `6-` releases it, and a bare `5` taps it, pressing it on
+//! the frame and releasing it on the next. Events apply at the start of
+//! their frame. `seed` sets the seed of the rng, 0 if not given, and
+//! `end` runs the demo for that many frames instead of stopping after
+//! the last event. `#` starts a comment.
+//!
+//! [`Demo::movie`] turns a demo into a [`Movie`] with the settings of a
+//! core, so it plays back anywhere movies do.
+//!
+//! ```
+//! use oxid8_core::{Oxid8, demo::Demo};
+//!
+//! let mut emu = Oxid8::new();
+//! emu.load_rom_bytes(&[0x12, 0x00]).unwrap(); // JP 200
+//! let demo: Demo = "2 a\n4 1+ 2+\nend 10".parse().unwrap();
+//! let movie = demo.movie(&mut emu);
+//! assert_eq!(movie.frames.len(), 10);
+//! assert_eq!(movie.frames[3][0].to_string(), "0:a-");
+//! for index in 0..movie.frames.len() {
+//!     movie.play_frame(&mut emu, index).unwrap();
+//! }
+//! assert!(emu.keys_ref()[0x1] && emu.keys_ref()[0x2]);
+//! ```
+
+use std::{fs, io, path::Path, str::FromStr};
+
+use crate::{NUM_KEYS, Oxid8, input::InputEvent, movie::Movie};
+
+/// Key events to apply at given frames.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Demo {
+    /// Seed of the rng.
+    pub seed: u64,
+    /// Frames to run, if longer than the events.
+    pub end: Option<usize>,
+    /// Frames and the events applied at their start, sorted by frame.
+    pub events: Vec<(usize, InputEvent)>,
+}
+
+impl Demo {
+    /// Loads a demo from `path`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read or is not a demo.
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        fs::read_to_string(path)?
+            .parse()
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+    }
+
+    /// Frames the demo runs for: up to `end`, and at least to the frame
+    /// of its last event.
+    #[must_use]
+    pub fn frames(&self) -> usize {
+        let last = self.events.last().map_or(0, |&(frame, _)| frame + 1);
+        self.end.unwrap_or(0).max(last)
+    }
+
+    /// A movie of the demo, recorded from `core` with its settings.
+    /// Reseeds the rng of `core` like [`Movie::record`], so call it
+    /// right after loading the rom.
+    pub fn movie(&self, core: &mut Oxid8) -> Movie {
+        let mut movie = Movie::record(core, self.seed);
+        movie.frames = vec![Vec::new(); self.frames()];
+        for &(frame, event) in &self.events {
+            movie.frames[frame].push(event);
+        }
+        movie
+    }
+}
+
+impl FromStr for Demo {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut demo = Demo::default();
+        for line in s.lines() {
+            let line = line.split_once('#').map_or(line, |(line, _)| line).trim();
+            let invalid = || format!("Invalid demo line: {line}");
+            let mut words = line.split_whitespace();
+            let Some(first) = words.next() else {
+                continue;
+            };
+            match first {
+                "seed" => demo.seed = parse_one(words).ok_or_else(invalid)?,
+                "end" => demo.end = Some(parse_one(words).ok_or_else(invalid)?),
+                frame => {
+                    let frame: usize = frame.parse().map_err(|_| invalid())?;
+                    let mut keys = words.peekable();
+                    if keys.peek().is_none() {
+                        return Err(invalid());
+                    }
+                    for key in keys {
+                        let (key, pressed) = match key.split_at_checked(key.len() - 1) {
+                            Some((key, "+")) => (key, Some(true)),
+                            Some((key, "-")) => (key, Some(false)),
+                            _ => (key, None),
+                        };
+                        let key = u8::from_str_radix(key, 16)
+                            .ok()
+                            .filter(|&key| (key as usize) < NUM_KEYS)
+                            .ok_or_else(invalid)?;
+                        let event = |pressed| InputEvent {
+                            cycle: 0,
+                            key,
+                            pressed,
+                        };
+                        match pressed {
+                            Some(pressed) => demo.events.push((frame, event(pressed))),
+                            None => {
+                                demo.events.push((frame, event(true)));
+                                demo.events.push((frame + 1, event(false)));
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        // Stable, so events of a frame stay in the order written.
+        demo.events.sort_by_key(|&(frame, _)| frame);
+        Ok(demo)
+    }
+}
+
+/// The only word left, parsed.
+fn parse_one<'a, T: FromStr>(mut words: impl Iterator<Item = &'a str>) -> Option<T> {
+    let value = words.next()?.parse().ok()?;
+    words.next().is_none().then_some(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses() {
+        let demo: Demo = "# A demo\nseed 7\n\n3 f- 1  # tap\n1 2+\n".parse().unwrap();
+        assert_eq!(demo.seed, 7);
+        let events: Vec<String> = demo
+            .events
+            .iter()
+            .map(|(frame, event)| format!("{frame} {event}"))
+            .collect();
+        assert_eq!(events, ["1 0:2+", "3 0:f-", "3 0:1+", "4 0:1-"]);
+        assert_eq!(demo.frames(), 5);
+
+        for bad in ["x 1", "3", "3 g", "3 10+", "seed", "end 1 2", "-1 1"] {
+            assert!(bad.parse::<Demo>().is_err(), "{bad}");
+        }
+    }
+
+    #[test]
+    fn movie_uses_core_settings() {
+        let mut emu = Oxid8::new();
+        emu.load_rom_bytes(&[0x12, 0x00]).unwrap();
+        emu.set_cycles_per_frame(30);
+        let demo: Demo = "seed 3\n0 4".parse().unwrap();
+        let movie = demo.movie(&mut emu);
+        assert_eq!((movie.seed, movie.cycles_per_frame), (3, 30));
+        assert_eq!(movie.rom_hash.as_deref(), emu.rom_hash());
+        assert_eq!(movie.frames.len(), 2);
+    }
+}
@@ -0,0 +1,563 @@
+//! Instruction mnemonics for debuggers, as in Cowgod's Chip-8
+//! technical reference, and [`OPCODES`], a table of what each
+//! instruction's fields are and what it does, for opcode inspectors.
+//!
+//! ```
+//! use oxid8_core::disasm;
+//!
+//! assert_eq!(disasm::mnemonic(0x6A2B), "LD VA, 2B");
+//!
+//! let ram = [0x00, 0xE0, 0x12, 0x00];
+//! let lines = disasm::disassemble(&ram, 0.into(), 4);
+//! assert_eq!(lines[1].to_string(), "0x002 1200 JP 200");
+//! assert_eq!(lines.len(), 2);
+//!
+//! let info = disasm::info(0x6A2B).unwrap();
+//! assert_eq!(info.pattern, "6xkk");
+//! assert_eq!(info.description, "Set Vx to kk.");
+//! ```
+
+use std::fmt;
+
+use crate::{Opcode, golden, layout::Addr};
+
+/// A field encoded in an instruction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Field {
+    /// Register index in the second nibble.
+    X,
+    /// Register index in the third nibble.
+    Y,
+    /// The low nibble.
+    N,
+    /// The low byte.
+    Kk,
+    /// The low 12 bits, an address.
+    Nnn,
+}
+
+impl Field {
+    /// The value of this field in `opcode`.
+    #[must_use]
+    pub fn value(self, opcode: u16) -> u16 {
+        match self {
+            Field::X => opcode >> 8 & 0xF,
+            Field::Y => opcode >> 4 & 0xF,
+            Field::N => opcode & 0xF,
+            Field::Kk => opcode & 0xFF,
+            Field::Nnn => opcode & 0xFFF,
+        }
+    }
+}
+
+/// Formatted as in the patterns, e.g. "x" or "nnn".
+impl fmt::Display for Field {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Field::X => "x",
+            Field::Y => "y",
+            Field::N => "n",
+            Field::Kk => "kk",
+            Field::Nnn => "nnn",
+        })
+    }
+}
+
+/// A register an instruction reads or writes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operand {
+    Vx,
+    Vy,
+    V0,
+    Vf,
+    I,
+    Dt,
+    St,
+    Sp,
+}
+
+impl Operand {
+    /// The register `opcode` means, to read with [`golden::Field::read`].
+    #[must_use]
+    pub fn register(self, opcode: u16) -> golden::Field {
+        match self {
+            Operand::Vx => golden::Field::V(Field::X.value(opcode) as u8),
+            Operand::Vy => golden::Field::V(Field::Y.value(opcode) as u8),
+            Operand::V0 => golden::Field::V(0),
+            Operand::Vf => golden::Field::V(0xF),
+            Operand::I => golden::Field::I,
+            Operand::Dt => golden::Field::Dt,
+            Operand::St => golden::Field::St,
+            Operand::Sp => golden::Field::Sp,
+        }
+    }
+}
+
+/// What an instruction is, for opcode inspectors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OpcodeInfo {
+    /// Bits of the opcode that pick the instruction.
+    mask: u16,
+    bits: u16,
+    /// Written like "8xy4".
+    pub pattern: &'static str,
+    pub fields: &'static [Field],
+    /// Registers read or written.
+    pub operands: &'static [Operand],
+    /// What it does in plain English.
+    pub description: &'static str,
+}
+
+impl OpcodeInfo {
+    /// Returns true if `opcode` is this instruction.
+    #[must_use]
+    pub fn matches(&self, opcode: u16) -> bool {
+        opcode & self.mask == self.bits
+    }
+
+    /// The registers `opcode` reads or writes, without repeats when x
+    /// and y are the same.
+    #[must_use]
+    pub fn registers(&self, opcode: u16) -> Vec<golden::Field> {
+        let mut registers = Vec::new();
+        for operand in self.operands {
+            let register = operand.register(opcode);
+            if !registers.contains(&register) {
+                registers.push(register);
+            }
+        }
+        registers
+    }
+}
+
+macro_rules! op {
+    ($mask:literal, $bits:literal, $pattern:literal, [$($field:ident),*], [$($operand:ident),*], $description:literal) => {
+        OpcodeInfo {
+            mask: $mask,
+            bits: $bits,
+            pattern: $pattern,
+            fields: &[$(Field::$field),*],
+            operands: &[$(Operand::$operand),*],
+            description: $description,
+        }
+    };
+}
+
+/// Every instruction the interpreter runs, as it runs them.
+pub static OPCODES: [OpcodeInfo; 34] = [
+    op!(0xFFFF, 0x00E0, "00E0", [], [], "Clear the screen."),
+    op!(
+        0xFFFF,
+        0x00EE,
+        "00EE",
+        [],
+        [Sp],
+        "Return from a subroutine to the address on top of the stack."
+    ),
+    op!(0xF000, 0x1000, "1nnn", [Nnn], [], "Jump to nnn."),
+    op!(
+        0xF000,
+        0x2000,
+        "2nnn",
+        [Nnn],
+        [Sp],
+        "Call the subroutine at nnn, pushing the next address onto the stack."
+    ),
+    op!(
+        0xF000,
+        0x3000,
+        "3xkk",
+        [X, Kk],
+        [Vx],
+        "Skip the next instruction if Vx equals kk."
+    ),
+    op!(
+        0xF000,
+        0x4000,
+        "4xkk",
+        [X, Kk],
+        [Vx],
+        "Skip the next instruction if Vx does not equal kk."
+    ),
+    op!(
+        0xF000,
+        0x5000,
+        "5xy0",
+        [X, Y],
+        [Vx, Vy],
+        "Skip the next instruction if Vx equals Vy."
+    ),
+    op!(0xF000, 0x6000, "6xkk", [X, Kk], [Vx], "Set Vx to kk."),
+    op!(
+        0xF000,
+        0x7000,
+        "7xkk",
+        [X, Kk],
+        [Vx],
+        "Add kk to Vx, wrapping without a carry."
+    ),
+    op!(0xF00F, 0x8000, "8xy0", [X, Y], [Vx, Vy], "Set Vx to Vy."),
+    op!(
+        0xF00F,
+        0x8001,
+        "8xy1",
+        [X, Y],
+        [Vx, Vy],
+        "Set Vx to Vx OR Vy."
+    ),
+    op!(
+        0xF00F,
+        0x8002,
+        "8xy2",
+        [X, Y],
+        [Vx, Vy],
+        "Set Vx to Vx AND Vy."
+    ),
+    op!(
+        0xF00F,
+        0x8003,
+        "8xy3",
+        [X, Y],
+        [Vx, Vy],
+        "Set Vx to Vx XOR Vy."
+    ),
+    op!(
+        0xF00F,
+        0x8004,
+        "8xy4",
+        [X, Y],
+        [Vx, Vy, Vf],
+        "Add Vy to Vx, then set VF to 1 if it carried or 0 if not."
+    ),
+    op!(
+        0xF00F,
+        0x8005,
+        "8xy5",
+        [X, Y],
+        [Vx, Vy, Vf],
+        "Subtract Vy from Vx, then set VF to 1 if it did not borrow or 0 if it did."
+    ),
+    op!(
+        0xF00F,
+        0x8006,
+        "8xy6",
+        [X, Y],
+        [Vx, Vf],
+        "Shift Vx right by one, then set VF to the bit shifted out. Vy is ignored unless shift=vy."
+    ),
+    op!(
+        0xF00F,
+        0x8007,
+        "8xy7",
+        [X, Y],
+        [Vx, Vy, Vf],
+        "Set Vx to Vy minus Vx, then set VF to 1 if it did not borrow or 0 if it did."
+    ),
+    op!(
+        0xF00F,
+        0x800E,
+        "8xyE",
+        [X, Y],
+        [Vx, Vf],
+        "Shift Vx left by one, then set VF to the bit shifted out. Vy is ignored unless shift=vy."
+    ),
+    op!(
+        0xF000,
+        0x9000,
+        "9xy0",
+        [X, Y],
+        [Vx, Vy],
+        "Skip the next instruction if Vx does not equal Vy."
+    ),
+    op!(0xF000, 0xA000, "Annn", [Nnn], [I], "Set I to nnn."),
+    op!(0xF000, 0xB000, "Bnnn", [Nnn], [V0], "Jump to nnn plus V0."),
+    op!(
+        0xF000,
+        0xC000,
+        "Cxkk",
+        [X, Kk],
+        [Vx],
+        "Set Vx to a random byte AND kk."
+    ),
+    op!(
+        0xF000,
+        0xD000,
+        "Dxyn",
+        [X, Y, N],
+        [Vx, Vy, I, Vf],
+        "Draw the n byte sprite at I at (Vx, Vy) by XORing it onto the screen, then set VF to 1 if any lit pixel was turned off or 0 if not."
+    ),
+    op!(
+        0xF0FF,
+        0xE09E,
+        "Ex9E",
+        [X],
+        [Vx],
+        "Skip the next instruction if the key in Vx is held."
+    ),
+    op!(
+        0xF0FF,
+        0xE0A1,
+        "ExA1",
+        [X],
+        [Vx],
+        "Skip the next instruction if the key in Vx is not held."
+    ),
+    op!(
+        0xF0FF,
+        0xF007,
+        "Fx07",
+        [X],
+        [Vx, Dt],
+        "Set Vx to the delay timer."
+    ),
+    op!(
+        0xF0FF,
+        0xF00A,
+        "Fx0A",
+        [X],
+        [Vx],
+        "Wait for a key to be pressed and released, then set Vx to it."
+    ),
+    op!(
+        0xF0FF,
+        0xF015,
+        "Fx15",
+        [X],
+        [Vx, Dt],
+        "Set the delay timer to Vx."
+    ),
+    op!(
+        0xF0FF,
+        0xF018,
+        "Fx18",
+        [X],
+        [Vx, St],
+        "Set the sound timer to Vx, the beeper sounds while it is above 0."
+    ),
+    op!(0xF0FF, 0xF01E, "Fx1E", [X], [Vx, I], "Add Vx to I."),
+    op!(
+        0xF0FF,
+        0xF029,
+        "Fx29",
+        [X],
+        [Vx, I],
+        "Set I to the font glyph for the low digit of Vx."
+    ),
+    op!(
+        0xF0FF,
+        0xF033,
+        "Fx33",
+        [X],
+        [Vx, I],
+        "Store the hundreds, tens, and ones digits of Vx at I, I + 1, and I + 2."
+    ),
+    op!(
+        0xF0FF,
+        0xF055,
+        "Fx55",
+        [X],
+        [Vx, I],
+        "Store V0 through Vx in memory from I. I is not changed."
+    ),
+    op!(
+        0xF0FF,
+        0xF065,
+        "Fx65",
+        [X],
+        [Vx, I],
+        "Load V0 through Vx from memory from I. I is not changed."
+    ),
+];
+
+/// The entry of [`OPCODES`] for `opcode`, none if it isn't an
+/// instruction.
+#[must_use]
+pub fn info(opcode: u16) -> Option<&'static OpcodeInfo> {
+    OPCODES.iter().find(|info| info.matches(opcode))
+}
+
+/// The mnemonic for `opcode`, `???` if it isn't an instruction.
+#[must_use]
+pub fn mnemonic(opcode: u16) -> String {
+    let [hi, lo] = opcode.to_be_bytes();
+    let op = Opcode::new(hi, lo);
+    let (x, y, n, kk, nnn) = (op.x(), op.y(), op.n(), op.kk(), op.nnn().get());
+    match (op.0, n) {
+        (0x0, _) if opcode == 0x00E0 => "CLS".into(),
+        (0x0, _) if opcode == 0x00EE => "RET".into(),
+        (0x1, _) => format!("JP {nnn:X}"),
+        (0x2, _) => format!("CALL {nnn:X}"),
+        (0x3, _) => format!("SE V{x:X}, {kk:02X}"),
+        (0x4, _) => format!("SNE V{x:X}, {kk:02X}"),
+        (0x5, _) => format!("SE V{x:X}, V{y:X}"),
+        (0x6, _) => format!("LD V{x:X}, {kk:02X}"),
+        (0x7, _) => format!("ADD V{x:X}, {kk:02X}"),
+        (0x8, 0x0) => format!("LD V{x:X}, V{y:X}"),
+        (0x8, 0x1) => format!("OR V{x:X}, V{y:X}"),
+        (0x8, 0x2) => format!("AND V{x:X}, V{y:X}"),
+        (0x8, 0x3) => format!("XOR V{x:X}, V{y:X}"),
+        (0x8, 0x4) => format!("ADD V{x:X}, V{y:X}"),
+        (0x8, 0x5) => format!("SUB V{x:X}, V{y:X}"),
+        (0x8, 0x6) => format!("SHR V{x:X}, V{y:X}"),
+        (0x8, 0x7) => format!("SUBN V{x:X}, V{y:X}"),
+        (0x8, 0xE) => format!("SHL V{x:X}, V{y:X}"),
+        (0x9, _) => format!("SNE V{x:X}, V{y:X}"),
+        (0xA, _) => format!("LD I, {nnn:X}"),
+        (0xB, _) => format!("JP V0, {nnn:X}"),
+        (0xC, _) => format!("RND V{x:X}, {kk:02X}"),
+        (0xD, _) => format!("DRW V{x:X}, V{y:X}, {n:X}"),
+        (0xE, _) if kk == 0x9E => format!("SKP V{x:X}"),
+        (0xE, _) if kk == 0xA1 => format!("SKNP V{x:X}"),
+        (0xF, _) => match kk {
+            0x07 => format!("LD V{x:X}, DT"),
+            0x0A => format!("LD V{x:X}, K"),
+            0x15 => format!("LD DT, V{x:X}"),
+            0x18 => format!("LD ST, V{x:X}"),
+            0x1E => format!("ADD I, V{x:X}"),
+            0x29 => format!("LD F, V{x:X}"),
+            0x33 => format!("LD B, V{x:X}"),
+            0x55 => format!("LD [I], V{x:X}"),
+            0x65 => format!("LD V{x:X}, [I]"),
+            _ => "???".into(),
+        },
+        _ => "???".into(),
+    }
+}
+
+/// A disassembled instruction.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Line {
+    pub addr: Addr,
+    pub opcode: u16,
+    pub mnemonic: String,
+}
+
+/// Formatted like "0x200 6A2B LD VA, 2B".
+impl fmt::Display for Line {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {:04X} {}", self.addr, self.opcode, self.mnemonic)
+    }
+}
+
+/// Disassembles up to `count` instructions of `ram` from `addr`,
+/// stopping at the end of RAM.
+#[must_use]
+pub fn disassemble(ram: &[u8], addr: Addr, count: usize) -> Vec<Line> {
+    (0..count as u16)
+        .map(|i| addr.wrapping_add(2 * i))
+        .map_while(|addr| {
+            let bytes = ram.get(addr.range(2))?;
+            let opcode = u16::from_be_bytes([bytes[0], bytes[1]]);
+            Some(Line {
+                addr,
+                opcode,
+                mnemonic: mnemonic(opcode),
+            })
+        })
+        .collect()
+}
+
+/// Disassembles up to `count` instructions from `before` instructions
+/// ahead of `pc`, or from as far back as RAM goes. The window keeps
+/// the alignment of `pc`, so an odd program counter, or code jumped
+/// into halfway, is shown as it will run.
+#[must_use]
+pub fn around(ram: &[u8], pc: Addr, before: usize, count: usize) -> Vec<Line> {
+    let before = (pc.get() as usize / 2).min(before) as u16;
+    disassemble(ram, Addr::new(pc.get() - 2 * before), count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mnemonics() {
+        for (opcode, expected) in [
+            (0x00E0, "CLS"),
+            (0x00EE, "RET"),
+            (0x0123, "???"),
+            (0x2ABC, "CALL ABC"),
+            (0x3105, "SE V1, 05"),
+            (0x8AB6, "SHR VA, VB"),
+            (0x8AB8, "???"),
+            (0xB300, "JP V0, 300"),
+            (0xD12F, "DRW V1, V2, F"),
+            (0xE59E, "SKP V5"),
+            (0xE5A2, "???"),
+            (0xF40A, "LD V4, K"),
+            (0xFF55, "LD [I], VF"),
+            (0xF0FF, "???"),
+        ] {
+            assert_eq!(mnemonic(opcode), expected, "{opcode:04X}");
+        }
+    }
+
+    #[test]
+    fn around_odd_pc() {
+        let ram = [0x60, 0x01, 0x12, 0x01, 0x00, 0xE0];
+        let addrs = |pc: u16| -> Vec<u16> {
+            around(&ram, Addr::new(pc), 2, 4)
+                .iter()
+                .map(|line| line.addr.get())
+                .collect()
+        };
+        assert_eq!(addrs(0x4), [0x0, 0x2, 0x4]);
+        assert_eq!(addrs(0x3), [0x1, 0x3]);
+        assert_eq!(addrs(0x1), [0x1, 0x3]);
+
+        // Data read as the code it is when jumped into.
+        let lines = around(&ram, Addr::new(0x1), 0, 1);
+        assert_eq!(lines[0].to_string(), "0x001 0112 ???");
+    }
+
+    #[test]
+    fn table_matches_mnemonics() {
+        for opcode in 0..=u16::MAX {
+            assert_eq!(
+                info(opcode).is_some(),
+                mnemonic(opcode) != "???",
+                "{opcode:04X}"
+            );
+        }
+        for (i, info) in OPCODES.iter().enumerate() {
+            assert_eq!(super::info(info.bits), Some(info), "{}", info.pattern);
+            assert!(
+                OPCODES[..i]
+                    .iter()
+                    .all(|other| other.pattern != info.pattern)
+            );
+        }
+    }
+
+    #[test]
+    fn fields_and_registers() {
+        let info = info(0x8AB4).unwrap();
+        assert_eq!(info.pattern, "8xy4");
+        let fields: Vec<String> = info
+            .fields
+            .iter()
+            .map(|field| format!("{field} = {:X}", field.value(0x8AB4)))
+            .collect();
+        assert_eq!(fields, ["x = A", "y = B"]);
+        assert_eq!(
+            info.registers(0x8AB4),
+            [
+                golden::Field::V(0xA),
+                golden::Field::V(0xB),
+                golden::Field::V(0xF)
+            ]
+        );
+        // VF once when x is F.
+        assert_eq!(info.registers(0x8F14).len(), 2);
+    }
+
+    #[test]
+    fn stops_at_end_of_ram() {
+        let ram = [0x60, 0x01, 0x70];
+        let lines = disassemble(&ram, Addr::new(0), 8);
+        assert_eq!(lines.len(), 1);
+        assert_eq!(lines[0].to_string(), "0x000 6001 LD V0, 01");
+    }
+}
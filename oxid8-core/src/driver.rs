@@ -0,0 +1,385 @@
+//! Async driver for embedding the core in async frontends.
+//!
+//! [`run`] paces the core with a runtime agnostic timer, applies key
+//! events from a stream, and sends every frame to a sink, so async GUI
+//! frameworks and network servers can run the emulator as a task
+//! instead of a thread with a busy loop.
+//!
+//! [`run_with_clock`] paces frames off a [`Clock`] instead, e.g. an
+//! [`AudioClock`](crate::clock::AudioClock) counting samples played by
+//! the audio output, so sound and video never drift apart.
+//!
+//! [`run_with_capabilities`] and [`run_with_clock_and_capabilities`]
+//! adapt to the frontend's [`Capabilities`]: without key releases keys
+//! time out, and frames over its frame rate are not sent.
+//!
+//! ```no_run
+//! use futures::{channel::mpsc, executor::block_on, join, StreamExt};
+//! use oxid8_core::{Oxid8, driver};
+//!
+//! let mut core = Oxid8::new();
+//! core.load_font();
+//! core.load_rom("pong.ch8").unwrap();
+//!
+//! let (frame_tx, mut frame_rx) = mpsc::channel(1);
+//! let (key_tx, key_rx) = mpsc::unbounded();
+//!
+//! block_on(async {
+//!     let render = async move {
+//!         while let Some(frame) = frame_rx.next().await {
+//!             // Draw `frame.screen`...
+//!         }
+//!     };
+//!     let (result, ()) = join!(driver::run(&mut core, frame_tx, key_rx), render);
+//!     result.unwrap();
+//! });
+//! # drop(key_tx);
+//! ```
+
+use futures::{FutureExt, Sink, SinkExt, Stream, StreamExt, select_biased, stream::Fuse};
+use futures_timer::Delay;
+use std::time::Duration;
+
+use crate::{
+    Oxid8, SCREEN_AREA,
+    clock::Clock,
+    frontend::{Capabilities, KeyTimeout},
+};
+
+/// A frame emitted by [`run`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Frame {
+    /// Copy of the screen, see [`Oxid8::screen_ref`].
+    pub screen: [bool; SCREEN_AREA],
+    /// Whether the sound timer is active, see [`Oxid8::sound`].
+    pub sound: bool,
+}
+
+impl Frame {
+    /// Captures the current frame of `core`.
+    #[must_use]
+    pub fn capture(core: &Oxid8) -> Self {
+        Self {
+            screen: *core.screen_ref(),
+            sound: core.sound(),
+        }
+    }
+}
+
+/// A key on the virtual keypad was pressed or released.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyEvent {
+    /// 0x0 - 0xF
+    pub key: u8,
+    pub pressed: bool,
+}
+
+/// Runs `core` at its timer rate until `input` ends or `frames` is
+/// closed.
+///
+/// Key events are applied as soon as they arrive. After each frame is
+/// emulated it is sent to `frames`; a slow sink slows down emulation
+/// instead of dropping frames.
+///
+/// # Errors
+///
+/// Returns the error of `next_frame` if the rom is bad.
+pub async fn run<S, I>(core: &mut Oxid8, frames: S, input: I) -> Result<(), String>
+where
+    S: Sink<Frame> + Unpin,
+    I: Stream<Item = KeyEvent> + Unpin,
+{
+    run_with_capabilities(core, frames, input, Capabilities::default()).await
+}
+
+/// Runs `core` like [`run`] for a frontend with `capabilities`.
+///
+/// # Errors
+///
+/// Returns the error of `next_frame` if the rom is bad.
+pub async fn run_with_capabilities<S, I>(
+    core: &mut Oxid8,
+    mut frames: S,
+    input: I,
+    capabilities: Capabilities,
+) -> Result<(), String>
+where
+    S: Sink<Frame> + Unpin,
+    I: Stream<Item = KeyEvent> + Unpin,
+{
+    let mut input = input.fuse();
+    let mut session = Session::new(capabilities);
+
+    loop {
+        if !session
+            .wait(core, &mut input, core.timer_rate().tick())
+            .await
+        {
+            return Ok(());
+        }
+
+        if let Some(frame) = session.next_frame(core)?
+            && frames.send(frame).await.is_err()
+        {
+            return Ok(());
+        }
+    }
+}
+
+/// Runs `core` like [`run`], emulating frames when `clock` says they
+/// are due instead of once per timer tick.
+///
+/// The clock is polled every quarter of a tick, and frames due at the
+/// same time are emulated back to back.
+///
+/// # Errors
+///
+/// Returns the error of `next_frame` if the rom is bad.
+pub async fn run_with_clock<S, I, C>(
+    core: &mut Oxid8,
+    frames: S,
+    input: I,
+    clock: C,
+) -> Result<(), String>
+where
+    S: Sink<Frame> + Unpin,
+    I: Stream<Item = KeyEvent> + Unpin,
+    C: Clock,
+{
+    run_with_clock_and_capabilities(core, frames, input, clock, Capabilities::default()).await
+}
+
+/// Runs `core` like [`run_with_clock`] for a frontend with
+/// `capabilities`.
+///
+/// # Errors
+///
+/// Returns the error of `next_frame` if the rom is bad.
+pub async fn run_with_clock_and_capabilities<S, I, C>(
+    core: &mut Oxid8,
+    mut frames: S,
+    input: I,
+    mut clock: C,
+    capabilities: Capabilities,
+) -> Result<(), String>
+where
+    S: Sink<Frame> + Unpin,
+    I: Stream<Item = KeyEvent> + Unpin,
+    C: Clock,
+{
+    let mut input = input.fuse();
+    let mut session = Session::new(capabilities);
+
+    loop {
+        let due = clock.frames_due(core.timer_rate());
+        if due == 0 {
+            if !session
+                .wait(core, &mut input, core.timer_rate().tick() / 4)
+                .await
+            {
+                return Ok(());
+            }
+            continue;
+        }
+
+        for _ in 0..due {
+            if let Some(frame) = session.next_frame(core)?
+                && frames.send(frame).await.is_err()
+            {
+                return Ok(());
+            }
+        }
+    }
+}
+
+/// Driver state adapting to the frontend's capabilities.
+struct Session {
+    capabilities: Capabilities,
+    timeout: KeyTimeout,
+    /// Frames emulated so far.
+    emulated: u64,
+    /// Sound in the last frame.
+    sound: bool,
+}
+
+impl Session {
+    fn new(capabilities: Capabilities) -> Self {
+        Self {
+            capabilities,
+            timeout: KeyTimeout::default(),
+            emulated: 0,
+            sound: false,
+        }
+    }
+
+    /// Applies key events from `input` for `duration`. Returns false if
+    /// `input` ended.
+    async fn wait<I>(&mut self, core: &mut Oxid8, input: &mut Fuse<I>, duration: Duration) -> bool
+    where
+        I: Stream<Item = KeyEvent> + Unpin,
+    {
+        let mut tick = Delay::new(duration).fuse();
+        loop {
+            select_biased! {
+                () = tick => return true,
+                event = input.next() => match event {
+                    Some(event) => self.key(core, event),
+                    None => return false,
+                },
+            }
+        }
+    }
+
+    fn key(&mut self, core: &mut Oxid8, KeyEvent { key, pressed }: KeyEvent) {
+        let k = key as usize;
+        match (k < 0x10, self.capabilities.key_release, pressed) {
+            (false, _, _) => (),
+            (true, true, _) => core.set_key(k, pressed),
+            (true, false, true) => self.timeout.press(core, k),
+            (true, false, false) => self.timeout.release(core, k),
+        }
+    }
+
+    /// Emulates a frame, returning it if it should be presented.
+    fn next_frame(&mut self, core: &mut Oxid8) -> Result<Option<Frame>, String> {
+        core.next_frame()?;
+        if !self.capabilities.key_release {
+            self.timeout.tick(core);
+        }
+
+        let n = self.emulated;
+        self.emulated += 1;
+        let beep = self.capabilities.audio && core.sound() != self.sound;
+        self.sound = core.sound();
+
+        let present = beep || self.capabilities.presents(n, core.timer_rate().hz());
+        Ok(present.then(|| Frame::capture(core)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frontend::KEY_TIMEOUT;
+    use futures::{channel::mpsc, executor::block_on, join};
+
+    #[test]
+    fn frames_and_keys() {
+        // 00E0 - CLS; 1200 - JP 200
+        let mut core = Oxid8::new();
+        core.load_rom_bytes(&[0x00, 0xE0, 0x12, 0x00]).unwrap();
+
+        let (frame_tx, mut frame_rx) = mpsc::channel::<Frame>(0);
+        let (mut key_tx, key_rx) = mpsc::unbounded();
+
+        let client = async move {
+            key_tx
+                .send(KeyEvent {
+                    key: 0x5,
+                    pressed: true,
+                })
+                .await
+                .unwrap();
+            for _ in 0..3 {
+                let frame = frame_rx.next().await.unwrap();
+                assert!(!frame.sound);
+                assert!(frame.screen.iter().all(|&px| !px));
+            }
+            // Closing the input stops the driver.
+        };
+
+        let (result, ()) = block_on(async { join!(run(&mut core, frame_tx, key_rx), client) });
+        assert_eq!(result, Ok(()));
+        assert!(core.keys[0x5]);
+    }
+
+    #[test]
+    fn audio_paced() {
+        use crate::clock::AudioClock;
+
+        let mut core = Oxid8::new();
+        core.load_rom_bytes(&[0x12, 0x00]).unwrap();
+
+        let clock = AudioClock::new(44_100);
+        let played = clock.counter();
+        let (frame_tx, mut frame_rx) = mpsc::channel::<Frame>(0);
+        let (key_tx, key_rx) = mpsc::unbounded::<KeyEvent>();
+
+        let audio = async move {
+            // Three frames of samples played, three frames emulated.
+            played.add(735 * 3);
+            for _ in 0..3 {
+                frame_rx.next().await.unwrap();
+            }
+            drop(key_tx);
+        };
+
+        let (result, ()) = block_on(async {
+            join!(
+                run_with_clock_and_capabilities(
+                    &mut core,
+                    frame_tx,
+                    key_rx,
+                    clock,
+                    Capabilities {
+                        audio: true,
+                        ..Capabilities::default()
+                    }
+                ),
+                audio
+            )
+        });
+        assert_eq!(result, Ok(()));
+    }
+
+    #[test]
+    fn closed_sink() {
+        let mut core = Oxid8::new();
+        core.load_rom_bytes(&[0x12, 0x00]).unwrap();
+
+        let (frame_tx, frame_rx) = mpsc::channel(0);
+        let (_key_tx, key_rx) = mpsc::unbounded::<KeyEvent>();
+        drop(frame_rx);
+
+        assert_eq!(block_on(run(&mut core, frame_tx, key_rx)), Ok(()));
+    }
+
+    #[test]
+    fn key_timeout_and_frame_rate() {
+        // 1200 - JP 200
+        let mut core = Oxid8::new();
+        core.load_rom_bytes(&[0x12, 0x00]).unwrap();
+
+        let (frame_tx, mut frame_rx) = mpsc::channel::<Frame>(0);
+        let (mut key_tx, key_rx) = mpsc::unbounded();
+        let capabilities = Capabilities {
+            key_release: false,
+            max_frame_rate: Some(30),
+            ..Capabilities::default()
+        };
+
+        let client = async move {
+            key_tx
+                .send(KeyEvent {
+                    key: 0x5,
+                    pressed: true,
+                })
+                .await
+                .unwrap();
+            // Every other frame is sent, so the key timed out by the
+            // one after half the timeout.
+            for _ in 0..=KEY_TIMEOUT / 2 {
+                frame_rx.next().await.unwrap();
+            }
+        };
+
+        let (result, ()) = block_on(async {
+            join!(
+                run_with_capabilities(&mut core, frame_tx, key_rx, capabilities),
+                client
+            )
+        });
+        assert_eq!(result, Ok(()));
+        assert!(!core.keys[0x5]);
+    }
+}
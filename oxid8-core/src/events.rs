@@ -0,0 +1,231 @@
+//! Notable events of a headless run, as JSON lines.
+//!
+//! Tools and CI jobs checking a run want to know what happened, not
+//! read text written for people. An [`EventLog`] watches the core after
+//! every frame and reports the [`Event`]s worth asserting on: pixels
+//! drawn, the sound starting or stopping, and errors. Each event is
+//! formatted as one JSON object, so a run's log is [JSON Lines] and can
+//! be filtered with `jq` or read a line at a time.
+//!
+//! ```
+//! use oxid8_core::{Oxid8, events::{Event, EventLog}};
+//!
+//! let mut emu = Oxid8::new();
+//! // LD V0, 5; LD ST, V0; LD I, 20A; DRW V0, V0, 1; JP 208; 0xFF
+//! let rom = [0x60, 0x05, 0xF0, 0x18, 0xA2, 0x0A, 0xD0, 0x01, 0x12, 0x08, 0xFF];
+//! emu.load_rom_bytes(&rom).unwrap();
+//! let mut log = EventLog::new(&mut emu);
+//!
+//! emu.next_frame().unwrap();
+//! let events = log.frame(&mut emu);
+//! assert_eq!(events[0], Event::Frame { frame: 0 });
+//! assert!(matches!(events[1], Event::Draw { pixels: 8, .. }));
+//! assert_eq!(
+//!     events[2].to_string(),
+//!     r#"{"event":"sound","frame":0,"on":true}"#
+//! );
+//! ```
+//!
+//! [JSON Lines]: https://jsonlines.org
+
+use std::fmt;
+
+use crate::{Oxid8, video::Region};
+
+/// Something that happened during a run.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Event {
+    /// A frame ran.
+    Frame { frame: usize },
+    /// Pixels were flipped during a frame.
+    Draw {
+        frame: usize,
+        pixels: u32,
+        region: Region,
+    },
+    /// The sound started or stopped during a frame.
+    Sound { frame: usize, on: bool },
+    /// The core failed during a frame.
+    Error { frame: usize, message: String },
+    /// The run finished after `frames` frames, with a screen hashing to
+    /// `screen`.
+    Done { frames: usize, screen: String },
+}
+
+/// Formatted like `{"event":"draw","frame":3,"pixels":5,"x":0,"y":0,"width":8,"height":5}`.
+impl fmt::Display for Event {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Event::Frame { frame } => write!(f, r#"{{"event":"frame","frame":{frame}}}"#),
+            Event::Draw {
+                frame,
+                pixels,
+                region,
+            } => write!(
+                f,
+                r#"{{"event":"draw","frame":{frame},"pixels":{pixels},"x":{},"y":{},"width":{},"height":{}}}"#,
+                region.x, region.y, region.width, region.height
+            ),
+            Event::Sound { frame, on } => {
+                write!(f, r#"{{"event":"sound","frame":{frame},"on":{on}}}"#)
+            }
+            Event::Error { frame, message } => write!(
+                f,
+                r#"{{"event":"error","frame":{frame},"message":{}}}"#,
+                JsonString(message)
+            ),
+            Event::Done { frames, screen } => write!(
+                f,
+                r#"{{"event":"done","frames":{frames},"screen":{}}}"#,
+                JsonString(screen)
+            ),
+        }
+    }
+}
+
+/// A string quoted and escaped for JSON.
+struct JsonString<'a>(&'a str);
+
+impl fmt::Display for JsonString<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("\"")?;
+        for c in self.0.chars() {
+            match c {
+                '"' => f.write_str("\\\"")?,
+                '\\' => f.write_str("\\\\")?,
+                '\n' => f.write_str("\\n")?,
+                '\r' => f.write_str("\\r")?,
+                '\t' => f.write_str("\\t")?,
+                c if c.is_control() => write!(f, "\\u{:04x}", c as u32)?,
+                c => write!(f, "{c}")?,
+            }
+        }
+        f.write_str("\"")
+    }
+}
+
+/// Turns what a core did each frame into [`Event`]s.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EventLog {
+    /// Frames reported so far.
+    frames: usize,
+    /// Whether the sound was on after the last frame.
+    sound: bool,
+}
+
+impl EventLog {
+    /// A log of `core` from its current state, taking the pixels drawn
+    /// before it so they aren't reported.
+    #[must_use]
+    pub fn new(core: &mut Oxid8) -> Self {
+        core.take_screen_changes();
+        Self {
+            frames: 0,
+            sound: core.sound(),
+        }
+    }
+
+    /// Frames reported so far.
+    #[must_use]
+    pub fn frames(&self) -> usize {
+        self.frames
+    }
+
+    /// The events of the frame `core` just ran. Takes the core's
+    /// [`ScreenChanges`](crate::video::ScreenChanges).
+    pub fn frame(&mut self, core: &mut Oxid8) -> Vec<Event> {
+        let frame = self.frames;
+        self.frames += 1;
+        let mut events = vec![Event::Frame { frame }];
+        let changes = core.take_screen_changes();
+        if let Some(region) = changes.region {
+            events.push(Event::Draw {
+                frame,
+                pixels: changes.pixels,
+                region,
+            });
+        }
+        if core.sound() != self.sound {
+            self.sound = core.sound();
+            events.push(Event::Sound {
+                frame,
+                on: self.sound,
+            });
+        }
+        events
+    }
+
+    /// An error during the frame after the last one reported.
+    #[must_use]
+    pub fn error(&self, message: impl Into<String>) -> Event {
+        Event::Error {
+            frame: self.frames,
+            message: message.into(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sound_transitions() {
+        let mut emu = Oxid8::new();
+        // LD V0, 2; LD ST, V0; JP 204
+        emu.load_rom_bytes(&[0x60, 0x02, 0xF0, 0x18, 0x12, 0x04])
+            .unwrap();
+        let mut log = EventLog::new(&mut emu);
+        let mut sounds = Vec::new();
+        for _ in 0..4 {
+            emu.next_frame().unwrap();
+            sounds.extend(
+                log.frame(&mut emu)
+                    .into_iter()
+                    .filter(|event| matches!(event, Event::Sound { .. })),
+            );
+        }
+        assert_eq!(
+            sounds,
+            [
+                Event::Sound { frame: 0, on: true },
+                Event::Sound {
+                    frame: 1,
+                    on: false
+                },
+            ]
+        );
+        assert_eq!(log.frames(), 4);
+    }
+
+    #[test]
+    fn json() {
+        let event = Event::Draw {
+            frame: 3,
+            pixels: 5,
+            region: Region {
+                x: 0,
+                y: 0,
+                width: 8,
+                height: 5,
+            },
+        };
+        assert_eq!(
+            event.to_string(),
+            r#"{"event":"draw","frame":3,"pixels":5,"x":0,"y":0,"width":8,"height":5}"#
+        );
+        let log = EventLog::new(&mut Oxid8::new());
+        assert_eq!(
+            log.error("Bad \"opcode\"\n\u{1}").to_string(),
+            r#"{"event":"error","frame":0,"message":"Bad \"opcode\"\n\u0001"}"#
+        );
+        let done = Event::Done {
+            frames: 10,
+            screen: "abc".into(),
+        };
+        assert_eq!(
+            done.to_string(),
+            r#"{"event":"done","frames":10,"screen":"abc"}"#
+        );
+    }
+}
@@ -0,0 +1,405 @@
+//! Export the virtual screen as an RGBA image, ANSI art, or a packed
+//! bitmap.
+//!
+//! Images are used for screenshots, thumbnails, and documentation
+//! imagery. Each Chip-8 pixel is drawn as a `scale` x `scale` block,
+//! optionally separated by one pixel wide grid lines.
+//!
+//! ANSI art prints the screen to any terminal with truecolor support,
+//! for debugging prints, headless tools, and pasting into bug reports.
+//!
+//! Packed bitmaps are a compact format for sending frames over the
+//! network, one bit per pixel.
+
+use std::fmt::Write;
+
+use crate::{
+    Oxid8, SCREEN_AREA, SCREEN_HEIGHT, SCREEN_WIDTH,
+    color::{GRID, Palette, Rgba},
+    filter::{Frame, colorize},
+};
+
+/// Size of a packed screen in bytes.
+pub const PACKED_SIZE: usize = SCREEN_AREA / 8;
+
+/// A row-major RGBA8 image.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RgbaImage {
+    width: usize,
+    height: usize,
+    pixels: Vec<u8>,
+}
+
+impl RgbaImage {
+    /// Create a new image filled with `color`.
+    pub fn new(width: usize, height: usize, color: Rgba) -> Self {
+        Self {
+            width,
+            height,
+            pixels: color.to_bytes().repeat(width * height),
+        }
+    }
+
+    /// Image width in pixels.
+    #[must_use]
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Image height in pixels.
+    #[must_use]
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Raw RGBA bytes, 4 per pixel, row-major.
+    #[must_use]
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.pixels
+    }
+
+    /// Consumes the image returning the raw RGBA bytes.
+    #[must_use]
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.pixels
+    }
+
+    /// Returns the color of the pixel at (x, y).
+    ///
+    /// # Panics
+    ///
+    /// `pixel` panics if (x, y) is out of bounds.
+    #[must_use]
+    pub fn pixel(&self, x: usize, y: usize) -> Rgba {
+        assert!(x < self.width && y < self.height, "pixel out of bounds");
+        let i = 4 * (x + y * self.width);
+        Rgba(
+            self.pixels[i],
+            self.pixels[i + 1],
+            self.pixels[i + 2],
+            self.pixels[i + 3],
+        )
+    }
+
+    /// Sets the color of the pixel at (x, y).
+    ///
+    /// # Panics
+    ///
+    /// `set_pixel` panics if (x, y) is out of bounds.
+    pub fn set_pixel(&mut self, x: usize, y: usize, color: Rgba) {
+        assert!(x < self.width && y < self.height, "pixel out of bounds");
+        let i = 4 * (x + y * self.width);
+        self.pixels[i..i + 4].copy_from_slice(&color.to_bytes());
+    }
+
+    /// Encodes the image as a PNG to `writer`, for screenshots.
+    ///
+    /// # Errors
+    ///
+    /// If writing fails.
+    #[cfg(feature = "png")]
+    pub fn write_png(&self, writer: impl std::io::Write) -> std::io::Result<()> {
+        let mut encoder = png::Encoder::new(writer, self.width as u32, self.height as u32);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder
+            .write_header()
+            .and_then(|mut writer| writer.write_image_data(&self.pixels))
+            .map_err(std::io::Error::other)
+    }
+}
+
+/// Renders `screen` scaled by an integer factor.
+///
+/// With `grid` enabled a one pixel wide line is drawn between every
+/// pair of neighboring Chip-8 pixels (but not around the border), so
+/// the output is `64 * scale + 63` by `32 * scale + 31` pixels.
+/// Without it the output is `64 * scale` by `32 * scale` pixels.
+///
+/// # Panics
+///
+/// `render_scaled` panics if `scale` is zero.
+#[must_use]
+pub fn render_scaled(screen: &[bool; SCREEN_AREA], scale: usize, grid: bool) -> RgbaImage {
+    render_colored(screen, scale, grid, Palette::default())
+}
+
+/// Renders `screen` like [`render_scaled`] in the colors of `palette`,
+/// e.g. for screenshots in the colors the game is played in.
+///
+/// # Panics
+///
+/// `render_colored` panics if `scale` is zero.
+#[must_use]
+pub fn render_colored(
+    screen: &[bool; SCREEN_AREA],
+    scale: usize,
+    grid: bool,
+    palette: Palette,
+) -> RgbaImage {
+    render_frame(&colorize(screen, palette), scale, grid)
+}
+
+/// Renders a [`Frame`] like [`render_scaled`], e.g. for screenshots
+/// with the filters the game is played with, see
+/// [`filter`](crate::filter).
+///
+/// # Panics
+///
+/// `render_frame` panics if `scale` is zero.
+#[must_use]
+pub fn render_frame(frame: &Frame, scale: usize, grid: bool) -> RgbaImage {
+    assert!(scale > 0, "scale must be at least 1");
+
+    let gap = grid as usize;
+    let step = scale + gap;
+    let width = SCREEN_WIDTH * step - gap;
+    let height = SCREEN_HEIGHT * step - gap;
+
+    // Behind the grid lines only.
+    let mut image = RgbaImage::new(width, height, GRID);
+
+    for y in 0..SCREEN_HEIGHT {
+        for x in 0..SCREEN_WIDTH {
+            let color = frame[x + y * SCREEN_WIDTH];
+            for dy in 0..scale {
+                for dx in 0..scale {
+                    image.set_pixel(x * step + dx, y * step + dy, color);
+                }
+            }
+        }
+    }
+
+    image
+}
+
+/// Renders `screen` as lines of `▀` half blocks colored with 24-bit
+/// ANSI escapes, two pixel rows per line, so it is 64 columns by 16
+/// lines.
+///
+/// Colors are only set when they change and reset at the end of every
+/// line, so the output can be printed or embedded anywhere.
+#[must_use]
+pub fn render_ansi(screen: &[bool; SCREEN_AREA], palette: Palette) -> String {
+    let color = |lit: bool| match lit {
+        true => palette.foreground,
+        false => palette.background,
+    };
+    let mut out = String::new();
+    for y in (0..SCREEN_HEIGHT).step_by(2) {
+        let mut last = None;
+        for x in 0..SCREEN_WIDTH {
+            let cell = (
+                color(screen[x + y * SCREEN_WIDTH]),
+                color(screen[x + (y + 1) * SCREEN_WIDTH]),
+            );
+            if last != Some(cell) {
+                let ([fr, fg, fb], [br, bg, bb]) = (cell.0.to_rgb(), cell.1.to_rgb());
+                // Writing to a String cannot fail.
+                let _ = write!(out, "\x1b[38;2;{fr};{fg};{fb};48;2;{br};{bg};{bb}m");
+                last = Some(cell);
+            }
+            out.push('▀');
+        }
+        out.push_str("\x1b[0m\n");
+    }
+    out
+}
+
+/// Packs the screen one bit per pixel.
+///
+/// Pixels are row-major, eight per byte with the leftmost pixel in the
+/// most significant bit, so each row is 8 bytes.
+#[must_use]
+pub fn pack_screen(screen: &[bool; SCREEN_AREA]) -> [u8; PACKED_SIZE] {
+    let mut packed = [0; PACKED_SIZE];
+    for (byte, pixels) in packed.iter_mut().zip(screen.chunks_exact(8)) {
+        *byte = pixels
+            .iter()
+            .fold(0, |byte, &pixel| byte << 1 | pixel as u8);
+    }
+    packed
+}
+
+/// Unpacks a screen packed by [`pack_screen`].
+#[must_use]
+pub fn unpack_screen(packed: &[u8; PACKED_SIZE]) -> [bool; SCREEN_AREA] {
+    let mut screen = [false; SCREEN_AREA];
+    for (i, pixel) in screen.iter_mut().enumerate() {
+        *pixel = packed[i / 8] & (0x80 >> (i % 8)) != 0;
+    }
+    screen
+}
+
+impl Oxid8 {
+    /// Renders the current screen scaled by an integer factor.
+    ///
+    /// See [`render_scaled`] for details.
+    ///
+    /// # Panics
+    ///
+    /// `render_scaled` panics if `scale` is zero.
+    #[must_use]
+    pub fn render_scaled(&self, scale: usize, grid: bool) -> RgbaImage {
+        render_scaled(self.screen_ref(), scale, grid)
+    }
+
+    /// Renders the current screen scaled in the colors of `palette`,
+    /// see [`render_colored`].
+    ///
+    /// # Panics
+    ///
+    /// `render_colored` panics if `scale` is zero.
+    #[must_use]
+    pub fn render_colored(&self, scale: usize, grid: bool, palette: Palette) -> RgbaImage {
+        render_colored(self.screen_ref(), scale, grid, palette)
+    }
+
+    /// Renders the current screen as ANSI art, see [`render_ansi`].
+    #[must_use]
+    pub fn render_ansi(&self, palette: Palette) -> String {
+        render_ansi(self.screen_ref(), palette)
+    }
+
+    /// Packs the current screen, see [`pack_screen`].
+    #[must_use]
+    pub fn pack_screen(&self) -> [u8; PACKED_SIZE] {
+        pack_screen(self.screen_ref())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn screen_with(pixels: &[(usize, usize)]) -> [bool; SCREEN_AREA] {
+        let mut screen = [false; SCREEN_AREA];
+        for &(x, y) in pixels {
+            screen[x + y * SCREEN_WIDTH] = true;
+        }
+        screen
+    }
+
+    #[test]
+    fn scale_one_no_grid() {
+        let screen = screen_with(&[(0, 0), (63, 31)]);
+        let image = render_scaled(&screen, 1, false);
+        assert_eq!(image.width(), SCREEN_WIDTH);
+        assert_eq!(image.height(), SCREEN_HEIGHT);
+        assert_eq!(image.as_bytes().len(), 4 * SCREEN_AREA);
+        assert_eq!(image.pixel(0, 0), Rgba::WHITE);
+        assert_eq!(image.pixel(1, 0), Rgba::BLACK);
+        assert_eq!(image.pixel(63, 31), Rgba::WHITE);
+    }
+
+    #[test]
+    fn scale_without_grid() {
+        let screen = screen_with(&[(1, 1)]);
+        let image = render_scaled(&screen, 4, false);
+        assert_eq!(image.width(), SCREEN_WIDTH * 4);
+        assert_eq!(image.height(), SCREEN_HEIGHT * 4);
+        for y in 0..12 {
+            for x in 0..12 {
+                let lit = (4..8).contains(&x) && (4..8).contains(&y);
+                let expected = if lit { Rgba::WHITE } else { Rgba::BLACK };
+                assert_eq!(image.pixel(x, y), expected, "at ({x}, {y})");
+            }
+        }
+    }
+
+    #[test]
+    fn scale_with_grid() {
+        let screen = screen_with(&[(1, 0), (63, 31)]);
+        let image = render_scaled(&screen, 3, true);
+        assert_eq!(image.width(), SCREEN_WIDTH * 4 - 1);
+        assert_eq!(image.height(), SCREEN_HEIGHT * 4 - 1);
+
+        // Pixel (0, 0) is unlit and occupies [0, 3).
+        assert_eq!(image.pixel(0, 0), Rgba::BLACK);
+        assert_eq!(image.pixel(2, 2), Rgba::BLACK);
+        // Grid lines between pixels.
+        assert_eq!(image.pixel(3, 0), GRID);
+        assert_eq!(image.pixel(0, 3), GRID);
+        assert_eq!(image.pixel(3, 3), GRID);
+        // Pixel (1, 0) is lit and occupies [4, 7).
+        assert_eq!(image.pixel(4, 0), Rgba::WHITE);
+        assert_eq!(image.pixel(6, 2), Rgba::WHITE);
+        assert_eq!(image.pixel(7, 0), GRID);
+        // Last pixel touches the border with no trailing grid line.
+        let (w, h) = (image.width(), image.height());
+        assert_eq!(image.pixel(w - 1, h - 1), Rgba::WHITE);
+        assert_eq!(image.pixel(w - 3, h - 3), Rgba::WHITE);
+        assert_eq!(image.pixel(w - 4, h - 1), GRID);
+    }
+
+    #[test]
+    fn render_from_core() {
+        let emu = Oxid8::new();
+        let image = emu.render_scaled(2, false);
+        assert!(
+            image
+                .as_bytes()
+                .chunks(4)
+                .all(|p| p == Rgba::BLACK.to_bytes())
+        );
+    }
+
+    #[test]
+    fn render_in_palette() {
+        let palette = crate::color::PRESETS[3].palette;
+        let image = render_colored(&screen_with(&[(0, 0)]), 2, true, palette);
+        assert_eq!(image.pixel(1, 1), palette.foreground);
+        assert_eq!(image.pixel(2, 0), GRID);
+        assert_eq!(image.pixel(3, 0), palette.background);
+    }
+
+    #[test]
+    #[should_panic(expected = "scale must be at least 1")]
+    fn zero_scale_panics() {
+        let _ = render_scaled(&[false; SCREEN_AREA], 0, false);
+    }
+
+    #[test]
+    fn ansi_half_blocks() {
+        let palette = Palette {
+            foreground: Rgba::rgb(1, 2, 3),
+            background: Rgba::rgb(4, 5, 6),
+        };
+        // Top half of the first cell, bottom half of the second.
+        let screen = screen_with(&[(0, 0), (1, 1)]);
+        let ansi = render_ansi(&screen, palette);
+        let lines: Vec<&str> = ansi.lines().collect();
+        assert_eq!(lines.len(), SCREEN_HEIGHT / 2);
+        assert!(lines[0].starts_with(
+            "\x1b[38;2;1;2;3;48;2;4;5;6m▀\x1b[38;2;4;5;6;48;2;1;2;3m▀\x1b[38;2;4;5;6;48;2;4;5;6m▀▀"
+        ));
+        for line in &lines {
+            assert_eq!(line.matches('▀').count(), SCREEN_WIDTH);
+            assert!(line.ends_with("\x1b[0m"));
+        }
+        // Unchanged colors are not repeated.
+        assert_eq!(lines[1].matches('\x1b').count(), 2);
+    }
+
+    #[test]
+    fn pack_roundtrip() {
+        let screen = screen_with(&[(0, 0), (7, 0), (8, 0), (63, 31)]);
+        let packed = pack_screen(&screen);
+        assert_eq!(packed[0], 0x81);
+        assert_eq!(packed[1], 0x80);
+        assert_eq!(packed[PACKED_SIZE - 1], 0x01);
+        assert_eq!(packed.iter().filter(|&&b| b != 0).count(), 3);
+        assert_eq!(unpack_screen(&packed), screen);
+    }
+
+    #[cfg(feature = "png")]
+    #[test]
+    fn png_header() {
+        let image = render_scaled(&screen_with(&[(0, 0)]), 2, false);
+        let mut bytes = Vec::new();
+        image.write_png(&mut bytes).unwrap();
+        assert_eq!(&bytes[..8], b"\x89PNG\r\n\x1a\n");
+        // Width and height in the IHDR chunk.
+        assert_eq!(&bytes[16..24], [0, 0, 0, 128, 0, 0, 0, 64]);
+    }
+}
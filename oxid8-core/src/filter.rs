@@ -0,0 +1,387 @@
+//! Post-processing the screen before it is shown.
+//!
+//! A [`FrameFilter`] changes the colors of a [`Frame`], the screen in
+//! the colors of a [`Palette`], and a [`FilterChain`] runs filters one
+//! after another once per emulated frame. Frontends draw the chain's
+//! frame in place of the screen, and exports render it with
+//! [`render_frame`](crate::export::render_frame), so every filter is
+//! written once for the terminal, wgpu, web, and exported images.
+//!
+//! The built-in filters are [`Ghosting`], pixels slow to change like an
+//! old LCD, [`FlickerBlend`], averaging each frame with the one before
+//! so sprites drawn every other frame stop flickering, and
+//! [`ColorGrade`]. Chains parse from comma separated filters, each
+//! `name` or `name=value`, as frontends take them with `--filter`:
+//!
+//! | Filter          | Value                                  |
+//! |-----------------|----------------------------------------|
+//! | `ghosting`      | Share of the last frame kept, 0.5      |
+//! | `flicker-blend` |                                        |
+//! | `brightness`    | Multiplier, 1 unchanged                |
+//! | `contrast`      | Multiplier, 1 unchanged                |
+//! | `saturation`    | Multiplier, 1 unchanged, 0 for grays   |
+//! | `grayscale`     |                                        |
+//!
+//! ```
+//! use oxid8_core::{SCREEN_AREA, color::{Palette, Rgba}, filter::FilterChain};
+//!
+//! let mut chain: FilterChain = "flicker-blend,grayscale".parse().unwrap();
+//! let palette = Palette::default();
+//! chain.process(&[true; SCREEN_AREA], palette);
+//! // Lit one frame, unlit the next, shown half lit.
+//! let frame = chain.process(&[false; SCREEN_AREA], palette);
+//! assert_eq!(frame[0], Rgba::rgb(127, 127, 127));
+//! ```
+
+use std::{fmt, str::FromStr};
+
+use crate::{
+    SCREEN_AREA,
+    color::{Palette, Rgba},
+};
+
+/// The screen in color, a row at a time.
+pub type Frame = [Rgba; SCREEN_AREA];
+
+/// `screen` in the colors of `palette`.
+#[must_use]
+pub fn colorize(screen: &[bool; SCREEN_AREA], palette: Palette) -> Box<Frame> {
+    let mut frame = Box::new([palette.background; SCREEN_AREA]);
+    for (color, &lit) in frame.iter_mut().zip(screen) {
+        if lit {
+            *color = palette.foreground;
+        }
+    }
+    frame
+}
+
+/// Changes the colors of every frame shown.
+pub trait FrameFilter: fmt::Debug + Send {
+    /// Filters the next frame in place.
+    fn apply(&mut self, frame: &mut Frame);
+
+    /// Forgets the frames before, e.g. when a game is loaded.
+    fn reset(&mut self) {}
+}
+
+/// Pixels keep part of the color they were shown in the last frame, so
+/// they fade in and out over a few frames.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Ghosting {
+    /// Share of the last frame's color kept, from 0 to 1.
+    pub keep: f32,
+    last: Option<Box<Frame>>,
+}
+
+impl Ghosting {
+    /// Keeps `keep` of the last frame, clamped between 0 and 1.
+    #[must_use]
+    pub fn new(keep: f32) -> Self {
+        Self {
+            keep: keep.clamp(0.0, 1.0),
+            last: None,
+        }
+    }
+}
+
+impl Default for Ghosting {
+    fn default() -> Self {
+        Self::new(0.5)
+    }
+}
+
+impl FrameFilter for Ghosting {
+    fn apply(&mut self, frame: &mut Frame) {
+        let level = (self.keep * 255.0).round() as u8;
+        if let Some(last) = &mut self.last {
+            for (color, last) in frame.iter_mut().zip(last.iter_mut()) {
+                *color = last.mix(*color, level);
+                *last = *color;
+            }
+        } else {
+            self.last = Some(Box::new(*frame));
+        }
+    }
+
+    fn reset(&mut self) {
+        self.last = None;
+    }
+}
+
+/// Shows each frame mixed evenly with the one before, so pixels drawn
+/// every other frame show steady at half brightness. Unlike
+/// [`Ghosting`] nothing trails for longer than a frame.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FlickerBlend {
+    /// The last frame, before blending.
+    last: Option<Box<Frame>>,
+}
+
+impl FrameFilter for FlickerBlend {
+    fn apply(&mut self, frame: &mut Frame) {
+        let Some(last) = &mut self.last else {
+            self.last = Some(Box::new(*frame));
+            return;
+        };
+        for (color, last) in frame.iter_mut().zip(last.iter_mut()) {
+            let shown = color.mix(*last, 128);
+            *last = *color;
+            *color = shown;
+        }
+    }
+
+    fn reset(&mut self) {
+        self.last = None;
+    }
+}
+
+/// Adjusts brightness, contrast, and saturation. Each is a multiplier,
+/// 1 leaves the colors as they are.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ColorGrade {
+    pub brightness: f32,
+    pub contrast: f32,
+    pub saturation: f32,
+}
+
+impl Default for ColorGrade {
+    fn default() -> Self {
+        Self {
+            brightness: 1.0,
+            contrast: 1.0,
+            saturation: 1.0,
+        }
+    }
+}
+
+impl ColorGrade {
+    /// Drops the colors, leaving shades of gray.
+    pub const GRAYSCALE: ColorGrade = ColorGrade {
+        brightness: 1.0,
+        contrast: 1.0,
+        saturation: 0.0,
+    };
+
+    /// `color` graded. Alpha is left as is.
+    #[must_use]
+    pub fn grade(&self, color: Rgba) -> Rgba {
+        let channels = color.to_rgb().map(|c| {
+            let c = f32::from(c) / 255.0;
+            ((c - 0.5) * self.contrast + 0.5) * self.brightness
+        });
+        // Rec. 601 luma, as for gray televisions.
+        let luma = 0.299 * channels[0] + 0.587 * channels[1] + 0.114 * channels[2];
+        let [r, g, b] = channels
+            .map(|c| ((luma + (c - luma) * self.saturation).clamp(0.0, 1.0) * 255.0).round() as u8);
+        Rgba(r, g, b, color.3)
+    }
+}
+
+impl FrameFilter for ColorGrade {
+    fn apply(&mut self, frame: &mut Frame) {
+        for color in frame.iter_mut() {
+            *color = self.grade(*color);
+        }
+    }
+}
+
+/// Filters run in order on every frame, see the [module docs](self).
+/// An empty chain shows the screen as is.
+#[derive(Debug)]
+pub struct FilterChain {
+    filters: Vec<Box<dyn FrameFilter>>,
+    /// The last frame processed.
+    frame: Box<Frame>,
+}
+
+impl Default for FilterChain {
+    fn default() -> Self {
+        Self {
+            filters: Vec::new(),
+            frame: colorize(&[false; SCREEN_AREA], Palette::default()),
+        }
+    }
+}
+
+impl FilterChain {
+    /// Runs `filter` after the ones before.
+    #[must_use]
+    pub fn with(mut self, filter: impl FrameFilter + 'static) -> Self {
+        self.filters.push(Box::new(filter));
+        self
+    }
+
+    /// Returns true if there are no filters.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.filters.is_empty()
+    }
+
+    /// Returns the number of filters.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.filters.len()
+    }
+
+    /// Colors `screen` in `palette` and runs every filter on it, once
+    /// per emulated frame.
+    pub fn process(&mut self, screen: &[bool; SCREEN_AREA], palette: Palette) -> &Frame {
+        // In place, frames should not allocate.
+        for (color, &lit) in self.frame.iter_mut().zip(screen) {
+            *color = if lit {
+                palette.foreground
+            } else {
+                palette.background
+            };
+        }
+        for filter in &mut self.filters {
+            filter.apply(&mut self.frame);
+        }
+        &self.frame
+    }
+
+    /// The last frame processed.
+    #[must_use]
+    pub fn frame(&self) -> &Frame {
+        &self.frame
+    }
+
+    /// Forgets the frames before in every filter.
+    pub fn reset(&mut self) {
+        for filter in &mut self.filters {
+            filter.reset();
+        }
+    }
+}
+
+impl FromStr for FilterChain {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut chain = FilterChain::default();
+        for part in s.split(',').map(str::trim).filter(|part| !part.is_empty()) {
+            let (name, value) = match part.split_once('=') {
+                Some((name, value)) => {
+                    let value = value
+                        .trim()
+                        .parse::<f32>()
+                        .ok()
+                        .filter(|value| value.is_finite() && *value >= 0.0)
+                        .ok_or_else(|| format!("Invalid filter value: {part}"))?;
+                    (name.trim(), Some(value))
+                }
+                None => (part, None),
+            };
+            let grade = |set: fn(&mut ColorGrade, f32)| match value {
+                Some(value) => {
+                    let mut grade = ColorGrade::default();
+                    set(&mut grade, value);
+                    Ok(grade)
+                }
+                None => Err(format!("Filter needs a value: {name}")),
+            };
+            chain = match name {
+                "ghosting" => chain.with(value.map_or_else(Ghosting::default, Ghosting::new)),
+                "flicker-blend" if value.is_none() => chain.with(FlickerBlend::default()),
+                "grayscale" if value.is_none() => chain.with(ColorGrade::GRAYSCALE),
+                "brightness" => chain.with(grade(|grade, value| grade.brightness = value)?),
+                "contrast" => chain.with(grade(|grade, value| grade.contrast = value)?),
+                "saturation" => chain.with(grade(|grade, value| grade.saturation = value)?),
+                "flicker-blend" | "grayscale" => {
+                    return Err(format!("Filter takes no value: {name}"));
+                }
+                _ => return Err(format!("Unknown filter: {name}")),
+            };
+        }
+        Ok(chain)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PALETTE: Palette = Palette {
+        foreground: Rgba::WHITE,
+        background: Rgba::BLACK,
+    };
+
+    #[test]
+    fn empty_chains_show_the_screen() {
+        let mut chain = FilterChain::default();
+        assert!(chain.is_empty());
+        let mut screen = [false; SCREEN_AREA];
+        screen[3] = true;
+        let frame = chain.process(&screen, PALETTE);
+        assert_eq!(frame[3], Rgba::WHITE);
+        assert_eq!(frame[4], Rgba::BLACK);
+        assert_eq!(*chain.frame(), *colorize(&screen, PALETTE));
+    }
+
+    #[test]
+    fn ghosting_fades() {
+        let mut chain = FilterChain::default().with(Ghosting::new(0.5));
+        chain.process(&[true; SCREEN_AREA], PALETTE);
+        let mut levels = Vec::new();
+        for _ in 0..3 {
+            levels.push(chain.process(&[false; SCREEN_AREA], PALETTE)[0].0);
+        }
+        assert_eq!(levels, [128, 64, 32]);
+
+        // Nothing to fade from after a reset.
+        chain.reset();
+        chain.process(&[false; SCREEN_AREA], PALETTE);
+        assert_eq!(chain.frame()[0], Rgba::BLACK);
+    }
+
+    #[test]
+    fn flicker_blend_averages_two_frames() {
+        let mut chain = FilterChain::default().with(FlickerBlend::default());
+        assert_eq!(chain.process(&[true; SCREEN_AREA], PALETTE)[0], Rgba::WHITE);
+        for frame in 1..4 {
+            let shown = chain.process(&[frame % 2 == 0; SCREEN_AREA], PALETTE)[0];
+            assert!((127..=128).contains(&shown.0), "{shown}");
+        }
+        // No trail once the screen settles.
+        chain.process(&[false; SCREEN_AREA], PALETTE);
+        assert_eq!(
+            chain.process(&[false; SCREEN_AREA], PALETTE)[0],
+            Rgba::BLACK
+        );
+    }
+
+    #[test]
+    fn grades() {
+        let red = Rgba(200, 0, 0, 7);
+        assert_eq!(ColorGrade::default().grade(red), red);
+        assert_eq!(ColorGrade::GRAYSCALE.grade(red), Rgba(60, 60, 60, 7));
+        let darker = ColorGrade {
+            brightness: 0.5,
+            ..ColorGrade::default()
+        };
+        assert_eq!(darker.grade(Rgba::WHITE), Rgba::rgb(128, 128, 128));
+        let flat = ColorGrade {
+            contrast: 0.0,
+            ..ColorGrade::default()
+        };
+        assert_eq!(flat.grade(Rgba::BLACK), flat.grade(Rgba::WHITE));
+    }
+
+    #[test]
+    fn parse() {
+        let chain: FilterChain = " ghosting=0.25, flicker-blend ,saturation=0,"
+            .parse()
+            .unwrap();
+        assert_eq!(chain.len(), 3);
+        assert!("".parse::<FilterChain>().unwrap().is_empty());
+        for (spec, err) in [
+            ("bloom", "Unknown filter: bloom"),
+            ("ghosting=lots", "Invalid filter value: ghosting=lots"),
+            ("contrast=-1", "Invalid filter value: contrast=-1"),
+            ("brightness", "Filter needs a value: brightness"),
+            ("grayscale=1", "Filter takes no value: grayscale"),
+        ] {
+            assert_eq!(spec.parse::<FilterChain>().unwrap_err(), err, "{spec}");
+        }
+    }
+}
@@ -0,0 +1,154 @@
+//! Damping rapid flashing, for players sensitive to it.
+//!
+//! Some games flash the whole screen on every frame, e.g. inverting it
+//! for an explosion or redrawing everything with `00E0` and `Dxyn`. A
+//! [`FlashFilter`] watches the screen after every frame and, once more
+//! than [`MAX_FLASHES`] frames in a second have flipped at least
+//! [`FLASH_PIXELS`] pixels, stops showing the screen as is. Instead
+//! each pixel fades a quarter of the way towards the game's screen
+//! every frame, so pixels flipping every frame settle on a steady gray.
+//! It shows the screen as is again after a second without flashing.
+//!
+//! Frontends opt in with `--reduce-flashes` and draw
+//! [`FlashFilter::levels`] in place of the screen, mixing their colors
+//! with [`Rgba::mix`](crate::color::Rgba::mix).
+//!
+//! ```
+//! use oxid8_core::{SCREEN_AREA, flash::FlashFilter};
+//!
+//! let mut filter = FlashFilter::default();
+//! for frame in 0..10 {
+//!     // The whole screen inverted every frame.
+//!     filter.frame(&[frame % 2 == 0; SCREEN_AREA]);
+//! }
+//! assert!(filter.damping());
+//! let level = filter.levels()[0];
+//! assert!((64..192).contains(&level));
+//! ```
+
+use crate::SCREEN_AREA;
+
+/// Pixels a frame has to flip to count as a flash, a quarter of the
+/// screen.
+pub const FLASH_PIXELS: usize = SCREEN_AREA / 4;
+
+/// Frames flashes are counted over, a second at 60Hz.
+pub const WINDOW: u32 = 60;
+
+/// Flashes in [`WINDOW`] frames allowed before damping starts.
+pub const MAX_FLASHES: u32 = 3;
+
+/// Turns the screen into brightness levels, damping flashes, see the
+/// [module docs](self).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FlashFilter {
+    /// The game's screen after the last frame.
+    last: [bool; SCREEN_AREA],
+    /// Brightness of each pixel shown.
+    levels: [u8; SCREEN_AREA],
+    /// Whether each of the last [`WINDOW`] frames flashed, newest in
+    /// the lowest bit.
+    flashes: u64,
+    damping: bool,
+}
+
+impl Default for FlashFilter {
+    fn default() -> Self {
+        Self {
+            last: [false; SCREEN_AREA],
+            levels: [0; SCREEN_AREA],
+            flashes: 0,
+            damping: false,
+        }
+    }
+}
+
+impl FlashFilter {
+    /// Takes the screen after a frame.
+    pub fn frame(&mut self, screen: &[bool; SCREEN_AREA]) {
+        let flipped = screen
+            .iter()
+            .zip(&self.last)
+            .filter(|(now, last)| now != last)
+            .count();
+        self.last = *screen;
+        self.flashes =
+            (self.flashes << 1 | u64::from(flipped >= FLASH_PIXELS)) & ((1 << WINDOW) - 1);
+        let flashes = self.flashes.count_ones();
+        if flashes > MAX_FLASHES {
+            self.damping = true;
+        } else if flashes == 0 {
+            self.damping = false;
+        }
+
+        for (level, &pixel) in self.levels.iter_mut().zip(screen) {
+            let target = if pixel { u8::MAX } else { 0 };
+            let step = (i16::from(target) - i16::from(*level)) / 4;
+            // Close enough, or not damping.
+            *level = match self.damping && step != 0 {
+                true => (i16::from(*level) + step) as u8,
+                false => target,
+            };
+        }
+    }
+
+    /// Returns true if flashes are being damped.
+    #[must_use]
+    pub fn damping(&self) -> bool {
+        self.damping
+    }
+
+    /// Brightness of each pixel to show, from 0 for unlit to 255 for
+    /// lit. Lit and unlit exactly unless damping.
+    #[must_use]
+    pub fn levels(&self) -> &[u8; SCREEN_AREA] {
+        &self.levels
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn steady_screens_pass_through() {
+        let mut filter = FlashFilter::default();
+        let mut screen = [false; SCREEN_AREA];
+        screen[5] = true;
+        for _ in 0..100 {
+            filter.frame(&screen);
+            // A sprite moving, few pixels flipped.
+            screen.rotate_right(1);
+        }
+        assert!(!filter.damping());
+        assert_eq!(filter.levels()[104], u8::MAX);
+        assert_eq!(filter.levels()[103], 0);
+    }
+
+    #[test]
+    fn damps_until_calm() {
+        let mut filter = FlashFilter::default();
+        let (lit, unlit) = ([true; SCREEN_AREA], [false; SCREEN_AREA]);
+        // Three flashes a second are allowed.
+        for frame in 0..WINDOW {
+            filter.frame(if frame % 40 < 20 { &lit } else { &unlit });
+        }
+        assert!(!filter.damping());
+
+        for frame in 0..WINDOW {
+            filter.frame(if frame % 2 == 0 { &lit } else { &unlit });
+        }
+        assert!(filter.damping());
+        assert!((64..192).contains(&filter.levels()[0]));
+
+        // Fades in while still damping.
+        filter.frame(&lit);
+        filter.frame(&lit);
+        assert!(filter.levels()[0] < u8::MAX);
+        for _ in 0..WINDOW {
+            filter.frame(&lit);
+        }
+        assert!(!filter.damping());
+        assert_eq!(filter.levels()[0], u8::MAX);
+    }
+}
@@ -0,0 +1,336 @@
+//! A flight recorder for reporting glitches after the fact.
+//!
+//! [`FlightRecorder`] keeps the input of the last frames in memory,
+//! with a savestate every [`SNAPSHOT_INTERVAL`] frames. When something
+//! odd happens, [`FlightRecorder::dump`] turns the oldest savestate and
+//! the input after it into a [`FlightDump`], which replays the last
+//! seconds exactly and can be written to disk for a bug report.
+//!
+//! Savestates don't keep the uniform rng, so the recorder reseeds it
+//! with a fresh seed at every snapshot and saves the seed alongside.
+//! Replay dumps with [`FlightDump::play_frame`], which reseeds at the
+//! same frames.
+//!
+//! Recording costs one savestate per snapshot and the key events of
+//! each frame, so it can stay on.
+//!
+//! ```
+//! use oxid8_core::{Oxid8, flight::FlightRecorder};
+//!
+//! let mut emu = Oxid8::new();
+//! // ADD V0, 1; JP 200
+//! emu.load_rom_bytes(&[0x70, 0x01, 0x12, 0x00]).unwrap();
+//!
+//! let mut recorder = FlightRecorder::new(120, 60);
+//! for _ in 0..300 {
+//!     recorder.push_frame(&mut emu, &[]);
+//!     emu.next_frame_with_input(&[]).unwrap();
+//! }
+//! let dump = recorder.dump().unwrap();
+//! assert_eq!(dump.movie.frames.len(), 120);
+//!
+//! // Replays to where the game is now.
+//! let mut copy = Oxid8::new();
+//! dump.prepare(&mut copy);
+//! for frame in 0..dump.movie.frames.len() {
+//!     dump.play_frame(&mut copy, frame).unwrap();
+//! }
+//! assert_eq!(copy.save_state(), emu.save_state());
+//! ```
+
+use std::{
+    collections::VecDeque,
+    fs, io,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::{Oxid8, input::InputEvent, movie::Movie, savestate::SaveState};
+
+/// Frames kept by default, ten seconds at 60Hz.
+pub const DEFAULT_FRAMES: usize = 600;
+
+/// Frames between savestates by default, a second at 60Hz.
+pub const SNAPSHOT_INTERVAL: usize = 60;
+
+/// A savestate and the frames run after it.
+#[derive(Debug, Clone)]
+struct Segment {
+    start: SaveState,
+    /// Input and settings of the frames, the seed the uniform rng was
+    /// reseeded with at `start`.
+    movie: Movie,
+}
+
+/// The input of the last frames run, see the [module docs](self).
+#[derive(Debug, Clone)]
+pub struct FlightRecorder {
+    segments: VecDeque<Segment>,
+    capacity: usize,
+    interval: usize,
+    /// Frames kept, across every segment.
+    frames: usize,
+}
+
+impl Default for FlightRecorder {
+    fn default() -> Self {
+        Self::new(DEFAULT_FRAMES, SNAPSHOT_INTERVAL)
+    }
+}
+
+impl FlightRecorder {
+    /// Keeps at least the last `capacity` frames, taking a savestate
+    /// every `interval` frames. Up to `interval` more frames are kept,
+    /// so a dump always starts at a savestate.
+    #[must_use]
+    pub fn new(capacity: usize, interval: usize) -> Self {
+        FlightRecorder {
+            segments: VecDeque::new(),
+            capacity: capacity.max(1),
+            interval: interval.max(1),
+            frames: 0,
+        }
+    }
+
+    /// Records the input of the frame `core` is about to run with
+    /// `next_frame_with_input`, taking a savestate first if one is due.
+    ///
+    /// Frames must run in full and in order, so clear the recorder
+    /// after changing the core's settings, restoring a savestate, or
+    /// stopping a frame early.
+    pub fn push_frame(&mut self, core: &mut Oxid8, events: &[InputEvent]) {
+        let due = self
+            .segments
+            .back()
+            .is_none_or(|segment| segment.movie.frames.len() >= self.interval);
+        if due {
+            let seed = rand::random();
+            core.rng.reseed(seed);
+            self.segments.push_back(Segment {
+                start: core.save_state(),
+                movie: Movie {
+                    rom_hash: core.rom_hash().map(str::to_string),
+                    seed,
+                    cycles_per_frame: core.cycles_per_frame(),
+                    timer_rate: core.timer_rate(),
+                    quirks: core.quirks(),
+                    cycle_weights: core.cycle_weights().clone(),
+                    skip_invalid: core.skip_invalid(),
+                    frames: Vec::with_capacity(self.interval),
+                },
+            });
+        }
+        if let Some(segment) = self.segments.back_mut() {
+            segment.movie.push_frame(events);
+        }
+        self.frames += 1;
+
+        // Drop whole segments once the rest still cover the capacity.
+        while let Some(front) = self.segments.front()
+            && self.frames - front.movie.frames.len() >= self.capacity
+        {
+            self.frames -= front.movie.frames.len();
+            self.segments.pop_front();
+        }
+    }
+
+    /// The oldest savestate and every frame recorded after it, or
+    /// `None` if nothing has been recorded.
+    #[must_use]
+    pub fn dump(&self) -> Option<FlightDump> {
+        let (first, rest) = (self.segments.front()?, self.segments.range(1..));
+        let mut movie = first.movie.clone();
+        let mut seeds = Vec::new();
+        for segment in rest {
+            seeds.push((movie.frames.len(), segment.movie.seed));
+            movie.frames.extend_from_slice(&segment.movie.frames);
+        }
+        Some(FlightDump {
+            start: first.start.clone(),
+            movie,
+            seeds,
+        })
+    }
+
+    /// Frames kept.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.frames
+    }
+
+    /// Returns true if no frames are kept.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.frames == 0
+    }
+
+    /// Forgets every frame, e.g. after changing the core's settings.
+    pub fn clear(&mut self) {
+        self.segments.clear();
+        self.frames = 0;
+    }
+}
+
+/// The last seconds recorded by a [`FlightRecorder`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FlightDump {
+    /// State before the first frame.
+    pub start: SaveState,
+    /// Input of every frame, with the settings and the uniform rng seed
+    /// at `start`.
+    pub movie: Movie,
+    /// Frames the uniform rng was reseeded before after the first, with
+    /// the seed.
+    pub seeds: Vec<(usize, u64)>,
+}
+
+impl FlightDump {
+    /// Puts `core` where the first frame starts, ready to play the
+    /// frames with [`FlightDump::play_frame`].
+    pub fn prepare(&self, core: &mut Oxid8) {
+        self.movie.prepare(core);
+        core.restore_state(&self.start);
+        core.rng.reseed(self.movie.seed);
+    }
+
+    /// Emulates frame `index` of `core` like [`Movie::play_frame`],
+    /// reseeding the uniform rng first where the recorder did.
+    ///
+    /// Returns false once the dump has ended.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error of `next_frame_with_input` if the rom is bad.
+    pub fn play_frame(&self, core: &mut Oxid8, index: usize) -> Result<bool, String> {
+        if let Ok(i) = self.seeds.binary_search_by_key(&index, |&(frame, _)| frame) {
+            core.rng.reseed(self.seeds[i].1);
+        }
+        self.movie.play_frame(core, index)
+    }
+
+    /// Writes `state.bin`, `input.movie`, and `seeds`, a line of frame
+    /// and seed for each reseed, to a new directory in `dir`, named
+    /// after the rom and the time, and returns its path.
+    ///
+    /// # Errors
+    ///
+    /// If the directory or a file in it can't be written.
+    pub fn write(&self, dir: impl AsRef<Path>) -> io::Result<PathBuf> {
+        let rom = self.movie.rom_hash.as_deref().unwrap_or("unknown");
+        let secs = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |time| time.as_secs());
+        let path = dir
+            .as_ref()
+            .join(format!("{}-{secs}", &rom[..rom.len().min(8)]));
+        fs::create_dir_all(&path)?;
+        fs::write(path.join("state.bin"), self.start.to_bytes())?;
+        self.movie.save(path.join("input.movie"))?;
+        let seeds: String = self
+            .seeds
+            .iter()
+            .map(|(frame, seed)| format!("{frame} {seed}\n"))
+            .collect();
+        fs::write(path.join("seeds"), seeds)?;
+        Ok(path)
+    }
+
+    /// Reads a dump written by [`FlightDump::write`] from `dir`.
+    ///
+    /// # Errors
+    ///
+    /// If a file can't be read or isn't what it should be.
+    pub fn load(dir: impl AsRef<Path>) -> io::Result<Self> {
+        let dir = dir.as_ref();
+        let start = SaveState::from_bytes(&fs::read(dir.join("state.bin"))?)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        let movie = Movie::load(dir.join("input.movie"))?;
+        let seeds = fs::read_to_string(dir.join("seeds"))?
+            .lines()
+            .map(|line| {
+                let (frame, seed) = line.split_once(' ')?;
+                Some((frame.parse().ok()?, seed.parse().ok()?))
+            })
+            .collect::<Option<_>>()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "Invalid seeds"))?;
+        Ok(FlightDump {
+            start,
+            movie,
+            seeds,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// RND V0, FF; ADD V1, V0; SKP V2; JP 200; ADD V3, 1; JP 200
+    const ROM: [u8; 12] = [
+        0xC0, 0xFF, 0x81, 0x04, 0xE2, 0x9E, 0x12, 0x00, 0x73, 0x01, 0x12, 0x00,
+    ];
+
+    fn core() -> Oxid8 {
+        let mut emu = Oxid8::new();
+        emu.load_rom_bytes(&ROM).unwrap();
+        emu
+    }
+
+    /// Key 2 goes down and up every few frames.
+    fn events(frame: usize) -> Vec<InputEvent> {
+        match frame % 7 {
+            0 | 3 => vec![InputEvent {
+                cycle: 4,
+                key: 2,
+                pressed: frame.is_multiple_of(7),
+            }],
+            _ => Vec::new(),
+        }
+    }
+
+    #[test]
+    fn keeps_whole_segments() {
+        let mut emu = core();
+        let mut recorder = FlightRecorder::new(25, 10);
+        assert!(recorder.dump().is_none());
+        for frame in 0..47 {
+            recorder.push_frame(&mut emu, &events(frame));
+            emu.next_frame_with_input(&events(frame)).unwrap();
+        }
+        // Segments from frames 20, 30, and 40.
+        assert_eq!(recorder.len(), 27);
+        assert_eq!(recorder.dump().unwrap().movie.frames[0], events(20));
+
+        recorder.clear();
+        assert!(recorder.is_empty());
+        assert!(recorder.dump().is_none());
+    }
+
+    #[test]
+    fn written_dump_replays() {
+        let mut emu = core();
+        let mut recorder = FlightRecorder::new(30, 8);
+        for frame in 0..100 {
+            recorder.push_frame(&mut emu, &events(frame));
+            emu.next_frame_with_input(&events(frame)).unwrap();
+        }
+
+        let dir = std::env::temp_dir().join(format!("oxid8-flight-{}", std::process::id()));
+        let path = recorder.dump().unwrap().write(&dir).unwrap();
+        let dump = FlightDump::load(&path).unwrap();
+        fs::remove_dir_all(dir).unwrap();
+        let expected = recorder.dump().unwrap();
+        assert_eq!(dump.start.to_bytes(), expected.start.to_bytes());
+        assert_eq!(
+            (&dump.movie, &dump.seeds),
+            (&expected.movie, &expected.seeds)
+        );
+
+        let mut copy = core();
+        dump.prepare(&mut copy);
+        for frame in 0..dump.movie.frames.len() {
+            assert!(dump.play_frame(&mut copy, frame).unwrap());
+        }
+        assert_eq!(copy.save_state().to_bytes(), emu.save_state().to_bytes());
+    }
+}
@@ -0,0 +1,344 @@
+//! Capabilities frontends declare so shared code can adapt to them.
+//!
+//! Terminals without the kitty keyboard protocol never report key
+//! releases, some outputs can't present a frame every tick, and not
+//! every frontend plays sound. A frontend describes itself with
+//! [`Capabilities`] instead of each one working around its gaps on its
+//! own, and the async driver adapts to them.
+//!
+//! Without key releases, keys are released by [`KeyTimeout`] once they
+//! haven't been pressed again for [`KEY_TIMEOUT`] frames. OS auto-repeat
+//! keeps a held key pressed.
+//!
+//! For players who can't hold keys down or tap them quickly,
+//! [`KeyAssist`] sits between the keyboard and the keypad: with sticky
+//! keys a press toggles a key on or off, and a minimum hold keeps keys
+//! down for a few frames however briefly they were tapped.
+//!
+//! ```
+//! use oxid8_core::{Oxid8, frontend::{Capabilities, KeyTimeout}};
+//!
+//! let capabilities = Capabilities {
+//!     key_release: false,
+//!     ..Capabilities::default()
+//! };
+//! let mut emu = Oxid8::new();
+//! let mut timeout = KeyTimeout::default();
+//!
+//! // On a key press:
+//! timeout.press(&mut emu, 0x5);
+//!
+//! // After every frame:
+//! if !capabilities.key_release {
+//!     timeout.tick(&mut emu);
+//! }
+//! ```
+
+use crate::{NUM_KEYS, Oxid8};
+
+/// Frames a key stays held after its last press without key releases,
+/// 600ms at 60Hz.
+///
+/// Auto-repeat starts 250 to 600ms after a key goes down on most
+/// systems, so a shorter timeout would release a held key before its
+/// first repeat arrives. The same delay as
+/// [`Debounce::INTERVAL`](crate::hotkeys::Debounce::INTERVAL), at the
+/// cost of a tap holding the key as long.
+pub const KEY_TIMEOUT: u8 = 36;
+
+/// What a frontend supports.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Capabilities {
+    /// Key releases are reported. Without them keys are released by a
+    /// [`KeyTimeout`].
+    pub key_release: bool,
+    /// Sound is played. Frames that start or stop the beep are always
+    /// presented.
+    pub audio: bool,
+    /// Most frames per second the frontend presents, `None` for every
+    /// frame. Frames over the rate are still emulated.
+    pub max_frame_rate: Option<u32>,
+}
+
+/// Key releases, no audio, and every frame.
+impl Default for Capabilities {
+    fn default() -> Self {
+        Self {
+            key_release: true,
+            audio: false,
+            max_frame_rate: None,
+        }
+    }
+}
+
+impl Capabilities {
+    /// Returns true if frame `n`, counting from 0, of frames emulated at
+    /// `hz` should be presented under `max_frame_rate`.
+    ///
+    /// Presented frames are spread evenly, e.g. every other frame for 30
+    /// of 60.
+    #[must_use]
+    pub fn presents(&self, n: u64, hz: u32) -> bool {
+        match self.max_frame_rate {
+            Some(max) if max < hz => {
+                let (max, hz) = (u64::from(max), u64::from(hz));
+                (n + 1) * max / hz > n * max / hz
+            }
+            _ => true,
+        }
+    }
+}
+
+/// Accessibility settings for the keypad, see [`KeyAssist`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Assist {
+    /// Presses toggle keys on and off, releases are ignored.
+    pub sticky: bool,
+    /// Frames a key stays down at least after a press, 0 for none.
+    /// Ignored with sticky keys.
+    pub min_hold: u8,
+}
+
+/// Applies [`Assist`] settings to key presses and releases.
+///
+/// Frontends pass presses and releases through it and apply what it
+/// returns to the keypad, as keys or timestamped input events. OS
+/// auto-repeat should not be passed on, with sticky keys it would
+/// toggle the key again.
+///
+/// ```
+/// use oxid8_core::frontend::{Assist, KeyAssist};
+///
+/// let mut assist = KeyAssist::new(Assist {
+///     min_hold: 2,
+///     ..Assist::default()
+/// });
+/// assert_eq!(assist.press(0x5), Some(true));
+/// // A quick tap stays down for two frames.
+/// assert_eq!(assist.release(0x5), None);
+/// assert!(assist.tick().is_empty());
+/// assert_eq!(assist.tick(), [0x5]);
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct KeyAssist {
+    assist: Assist,
+    /// Keys down, one bit per key.
+    down: u16,
+    /// Keys released while their minimum hold runs.
+    pending: u16,
+    /// Frames left of each key's minimum hold.
+    remaining: [u8; NUM_KEYS],
+}
+
+impl KeyAssist {
+    /// Assists with `assist`, starting with no keys down.
+    #[must_use]
+    pub fn new(assist: Assist) -> Self {
+        Self {
+            assist,
+            ..Self::default()
+        }
+    }
+
+    /// The settings in use.
+    #[must_use]
+    pub fn assist(&self) -> Assist {
+        self.assist
+    }
+
+    /// Handles a press of `k`, returning whether it should be down now.
+    pub fn press(&mut self, k: usize) -> Option<bool> {
+        let bit = 1 << k;
+        if self.assist.sticky {
+            self.down ^= bit;
+            return Some(self.down & bit != 0);
+        }
+        self.down |= bit;
+        self.pending &= !bit;
+        self.remaining[k] = self.assist.min_hold;
+        Some(true)
+    }
+
+    /// Handles a release of `k`, returning `Some(false)` if it should
+    /// be released now, or `None` to leave it down.
+    pub fn release(&mut self, k: usize) -> Option<bool> {
+        let bit = 1 << k;
+        if self.assist.sticky {
+            return None;
+        }
+        if self.remaining[k] > 0 {
+            self.pending |= bit;
+            return None;
+        }
+        self.down &= !bit;
+        Some(false)
+    }
+
+    /// Counts down a frame, returning the keys to release now that
+    /// their minimum hold is over.
+    pub fn tick(&mut self) -> Vec<usize> {
+        let mut released = Vec::new();
+        for (k, remaining) in self.remaining.iter_mut().enumerate() {
+            if *remaining == 0 {
+                continue;
+            }
+            *remaining -= 1;
+            let bit = 1 << k;
+            if *remaining == 0 && self.pending & bit != 0 {
+                self.pending &= !bit;
+                self.down &= !bit;
+                released.push(k);
+            }
+        }
+        released
+    }
+}
+
+/// Releases keys that haven't been pressed for [`KEY_TIMEOUT`] frames,
+/// for frontends without key releases.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyTimeout {
+    /// Frames left until each key is released, 0 if not held.
+    remaining: [u8; NUM_KEYS],
+    timeout: u8,
+}
+
+impl Default for KeyTimeout {
+    fn default() -> Self {
+        Self::new(KEY_TIMEOUT)
+    }
+}
+
+impl KeyTimeout {
+    /// New timeout releasing keys after `frames` frames.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `frames` is 0.
+    #[must_use]
+    pub fn new(frames: u8) -> Self {
+        assert!(frames > 0, "Key timeout must be at least one frame");
+        Self {
+            remaining: [0; NUM_KEYS],
+            timeout: frames,
+        }
+    }
+
+    /// Presses `k` on `core`, or keeps it held if it already is.
+    ///
+    /// # Panics
+    ///
+    /// See [`Oxid8::set_key`].
+    pub fn press(&mut self, core: &mut Oxid8, k: usize) {
+        core.set_key(k, true);
+        self.remaining[k] = self.timeout;
+    }
+
+    /// Releases `k` on `core` now, for frontends that report some
+    /// releases.
+    ///
+    /// # Panics
+    ///
+    /// See [`Oxid8::set_key`].
+    pub fn release(&mut self, core: &mut Oxid8, k: usize) {
+        core.set_key(k, false);
+        self.remaining[k] = 0;
+    }
+
+    /// Counts down a frame, releasing keys that timed out.
+    pub fn tick(&mut self, core: &mut Oxid8) {
+        for (k, remaining) in self.remaining.iter_mut().enumerate() {
+            if *remaining > 0 {
+                *remaining -= 1;
+                if *remaining == 0 {
+                    core.set_key(k, false);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn key_timeout() {
+        let mut emu = Oxid8::new();
+        let mut timeout = KeyTimeout::new(2);
+
+        timeout.press(&mut emu, 0x5);
+        timeout.tick(&mut emu);
+        assert!(emu.keys[0x5]);
+        // Auto-repeat keeps the key held.
+        timeout.press(&mut emu, 0x5);
+        timeout.tick(&mut emu);
+        assert!(emu.keys[0x5]);
+        timeout.tick(&mut emu);
+        assert!(!emu.keys[0x5]);
+
+        timeout.press(&mut emu, 0xA);
+        timeout.release(&mut emu, 0xA);
+        assert!(!emu.keys[0xA]);
+    }
+
+    #[test]
+    fn sticky_keys() {
+        let mut assist = KeyAssist::new(Assist {
+            sticky: true,
+            min_hold: 3,
+        });
+        assert_eq!(assist.press(0x5), Some(true));
+        assert_eq!(assist.release(0x5), None);
+        assert!(assist.tick().is_empty());
+        assert_eq!(assist.press(0x5), Some(false));
+        assert_eq!(assist.press(0xA), Some(true));
+        assert_eq!(assist.press(0x5), Some(true));
+    }
+
+    #[test]
+    fn min_hold() {
+        let mut assist = KeyAssist::new(Assist {
+            sticky: false,
+            min_hold: 2,
+        });
+        // Held past the minimum, released right away.
+        assist.press(0x1);
+        assert!(assist.tick().is_empty());
+        assert!(assist.tick().is_empty());
+        assert_eq!(assist.release(0x1), Some(false));
+
+        // Pressed again during the hold, the earlier release is void.
+        assist.press(0x2);
+        assert_eq!(assist.release(0x2), None);
+        assist.press(0x2);
+        assert!(assist.tick().is_empty());
+        assert!(assist.tick().is_empty());
+        assert_eq!(assist.release(0x2), Some(false));
+
+        // No assistance by default.
+        let mut assist = KeyAssist::default();
+        assist.press(0x3);
+        assert_eq!(assist.release(0x3), Some(false));
+    }
+
+    #[test]
+    fn presents() {
+        let every = Capabilities::default();
+        assert!((0..60).all(|n| every.presents(n, 60)));
+
+        let half = Capabilities {
+            max_frame_rate: Some(30),
+            ..Capabilities::default()
+        };
+        let presented: Vec<u64> = (0..6).filter(|&n| half.presents(n, 60)).collect();
+        assert_eq!(presented, [1, 3, 5]);
+        assert_eq!((0..60).filter(|&n| half.presents(n, 60)).count(), 30);
+
+        let faster = Capabilities {
+            max_frame_rate: Some(144),
+            ..Capabilities::default()
+        };
+        assert!((0..50).all(|n| faster.presents(n, 50)));
+    }
+}
@@ -0,0 +1,220 @@
+//! Goals met by reaching conditions on the machine state.
+//!
+//! A [`Goal`] is a [`Watch`] condition with a name, met the first time
+//! it evaluates to nonzero at the end of a frame, e.g. a score kept in
+//! RAM reaching ten. With the `config` feature they are read from a
+//! small TOML file per ROM, mapping conditions to names in the order
+//! they are checked:
+//!
+//! ```toml
+//! # <dir>/<sha1>.goals.toml
+//! "ram[0x3A0] >= 10" = "Score 10 in Brix"
+//! "ram[0x3A1] == 0 && V0 > 3" = "Clear a row"
+//! ```
+//!
+//! [`Goals::check`] returns the goals met since the last check, for
+//! frontends to announce, e.g. with the [OSD](crate::osd).
+//!
+//! ```
+//! use oxid8_core::{Oxid8, goals::{Goal, Goals}};
+//!
+//! let mut emu = Oxid8::new();
+//! // ADD V0, 1; JP 200
+//! emu.load_rom_bytes(&[0x70, 0x01, 0x12, 0x00]).unwrap();
+//!
+//! let mut goals = Goals::new(vec![Goal {
+//!     condition: "V0 >= 20".parse().unwrap(),
+//!     name: "Count to 20".into(),
+//! }]);
+//! emu.next_frame().unwrap();
+//! assert!(goals.check(&emu).is_empty());
+//! for _ in 0..3 {
+//!     emu.next_frame().unwrap();
+//! }
+//! assert_eq!(goals.check(&emu)[0].name, "Count to 20");
+//! // Goals are only met once.
+//! assert!(goals.check(&emu).is_empty());
+//! assert_eq!(goals.met(), 1);
+//! ```
+
+use std::fmt;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Deserializer, de};
+
+use crate::{Oxid8, watch::Watch};
+
+/// A condition to reach and its name.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Goal {
+    /// Met once it isn't zero.
+    pub condition: Watch,
+    pub name: String,
+}
+
+/// Formatted like "Score 10 in Brix (ram[0x3A0] >= 10)".
+impl fmt::Display for Goal {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} ({})", self.name, self.condition)
+    }
+}
+
+/// Goals for a game and which have been met, see the
+/// [module docs](self).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Goals {
+    goals: Vec<Goal>,
+    met: Vec<bool>,
+}
+
+impl Goals {
+    /// None of `goals` met yet.
+    #[must_use]
+    pub fn new(goals: Vec<Goal>) -> Self {
+        let met = vec![false; goals.len()];
+        Goals { goals, met }
+    }
+
+    /// Every goal, in the order they are checked.
+    #[must_use]
+    pub fn goals(&self) -> &[Goal] {
+        &self.goals
+    }
+
+    /// Returns true if goal `index` has been met.
+    #[must_use]
+    pub fn is_met(&self, index: usize) -> bool {
+        self.met.get(index).copied().unwrap_or(false)
+    }
+
+    /// Number of goals met.
+    #[must_use]
+    pub fn met(&self) -> usize {
+        self.met.iter().filter(|&&met| met).count()
+    }
+
+    /// Number of goals.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.goals.len()
+    }
+
+    /// Returns true if there are no goals.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.goals.is_empty()
+    }
+
+    /// Checks the goals not met yet against `core`, call once per
+    /// frame. Returns the goals met now, in order.
+    ///
+    /// A condition that fails to evaluate, e.g. dividing by zero, is
+    /// not met.
+    pub fn check(&mut self, core: &Oxid8) -> Vec<&Goal> {
+        let mut met = Vec::new();
+        for (goal, done) in self.goals.iter().zip(&mut self.met) {
+            if !*done && goal.condition.is_true(core) == Ok(true) {
+                *done = true;
+                met.push(goal);
+            }
+        }
+        met
+    }
+
+    /// Forgets which goals have been met, e.g. when the game restarts.
+    pub fn reset(&mut self) {
+        self.met.fill(false);
+    }
+
+    /// Parses goals from TOML, see the [module docs](self).
+    ///
+    /// # Errors
+    ///
+    /// Returns an error message if the TOML is malformed, a name isn't
+    /// a string, or a condition is an invalid watch expression.
+    #[cfg(feature = "config")]
+    pub fn from_toml(s: &str) -> Result<Self, String> {
+        toml::from_str(s).map_err(|err| err.to_string())
+    }
+}
+
+/// Deserialized from a map of conditions to names, keeping their order.
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for Goals {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct Visitor;
+
+        impl<'de> de::Visitor<'de> for Visitor {
+            type Value = Goals;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a map of conditions to goal names")
+            }
+
+            fn visit_map<A: de::MapAccess<'de>>(self, mut map: A) -> Result<Goals, A::Error> {
+                let mut goals = Vec::new();
+                while let Some((condition, name)) = map.next_entry::<String, String>()? {
+                    let condition = condition
+                        .parse()
+                        .map_err(|err| de::Error::custom(format!("{condition}: {err}")))?;
+                    goals.push(Goal { condition, name });
+                }
+                Ok(Goals::new(goals))
+            }
+        }
+
+        deserializer.deserialize_map(Visitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn goal(condition: &str, name: &str) -> Goal {
+        Goal {
+            condition: condition.parse().unwrap(),
+            name: name.into(),
+        }
+    }
+
+    #[test]
+    fn met_once_in_order() {
+        let mut emu = Oxid8::new();
+        let mut goals = Goals::new(vec![
+            goal("V1 == 1", "one"),
+            goal("V1 / V2", "divide"),
+            goal("V1 >= 1", "at least one"),
+        ]);
+        assert!(goals.check(&emu).is_empty());
+
+        emu.v_reg[1] = 1;
+        let met: Vec<&str> = goals.check(&emu).iter().map(|g| g.name.as_str()).collect();
+        assert_eq!(met, ["one", "at least one"]);
+        assert!(goals.is_met(2) && !goals.is_met(1));
+
+        emu.v_reg[2] = 1;
+        assert_eq!(goals.check(&emu), [&goal("V1 / V2", "divide")]);
+        assert_eq!(goals.met(), goals.len());
+
+        goals.reset();
+        assert_eq!(goals.met(), 0);
+        assert_eq!(goals.goals()[0].to_string(), "one (V1 == 1)");
+    }
+
+    #[cfg(feature = "config")]
+    #[test]
+    fn toml_keeps_order() {
+        let goals = Goals::from_toml(
+            "\"V2 > 1\" = \"second\"\n\"ram[0x3A0] >= 10\" = \"Score 10\"\n\"V0\" = \"first\"\n",
+        )
+        .unwrap();
+        let names: Vec<&str> = goals.goals().iter().map(|g| g.name.as_str()).collect();
+        assert_eq!(names, ["second", "Score 10", "first"]);
+        assert_eq!(goals.goals()[1].condition.source(), "ram[0x3A0] >= 10");
+
+        assert!(Goals::from_toml("\"V3 +\" = \"bad\"").is_err());
+        assert!(Goals::from_toml("\"V3\" = 1").is_err());
+        assert!(Goals::from_toml("").unwrap().is_empty());
+    }
+}
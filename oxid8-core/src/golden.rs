@@ -0,0 +1,452 @@
+//! Golden traces for comparing against reference interpreters.
+//!
+//! A trace lists the machine state before every executed instruction.
+//! Import one logged by an established emulator, run the same ROM in
+//! lockstep with [`Oxid8::compare_trace`], and the first instruction
+//! where the two disagree points at the quirk or opcode to look at.
+//!
+//! # Schema
+//!
+//! Traces are CSV. The first line names the columns, and every line
+//! after it is one instruction. Values are hex, with or without `0x`.
+//! Blank lines and lines starting with `#` are ignored.
+//!
+//! | Column       | Value                                 |
+//! |--------------|---------------------------------------|
+//! | `pc`         | Address of the instruction            |
+//! | `opcode`     | The instruction at `pc`               |
+//! | `i`          | I register                            |
+//! | `sp`         | Stack depth                           |
+//! | `v0` to `vf` | V registers                           |
+//! | `dt`, `st`   | Delay and sound timers                |
+//!
+//! Any subset of columns in any order is fine, only the columns in the
+//! trace are compared. Timers count down once every
+//! [`Oxid8::cycles_per_frame`] instructions, so leave them out for
+//! emulators that time frames differently.
+//!
+//! ```
+//! use oxid8_core::{Oxid8, golden::Trace};
+//!
+//! let trace = Trace::parse_csv("pc,opcode,v0\n200,602a,00\n202,1202,2a\n").unwrap();
+//!
+//! let mut emu = Oxid8::new();
+//! emu.load_rom_bytes(&[0x60, 0x2A, 0x12, 0x02]).unwrap(); // LD V0, 2A; JP 202
+//! assert_eq!(emu.compare_trace(&trace), Ok(None));
+//! ```
+
+use std::fmt;
+
+use crate::Oxid8;
+
+/// A column of a [`Trace`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Field {
+    Pc,
+    Opcode,
+    I,
+    Sp,
+    /// Vx
+    V(u8),
+    Dt,
+    St,
+}
+
+impl Field {
+    /// Every field, in the order [`Trace::record`] writes them.
+    pub const ALL: [Field; 22] = [
+        Field::Pc,
+        Field::Opcode,
+        Field::I,
+        Field::Sp,
+        Field::V(0x0),
+        Field::V(0x1),
+        Field::V(0x2),
+        Field::V(0x3),
+        Field::V(0x4),
+        Field::V(0x5),
+        Field::V(0x6),
+        Field::V(0x7),
+        Field::V(0x8),
+        Field::V(0x9),
+        Field::V(0xA),
+        Field::V(0xB),
+        Field::V(0xC),
+        Field::V(0xD),
+        Field::V(0xE),
+        Field::V(0xF),
+        Field::Dt,
+        Field::St,
+    ];
+
+    /// The value of this field in `core`.
+    #[must_use]
+    pub fn read(self, core: &Oxid8) -> u16 {
+        match self {
+            Field::Pc => core.pc.get(),
+            Field::Opcode => {
+                let pc = core.pc.index();
+                let byte = |addr: usize| core.ram.get(addr).copied().unwrap_or(0) as u16;
+                (byte(pc) << 8) | byte(pc + 1)
+            }
+            Field::I => core.i_reg.get(),
+            Field::Sp => core.sp,
+            Field::V(x) => core.v_reg[x as usize] as u16,
+            Field::Dt => core.dt as u16,
+            Field::St => core.st as u16,
+        }
+    }
+
+    pub(crate) fn parse(name: &str) -> Option<Self> {
+        let name = name.trim().to_ascii_lowercase();
+        Some(match name.as_str() {
+            "pc" => Field::Pc,
+            "opcode" => Field::Opcode,
+            "i" => Field::I,
+            "sp" => Field::Sp,
+            "dt" => Field::Dt,
+            "st" => Field::St,
+            _ => {
+                let x = name.strip_prefix('v')?;
+                if x.len() != 1 {
+                    return None;
+                }
+                Field::V(u8::from_str_radix(x, 16).ok()?)
+            }
+        })
+    }
+}
+
+/// Formatted as the column name, e.g. "pc" or "va".
+impl fmt::Display for Field {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Field::Pc => write!(f, "pc"),
+            Field::Opcode => write!(f, "opcode"),
+            Field::I => write!(f, "i"),
+            Field::Sp => write!(f, "sp"),
+            Field::V(x) => write!(f, "v{x:x}"),
+            Field::Dt => write!(f, "dt"),
+            Field::St => write!(f, "st"),
+        }
+    }
+}
+
+/// The state before each instruction of a run, see the module docs.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Trace {
+    fields: Vec<Field>,
+    /// Values of `fields` for each instruction.
+    rows: Vec<Vec<u16>>,
+    /// Line of each row in the source, for error messages.
+    lines: Vec<usize>,
+}
+
+impl Trace {
+    /// Parses a CSV trace.
+    ///
+    /// # Errors
+    ///
+    /// Returns "line N: message" for an unknown or repeated column, a
+    /// row with the wrong number of values, or a value that isn't hex.
+    pub fn parse_csv(s: &str) -> Result<Self, String> {
+        let mut lines = s
+            .lines()
+            .enumerate()
+            .map(|(i, line)| (i + 1, line.trim()))
+            .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'));
+
+        let (n, header) = lines.next().ok_or("Empty trace")?;
+        let mut fields = Vec::new();
+        for name in header.split(',') {
+            let field = Field::parse(name)
+                .ok_or_else(|| format!("line {n}: Unknown column: {}", name.trim()))?;
+            if fields.contains(&field) {
+                return Err(format!("line {n}: Repeated column: {field}"));
+            }
+            fields.push(field);
+        }
+
+        let mut trace = Trace {
+            fields,
+            ..Trace::default()
+        };
+        for (n, line) in lines {
+            let row = line
+                .split(',')
+                .map(|value| {
+                    let value = value.trim();
+                    let hex = value.trim_start_matches("0x").trim_start_matches("0X");
+                    u16::from_str_radix(hex, 16)
+                        .map_err(|_| format!("line {n}: Invalid value: {value}"))
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            if row.len() != trace.fields.len() {
+                return Err(format!(
+                    "line {n}: Expected {} values, got {}",
+                    trace.fields.len(),
+                    row.len()
+                ));
+            }
+            trace.rows.push(row);
+            trace.lines.push(n);
+        }
+        Ok(trace)
+    }
+
+    /// Runs `core` for `steps` instructions, recording `fields` before
+    /// each one. Stops early on an invalid instruction.
+    #[must_use]
+    pub fn record(core: &mut Oxid8, fields: &[Field], steps: usize) -> Self {
+        let mut trace = Trace {
+            fields: fields.to_vec(),
+            ..Trace::default()
+        };
+        let mut lockstep = Lockstep::default();
+        for step in 0..steps {
+            trace
+                .rows
+                .push(fields.iter().map(|field| field.read(core)).collect());
+            trace.lines.push(step + 2);
+            if lockstep.step(core).is_err() {
+                break;
+            }
+        }
+        trace
+    }
+
+    /// A trace of rows already read, e.g. kept in a ring buffer.
+    pub(crate) fn from_rows(fields: &[Field], rows: Vec<Vec<u16>>) -> Self {
+        Trace {
+            fields: fields.to_vec(),
+            lines: (2..rows.len() + 2).collect(),
+            rows,
+        }
+    }
+
+    /// Writes the trace as CSV.
+    #[must_use]
+    pub fn to_csv(&self) -> String {
+        let mut csv = self
+            .fields
+            .iter()
+            .map(Field::to_string)
+            .collect::<Vec<_>>()
+            .join(",");
+        csv.push('\n');
+        for row in &self.rows {
+            let values: Vec<String> = self
+                .fields
+                .iter()
+                .zip(row)
+                .map(|(field, value)| match field {
+                    Field::Pc | Field::Opcode | Field::I => format!("{value:04x}"),
+                    _ => format!("{value:02x}"),
+                })
+                .collect();
+            csv.push_str(&values.join(","));
+            csv.push('\n');
+        }
+        csv
+    }
+
+    /// The columns of this trace.
+    #[must_use]
+    pub fn fields(&self) -> &[Field] {
+        &self.fields
+    }
+
+    /// Number of instructions in this trace.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.rows.len()
+    }
+
+    /// Returns true if this trace has no instructions.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.rows.is_empty()
+    }
+}
+
+/// A field that differs from the reference.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Mismatch {
+    pub field: Field,
+    pub expected: u16,
+    pub actual: u16,
+}
+
+/// Formatted like "v3: expected 2a, got 00".
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}: expected {:x}, got {:x}",
+            self.field, self.expected, self.actual
+        )
+    }
+}
+
+/// The first instruction where a run disagrees with a trace.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Divergence {
+    /// Instructions run before the divergence.
+    pub step: usize,
+    /// Line of the reference row in the trace.
+    pub line: usize,
+    /// Program counter of the instruction about to run.
+    pub pc: u16,
+    pub mismatches: Vec<Mismatch>,
+}
+
+/// Formatted like "step 12 (line 14) at 0x21C: v3: expected 2a, got 00".
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "step {} (line {}) at {:#05X}",
+            self.step, self.line, self.pc
+        )?;
+        for mismatch in &self.mismatches {
+            write!(f, ": {mismatch}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Runs instructions one at a time, counting down the timers once per
+/// `cycles_per_frame` instructions like `next_frame`.
+#[derive(Debug, Default)]
+struct Lockstep {
+    cycles: u32,
+}
+
+impl Lockstep {
+    fn step(&mut self, core: &mut Oxid8) -> Result<(), String> {
+        core.run_cycle()?;
+        self.cycles += 1;
+        if self.cycles >= core.cycles_per_frame {
+            self.cycles = 0;
+            core.dec_timers();
+        }
+        Ok(())
+    }
+}
+
+impl Oxid8 {
+    /// Runs the loaded ROM in lockstep with a reference trace,
+    /// returning the first instruction where they disagree.
+    ///
+    /// # Errors
+    ///
+    /// Returns "step N: message" if the ROM hits an invalid instruction
+    /// before the trace ends.
+    ///
+    /// # Panics
+    ///
+    /// See `run_cycle`.
+    pub fn compare_trace(&mut self, trace: &Trace) -> Result<Option<Divergence>, String> {
+        let mut lockstep = Lockstep::default();
+        for (step, (row, &line)) in trace.rows.iter().zip(&trace.lines).enumerate() {
+            let mismatches: Vec<Mismatch> = trace
+                .fields
+                .iter()
+                .zip(row)
+                .map(|(&field, &expected)| Mismatch {
+                    field,
+                    expected,
+                    actual: field.read(self),
+                })
+                .filter(|m| m.expected != m.actual)
+                .collect();
+            if !mismatches.is_empty() {
+                return Ok(Some(Divergence {
+                    step,
+                    line,
+                    pc: self.pc.get(),
+                    mismatches,
+                }));
+            }
+
+            lockstep
+                .step(self)
+                .map_err(|err| format!("step {step}: {err}"))?;
+        }
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// LD V0, 2A; ADD V0, 1; JP 202
+    const ROM: [u8; 6] = [0x60, 0x2A, 0x70, 0x01, 0x12, 0x02];
+
+    fn emu() -> Oxid8 {
+        let mut emu = Oxid8::new();
+        emu.load_rom_bytes(&ROM).unwrap();
+        emu
+    }
+
+    #[test]
+    fn parse() {
+        let trace = Trace::parse_csv("# from another emulator\nPC, V0,vf\n\n0x200,0,1\n").unwrap();
+        assert_eq!(trace.fields(), [Field::Pc, Field::V(0), Field::V(0xF)]);
+        assert_eq!(trace.rows, [vec![0x200, 0, 1]]);
+        assert_eq!(trace.lines, [4]);
+
+        let err = |s| Trace::parse_csv(s).unwrap_err();
+        assert_eq!(err(""), "Empty trace");
+        assert_eq!(err("pc,v10"), "line 1: Unknown column: v10");
+        assert_eq!(err("pc,PC"), "line 1: Repeated column: pc");
+        assert_eq!(err("pc,v0\n200"), "line 2: Expected 2 values, got 1");
+        assert_eq!(err("pc\nxyz"), "line 2: Invalid value: xyz");
+    }
+
+    #[test]
+    fn record_roundtrip() {
+        let trace = Trace::record(&mut emu(), &Field::ALL, 5);
+        assert_eq!(trace.len(), 5);
+        let csv = trace.to_csv();
+        assert!(csv.starts_with("pc,opcode,i,sp,v0,"));
+        assert!(csv.contains("\n0200,602a,0000,00,00,"));
+
+        let parsed = Trace::parse_csv(&csv).unwrap();
+        assert_eq!(parsed.rows, trace.rows);
+        assert_eq!(emu().compare_trace(&parsed), Ok(None));
+    }
+
+    #[test]
+    fn divergence() {
+        // The reference adds 2 instead of 1.
+        let trace = Trace::parse_csv("pc,v0\n200,00\n202,2a\n204,2c\n202,2c\n").unwrap();
+        let divergence = emu().compare_trace(&trace).unwrap().unwrap();
+        assert_eq!(divergence.step, 2);
+        assert_eq!(divergence.line, 4);
+        assert_eq!(
+            divergence.mismatches,
+            [Mismatch {
+                field: Field::V(0),
+                expected: 0x2C,
+                actual: 0x2B,
+            }]
+        );
+        assert_eq!(
+            divergence.to_string(),
+            "step 2 (line 4) at 0x204: v0: expected 2c, got 2b"
+        );
+    }
+
+    #[test]
+    fn invalid_instruction() {
+        let mut emu = Oxid8::new();
+        emu.load_rom_bytes(&[0xFF, 0xFF]).unwrap();
+        let trace = Trace::parse_csv("pc\n200\n202\n").unwrap();
+        assert_eq!(
+            emu.compare_trace(&trace),
+            Err("step 0: Invalid Instruction: FFFF at 512".into())
+        );
+    }
+}
@@ -0,0 +1,22 @@
+//! ROM identification by content hash.
+//!
+//! ROMs are identified by the SHA-1 of their contents, the same scheme
+//! used by the community CHIP-8 database, so per-game data survives
+//! renaming or moving files around.
+
+/// Returns the lowercase hex SHA-1 digest of `rom`.
+#[must_use]
+pub fn rom_hash(rom: &[u8]) -> String {
+    sha1_smol::Sha1::from(rom).digest().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn known_digest() {
+        assert_eq!(rom_hash(b""), "da39a3ee5e6b4b0d3255bfef95601890afd80709");
+        assert_eq!(rom_hash(b"abc"), "a9993e364706816aba3e25717850c26c9cd0d89d");
+    }
+}
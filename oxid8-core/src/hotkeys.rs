@@ -0,0 +1,475 @@
+//! Frontend shortcuts that players can rebind.
+//!
+//! Each frontend lists the [`Action`]s it supports with a default
+//! [`Key`], and looks up the action for a key press in [`Hotkeys`]
+//! instead of matching on keys itself. With the `config` feature,
+//! bindings are overridden from a small TOML file shared by every
+//! frontend, naming the key for each action, or `"none"` to unbind it.
+//! Actions a frontend doesn't support are ignored, so one file works
+//! for all of them.
+//!
+//! ```toml
+//! # <config_dir>/oxid8/hotkeys.toml
+//! pause = "p"
+//! reset = "F12"
+//! screenshot = "none"
+//! ```
+//!
+//! A key is bound to at most one action, rebinding a key takes it from
+//! the action that had it.
+//!
+//! ```
+//! use oxid8_core::hotkeys::{Action, Hotkeys, Key};
+//!
+//! let mut hotkeys = Hotkeys::new(&[
+//!     (Key::F(3), Action::TimerRate),
+//!     (Key::Char('p'), Action::Pause),
+//! ]);
+//! assert_eq!(hotkeys.action(Key::F(3)), Some(Action::TimerRate));
+//!
+//! hotkeys.bind(Action::Pause, Some(Key::F(3)));
+//! assert_eq!(hotkeys.action(Key::F(3)), Some(Action::Pause));
+//! assert_eq!(hotkeys.key(Action::TimerRate), None);
+//! ```
+
+use std::{fmt, str::FromStr, time::Duration};
+
+/// A key on the keyboard, as named in the hotkeys file.
+///
+/// Characters are the keys typing them on a QWERTY keyboard, like the
+/// [`keymap`](crate::keymap) layouts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Key {
+    Char(char),
+    /// A function key, `F(1)` to `F(24)`.
+    F(u8),
+    Esc,
+    Enter,
+    Tab,
+    Backspace,
+    Insert,
+    Delete,
+    Home,
+    End,
+    PageUp,
+    PageDown,
+}
+
+/// Names of the keys that aren't characters or function keys.
+const KEY_NAMES: [(Key, &str); 10] = [
+    (Key::Esc, "Esc"),
+    (Key::Enter, "Enter"),
+    (Key::Tab, "Tab"),
+    (Key::Backspace, "Backspace"),
+    (Key::Insert, "Insert"),
+    (Key::Delete, "Delete"),
+    (Key::Home, "Home"),
+    (Key::End, "End"),
+    (Key::PageUp, "PageUp"),
+    (Key::PageDown, "PageDown"),
+];
+
+/// Formatted like "F5", "PageUp", "p", or "Space".
+impl fmt::Display for Key {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Key::Char(' ') => f.write_str("Space"),
+            Key::Char(c) => write!(f, "{c}"),
+            Key::F(n) => write!(f, "F{n}"),
+            key => {
+                let (_, name) = KEY_NAMES.iter().find(|(k, _)| k == key).unwrap();
+                f.write_str(name)
+            }
+        }
+    }
+}
+
+/// Parses a single character or a key name, ignoring case, as
+/// formatted by `Display`. "Escape" also names [`Key::Esc`].
+impl FromStr for Key {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut chars = s.chars();
+        if let (Some(c), None) = (chars.next(), chars.next()) {
+            return Ok(Key::Char(c));
+        }
+        if s.eq_ignore_ascii_case("space") {
+            return Ok(Key::Char(' '));
+        }
+        if s.eq_ignore_ascii_case("escape") {
+            return Ok(Key::Esc);
+        }
+        if let Some(n) = s.strip_prefix(['F', 'f'])
+            && let Ok(n) = n.parse()
+            && (1..=24).contains(&n)
+        {
+            return Ok(Key::F(n));
+        }
+        KEY_NAMES
+            .iter()
+            .find(|(_, name)| name.eq_ignore_ascii_case(s))
+            .map(|&(key, _)| key)
+            .ok_or_else(|| format!("Invalid key: {s}"))
+    }
+}
+
+/// Something a frontend does on a hotkey. Not every frontend supports
+/// every action.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Action {
+    Help,
+    /// Leave the game for the menu.
+    Menu,
+    Pause,
+    /// Restart the rom from power on.
+    Reset,
+    /// Save the quick savestate slot.
+    SaveState,
+    /// Load the quick savestate slot.
+    LoadState,
+    Slower,
+    Faster,
+    Screenshot,
+    /// Save the settings for the loaded rom.
+    SaveSettings,
+    /// Switch between 60Hz and 50Hz timers.
+    TimerRate,
+    /// Switch between the one and two player keymaps.
+    Keymap,
+    Rotate,
+    KeyLog,
+    Record,
+    /// Take a savestate for the debugger to diff.
+    Snapshot,
+    Diff,
+    Palette,
+    Debugger,
+    Magnifier,
+    Fps,
+    Keypad,
+    /// Show the active configuration.
+    Profile,
+    /// Show the speedrun timer.
+    Timer,
+    /// Copy a savestate as text.
+    CopyState,
+    /// Switch to the next color preset.
+    CyclePalette,
+    /// Open the color editor.
+    Colors,
+    /// Zoom the screen in terminals, see
+    /// [`viewport`](crate::viewport).
+    Zoom,
+}
+
+/// Each action with its name in the hotkeys file and a description
+/// for help screens.
+const ACTIONS: [(Action, &str, &str); 28] = [
+    (Action::Help, "help", "Help"),
+    (Action::Menu, "menu", "Back to the menu"),
+    (Action::Pause, "pause", "Pause"),
+    (Action::Reset, "reset", "Restart the rom"),
+    (Action::SaveState, "save_state", "Quick save"),
+    (Action::LoadState, "load_state", "Quick load"),
+    (Action::Slower, "slower", "Slower"),
+    (Action::Faster, "faster", "Faster"),
+    (Action::Screenshot, "screenshot", "Save a screenshot"),
+    (Action::SaveSettings, "save_settings", "Save game settings"),
+    (Action::TimerRate, "timer_rate", "60Hz or 50Hz timers"),
+    (Action::Keymap, "keymap", "One or two players"),
+    (Action::Rotate, "rotate", "Rotate the display"),
+    (Action::KeyLog, "key_log", "Key events"),
+    (Action::Record, "record", "Record a movie"),
+    (Action::Snapshot, "snapshot", "Take a savestate to diff"),
+    (Action::Diff, "diff", "Diff the last two savestates"),
+    (Action::Palette, "palette", "Command palette"),
+    (Action::Debugger, "debugger", "Debugger"),
+    (Action::Magnifier, "magnifier", "Magnifier, arrows move it"),
+    (Action::Fps, "fps", "Frame rate"),
+    (Action::Keypad, "keypad", "Keypad"),
+    (Action::Profile, "profile", "Configuration"),
+    (Action::Timer, "timer", "Speedrun timer"),
+    (Action::CopyState, "copy_state", "Copy a savestate"),
+    (Action::CyclePalette, "cycle_palette", "Next color preset"),
+    (Action::Colors, "colors", "Edit the colors"),
+    (Action::Zoom, "zoom", "Zoom, arrows pan"),
+];
+
+impl Action {
+    fn entry(self) -> &'static (Action, &'static str, &'static str) {
+        ACTIONS.iter().find(|(action, ..)| *action == self).unwrap()
+    }
+
+    /// The name in the hotkeys file, like `save_state`.
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        self.entry().1
+    }
+
+    /// What the action does, for help screens.
+    #[must_use]
+    pub fn description(self) -> &'static str {
+        self.entry().2
+    }
+}
+
+/// Formatted like the name in the hotkeys file.
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Action {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        ACTIONS
+            .iter()
+            .find(|(_, name, _)| *name == s)
+            .map(|&(action, ..)| action)
+            .ok_or_else(|| format!("Invalid action: {s}"))
+    }
+}
+
+/// The actions a frontend supports and the keys bound to them, see the
+/// [module docs](self). The default has no actions.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Hotkeys {
+    bindings: Vec<(Action, Option<Key>)>,
+}
+
+impl Hotkeys {
+    /// The actions of a frontend with their default keys, in the order
+    /// help screens list them.
+    #[must_use]
+    pub fn new(defaults: &[(Key, Action)]) -> Self {
+        let mut hotkeys = Hotkeys {
+            bindings: defaults.iter().map(|&(_, action)| (action, None)).collect(),
+        };
+        for &(key, action) in defaults {
+            hotkeys.bind(action, Some(key));
+        }
+        hotkeys
+    }
+
+    /// Binds `key` to `action`, or unbinds it with `None`, and returns
+    /// true if the frontend supports the action.
+    pub fn bind(&mut self, action: Action, key: Option<Key>) -> bool {
+        if !self.bindings.iter().any(|&(a, _)| a == action) {
+            return false;
+        }
+        for (a, k) in &mut self.bindings {
+            if *a == action {
+                *k = key;
+            } else if key.is_some() && *k == key {
+                *k = None;
+            }
+        }
+        true
+    }
+
+    /// The action bound to `key`.
+    #[must_use]
+    pub fn action(&self, key: Key) -> Option<Action> {
+        self.bindings
+            .iter()
+            .find(|&&(_, k)| k == Some(key))
+            .map(|&(action, _)| action)
+    }
+
+    /// The key bound to `action`.
+    #[must_use]
+    pub fn key(&self, action: Action) -> Option<Key> {
+        self.bindings
+            .iter()
+            .find(|&&(a, _)| a == action)
+            .and_then(|&(_, key)| key)
+    }
+
+    /// The bound keys and their actions, in the order of the defaults.
+    pub fn iter(&self) -> impl Iterator<Item = (Key, Action)> + '_ {
+        self.bindings
+            .iter()
+            .filter_map(|&(action, key)| Some((key?, action)))
+    }
+
+    /// Rebinds actions from a hotkeys file, see the [module docs](self).
+    ///
+    /// # Errors
+    ///
+    /// If the file isn't valid TOML, or names an unknown action or key.
+    #[cfg(feature = "config")]
+    pub fn apply_toml(&mut self, s: &str) -> Result<(), String> {
+        let table: toml::Table = toml::from_str(s).map_err(|err| err.to_string())?;
+        for (name, value) in table {
+            let action = name.parse()?;
+            let key = match value.as_str() {
+                Some(key) if key.eq_ignore_ascii_case("none") => None,
+                Some(key) => Some(key.parse()?),
+                None => return Err(format!("Invalid key for {name}: {value}")),
+            };
+            self.bind(action, key);
+        }
+        Ok(())
+    }
+
+    /// The `defaults` rebound from the hotkeys file at `path`, if it
+    /// exists.
+    ///
+    /// # Errors
+    ///
+    /// If the file exists but can't be read or is invalid.
+    #[cfg(feature = "config")]
+    pub fn load(
+        path: impl AsRef<std::path::Path>,
+        defaults: &[(Key, Action)],
+    ) -> std::io::Result<Self> {
+        use std::io;
+
+        let mut hotkeys = Hotkeys::new(defaults);
+        match std::fs::read_to_string(path) {
+            Ok(s) => hotkeys
+                .apply_toml(&s)
+                .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => (),
+            Err(err) => return Err(err),
+        }
+        Ok(hotkeys)
+    }
+}
+
+/// Lets a held hotkey through once, for actions like reset that
+/// shouldn't repeat.
+///
+/// Terminals without the Kitty keyboard protocol report a held key as
+/// repeated presses and never its release, so a press within
+/// [`Debounce::INTERVAL`] of the last one counts as the key still being
+/// held. Frontends that see releases report them with
+/// [`Debounce::release`], so tapping the key quickly still works.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Debounce {
+    /// When the key was last pressed, while it may be held.
+    last: Option<Duration>,
+}
+
+impl Debounce {
+    /// Presses closer together than this are one held press. Longer
+    /// than the delay before keys repeat on most systems.
+    pub const INTERVAL: Duration = Duration::from_millis(600);
+
+    /// Returns true if a press at `now`, the time since any fixed
+    /// point, should run the action.
+    pub fn press(&mut self, now: Duration) -> bool {
+        let held = self
+            .last
+            .is_some_and(|last| now.saturating_sub(last) < Self::INTERVAL);
+        self.last = Some(now);
+        !held
+    }
+
+    /// The key was released, the next press runs the action.
+    pub fn release(&mut self) {
+        self.last = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DEFAULTS: [(Key, Action); 3] = [
+        (Key::F(1), Action::Help),
+        (Key::Char('-'), Action::Slower),
+        (Key::PageUp, Action::SaveState),
+    ];
+
+    #[test]
+    fn keys() {
+        for key in [
+            Key::Char('p'),
+            Key::Char(' '),
+            Key::F(12),
+            Key::Esc,
+            Key::PageDown,
+        ] {
+            assert_eq!(key.to_string().parse(), Ok(key));
+        }
+        assert_eq!("escape".parse(), Ok(Key::Esc));
+        assert_eq!("pageup".parse(), Ok(Key::PageUp));
+        assert_eq!("f5".parse(), Ok(Key::F(5)));
+        assert_eq!("P".parse(), Ok(Key::Char('P')));
+        assert!("F25".parse::<Key>().is_err());
+        assert!("Hyper".parse::<Key>().is_err());
+    }
+
+    #[test]
+    fn actions() {
+        for (action, name, _) in ACTIONS {
+            assert_eq!(action.to_string(), name);
+            assert_eq!(name.parse(), Ok(action));
+        }
+        assert!("jump".parse::<Action>().is_err());
+    }
+
+    #[test]
+    fn bindings() {
+        let mut hotkeys = Hotkeys::new(&DEFAULTS);
+        assert_eq!(hotkeys.iter().collect::<Vec<_>>(), DEFAULTS);
+        assert_eq!(hotkeys.action(Key::Char('-')), Some(Action::Slower));
+        assert_eq!(hotkeys.action(Key::Char('=')), None);
+
+        // Unsupported actions are ignored.
+        assert!(!hotkeys.bind(Action::Pause, Some(Key::F(1))));
+        assert_eq!(hotkeys.action(Key::F(1)), Some(Action::Help));
+
+        assert!(hotkeys.bind(Action::SaveState, Some(Key::F(1))));
+        assert_eq!(hotkeys.key(Action::Help), None);
+        assert!(hotkeys.bind(Action::Slower, None));
+        assert_eq!(
+            hotkeys.iter().collect::<Vec<_>>(),
+            [(Key::F(1), Action::SaveState)]
+        );
+    }
+
+    #[test]
+    fn debounce() {
+        let ms = Duration::from_millis;
+        let mut debounce = Debounce::default();
+        assert!(debounce.press(ms(1000)));
+        // Held, repeating every 30ms for a second.
+        assert!((1..=33).all(|i| !debounce.press(ms(1000 + 30 * i))));
+        assert!(debounce.press(ms(3000)));
+        debounce.release();
+        assert!(debounce.press(ms(3100)));
+    }
+
+    #[cfg(feature = "config")]
+    #[test]
+    fn from_toml() {
+        let mut hotkeys = Hotkeys::new(&DEFAULTS);
+        hotkeys
+            .apply_toml("save_state = \"F5\"\nslower = \"none\"\npause = \"p\"\n")
+            .unwrap();
+        assert_eq!(hotkeys.key(Action::SaveState), Some(Key::F(5)));
+        assert_eq!(hotkeys.key(Action::Slower), None);
+        assert_eq!(hotkeys.key(Action::Pause), None);
+
+        assert!(hotkeys.apply_toml("jump = \"j\"").is_err());
+        assert!(hotkeys.apply_toml("help = \"Hyper\"").is_err());
+        assert!(hotkeys.apply_toml("help = 1").is_err());
+
+        let path = std::env::temp_dir().join(format!("oxid8-hotkeys-{}", std::process::id()));
+        assert_eq!(
+            Hotkeys::load(&path, &DEFAULTS).unwrap(),
+            Hotkeys::new(&DEFAULTS)
+        );
+        std::fs::write(&path, "help = \"Esc\"").unwrap();
+        let hotkeys = Hotkeys::load(&path, &DEFAULTS).unwrap();
+        assert_eq!(hotkeys.action(Key::Esc), Some(Action::Help));
+        std::fs::write(&path, "help = ").unwrap();
+        assert!(Hotkeys::load(&path, &DEFAULTS).is_err());
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,368 @@
+//! Key events timestamped within a frame.
+//!
+//! Frontends poll input between frames, so applying every event at the
+//! start of the next frame loses their order relative to the
+//! instructions that read the keypad. At high cycle rates a press and
+//! release within one frame may never be seen by `Ex9E` or may resolve
+//! `Fx0A` on the wrong instruction. [`Oxid8::next_frame_with_input`]
+//! applies each event just before the cycle it was timestamped with.
+//!
+//! Presses and releases are also tracked as edges until the end of the
+//! frame that sees them, see [`Oxid8::just_pressed`], so taps between
+//! frames reach the next one. Repeated presses of a held key, as sent
+//! by OS auto-repeat on some platforms, are not edges, so `Ex9E`,
+//! `ExA1`, and `Fx0A` behave the same whether or not the frontend
+//! filters them.
+
+use std::{fmt, str::FromStr, time::Duration};
+
+use crate::{NUM_KEYS, Oxid8};
+
+/// A key on the virtual keypad was pressed or released.
+///
+/// Formatted as `<cycle>:<key><+|->`, e.g. `12:a+` for key A pressed
+/// before the 12th cycle of the frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InputEvent {
+    /// Cycle of the frame the event is applied before.
+    pub cycle: u32,
+    /// 0x0 - 0xF
+    pub key: u8,
+    pub pressed: bool,
+}
+
+/// Keys pressed and released this frame, one bit per key.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) struct KeyEdges {
+    pressed: u16,
+    released: u16,
+}
+
+impl KeyEdges {
+    pub(crate) fn record(&mut self, key: usize, pressed: bool) {
+        match pressed {
+            true => self.pressed |= 1 << key,
+            false => self.released |= 1 << key,
+        }
+    }
+
+    /// Uses up the press of `key`, returning true if there was one.
+    pub(crate) fn take_pressed(&mut self, key: usize) -> bool {
+        let pressed = self.pressed & (1 << key) != 0;
+        self.pressed &= !(1 << key);
+        pressed
+    }
+}
+
+impl Oxid8 {
+    /// Returns true if `key` was pressed since the last frame ended,
+    /// even if it has been released again.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `key` is not on the keypad, 0x0 - 0xF.
+    #[must_use]
+    pub fn just_pressed(&self, key: usize) -> bool {
+        assert!(key < NUM_KEYS, "No key {key:#X}");
+        self.key_edges.pressed & (1 << key) != 0
+    }
+
+    /// Returns true if `key` was released since the last frame ended,
+    /// even if it has been pressed again.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `key` is not on the keypad, 0x0 - 0xF.
+    #[must_use]
+    pub fn just_released(&self, key: usize) -> bool {
+        assert!(key < NUM_KEYS, "No key {key:#X}");
+        self.key_edges.released & (1 << key) != 0
+    }
+
+    /// Forgets the key edges, ending a frame.
+    ///
+    /// `next_frame` and `next_frame_with_input` call this after the
+    /// timers. Call it yourself if you run cycles with `run_cycle`.
+    pub fn clear_key_edges(&mut self) {
+        self.key_edges = KeyEdges::default();
+    }
+
+    /// Number of cycles the next frame will run.
+    ///
+    /// Varies by one between frames when the speed is not a multiple of
+    /// the timer rate. With [`cycle weights`](crate::weights), heavier
+    /// instructions use up more of them.
+    #[must_use]
+    pub fn frame_cycles(&self) -> u32 {
+        self.cycle_budget() / self.timer_rate.hz()
+    }
+
+    /// Cycles of the next frame times the timer rate, with the fraction
+    /// carried over from the last. Saturates at the highest speeds.
+    fn cycle_budget(&self) -> u32 {
+        self.cycle_remainder
+            .saturating_add(self.cycles_per_frame.saturating_mul(60))
+    }
+
+    /// Cycle of the next frame corresponding to `elapsed` time since the
+    /// last frame, for timestamping events as they arrive.
+    #[must_use]
+    pub fn cycle_at(&self, elapsed: Duration) -> u32 {
+        let tick = self.timer_rate.tick().as_nanos();
+        let cycles = u128::from(self.frame_cycles());
+        (elapsed.as_nanos().min(tick) * cycles / tick) as u32
+    }
+
+    /// Emulates a frame like `next_frame`, applying each of `events`
+    /// before its cycle.
+    ///
+    /// `events` must be sorted by cycle. Events past the end of the
+    /// frame are applied after its last cycle.
+    ///
+    /// # Errors
+    ///
+    /// See `next_frame`.
+    ///
+    /// # Panics
+    ///
+    /// See `next_frame`. Also panics if an event's key is not on the
+    /// keypad.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            level = "debug",
+            name = "frame",
+            skip_all,
+            err,
+            fields(frame = self.time.frames(), cycles = self.frame_cycles(), events = events.len())
+        )
+    )]
+    pub fn next_frame_with_input(&mut self, events: &[InputEvent]) -> Result<(), String> {
+        self.run_frame(events, |_| false).map(|_| ())
+    }
+
+    /// Emulates a frame, stopping before an instruction where `stop`
+    /// is true and returning its address.
+    pub(crate) fn run_frame(
+        &mut self,
+        events: &[InputEvent],
+        stop: impl Fn(&Self) -> bool,
+    ) -> Result<Option<u16>, String> {
+        // Carry the fraction of a cycle over to the next frame.
+        let hz = self.timer_rate.hz();
+        let budget = self.cycle_budget();
+        self.cycle_remainder = budget % hz;
+
+        // Instructions cost their weight, 1 unless set, and events are
+        // timed by the instructions run.
+        let units = budget / hz;
+        let mut spent = 0;
+        let mut events = events.iter().peekable();
+        let mut stopped = None;
+        for cycle in 0.. {
+            if spent >= units {
+                break;
+            }
+            while let Some(event) = events.next_if(|event| event.cycle <= cycle) {
+                #[cfg(feature = "tracing")]
+                tracing::trace!(cycle, key = event.key, pressed = event.pressed, "Key event");
+                self.set_key(event.key as usize, event.pressed);
+            }
+            if stop(self) {
+                stopped = Some(self.pc.get());
+                break;
+            }
+            spent += self.next_weight();
+            self.run_cycle()?;
+        }
+        for event in events {
+            self.set_key(event.key as usize, event.pressed);
+        }
+        // Edges from between frames were seen by this one, a frame
+        // stopped early keeps them for the instruction it stopped at.
+        if stopped.is_none() {
+            self.dec_timers();
+            self.clear_key_edges();
+        }
+
+        Ok(stopped)
+    }
+}
+
+impl fmt::Display for InputEvent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let state = if self.pressed { '+' } else { '-' };
+        write!(f, "{}:{:x}{state}", self.cycle, self.key)
+    }
+}
+
+impl FromStr for InputEvent {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("Invalid input event: {s}");
+        let (cycle, key) = s.split_once(':').ok_or_else(invalid)?;
+        let (key, pressed) = match key.split_at_checked(key.len().wrapping_sub(1)) {
+            Some((key, "+")) => (key, true),
+            Some((key, "-")) => (key, false),
+            _ => return Err(invalid()),
+        };
+        let key = u8::from_str_radix(key, 16).map_err(|_| invalid())?;
+        if key as usize >= NUM_KEYS {
+            return Err(invalid());
+        }
+        Ok(Self {
+            cycle: cycle.parse().map_err(|_| invalid())?,
+            key,
+            pressed,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{TimerRate, quirks::KeyWait};
+
+    fn press(cycle: u32, key: u8) -> InputEvent {
+        InputEvent {
+            cycle,
+            key,
+            pressed: true,
+        }
+    }
+
+    #[test]
+    fn parse() {
+        assert_eq!("12:a+".parse(), Ok(press(12, 0xA)));
+        assert_eq!(press(12, 0xA).to_string(), "12:a+");
+        assert!(matches!(
+            "0:f-".parse(),
+            Ok(InputEvent { pressed: false, .. })
+        ));
+        for bad in ["", "1:", "1:+", "a+", "1:10+", "x:1+", "1:1*"] {
+            assert!(bad.parse::<InputEvent>().is_err(), "{bad}");
+        }
+    }
+
+    #[test]
+    fn subframe() {
+        // ADD V1, 1; SKP V0; JP 200; JP 206
+        let rom = [0x71, 0x01, 0xE0, 0x9E, 0x12, 0x00, 0x12, 0x06];
+        let run = |events: &[InputEvent]| {
+            let mut emu = Oxid8::new();
+            emu.load_rom_bytes(&rom).unwrap();
+            emu.next_frame_with_input(events).unwrap();
+            emu.v_reg[1]
+        };
+
+        // Seen by the first SKP at the start of the frame, the second
+        // when pressed before cycle 4.
+        assert_eq!(run(&[press(0, 0)]), 1);
+        assert_eq!(run(&[press(4, 0)]), 2);
+        // Pressed after the frame ends, never seen.
+        assert_eq!(run(&[press(u32::MAX, 0)]), 4);
+    }
+
+    #[test]
+    fn edges() {
+        let mut emu = Oxid8::new();
+        emu.set_key(3, true);
+        assert!(emu.just_pressed(3) && !emu.just_released(3));
+
+        // Auto-repeat is not another press.
+        emu.clear_key_edges();
+        emu.set_key(3, true);
+        assert!(!emu.just_pressed(3));
+        emu.set_key(3, false);
+        emu.set_key(3, false);
+        assert!(emu.just_released(3) && emu.keys_ref() == &[false; NUM_KEYS]);
+
+        emu.clear_key_edges();
+        assert!(!emu.just_released(3));
+    }
+
+    #[test]
+    fn tap_between_cycles() {
+        // LD V0, K; JP 202
+        let rom = [0xF0, 0x0A, 0x12, 0x02];
+        let mut emu = Oxid8::new();
+        emu.load_rom_bytes(&rom).unwrap();
+        let release = InputEvent {
+            pressed: false,
+            ..press(3, 0xB)
+        };
+
+        // Pressed and released before the same cycle, still a key press.
+        emu.next_frame_with_input(&[press(3, 0xB), release])
+            .unwrap();
+        assert_eq!(emu.v_reg[0], 0xB);
+        assert_eq!(emu.pc(), 0x202);
+    }
+
+    #[test]
+    fn tap_between_frames() {
+        // LD V0, K; JP 202
+        let rom = [0xF0, 0x0A, 0x12, 0x02];
+        let mut emu = Oxid8::new();
+        emu.load_rom_bytes(&rom).unwrap();
+        emu.next_frame().unwrap();
+
+        // Pressed and released while no frame ran, still a key press.
+        emu.set_key(5, true);
+        emu.set_key(5, false);
+        for _ in 0..3 {
+            emu.next_frame().unwrap();
+        }
+        assert_eq!(emu.v_reg[0], 5);
+        assert_eq!(emu.pc(), 0x202);
+        assert!(!emu.just_pressed(5));
+    }
+
+    #[test]
+    fn tap_resolves_one_wait() {
+        // LD V0, K; LD V1, K; JP 204
+        let rom = [0xF0, 0x0A, 0xF1, 0x0A, 0x12, 0x04];
+        let release = InputEvent {
+            pressed: false,
+            ..press(1, 0xB)
+        };
+        for key_wait in [KeyWait::Release, KeyWait::Press] {
+            let mut emu = Oxid8::new();
+            emu.quirks.key_wait = key_wait;
+            emu.load_rom_bytes(&rom).unwrap();
+            emu.next_frame_with_input(&[press(0, 0xB), release])
+                .unwrap();
+            assert_eq!(emu.v_reg[0], 0xB, "{key_wait:?}");
+            // The second wait needs a press of its own.
+            assert_eq!(emu.pc(), 0x202, "{key_wait:?}");
+        }
+    }
+
+    #[test]
+    fn timestamps() {
+        let emu = Oxid8::new();
+        let cycles = emu.frame_cycles();
+        let tick = emu.timer_rate().tick();
+        assert_eq!(emu.cycle_at(Duration::ZERO), 0);
+        assert_eq!(emu.cycle_at(tick / 2), cycles / 2);
+        assert_eq!(emu.cycle_at(tick * 2), cycles);
+    }
+
+    #[test]
+    fn top_speed() {
+        for rate in [TimerRate::Pal, TimerRate::Ntsc] {
+            let mut emu = Oxid8::new();
+            // JP 200
+            emu.load_rom_bytes(&[0x12, 0x00]).unwrap();
+            emu.set_timer_rate(rate);
+            emu.set_cycles_per_frame(u32::MAX);
+            emu.cycle_remainder = rate.hz() - 1;
+            assert_eq!(emu.frame_cycles(), u32::MAX / rate.hz());
+            // Too many cycles to run, stop before the first.
+            assert_eq!(emu.run_frame(&[], |_| true), Ok(Some(0x200)));
+            assert!(emu.cycle_remainder < rate.hz());
+            assert_eq!(emu.frame_cycles(), u32::MAX / rate.hz());
+        }
+    }
+}
@@ -0,0 +1,176 @@
+//! A log of raw host key events for diagnosing input problems.
+//!
+//! Terminals often report presses but not releases, keyboards drop keys
+//! when too many are held at once (ghosting), and keymaps can put keys
+//! somewhere unexpected. Frontends push every host key event into a
+//! [`KeyLog`] and show it beside the keypad state so users can see which
+//! of these is happening.
+//!
+//! ```
+//! use oxid8_core::keylog::{KeyAction, KeyLog};
+//!
+//! let mut log = KeyLog::default();
+//! log.push("w", KeyAction::Press, Some(0x5));
+//! log.push("F6", KeyAction::Press, None);
+//! assert_eq!(log.records().next().unwrap().to_string(), "F6 down");
+//! ```
+
+use std::{collections::VecDeque, fmt};
+
+/// Events kept in the log, the oldest are dropped first.
+pub const LOG_LEN: usize = 8;
+
+/// Presses seen before a missing release is reported.
+const RELEASE_WARNING_PRESSES: u32 = 4;
+
+/// What the host reported for a key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyAction {
+    Press,
+    /// OS auto-repeat of a held key.
+    Repeat,
+    Release,
+}
+
+/// Formatted as `down`, `repeat`, or `up`.
+impl fmt::Display for KeyAction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            KeyAction::Press => "down",
+            KeyAction::Repeat => "repeat",
+            KeyAction::Release => "up",
+        })
+    }
+}
+
+/// A host key event and the keypad key it maps to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyRecord {
+    /// The host's name for the key.
+    pub key: String,
+    pub action: KeyAction,
+    /// 0x0 - 0xF, none if the key is not in the keymap.
+    pub mapped: Option<u8>,
+}
+
+/// Formatted like `w down: 5`, without the keypad key if unmapped.
+impl fmt::Display for KeyRecord {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {}", self.key, self.action)?;
+        if let Some(k) = self.mapped {
+            write!(f, ": {k:X}")?;
+        }
+        Ok(())
+    }
+}
+
+/// The most recent host key events with counts since the log started.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct KeyLog {
+    records: VecDeque<KeyRecord>,
+    presses: u32,
+    repeats: u32,
+    releases: u32,
+}
+
+impl KeyLog {
+    /// Logs an event, dropping the oldest past [`LOG_LEN`].
+    pub fn push(&mut self, key: impl Into<String>, action: KeyAction, mapped: Option<u8>) {
+        match action {
+            KeyAction::Press => self.presses += 1,
+            KeyAction::Repeat => self.repeats += 1,
+            KeyAction::Release => self.releases += 1,
+        }
+        if self.records.len() == LOG_LEN {
+            self.records.pop_back();
+        }
+        self.records.push_front(KeyRecord {
+            key: key.into(),
+            action,
+            mapped,
+        });
+    }
+
+    /// Logged events, newest first.
+    pub fn records(&self) -> impl Iterator<Item = &KeyRecord> {
+        self.records.iter()
+    }
+
+    /// Formatted like `12 down 3 repeat 11 up`.
+    #[must_use]
+    pub fn counts(&self) -> String {
+        format!(
+            "{} down {} repeat {} up",
+            self.presses, self.repeats, self.releases
+        )
+    }
+
+    /// A likely problem with the host's input, if any.
+    #[must_use]
+    pub fn warning(&self) -> Option<&'static str> {
+        (self.presses >= RELEASE_WARNING_PRESSES && self.releases == 0)
+            .then_some("No key releases seen from the host")
+    }
+}
+
+/// Held keypad keys in hex, formatted like `1 5 A`, or `none`.
+#[must_use]
+pub fn held_keys(keys: &[bool]) -> String {
+    let held: Vec<String> = keys
+        .iter()
+        .enumerate()
+        .filter(|&(_, &held)| held)
+        .map(|(k, _)| format!("{k:X}"))
+        .collect();
+    match held.is_empty() {
+        true => "none".into(),
+        false => held.join(" "),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn log_is_bounded() {
+        let mut log = KeyLog::default();
+        for i in 0..LOG_LEN as u8 + 2 {
+            log.push(i.to_string(), KeyAction::Press, Some(i & 0xF));
+        }
+        assert_eq!(log.records().count(), LOG_LEN);
+        assert_eq!(log.records().next().unwrap().key, (LOG_LEN + 1).to_string());
+        assert_eq!(log.records().last().unwrap().key, "2");
+        assert_eq!(log.counts(), "10 down 0 repeat 0 up");
+    }
+
+    #[test]
+    fn records_display() {
+        let mut log = KeyLog::default();
+        log.push("v", KeyAction::Release, Some(0xF));
+        log.push("Space", KeyAction::Repeat, None);
+        let lines: Vec<String> = log.records().map(ToString::to_string).collect();
+        assert_eq!(lines, ["Space repeat", "v up: F"]);
+    }
+
+    #[test]
+    fn missing_releases() {
+        let mut log = KeyLog::default();
+        for _ in 0..RELEASE_WARNING_PRESSES {
+            assert_eq!(log.warning(), None);
+            log.push("w", KeyAction::Press, Some(0x5));
+        }
+        assert!(log.warning().is_some());
+        log.push("w", KeyAction::Release, Some(0x5));
+        assert_eq!(log.warning(), None);
+    }
+
+    #[test]
+    fn held() {
+        let mut keys = [false; 16];
+        assert_eq!(held_keys(&keys), "none");
+        keys[0x1] = true;
+        keys[0xA] = true;
+        assert_eq!(held_keys(&keys), "1 A");
+    }
+}
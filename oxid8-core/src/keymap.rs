@@ -0,0 +1,214 @@
+//! Keyboard layouts for the virtual keypad.
+//!
+//! Keys are identified by the character they type on a QWERTY keyboard
+//! so every frontend shares the same layouts.
+//!
+//! ```text
+//! Keypad     Standard   Two player
+//! 1 2 3 C    1 2 3 4    1 2   8 9
+//! 4 5 6 D    q w e r    q w   i o
+//! 7 8 9 E    a s d f    a s   k l
+//! A 0 B F    z x c v    z x   , .
+//! ```
+//!
+//! In two player mode the left half of the keypad belongs to player 1
+//! and the right half to player 2, so games like Pong 2 can be played
+//! by two people on one keyboard.
+//!
+//! [`Keymap::diagram`] lays out the keypad labeled with the keyboard
+//! keys of a keymap, for frontends to draw with the held keys lit.
+//!
+//! ```
+//! use oxid8_core::keymap::Keymap;
+//!
+//! let mut held = [false; 16];
+//! held[0x5] = true;
+//! let diagram = Keymap::TwoPlayer.diagram(&held);
+//! assert_eq!(diagram[1][1].label, 'w');
+//! assert!(diagram[1][1].held);
+//! assert_eq!(diagram[3][2].label, ',');
+//! assert_eq!(diagram[3][2].player, Some(2));
+//! ```
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Keypad keys as they are laid out on the original hardware.
+pub const KEYPAD: [[u8; 4]; 4] = [
+    [0x1, 0x2, 0x3, 0xC],
+    [0x4, 0x5, 0x6, 0xD],
+    [0x7, 0x8, 0x9, 0xE],
+    [0xA, 0x0, 0xB, 0xF],
+];
+
+const STANDARD: [[char; 4]; 4] = [
+    ['1', '2', '3', '4'],
+    ['q', 'w', 'e', 'r'],
+    ['a', 's', 'd', 'f'],
+    ['z', 'x', 'c', 'v'],
+];
+
+const TWO_PLAYER: [[char; 4]; 4] = [
+    ['1', '2', '8', '9'],
+    ['q', 'w', 'i', 'o'],
+    ['a', 's', 'k', 'l'],
+    ['z', 'x', ',', '.'],
+];
+
+/// A keypad key in a [`Keymap::diagram`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyCap {
+    /// The keypad key.
+    pub key: u8,
+    /// The keyboard key typing it.
+    pub label: char,
+    /// The player owning it in two player mode.
+    pub player: Option<u8>,
+    /// Whether it is held.
+    pub held: bool,
+}
+
+/// A mapping of keyboard keys onto the keypad.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub enum Keymap {
+    /// The keypad on the left of the keyboard.
+    #[default]
+    Standard,
+    /// The keypad split between both sides of the keyboard.
+    TwoPlayer,
+}
+
+impl Keymap {
+    /// The keyboard keys laid out like [`KEYPAD`], for legends and help.
+    #[must_use]
+    pub fn rows(self) -> &'static [[char; 4]; 4] {
+        match self {
+            Keymap::Standard => &STANDARD,
+            Keymap::TwoPlayer => &TWO_PLAYER,
+        }
+    }
+
+    /// The keypad key for the keyboard key typing `c`.
+    #[must_use]
+    pub fn key(self, c: char) -> Option<u8> {
+        self.rows().iter().zip(KEYPAD).find_map(|(row, keys)| {
+            let col = row.iter().position(|&label| label == c)?;
+            Some(keys[col])
+        })
+    }
+
+    /// The keyboard key for keypad `key`, the inverse of [`Keymap::key`].
+    #[must_use]
+    pub fn label(self, key: u8) -> Option<char> {
+        KEYPAD.iter().zip(self.rows()).find_map(|(keys, row)| {
+            let col = keys.iter().position(|&k| k == key)?;
+            Some(row[col])
+        })
+    }
+
+    /// The player 1 or 2 owning keypad `key` in two player mode.
+    #[must_use]
+    pub fn player(self, key: u8) -> Option<u8> {
+        if self != Keymap::TwoPlayer {
+            return None;
+        }
+        let col = KEYPAD
+            .iter()
+            .find_map(|keys| keys.iter().position(|&k| k == key))?;
+        Some(if col < 2 { 1 } else { 2 })
+    }
+
+    /// The keypad laid out like [`KEYPAD`], each key labeled with the
+    /// keyboard key typing it and lit if it is `held`.
+    #[must_use]
+    pub fn diagram(self, held: &[bool; 16]) -> [[KeyCap; 4]; 4] {
+        KEYPAD.map(|keys| {
+            keys.map(|key| KeyCap {
+                key,
+                label: self.label(key).unwrap_or(' '),
+                player: self.player(key),
+                held: held[key as usize],
+            })
+        })
+    }
+
+    /// The other keymap.
+    #[must_use]
+    pub fn toggle(self) -> Self {
+        match self {
+            Keymap::Standard => Keymap::TwoPlayer,
+            Keymap::TwoPlayer => Keymap::Standard,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn standard() {
+        let keymap = Keymap::Standard;
+        assert_eq!(keymap.key('1'), Some(0x1));
+        assert_eq!(keymap.key('4'), Some(0xC));
+        assert_eq!(keymap.key('x'), Some(0x0));
+        assert_eq!(keymap.key('v'), Some(0xF));
+        assert_eq!(keymap.key('i'), None);
+        assert_eq!(keymap.player(0x1), None);
+    }
+
+    #[test]
+    fn two_player() {
+        // Pong 2 paddles
+        let keymap = Keymap::TwoPlayer;
+        assert_eq!(keymap.key('1'), Some(0x1));
+        assert_eq!(keymap.key('q'), Some(0x4));
+        assert_eq!(keymap.key('9'), Some(0xC));
+        assert_eq!(keymap.key('o'), Some(0xD));
+        assert_eq!(keymap.key('4'), None);
+        assert_eq!(keymap.player(0x4), Some(1));
+        assert_eq!(keymap.player(0xD), Some(2));
+    }
+
+    #[test]
+    fn labels() {
+        for keymap in [Keymap::Standard, Keymap::TwoPlayer] {
+            for key in 0..16 {
+                let label = keymap.label(key).unwrap();
+                assert_eq!(keymap.key(label), Some(key));
+            }
+        }
+        assert_eq!(Keymap::Standard.label(0x10), None);
+    }
+
+    #[test]
+    fn rows() {
+        for keymap in [Keymap::Standard, Keymap::TwoPlayer] {
+            for (labels, keys) in keymap.rows().iter().zip(KEYPAD) {
+                for (&label, key) in labels.iter().zip(keys) {
+                    assert_eq!(keymap.label(key), Some(label));
+                }
+            }
+        }
+        assert_eq!(Keymap::TwoPlayer.rows()[3], ['z', 'x', ',', '.']);
+    }
+
+    #[test]
+    fn diagram() {
+        let mut held = [false; 16];
+        held[0xF] = true;
+        for keymap in [Keymap::Standard, Keymap::TwoPlayer] {
+            let diagram = keymap.diagram(&held);
+            for (caps, labels) in diagram.iter().zip(keymap.rows()) {
+                for (cap, &label) in caps.iter().zip(labels) {
+                    assert_eq!(cap.label, label);
+                    assert_eq!(keymap.key(label), Some(cap.key));
+                    assert_eq!(cap.held, cap.key == 0xF);
+                }
+            }
+        }
+        assert_eq!(Keymap::Standard.diagram(&held)[3][3].player, None);
+    }
+}
@@ -0,0 +1,137 @@
+//! The CHIP-8 address map.
+//!
+//! ```text
+//! 0x000 - 0x04F  unused, the interpreter itself on the COSMAC VIP
+//! 0x050 - 0x09F  font, 16 glyphs of 5 bytes
+//! 0x0A0 - 0x1FF  unused
+//! 0x200 - 0xFFF  program
+//! ```
+//!
+//! Addresses into RAM are [`Addr`]s rather than bare `u16` or `usize`,
+//! so every conversion to a RAM index goes through one place. The
+//! layout constants are checked against each other at compile time.
+
+use std::{
+    fmt,
+    ops::{Add, AddAssign, Range, SubAssign},
+};
+
+/// Size of RAM in bytes, every 12-bit address.
+pub const RAM_SIZE: usize = 4096;
+
+/// Bytes in a glyph of the built-in font.
+pub const GLYPH_SIZE: usize = 5;
+
+/// Size of the built-in font in bytes, a glyph for each hex digit.
+pub const FONTSET_SIZE: usize = 16 * GLYPH_SIZE;
+
+/// Where the built-in font is loaded.
+pub const FONT_ADDR: Addr = Addr(0x050);
+
+/// Where roms are loaded and execution starts.
+pub const START_ADDR: Addr = Addr(0x200);
+
+/// Largest rom that fits in RAM.
+pub const MAX_ROM_SIZE: usize = RAM_SIZE - START_ADDR.index();
+
+const _: () = assert!(RAM_SIZE == 1 << 12, "addresses are 12 bits");
+const _: () = assert!(crate::FONTSET.len() == FONTSET_SIZE);
+const _: () = assert!(FONT_ADDR.index() + FONTSET_SIZE <= START_ADDR.index());
+const _: () = assert!(START_ADDR.index() < RAM_SIZE);
+const _: () = assert!(START_ADDR.0.is_multiple_of(2), "instructions are aligned");
+
+/// An address in memory.
+///
+/// I and the program counter are 16 bits wide, so an address can point
+/// past the end of RAM. Instructions that read or write there panic.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Addr(u16);
+
+impl Addr {
+    /// `addr`, not checked against the size of RAM.
+    #[must_use]
+    pub const fn new(addr: u16) -> Self {
+        Addr(addr)
+    }
+
+    /// The address as a 16-bit value.
+    #[must_use]
+    pub const fn get(self) -> u16 {
+        self.0
+    }
+
+    /// The address as an index into RAM.
+    #[must_use]
+    pub const fn index(self) -> usize {
+        self.0 as usize
+    }
+
+    /// RAM indices of the `len` bytes starting here.
+    #[must_use]
+    pub const fn range(self, len: usize) -> Range<usize> {
+        self.index()..self.index() + len
+    }
+
+    /// `self + n`, wrapping around at 16 bits like Fx1E.
+    #[must_use]
+    pub const fn wrapping_add(self, n: u16) -> Self {
+        Addr(self.0.wrapping_add(n))
+    }
+}
+
+impl From<u16> for Addr {
+    fn from(addr: u16) -> Self {
+        Addr(addr)
+    }
+}
+
+impl From<Addr> for u16 {
+    fn from(addr: Addr) -> Self {
+        addr.0
+    }
+}
+
+impl Add<u16> for Addr {
+    type Output = Addr;
+
+    fn add(self, n: u16) -> Addr {
+        Addr(self.0 + n)
+    }
+}
+
+impl AddAssign<u16> for Addr {
+    fn add_assign(&mut self, n: u16) {
+        self.0 += n;
+    }
+}
+
+impl SubAssign<u16> for Addr {
+    fn sub_assign(&mut self, n: u16) {
+        self.0 -= n;
+    }
+}
+
+/// Formatted like `0x200`.
+impl fmt::Display for Addr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:#05X}", self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn addr() {
+        let mut pc = START_ADDR;
+        pc += 2;
+        assert_eq!(pc.get(), 0x202);
+        pc -= 2;
+        assert_eq!(pc, START_ADDR);
+        assert_eq!(FONT_ADDR.range(FONTSET_SIZE), 0x50..0xA0);
+        assert_eq!((FONT_ADDR + 5).index(), 0x55);
+        assert_eq!(Addr::new(0xFFFF).wrapping_add(2), Addr::new(1));
+        assert_eq!(START_ADDR.to_string(), "0x200");
+    }
+}
@@ -59,6 +59,17 @@
 //! }
 //! ```
 //!
+//! # API Stability
+//!
+//! [`prelude`] is the surface meant to be stabilized for 1.0: the core,
+//! its settings, the frame clock, and savestates. [`SaveState`](savestate::SaveState)'s
+//! fields are public, the [`rng::Rng`] state among them, so changing
+//! what a savestate holds is a breaking change.
+//!
+//! The other modules are public because the frontends and tools in
+//! this repository use them, and may still change as they are built
+//! out.
+//!
 //! # WASM Compatibility
 //!
 //! ```toml
@@ -84,18 +95,152 @@
 //! vsync. `std::time::{Instant, Duration}` panic in the web so use the
 //! [web-time](https://crates.io/crates/web-time) crate when compiling to
 //! web assembly.
+//!
+//! # Allocations
+//!
+//! Once a rom is loaded, emulating frames with `next_frame`,
+//! `next_frame_with_input`, or `run_cycle`, handling keys, and reading
+//! or packing the screen never allocate, so the core can run on
+//! embedded targets and in tight wasm frame loops. Only errors allocate
+//! their message. Recording movies and a `tracing` subscriber allocate
+//! on their own.
 
-use rand::{Rng, rng, rngs::ThreadRng};
 use std::{fmt, io, time::Duration};
 
+#[cfg(feature = "zip")]
+pub mod archive;
+#[cfg(feature = "audio")]
+pub mod audio;
+#[cfg(feature = "debugger")]
+pub mod breakpoint;
+#[cfg(feature = "octo")]
+pub mod builtin;
+pub mod clock;
+pub mod color;
+pub mod compat;
+#[cfg(feature = "config")]
+pub mod config;
+pub mod delta;
+#[cfg(feature = "movie")]
+pub mod demo;
+pub mod disasm;
+#[cfg(feature = "async")]
+pub mod driver;
+pub mod events;
+pub mod export;
+pub mod filter;
+pub mod flash;
+#[cfg(feature = "movie")]
+pub mod flight;
+pub mod frontend;
+#[cfg(feature = "debugger")]
+pub mod goals;
+pub mod golden;
+pub mod hash;
+pub mod hotkeys;
+pub mod input;
+pub mod keylog;
+pub mod keymap;
+pub mod layout;
+pub mod memmap;
+pub mod minidump;
+#[cfg(feature = "movie")]
+pub mod movie;
+#[cfg(feature = "octo")]
+pub mod octo;
+#[cfg(feature = "osd")]
+pub mod osd;
+#[cfg(feature = "config")]
+pub mod palette;
+#[cfg(feature = "playlist")]
+pub mod playlist;
+pub mod prelude;
+#[cfg(feature = "debugger")]
+pub mod profiler;
+pub mod quirks;
+pub mod repro;
+pub mod reset;
+#[cfg(feature = "debugger")]
+pub mod rewind;
+pub mod rng;
+pub mod rom;
+pub mod rotation;
+pub mod savestate;
+#[cfg(feature = "script")]
+pub mod script;
+pub mod skip;
+pub mod slowdraw;
+pub mod speedrun;
+pub mod states;
+pub mod swarm;
+pub mod sweep;
+pub mod time;
+pub mod triple;
+pub mod variant;
+pub mod video;
+pub mod viewport;
+#[cfg(feature = "debugger")]
+pub mod watch;
+pub mod watchdog;
+pub mod weights;
+pub mod writes;
+
+use input::KeyEdges;
+use layout::{Addr, FONT_ADDR, FONTSET_SIZE, GLYPH_SIZE, MAX_ROM_SIZE, RAM_SIZE, START_ADDR};
+use quirks::{KeyWait, Quirks, Shift};
+use rng::Rng;
+use slowdraw::SpriteBit;
+use variant::Variant;
+use video::ScreenChanges;
+
 /// Standard CPU tick rate set to 700Hz. This value is not used internally.
 /// Run a CPU cycle this often.
 pub const CPU_TICK: Duration = Duration::from_micros(1430);
 
+/// Standard CPU rate, `CPU_TICK` as a frequency. This value is not used
+/// internally.
+pub const CPU_HZ: u32 = 700;
+
 /// Standard TIMER tick rate set to 60Hz. This value is not used internally.
 /// Decrement the timers and refresh the display this often.
 pub const TIMER_TICK: Duration = Duration::from_micros(16667);
 
+/// Rate the timers count down and the display refreshes.
+///
+/// Most software expects 60Hz. Some software written for PAL machines
+/// expects 50Hz and runs too fast otherwise.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TimerRate {
+    /// 60Hz
+    #[default]
+    #[cfg_attr(feature = "serde", serde(rename = "60hz"))]
+    Ntsc,
+    /// 50Hz
+    #[cfg_attr(feature = "serde", serde(rename = "50hz"))]
+    Pal,
+}
+
+impl TimerRate {
+    /// Frequency in Hz.
+    #[must_use]
+    pub fn hz(self) -> u32 {
+        match self {
+            TimerRate::Ntsc => 60,
+            TimerRate::Pal => 50,
+        }
+    }
+
+    /// Time between frames, `TIMER_TICK` at 60Hz.
+    #[must_use]
+    pub fn tick(self) -> Duration {
+        match self {
+            TimerRate::Ntsc => TIMER_TICK,
+            TimerRate::Pal => Duration::from_millis(20),
+        }
+    }
+}
+
 /// Virtual screen width (64 pixels).
 pub const SCREEN_WIDTH: usize = 64;
 
@@ -105,10 +250,11 @@ pub const SCREEN_HEIGHT: usize = 32;
 /// Virtual screen area (2048 pixels).
 pub const SCREEN_AREA: usize = SCREEN_WIDTH * SCREEN_HEIGHT;
 
+/// Default number of cpu cycles emulated by `next_frame` at 60Hz.
+pub const DEFAULT_CYCLES_PER_FRAME: u32 = 10;
+
 // Source for font and constants:
 // https://aquova.net/emudev/chip8/
-const FONTSET_SIZE: usize = 80;
-const FONT_ADDR: u16 = 0x050;
 
 // Some games may behave differently based on the font.
 // This font set is common.
@@ -131,12 +277,10 @@ const FONTSET: [u8; FONTSET_SIZE] = [
     0xF0, 0x80, 0xF0, 0x80, 0x80, // F
 ];
 
-const RAM_SIZE: usize = 4096;
 const NUM_REGS: usize = 16;
 const STACK_SIZE: usize = 16;
 const NUM_KEYS: usize = 16;
 const VF: usize = 15;
-const START_ADDR: u16 = 0x200;
 
 #[derive(Debug)]
 struct Opcode(u8, u8, u8, u8);
@@ -147,18 +291,32 @@ struct Opcode(u8, u8, u8, u8);
 /// Oxid8 Core
 #[derive(Debug)]
 pub struct Oxid8 {
-    pc: u16,                     // Program Counter
-    ram: [u8; RAM_SIZE],         // RAM
-    screen: [bool; SCREEN_AREA], // Monochrome Display
-    v_reg: [u8; NUM_REGS],       // 8-bit V Registers
-    i_reg: u16,                  // 16[12]-bit I Register
-    sp: u16,                     // Stack Pointer
-    stack: [u16; STACK_SIZE],    // Stack
-    keys: [bool; NUM_KEYS],      // Keys (0-F)
-    stored_key: Option<usize>,   // Stored key
-    dt: u8,                      // Delay Timer
-    st: u8,                      // Sound Timer
-    rng: ThreadRng,              // RNG
+    pc: Addr,                             // Program Counter
+    ram: Box<[u8; RAM_SIZE]>,             // RAM
+    screen: Box<[bool; SCREEN_AREA]>,     // Monochrome Display
+    v_reg: [u8; NUM_REGS],                // 8-bit V Registers
+    i_reg: Addr,                          // 16[12]-bit I Register
+    sp: u16,                              // Stack Pointer
+    stack: [Addr; STACK_SIZE],            // Stack
+    keys: [bool; NUM_KEYS],               // Keys (0-F)
+    key_edges: KeyEdges,                  // Key edges this frame
+    stored_key: Option<usize>,            // Stored key
+    dt: u8,                               // Delay Timer
+    st: u8,                               // Sound Timer
+    rng: Rng,                             // RNG
+    quirks: Quirks,                       // Quirks
+    variant: Variant,                     // Dialect
+    cycles_per_frame: u32,                // Speed
+    cycle_remainder: u32,                 // Speed
+    timer_rate: TimerRate,                // Frame rate
+    rom_hash: Option<String>,             // Loaded ROM
+    rom_len: usize,                       // Loaded ROM
+    screen_changes: ScreenChanges,        // Pixels drawn since last taken
+    skip_invalid: bool,                   // Skip invalid opcodes
+    skipped: skip::SkipLog,               // Invalid opcodes skipped
+    ram_writes: writes::RamWrites,        // RAM written since last taken
+    cycle_weights: weights::CycleWeights, // Speed
+    time: time::EmuTime,                  // Emulated time
 }
 
 /// 4-byte opcode.
@@ -178,9 +336,9 @@ impl Opcode {
         (self.0 as u16) << 12 | (self.1 as u16) << 8 | (self.2 as u16) << 4 | (self.3 as u16)
     }
 
-    /// A 12-bit value, the lowest 12 bits of the instruction.
-    fn nnn(&self) -> u16 {
-        (self.1 as u16) << 8 | (self.2 as u16) << 4 | (self.3 as u16)
+    /// A 12-bit address, the lowest 12 bits of the instruction.
+    fn nnn(&self) -> Addr {
+        Addr::new((self.1 as u16) << 8 | (self.2 as u16) << 4 | (self.3 as u16))
     }
 
     /// A 4-bit value, the lowest 4 bits of the instruction.
@@ -218,17 +376,117 @@ impl Oxid8 {
         Oxid8::default()
     }
 
+    /// Create a new oxid8 instance on the heap, for frontends that keep
+    /// it in a struct that moves around. Memory and the display are on
+    /// the heap already, so the core itself is small either way.
+    #[must_use]
+    pub fn boxed() -> Box<Self> {
+        Box::new(Oxid8::default())
+    }
+
     /// Reset all parameters to default.
     /// Must call `load_font` to reload font.
+    ///
+    /// Settings such as `cycles_per_frame`, `cycle_weights`,
+    /// `timer_rate`, `quirks`, and `skip_invalid` are kept. To restart
+    /// the loaded game instead, see [`Oxid8::restart`].
     pub fn reset(&mut self) {
-        *self = Oxid8::default();
+        self.set_screen(&[false; SCREEN_AREA]);
+        *self = Oxid8 {
+            screen_changes: self.screen_changes,
+            cycles_per_frame: self.cycles_per_frame,
+            timer_rate: self.timer_rate,
+            quirks: self.quirks,
+            variant: self.variant,
+            skip_invalid: self.skip_invalid,
+            cycle_weights: std::mem::take(&mut self.cycle_weights),
+            ..Oxid8::default()
+        };
+    }
+
+    /// Number of cpu cycles emulated by `next_frame` at 60Hz.
+    #[must_use]
+    pub fn cycles_per_frame(&self) -> u32 {
+        self.cycles_per_frame
+    }
+
+    /// Sets the number of cpu cycles emulated by `next_frame` at 60Hz.
+    ///
+    /// At 60 frames per second the default of 10 runs the cpu
+    /// at 600Hz. At other timer rates the cycles per frame are
+    /// scaled to keep the same cpu speed.
+    pub fn set_cycles_per_frame(&mut self, cycles: u32) {
+        self.cycles_per_frame = cycles;
+    }
+
+    /// Rate `next_frame` should be called at.
+    #[must_use]
+    pub fn timer_rate(&self) -> TimerRate {
+        self.timer_rate
+    }
+
+    /// Sets the rate `next_frame` should be called at.
+    ///
+    /// Frontends should pace frames with [`TimerRate::tick`].
+    pub fn set_timer_rate(&mut self, rate: TimerRate) {
+        self.timer_rate = rate;
+        self.cycle_remainder = 0;
+    }
+
+    /// The random number generator used by `Cxkk`.
+    ///
+    /// Save it with the rest of the machine state and restore it with
+    /// `set_rng` to get the same random bytes again.
+    #[must_use]
+    pub fn rng(&self) -> &Rng {
+        &self.rng
+    }
+
+    /// Replaces the random number generator, see [`Rng::from_seed`].
+    pub fn set_rng(&mut self, rng: Rng) {
+        self.rng = rng;
+    }
+
+    /// The active interpreter quirks.
+    #[must_use]
+    pub fn quirks(&self) -> Quirks {
+        self.quirks
+    }
+
+    /// Sets the interpreter quirks, see [`Quirks`].
+    pub fn set_quirks(&mut self, quirks: Quirks) {
+        self.quirks = quirks;
+    }
+
+    /// Address of the next instruction.
+    #[must_use]
+    pub fn pc(&self) -> u16 {
+        self.pc.get()
+    }
+
+    /// SHA-1 of the loaded ROM, see [`hash::rom_hash`].
+    ///
+    /// `None` until a ROM is loaded or after `reset`.
+    #[must_use]
+    pub fn rom_hash(&self) -> Option<&str> {
+        self.rom_hash.as_deref()
+    }
+
+    /// Size of the loaded ROM in bytes, 0 until a ROM is loaded or
+    /// after `reset`.
+    #[must_use]
+    pub fn rom_len(&self) -> usize {
+        self.rom_len
     }
 
     /// Emulates a full frame.
     ///
-    /// Each frame emulates 10 cpu cycles and decrements
-    /// the sound and delay timers. If your frame time is
-    /// 60Hz, cpu cycles run at 600Hz and timers at 60Hz.
+    /// Each frame emulates `cycles_per_frame` (default 10)
+    /// cpu cycles and decrements the sound and delay timers.
+    /// If your frame time is 60Hz, cpu cycles run at 600Hz
+    /// and timers at 60Hz. Call it at the rate given by
+    /// `timer_rate`; at 50Hz each frame emulates 6/5 as many
+    /// cycles so the cpu speed stays the same.
     /// CHIP-8 cpu cycles have historically ran anywhere
     /// between 500Hz to 700Hz depending on hardware and
     /// implementation. If you want finer control over
@@ -250,12 +508,7 @@ impl Oxid8 {
     /// perform an invalid action. Otherwise the interpreter
     /// can be left in an invalid state. The rom is bad.
     pub fn next_frame(&mut self) -> Result<(), String> {
-        for _ in 0..10 {
-            self.run_cycle()?;
-        }
-        self.dec_timers();
-
-        Ok(())
+        self.next_frame_with_input(&[])
     }
 
     /// Emulates a single cycle.
@@ -277,28 +530,26 @@ impl Oxid8 {
     /// Other opcodes may panic if the game attempts to
     /// perform an invalid action. Otherwise the interpreter
     /// can be left in an invalid state. The rom is bad.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "trace", skip_all, fields(pc = self.pc.get()))
+    )]
     pub fn run_cycle(&mut self) -> Result<(), String> {
         let opcode = Opcode::new(
-            self.ram[self.pc as usize],     //
-            self.ram[self.pc as usize + 1], //
+            self.ram[self.pc.index()],     //
+            self.ram[self.pc.index() + 1], //
         );
 
         let pc_at_err = self.pc;
         self.pc += 2;
-
-        let invalid = || -> Result<(), String> {
-            Err(format!(
-                "Invalid Instruction: {:04X} at {}",
-                opcode.full(),
-                pc_at_err,
-            ))
-        };
+        self.rng.step();
+        self.time.tick_cycle();
 
         match opcode.0 {
             0x0 => match opcode.kk() {
                 0xE0 => self.cls(),
                 0xEE => self.ret(),
-                _ => invalid()?,
+                _ => self.invalid_opcode(opcode.full(), pc_at_err.get())?,
             },
             0x1 => self.jp_nnn(opcode.nnn()),
             0x2 => self.call(opcode.nnn()),
@@ -317,7 +568,7 @@ impl Oxid8 {
                 0x6 => self.shr(opcode.x() as usize, opcode.y() as usize),
                 0x7 => self.subn_xy(opcode.x() as usize, opcode.y() as usize),
                 0xE => self.shl(opcode.x() as usize, opcode.y() as usize),
-                _ => invalid()?,
+                _ => self.invalid_opcode(opcode.full(), pc_at_err.get())?,
             },
             0x9 => self.sne_xy(opcode.x() as usize, opcode.y() as usize),
             0xA => self.ld_innn(opcode.nnn()),
@@ -333,7 +584,7 @@ impl Oxid8 {
             0xE => match opcode.kk() {
                 0x9E => self.skp(opcode.x() as usize),
                 0xA1 => self.sknp(opcode.x() as usize),
-                _ => invalid()?,
+                _ => self.invalid_opcode(opcode.full(), pc_at_err.get())?,
             },
             0xF => match opcode.kk() {
                 0x07 => self.ld_xdt(opcode.x() as usize),
@@ -345,19 +596,21 @@ impl Oxid8 {
                 0x33 => self.ld_bx(opcode.x() as usize),
                 0x55 => self.ld_ix(opcode.x() as usize),
                 0x65 => self.ld_xi(opcode.x() as usize),
-                _ => invalid()?,
+                _ => self.invalid_opcode(opcode.full(), pc_at_err.get())?,
             },
-            _ => invalid()?,
+            _ => self.invalid_opcode(opcode.full(), pc_at_err.get())?,
         }
 
         Ok(())
     }
 
-    /// Decrements the delay and sound and timers.
+    /// Decrements the delay and sound and timers, and counts a frame of
+    /// [`Oxid8::time`].
     ///
     /// Use `next_frame` instead if you don't want to
     /// control cpu speed.
     pub fn dec_timers(&mut self) {
+        self.time.tick_frame(self.timer_rate);
         if self.dt > 0 {
             self.dt -= 1;
         }
@@ -374,17 +627,32 @@ impl Oxid8 {
 
     /// Sets a key on the virtual keypad.
     ///
+    /// Pressing a held key or releasing a released one does nothing,
+    /// so OS auto-repeat can be passed straight through. Changes are
+    /// recorded as edges, see [`Oxid8::just_pressed`].
+    ///
     /// # Panics
     ///
     /// `set_key` panics if key is out of bounds.
     /// Expects 0x0 - 0xF (0 - 15).
     pub fn set_key(&mut self, k: usize, val: bool) {
-        self.keys[k] = val;
+        if self.keys[k] != val {
+            self.keys[k] = val;
+            self.key_edges.record(k, val);
+        }
+    }
+
+    /// Returns a reference to the virtual keypad, true for held keys.
+    #[must_use]
+    pub fn keys_ref(&self) -> &[bool; NUM_KEYS] {
+        &self.keys
     }
 
     /// Clears the virtual keypad.
     pub fn clear_keys(&mut self) {
-        self.keys = [false; NUM_KEYS];
+        for k in 0..NUM_KEYS {
+            self.set_key(k, false);
+        }
     }
 
     /// Returns a reference to the screen.
@@ -395,38 +663,52 @@ impl Oxid8 {
 
     /// Instructs the interpreter to load the fontset.
     pub fn load_font(&mut self) {
-        self.ram[FONT_ADDR as usize..(FONT_ADDR as usize + FONTSET_SIZE)] //
-            .copy_from_slice(&FONTSET);
+        self.ram[FONT_ADDR.range(FONTSET_SIZE)].copy_from_slice(&FONTSET);
     }
 
-    /// Loads a rom given a filename.
+    /// Loads a rom given a filename, or from standard input if it is
+    /// `-`, see `rom::read`.
+    ///
+    /// With the `zip` feature enabled, the file may also be a zip
+    /// archive containing a single `.ch8` file. With the `octo` feature
+    /// enabled, `.o8` files are assembled, see `octo::assemble`.
     ///
     /// # Errors
     ///
     /// If there is any issue loading the ROM, then an error is returned.
     pub fn load_rom(&mut self, path: impl AsRef<std::path::Path>) -> io::Result<()> {
-        use std::fs;
-
-        let rom_data: Vec<u8> = fs::read(path)?;
-        self.load_rom_bytes(rom_data.as_slice())
+        self.load_rom_bytes(&rom::read(path)?)
     }
 
     /// Loads a rom from byte array.
     ///
+    /// With the `zip` feature enabled, zip archives containing a
+    /// single `.ch8` file are extracted transparently.
+    ///
     /// # Errors
     ///
     /// If there is any issue loading the ROM, then an error is returned.
     pub fn load_rom_bytes(&mut self, rom_data: &[u8]) -> io::Result<()> {
+        #[cfg(feature = "zip")]
+        if archive::is_zip(rom_data) {
+            return self.load_rom_bytes(&archive::extract_rom(rom_data)?);
+        }
+
         let len = rom_data.len();
-        if len > (RAM_SIZE - START_ADDR as usize) {
+        if len > MAX_ROM_SIZE {
             return Err(io::Error::new(
                 io::ErrorKind::FileTooLarge,
                 format!("ROM too large: {}", len),
             ));
         }
 
-        self.ram[START_ADDR as usize..(START_ADDR as usize + len)] //
-            .copy_from_slice(rom_data);
+        self.ram[START_ADDR.range(len)].copy_from_slice(rom_data);
+        self.rom_hash = Some(hash::rom_hash(rom_data));
+        self.rom_len = len;
+        self.skipped.clear();
+
+        #[cfg(feature = "tracing")]
+        tracing::info!(len, hash = self.rom_hash, "Loaded rom");
 
         Ok(())
     }
@@ -436,7 +718,7 @@ impl Oxid8 {
     /// # Panics
     ///
     /// `push` panics if the stack overflows.
-    fn push(&mut self, val: u16) {
+    fn push(&mut self, val: Addr) {
         match self.sp as usize {
             0..STACK_SIZE => {
                 self.stack[self.sp as usize] = val;
@@ -451,7 +733,7 @@ impl Oxid8 {
     /// # Panics
     ///
     /// `pop` panics if the stack underflows.
-    fn pop(&mut self) -> u16 {
+    fn pop(&mut self) -> Addr {
         match self.sp as usize {
             1..=STACK_SIZE => {
                 self.sp -= 1;
@@ -466,21 +748,43 @@ impl Default for Oxid8 {
     fn default() -> Self {
         Self {
             pc: START_ADDR,
-            ram: [0; RAM_SIZE],
-            screen: [false; SCREEN_WIDTH * SCREEN_HEIGHT],
+            ram: Box::new([0; RAM_SIZE]),
+            screen: Box::new([false; SCREEN_AREA]),
             v_reg: [0; NUM_REGS],
-            i_reg: 0,
+            i_reg: Addr::default(),
             sp: 0,
-            stack: [0; STACK_SIZE],
+            stack: [Addr::default(); STACK_SIZE],
             keys: [false; NUM_KEYS],
+            key_edges: KeyEdges::default(),
             stored_key: None,
             dt: 0,
             st: 0,
-            rng: rng(),
+            rng: Rng::default(),
+            quirks: Quirks::default(),
+            variant: Variant::default(),
+            cycles_per_frame: DEFAULT_CYCLES_PER_FRAME,
+            cycle_remainder: 0,
+            timer_rate: TimerRate::default(),
+            rom_hash: None,
+            rom_len: 0,
+            screen_changes: ScreenChanges::default(),
+            skip_invalid: false,
+            skipped: skip::SkipLog::default(),
+            ram_writes: writes::RamWrites::default(),
+            cycle_weights: weights::CycleWeights::default(),
+            time: time::EmuTime::default(),
         }
     }
 }
 
+/// Error for an invalid opcode, kept out of line so the formatting
+/// code stays out of the instruction loop.
+#[cold]
+#[inline(never)]
+fn invalid_instruction(opcode: u16, pc: u16) -> String {
+    format!("Invalid Instruction: {opcode:04X} at {pc}")
+}
+
 // Cowgod's Chip-8 Technical Reference v1.0:
 // http://devernay.free.fr/hacks/chip8/C8TECH10.HTM#0.1
 
@@ -497,7 +801,7 @@ impl Default for Oxid8 {
 impl Oxid8 {
     /// 00E0 - Clear the display.
     fn cls(&mut self) {
-        self.screen = [false; SCREEN_WIDTH * SCREEN_HEIGHT];
+        self.set_screen(&[false; SCREEN_AREA]);
     }
 
     /// 00EE - Return from a subroutine.
@@ -506,12 +810,12 @@ impl Oxid8 {
     }
 
     /// 1nnn - Jump to location nnn.
-    fn jp_nnn(&mut self, nnn: u16) {
+    fn jp_nnn(&mut self, nnn: Addr) {
         self.pc = nnn;
     }
 
     /// 2nnn - Call subroutine at nnn.
-    fn call(&mut self, nnn: u16) {
+    fn call(&mut self, nnn: Addr) {
         self.push(self.pc);
         self.pc = nnn;
     }
@@ -582,8 +886,10 @@ impl Oxid8 {
     }
 
     /// 8xy6 - Set Vx = Vx SHR 1.
-    fn shr(&mut self, x: usize, _y: usize) {
-        let vx = self.v_reg[x];
+    ///
+    /// Shifts Vy instead under [`Shift::Vy`].
+    fn shr(&mut self, x: usize, y: usize) {
+        let vx = self.v_reg[self.shifted(x, y)];
         self.v_reg[x] = vx >> 1;
         self.v_reg[VF] = vx & 1;
     }
@@ -596,12 +902,22 @@ impl Oxid8 {
     }
 
     /// 8xyE - Set Vx = Vx SHL 1.
-    fn shl(&mut self, x: usize, _y: usize) {
-        let vx = self.v_reg[x];
+    ///
+    /// Shifts Vy instead under [`Shift::Vy`].
+    fn shl(&mut self, x: usize, y: usize) {
+        let vx = self.v_reg[self.shifted(x, y)];
         self.v_reg[x] = vx << 1;
         self.v_reg[VF] = (vx >> 7) & 1;
     }
 
+    /// The register `8xy6` and `8xyE` shift.
+    fn shifted(&self, x: usize, y: usize) -> usize {
+        match self.quirks.shift {
+            Shift::Vx => x,
+            Shift::Vy => y,
+        }
+    }
+
     /// 9xy0 - Skip next instruction if Vx != Vy.
     fn sne_xy(&mut self, x: usize, y: usize) {
         if self.v_reg[x] != self.v_reg[y] {
@@ -610,58 +926,78 @@ impl Oxid8 {
     }
 
     /// Annn - Set I = nnn.
-    fn ld_innn(&mut self, nnn: u16) {
+    fn ld_innn(&mut self, nnn: Addr) {
         self.i_reg = nnn;
     }
 
     /// Bnnn - Jump to location nnn + V0.
-    fn jp_0nnn(&mut self, nnn: u16) {
-        self.pc = nnn + self.v_reg[0] as u16;
+    ///
+    /// Jumps to xnn + Vx instead under [`Quirks::jump_vx`].
+    fn jp_0nnn(&mut self, nnn: Addr) {
+        let x = match self.quirks.jump_vx {
+            true => (nnn.get() >> 8) as usize,
+            false => 0,
+        };
+        self.pc = nnn + u16::from(self.v_reg[x]);
     }
 
     /// Cxkk - Set Vx = random byte AND kk.
     fn rnd(&mut self, x: usize, kk: u8) {
-        self.v_reg[x] = self.rng.random_range(0..=0xFF) as u8 & kk;
+        self.v_reg[x] = self.rng.byte(self.quirks.rng) & kk;
     }
 
     /// Dxyn - Display n-byte sprite starting at memory location I at (Vx, Vy),
     /// set VF = collision.
     fn drw(&mut self, x: usize, y: usize, n: u8) {
-        // a sprite is a byte wide and n in [1,15] rows where n is an integer
-        let (x, y) = (
-            self.v_reg[x] as usize % SCREEN_WIDTH,  // wrap
-            self.v_reg[y] as usize % SCREEN_HEIGHT, // wrap
-        );
+        // Vx and Vy are read before VF is cleared.
+        let bits = self.sprite_bits(x, y, n);
         self.v_reg[VF] = 0; // turn off collision flag
-        let start_pixel: usize = (y * SCREEN_WIDTH) + x;
-        let start_addr: usize = self.i_reg as usize;
 
-        // draw n bytes to the screen
-        for i in 0..n as usize {
-            if y + i >= SCREEN_HEIGHT {
-                break; // clip
+        for bit in bits {
+            if !bit.set {
+                continue;
             }
-            let pixel_posn: usize = start_pixel + (SCREEN_WIDTH * i);
-            let sprite_row: u8 = self.ram[start_addr + i];
-
-            // for each bit
-            for j in 0..8 {
-                if x + j >= SCREEN_WIDTH {
-                    break; // clip
-                }
-                let ref mut pixel_ref = self.screen[pixel_posn + j];
-                let old_pixel = *pixel_ref;
-
-                let sprite_pixel = (sprite_row >> (0x7 - j)) & 0x1;
-                *pixel_ref ^= sprite_pixel != 0;
-
-                if !(*pixel_ref) && old_pixel {
-                    self.v_reg[VF] = 1; // turn on collision flag
-                }
+            let pixel = &mut self.screen[bit.y * SCREEN_WIDTH + bit.x];
+            if *pixel {
+                self.v_reg[VF] = 1; // turn on collision flag
             }
+            *pixel ^= true;
+            self.screen_changes.mark(bit.x, bit.y);
         }
     }
 
+    /// The bits of an `n` row sprite at Vx, Vy in the order Dxyn XORs
+    /// them onto the screen, without those clipped off the edges.
+    ///
+    /// A sprite is a byte wide and up to 15 rows tall. Its top left
+    /// always wraps onto the screen, the rest wraps or clips by the
+    /// quirks.
+    fn sprite_bits(&self, x: usize, y: usize, n: u8) -> impl Iterator<Item = SpriteBit> + use<> {
+        let (x, y) = (
+            self.v_reg[x] as usize % SCREEN_WIDTH,
+            self.v_reg[y] as usize % SCREEN_HEIGHT,
+        );
+        let Quirks { wrap_x, wrap_y, .. } = self.quirks;
+        let rows = match wrap_y {
+            true => n as usize,
+            false => (n as usize).min(SCREEN_HEIGHT - y),
+        };
+        let mut sprite = [0u8; 15];
+        sprite[..rows].copy_from_slice(&self.ram[self.i_reg.range(rows)]);
+
+        (0..rows).flat_map(move |row| {
+            (0..8)
+                .take_while(move |bit| wrap_x || x + bit < SCREEN_WIDTH)
+                .map(move |bit| SpriteBit {
+                    row,
+                    bit,
+                    x: (x + bit) % SCREEN_WIDTH,
+                    y: (y + row) % SCREEN_HEIGHT,
+                    set: (sprite[row] >> (7 - bit)) & 1 != 0,
+                })
+        })
+    }
+
     /// Ex9E - Skip next instruction if key with the value of Vx is pressed.
     fn skp(&mut self, x: usize) {
         if self.keys[self.v_reg[x] as usize] {
@@ -682,7 +1018,21 @@ impl Oxid8 {
     }
 
     /// Fx0A - Wait for a key press, store the value of the key in Vx.
+    ///
+    /// Waits for the key to be released too under [`KeyWait::Release`].
     fn ld_xk(&mut self, x: usize) {
+        if self.quirks.key_wait == KeyWait::Press {
+            // Any key down, including taps released before this cycle.
+            match (0..NUM_KEYS).find(|&k| self.keys[k] || self.just_pressed(k)) {
+                Some(k) => {
+                    // A tap resolves one wait.
+                    self.key_edges.take_pressed(k);
+                    self.v_reg[x] = k as u8;
+                }
+                None => self.pc -= 2,
+            }
+            return;
+        }
         match self.stored_key {
             Some(k) => {
                 // Wait for key release
@@ -693,12 +1043,11 @@ impl Oxid8 {
                 }
             }
             None => {
-                // Store key press
-                for (k, &pressed) in self.keys.iter().enumerate() {
-                    if pressed {
-                        self.stored_key = Some(k);
-                        break;
-                    }
+                // Store key press, including taps released before
+                // this cycle. A tap resolves one wait.
+                self.stored_key = (0..NUM_KEYS).find(|&k| self.keys[k] || self.just_pressed(k));
+                if let Some(k) = self.stored_key {
+                    self.key_edges.take_pressed(k);
                 }
             }
         }
@@ -717,34 +1066,40 @@ impl Oxid8 {
     }
 
     /// Fx1E - Set I = I + Vx.
+    ///
+    /// Sets VF = 1 if I goes past 0xFFF under [`Quirks::i_overflow`].
     fn add_ix(&mut self, x: usize) {
-        self.i_reg = self.i_reg.wrapping_add(self.v_reg[x] as u16);
+        let sum = u32::from(self.i_reg.get()) + u32::from(self.v_reg[x]);
+        self.i_reg = self.i_reg.wrapping_add(u16::from(self.v_reg[x]));
+        if self.quirks.i_overflow {
+            self.v_reg[VF] = (sum > 0xFFF) as u8;
+        }
     }
 
     /// Fx29 - Set I = location of sprite for digit Vx.
     fn ld_fx(&mut self, x: usize) {
-        self.i_reg = FONT_ADDR + (self.v_reg[x] as u16 * 5);
+        self.i_reg = FONT_ADDR + u16::from(self.v_reg[x]) * GLYPH_SIZE as u16;
     }
 
     /// Fx33 - Store BCD representation of Vx in memory locations I, I+1, and I+2.
     fn ld_bx(&mut self, x: usize) {
-        let i = self.i_reg as usize;
+        let i = self.i_reg.index();
         let v = self.v_reg[x];
         self.ram[i] = (v / 100) % 10;
         self.ram[i + 1] = (v / 10) % 10;
         self.ram[i + 2] = v % 10;
+        self.ram_writes.mark(i..i + 3);
     }
 
     /// Fx55 - Store registers V0 through Vx in memory starting at location I.
     fn ld_ix(&mut self, x: usize) {
-        let i = self.i_reg as usize;
-        self.ram[i..=(i + x)].copy_from_slice(&self.v_reg[0..=x]);
+        self.ram[self.i_reg.range(x + 1)].copy_from_slice(&self.v_reg[0..=x]);
+        self.ram_writes.mark(self.i_reg.range(x + 1));
     }
 
     /// Fx65 - Read registers V0 through Vx from memory starting at location I.
     fn ld_xi(&mut self, x: usize) {
-        let i = self.i_reg as usize;
-        self.v_reg[0..=x].copy_from_slice(&self.ram[i..=(i + x)]);
+        self.v_reg[0..=x].copy_from_slice(&self.ram[self.i_reg.range(x + 1)]);
     }
 }
 
@@ -761,6 +1116,15 @@ mod tests {
         assert_eq!(155, a[i as usize + 1]);
     }
 
+    #[test]
+    fn small_core() {
+        // Memory and the display are boxed, so moving the core,
+        // `reset`, and savestates don't copy kilobytes on the stack.
+        assert!(std::mem::size_of::<Oxid8>() <= 1024);
+        let emu = Oxid8::boxed();
+        assert_eq!(emu.pc(), 0x200);
+    }
+
     #[test]
     fn opcode_new() {
         let opcode = Opcode::new(0x12, 0x34);
@@ -774,7 +1138,7 @@ mod tests {
     fn opcode_decode() {
         let opcode = Opcode::new(0x12, 0x34);
         assert_eq!(opcode.full(), 0x1234);
-        assert_eq!(opcode.nnn(), 0x234);
+        assert_eq!(opcode.nnn(), Addr::new(0x234));
         assert_eq!(opcode.n(), 0x4);
         assert_eq!(opcode.x(), 0x2);
         assert_eq!(opcode.y(), 0x3);
@@ -784,12 +1148,12 @@ mod tests {
     #[test]
     fn invalid_opcode() {
         let mut emu = Oxid8::new();
-        emu.ram[START_ADDR as usize] = 0xFF;
-        emu.ram[START_ADDR as usize + 1] = 0xFF;
+        emu.ram[START_ADDR.index()] = 0xFF;
+        emu.ram[START_ADDR.index() + 1] = 0xFF;
         assert!(emu.run_cycle().is_err_and(|msg| msg
             == format!(
                 "Invalid Instruction: FFFF at {}", //
-                START_ADDR                         //
+                START_ADDR.get()                   //
             )))
     }
 
@@ -797,10 +1161,10 @@ mod tests {
     fn push_pop() {
         let mut emu = Oxid8::new();
         assert_eq!(emu.sp, 0); // base stack pointer
-        emu.push(1); // push
+        emu.push(Addr::new(1)); // push
         assert_eq!(emu.sp, 1); // inc stack pointer
-        assert_eq!(emu.stack[0], 1); // value on stack
-        assert_eq!(emu.pop(), 1); // pop
+        assert_eq!(emu.stack[0], Addr::new(1)); // value on stack
+        assert_eq!(emu.pop(), Addr::new(1)); // pop
         assert_eq!(emu.sp, 0); // dec stack pointer
     }
 
@@ -809,7 +1173,7 @@ mod tests {
     fn push_panic() {
         let mut emu = Oxid8::new();
         for _ in 0..=STACK_SIZE {
-            emu.push(1);
+            emu.push(Addr::new(1));
         }
     }
 
@@ -820,14 +1184,100 @@ mod tests {
         emu.pop();
     }
 
+    /// Runs `Cxkk` into every register and returns them.
+    fn random_regs(emu: &mut Oxid8) -> [u8; NUM_REGS] {
+        emu.pc = START_ADDR;
+        for x in 0..NUM_REGS {
+            let addr = START_ADDR.index() + x * 2;
+            emu.ram[addr] = 0xC0 | x as u8;
+            emu.ram[addr + 1] = 0xFF;
+            emu.run_cycle().unwrap();
+        }
+        emu.v_reg
+    }
+
+    #[test]
+    fn rnd_uniform() {
+        let mut emu = Oxid8::new();
+        let mut seen = [false; 256];
+        for _ in 0..10_000 {
+            emu.rnd(0, 0xFF);
+            seen[emu.v_reg[0] as usize] = true;
+        }
+        assert!(seen.iter().all(|&b| b));
+    }
+
+    #[test]
+    fn rnd_lfsr() {
+        let lfsr = Quirks {
+            rng: quirks::RngMode::Lfsr,
+            ..Quirks::default()
+        };
+        let mut a = Oxid8::new();
+        let mut b = Oxid8::new();
+        a.set_quirks(lfsr);
+        b.set_quirks(lfsr);
+
+        // Same sequence on every boot.
+        let regs = random_regs(&mut a);
+        assert_eq!(regs, random_regs(&mut b));
+        assert_ne!(regs, [regs[0]; NUM_REGS]);
+
+        // Values depend on when `Cxkk` runs.
+        a.reset();
+        a.set_quirks(lfsr);
+        a.rng.step();
+        assert_ne!(random_regs(&mut a), regs);
+
+        // kk masks the byte.
+        b.reset();
+        b.rnd(0, 0x0F);
+        assert_eq!(b.v_reg[0], rng::Lfsr::default().byte() & 0x0F);
+    }
+
+    #[test]
+    fn timer_rate_budget() {
+        // 7001 - ADD V0, 1 counts cycles.
+        let mut emu = Oxid8::new();
+        emu.load_rom_bytes(&[0x70, 0x01].repeat(100)).unwrap();
+        emu.next_frame().unwrap();
+        assert_eq!(emu.v_reg[0], 10);
+
+        // 7 cycles at 60Hz is 8.4 at 50Hz.
+        emu.reset();
+        emu.load_rom_bytes(&[0x70, 0x01].repeat(100)).unwrap();
+        emu.set_cycles_per_frame(7);
+        emu.set_timer_rate(TimerRate::Pal);
+        emu.next_frame().unwrap();
+        assert_eq!(emu.v_reg[0], 8);
+        for _ in 0..4 {
+            emu.next_frame().unwrap();
+        }
+        assert_eq!(emu.v_reg[0], 42);
+        assert_eq!(TimerRate::Pal.tick(), Duration::from_millis(20));
+    }
+
+    #[test]
+    fn rnd_seeded() {
+        let mut a = Oxid8::new();
+        let mut b = Oxid8::new();
+        a.set_rng(Rng::from_seed(1));
+        b.set_rng(Rng::from_seed(1));
+        assert_eq!(random_regs(&mut a), random_regs(&mut b));
+
+        // Restoring the rng repeats the same bytes.
+        let saved = a.rng().clone();
+        let regs = random_regs(&mut a);
+        a.reset();
+        a.set_rng(saved);
+        assert_eq!(random_regs(&mut a), regs);
+    }
+
     #[test]
     fn load_font() {
         let mut emu = Oxid8::new();
         emu.load_font();
-        assert_eq!(
-            emu.ram[FONT_ADDR as usize..(FONT_ADDR as usize + FONTSET_SIZE)],
-            FONTSET
-        );
+        assert_eq!(emu.ram[FONT_ADDR.range(FONTSET_SIZE)], FONTSET);
     }
 
     #[test]
@@ -862,7 +1312,7 @@ mod tests {
         let mut emu = Oxid8::new();
 
         emu.i_reg = START_ADDR;
-        let start = START_ADDR as usize;
+        let start = START_ADDR.index();
 
         emu.ram[start..start + sprite.len()].copy_from_slice(&sprite);
         emu.drw(0, 0, sprite.len() as u8);
@@ -871,9 +1321,87 @@ mod tests {
             let offset1: usize = i * SCREEN_WIDTH;
             let offset2: usize = i * 8;
             assert_eq!(
-                emu.screen[offset1 + 0..offset1 + 8],
-                screen[offset2 + 0..offset2 + 8]
+                emu.screen[offset1..offset1 + 8],
+                screen[offset2..offset2 + 8]
             );
         }
     }
+
+    /// Draws an 8x4 solid sprite at `x`, `y` and returns the lit pixels.
+    fn draw_block(wrap_x: bool, wrap_y: bool, x: u8, y: u8) -> Vec<(usize, usize)> {
+        let mut emu = Oxid8::new();
+        emu.set_quirks(Quirks {
+            wrap_x,
+            wrap_y,
+            ..Quirks::default()
+        });
+        emu.i_reg = START_ADDR;
+        let start = START_ADDR.index();
+        emu.ram[start..start + 4].fill(0xFF);
+        emu.v_reg[0] = x;
+        emu.v_reg[1] = y;
+        emu.drw(0, 1, 4);
+
+        (0..SCREEN_AREA)
+            .filter(|&i| emu.screen[i])
+            .map(|i| (i % SCREEN_WIDTH, i / SCREEN_WIDTH))
+            .collect()
+    }
+
+    #[test]
+    fn draw_clip() {
+        let lit = draw_block(false, false, 60, 30);
+        assert_eq!(lit.len(), 4 * 2);
+        assert!(lit.iter().all(|&(x, y)| x >= 60 && y >= 30));
+    }
+
+    #[test]
+    fn draw_wrap_x() {
+        let lit = draw_block(true, false, 60, 30);
+        assert_eq!(lit.len(), 8 * 2);
+        assert!(lit.contains(&(0, 30)) && lit.contains(&(3, 31)));
+        assert!(!lit.contains(&(4, 30)) && !lit.contains(&(60, 0)));
+    }
+
+    #[test]
+    fn draw_wrap_y() {
+        let lit = draw_block(false, true, 60, 30);
+        assert_eq!(lit.len(), 4 * 4);
+        assert!(lit.contains(&(60, 0)) && lit.contains(&(63, 1)));
+        assert!(!lit.contains(&(60, 2)) && !lit.contains(&(0, 30)));
+    }
+
+    #[test]
+    fn draw_wrap_both() {
+        // Crosses both edges, so a piece lands in every corner.
+        let lit = draw_block(true, true, 60, 30);
+        assert_eq!(lit.len(), 8 * 4);
+        for corner in [(63, 31), (0, 31), (63, 0), (0, 0)] {
+            assert!(lit.contains(&corner), "{corner:?}");
+        }
+        assert!(!lit.contains(&(4, 0)) && !lit.contains(&(0, 2)));
+    }
+
+    #[test]
+    fn draw_wrap_collision() {
+        let mut emu = Oxid8::new();
+        emu.set_quirks(Quirks {
+            wrap_x: true,
+            wrap_y: true,
+            ..Quirks::default()
+        });
+        emu.i_reg = START_ADDR;
+        emu.ram[START_ADDR.index()] = 0xFF;
+        // Start coordinates wrap regardless of the quirks.
+        emu.v_reg[0] = 64 + 60;
+        emu.v_reg[1] = 31;
+        emu.drw(0, 1, 1);
+        assert_eq!(emu.v_reg[VF], 0);
+
+        // Only the wrapped pixels overlap.
+        emu.v_reg[0] = 0;
+        emu.drw(0, 1, 1);
+        assert_eq!(emu.v_reg[VF], 1);
+        assert!(!emu.screen[31 * SCREEN_WIDTH] && emu.screen[31 * SCREEN_WIDTH + 4]);
+    }
 }
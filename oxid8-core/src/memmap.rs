@@ -0,0 +1,215 @@
+//! What each part of the 4KB address space holds.
+//!
+//! A [`MemoryMap`] sorts every address into a [`Region`]: the font,
+//! the rest of the interpreter's area, the loaded rom, data the program
+//! has written, and free memory. It is updated with the [`RamWrites`]
+//! taken from the core, so rom developers can watch their memory
+//! footprint grow as the game runs. [`MemoryMap::bar`] squeezes the
+//! map into a bar of cells for drawing. The stack lives outside RAM
+//! here, so its depth is tracked beside the map.
+//!
+//! ```
+//! use oxid8_core::{Oxid8, memmap::{MemoryMap, Region}};
+//!
+//! let mut emu = Oxid8::new();
+//! // LD I, 300; LD [I], V1
+//! emu.load_rom_bytes(&[0xA3, 0x00, 0xF1, 0x55]).unwrap();
+//! let mut map = MemoryMap::new(&emu);
+//! assert_eq!(map.region(0x202), Region::Rom);
+//!
+//! emu.run_cycle().unwrap();
+//! emu.run_cycle().unwrap();
+//! let writes = emu.take_ram_writes();
+//! map.update(&emu, &writes);
+//! assert_eq!(map.region(0x301), Region::Data);
+//! assert_eq!(map.bytes(Region::Data), 2);
+//! assert_eq!(map.bytes(Region::Rom), 4);
+//! ```
+//!
+//! [`RamWrites`]: crate::writes::RamWrites
+
+use std::fmt;
+
+use crate::{
+    Oxid8, STACK_SIZE,
+    layout::{FONT_ADDR, FONTSET_SIZE, RAM_SIZE, START_ADDR},
+    writes::RamWrites,
+};
+
+/// Calls the stack holds.
+pub const STACK_DEPTH: usize = STACK_SIZE;
+
+/// What an address holds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Region {
+    /// Never loaded or written.
+    Free,
+    /// Below the program, the interpreter itself on the COSMAC VIP.
+    Interpreter,
+    /// The built-in font.
+    Font,
+    /// Loaded from the rom and not written since.
+    Rom,
+    /// Written by the program, in the rom or outside it.
+    Data,
+}
+
+/// Every region, in the order they are listed in.
+pub const REGIONS: [Region; 5] = [
+    Region::Interpreter,
+    Region::Font,
+    Region::Rom,
+    Region::Data,
+    Region::Free,
+];
+
+/// Formatted like "interpreter".
+impl fmt::Display for Region {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Region::Free => "free",
+            Region::Interpreter => "interpreter",
+            Region::Font => "font",
+            Region::Rom => "rom",
+            Region::Data => "data",
+        })
+    }
+}
+
+/// The regions of RAM, and the stack depth, as of the last update.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MemoryMap {
+    /// End of the loaded rom.
+    rom_end: usize,
+    written: RamWrites,
+    /// Calls on the stack at the last update.
+    stack: usize,
+    /// Most calls on the stack at any update.
+    deepest: usize,
+}
+
+impl MemoryMap {
+    /// The map of `core` as loaded, before the program has written
+    /// anything.
+    #[must_use]
+    pub fn new(core: &Oxid8) -> Self {
+        let stack = core.sp as usize;
+        MemoryMap {
+            rom_end: START_ADDR.index() + core.rom_len,
+            written: RamWrites::default(),
+            stack,
+            deepest: stack,
+        }
+    }
+
+    /// Adds `writes` taken from `core` and samples its stack.
+    pub fn update(&mut self, core: &Oxid8, writes: &RamWrites) {
+        self.written.merge(writes);
+        self.stack = core.sp as usize;
+        self.deepest = self.deepest.max(self.stack);
+    }
+
+    /// What `addr` holds.
+    #[must_use]
+    pub fn region(&self, addr: u16) -> Region {
+        let addr = addr as usize;
+        let font = FONT_ADDR.index()..FONT_ADDR.index() + FONTSET_SIZE;
+        match addr {
+            _ if self.written.contains(addr as u16) => Region::Data,
+            _ if font.contains(&addr) => Region::Font,
+            _ if addr < START_ADDR.index() => Region::Interpreter,
+            _ if addr < self.rom_end => Region::Rom,
+            _ => Region::Free,
+        }
+    }
+
+    /// Bytes of RAM in `region`.
+    #[must_use]
+    pub fn bytes(&self, region: Region) -> usize {
+        (0..RAM_SIZE as u16)
+            .filter(|&addr| self.region(addr) == region)
+            .count()
+    }
+
+    /// RAM split into `cells` equal spans, each shown as the region
+    /// most worth seeing in it: data over rom over font over the
+    /// interpreter over free memory.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `cells` is 0.
+    #[must_use]
+    pub fn bar(&self, cells: usize) -> Vec<Region> {
+        assert!(cells > 0, "a bar has cells");
+        (0..cells)
+            .map(|cell| {
+                let (start, end) = (cell * RAM_SIZE / cells, (cell + 1) * RAM_SIZE / cells);
+                (start..end.max(start + 1))
+                    .map(|addr| self.region(addr as u16))
+                    .max()
+                    .unwrap_or(Region::Free)
+            })
+            .collect()
+    }
+
+    /// Calls on the stack at the last update.
+    #[must_use]
+    pub fn stack(&self) -> usize {
+        self.stack
+    }
+
+    /// Most calls on the stack at any update, calls made and returned
+    /// from between updates aren't seen.
+    #[must_use]
+    pub fn deepest(&self) -> usize {
+        self.deepest
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn regions_and_bar() {
+        let mut emu = Oxid8::new();
+        emu.load_font();
+        emu.load_rom_bytes(&[0; 0x100]).unwrap();
+        let map = MemoryMap::new(&emu);
+        assert_eq!(map.region(0x000), Region::Interpreter);
+        assert_eq!(map.region(0x050), Region::Font);
+        assert_eq!(map.region(0x2FF), Region::Rom);
+        assert_eq!(map.region(0x300), Region::Free);
+        assert_eq!(map.region(0x1000), Region::Free);
+        assert_eq!(map.bytes(Region::Font), FONTSET_SIZE);
+        assert_eq!(
+            REGIONS.iter().map(|&r| map.bytes(r)).sum::<usize>(),
+            RAM_SIZE
+        );
+
+        // 256 bytes a cell.
+        let bar = map.bar(16);
+        assert_eq!(bar[0], Region::Font);
+        assert_eq!(bar[1], Region::Interpreter);
+        assert_eq!(bar[2], Region::Rom);
+        assert!(bar[3..].iter().all(|&r| r == Region::Free));
+        // More cells than bytes.
+        assert_eq!(map.bar(RAM_SIZE * 2).len(), RAM_SIZE * 2);
+    }
+
+    #[test]
+    fn stack() {
+        let mut emu = Oxid8::new();
+        // CALL 204; RET (unreached); RET
+        emu.load_rom_bytes(&[0x22, 0x04, 0x00, 0xEE, 0x00, 0xEE])
+            .unwrap();
+        let mut map = MemoryMap::new(&emu);
+        emu.run_cycle().unwrap();
+        let writes = emu.take_ram_writes();
+        map.update(&emu, &writes);
+        emu.run_cycle().unwrap();
+        let writes = emu.take_ram_writes();
+        map.update(&emu, &writes);
+        assert_eq!((map.stack(), map.deepest()), (0, 1));
+    }
+}
@@ -0,0 +1,212 @@
+//! Crash dumps for bug reports.
+//!
+//! When a frame fails, [`Minidump::capture`] runs it again from the
+//! savestate taken at its start, keeping the last [`TRACE_LEN`]
+//! instructions like a [golden trace](crate::golden). [`Minidump::write`]
+//! saves everything needed to reproduce the failure in a new directory:
+//!
+//! - `crash.txt`: the error, the rom hash, the [`Profile`] and cycle
+//!   weights the core ran with, and the frame's input events
+//! - `state.bin`: the savestate from the start of the frame, see
+//!   [`SaveState::to_bytes`], with the uniform rng left out
+//! - `trace.csv`: the instructions leading up to the error, the one
+//!   that failed last
+//!
+//! ```
+//! use oxid8_core::{Oxid8, minidump::Minidump};
+//!
+//! let mut emu = Oxid8::new();
+//! // ADD V0, 1; then an invalid instruction
+//! emu.load_rom_bytes(&[0x70, 0x01, 0xFF, 0xFF]).unwrap();
+//!
+//! let start = emu.save_state();
+//! let err = emu.next_frame().unwrap_err();
+//! let dump = Minidump::capture(&mut emu, &start, &[], &err);
+//! assert_eq!(dump.trace.len(), 2);
+//! assert!(dump.trace.to_csv().ends_with("\n0202,ffff,0000,00,01,00,00,00,00,00,00,00,00,00,00,00,00,00,00,00,00,00\n"));
+//! ```
+
+use std::{
+    cell::RefCell,
+    collections::VecDeque,
+    fmt::Write,
+    fs, io,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::{
+    Oxid8,
+    golden::{Field, Trace},
+    input::InputEvent,
+    savestate::SaveState,
+    variant::Profile,
+    weights::CycleWeights,
+};
+
+/// Instructions kept in a dump's trace.
+pub const TRACE_LEN: usize = 256;
+
+/// Everything needed to reproduce a failed frame.
+#[derive(Debug, Clone)]
+pub struct Minidump {
+    pub error: String,
+    pub profile: Profile,
+    pub cycle_weights: CycleWeights,
+    /// The state at the start of the frame.
+    pub start: SaveState,
+    /// The frame's input.
+    pub events: Vec<InputEvent>,
+    /// The last instructions of the frame, with every [`Field`].
+    pub trace: Trace,
+}
+
+impl Minidump {
+    /// Runs the frame that failed with `error` again on `core`, from
+    /// `start` with `events`. `core` is left where the frame stopped.
+    #[must_use]
+    pub fn capture(
+        core: &mut Oxid8,
+        start: &SaveState,
+        events: &[InputEvent],
+        error: &str,
+    ) -> Self {
+        core.restore_state(start);
+        let rows = RefCell::new(VecDeque::with_capacity(TRACE_LEN));
+        // Fails again at the same instruction, or not at all if the
+        // error came from outside the frame.
+        let _ = core.run_frame(events, |core| {
+            let mut rows = rows.borrow_mut();
+            if rows.len() == TRACE_LEN {
+                rows.pop_front();
+            }
+            rows.push_back(Field::ALL.iter().map(|field| field.read(core)).collect());
+            false
+        });
+        Minidump {
+            error: error.to_owned(),
+            profile: core.profile(),
+            cycle_weights: core.cycle_weights().clone(),
+            start: start.clone(),
+            events: events.to_vec(),
+            trace: Trace::from_rows(&Field::ALL, rows.into_inner().into()),
+        }
+    }
+
+    /// The contents of `crash.txt`, see the [module docs](self).
+    #[must_use]
+    pub fn summary(&self) -> String {
+        let mut out = String::new();
+        // Writing to a String cannot fail.
+        let _ = writeln!(out, "error: {}", self.error);
+        let _ = writeln!(
+            out,
+            "rom: {}",
+            self.start.rom_hash.as_deref().unwrap_or("unknown")
+        );
+        let _ = writeln!(out, "profile: {}", self.profile);
+        if !self.cycle_weights.is_empty() {
+            let _ = writeln!(out, "weights: {}", self.cycle_weights);
+        }
+        let events: Vec<String> = self.events.iter().map(InputEvent::to_string).collect();
+        let _ = writeln!(out, "events: {}", events.join(" "));
+        out
+    }
+
+    /// Writes the dump to a new directory in `dir`, named after the rom
+    /// and the time, and returns its path.
+    ///
+    /// # Errors
+    ///
+    /// If the directory or a file in it can't be written.
+    pub fn write(&self, dir: impl AsRef<Path>) -> io::Result<PathBuf> {
+        let rom = self.start.rom_hash.as_deref().unwrap_or("unknown");
+        let secs = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |time| time.as_secs());
+        let path = dir
+            .as_ref()
+            .join(format!("{}-{secs}", &rom[..rom.len().min(8)]));
+        fs::create_dir_all(&path)?;
+        fs::write(path.join("crash.txt"), self.summary())?;
+        fs::write(path.join("state.bin"), self.start.to_bytes())?;
+        fs::write(path.join("trace.csv"), self.trace.to_csv())?;
+        Ok(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// ADD V0, 1; JP 200 while V0 != 3, then an invalid instruction
+    const ROM: [u8; 8] = [0x70, 0x01, 0x30, 0x03, 0x12, 0x00, 0xFF, 0xFF];
+
+    fn crash() -> (Oxid8, Minidump) {
+        let mut emu = Oxid8::new();
+        emu.load_rom_bytes(&ROM).unwrap();
+        let press = InputEvent {
+            cycle: 1,
+            key: 5,
+            pressed: true,
+        };
+        let start = emu.save_state();
+        let err = emu.next_frame_with_input(&[press]).unwrap_err();
+        let after = emu.save_state();
+
+        let dump = Minidump::capture(&mut emu, &start, &[press], &err);
+        assert_eq!(emu.save_state(), after);
+        (emu, dump)
+    }
+
+    #[test]
+    fn captures_failed_frame() {
+        let (emu, dump) = crash();
+        // Three loops, the last skipping the JP, then the invalid
+        // instruction.
+        assert_eq!(dump.trace.len(), 9);
+        assert!(
+            dump.trace
+                .to_csv()
+                .lines()
+                .last()
+                .unwrap()
+                .starts_with("0206,ffff,")
+        );
+
+        let summary = dump.summary();
+        assert!(summary.starts_with(&format!("error: {}\n", dump.error)));
+        assert!(summary.contains(&format!("rom: {}\n", emu.rom_hash().unwrap())));
+        assert!(summary.contains(&format!("profile: {}\n", emu.profile())));
+        assert!(summary.ends_with("events: 1:5+\n"));
+    }
+
+    #[test]
+    fn keeps_last_instructions() {
+        let mut emu = Oxid8::new();
+        // JP 200
+        emu.load_rom_bytes(&[0x12, 0x00]).unwrap();
+        emu.set_cycles_per_frame(TRACE_LEN as u32 + 10);
+        let start = emu.save_state();
+        let dump = Minidump::capture(&mut emu, &start, &[], "not an error");
+        assert_eq!(dump.trace.len(), TRACE_LEN);
+    }
+
+    #[test]
+    fn writes_files() {
+        let (_, dump) = crash();
+        let dir = std::env::temp_dir().join(format!("oxid8-minidump-{}", std::process::id()));
+        let path = dump.write(&dir).unwrap();
+
+        assert_eq!(
+            fs::read_to_string(path.join("crash.txt")).unwrap(),
+            dump.summary()
+        );
+        let state = fs::read(path.join("state.bin")).unwrap();
+        assert_eq!(state, dump.start.to_bytes());
+        let trace = Trace::parse_csv(&fs::read_to_string(path.join("trace.csv")).unwrap()).unwrap();
+        assert_eq!(trace, dump.trace);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,392 @@
+//! Input movies for deterministic replays.
+//!
+//! A movie records the keypad state of every frame along with the
+//! settings and rng seed needed to replay it exactly, so recordings can
+//! be turned into audio and video captures reproducibly.
+//!
+//! ```text
+//! oxid8-movie 2
+//! rom 2f6a6c7e3cb4e8c1b0cbd7e0a8d1f0d5c4a7a0b2
+//! seed 42
+//! cycles_per_frame 10
+//! timer_rate 60
+//! rng uniform
+//! wrap_x false
+//! wrap_y false
+//! key_wait release
+//! i_overflow false
+//! jump_vx false
+//! shift vx
+//! weights Dxyn=4
+//! skip_invalid false
+//! frames
+//! .
+//! 3:1+ 7:4+
+//! 0:1- 0:4-
+//! ```
+//!
+//! Each frame is a line of the key events applied during it, see
+//! [`InputEvent`], or `.` for none. Version 1 movies, where each frame
+//! is the held keys as a hex bitmask, are still loaded. `weights` is
+//! left out when every instruction costs 1, see [`crate::weights`].
+
+use std::{fmt, fs, io, path::Path, str::FromStr};
+
+use crate::{
+    NUM_KEYS, Oxid8, TimerRate,
+    input::InputEvent,
+    quirks::{KeyWait, Quirks, RngMode, Shift},
+    rng::Rng,
+    weights::CycleWeights,
+};
+
+const MAGIC: &str = "oxid8-movie 2";
+const MAGIC_V1: &str = "oxid8-movie 1";
+
+/// Recorded input and the settings needed to replay it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Movie {
+    /// Hash of the recorded rom, see [`Oxid8::rom_hash`].
+    pub rom_hash: Option<String>,
+    /// Seed of the rng, see [`Rng::from_seed`].
+    pub seed: u64,
+    pub cycles_per_frame: u32,
+    pub timer_rate: TimerRate,
+    pub quirks: Quirks,
+    pub cycle_weights: CycleWeights,
+    /// See [`Oxid8::skip_invalid`].
+    pub skip_invalid: bool,
+    /// Key events of each frame, sorted by cycle.
+    pub frames: Vec<Vec<InputEvent>>,
+}
+
+impl Movie {
+    /// Starts recording `core`, reseeding its rng with `seed`.
+    ///
+    /// Call right after loading the rom so replays start from the same
+    /// state.
+    pub fn record(core: &mut Oxid8, seed: u64) -> Self {
+        core.set_rng(Rng::from_seed(seed));
+        Self {
+            rom_hash: core.rom_hash().map(str::to_string),
+            seed,
+            cycles_per_frame: core.cycles_per_frame(),
+            timer_rate: core.timer_rate(),
+            quirks: core.quirks(),
+            cycle_weights: core.cycle_weights().clone(),
+            skip_invalid: core.skip_invalid(),
+            frames: Vec::new(),
+        }
+    }
+
+    /// Records the events passed to `next_frame_with_input` for the
+    /// next frame.
+    pub fn push_frame(&mut self, events: &[InputEvent]) {
+        self.frames.push(events.to_vec());
+    }
+
+    /// Applies the recorded settings and seed to `core`. Call right
+    /// after loading the rom.
+    pub fn prepare(&self, core: &mut Oxid8) {
+        core.set_cycles_per_frame(self.cycles_per_frame);
+        core.set_timer_rate(self.timer_rate);
+        core.set_quirks(self.quirks);
+        core.set_cycle_weights(self.cycle_weights.clone());
+        core.set_skip_invalid(self.skip_invalid);
+        core.set_rng(Rng::from_seed(self.seed));
+    }
+
+    /// Emulates recorded frame `index` of `core`.
+    ///
+    /// Returns false once the movie has ended.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error of `next_frame_with_input` if the rom is bad.
+    pub fn play_frame(&self, core: &mut Oxid8, index: usize) -> Result<bool, String> {
+        let Some(events) = self.frames.get(index) else {
+            return Ok(false);
+        };
+        core.next_frame_with_input(events)?;
+        Ok(true)
+    }
+
+    /// Loads a movie from `path`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read or is not a movie.
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        fs::read_to_string(path)?
+            .parse()
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+    }
+
+    /// Saves the movie to `path`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be written.
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        fs::write(path, self.to_string())
+    }
+}
+
+impl fmt::Display for Movie {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{MAGIC}")?;
+        if let Some(hash) = &self.rom_hash {
+            writeln!(f, "rom {hash}")?;
+        }
+        writeln!(f, "seed {}", self.seed)?;
+        writeln!(f, "cycles_per_frame {}", self.cycles_per_frame)?;
+        writeln!(f, "timer_rate {}", self.timer_rate.hz())?;
+        let rng = match self.quirks.rng {
+            RngMode::Uniform => "uniform",
+            RngMode::Lfsr => "lfsr",
+        };
+        writeln!(f, "rng {rng}")?;
+        writeln!(f, "wrap_x {}", self.quirks.wrap_x)?;
+        writeln!(f, "wrap_y {}", self.quirks.wrap_y)?;
+        writeln!(f, "key_wait {}", self.quirks.key_wait)?;
+        writeln!(f, "i_overflow {}", self.quirks.i_overflow)?;
+        writeln!(f, "jump_vx {}", self.quirks.jump_vx)?;
+        writeln!(f, "shift {}", self.quirks.shift)?;
+        if !self.cycle_weights.is_empty() {
+            writeln!(f, "weights {}", self.cycle_weights)?;
+        }
+        writeln!(f, "skip_invalid {}", self.skip_invalid)?;
+        writeln!(f, "frames")?;
+        for events in &self.frames {
+            if events.is_empty() {
+                writeln!(f, ".")?;
+                continue;
+            }
+            for (i, event) in events.iter().enumerate() {
+                let sep = if i == 0 { "" } else { " " };
+                write!(f, "{sep}{event}")?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Movie {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut lines = s.lines();
+        let v1 = match lines.next() {
+            Some(MAGIC) => false,
+            Some(MAGIC_V1) => true,
+            _ => return Err("Not an oxid8 movie".into()),
+        };
+
+        let mut movie = Movie {
+            rom_hash: None,
+            seed: 0,
+            cycles_per_frame: crate::DEFAULT_CYCLES_PER_FRAME,
+            timer_rate: TimerRate::default(),
+            quirks: Quirks::default(),
+            cycle_weights: CycleWeights::default(),
+            skip_invalid: false,
+            frames: Vec::new(),
+        };
+        let invalid = |line: &str| format!("Invalid movie line: {line}");
+
+        for line in lines.by_ref() {
+            let (key, value) = line.split_once(' ').unwrap_or((line, ""));
+            match key {
+                "rom" => movie.rom_hash = Some(value.to_string()),
+                "seed" => movie.seed = value.parse().map_err(|_| invalid(line))?,
+                "cycles_per_frame" => {
+                    movie.cycles_per_frame = value.parse().map_err(|_| invalid(line))?;
+                }
+                "timer_rate" => {
+                    movie.timer_rate = match value {
+                        "60" => TimerRate::Ntsc,
+                        "50" => TimerRate::Pal,
+                        _ => return Err(invalid(line)),
+                    };
+                }
+                "rng" => {
+                    movie.quirks.rng = match value {
+                        "uniform" => RngMode::Uniform,
+                        // Named vip before.
+                        "lfsr" | "vip" => RngMode::Lfsr,
+                        _ => return Err(invalid(line)),
+                    };
+                }
+                "wrap_x" => movie.quirks.wrap_x = value.parse().map_err(|_| invalid(line))?,
+                "wrap_y" => movie.quirks.wrap_y = value.parse().map_err(|_| invalid(line))?,
+                "key_wait" => {
+                    movie.quirks.key_wait = match value {
+                        "release" => KeyWait::Release,
+                        "press" => KeyWait::Press,
+                        _ => return Err(invalid(line)),
+                    };
+                }
+                "i_overflow" => {
+                    movie.quirks.i_overflow = value.parse().map_err(|_| invalid(line))?;
+                }
+                "jump_vx" => movie.quirks.jump_vx = value.parse().map_err(|_| invalid(line))?,
+                "shift" => {
+                    movie.quirks.shift = match value {
+                        "vx" => Shift::Vx,
+                        "vy" => Shift::Vy,
+                        _ => return Err(invalid(line)),
+                    };
+                }
+                "weights" => movie.cycle_weights = value.parse().map_err(|_| invalid(line))?,
+                "skip_invalid" => {
+                    movie.skip_invalid = value.parse().map_err(|_| invalid(line))?;
+                }
+                "frames" => break,
+                _ => return Err(invalid(line)),
+            }
+        }
+
+        let lines = lines.filter(|line| !line.is_empty());
+        movie.frames = if v1 {
+            let masks = lines
+                .map(|line| u16::from_str_radix(line, 16).map_err(|_| invalid(line)))
+                .collect::<Result<Vec<_>, _>>()?;
+            from_masks(&masks)
+        } else {
+            lines
+                .map(|line| match line {
+                    "." => Ok(Vec::new()),
+                    _ => line.split(' ').map(str::parse).collect(),
+                })
+                .collect::<Result<_, _>>()?
+        };
+
+        Ok(movie)
+    }
+}
+
+/// Converts version 1 frames of held keys into events at the start of
+/// each frame.
+fn from_masks(masks: &[u16]) -> Vec<Vec<InputEvent>> {
+    let mut held = 0;
+    masks
+        .iter()
+        .map(|&mask| {
+            let changed = held ^ mask;
+            held = mask;
+            (0..NUM_KEYS as u8)
+                .filter(|k| changed & (1 << k) != 0)
+                .map(|key| InputEvent {
+                    cycle: 0,
+                    key,
+                    pressed: mask & (1 << key) != 0,
+                })
+                .collect()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Cxkk into every register then loop, so the screen depends on
+    /// the rng and the keys.
+    fn core() -> Oxid8 {
+        let mut core = Oxid8::new();
+        let mut rom = Vec::new();
+        for x in 0..15 {
+            rom.extend([0xC0 | x, 0xFF]);
+        }
+        // LD V0, K; JP 200
+        rom.extend([0xF0, 0x0A, 0x12, 0x00]);
+        core.load_font();
+        core.load_rom_bytes(&rom).unwrap();
+        core
+    }
+
+    fn event(cycle: u32, key: u8, pressed: bool) -> InputEvent {
+        InputEvent {
+            cycle,
+            key,
+            pressed,
+        }
+    }
+
+    #[test]
+    fn roundtrip() {
+        let movie = Movie {
+            rom_hash: Some("abc".into()),
+            seed: 42,
+            cycles_per_frame: 12,
+            timer_rate: TimerRate::Pal,
+            quirks: Quirks {
+                rng: RngMode::Lfsr,
+                wrap_y: true,
+                key_wait: KeyWait::Press,
+                i_overflow: true,
+                jump_vx: true,
+                shift: Shift::Vy,
+                ..Quirks::default()
+            },
+            cycle_weights: "dxyn=4 fx55=2".parse().unwrap(),
+            skip_invalid: true,
+            frames: vec![
+                vec![],
+                vec![event(3, 1, true), event(7, 0xF, true)],
+                vec![event(0, 1, false)],
+            ],
+        };
+        let s = movie.to_string();
+        assert!(s.starts_with("oxid8-movie 2\nrom abc\nseed 42\n"));
+        assert!(
+            s.ends_with("weights Dxyn=4 Fx55=2\nskip_invalid true\nframes\n.\n3:1+ 7:f+\n0:1-\n")
+        );
+        assert_eq!(s.parse(), Ok(movie));
+        assert!("oxid8-movie 2\nbogus 1\n".parse::<Movie>().is_err());
+        assert!("oxid8-movie 2\nframes\n3:1+ x\n".parse::<Movie>().is_err());
+    }
+
+    #[test]
+    fn version_1() {
+        let movie: Movie = "oxid8-movie 1\nseed 1\nframes\n0000\n0012\n0002\n"
+            .parse()
+            .unwrap();
+        assert_eq!(
+            movie.frames,
+            [
+                vec![],
+                vec![event(0, 1, true), event(0, 4, true)],
+                vec![event(0, 4, false)],
+            ]
+        );
+    }
+
+    #[test]
+    fn replay() {
+        let mut core1 = core();
+        // Replays run with the weights recorded, not the player's.
+        core1.set_cycle_weights("cxkk=3".parse().unwrap());
+        core1.set_skip_invalid(true);
+        let mut movie = Movie::record(&mut core1, 7);
+        for frame in 0..30 {
+            let key = (frame % 16) as u8;
+            let events = [event(frame % 7, key, true), event(8, key, false)];
+            movie.push_frame(&events);
+            core1.next_frame_with_input(&events).unwrap();
+        }
+
+        let mut core2 = core();
+        movie.prepare(&mut core2);
+        let mut index = 0;
+        while movie.play_frame(&mut core2, index).unwrap() {
+            index += 1;
+        }
+
+        assert_eq!(index, 30);
+        assert_eq!(core1.v_reg, core2.v_reg);
+        assert_eq!(core1.rng(), core2.rng());
+        assert!(core2.skip_invalid());
+    }
+}
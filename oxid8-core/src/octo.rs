@@ -0,0 +1,617 @@
+//! An assembler for Octo source files.
+//!
+//! [Octo](https://github.com/JohnEarnest/Octo) is the usual way to write
+//! CHIP-8 programs today. With the `octo` feature enabled,
+//! [`Oxid8::load_rom`](crate::Oxid8::load_rom) assembles `.o8` files on
+//! load, so programs can be run straight from source.
+//!
+//! The CHIP-8 subset of the language is supported: every instruction,
+//! labels, `:const`, `:alias`, `:byte`, data bytes, `if ... then`,
+//! `if ... begin ... else ... end`, and `loop ... while ... again`.
+//! SCHIP and XO-CHIP instructions, macros, and `:calc` are not.
+//! Execution starts at `: main`.
+//!
+//! ```
+//! use oxid8_core::octo;
+//!
+//! let rom = octo::assemble(": main  v0 := 5  loop again").unwrap();
+//! assert_eq!(rom, [0x60, 0x05, 0x12, 0x02]);
+//!
+//! let err = octo::assemble(": main\n  v0 := 5\n  v0 += spam\n").unwrap_err();
+//! assert_eq!(err, "line 3: Expected a number, found 'spam'");
+//! ```
+
+use std::collections::HashMap;
+
+use crate::layout::START_ADDR;
+
+/// SCHIP and XO-CHIP instructions, named in errors.
+const UNSUPPORTED: [&str; 13] = [
+    "hires",
+    "lores",
+    "exit",
+    "scroll-down",
+    "scroll-up",
+    "scroll-left",
+    "scroll-right",
+    "saveflags",
+    "loadflags",
+    "plane",
+    "audio",
+    "pitch",
+    "bighex",
+];
+
+/// Assembles Octo `source` into a rom.
+///
+/// # Errors
+///
+/// Returns the first error, prefixed with its line number, e.g.
+/// `line 3: Undefined label 'draw'`.
+pub fn assemble(source: &str) -> Result<Vec<u8>, String> {
+    let tokens = source
+        .lines()
+        .enumerate()
+        .flat_map(|(i, line)| {
+            let code = line.split('#').next().unwrap_or_default();
+            code.split_whitespace().map(move |token| (i + 1, token))
+        })
+        .collect();
+
+    let mut assembler = Assembler {
+        tokens,
+        ..Assembler::default()
+    };
+    // Octo jumps to main first, unless main comes first.
+    assembler.emit_fixup(0x1000, "main", 1);
+    while assembler.pos < assembler.tokens.len() {
+        assembler.statement()?;
+    }
+    assembler.finish()
+}
+
+#[derive(Default)]
+struct Assembler<'a> {
+    /// Tokens with their line numbers.
+    tokens: Vec<(usize, &'a str)>,
+    pos: usize,
+    rom: Vec<u8>,
+    labels: HashMap<&'a str, u16>,
+    consts: HashMap<&'a str, i32>,
+    aliases: HashMap<&'a str, u8>,
+    /// Label addresses to fill in once every label is known.
+    fixups: Vec<Fixup<'a>>,
+    blocks: Vec<Block>,
+}
+
+struct Fixup<'a> {
+    offset: usize,
+    label: &'a str,
+    line: usize,
+}
+
+enum Block {
+    /// Jump to patch at `else` or `end`.
+    If { jump: usize, line: usize },
+    /// Loop start and `while` jumps to patch at `again`.
+    Loop {
+        start: u16,
+        exits: Vec<usize>,
+        line: usize,
+    },
+}
+
+/// Which way a condition skips.
+#[derive(Clone, Copy)]
+struct Skip {
+    /// Skips the next instruction unless the condition holds.
+    unless: u16,
+    /// Skips the next instruction if the condition holds.
+    when: u16,
+}
+
+impl<'a> Assembler<'a> {
+    /// `msg` at the line of the last token read.
+    fn err(&self, msg: impl std::fmt::Display) -> String {
+        format!("line {}: {msg}", self.line())
+    }
+
+    fn next(&mut self) -> Result<&'a str, String> {
+        let token = self.tokens.get(self.pos).map(|&(_, token)| token);
+        self.pos += 1;
+        token.ok_or_else(|| self.err("Unexpected end of file"))
+    }
+
+    fn line(&self) -> usize {
+        self.tokens
+            .get(self.pos.saturating_sub(1))
+            .map_or(1, |&(line, _)| line)
+    }
+
+    fn here(&self) -> u16 {
+        START_ADDR.get() + self.rom.len() as u16
+    }
+
+    fn emit(&mut self, op: u16) {
+        self.rom.extend(op.to_be_bytes());
+    }
+
+    fn op(&mut self, op: u16) -> Result<(), String> {
+        self.emit(op);
+        Ok(())
+    }
+
+    /// Emits `op` with a register read as `x`.
+    fn op_x(&mut self, op: u16) -> Result<(), String> {
+        let x = self.expect_register()?;
+        self.op(op | x << 8)
+    }
+
+    /// Emits `op` with the address of `label` filled in later.
+    fn emit_fixup(&mut self, op: u16, label: &'a str, line: usize) {
+        self.fixups.push(Fixup {
+            offset: self.rom.len(),
+            label,
+            line,
+        });
+        self.emit(op);
+    }
+
+    /// Fills in the address of the jump at `offset` with `addr`.
+    fn patch(&mut self, offset: usize, addr: u16) {
+        self.rom[offset] |= (addr >> 8) as u8;
+        self.rom[offset + 1] |= addr as u8;
+    }
+
+    fn expect(&mut self, expected: &str) -> Result<(), String> {
+        match self.next()? {
+            token if token == expected => Ok(()),
+            token => Err(self.err(format!("Expected '{expected}', found '{token}'"))),
+        }
+    }
+
+    fn register(&self, token: &str) -> Option<u8> {
+        if let Some(&x) = self.aliases.get(token) {
+            return Some(x);
+        }
+        let hex = token.strip_prefix(['v', 'V'])?;
+        (hex.len() == 1).then(|| u8::from_str_radix(hex, 16).ok())?
+    }
+
+    fn expect_register(&mut self) -> Result<u16, String> {
+        let token = self.next()?;
+        match self.register(token) {
+            Some(x) => Ok(x as u16),
+            None => Err(self.err(format!("Expected a register, found '{token}'"))),
+        }
+    }
+
+    fn number(&self, token: &str) -> Option<i32> {
+        if let Some(&value) = self.consts.get(token) {
+            return Some(value);
+        }
+        let (negative, digits) = match token.strip_prefix('-') {
+            Some(digits) => (true, digits),
+            None => (false, token),
+        };
+        let value = if let Some(hex) = digits.strip_prefix("0x") {
+            i32::from_str_radix(hex, 16)
+        } else if let Some(bin) = digits.strip_prefix("0b") {
+            i32::from_str_radix(bin, 2)
+        } else {
+            digits.parse()
+        };
+        value
+            .ok()
+            .map(|value| if negative { -value } else { value })
+    }
+
+    fn expect_value(&mut self) -> Result<i32, String> {
+        let token = self.next()?;
+        self.number(token)
+            .ok_or_else(|| self.err(format!("Expected a number, found '{token}'")))
+    }
+
+    fn expect_number(&mut self, min: i32, max: i32) -> Result<u16, String> {
+        match self.expect_value()? {
+            value if (min..=max).contains(&value) => Ok(value as u16),
+            value => Err(self.err(format!("{value} is out of range {min} to {max}"))),
+        }
+    }
+
+    /// A byte, negative values wrap around.
+    fn expect_byte(&mut self) -> Result<u16, String> {
+        self.expect_number(-128, 255).map(|byte| byte & 0xFF)
+    }
+
+    /// Emits `op` with a 12-bit address, a number or a label.
+    fn emit_addr(&mut self, op: u16) -> Result<(), String> {
+        let token = self.next()?;
+        if let Some(value) = self.number(token) {
+            return match u16::try_from(value) {
+                Ok(addr) if addr < 0x1000 => self.op(op | addr),
+                _ => Err(self.err(format!("{value} is not an address"))),
+            };
+        }
+        match self.labels.get(token) {
+            Some(&addr) => self.emit(op | addr),
+            None => self.emit_fixup(op, token, self.line()),
+        }
+        Ok(())
+    }
+
+    fn statement(&mut self) -> Result<(), String> {
+        let token = self.next()?;
+        if let Some(x) = self.register(token) {
+            return self.assign(x as u16);
+        }
+
+        match token {
+            ":" => self.label(),
+            ":const" => {
+                let name = self.next()?;
+                let value = self.expect_value()?;
+                self.consts.insert(name, value);
+                Ok(())
+            }
+            ":alias" => {
+                let name = self.next()?;
+                let x = self.expect_register()?;
+                self.aliases.insert(name, x as u8);
+                Ok(())
+            }
+            ":byte" => {
+                let byte = self.expect_byte()?;
+                self.rom.push(byte as u8);
+                Ok(())
+            }
+            ":breakpoint" => self.next().map(|_| ()),
+            "clear" => self.op(0x00E0),
+            "return" | ";" => self.op(0x00EE),
+            "jump" => self.emit_addr(0x1000),
+            "jump0" => self.emit_addr(0xB000),
+            "native" => self.emit_addr(0x0000),
+            "sprite" => {
+                let x = self.expect_register()?;
+                let y = self.expect_register()?;
+                let n = self.expect_number(0, 15)?;
+                self.op(0xD000 | x << 8 | y << 4 | n)
+            }
+            "bcd" => self.op_x(0xF033),
+            "save" => self.op_x(0xF055),
+            "load" => self.op_x(0xF065),
+            "delay" | "buzzer" => {
+                self.expect(":=")?;
+                let op = if token == "delay" { 0xF015 } else { 0xF018 };
+                self.op_x(op)
+            }
+            "i" | "I" => self.assign_i(),
+            "if" => self.if_statement(),
+            "else" => match self.blocks.pop() {
+                Some(Block::If { jump, .. }) => {
+                    let line = self.line();
+                    self.blocks.push(Block::If {
+                        jump: self.rom.len(),
+                        line,
+                    });
+                    self.emit(0x1000);
+                    self.patch(jump, self.here());
+                    Ok(())
+                }
+                _ => Err(self.err("'else' without 'if ... begin'")),
+            },
+            "end" => match self.blocks.pop() {
+                Some(Block::If { jump, .. }) => {
+                    self.patch(jump, self.here());
+                    Ok(())
+                }
+                _ => Err(self.err("'end' without 'if ... begin'")),
+            },
+            "loop" => {
+                let line = self.line();
+                self.blocks.push(Block::Loop {
+                    start: self.here(),
+                    exits: Vec::new(),
+                    line,
+                });
+                Ok(())
+            }
+            "while" => {
+                let skip = self.condition()?;
+                let exit = self.rom.len() + 2;
+                match self.blocks.iter_mut().rev().find_map(|block| match block {
+                    Block::Loop { exits, .. } => Some(exits),
+                    Block::If { .. } => None,
+                }) {
+                    Some(exits) => exits.push(exit),
+                    None => return Err(self.err("'while' outside of a loop")),
+                }
+                self.emit(skip.when);
+                self.emit(0x1000);
+                Ok(())
+            }
+            "again" => match self.blocks.pop() {
+                Some(Block::Loop { start, exits, .. }) => {
+                    self.emit(0x1000 | start);
+                    for exit in exits {
+                        self.patch(exit, self.here());
+                    }
+                    Ok(())
+                }
+                _ => Err(self.err("'again' without 'loop'")),
+            },
+            _ if UNSUPPORTED.contains(&token) => {
+                Err(self.err(format!("'{token}' is not a CHIP-8 instruction")))
+            }
+            _ if token.starts_with(':') => {
+                Err(self.err(format!("Unsupported directive '{token}'")))
+            }
+            _ => {
+                // A bare number is a data byte.
+                if self.number(token).is_some() {
+                    self.pos -= 1;
+                    let byte = self.expect_byte()?;
+                    self.rom.push(byte as u8);
+                    return Ok(());
+                }
+                // A bare label calls a subroutine.
+                match self.labels.get(token) {
+                    Some(&addr) => self.emit(0x2000 | addr),
+                    None => self.emit_fixup(0x2000, token, self.line()),
+                }
+                Ok(())
+            }
+        }
+    }
+
+    fn label(&mut self) -> Result<(), String> {
+        let name = self.next()?;
+        if self.labels.contains_key(name) {
+            return Err(self.err(format!("Label '{name}' is defined twice")));
+        }
+        // No need to jump to main when it comes first.
+        if name == "main" && self.rom.len() == 2 && self.fixups.len() == 1 {
+            self.rom.clear();
+            self.fixups.clear();
+        }
+        self.labels.insert(name, self.here());
+        Ok(())
+    }
+
+    /// `vx := ...`, `vx += ...`, and the other register operators.
+    fn assign(&mut self, x: u16) -> Result<(), String> {
+        let op = self.next()?;
+        let token = self.next()?;
+        let vy = self.register(token).map(|y| (y as u16) << 4);
+        let alu = |n: u16| vy.map(|y| 0x8000 | x << 8 | y | n);
+
+        let opcode = match (op, vy) {
+            (":=", Some(_)) => alu(0x0),
+            (":=", None) if token == "random" => Some(0xC000 | x << 8 | self.expect_byte()?),
+            (":=", None) if token == "key" => Some(0xF00A | x << 8),
+            (":=", None) if token == "delay" => Some(0xF007 | x << 8),
+            (":=", None) => {
+                self.pos -= 1;
+                Some(0x6000 | x << 8 | self.expect_byte()?)
+            }
+            ("|=", _) => alu(0x1),
+            ("&=", _) => alu(0x2),
+            ("^=", _) => alu(0x3),
+            ("+=", Some(_)) => alu(0x4),
+            ("+=", None) => {
+                self.pos -= 1;
+                Some(0x7000 | x << 8 | self.expect_byte()?)
+            }
+            ("-=", Some(_)) => alu(0x5),
+            ("-=", None) => {
+                self.pos -= 1;
+                let n = self.expect_byte()?;
+                Some(0x7000 | x << 8 | (n as u8).wrapping_neg() as u16)
+            }
+            (">>=", _) => alu(0x6),
+            ("=-", _) => alu(0x7),
+            ("<<=", _) => alu(0xE),
+            _ => return Err(self.err(format!("Unknown operator '{op}'"))),
+        };
+        match opcode {
+            Some(opcode) => self.op(opcode),
+            None => Err(self.err(format!("Expected a register, found '{token}'"))),
+        }
+    }
+
+    /// `i := addr`, `i := hex vx`, and `i += vx`.
+    fn assign_i(&mut self) -> Result<(), String> {
+        match self.next()? {
+            ":=" if self.tokens.get(self.pos).is_some_and(|&(_, t)| t == "hex") => {
+                self.pos += 1;
+                self.op_x(0xF029)
+            }
+            ":=" => self.emit_addr(0xA000),
+            "+=" => self.op_x(0xF01E),
+            op => Err(self.err(format!("Unknown operator '{op}'"))),
+        }
+    }
+
+    /// `vx == n`, `vx != vy`, `vx key`, `vx -key`.
+    fn condition(&mut self) -> Result<Skip, String> {
+        let x = self.expect_register()? << 8;
+        let op = self.next()?;
+        let key = |when, unless| Skip {
+            when: when | x,
+            unless: unless | x,
+        };
+        match op {
+            "key" => return Ok(key(0xE09E, 0xE0A1)),
+            "-key" => return Ok(key(0xE0A1, 0xE09E)),
+            "==" | "!=" => (),
+            _ => return Err(self.err(format!("Unknown comparison '{op}'"))),
+        }
+
+        let token = self.next()?;
+        let (eq, ne) = match self.register(token) {
+            Some(y) => (0x5000 | x | (y as u16) << 4, 0x9000 | x | (y as u16) << 4),
+            None => {
+                self.pos -= 1;
+                let n = self.expect_byte()?;
+                (0x3000 | x | n, 0x4000 | x | n)
+            }
+        };
+        Ok(match op {
+            "==" => Skip {
+                when: eq,
+                unless: ne,
+            },
+            _ => Skip {
+                when: ne,
+                unless: eq,
+            },
+        })
+    }
+
+    fn if_statement(&mut self) -> Result<(), String> {
+        let skip = self.condition()?;
+        match self.next()? {
+            "then" => {
+                self.emit(skip.unless);
+                self.statement()
+            }
+            "begin" => {
+                let line = self.line();
+                self.emit(skip.when);
+                self.blocks.push(Block::If {
+                    jump: self.rom.len(),
+                    line,
+                });
+                self.emit(0x1000);
+                Ok(())
+            }
+            token => Err(self.err(format!("Expected 'then' or 'begin', found '{token}'"))),
+        }
+    }
+
+    fn finish(mut self) -> Result<Vec<u8>, String> {
+        if let Some(block) = self.blocks.last() {
+            let (name, line) = match *block {
+                Block::If { line, .. } => ("'begin' without 'end'", line),
+                Block::Loop { line, .. } => ("'loop' without 'again'", line),
+            };
+            return Err(format!("line {line}: {name}"));
+        }
+        for fixup in std::mem::take(&mut self.fixups) {
+            match self.labels.get(fixup.label) {
+                Some(&addr) => self.patch(fixup.offset, addr),
+                None if fixup.label == "main" => return Err("No ': main' label".into()),
+                None => {
+                    return Err(format!(
+                        "line {}: Undefined label '{}'",
+                        fixup.line, fixup.label
+                    ));
+                }
+            }
+        }
+        Ok(self.rom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn instructions() {
+        let source = "
+            : main
+                clear
+                v0 := 0x12  v1 := v0  v2 := random 0xFF
+                v3 := key  v4 := delay
+                v0 += 1  v0 -= 1  v0 += v1  v0 -= v1  v0 =- v1
+                v0 |= v1  v0 &= v1  v0 ^= v1  v0 >>= v1  v0 <<= v1
+                i := sprite  i := hex v5  i += v6
+                sprite v0 v1 5
+                delay := v7  buzzer := v8
+                bcd v9  save va  load vb
+                jump0 0x300
+                ;
+            : sprite
+                0b11110000 -1
+        ";
+        let rom = assemble(source).unwrap();
+        let ops: Vec<u16> = rom
+            .chunks(2)
+            .map(|op| u16::from_be_bytes([op[0], op[1]]))
+            .collect();
+        assert_eq!(
+            ops,
+            [
+                0x00E0, 0x6012, 0x8100, 0xC2FF, 0xF30A, 0xF407, 0x7001, 0x70FF, 0x8014, 0x8015,
+                0x8017, 0x8011, 0x8012, 0x8013, 0x8016, 0x801E, 0xA236, 0xF529, 0xF61E, 0xD015,
+                0xF715, 0xF818, 0xF933, 0xFA55, 0xFB65, 0xB300, 0x00EE, 0xF0FF,
+            ]
+        );
+    }
+
+    #[test]
+    fn control_flow() {
+        let source = "
+            :alias x v1
+            :const limit 3
+            : main
+                loop
+                    x += 1
+                    while x != limit
+                    if x key then draw
+                    if x == v2 begin
+                        x := 0
+                    else
+                        x := 1
+                    end
+                again
+            : draw
+                return
+        ";
+        assert_eq!(
+            assemble(source).unwrap(),
+            [
+                0x71, 0x01, // 200: x += 1
+                0x41, 0x03, // 202: skip if x != 3
+                0x12, 0x16, // 204: jump 216
+                0xE1, 0xA1, // 206: skip unless key x
+                0x22, 0x16, // 208: draw
+                0x51, 0x20, // 20A: skip if x == v2
+                0x12, 0x12, // 20C: jump 212
+                0x61, 0x00, // 20E: x := 0
+                0x12, 0x14, // 210: jump 214
+                0x61, 0x01, // 212: x := 1
+                0x12, 0x00, // 214: again
+                0x00, 0xEE, // 216: return
+            ]
+        );
+    }
+
+    #[test]
+    fn jump_to_main() {
+        // Main isn't first, so the rom starts with a jump to it.
+        let rom = assemble(": draw return : main draw").unwrap();
+        assert_eq!(rom, [0x12, 0x04, 0x00, 0xEE, 0x22, 0x02]);
+    }
+
+    #[test]
+    fn errors() {
+        let err = |source| assemble(source).unwrap_err();
+        assert_eq!(err(": main\n\n  draw"), "line 3: Undefined label 'draw'");
+        assert_eq!(
+            err(": main v0 := 256"),
+            "line 1: 256 is out of range -128 to 255"
+        );
+        assert_eq!(
+            err(": main\nhires"),
+            "line 2: 'hires' is not a CHIP-8 instruction"
+        );
+        assert_eq!(err(": main\nloop\n"), "line 2: 'loop' without 'again'");
+        assert_eq!(
+            err(": main : main"),
+            "line 1: Label 'main' is defined twice"
+        );
+        assert_eq!(err("clear"), "No ': main' label");
+    }
+}
@@ -0,0 +1,224 @@
+//! On-screen display messages drawn into the framebuffer.
+//!
+//! Short feedback like "State saved" or "Speed 12" is drawn with the
+//! built-in 4x5 font, extended to letters and a little punctuation, so
+//! every frontend can show messages without a text renderer of its own.
+//! Messages are drawn onto a copy of the screen and never touch the
+//! emulated display.
+//!
+//! ```
+//! use oxid8_core::{Oxid8, osd::Osd};
+//!
+//! let emu = Oxid8::new();
+//! let mut osd = Osd::default();
+//! osd.show("State saved");
+//!
+//! // Every frame:
+//! let screen = osd.overlay(emu.screen_ref());
+//! osd.tick();
+//! ```
+
+use crate::{FONTSET, SCREEN_AREA, SCREEN_HEIGHT, SCREEN_WIDTH};
+
+/// Frames a message is shown for, two seconds at 60Hz.
+pub const MESSAGE_FRAMES: u32 = 120;
+
+/// Glyph height in pixels. Glyphs are 4 pixels wide.
+pub const GLYPH_HEIGHT: usize = 5;
+
+/// Horizontal distance between glyphs in pixels.
+pub const GLYPH_ADVANCE: usize = 5;
+
+/// Characters that fit across the screen.
+pub const MAX_CHARS: usize = SCREEN_WIDTH / GLYPH_ADVANCE;
+
+/// Rows of a glyph, the high nibble of each byte from left to right
+/// like the built-in font.
+type Glyph = [u8; GLYPH_HEIGHT];
+
+#[rustfmt::skip]
+const LETTERS: [Glyph; 20] = [
+    [0xF0, 0x80, 0xB0, 0x90, 0xF0], // G
+    [0x90, 0x90, 0xF0, 0x90, 0x90], // H
+    [0xE0, 0x40, 0x40, 0x40, 0xE0], // I
+    [0x70, 0x20, 0x20, 0xA0, 0x40], // J
+    [0x90, 0xA0, 0xC0, 0xA0, 0x90], // K
+    [0x80, 0x80, 0x80, 0x80, 0xF0], // L
+    [0x90, 0xF0, 0xF0, 0x90, 0x90], // M
+    [0x90, 0xD0, 0xB0, 0x90, 0x90], // N
+    [0x60, 0x90, 0x90, 0x90, 0x60], // O
+    [0xE0, 0x90, 0xE0, 0x80, 0x80], // P
+    [0x60, 0x90, 0x90, 0xB0, 0x70], // Q
+    [0xE0, 0x90, 0xE0, 0xA0, 0x90], // R
+    [0x70, 0x80, 0x60, 0x10, 0xE0], // S
+    [0xE0, 0x40, 0x40, 0x40, 0x40], // T
+    [0x90, 0x90, 0x90, 0x90, 0xF0], // U
+    [0x90, 0x90, 0x90, 0xA0, 0x40], // V
+    [0x90, 0x90, 0xF0, 0xF0, 0x90], // W
+    [0x90, 0x90, 0x60, 0x90, 0x90], // X
+    [0xA0, 0xA0, 0x40, 0x40, 0x40], // Y
+    [0xF0, 0x10, 0x60, 0x80, 0xF0], // Z
+];
+
+/// The glyph for `c`, case insensitive.
+///
+/// Covers digits, letters, space, and `.,:-+/!?%`. Anything else is
+/// drawn as `?`.
+#[must_use]
+pub fn glyph(c: char) -> Glyph {
+    let c = c.to_ascii_uppercase();
+    let font = |i: usize| {
+        let mut glyph = [0; GLYPH_HEIGHT];
+        glyph.copy_from_slice(&FONTSET[i * GLYPH_HEIGHT..(i + 1) * GLYPH_HEIGHT]);
+        glyph
+    };
+    match c {
+        '0'..='9' => font(c as usize - '0' as usize),
+        'A'..='F' => font(c as usize - 'A' as usize + 10),
+        'G'..='Z' => LETTERS[c as usize - 'G' as usize],
+        ' ' => [0x00, 0x00, 0x00, 0x00, 0x00],
+        '.' => [0x00, 0x00, 0x00, 0x00, 0x40],
+        ',' => [0x00, 0x00, 0x00, 0x40, 0x80],
+        ':' => [0x00, 0x40, 0x00, 0x40, 0x00],
+        '-' => [0x00, 0x00, 0xE0, 0x00, 0x00],
+        '+' => [0x00, 0x40, 0xE0, 0x40, 0x00],
+        '/' => [0x10, 0x10, 0x20, 0x40, 0x80],
+        '!' => [0x40, 0x40, 0x40, 0x00, 0x40],
+        '%' => [0x90, 0x10, 0x60, 0x80, 0x90],
+        _ => [0xE0, 0x10, 0x60, 0x00, 0x40], // ?
+    }
+}
+
+/// Draws `text` with its top left at `x`, `y`, clipped to the screen.
+/// Only lit pixels are drawn.
+pub fn draw_text(screen: &mut [bool; SCREEN_AREA], x: usize, y: usize, text: &str) {
+    for (i, c) in text.chars().enumerate() {
+        let left = x + i * GLYPH_ADVANCE;
+        for (row, bits) in glyph(c).into_iter().enumerate() {
+            for col in 0..4 {
+                let (px, py) = (left + col, y + row);
+                if bits & (0x80 >> col) != 0 && px < SCREEN_WIDTH && py < SCREEN_HEIGHT {
+                    screen[px + py * SCREEN_WIDTH] = true;
+                }
+            }
+        }
+    }
+}
+
+/// The message currently shown, if any.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Osd {
+    message: String,
+    frames: u32,
+}
+
+impl Osd {
+    /// Shows `message` for [`MESSAGE_FRAMES`], replacing any current
+    /// message. Only the first [`MAX_CHARS`] characters fit.
+    pub fn show(&mut self, message: impl Into<String>) {
+        self.message = message.into();
+        self.frames = MESSAGE_FRAMES;
+    }
+
+    /// Counts down the current message, call once per frame.
+    pub fn tick(&mut self) {
+        self.frames = self.frames.saturating_sub(1);
+    }
+
+    /// The message currently shown.
+    #[must_use]
+    pub fn message(&self) -> Option<&str> {
+        (self.frames > 0).then_some(self.message.as_str())
+    }
+
+    /// A copy of `screen` with the current message drawn in a cleared
+    /// box along the bottom left.
+    #[must_use]
+    pub fn overlay(&self, screen: &[bool; SCREEN_AREA]) -> [bool; SCREEN_AREA] {
+        let mut screen = *screen;
+        let Some(message) = self.message() else {
+            return screen;
+        };
+
+        let chars = message.chars().count().min(MAX_CHARS);
+        let width = (chars * GLYPH_ADVANCE + 1).min(SCREEN_WIDTH);
+        let top = SCREEN_HEIGHT - GLYPH_HEIGHT - 2;
+        for y in top..SCREEN_HEIGHT {
+            screen[y * SCREEN_WIDTH..y * SCREEN_WIDTH + width].fill(false);
+        }
+
+        let end = message
+            .char_indices()
+            .nth(MAX_CHARS)
+            .map_or(message.len(), |(i, _)| i);
+        draw_text(&mut screen, 1, top + 1, &message[..end]);
+        screen
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lit(screen: &[bool; SCREEN_AREA]) -> usize {
+        screen.iter().filter(|&&px| px).count()
+    }
+
+    #[test]
+    fn glyphs() {
+        assert_eq!(glyph('0'), [0xF0, 0x90, 0x90, 0x90, 0xF0]);
+        assert_eq!(glyph('f'), glyph('F'));
+        assert_eq!(glyph('s'), LETTERS[(b'S' - b'G') as usize]);
+        assert_eq!(glyph('~'), glyph('?'));
+        for glyph in LETTERS {
+            assert!(glyph.iter().all(|row| row & 0x0F == 0));
+        }
+    }
+
+    #[test]
+    fn draw_clipped() {
+        let mut screen = [false; SCREEN_AREA];
+        draw_text(&mut screen, 0, 0, "1");
+        // 1 is 0x20, 0x60, 0x20, 0x20, 0x70
+        assert_eq!(lit(&screen), 1 + 2 + 1 + 1 + 3);
+        assert!(screen[2] && !screen[0]);
+
+        // Past the corner only the top left pixels are drawn.
+        let mut screen = [false; SCREEN_AREA];
+        draw_text(&mut screen, SCREEN_WIDTH - 1, SCREEN_HEIGHT - 1, "8");
+        assert_eq!(lit(&screen), 1);
+    }
+
+    #[test]
+    fn message_expires() {
+        let mut osd = Osd::default();
+        let screen = [true; SCREEN_AREA];
+        assert_eq!(osd.overlay(&screen), screen);
+
+        osd.show("State saved!");
+        let overlay = osd.overlay(&screen);
+        assert_ne!(overlay, screen);
+        // The box is cleared behind the text, the rest is untouched.
+        assert!(!overlay[(SCREEN_HEIGHT - 1) * SCREEN_WIDTH]);
+        assert!(overlay[(SCREEN_HEIGHT - 8) * SCREEN_WIDTH]);
+
+        for _ in 0..MESSAGE_FRAMES {
+            assert_eq!(osd.message(), Some("State saved!"));
+            osd.tick();
+        }
+        assert_eq!(osd.message(), None);
+        assert_eq!(osd.overlay(&screen), screen);
+    }
+
+    #[test]
+    fn long_messages_truncated() {
+        let mut osd = Osd::default();
+        osd.show("A message that is far too long");
+        let overlay = osd.overlay(&[false; SCREEN_AREA]);
+        let last_col = (0..SCREEN_AREA)
+            .filter(|&i| overlay[i])
+            .map(|i| i % SCREEN_WIDTH)
+            .max();
+        assert!(last_col < Some(MAX_CHARS * GLYPH_ADVANCE));
+    }
+}
@@ -0,0 +1,324 @@
+//! Color presets and an editor for the screen colors.
+//!
+//! The colors a game is drawn in are part of its [`GameConfig`]: lit
+//! pixels, unlit pixels, and the border around the screen. Players can
+//! cycle through the [`PRESETS`] with [`next_preset`], or change one
+//! channel of one color at a time with a [`PaletteEditor`]. Both write
+//! straight into the config, so frontends drawing from it preview the
+//! colors as they change. [`ConfigStore::save_colors`] keeps them for
+//! the rom, or for every game.
+//!
+//! The core draws a single plane, so there are no XO-CHIP plane colors
+//! to edit yet.
+//!
+//! ```
+//! use oxid8_core::{
+//!     color::Rgba,
+//!     config::GameConfig,
+//!     palette::{self, PaletteEditor, Slot},
+//! };
+//!
+//! let mut config = GameConfig::default();
+//! // White on black is the first preset.
+//! let preset = palette::next_preset(&config);
+//! preset.apply(&mut config);
+//! assert_eq!(preset.name, "green phosphor");
+//!
+//! let mut editor = PaletteEditor::new(&config);
+//! editor.select_slot(1);
+//! assert_eq!(editor.slot(), Slot::Background);
+//! editor.adjust(&mut config, 0x10);
+//! assert_eq!(config.background, Some(Rgba::rgb(0x20, 0x10, 0x10)));
+//!
+//! editor.cancel(&mut config);
+//! assert_eq!(config.background, Some(Rgba::rgb(0x10, 0x10, 0x10)));
+//! ```
+
+use std::{fmt, io};
+
+pub use crate::color::{PRESETS, Preset};
+use crate::{
+    color::{Palette, Rgba},
+    config::{ConfigStore, GameConfig},
+};
+
+/// A color of the screen that can be edited.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Slot {
+    /// Lit pixels.
+    Foreground,
+    /// Unlit pixels.
+    Background,
+    /// Around the screen.
+    Border,
+}
+
+/// Every slot, in the order editors list them.
+pub const SLOTS: [Slot; 3] = [Slot::Foreground, Slot::Background, Slot::Border];
+
+/// Names of the channels of a color, in order.
+pub const CHANNELS: [&str; 3] = ["red", "green", "blue"];
+
+impl Slot {
+    /// The color set in `config`, if any.
+    #[must_use]
+    pub fn get(self, config: &GameConfig) -> Option<Rgba> {
+        match self {
+            Slot::Foreground => config.foreground,
+            Slot::Background => config.background,
+            Slot::Border => config.border,
+        }
+    }
+
+    /// Sets the color in `config`, `None` for the frontend's default.
+    pub fn set(self, config: &mut GameConfig, color: Option<Rgba>) {
+        match self {
+            Slot::Foreground => config.foreground = color,
+            Slot::Background => config.background = color,
+            Slot::Border => config.border = color,
+        }
+    }
+
+    /// The color in `config`, or the default if it isn't set.
+    #[must_use]
+    pub fn color(self, config: &GameConfig) -> Rgba {
+        self.get(config).unwrap_or(self.default_color())
+    }
+
+    /// The color used when none is set, white on black.
+    #[must_use]
+    pub fn default_color(self) -> Rgba {
+        match self {
+            Slot::Foreground => Rgba::WHITE,
+            Slot::Background | Slot::Border => Rgba::BLACK,
+        }
+    }
+}
+
+/// Formatted like "foreground".
+impl fmt::Display for Slot {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.pad(match self {
+            Slot::Foreground => "foreground",
+            Slot::Background => "background",
+            Slot::Border => "border",
+        })
+    }
+}
+
+impl Preset {
+    /// Sets the foreground and background of `config`, keeping the
+    /// border.
+    pub fn apply(&self, config: &mut GameConfig) {
+        config.foreground = Some(self.palette.foreground);
+        config.background = Some(self.palette.background);
+    }
+}
+
+/// The preset after the one `config` is drawn in, or the first if it
+/// isn't drawn in one.
+#[must_use]
+pub fn next_preset(config: &GameConfig) -> &'static Preset {
+    let colors = (
+        Slot::Foreground.color(config),
+        Slot::Background.color(config),
+    );
+    let next = PRESETS
+        .iter()
+        .position(|preset| (preset.palette.foreground, preset.palette.background) == colors)
+        .map_or(0, |i| (i + 1) % PRESETS.len());
+    &PRESETS[next]
+}
+
+/// Only the colors of `config`, for saving them without the other
+/// settings.
+#[must_use]
+pub fn colors(config: &GameConfig) -> GameConfig {
+    GameConfig {
+        foreground: config.foreground,
+        background: config.background,
+        border: config.border,
+        ..GameConfig::default()
+    }
+}
+
+/// The colors `config` draws the screen in, white on black unless set.
+#[must_use]
+pub fn screen_palette(config: &GameConfig) -> Palette {
+    Palette {
+        foreground: Slot::Foreground.color(config),
+        background: Slot::Background.color(config),
+    }
+}
+
+/// Edits the colors of a config a channel at a time, remembering them
+/// as they were opened with to cancel.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PaletteEditor {
+    /// Index into `SLOTS`.
+    slot: usize,
+    /// Index into `CHANNELS`.
+    channel: usize,
+    original: [Option<Rgba>; SLOTS.len()],
+}
+
+impl PaletteEditor {
+    /// Opens the editor on the colors of `config`, at the foreground's
+    /// red channel.
+    #[must_use]
+    pub fn new(config: &GameConfig) -> Self {
+        Self {
+            slot: 0,
+            channel: 0,
+            original: SLOTS.map(|slot| slot.get(config)),
+        }
+    }
+
+    /// The color being edited.
+    #[must_use]
+    pub fn slot(&self) -> Slot {
+        SLOTS[self.slot]
+    }
+
+    /// The channel being edited, an index into [`CHANNELS`].
+    #[must_use]
+    pub fn channel(&self) -> usize {
+        self.channel
+    }
+
+    /// Moves `delta` colors down the list, wrapping around.
+    pub fn select_slot(&mut self, delta: isize) {
+        self.slot = (self.slot as isize + delta).rem_euclid(SLOTS.len() as isize) as usize;
+    }
+
+    /// Moves `delta` channels along, wrapping around.
+    pub fn select_channel(&mut self, delta: isize) {
+        self.channel = (self.channel as isize + delta).rem_euclid(CHANNELS.len() as isize) as usize;
+    }
+
+    /// Adds `delta` to the channel being edited in `config`, stopping
+    /// at 0 and 255. An unset color starts from its default.
+    pub fn adjust(&self, config: &mut GameConfig, delta: i16) {
+        let slot = self.slot();
+        let color = slot.color(config);
+        let mut channels = color.to_bytes();
+        let channel = &mut channels[self.channel];
+        *channel = (*channel as i16 + delta).clamp(0, 255) as u8;
+        let [r, g, b, a] = channels;
+        slot.set(config, Some(Rgba(r, g, b, a)));
+    }
+
+    /// Returns true if `config` has different colors from when the
+    /// editor was opened.
+    #[must_use]
+    pub fn is_changed(&self, config: &GameConfig) -> bool {
+        SLOTS.map(|slot| slot.get(config)) != self.original
+    }
+
+    /// Puts the colors of `config` back as they were opened with.
+    pub fn cancel(&self, config: &mut GameConfig) {
+        for (slot, color) in SLOTS.iter().zip(self.original) {
+            slot.set(config, color);
+        }
+    }
+}
+
+impl ConfigStore {
+    /// Saves the colors of `config` for `hash`, or for every game if
+    /// `None`, keeping the other settings saved there.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the saved config can't be read or parsed, or
+    /// the file can't be written.
+    pub fn save_colors(&self, hash: Option<&str>, config: &GameConfig) -> io::Result<()> {
+        let saved = match hash {
+            Some(hash) => self.load(hash)?,
+            None => self.load_defaults()?,
+        };
+        let merged = saved.unwrap_or_default().merge(&colors(config));
+        match hash {
+            Some(hash) => self.save(hash, &merged),
+            None => self.save_defaults(&merged),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cycles_presets() {
+        let mut config = GameConfig::default();
+        for preset in PRESETS.iter().cycle().skip(1).take(PRESETS.len() + 1) {
+            let next = next_preset(&config);
+            assert_eq!(next, preset);
+            next.apply(&mut config);
+        }
+        // Colors of no preset start over.
+        config.foreground = Some(Rgba::rgb(1, 2, 3));
+        assert_eq!(next_preset(&config), &PRESETS[0]);
+    }
+
+    #[test]
+    fn edit_channels() {
+        let mut config = GameConfig {
+            border: Some(Rgba::rgb(0x20, 0x20, 0x20)),
+            ..Default::default()
+        };
+        let mut editor = PaletteEditor::new(&config);
+        assert!(!editor.is_changed(&config));
+
+        // Wraps around to blue.
+        editor.select_channel(-1);
+        assert_eq!(CHANNELS[editor.channel()], "blue");
+        editor.adjust(&mut config, -10);
+        assert_eq!(config.foreground, Some(Rgba::rgb(0xFF, 0xFF, 0xF5)));
+        editor.adjust(&mut config, 100);
+        assert_eq!(config.foreground, Some(Rgba::rgb(0xFF, 0xFF, 0xFF)));
+
+        editor.select_slot(-1);
+        assert_eq!(editor.slot(), Slot::Border);
+        editor.adjust(&mut config, -0x40);
+        assert_eq!(config.border, Some(Rgba::rgb(0x20, 0x20, 0)));
+        assert!(editor.is_changed(&config));
+
+        editor.cancel(&mut config);
+        assert_eq!(
+            SLOTS.map(|slot| slot.get(&config)),
+            [None, None, Some(Rgba::rgb(0x20, 0x20, 0x20))]
+        );
+        assert!(!editor.is_changed(&config));
+    }
+
+    #[test]
+    fn save_colors() {
+        let dir = std::env::temp_dir().join(format!("oxid8-palette-{}", std::process::id()));
+        let store = ConfigStore::new(&dir);
+        let hash = "da39a3ee5e6b4b0d3255bfef95601890afd80709";
+        store
+            .save(
+                hash,
+                &GameConfig {
+                    cycles_per_frame: Some(30),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+
+        let mut config = GameConfig {
+            cycles_per_frame: Some(5),
+            ..Default::default()
+        };
+        PRESETS[3].apply(&mut config);
+        store.save_colors(Some(hash), &config).unwrap();
+        let saved = store.load(hash).unwrap().unwrap();
+        assert_eq!(saved.cycles_per_frame, Some(30));
+        assert_eq!(saved.foreground, Some(PRESETS[3].palette.foreground));
+
+        store.save_colors(None, &config).unwrap();
+        assert_eq!(store.load_defaults().unwrap(), Some(colors(&config)));
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,131 @@
+//! JSON playlists of ROMs.
+//!
+//! A playlist lists ROMs in the order they should be shown, with
+//! optional display names and per-entry [`GameConfig`] overrides, so a
+//! curated set of games can be handed to a frontend as a single file.
+//!
+//! ```json
+//! {
+//!   "name": "Arcade",
+//!   "entries": [
+//!     { "path": "pong.ch8", "name": "Pong", "keymap": "two-player" },
+//!     { "path": "roms/blitz.ch8", "cycles_per_frame": 15, "quirks": { "wrap_x": true } }
+//!   ]
+//! }
+//! ```
+//!
+//! Relative paths are resolved against the directory of the playlist.
+
+use serde::{Deserialize, Serialize};
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use crate::config::GameConfig;
+
+/// An ordered list of ROMs.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Playlist {
+    /// Title of the playlist.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    pub entries: Vec<PlaylistEntry>,
+}
+
+/// A ROM in a [`Playlist`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlaylistEntry {
+    pub path: PathBuf,
+    /// Display name, defaults to one derived from the path.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// Overrides applied on top of the settings saved for the ROM.
+    #[serde(flatten)]
+    pub config: GameConfig,
+}
+
+impl Playlist {
+    /// Parses a playlist from JSON.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error message if the JSON is malformed.
+    pub fn from_json(s: &str) -> Result<Self, String> {
+        serde_json::from_str(s).map_err(|err| err.to_string())
+    }
+
+    /// Serializes the playlist as pretty printed JSON.
+    #[must_use]
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("Playlist is always serializable")
+    }
+
+    /// Loads a playlist from `path`, resolving relative entry paths
+    /// against its directory.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read or parsed.
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref();
+        let mut playlist = Self::from_json(&fs::read_to_string(path)?)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        let dir = path.parent().unwrap_or(Path::new(""));
+        for entry in &mut playlist.entries {
+            entry.path = dir.join(&entry.path);
+        }
+        Ok(playlist)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keymap::Keymap;
+
+    #[test]
+    fn json_roundtrip() {
+        let playlist = Playlist::from_json(
+            r#"{
+                "name": "Arcade",
+                "entries": [
+                    { "path": "pong.ch8", "name": "Pong", "keymap": "two-player" },
+                    { "path": "blitz.ch8", "cycles_per_frame": 15, "quirks": { "wrap_x": true } }
+                ]
+            }"#,
+        )
+        .unwrap();
+
+        let [pong, blitz] = &playlist.entries[..] else {
+            panic!("{playlist:?}");
+        };
+        assert_eq!(pong.name.as_deref(), Some("Pong"));
+        assert_eq!(pong.config.keymap, Some(Keymap::TwoPlayer));
+        assert_eq!(blitz.name, None);
+        assert_eq!(blitz.config.cycles_per_frame, Some(15));
+        assert_eq!(blitz.config.quirks.unwrap().wrap_x, Some(true));
+
+        assert_eq!(Playlist::from_json(&playlist.to_json()), Ok(playlist));
+        assert!(Playlist::from_json(r#"{ "entries": [{}] }"#).is_err());
+    }
+
+    #[test]
+    fn relative_paths() {
+        let dir = std::env::temp_dir().join(format!("oxid8-playlist-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("list.json");
+        fs::write(
+            &path,
+            r#"{ "entries": [{ "path": "a.ch8" }, { "path": "/roms/b.ch8" }] }"#,
+        )
+        .unwrap();
+
+        let playlist = Playlist::load(&path).unwrap();
+        assert_eq!(playlist.entries[0].path, dir.join("a.ch8"));
+        assert_eq!(playlist.entries[1].path, Path::new("/roms/b.ch8"));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
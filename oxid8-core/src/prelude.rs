@@ -0,0 +1,29 @@
+//! The types most frontends need, for glob importing.
+//!
+//! ```
+//! use oxid8_core::prelude::*;
+//!
+//! let mut emu = Oxid8::new();
+//! emu.load_font();
+//! emu.load_rom_bytes(&[0x12, 0x00]).unwrap();
+//! emu.set_quirks(Quirks::default());
+//!
+//! let mut clock = FrameClock::new(CPU_HZ, emu.timer_rate().hz());
+//! clock.tick(std::time::Duration::ZERO);
+//! emu.next_frame().unwrap();
+//! let state: SaveState = emu.save_state();
+//! assert_eq!(emu.screen_ref().len(), SCREEN_WIDTH * SCREEN_HEIGHT);
+//! # emu.restore_state(&state);
+//! ```
+//!
+//! There is no error type to import. Emulation errors are `String`s
+//! and loading roms fails with an `io::Error`.
+
+pub use crate::{
+    CPU_HZ, Oxid8, SCREEN_AREA, SCREEN_HEIGHT, SCREEN_WIDTH, TimerRate,
+    clock::{CatchUp, FrameClock, Ticks},
+    input::InputEvent,
+    quirks::Quirks,
+    savestate::SaveState,
+    variant::Variant,
+};
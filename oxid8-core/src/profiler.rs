@@ -0,0 +1,274 @@
+//! Profiling Chip-8 programs for callgrind viewers.
+//!
+//! [`Profiler`] runs frames and instructions like [`Oxid8::next_frame_with_input`]
+//! and [`Oxid8::run_cycle`], counting the instructions run at every
+//! address. Calls are rebuilt from `2nnn` and `00EE`: each subroutine
+//! is a function named by its address, the program's entry point is
+//! `main`, and an instruction counts towards the subroutine it ran in.
+//!
+//! [`Profiler::to_callgrind`] writes the counts in the callgrind
+//! format, so a program's hot loops and expensive subroutines can be
+//! browsed in KCachegrind or QCachegrind. Positions are addresses and
+//! the only event is instructions run.
+//!
+//! ```
+//! use oxid8_core::{Oxid8, profiler::Profiler};
+//!
+//! let mut emu = Oxid8::new();
+//! // CALL 206; JP 202; ADD V0, 1; RET
+//! emu.load_rom_bytes(&[0x22, 0x06, 0x12, 0x02, 0x00, 0x00, 0x70, 0x01, 0x00, 0xEE])
+//!     .unwrap();
+//!
+//! let mut profiler = Profiler::default();
+//! for _ in 0..4 {
+//!     profiler.run_cycle(&mut emu).unwrap();
+//! }
+//! let out = profiler.to_callgrind();
+//! assert!(out.contains("cfn=0x206\ncalls=1 0x206\n0x200 2\n"));
+//! ```
+
+use std::{cell::RefCell, collections::BTreeMap, fmt::Write};
+
+use crate::{Oxid8, input::InputEvent, layout::START_ADDR};
+
+/// A call that has not returned yet.
+#[derive(Debug, Clone, Copy)]
+struct Frame {
+    /// Function the call was made from.
+    caller: u16,
+    /// Address of the `2nnn`.
+    site: u16,
+    callee: u16,
+    /// Instructions run before the call, not counting the `2nnn`.
+    start: u64,
+}
+
+/// Calls from one site to one function.
+#[derive(Debug, Clone, Copy, Default)]
+struct Calls {
+    count: u64,
+    /// Instructions run inside the calls, the `00EE`s included.
+    inclusive: u64,
+}
+
+/// Instruction counts by address and subroutine.
+#[derive(Debug, Clone)]
+pub struct Profiler {
+    /// Instructions run, by function and address.
+    counts: BTreeMap<(u16, u16), u64>,
+    /// Completed calls, by caller, call site, and callee.
+    calls: BTreeMap<(u16, u16, u16), Calls>,
+    stack: Vec<Frame>,
+    total: u64,
+}
+
+impl Default for Profiler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Profiler {
+    /// An empty profile. Instructions run before any call count towards
+    /// `main`.
+    #[must_use]
+    pub fn new() -> Self {
+        Profiler {
+            counts: BTreeMap::new(),
+            calls: BTreeMap::new(),
+            stack: Vec::new(),
+            total: 0,
+        }
+    }
+
+    /// Emulates a frame of `core` like `next_frame_with_input`,
+    /// counting every instruction.
+    ///
+    /// # Errors
+    ///
+    /// See `next_frame`. The instruction that failed is counted.
+    ///
+    /// # Panics
+    ///
+    /// See `next_frame_with_input`.
+    pub fn next_frame(&mut self, core: &mut Oxid8, events: &[InputEvent]) -> Result<(), String> {
+        let profiler = RefCell::new(self);
+        core.run_frame(events, |core| {
+            profiler.borrow_mut().record(core);
+            false
+        })
+        .map(|_| ())
+    }
+
+    /// Emulates an instruction of `core` like `run_cycle`, counting it.
+    ///
+    /// # Errors
+    ///
+    /// See `run_cycle`. The instruction is counted anyway.
+    ///
+    /// # Panics
+    ///
+    /// See `run_cycle`.
+    pub fn run_cycle(&mut self, core: &mut Oxid8) -> Result<(), String> {
+        self.record(core);
+        core.run_cycle()
+    }
+
+    /// Counts the instruction `core` is about to run.
+    fn record(&mut self, core: &Oxid8) {
+        let pc = core.pc.get();
+        let byte = |offset| core.ram.get(pc as usize + offset).copied().unwrap_or(0);
+        let opcode = u16::from_be_bytes([byte(0), byte(1)]);
+
+        let function = self.function();
+        *self.counts.entry((function, pc)).or_default() += 1;
+        self.total += 1;
+
+        if opcode & 0xF000 == 0x2000 {
+            self.stack.push(Frame {
+                caller: function,
+                site: pc,
+                callee: opcode & 0x0FFF,
+                start: self.total,
+            });
+        } else if opcode == 0x00EE {
+            // Returns without a call, e.g. from before profiling
+            // started, stay in the caller.
+            if let Some(frame) = self.stack.pop() {
+                let calls = self
+                    .calls
+                    .entry((frame.caller, frame.site, frame.callee))
+                    .or_default();
+                calls.count += 1;
+                calls.inclusive += self.total - frame.start;
+            }
+        }
+    }
+
+    /// The function the next instruction runs in.
+    fn function(&self) -> u16 {
+        self.stack
+            .last()
+            .map_or(START_ADDR.get(), |frame| frame.callee)
+    }
+
+    /// Instructions counted.
+    #[must_use]
+    pub fn total(&self) -> u64 {
+        self.total
+    }
+
+    /// Forgets every count and call.
+    pub fn clear(&mut self) {
+        *self = Self::new();
+    }
+
+    /// The profile in the callgrind format. Calls that have not returned
+    /// yet count up to the last instruction run.
+    #[must_use]
+    pub fn to_callgrind(&self) -> String {
+        let mut calls = self.calls.clone();
+        for frame in &self.stack {
+            let calls = calls
+                .entry((frame.caller, frame.site, frame.callee))
+                .or_default();
+            calls.count += 1;
+            calls.inclusive += self.total - frame.start;
+        }
+
+        let mut out = String::new();
+        // Writing to a String cannot fail.
+        let _ = writeln!(out, "# callgrind format");
+        let _ = writeln!(out, "version: 1");
+        let _ = writeln!(out, "creator: oxid8");
+        let _ = writeln!(out, "positions: instr");
+        let _ = writeln!(out, "events: Instructions");
+        let _ = writeln!(out, "summary: {}", self.total);
+
+        let mut functions: Vec<u16> = self.counts.keys().map(|&(function, _)| function).collect();
+        functions.extend(calls.keys().map(|&(caller, _, _)| caller));
+        functions.sort_unstable();
+        functions.dedup();
+        for function in functions {
+            let _ = writeln!(out, "\nfl=rom\nfn={}", name(function));
+            for (&(_, addr), count) in self.counts.range((function, 0)..=(function, u16::MAX)) {
+                let _ = writeln!(out, "{addr:#05X} {count}");
+            }
+            let from = (function, 0, 0)..=(function, u16::MAX, u16::MAX);
+            for (&(_, site, callee), call) in calls.range(from) {
+                let _ = writeln!(out, "cfn={}", name(callee));
+                let _ = writeln!(out, "calls={} {callee:#05X}", call.count);
+                let _ = writeln!(out, "{site:#05X} {}", call.inclusive);
+            }
+        }
+        out
+    }
+}
+
+/// `main` for the entry point, otherwise the address.
+fn name(function: u16) -> String {
+    match function == START_ADDR.get() {
+        true => "main".into(),
+        false => format!("{function:#05X}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// CALL 208; CALL 208; JP 204; --; CALL 20E; RET; --; ADD V0, 1; RET
+    const ROM: [u8; 18] = [
+        0x22, 0x08, 0x22, 0x08, 0x12, 0x04, 0x00, 0x00, 0x22, 0x0E, 0x00, 0xEE, 0x00, 0x00, 0x70,
+        0x01, 0x00, 0xEE,
+    ];
+
+    fn profile(steps: usize) -> Profiler {
+        let mut emu = Oxid8::new();
+        emu.load_rom_bytes(&ROM).unwrap();
+        let mut profiler = Profiler::new();
+        for _ in 0..steps {
+            profiler.run_cycle(&mut emu).unwrap();
+        }
+        profiler
+    }
+
+    #[test]
+    fn nested_calls() {
+        // Two calls of 208, each calling 20E, then two jumps.
+        let profiler = profile(12);
+        assert_eq!(profiler.total(), 12);
+        let out = profiler.to_callgrind();
+        assert_eq!(
+            out.split("\n\n").skip(1).collect::<Vec<_>>(),
+            [
+                "fl=rom\nfn=main\n0x200 1\n0x202 1\n0x204 2\ncfn=0x208\ncalls=1 0x208\n0x200 4\ncfn=0x208\ncalls=1 0x208\n0x202 4",
+                "fl=rom\nfn=0x208\n0x208 2\n0x20A 2\ncfn=0x20E\ncalls=2 0x20E\n0x208 4",
+                "fl=rom\nfn=0x20E\n0x20E 2\n0x210 2\n",
+            ]
+        );
+        assert!(out.contains("summary: 12\n"));
+    }
+
+    #[test]
+    fn open_calls_count_so_far() {
+        // Inside the first call of 20E, after ADD.
+        let profiler = profile(3);
+        let out = profiler.to_callgrind();
+        assert!(out.contains("cfn=0x208\ncalls=1 0x208\n0x200 2\n"), "{out}");
+        assert!(out.contains("cfn=0x20E\ncalls=1 0x20E\n0x208 1\n"), "{out}");
+
+        let mut profiler = profiler;
+        profiler.clear();
+        assert_eq!(profiler.total(), 0);
+    }
+
+    #[test]
+    fn frames() {
+        let mut emu = Oxid8::new();
+        emu.load_rom_bytes(&ROM).unwrap();
+        let mut profiler = Profiler::default();
+        profiler.next_frame(&mut emu, &[]).unwrap();
+        assert_eq!(profiler.total(), u64::from(emu.cycles_per_frame()));
+    }
+}
@@ -0,0 +1,130 @@
+//! Behaviour that differs between CHIP-8 interpreters.
+//!
+//! The defaults match the interpreter's historical behaviour. Games
+//! written for a specific platform may need some quirks changed to
+//! run correctly.
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Interpreter quirks, see [`crate::Oxid8::set_quirks`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct Quirks {
+    /// Source of random bytes for `Cxkk`.
+    pub rng: RngMode,
+    /// `Dxyn` wraps sprite pixels past the right edge around to the
+    /// left edge instead of clipping them.
+    pub wrap_x: bool,
+    /// `Dxyn` wraps sprite pixels past the bottom edge around to the
+    /// top edge instead of clipping them.
+    pub wrap_y: bool,
+    /// When `Fx0A` stops waiting.
+    pub key_wait: KeyWait,
+    /// `Fx1E` sets VF to 1 when I goes past 0xFFF and to 0 otherwise,
+    /// like the Amiga interpreter. Some games, e.g. Spacefight 2091!,
+    /// rely on it. The VIP left VF alone.
+    pub i_overflow: bool,
+    /// `Bxnn` jumps to `xnn` + Vx like SCHIP, instead of `nnn` + V0.
+    pub jump_vx: bool,
+    /// Which register `8xy6` and `8xyE` shift.
+    pub shift: Shift,
+}
+
+/// How `Cxkk` generates random bytes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum RngMode {
+    /// Uniformly distributed bytes from a general purpose PRNG.
+    /// Every run is different unless the rng is seeded.
+    #[default]
+    Uniform,
+    /// Bytes from a 16-bit linear feedback shift register that is
+    /// stepped on every cpu cycle, not only on `Cxkk`. It is not the
+    /// COSMAC VIP's routine, only as predictable.
+    ///
+    /// The sequence always starts from the same seed, so the values a
+    /// game sees depend only on its inputs and the timing of its
+    /// `Cxkk` instructions, the same levels on every boot. Read as
+    /// "vip" too, its name before.
+    #[cfg_attr(feature = "serde", serde(alias = "vip"))]
+    Lfsr,
+}
+
+/// How `Fx0A` waits for a key.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum KeyWait {
+    /// Waits for a key to be pressed and released again, like the
+    /// COSMAC VIP, so the key isn't still down when the game moves on.
+    #[default]
+    Release,
+    /// Stops waiting as soon as a key is down, like many later
+    /// interpreters. A key held from before the wait counts too.
+    Press,
+}
+
+/// How `8xy6` and `8xyE` shift.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum Shift {
+    /// Shifts Vx in place and ignores Vy, like SCHIP.
+    #[default]
+    Vx,
+    /// Shifts Vy and stores the result in Vx, like the COSMAC VIP.
+    Vy,
+}
+
+/// Formatted like the `:quirk` command, e.g. "rng=lfsr wrap_x=on
+/// wrap_y=off key_wait=release i_overflow=off jump_vx=off shift=vx".
+impl fmt::Display for Quirks {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let on = |b: bool| if b { "on" } else { "off" };
+        write!(
+            f,
+            "rng={} wrap_x={} wrap_y={} key_wait={} i_overflow={} jump_vx={} shift={}",
+            self.rng,
+            on(self.wrap_x),
+            on(self.wrap_y),
+            self.key_wait,
+            on(self.i_overflow),
+            on(self.jump_vx),
+            self.shift
+        )
+    }
+}
+
+/// Formatted as "uniform" or "lfsr".
+impl fmt::Display for RngMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            RngMode::Uniform => "uniform",
+            RngMode::Lfsr => "lfsr",
+        })
+    }
+}
+
+/// Formatted as "release" or "press".
+impl fmt::Display for KeyWait {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            KeyWait::Release => "release",
+            KeyWait::Press => "press",
+        })
+    }
+}
+
+/// Formatted as "vx" or "vy".
+impl fmt::Display for Shift {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Shift::Vx => "vx",
+            Shift::Vy => "vy",
+        })
+    }
+}
@@ -0,0 +1,497 @@
+//! Minimal reproductions of core bugs, kept as regression tests.
+//!
+//! A [`Repro`] is the machine state just before a bug, the RAM it needs,
+//! and how many cycles to run. [`Repro::check`] captures one when the
+//! core errors or panics, and `oxid-replay --repro <out>` does this for
+//! the frame where a movie fails. Once the bug is fixed, add `expect`
+//! lines for the correct result and save the file to
+//! `oxid8-core/tests/repros/`, `cargo test` replays every file there.
+//!
+//! Repros are plain text, one field per line:
+//!
+//! ```text
+//! # 8XY4 with X = F: the carry replaces the sum
+//! cycles 1
+//! pc 0x200
+//! v 00 01 00 00 00 00 00 00 00 00 00 00 00 00 00 FF
+//! ram 0x200 8F 14
+//! expect vf 1
+//! ```
+//!
+//! Numbers are hex except `cycles` and `sp`. Every line but `cycles` is
+//! optional and defaults to zero or off:
+//!
+//! - `pc`, `i`, `sp`, `dt`, `st`, and `v` for V0 to VF
+//! - `stack` for the return addresses below `sp`
+//! - `keys` for held keys, and `stored_key` for a key pressed in Fx0A
+//! - `font` for the built-in font, and `ram <addr> <bytes>` for the rest
+//!   of RAM
+//! - `rng lfsr`, `wrap_x`, `wrap_y`, `key_wait press`, `i_overflow`,
+//!   `jump_vx`, and `shift vy` for non-default [`Quirks`]
+//! - `expect <field> <value>` to check a [`Field`] after the last cycle,
+//!   and `expect error <message>` to expect a cycle to fail
+//!
+//! The screen starts blank and the uniform rng is seeded with 0, so a
+//! repro runs the same every time. Timers don't count down.
+
+use std::{
+    any::Any,
+    fmt,
+    panic::{self, AssertUnwindSafe},
+    str::FromStr,
+};
+
+use crate::{
+    FONTSET, NUM_REGS, Oxid8, SCREEN_AREA, STACK_SIZE,
+    golden::{Field, Mismatch},
+    keylog,
+    layout::{FONT_ADDR, FONTSET_SIZE, RAM_SIZE},
+    quirks::{KeyWait, Quirks, RngMode, Shift},
+    rng::Rng,
+    savestate::SaveState,
+};
+
+/// Most bytes written on one `ram` line.
+const RAM_LINE_LEN: usize = 16;
+
+/// Zero bytes kept inside a `ram` line rather than starting a new one.
+const RAM_LINE_GAP: usize = 4;
+
+/// A result checked after a [`Repro`] runs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Expect {
+    /// A field has this value after the last cycle.
+    Field(Field, u16),
+    /// A cycle fails with this message.
+    Error(String),
+}
+
+/// Formatted like a repro line, e.g. "expect v0 2a".
+impl fmt::Display for Expect {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Expect::Field(field, value) => write!(f, "expect {field} {value:x}"),
+            Expect::Error(message) => write!(f, "expect error {message}"),
+        }
+    }
+}
+
+/// A minimal reproduction of a bug, see the [module docs](self).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Repro {
+    /// Comment lines, usually what went wrong.
+    pub note: Vec<String>,
+    pub state: SaveState,
+    pub quirks: Quirks,
+    /// Cycles to run.
+    pub cycles: u32,
+    pub expect: Vec<Expect>,
+}
+
+impl Repro {
+    /// A repro running `cycles` cycles from the current state of `core`.
+    ///
+    /// The screen, timers counting down, and the uniform rng are not
+    /// kept, see the [module docs](self).
+    #[must_use]
+    pub fn capture(core: &Oxid8, cycles: u32, note: &str) -> Self {
+        let mut state = core.save_state();
+        state.screen = [false; SCREEN_AREA];
+        state.rng = Rng::from_seed(0);
+        state.cycle_remainder = 0;
+        state.rom_hash = None;
+        Repro {
+            note: note.lines().map(str::to_owned).collect(),
+            state,
+            quirks: core.quirks(),
+            cycles,
+            expect: Vec::new(),
+        }
+    }
+
+    /// Runs `cycles` cycles from the current state of `core` on a copy,
+    /// and captures a repro if one of them errors or panics.
+    #[must_use]
+    pub fn check(core: &Oxid8, cycles: u32) -> Option<Self> {
+        let repro = Repro::capture(core, cycles, "");
+        let note = match panic::catch_unwind(AssertUnwindSafe(|| repro.run())) {
+            Ok(Ok(_)) => return None,
+            Ok(Err((cycle, e))) => format!("cycle {cycle}: {e}"),
+            Err(panic) => format!("panicked: {}", panic_message(&*panic)),
+        };
+        Some(Repro {
+            note: vec![note],
+            ..repro
+        })
+    }
+
+    /// Runs the repro and checks its expectations, returning what
+    /// didn't match.
+    pub fn replay(&self) -> Result<(), String> {
+        let expected_error = self.expect.iter().find_map(|expect| match expect {
+            Expect::Error(message) => Some(message),
+            Expect::Field(..) => None,
+        });
+        let core = match (self.run(), expected_error) {
+            (Ok(core), None) => core,
+            (Err((_, e)), Some(message)) if e == *message => return Ok(()),
+            (Err((cycle, e)), _) => return Err(format!("cycle {cycle}: {e}")),
+            (Ok(_), Some(message)) => return Err(format!("expected error: {message}")),
+        };
+
+        let mismatches: Vec<String> = self
+            .expect
+            .iter()
+            .filter_map(|expect| match *expect {
+                Expect::Field(field, expected) => Some(Mismatch {
+                    field,
+                    expected,
+                    actual: field.read(&core),
+                }),
+                Expect::Error(_) => None,
+            })
+            .filter(|m| m.expected != m.actual)
+            .map(|m| m.to_string())
+            .collect();
+        match mismatches.is_empty() {
+            true => Ok(()),
+            false => Err(mismatches.join(", ")),
+        }
+    }
+
+    /// Runs every cycle, or until the one that fails.
+    fn run(&self) -> Result<Oxid8, (u32, String)> {
+        let mut core = Oxid8::new();
+        core.restore_state(&self.state);
+        core.set_quirks(self.quirks);
+        for cycle in 1..=self.cycles {
+            core.run_cycle().map_err(|e| (cycle, e))?;
+        }
+        Ok(core)
+    }
+}
+
+pub(crate) fn panic_message(panic: &(dyn Any + Send)) -> &str {
+    match (panic.downcast_ref::<&str>(), panic.downcast_ref::<String>()) {
+        (Some(s), _) => s,
+        (_, Some(s)) => s,
+        _ => "unknown",
+    }
+}
+
+fn hex_bytes(bytes: &[u8]) -> String {
+    let bytes: Vec<String> = bytes.iter().map(|b| format!("{b:02X}")).collect();
+    bytes.join(" ")
+}
+
+/// Formatted as a repro file, see the [module docs](self).
+impl fmt::Display for Repro {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let state = &self.state;
+        for line in &self.note {
+            writeln!(f, "# {line}")?;
+        }
+        writeln!(f, "cycles {}", self.cycles)?;
+        writeln!(f, "pc {:#05X}", state.pc)?;
+        writeln!(f, "i {:#05X}", state.i_reg)?;
+        writeln!(f, "sp {}", state.sp)?;
+        writeln!(f, "v {}", hex_bytes(&state.v_reg))?;
+        if state.sp > 0 {
+            let stack = state.stack.iter().take(state.sp as usize);
+            let stack: Vec<String> = stack.map(|addr| format!("{addr:#05X}")).collect();
+            writeln!(f, "stack {}", stack.join(" "))?;
+        }
+        writeln!(f, "dt {:X}", state.dt)?;
+        writeln!(f, "st {:X}", state.st)?;
+        if state.keys.contains(&true) {
+            writeln!(f, "keys {}", keylog::held_keys(&state.keys))?;
+        }
+        if let Some(key) = state.stored_key {
+            writeln!(f, "stored_key {key:X}")?;
+        }
+        if self.quirks.rng == RngMode::Lfsr {
+            writeln!(f, "rng lfsr")?;
+        }
+        if self.quirks.wrap_x {
+            writeln!(f, "wrap_x")?;
+        }
+        if self.quirks.wrap_y {
+            writeln!(f, "wrap_y")?;
+        }
+        if self.quirks.key_wait == KeyWait::Press {
+            writeln!(f, "key_wait press")?;
+        }
+        if self.quirks.i_overflow {
+            writeln!(f, "i_overflow")?;
+        }
+        if self.quirks.jump_vx {
+            writeln!(f, "jump_vx")?;
+        }
+        if self.quirks.shift == Shift::Vy {
+            writeln!(f, "shift vy")?;
+        }
+
+        let font = FONT_ADDR.range(FONTSET_SIZE);
+        let has_font = state.ram[font.clone()] == FONTSET;
+        if has_font {
+            writeln!(f, "font")?;
+        }
+        let skip = |addr: usize| has_font && font.contains(&addr);
+        let mut addr = 0;
+        while addr < RAM_SIZE {
+            if state.ram[addr] == 0 || skip(addr) {
+                addr += 1;
+                continue;
+            }
+            // Runs of bytes, split at long gaps of zeros.
+            let start = addr;
+            let mut end = addr + 1;
+            for next in start + 1..(start + RAM_LINE_LEN).min(RAM_SIZE) {
+                if skip(next) || next - end > RAM_LINE_GAP {
+                    break;
+                }
+                if state.ram[next] != 0 {
+                    end = next + 1;
+                }
+            }
+            writeln!(f, "ram {start:#05X} {}", hex_bytes(&state.ram[start..end]))?;
+            addr = end;
+        }
+
+        for expect in &self.expect {
+            writeln!(f, "{expect}")?;
+        }
+        Ok(())
+    }
+}
+
+fn parse_hex(s: &str) -> Result<u16, String> {
+    let digits = s.strip_prefix("0x").unwrap_or(s);
+    u16::from_str_radix(digits, 16).map_err(|_| format!("Invalid hex number: {s}"))
+}
+
+fn parse_byte(s: &str) -> Result<u8, String> {
+    u8::from_str_radix(s, 16).map_err(|_| format!("Invalid byte: {s}"))
+}
+
+impl FromStr for Repro {
+    type Err = String;
+
+    /// Parses a repro file, see the [module docs](self).
+    fn from_str(s: &str) -> Result<Self, String> {
+        let mut state = Oxid8::new().save_state();
+        state.rng = Rng::from_seed(0);
+        let mut repro = Repro {
+            note: Vec::new(),
+            state,
+            quirks: Quirks::default(),
+            cycles: 0,
+            expect: Vec::new(),
+        };
+        let mut cycles = None;
+
+        for (n, line) in s.lines().enumerate() {
+            let line = line.trim();
+            if let Some(note) = line.strip_prefix('#') {
+                repro.note.push(note.trim().to_owned());
+                continue;
+            }
+            let mut words = line.split_whitespace();
+            let Some(name) = words.next() else {
+                continue;
+            };
+            let args: Vec<&str> = words.collect();
+            let state = &mut repro.state;
+            let parsed: Result<(), String> = (|| {
+                let one = || match args[..] {
+                    [arg] => Ok(arg),
+                    _ => Err(format!("{name} takes one value")),
+                };
+                match name {
+                    "cycles" => {
+                        let arg = one()?;
+                        cycles = Some(arg.parse().map_err(|_| format!("Invalid count: {arg}"))?);
+                    }
+                    "pc" => state.pc = parse_hex(one()?)?,
+                    "i" => state.i_reg = parse_hex(one()?)?,
+                    "sp" => {
+                        let arg = one()?;
+                        state.sp = arg
+                            .parse()
+                            .ok()
+                            .filter(|&sp| sp as usize <= STACK_SIZE)
+                            .ok_or_else(|| format!("Invalid stack pointer: {arg}"))?;
+                    }
+                    "dt" => state.dt = parse_byte(one()?)?,
+                    "st" => state.st = parse_byte(one()?)?,
+                    "v" => {
+                        if args.len() != NUM_REGS {
+                            return Err(format!("v takes {NUM_REGS} values"));
+                        }
+                        for (v, arg) in state.v_reg.iter_mut().zip(&args) {
+                            *v = parse_byte(arg)?;
+                        }
+                    }
+                    "stack" => {
+                        if args.len() > STACK_SIZE {
+                            return Err(format!("stack takes up to {STACK_SIZE} values"));
+                        }
+                        for (addr, arg) in state.stack.iter_mut().zip(&args) {
+                            *addr = parse_hex(arg)?;
+                        }
+                    }
+                    "keys" => {
+                        for arg in &args {
+                            let key = parse_byte(arg)? as usize;
+                            *state
+                                .keys
+                                .get_mut(key)
+                                .ok_or_else(|| format!("Invalid key: {arg}"))? = true;
+                        }
+                    }
+                    "stored_key" => {
+                        let arg = one()?;
+                        let key = parse_byte(arg)? as usize;
+                        if key >= state.keys.len() {
+                            return Err(format!("Invalid key: {arg}"));
+                        }
+                        state.stored_key = Some(key);
+                    }
+                    "font" => state.ram[FONT_ADDR.range(FONTSET_SIZE)].copy_from_slice(&FONTSET),
+                    "ram" => {
+                        let (addr, bytes) = args.split_first().ok_or("ram takes an address")?;
+                        let start = parse_hex(addr)? as usize;
+                        let ram = state
+                            .ram
+                            .get_mut(start..start + bytes.len())
+                            .ok_or_else(|| format!("Past the end of RAM: {addr}"))?;
+                        for (byte, arg) in ram.iter_mut().zip(bytes) {
+                            *byte = parse_byte(arg)?;
+                        }
+                    }
+                    "rng" => match one()? {
+                        "lfsr" | "vip" => repro.quirks.rng = RngMode::Lfsr,
+                        "uniform" => repro.quirks.rng = RngMode::Uniform,
+                        arg => return Err(format!("Invalid rng: {arg}")),
+                    },
+                    "wrap_x" => repro.quirks.wrap_x = true,
+                    "wrap_y" => repro.quirks.wrap_y = true,
+                    "key_wait" => match one()? {
+                        "press" => repro.quirks.key_wait = KeyWait::Press,
+                        "release" => repro.quirks.key_wait = KeyWait::Release,
+                        arg => return Err(format!("Invalid key_wait: {arg}")),
+                    },
+                    "i_overflow" => repro.quirks.i_overflow = true,
+                    "jump_vx" => repro.quirks.jump_vx = true,
+                    "shift" => match one()? {
+                        "vy" => repro.quirks.shift = Shift::Vy,
+                        "vx" => repro.quirks.shift = Shift::Vx,
+                        arg => return Err(format!("Invalid shift: {arg}")),
+                    },
+                    "expect" => match args[..] {
+                        ["error", ..] => {
+                            let message = line.split_once("error").unwrap().1.trim();
+                            repro.expect.push(Expect::Error(message.to_owned()));
+                        }
+                        [field, value] => {
+                            let field = Field::parse(field)
+                                .ok_or_else(|| format!("Unknown field: {field}"))?;
+                            repro.expect.push(Expect::Field(field, parse_hex(value)?));
+                        }
+                        _ => return Err("expect takes a field and a value".into()),
+                    },
+                    _ => return Err(format!("Unknown line: {name}")),
+                }
+                Ok(())
+            })();
+            parsed.map_err(|e| format!("line {}: {e}", n + 1))?;
+        }
+
+        repro.cycles = cycles.ok_or("Missing cycles")?;
+        Ok(repro)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn core(rom: &[u8]) -> Oxid8 {
+        let mut core = Oxid8::new();
+        core.load_font();
+        core.load_rom_bytes(rom).unwrap();
+        core
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut core = core(&[0x60, 0x2A, 0x22, 0x08, 0, 0, 0, 0, 0x12, 0x08]);
+        core.set_key(0xA, true);
+        core.set_quirks(Quirks {
+            wrap_x: true,
+            key_wait: KeyWait::Press,
+            i_overflow: true,
+            ..Quirks::default()
+        });
+        for _ in 0..2 {
+            core.run_cycle().unwrap();
+        }
+        let mut repro = Repro::capture(&core, 3, "calls itself\nforever");
+        repro.expect.push(Expect::Field(Field::V(0), 0x2A));
+        repro
+            .expect
+            .push(Expect::Error("Invalid Instruction: 0000 at 512".into()));
+
+        let text = repro.to_string();
+        assert!(text.starts_with("# calls itself\n# forever\ncycles 3\npc 0x208\n"));
+        assert!(text.contains("\nstack 0x204\n"));
+        assert!(text.contains("\nkeys A\n"));
+        assert!(text.contains(
+            "\nwrap_x\nkey_wait press\ni_overflow\nfont\nram 0x200 60 2A 22 08 00 00 00 00 12 08\n"
+        ));
+        assert_eq!(text.parse::<Repro>().unwrap(), repro);
+    }
+
+    #[test]
+    fn replay_checks_expectations() {
+        let mut repro: Repro = "cycles 1\nv 00 01 00 00 00 00 00 00 00 00 00 00 00 00 00 FF\n\
+                                ram 0x200 8F 14\nexpect vf 1\n"
+            .parse()
+            .unwrap();
+        repro.replay().unwrap();
+
+        repro.expect = vec![Expect::Field(Field::V(0xF), 0)];
+        assert_eq!(repro.replay().unwrap_err(), "vf: expected 0, got 1");
+        repro.expect = vec![Expect::Error("Invalid Instruction: 0000 at 512".into())];
+        assert_eq!(
+            repro.replay().unwrap_err(),
+            "expected error: Invalid Instruction: 0000 at 512"
+        );
+    }
+
+    #[test]
+    fn check_captures_errors_and_panics() {
+        assert_eq!(Repro::check(&core(&[0x12, 0x00]), 10), None);
+
+        let repro = Repro::check(&core(&[0x00, 0x00]), 10).unwrap();
+        assert_eq!(repro.note, ["cycle 1: Invalid Instruction: 0000 at 512"]);
+        assert!(repro.replay().is_err());
+
+        // I past the end of RAM.
+        let repro = Repro::check(&core(&[0xAF, 0xFF, 0xFF, 0x55]), 2).unwrap();
+        assert!(repro.note[0].starts_with("panicked: "));
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert_eq!("pc 0x200".parse::<Repro>().unwrap_err(), "Missing cycles");
+        assert_eq!(
+            "cycles 1\nv 00".parse::<Repro>().unwrap_err(),
+            "line 2: v takes 16 values"
+        );
+        assert_eq!(
+            "cycles 1\nram 0xFFF 00 00".parse::<Repro>().unwrap_err(),
+            "line 2: Past the end of RAM: 0xFFF"
+        );
+    }
+}
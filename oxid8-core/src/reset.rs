@@ -0,0 +1,151 @@
+//! What resetting a game does.
+//!
+//! [`Oxid8::reset`] powers the machine off and on: RAM, registers, and
+//! the display are cleared and the rom has to be loaded again. The
+//! reset hotkeys of the frontends instead restart the loaded game with
+//! [`Oxid8::restart`], from a [`SaveState`] taken right after loading.
+//! The rng is restored with the rest of the machine, so the frames
+//! after a restart are exactly the frames after loading, given the same
+//! input.
+//!
+//! Both keep the settings: speed, cycle weights, timer rate, quirks,
+//! variant, and whether invalid opcodes are skipped. What else survives
+//! depends on the variant, see [`ResetPolicy`]. The core only emulates
+//! CHIP-8 so far, which has neither hi-res mode nor persistent flags,
+//! so everything else comes from the savestate. The policy says what a
+//! restart does with them once it does.
+//!
+//! ```
+//! use oxid8_core::Oxid8;
+//!
+//! let mut emu = Oxid8::new();
+//! // RND V0, FF; JP 200
+//! emu.load_rom_bytes(&[0xC0, 0xFF, 0x12, 0x00]).unwrap();
+//! let boot = emu.save_state();
+//! emu.next_frame().unwrap();
+//! let first = emu.save_state();
+//!
+//! emu.set_cycles_per_frame(20);
+//! emu.restart(&boot);
+//! assert_eq!(emu.cycles_per_frame(), 20);
+//! emu.set_cycles_per_frame(10);
+//! emu.next_frame().unwrap();
+//! assert_eq!(emu.save_state(), first);
+//! ```
+//!
+//! [`SaveState`]: crate::savestate::SaveState
+
+use crate::{Oxid8, savestate::SaveState, variant::Variant, writes::RamWrites};
+
+/// What a restart keeps besides the settings, which it always keeps.
+/// The default keeps nothing.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ResetPolicy {
+    /// Keep the 128x64 hi-res mode.
+    pub keep_hires: bool,
+    /// Keep the flags saved with `FX75`.
+    pub keep_flags: bool,
+}
+
+impl Variant {
+    /// What restarting a game for this variant keeps.
+    #[must_use]
+    pub fn reset_policy(self) -> ResetPolicy {
+        match self {
+            // The VIP has neither, a restart is a clean boot.
+            Variant::Chip8 => ResetPolicy::default(),
+            // Programs start in lo-res on the HP48, but the flags are RPL
+            // user flags, which outlive the interpreter.
+            Variant::Schip => ResetPolicy {
+                keep_hires: false,
+                keep_flags: true,
+            },
+            // Octo also starts in lo-res and keeps the flags in local
+            // storage, so games can use them for high scores.
+            Variant::XoChip => ResetPolicy {
+                keep_hires: false,
+                keep_flags: true,
+            },
+        }
+    }
+}
+
+impl Oxid8 {
+    /// What [`Oxid8::restart`] keeps for the variant running.
+    #[must_use]
+    pub fn reset_policy(&self) -> ResetPolicy {
+        self.variant.reset_policy()
+    }
+
+    /// Restart the loaded game from `boot`, a savestate taken right
+    /// after loading it, see the [module docs](self). Keys are released
+    /// and the invalid opcodes skipped are forgotten.
+    pub fn restart(&mut self, boot: &SaveState) {
+        let policy = self.reset_policy();
+        // Only CHIP-8 runs, which keeps nothing the savestate doesn't
+        // restore. Hi-res mode and the flags are carried over here once
+        // the core emulates them.
+        debug_assert_eq!(
+            policy,
+            ResetPolicy::default(),
+            "{} keeps state across restarts",
+            self.variant
+        );
+        self.restore_state(boot);
+        self.clear_key_edges();
+        self.skipped.clear();
+        self.ram_writes = RamWrites::default();
+        #[cfg(feature = "tracing")]
+        tracing::info!(?policy, "Restarted");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        TimerRate,
+        quirks::{Quirks, RngMode},
+    };
+
+    #[test]
+    fn policies() {
+        assert_eq!(Oxid8::new().reset_policy(), Variant::Chip8.reset_policy());
+        assert_eq!(Variant::Chip8.reset_policy(), ResetPolicy::default());
+        for variant in Variant::ALL {
+            let policy = variant.reset_policy();
+            assert!(!policy.keep_hires);
+            assert_eq!(policy.keep_flags, variant != Variant::Chip8);
+        }
+    }
+
+    #[test]
+    fn restart_is_frame_perfect() {
+        // RND V0, FF; LD I, 300; LD [I], V0; ADD V1, 1; JP 200
+        let rom = [0xC0, 0xFF, 0xA3, 0x00, 0xF0, 0x55, 0x71, 0x01, 0x12, 0x00];
+        let mut emu = Oxid8::new();
+        emu.load_rom_bytes(&rom).unwrap();
+        emu.set_timer_rate(TimerRate::Pal);
+        emu.set_quirks(Quirks {
+            rng: RngMode::Lfsr,
+            ..emu.quirks()
+        });
+        let boot = emu.save_state();
+        let frames = |emu: &mut Oxid8| {
+            (0..7)
+                .map(|_| {
+                    emu.next_frame().unwrap();
+                    emu.save_state()
+                })
+                .collect::<Vec<_>>()
+        };
+        let first = frames(&mut emu);
+
+        emu.set_key(3, true);
+        emu.restart(&boot);
+        assert!(!emu.keys_ref()[3] && !emu.just_pressed(3));
+        assert!(emu.take_ram_writes().is_empty());
+        assert_eq!(emu.timer_rate(), TimerRate::Pal);
+        assert_eq!(frames(&mut emu), first);
+    }
+}
@@ -0,0 +1,436 @@
+//! Stepping backwards through instructions for debuggers.
+//!
+//! [`Rewind`] runs frames like [`Oxid8::next_frame_until`] and steps
+//! like [`Oxid8::run_cycle`], keeping a savestate from the start of
+//! each frame with its input. [`Rewind::step_back`] restores the
+//! savestate of the current frame and runs it again up to the
+//! instruction before, so stepping back costs at most a frame of
+//! emulation.
+//!
+//! Every [`KEYFRAME_INTERVAL`] frames the savestate is kept whole, and
+//! the ones between as a [`StateDelta`] against it, so ten seconds of
+//! history take a few hundred kilobytes rather than megabytes.
+//!
+//! Frames run again with the core's current settings, so clear the
+//! history when changing speed, timer rate, or quirks, or restoring a
+//! savestate.
+//!
+//! ```
+//! use oxid8_core::{Oxid8, breakpoint::Breakpoints, rewind::Rewind};
+//!
+//! let mut emu = Oxid8::new();
+//! // ADD V0, 1; JP 200
+//! emu.load_rom_bytes(&[0x70, 0x01, 0x12, 0x00]).unwrap();
+//!
+//! let mut rewind = Rewind::default();
+//! rewind.next_frame(&mut emu, &[], &Breakpoints::default()).unwrap();
+//! let after = emu.save_state();
+//!
+//! // Back over JP 200, then forward again.
+//! assert!(rewind.step_back(&mut emu).unwrap());
+//! assert_eq!(emu.pc(), 0x202);
+//! rewind.step(&mut emu).unwrap();
+//! assert_eq!(emu.pc(), 0x200);
+//! assert_eq!(emu.save_state().v_reg, after.v_reg);
+//! ```
+
+use std::{cell::Cell, collections::VecDeque, mem};
+
+use crate::{
+    Oxid8, breakpoint::Breakpoints, delta::StateDelta, input::InputEvent, savestate::SaveState,
+};
+
+/// Frames kept by default, ten seconds at 60Hz.
+pub const DEFAULT_FRAMES: usize = 600;
+
+/// Frames from one whole savestate to the next, a second at 60Hz.
+pub const KEYFRAME_INTERVAL: usize = 60;
+
+/// The savestate from the start of a frame.
+#[derive(Debug, Clone)]
+enum Snapshot {
+    Key(Box<SaveState>),
+    /// Against the last keyframe before.
+    Delta(StateDelta),
+}
+
+/// A frame run and the instructions stepped after it.
+#[derive(Debug, Clone)]
+struct Frame {
+    start: Snapshot,
+    events: Vec<InputEvent>,
+    /// Instructions the frame ran.
+    ran: u32,
+    /// The frame ran to its end and decremented the timers, rather
+    /// than stopping at a breakpoint.
+    completed: bool,
+    /// Instructions stepped after the frame.
+    steps: u32,
+}
+
+impl Frame {
+    /// Instructions run since the start of the frame.
+    fn len(&self) -> u32 {
+        self.ran + self.steps
+    }
+
+    /// The savestate from the start of the frame, if kept whole.
+    fn key(&self) -> Option<&SaveState> {
+        match &self.start {
+            Snapshot::Key(state) => Some(state),
+            Snapshot::Delta(_) => None,
+        }
+    }
+}
+
+/// Savestates of the last frames run, for stepping back.
+#[derive(Debug, Clone)]
+pub struct Rewind {
+    frames: VecDeque<Frame>,
+    capacity: usize,
+}
+
+impl Default for Rewind {
+    fn default() -> Self {
+        Self::new(DEFAULT_FRAMES)
+    }
+}
+
+impl Rewind {
+    /// Keeps the last `capacity` frames, at least one.
+    #[must_use]
+    pub fn new(capacity: usize) -> Self {
+        let capacity = capacity.max(1);
+        Rewind {
+            frames: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    /// Emulates a frame of `core` like `next_frame_until`, keeping a
+    /// savestate from its start.
+    ///
+    /// # Errors
+    ///
+    /// See `next_frame`. The frame is not kept.
+    ///
+    /// # Panics
+    ///
+    /// See `next_frame_with_input`.
+    pub fn next_frame(
+        &mut self,
+        core: &mut Oxid8,
+        events: &[InputEvent],
+        breakpoints: &Breakpoints,
+    ) -> Result<Option<u16>, String> {
+        let start = core.save_state();
+        let calls = Cell::new(0);
+        let stopped = core.run_frame(events, |core| {
+            calls.set(calls.get() + 1);
+            breakpoints.contains(core.pc.get())
+        })?;
+
+        if self.frames.len() == self.capacity {
+            self.pop_front();
+        }
+        self.frames.push_back(Frame {
+            start: self.snapshot(start),
+            events: events.to_vec(),
+            // The call that stopped the frame ran no instruction.
+            ran: calls.get() - stopped.is_some() as u32,
+            completed: stopped.is_none(),
+            steps: 0,
+        });
+        Ok(stopped)
+    }
+
+    /// Emulates an instruction of `core` like `run_cycle`.
+    ///
+    /// # Errors
+    ///
+    /// See `run_cycle`. The instruction is not kept.
+    ///
+    /// # Panics
+    ///
+    /// See `run_cycle`.
+    pub fn step(&mut self, core: &mut Oxid8) -> Result<(), String> {
+        let start = core.save_state();
+        core.run_cycle()?;
+        match self.frames.back_mut() {
+            Some(frame) => frame.steps += 1,
+            None => self.frames.push_back(Frame {
+                start: Snapshot::Key(Box::new(start)),
+                events: Vec::new(),
+                ran: 0,
+                completed: false,
+                steps: 1,
+            }),
+        }
+        Ok(())
+    }
+
+    /// Returns `core` to before the last instruction it ran. Returns
+    /// false, leaving `core` as it is, if there is no instruction left
+    /// in the history.
+    ///
+    /// The frame that instruction was part of counts as stopped before
+    /// it, so frames run after start fresh like after a breakpoint. Key
+    /// events later in the frame are dropped.
+    ///
+    /// # Errors
+    ///
+    /// If running the frame again fails. It ran before, so only if the
+    /// core's settings changed since.
+    pub fn step_back(&mut self, core: &mut Oxid8) -> Result<bool, String> {
+        // Frames that ran no instructions start where the one before
+        // ended.
+        while self.frames.back().is_some_and(|frame| frame.len() == 0) {
+            self.frames.pop_back();
+        }
+        let Some(frame) = self.frames.back_mut() else {
+            return Ok(false);
+        };
+
+        let target = frame.len() - 1;
+        if target < frame.ran {
+            frame.ran = target;
+            frame.completed = false;
+            frame.events.retain(|event| event.cycle <= target);
+        }
+        frame.steps = target - frame.ran;
+
+        // Run the frame as it ran before, up to the target.
+        core.restore_state(&self.start(self.frames.len() - 1));
+        let frame = &self.frames[self.frames.len() - 1];
+        let stop_at = (!frame.completed).then_some(frame.ran);
+        let calls = Cell::new(0);
+        core.run_frame(&frame.events, |_| {
+            let call = calls.get();
+            calls.set(call + 1);
+            Some(call) == stop_at
+        })?;
+        for _ in 0..frame.steps {
+            core.run_cycle()?;
+        }
+        Ok(true)
+    }
+
+    /// Frames kept.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// Returns true if there are no frames to step back through.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    /// Forgets every frame, e.g. after changing the core's settings.
+    pub fn clear(&mut self) {
+        self.frames.clear();
+    }
+
+    /// Bytes the savestates kept take, roughly.
+    #[must_use]
+    pub fn size(&self) -> usize {
+        self.frames
+            .iter()
+            .map(|frame| match &frame.start {
+                Snapshot::Key(_) => mem::size_of::<SaveState>(),
+                Snapshot::Delta(delta) => delta.size(),
+            })
+            .sum()
+    }
+
+    /// `state` as the start of a new last frame: whole if the last
+    /// keyframe is [`KEYFRAME_INTERVAL`] frames back, else a delta.
+    fn snapshot(&self, state: SaveState) -> Snapshot {
+        match self
+            .frames
+            .iter()
+            .rev()
+            .take(KEYFRAME_INTERVAL - 1)
+            .find_map(Frame::key)
+        {
+            Some(key) => Snapshot::Delta(state.delta(key)),
+            None => Snapshot::Key(Box::new(state)),
+        }
+    }
+
+    /// The savestate from the start of frame `index`.
+    fn start(&self, index: usize) -> SaveState {
+        match &self.frames[index].start {
+            Snapshot::Key(state) => (**state).clone(),
+            Snapshot::Delta(delta) => {
+                // The first frame is always a keyframe.
+                let key = self.frames.range(..index).rev().find_map(Frame::key);
+                delta.apply(key.expect("rewind history starts with a keyframe"))
+            }
+        }
+    }
+
+    /// Forgets the first frame, making the frame after it a keyframe
+    /// for the deltas that were against it.
+    fn pop_front(&mut self) {
+        let Some(first) = self.frames.pop_front() else {
+            return;
+        };
+        let Snapshot::Key(old) = first.start else {
+            return;
+        };
+        let mut key: Option<SaveState> = None;
+        for frame in &mut self.frames {
+            let Snapshot::Delta(delta) = &frame.start else {
+                break;
+            };
+            let state = delta.apply(&old);
+            frame.start = match &key {
+                Some(key) => Snapshot::Delta(state.delta(key)),
+                None => {
+                    key = Some(state.clone());
+                    Snapshot::Key(Box::new(state))
+                }
+            };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// LD V1, 1; LD DT, V0; ADD V0, 1; SKP V1; JP 202; JP 200
+    const ROM: [u8; 12] = [
+        0x61, 0x01, 0xF0, 0x15, 0x70, 0x01, 0xE1, 0x9E, 0x12, 0x02, 0x12, 0x00,
+    ];
+
+    fn core() -> Oxid8 {
+        let mut emu = Oxid8::new();
+        emu.load_rom_bytes(&ROM).unwrap();
+        emu.set_cycles_per_frame(7);
+        emu
+    }
+
+    #[test]
+    fn steps_back_through_every_instruction() {
+        let mut emu = core();
+        let mut rewind = Rewind::default();
+        let mut breakpoints = Breakpoints::default();
+        breakpoints.toggle(0x208);
+        // Key 1 skips back to the start mid-frame, and the timers count
+        // down between frames.
+        let press = InputEvent {
+            cycle: 3,
+            key: 1,
+            pressed: true,
+        };
+
+        // The state before every instruction run, the same frame run
+        // on a copy.
+        let mut before = Vec::new();
+        for frame in 0..8 {
+            let events = if frame % 3 == 0 { vec![press] } else { vec![] };
+            let mut copy = core();
+            copy.restore_state(&emu.save_state());
+            let states = std::cell::RefCell::new(Vec::new());
+            copy.run_frame(&events, |core| {
+                let stop = breakpoints.contains(core.pc.get());
+                if !stop {
+                    states.borrow_mut().push(core.save_state());
+                }
+                stop
+            })
+            .unwrap();
+            before.extend(states.into_inner());
+
+            if rewind
+                .next_frame(&mut emu, &events, &breakpoints)
+                .unwrap()
+                .is_some()
+            {
+                before.push(emu.save_state());
+                rewind.step(&mut emu).unwrap();
+            }
+        }
+
+        for (i, expected) in before.iter().enumerate().rev() {
+            assert!(rewind.step_back(&mut emu).unwrap());
+            assert_eq!(emu.save_state(), *expected, "instruction {i}");
+        }
+        assert!(!rewind.step_back(&mut emu).unwrap());
+    }
+
+    #[test]
+    fn step_back_then_forward_matches() {
+        let mut emu = core();
+        let mut rewind = Rewind::default();
+        let none = Breakpoints::default();
+        for _ in 0..3 {
+            rewind.next_frame(&mut emu, &[], &none).unwrap();
+        }
+        let before = emu.save_state();
+
+        for _ in 0..10 {
+            assert!(rewind.step_back(&mut emu).unwrap());
+        }
+        for _ in 0..10 {
+            rewind.step(&mut emu).unwrap();
+        }
+        // Stepping over a frame's end skips its timer decrement.
+        assert_eq!(emu.pc(), before.pc);
+        assert_eq!(emu.save_state().v_reg, before.v_reg);
+    }
+
+    #[test]
+    fn keeps_capacity() {
+        let mut emu = core();
+        let mut rewind = Rewind::new(2);
+        let none = Breakpoints::default();
+        for _ in 0..5 {
+            rewind.next_frame(&mut emu, &[], &none).unwrap();
+        }
+        assert_eq!(rewind.len(), 2);
+
+        let mut back = 0;
+        while rewind.step_back(&mut emu).unwrap() {
+            back += 1;
+        }
+        assert_eq!(back, 14);
+        assert!(rewind.is_empty());
+        assert!(!rewind.step_back(&mut emu).unwrap());
+
+        rewind.clear();
+        assert!(rewind.is_empty());
+    }
+
+    #[test]
+    fn deltas_rebuild_full_states() {
+        let mut emu = Oxid8::new();
+        emu.load_font();
+        // RND V0, FF; LD I, 300; DRW V0, V0, 5; LD B, V0; JP 200
+        emu.load_rom_bytes(&[0xC0, 0xFF, 0xA3, 0x00, 0xD0, 0x05, 0xF0, 0x33, 0x12, 0x00])
+            .unwrap();
+        let capacity = 2 * KEYFRAME_INTERVAL + 10;
+        let mut rewind = Rewind::new(capacity);
+        let none = Breakpoints::default();
+        let mut starts = VecDeque::new();
+        for _ in 0..3 * KEYFRAME_INTERVAL + 25 {
+            if starts.len() == capacity {
+                starts.pop_front();
+            }
+            starts.push_back(emu.save_state());
+            rewind.next_frame(&mut emu, &[], &none).unwrap();
+        }
+
+        assert_eq!(rewind.len(), capacity);
+        assert!(rewind.frames[0].key().is_some());
+        for (i, expected) in starts.iter().enumerate() {
+            assert_eq!(rewind.start(i), *expected, "frame {i}");
+        }
+        let keyframes = rewind.frames.iter().filter(|f| f.key().is_some()).count();
+        assert_eq!(keyframes, 3);
+        assert!(rewind.size() < capacity * mem::size_of::<SaveState>() / 10);
+    }
+}
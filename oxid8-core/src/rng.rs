@@ -0,0 +1,317 @@
+//! Serializable random number generators.
+//!
+//! All randomness comes from an [`Rng`] owned by the core, so cloning or
+//! serializing it along with the rest of the machine state reproduces
+//! the exact same random bytes after a restore.
+
+use rand::{Rng as _, RngCore, SeedableRng, rand_core::impls};
+use rand_xoshiro::SplitMix64;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::quirks::RngMode;
+
+/// Random number generator state of the core.
+///
+/// See [`crate::Oxid8::rng`] and [`crate::Oxid8::set_rng`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Rng {
+    uniform: Xoshiro256,
+    lfsr: Lfsr,
+}
+
+impl Rng {
+    /// New generator with a fixed seed. The same seed always produces
+    /// the same bytes.
+    #[must_use]
+    pub fn from_seed(seed: u64) -> Self {
+        Self {
+            uniform: Xoshiro256::seed_from_u64(seed),
+            lfsr: Lfsr::default(),
+        }
+    }
+
+    /// New generator seeded from the thread local generator.
+    #[must_use]
+    pub fn from_entropy() -> Self {
+        Self {
+            uniform: Xoshiro256::from_rng(&mut rand::rng()),
+            lfsr: Lfsr::default(),
+        }
+    }
+
+    /// Reseeds the uniform generator, keeping the LFSR.
+    #[cfg(feature = "movie")]
+    pub(crate) fn reseed(&mut self, seed: u64) {
+        self.uniform = Xoshiro256::seed_from_u64(seed);
+    }
+
+    /// The uniform generator.
+    pub(crate) fn uniform(&self) -> &Xoshiro256 {
+        &self.uniform
+    }
+
+    /// Replaces the uniform generator, keeping the LFSR.
+    pub(crate) fn set_uniform(&mut self, uniform: Xoshiro256) {
+        self.uniform = uniform;
+    }
+
+    /// The LFSR generator.
+    pub(crate) fn lfsr(&self) -> Lfsr {
+        self.lfsr
+    }
+
+    /// Replaces the LFSR generator, keeping the uniform one.
+    pub(crate) fn set_lfsr(&mut self, lfsr: Lfsr) {
+        self.lfsr = lfsr;
+    }
+
+    /// Advances the generators that run every cpu cycle.
+    pub(crate) fn step(&mut self) {
+        self.lfsr.step();
+    }
+
+    /// Next random byte for `mode`.
+    pub(crate) fn byte(&mut self, mode: RngMode) -> u8 {
+        match mode {
+            RngMode::Uniform => self.uniform.random(),
+            RngMode::Lfsr => self.lfsr.byte(),
+        }
+    }
+}
+
+impl Default for Rng {
+    fn default() -> Self {
+        Self::from_entropy()
+    }
+}
+
+/// xoshiro256++, the same generator and seeding as `rand_xoshiro`'s
+/// `Xoshiro256PlusPlus`, whose state can't be read back out for
+/// savestates.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub(crate) struct Xoshiro256 {
+    s: [u64; 4],
+}
+
+impl Xoshiro256 {
+    /// The whole state, for [`Xoshiro256::from_state`].
+    pub fn state(&self) -> [u64; 4] {
+        self.s
+    }
+
+    /// A generator at `state`, `None` if it is all zero, the generator
+    /// would never change.
+    pub fn from_state(state: [u64; 4]) -> Option<Self> {
+        (state != [0; 4]).then_some(Self { s: state })
+    }
+}
+
+impl RngCore for Xoshiro256 {
+    fn next_u32(&mut self) -> u32 {
+        // The low bits are the weakest.
+        (self.next_u64() >> 32) as u32
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let s = &mut self.s;
+        let result = s[0].wrapping_add(s[3]).rotate_left(23).wrapping_add(s[0]);
+        let t = s[1] << 17;
+        s[2] ^= s[0];
+        s[3] ^= s[1];
+        s[1] ^= s[2];
+        s[0] ^= s[3];
+        s[2] ^= t;
+        s[3] = s[3].rotate_left(45);
+        result
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        impls::fill_bytes_via_next(self, dest);
+    }
+}
+
+impl SeedableRng for Xoshiro256 {
+    type Seed = [u8; 32];
+
+    fn from_seed(seed: [u8; 32]) -> Self {
+        let s = std::array::from_fn(|i| {
+            u64::from_le_bytes(seed[8 * i..8 * i + 8].try_into().expect("8 bytes"))
+        });
+        Self::from_state(s).unwrap_or_else(|| Self::seed_from_u64(0))
+    }
+
+    fn seed_from_u64(seed: u64) -> Self {
+        Self::from_rng(&mut SplitMix64::seed_from_u64(seed))
+    }
+}
+
+/// 16-bit Galois linear feedback shift register.
+///
+/// Visits every nonzero state once per period of 65535 steps. Over a
+/// full period the low byte takes each nonzero value 256 times and zero
+/// 255 times, so it is close to uniform but entirely predictable.
+///
+/// Deserialized states are checked like seeds passed to [`Lfsr::new`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(try_from = "u16", into = "u16")
+)]
+pub(crate) struct Lfsr(u16);
+
+impl Lfsr {
+    /// Seed used at power on.
+    pub const SEED: u16 = 0xACE1;
+
+    /// Feedback taps for x^16 + x^14 + x^13 + x^11 + 1.
+    const TAPS: u16 = 0xB400;
+
+    /// New register starting at `seed`.
+    ///
+    /// # Panics
+    ///
+    /// `new` panics if `seed` is zero, the register would never change.
+    #[must_use]
+    pub fn new(seed: u16) -> Self {
+        assert_ne!(seed, 0, "LFSR seed must be nonzero");
+        Self(seed)
+    }
+
+    /// Advances the register by one step.
+    pub fn step(&mut self) {
+        let lsb = self.0 & 1;
+        self.0 >>= 1;
+        if lsb == 1 {
+            self.0 ^= Self::TAPS;
+        }
+    }
+
+    /// The low byte of the current state.
+    #[must_use]
+    pub fn byte(&self) -> u8 {
+        self.0 as u8
+    }
+
+    /// The whole state, for [`Lfsr::new`].
+    #[must_use]
+    pub fn state(&self) -> u16 {
+        self.0
+    }
+}
+
+impl Default for Lfsr {
+    fn default() -> Self {
+        Self::new(Self::SEED)
+    }
+}
+
+impl TryFrom<u16> for Lfsr {
+    type Error = String;
+
+    fn try_from(state: u16) -> Result<Self, Self::Error> {
+        match state {
+            0 => Err("LFSR state must be nonzero".into()),
+            state => Ok(Self(state)),
+        }
+    }
+}
+
+impl From<Lfsr> for u16 {
+    fn from(lfsr: Lfsr) -> Self {
+        lfsr.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn seeded() {
+        let mut a = Rng::from_seed(8);
+        let mut b = Rng::from_seed(8);
+        let bytes: Vec<u8> = (0..32).map(|_| a.byte(RngMode::Uniform)).collect();
+        assert!(bytes.iter().all(|&byte| byte == b.byte(RngMode::Uniform)));
+        assert_ne!(bytes, vec![bytes[0]; 32]);
+        assert_ne!(Rng::from_seed(9), Rng::from_seed(8));
+    }
+
+    #[test]
+    fn restore() {
+        let mut rng = Rng::from_entropy();
+        rng.byte(RngMode::Uniform);
+        rng.step();
+
+        let mut saved = rng.clone();
+        for mode in [RngMode::Uniform, RngMode::Lfsr] {
+            assert_eq!(rng.byte(mode), saved.byte(mode));
+        }
+    }
+
+    #[test]
+    fn xoshiro_reference() {
+        use rand_xoshiro::Xoshiro256PlusPlus;
+
+        for seed in [0, 1, 0xACE1] {
+            let mut ours = Xoshiro256::seed_from_u64(seed);
+            let mut theirs = Xoshiro256PlusPlus::seed_from_u64(seed);
+            for _ in 0..64 {
+                assert_eq!(ours.next_u64(), theirs.next_u64());
+                assert_eq!(ours.random::<u8>(), theirs.random::<u8>());
+            }
+        }
+        let seed = std::array::from_fn(|i| i as u8);
+        let mut ours = Xoshiro256::from_seed(seed);
+        let mut theirs = Xoshiro256PlusPlus::from_seed(seed);
+        assert_eq!(ours.next_u64(), theirs.next_u64());
+        assert_eq!(Xoshiro256::from_seed([0; 32]), Xoshiro256::seed_from_u64(0));
+        assert_eq!(Xoshiro256::from_state([0; 4]), None);
+    }
+
+    #[test]
+    fn lfsr_period() {
+        let mut lfsr = Lfsr::default();
+        let mut counts = [0u32; 256];
+        for _ in 0..u16::MAX {
+            counts[lfsr.byte() as usize] += 1;
+            lfsr.step();
+        }
+        assert_eq!(lfsr, Lfsr::default());
+        assert_eq!(counts[0], 255);
+        assert!(counts[1..].iter().all(|&n| n == 256));
+    }
+
+    #[test]
+    fn lfsr_sequence() {
+        let mut lfsr = Lfsr::default();
+        let states: Vec<u16> = (0..6)
+            .map(|_| {
+                let state = lfsr.state();
+                lfsr.step();
+                state
+            })
+            .collect();
+        assert_eq!(states, [0xACE1, 0xE270, 0x7138, 0x389C, 0x1C4E, 0x0E27]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn lfsr_zero_seed() {
+        let _ = Lfsr::new(0);
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn lfsr_zero_state() {
+        use serde::de::{IntoDeserializer, value::Error};
+
+        let lfsr = Lfsr::deserialize(IntoDeserializer::<Error>::into_deserializer(0x1C4Eu16));
+        assert_eq!(lfsr.unwrap().state(), 0x1C4E);
+        let lfsr = Lfsr::deserialize(IntoDeserializer::<Error>::into_deserializer(0u16));
+        assert!(lfsr.unwrap_err().to_string().contains("nonzero"));
+    }
+}
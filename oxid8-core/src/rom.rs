@@ -0,0 +1,180 @@
+//! Reading roms from files or standard input.
+//!
+//! [`read`] returns the bytes of a rom, for
+//! [`Oxid8::load_rom_bytes`](crate::Oxid8::load_rom_bytes), which
+//! [`Oxid8::load_rom`](crate::Oxid8::load_rom) uses. A path of
+//! [`STDIN`], `-`, reads the rom from standard input instead, so it can
+//! be piped into a frontend, e.g. `cat game.ch8 | oxid-cli -`. A file
+//! named `-` is still read as `./-`.
+//!
+//! With the `octo` feature enabled, `.o8` files are assembled. A rom
+//! piped in is always bytes, assemble it first. Zip archives are
+//! recognised by their contents, so they can be piped in too.
+//!
+//! ```no_run
+//! use oxid8_core::{Oxid8, rom};
+//!
+//! let mut emu = Oxid8::new();
+//! let bytes = rom::read(rom::STDIN)?;
+//! emu.load_rom_bytes(&bytes)?;
+//! # Ok::<(), std::io::Error>(())
+//! ```
+//!
+//! Tiny programs shared as text, e.g. in a forum post, are read with
+//! [`parse_hex`], so frontends can load them from the clipboard.
+//!
+//! ```
+//! use oxid8_core::rom;
+//!
+//! let rom = rom::parse_hex("0200: 00E0 A22A\n0x60, 0x0C  # LD V0, C").unwrap();
+//! assert_eq!(rom, [0x00, 0xE0, 0xA2, 0x2A, 0x60, 0x0C]);
+//! ```
+
+use std::{
+    fs,
+    io::{self, IsTerminal, Read},
+    path::Path,
+};
+
+use crate::layout::MAX_ROM_SIZE;
+
+/// The path that reads the rom from standard input.
+pub const STDIN: &str = "-";
+
+/// Returns true if `path` reads the rom from standard input.
+#[must_use]
+pub fn is_stdin(path: impl AsRef<Path>) -> bool {
+    path.as_ref().as_os_str() == STDIN
+}
+
+/// The bytes of the rom at `path`, or piped into standard input if it
+/// is [`STDIN`].
+///
+/// # Errors
+///
+/// If the file can't be read, nothing is piped in, or a `.o8` file
+/// doesn't assemble.
+pub fn read(path: impl AsRef<Path>) -> io::Result<Vec<u8>> {
+    let path = path.as_ref();
+    if is_stdin(path) {
+        return read_stdin();
+    }
+
+    #[cfg(feature = "octo")]
+    if path.extension().is_some_and(|ext| ext == "o8") {
+        let source = fs::read_to_string(path)?;
+        return crate::octo::assemble(&source)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err));
+    }
+
+    fs::read(path)
+}
+
+fn read_stdin() -> io::Result<Vec<u8>> {
+    let stdin = io::stdin();
+    // Waiting on the keyboard would look like a hang.
+    if stdin.is_terminal() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "no rom piped into standard input",
+        ));
+    }
+    let mut rom = Vec::new();
+    stdin.lock().read_to_end(&mut rom)?;
+    if rom.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            "empty rom on standard input",
+        ));
+    }
+    Ok(rom)
+}
+
+/// The bytes of a rom written as hex, like `00E0 A22A`, `00 e0 a2 2a`,
+/// or `0x00, 0xE0`. Bytes may run together in words of any even
+/// length. Addresses ending in `:` starting a line, like `0200:`, and
+/// comments after `#` or `;` are skipped.
+///
+/// # Errors
+///
+/// If the text holds anything else, an odd number of digits in a word,
+/// no bytes, or more than fit in memory.
+pub fn parse_hex(text: &str) -> Result<Vec<u8>, String> {
+    let mut rom = Vec::new();
+    for line in text.lines() {
+        let line = line.split(['#', ';']).next().unwrap_or_default();
+        let mut words = line
+            .split(|c: char| c.is_whitespace() || c == ',')
+            .filter(|word| !word.is_empty())
+            .peekable();
+        words.next_if(|word| word.ends_with(':'));
+        for word in words {
+            let digits = ["0x", "0X", "$"]
+                .iter()
+                .find_map(|prefix| word.strip_prefix(prefix))
+                .unwrap_or(word);
+            if digits.is_empty() || !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+                return Err(format!("Not hex: {word}"));
+            }
+            if digits.len() % 2 != 0 {
+                return Err(format!("Odd number of hex digits: {word}"));
+            }
+            for pair in digits.as_bytes().chunks(2) {
+                let pair = std::str::from_utf8(pair).unwrap_or_default();
+                rom.push(u8::from_str_radix(pair, 16).map_err(|err| err.to_string())?);
+            }
+        }
+    }
+    if rom.is_empty() {
+        return Err("No hex bytes".into());
+    }
+    if rom.len() > MAX_ROM_SIZE {
+        return Err(format!("ROM too large: {} bytes", rom.len()));
+    }
+    Ok(rom)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn paths() {
+        assert!(is_stdin("-"));
+        assert!(!is_stdin("./-"));
+        assert!(!is_stdin("game.ch8"));
+
+        let path = std::env::temp_dir().join(format!("oxid8-rom-{}.ch8", std::process::id()));
+        fs::write(&path, [0x12, 0x00]).unwrap();
+        assert_eq!(read(&path).unwrap(), [0x12, 0x00]);
+        fs::remove_file(&path).unwrap();
+        assert!(read(&path).is_err());
+    }
+
+    #[test]
+    fn hex() {
+        assert_eq!(parse_hex("00e0a22a").unwrap(), [0x00, 0xE0, 0xA2, 0x2A]);
+        assert_eq!(
+            parse_hex("200: $12 $00 ; JP 200\n\n202: 0X12,0X02").unwrap(),
+            [0x12, 0x00, 0x12, 0x02]
+        );
+        assert_eq!(parse_hex("00E0 A2:").unwrap_err(), "Not hex: A2:");
+        assert_eq!(
+            parse_hex("00E0 A22").unwrap_err(),
+            "Odd number of hex digits: A22"
+        );
+        assert_eq!(parse_hex("00 E0 G1").unwrap_err(), "Not hex: G1");
+        assert_eq!(parse_hex("0x").unwrap_err(), "Not hex: 0x");
+        assert_eq!(parse_hex(" # nothing").unwrap_err(), "No hex bytes");
+        assert!(parse_hex(&"00".repeat(MAX_ROM_SIZE + 1)).is_err());
+    }
+
+    #[cfg(feature = "octo")]
+    #[test]
+    fn assembles_octo() {
+        let path = std::env::temp_dir().join(format!("oxid8-rom-{}.o8", std::process::id()));
+        fs::write(&path, ": main\n  loop again\n").unwrap();
+        assert_eq!(read(&path).unwrap(), [0x12, 0x00]);
+        fs::remove_file(&path).unwrap();
+    }
+}
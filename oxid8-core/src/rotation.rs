@@ -0,0 +1,155 @@
+//! Display rotation for rotated monitors and vertical games.
+//!
+//! Rotation only changes how frontends read the screen, the core always
+//! draws to a 64x32 screen.
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::{fmt, str::FromStr};
+
+use crate::{SCREEN_AREA, SCREEN_HEIGHT, SCREEN_WIDTH};
+
+/// Clockwise rotation of the displayed screen.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(try_from = "u16", into = "u16"))]
+pub enum Rotation {
+    #[default]
+    Deg0,
+    Deg90,
+    Deg180,
+    Deg270,
+}
+
+impl Rotation {
+    /// Clockwise rotation in degrees.
+    #[must_use]
+    pub fn degrees(self) -> u16 {
+        match self {
+            Rotation::Deg0 => 0,
+            Rotation::Deg90 => 90,
+            Rotation::Deg180 => 180,
+            Rotation::Deg270 => 270,
+        }
+    }
+
+    /// Rotates a further 90 degrees clockwise.
+    #[must_use]
+    pub fn next(self) -> Self {
+        match self {
+            Rotation::Deg0 => Rotation::Deg90,
+            Rotation::Deg90 => Rotation::Deg180,
+            Rotation::Deg180 => Rotation::Deg270,
+            Rotation::Deg270 => Rotation::Deg0,
+        }
+    }
+
+    /// Width and height of the displayed screen.
+    #[must_use]
+    pub fn dimensions(self) -> (usize, usize) {
+        match self {
+            Rotation::Deg0 | Rotation::Deg180 => (SCREEN_WIDTH, SCREEN_HEIGHT),
+            Rotation::Deg90 | Rotation::Deg270 => (SCREEN_HEIGHT, SCREEN_WIDTH),
+        }
+    }
+
+    /// Screen coordinates shown at displayed coordinates `x`, `y`.
+    #[must_use]
+    pub fn source(self, x: usize, y: usize) -> (usize, usize) {
+        match self {
+            Rotation::Deg0 => (x, y),
+            Rotation::Deg90 => (y, SCREEN_HEIGHT - 1 - x),
+            Rotation::Deg180 => (SCREEN_WIDTH - 1 - x, SCREEN_HEIGHT - 1 - y),
+            Rotation::Deg270 => (SCREEN_WIDTH - 1 - y, x),
+        }
+    }
+
+    /// The pixel of `screen` shown at displayed coordinates `x`, `y`.
+    ///
+    /// # Panics
+    ///
+    /// `pixel` panics if `x`, `y` are outside of `dimensions`.
+    #[must_use]
+    pub fn pixel(self, screen: &[bool; SCREEN_AREA], x: usize, y: usize) -> bool {
+        let (sx, sy) = self.source(x, y);
+        screen[sx + sy * SCREEN_WIDTH]
+    }
+}
+
+impl From<Rotation> for u16 {
+    fn from(rotation: Rotation) -> Self {
+        rotation.degrees()
+    }
+}
+
+impl TryFrom<u16> for Rotation {
+    type Error = String;
+
+    fn try_from(degrees: u16) -> Result<Self, Self::Error> {
+        match degrees {
+            0 => Ok(Rotation::Deg0),
+            90 => Ok(Rotation::Deg90),
+            180 => Ok(Rotation::Deg180),
+            270 => Ok(Rotation::Deg270),
+            _ => Err(format!(
+                "Invalid rotation: {degrees}, expected 0, 90, 180, or 270"
+            )),
+        }
+    }
+}
+
+/// Formatted as degrees
+impl fmt::Display for Rotation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.degrees())
+    }
+}
+
+impl FromStr for Rotation {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.parse::<u16>()
+            .map_err(|_| format!("Invalid rotation: {s}"))?
+            .try_into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn corners() {
+        // Top left of the screen is shown at the top right at 90.
+        assert_eq!(Rotation::Deg90.source(31, 0), (0, 0));
+        assert_eq!(Rotation::Deg180.source(63, 31), (0, 0));
+        assert_eq!(Rotation::Deg270.source(0, 63), (0, 0));
+        assert_eq!(Rotation::Deg90.dimensions(), (32, 64));
+    }
+
+    #[test]
+    fn every_pixel_once() {
+        let mut rotation = Rotation::default();
+        for _ in 0..4 {
+            let (width, height) = rotation.dimensions();
+            let mut seen = [false; SCREEN_AREA];
+            for y in 0..height {
+                for x in 0..width {
+                    let (sx, sy) = rotation.source(x, y);
+                    assert!(!seen[sx + sy * SCREEN_WIDTH]);
+                    seen[sx + sy * SCREEN_WIDTH] = true;
+                }
+            }
+            rotation = rotation.next();
+        }
+        assert_eq!(rotation, Rotation::Deg0);
+    }
+
+    #[test]
+    fn parse() {
+        assert_eq!("270".parse(), Ok(Rotation::Deg270));
+        assert!("45".parse::<Rotation>().is_err());
+        assert_eq!(Rotation::Deg90.to_string(), "90");
+    }
+}
@@ -0,0 +1,558 @@
+//! Savestates and tools to inspect them.
+//!
+//! A [`SaveState`] is a copy of the whole machine: cpu, memory, display,
+//! keys, timers, and rng. Settings such as speed and quirks are not part
+//! of it, like [`Oxid8::reset`] they belong to the frontend.
+//!
+//! Its fields are public so debuggers can inspect a state without
+//! restoring it. With the `debugger` feature, `SaveState::diff`
+//! answers "what changed between these two points".
+//!
+//! [`SaveState::to_bytes`] packs a state into about 4.5KB for sharing,
+//! e.g. as text on the clipboard, and [`SaveState::from_bytes`] reads it
+//! back.
+//!
+//! ```
+//! use oxid8_core::Oxid8;
+//!
+//! let mut emu = Oxid8::new();
+//! emu.load_rom_bytes(&[0x60, 0x2A, 0x12, 0x02]).unwrap(); // LD V0, 2A; JP 202
+//! let before = emu.save_state();
+//! emu.run_cycle().unwrap();
+//! assert_eq!(emu.save_state().v_reg[0], 0x2A);
+//!
+//! emu.restore_state(&before);
+//! assert_eq!(emu.save_state(), before);
+//! ```
+
+#[cfg(feature = "debugger")]
+use std::fmt;
+
+use crate::{
+    NUM_KEYS, NUM_REGS, Oxid8, SCREEN_AREA, STACK_SIZE, TimerRate,
+    export::{PACKED_SIZE, pack_screen, unpack_screen},
+    layout::{Addr, RAM_SIZE},
+    rng::{Lfsr, Rng, Xoshiro256},
+};
+
+/// Start of [`SaveState::to_bytes`], with the format version.
+const MAGIC: [u8; 4] = *b"O8S2";
+/// States packed before the uniform rng was, read with a fresh one.
+const MAGIC_V1: [u8; 4] = *b"O8S1";
+
+// Held keys are packed into a `u16`.
+const _: () = assert!(NUM_KEYS <= u16::BITS as usize);
+
+/// A copy of the machine state, see [`Oxid8::save_state`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct SaveState {
+    /// Program counter.
+    pub pc: u16,
+    /// 4KB of memory, including the font and the ROM.
+    pub ram: [u8; RAM_SIZE],
+    /// See [`Oxid8::screen_ref`].
+    pub screen: [bool; SCREEN_AREA],
+    /// V0 to VF.
+    pub v_reg: [u8; NUM_REGS],
+    /// I register.
+    pub i_reg: u16,
+    /// Stack pointer.
+    pub sp: u16,
+    /// Return addresses, valid below `sp`.
+    pub stack: [u16; STACK_SIZE],
+    /// See [`Oxid8::keys_ref`].
+    pub keys: [bool; NUM_KEYS],
+    /// Key pressed while waiting in Fx0A, stored until released.
+    pub stored_key: Option<usize>,
+    /// Delay timer.
+    pub dt: u8,
+    /// Sound timer.
+    pub st: u8,
+    /// Random number generator state.
+    pub rng: Rng,
+    /// Fractional cycles carried between frames at 50Hz.
+    pub cycle_remainder: u32,
+    /// See [`Oxid8::rom_hash`].
+    pub rom_hash: Option<String>,
+}
+
+/// A register shown in a [`StateDiff`].
+#[cfg(feature = "debugger")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Register {
+    Pc,
+    I,
+    Sp,
+    Dt,
+    St,
+    /// Vx
+    V(u8),
+    /// A stack entry.
+    Stack(u8),
+}
+
+/// Formatted like the mnemonics, e.g. "PC", "V3", or "S[0]".
+#[cfg(feature = "debugger")]
+impl fmt::Display for Register {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Register::Pc => write!(f, "PC"),
+            Register::I => write!(f, "I"),
+            Register::Sp => write!(f, "SP"),
+            Register::Dt => write!(f, "DT"),
+            Register::St => write!(f, "ST"),
+            Register::V(x) => write!(f, "V{x:X}"),
+            Register::Stack(i) => write!(f, "S[{i:X}]"),
+        }
+    }
+}
+
+/// A value that differs between two states.
+#[cfg(feature = "debugger")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Change<T> {
+    pub before: T,
+    pub after: T,
+}
+
+/// The differences between two savestates, see [`SaveState::diff`].
+#[cfg(feature = "debugger")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StateDiff {
+    /// Pixels that differ, the XOR of the two screens.
+    pub screen: [bool; SCREEN_AREA],
+    /// Registers that differ, in [`SaveState::registers`] order.
+    pub registers: Vec<(Register, Change<u16>)>,
+    /// Memory that differs, by address.
+    pub ram: Vec<(u16, Change<u8>)>,
+}
+
+#[cfg(feature = "debugger")]
+impl StateDiff {
+    /// Returns true if the states have the same display, registers,
+    /// and memory.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.registers.is_empty() && self.ram.is_empty() && !self.screen.contains(&true)
+    }
+
+    /// Number of pixels that differ.
+    #[must_use]
+    pub fn pixels(&self) -> usize {
+        self.screen.iter().filter(|&&px| px).count()
+    }
+}
+
+#[cfg(feature = "debugger")]
+impl SaveState {
+    /// Every register with its value, widened to 16 bits.
+    pub fn registers(&self) -> impl Iterator<Item = (Register, u16)> + '_ {
+        let v = (0..NUM_REGS as u8).map(|x| (Register::V(x), self.v_reg[x as usize] as u16));
+        let stack = (0..STACK_SIZE as u8).map(|i| (Register::Stack(i), self.stack[i as usize]));
+        [
+            (Register::Pc, self.pc),
+            (Register::I, self.i_reg),
+            (Register::Sp, self.sp),
+            (Register::Dt, self.dt as u16),
+            (Register::St, self.st as u16),
+        ]
+        .into_iter()
+        .chain(v)
+        .chain(stack)
+    }
+
+    /// What changed from this state to `after`.
+    ///
+    /// ```
+    /// use oxid8_core::Oxid8;
+    ///
+    /// let mut emu = Oxid8::new();
+    /// emu.load_rom_bytes(&[0x60, 0x2A, 0x12, 0x02]).unwrap(); // LD V0, 2A; JP 202
+    /// let before = emu.save_state();
+    /// emu.run_cycle().unwrap();
+    ///
+    /// let diff = before.diff(&emu.save_state());
+    /// assert_eq!(diff.registers.len(), 2); // PC and V0
+    /// assert!(diff.ram.is_empty());
+    /// ```
+    #[must_use]
+    pub fn diff(&self, after: &SaveState) -> StateDiff {
+        let mut screen = [false; SCREEN_AREA];
+        for (px, (a, b)) in screen.iter_mut().zip(self.screen.iter().zip(&after.screen)) {
+            *px = a ^ b;
+        }
+
+        let registers = self
+            .registers()
+            .zip(after.registers())
+            .filter(|((_, before), (_, after))| before != after)
+            .map(|((register, before), (_, after))| (register, Change { before, after }))
+            .collect();
+
+        let ram = self
+            .ram
+            .iter()
+            .zip(&after.ram)
+            .enumerate()
+            .filter(|(_, (before, after))| before != after)
+            .map(|(addr, (&before, &after))| (addr as u16, Change { before, after }))
+            .collect();
+
+        StateDiff {
+            screen,
+            registers,
+            ram,
+        }
+    }
+}
+
+impl Oxid8 {
+    /// Copies the machine state.
+    #[must_use]
+    pub fn save_state(&self) -> SaveState {
+        SaveState {
+            pc: self.pc.get(),
+            ram: *self.ram,
+            screen: *self.screen,
+            v_reg: self.v_reg,
+            i_reg: self.i_reg.get(),
+            sp: self.sp,
+            stack: self.stack.map(Addr::get),
+            keys: self.keys,
+            stored_key: self.stored_key,
+            dt: self.dt,
+            st: self.st,
+            rng: self.rng.clone(),
+            cycle_remainder: self.cycle_remainder,
+            rom_hash: self.rom_hash.clone(),
+        }
+    }
+
+    /// Restores the machine state from [`Oxid8::save_state`], keeping
+    /// the current settings.
+    pub fn restore_state(&mut self, state: &SaveState) {
+        self.pc = Addr::new(state.pc);
+        *self.ram = state.ram;
+        self.set_screen(&state.screen);
+        self.v_reg = state.v_reg;
+        self.i_reg = Addr::new(state.i_reg);
+        self.sp = state.sp;
+        self.stack = state.stack.map(Addr::new);
+        self.keys = state.keys;
+        self.stored_key = state.stored_key;
+        self.dt = state.dt;
+        self.st = state.st;
+        self.rng = state.rng.clone();
+        self.cycle_remainder = state.cycle_remainder;
+        self.rom_hash = state.rom_hash.clone();
+    }
+}
+
+impl SaveState {
+    /// Packs the state into bytes, see [`SaveState::from_bytes`].
+    ///
+    /// The screen is packed one bit per pixel. Both rngs are included,
+    /// so a restored state draws the same random bytes.
+    #[must_use]
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(MAGIC.len() + 96 + PACKED_SIZE + RAM_SIZE);
+        bytes.extend_from_slice(&MAGIC);
+        bytes.extend_from_slice(&self.pc.to_le_bytes());
+        bytes.extend_from_slice(&self.i_reg.to_le_bytes());
+        bytes.push(self.sp as u8);
+        for addr in self.stack {
+            bytes.extend_from_slice(&addr.to_le_bytes());
+        }
+        bytes.extend_from_slice(&self.v_reg);
+        bytes.push(self.dt);
+        bytes.push(self.st);
+        let keys = (0..NUM_KEYS).fold(0u16, |keys, k| keys | ((self.keys[k] as u16) << k));
+        bytes.extend_from_slice(&keys.to_le_bytes());
+        bytes.push(self.stored_key.map_or(0xFF, |k| k as u8));
+        bytes.extend_from_slice(&self.cycle_remainder.to_le_bytes());
+        bytes.extend_from_slice(&self.rng.lfsr().state().to_le_bytes());
+        for word in self.rng.uniform().state() {
+            bytes.extend_from_slice(&word.to_le_bytes());
+        }
+        bytes.extend_from_slice(&pack_screen(&self.screen));
+        bytes.extend_from_slice(&self.ram);
+        let hash = self.rom_hash.as_deref().unwrap_or("");
+        bytes.push(hash.len() as u8);
+        bytes.extend_from_slice(hash.as_bytes());
+        bytes
+    }
+
+    /// Reads a state packed by [`SaveState::to_bytes`]. States from
+    /// before the uniform rng was packed get a freshly seeded one.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `bytes` isn't a savestate, is cut short, or
+    /// holds impossible values, like I or an address on the stack past
+    /// the end of RAM.
+    pub fn from_bytes(bytes: &[u8]) -> Result<SaveState, String> {
+        let mut reader = Reader(bytes);
+        let v1 = match reader.take::<4>()? {
+            MAGIC => false,
+            MAGIC_V1 => true,
+            _ => return Err("Not an Oxid8 savestate".into()),
+        };
+
+        let pc = reader.u16()?;
+        let i_reg = reader.u16()?;
+        let sp = reader.u8()? as u16;
+        let mut stack = [0; STACK_SIZE];
+        for addr in &mut stack {
+            *addr = reader.u16()?;
+        }
+        let v_reg = reader.take::<NUM_REGS>()?;
+        let dt = reader.u8()?;
+        let st = reader.u8()?;
+        let keys = reader.u16()?;
+        let stored_key = match reader.u8()? {
+            0xFF => None,
+            k => Some(k as usize),
+        };
+        let cycle_remainder = u32::from_le_bytes(reader.take()?);
+        let lfsr = reader.u16()?;
+        let uniform = match v1 {
+            true => Some(Rng::from_entropy().uniform().clone()),
+            false => {
+                let mut state = [0; 4];
+                for word in &mut state {
+                    *word = u64::from_le_bytes(reader.take()?);
+                }
+                Xoshiro256::from_state(state)
+            }
+        };
+        let screen = unpack_screen(&reader.take::<PACKED_SIZE>()?);
+        let ram = reader.take::<RAM_SIZE>()?;
+        let len = reader.u8()? as usize;
+        let hash = reader.slice(len)?;
+
+        if pc as usize >= RAM_SIZE - 1 {
+            return Err(format!("Invalid savestate: pc {pc:#05X}"));
+        }
+        if sp as usize > STACK_SIZE {
+            return Err(format!("Invalid savestate: stack pointer {sp}"));
+        }
+        // Returns jump to the addresses in use, as if loaded into pc.
+        if let Some(addr) = stack[..sp as usize]
+            .iter()
+            .find(|&&addr| addr as usize >= RAM_SIZE - 1)
+        {
+            return Err(format!("Invalid savestate: return address {addr:#05X}"));
+        }
+        if i_reg as usize >= RAM_SIZE {
+            return Err(format!("Invalid savestate: I {i_reg:#05X}"));
+        }
+        if stored_key.is_some_and(|k| k >= NUM_KEYS) || lfsr == 0 {
+            return Err("Invalid savestate: keys or rng".into());
+        }
+        let Some(uniform) = uniform else {
+            return Err("Invalid savestate: keys or rng".into());
+        };
+        // Carried over from a frame, always less than the fastest rate.
+        if cycle_remainder >= TimerRate::Ntsc.hz() {
+            return Err(format!(
+                "Invalid savestate: cycle remainder {cycle_remainder}"
+            ));
+        }
+        let rom_hash = match hash {
+            [] => None,
+            hash => Some(
+                std::str::from_utf8(hash)
+                    .map_err(|_| "Invalid savestate: rom hash")?
+                    .to_string(),
+            ),
+        };
+
+        let mut rng = Rng::from_entropy();
+        rng.set_lfsr(Lfsr::new(lfsr));
+        rng.set_uniform(uniform);
+        Ok(SaveState {
+            pc,
+            ram,
+            screen,
+            v_reg,
+            i_reg,
+            sp,
+            stack,
+            keys: std::array::from_fn(|k| keys & (1 << k) != 0),
+            stored_key,
+            dt,
+            st,
+            rng,
+            cycle_remainder,
+            rom_hash,
+        })
+    }
+}
+
+/// Reads [`SaveState::to_bytes`] front to back.
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn slice(&mut self, len: usize) -> Result<&'a [u8], String> {
+        if self.0.len() < len {
+            return Err("Savestate is cut short".into());
+        }
+        let (head, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Ok(head)
+    }
+
+    fn take<const N: usize>(&mut self) -> Result<[u8; N], String> {
+        Ok(self.slice(N)?.try_into().unwrap_or([0; N]))
+    }
+
+    fn u8(&mut self) -> Result<u8, String> {
+        Ok(self.take::<1>()?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, String> {
+        Ok(u16::from_le_bytes(self.take()?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn restore() {
+        let mut emu = Oxid8::new();
+        emu.load_font();
+        // RND V0, FF; JP 200
+        emu.load_rom_bytes(&[0xC0, 0xFF, 0x12, 0x00]).unwrap();
+        emu.set_cycles_per_frame(3);
+        let state = emu.save_state();
+
+        emu.next_frame().unwrap();
+        let after = emu.save_state();
+        assert_ne!(after, state);
+
+        emu.set_cycles_per_frame(5);
+        emu.restore_state(&state);
+        assert_eq!(emu.save_state(), state);
+        assert_eq!(emu.cycles_per_frame(), 5);
+
+        // The rng is restored too, so the same frame replays.
+        emu.set_cycles_per_frame(3);
+        emu.next_frame().unwrap();
+        assert_eq!(emu.save_state(), after);
+    }
+
+    #[test]
+    fn bytes() {
+        let mut emu = Oxid8::new();
+        emu.load_font();
+        // RND V0, FF; DRW V0, V0, 5; CALL 200
+        emu.load_rom_bytes(&[0xC0, 0xFF, 0xD0, 0x05, 0x22, 0x00])
+            .unwrap();
+        emu.set_key(0x3, true);
+        emu.next_frame().unwrap();
+        let state = emu.save_state();
+
+        let bytes = state.to_bytes();
+        let read = SaveState::from_bytes(&bytes).unwrap();
+        assert_eq!(read, state);
+
+        // Both rngs come back, so the next RND bytes match.
+        emu.next_frame().unwrap();
+        let mut copy = Oxid8::new();
+        copy.restore_state(&read);
+        copy.next_frame().unwrap();
+        assert_eq!(copy.save_state(), emu.save_state());
+
+        // Older states read with a fresh uniform rng.
+        let mut v1 = bytes[..68].to_vec();
+        v1[..4].copy_from_slice(&MAGIC_V1);
+        v1.extend_from_slice(&bytes[100..]);
+        let mut read = SaveState::from_bytes(&v1).unwrap();
+        assert_eq!(read.rng.lfsr(), state.rng.lfsr());
+        read.rng = state.rng.clone();
+        assert_eq!(read, state);
+
+        let err = |bytes: &[u8]| SaveState::from_bytes(bytes).unwrap_err();
+        assert_eq!(err(b"PK\x03\x04"), "Not an Oxid8 savestate");
+        assert_eq!(err(&bytes[..100]), "Savestate is cut short");
+        let mut bad = bytes.clone();
+        bad[8] = STACK_SIZE as u8 + 1;
+        assert_eq!(err(&bad), "Invalid savestate: stack pointer 17");
+        let mut bad = bytes.clone();
+        bad[6..8].copy_from_slice(&0x1000u16.to_le_bytes());
+        assert_eq!(err(&bad), "Invalid savestate: I 0x1000");
+        let mut bad = bytes.clone();
+        bad[62..66].copy_from_slice(&60u32.to_le_bytes());
+        assert_eq!(err(&bad), "Invalid savestate: cycle remainder 60");
+        let mut bad = bytes.clone();
+        bad[62..66].copy_from_slice(&59u32.to_le_bytes());
+        assert!(SaveState::from_bytes(&bad).is_ok());
+        let mut bad = bytes.clone();
+        bad[68..100].fill(0);
+        assert_eq!(err(&bad), "Invalid savestate: keys or rng");
+        // The calls pushed a return address each.
+        let sp = usize::from(bytes[8]);
+        assert!((1..STACK_SIZE).contains(&sp));
+        let entry = |i: usize| 9 + 2 * i..11 + 2 * i;
+        let mut bad = bytes.clone();
+        bad[entry(sp - 1)].copy_from_slice(&0xFFFFu16.to_le_bytes());
+        assert_eq!(err(&bad), "Invalid savestate: return address 0xFFFF");
+        // Entries past the stack pointer are never returned to.
+        let mut stale = bytes.clone();
+        stale[entry(sp)].copy_from_slice(&0xFFFFu16.to_le_bytes());
+        assert!(SaveState::from_bytes(&stale).is_ok());
+    }
+
+    #[cfg(feature = "debugger")]
+    #[test]
+    fn diff() {
+        let mut emu = Oxid8::new();
+        let before = emu.save_state();
+        assert!(before.diff(&before).is_empty());
+
+        emu.screen[3] = true;
+        emu.v_reg[0xA] = 7;
+        emu.stack[0] = Addr::new(0x202);
+        emu.ram[0x300] = 0x12;
+        let after = emu.save_state();
+
+        let diff = before.diff(&after);
+        assert!(!diff.is_empty());
+        assert_eq!(diff.pixels(), 1);
+        assert!(diff.screen[3]);
+        assert_eq!(
+            diff.registers,
+            [
+                (
+                    Register::V(0xA),
+                    Change {
+                        before: 0,
+                        after: 7
+                    }
+                ),
+                (
+                    Register::Stack(0),
+                    Change {
+                        before: 0,
+                        after: 0x202
+                    }
+                ),
+            ]
+        );
+        assert_eq!(
+            diff.ram,
+            [(
+                0x300,
+                Change {
+                    before: 0,
+                    after: 0x12
+                }
+            )]
+        );
+        assert_eq!(diff.registers[0].0.to_string(), "VA");
+        assert_eq!(diff.registers[1].0.to_string(), "S[0]");
+    }
+}
@@ -0,0 +1,447 @@
+//! Rhai scripts run every frame, for automation and cheats.
+//!
+//! A script's top level runs once, on the first frame. After that its
+//! `on_frame` function, if it has one, runs before every frame. `this`
+//! in `on_frame` is a map kept between frames for the script's own
+//! state. Scripts only see the machine through these functions:
+//!
+//! | Function                          | Does                              |
+//! |-----------------------------------|-----------------------------------|
+//! | `v(x)`, `set_v(x, value)`         | Read and write Vx                 |
+//! | `i()`, `set_i(value)`             | Read and write I                  |
+//! | `dt()`, `set_dt(value)`           | Read and write the delay timer    |
+//! | `st()`, `set_st(value)`           | Read and write the sound timer    |
+//! | `peek(addr)`, `poke(addr, value)` | Read and write a byte of RAM      |
+//! | `pc()`                            | The program counter               |
+//! | `pixel(x, y)`                     | A pixel of the screen is lit      |
+//! | `held(key)`                       | A keypad key is held              |
+//! | `press(key)`, `release(key)`      | Press and release a keypad key    |
+//! | `frame()`                         | Frames run since the script began |
+//! | `announce(text)`                  | Say something about the game      |
+//!
+//! Arguments out of range are errors. Writes land before the frame
+//! runs, and presses at its first cycle. `print` adds a line to
+//! [`Script::hud`] for frontends to show over the game.
+//!
+//! `announce` is experimental, for playing simple games without
+//! seeing them. A script written for a game reads its state and
+//! announces what changes, like "Score: 12" or "Ball lost", once, and
+//! frontends show [`Script::announcements`] and speak them with a text
+//! to speech engine where there is one. Frontends load scripts for a
+//! game from `<sha1>.rhai` beside its [config](crate::config).
+//!
+//! ```
+//! use oxid8_core::{Oxid8, script::Script};
+//!
+//! let mut emu = Oxid8::new();
+//! emu.load_rom_bytes(&[0x70, 0x01, 0x12, 0x00]).unwrap(); // ADD V0, 1; JP 200
+//!
+//! // Announce V0 every time it reaches a multiple of 10.
+//! let mut script = Script::new(
+//!     "fn on_frame() {
+//!         if v(0) != this.last && v(0) % 10 == 0 { announce(`Score: ${v(0)}`) }
+//!         this.last = v(0);
+//!     }",
+//! )
+//! .unwrap();
+//! let mut said = Vec::new();
+//! for _ in 0..5 {
+//!     let events = script.run_frame(&mut emu).unwrap();
+//!     emu.next_frame_with_input(&events).unwrap();
+//!     said.extend(script.announcements());
+//! }
+//! assert_eq!(said, ["Score: 0", "Score: 10", "Score: 20"]);
+//! ```
+//!
+//! ```
+//! use oxid8_core::{Oxid8, script::Script};
+//!
+//! let mut emu = Oxid8::new();
+//! emu.load_rom_bytes(&[0x12, 0x00]).unwrap(); // JP 200
+//!
+//! // Infinite lives, and a frame counter.
+//! let mut script = Script::new(
+//!     "fn on_frame() {
+//!         set_v(3, 9);
+//!         print(`frame ${frame()}`);
+//!     }",
+//! )
+//! .unwrap();
+//! let events = script.run_frame(&mut emu).unwrap();
+//! emu.next_frame_with_input(&events).unwrap();
+//!
+//! assert_eq!(emu.save_state().v_reg[3], 9);
+//! assert_eq!(script.hud(), ["frame 0"]);
+//! ```
+
+use std::{cell::RefCell, fmt, fs, path::Path, rc::Rc};
+
+use rhai::{AST, CallFnOptions, Dynamic, Engine, EvalAltResult, INT, Map, Scope};
+
+use crate::{
+    NUM_KEYS, NUM_REGS, Oxid8, SCREEN_AREA, SCREEN_HEIGHT, SCREEN_WIDTH,
+    input::InputEvent,
+    layout::{Addr, RAM_SIZE},
+};
+
+/// Operations a script may run each frame before it is stopped, so a
+/// runaway loop can't hang the frontend.
+const MAX_OPERATIONS: u64 = 1_000_000;
+
+/// Lines of [`Script::hud`] and [`Script::announcements`] kept each
+/// frame.
+const MAX_HUD_LINES: usize = 16;
+
+type Result<T> = std::result::Result<T, Box<EvalAltResult>>;
+
+/// The machine as scripts see it, copied in before and written back
+/// after each call.
+struct Host {
+    v_reg: [u8; NUM_REGS],
+    i_reg: u16,
+    pc: u16,
+    dt: u8,
+    st: u8,
+    ram: [u8; RAM_SIZE],
+    screen: [bool; SCREEN_AREA],
+    keys: [bool; NUM_KEYS],
+    frame: u64,
+    events: Vec<InputEvent>,
+    hud: Vec<String>,
+    announcements: Vec<String>,
+}
+
+impl Host {
+    fn new() -> Self {
+        Host {
+            v_reg: [0; NUM_REGS],
+            i_reg: 0,
+            pc: 0,
+            dt: 0,
+            st: 0,
+            ram: [0; RAM_SIZE],
+            screen: [false; SCREEN_AREA],
+            keys: [false; NUM_KEYS],
+            frame: 0,
+            events: Vec::new(),
+            hud: Vec::new(),
+            announcements: Vec::new(),
+        }
+    }
+
+    fn read(&mut self, core: &Oxid8) {
+        self.v_reg = core.v_reg;
+        self.i_reg = core.i_reg.get();
+        self.pc = core.pc.get();
+        self.dt = core.dt;
+        self.st = core.st;
+        self.ram = *core.ram;
+        self.screen = *core.screen;
+        self.keys = core.keys;
+    }
+
+    fn write(&self, core: &mut Oxid8) {
+        core.v_reg = self.v_reg;
+        core.i_reg = Addr::new(self.i_reg);
+        core.dt = self.dt;
+        core.st = self.st;
+        *core.ram = self.ram;
+    }
+}
+
+/// A compiled script and the state it keeps between frames.
+pub struct Script {
+    engine: Engine,
+    ast: AST,
+    /// `this` in `on_frame`.
+    this: Dynamic,
+    host: Rc<RefCell<Host>>,
+    started: bool,
+}
+
+impl Script {
+    /// Compiles `source`, see the module docs.
+    ///
+    /// # Errors
+    ///
+    /// If the script doesn't parse.
+    pub fn new(source: &str) -> std::result::Result<Self, String> {
+        let host = Rc::new(RefCell::new(Host::new()));
+        let engine = engine(&host);
+        let ast = engine.compile(source).map_err(|err| err.to_string())?;
+        Ok(Script {
+            engine,
+            ast,
+            this: Map::new().into(),
+            host,
+            started: false,
+        })
+    }
+
+    /// Reads and compiles the script at `path`.
+    ///
+    /// # Errors
+    ///
+    /// If the file can't be read or doesn't parse.
+    pub fn load(path: impl AsRef<Path>) -> std::result::Result<Self, String> {
+        let source = fs::read_to_string(path).map_err(|err| err.to_string())?;
+        Self::new(&source)
+    }
+
+    /// Runs the script for the next frame of `core`, before it is
+    /// emulated. Returns the key presses and releases to emulate it
+    /// with, at cycle 0.
+    ///
+    /// # Errors
+    ///
+    /// If the script errors or runs too long. `core` is left as it
+    /// was, and the script carries on from the next frame.
+    pub fn run_frame(&mut self, core: &mut Oxid8) -> std::result::Result<Vec<InputEvent>, String> {
+        {
+            let mut host = self.host.borrow_mut();
+            host.read(core);
+            host.events.clear();
+            host.hud.clear();
+            host.announcements.clear();
+        }
+        let ran = self.call();
+        let mut host = self.host.borrow_mut();
+        host.frame += 1;
+        ran.map_err(|err| err.to_string())?;
+        host.write(core);
+        Ok(std::mem::take(&mut host.events))
+    }
+
+    /// Lines printed during the last frame.
+    #[must_use]
+    pub fn hud(&self) -> Vec<String> {
+        self.host.borrow().hud.clone()
+    }
+
+    /// Announcements made during the last frame, to show and speak.
+    #[must_use]
+    pub fn announcements(&self) -> Vec<String> {
+        self.host.borrow().announcements.clone()
+    }
+
+    fn call(&mut self) -> Result<()> {
+        if !std::mem::replace(&mut self.started, true) {
+            self.engine.run_ast(&self.ast)?;
+        }
+        if !self
+            .ast
+            .iter_functions()
+            .any(|f| f.name == "on_frame" && f.params.is_empty())
+        {
+            return Ok(());
+        }
+        let options = CallFnOptions::new()
+            .eval_ast(false)
+            .bind_this_ptr(&mut self.this);
+        self.engine
+            .call_fn_with_options(options, &mut Scope::new(), &self.ast, "on_frame", ())
+    }
+}
+
+/// Formatted like "Script { frame: 12 }".
+impl fmt::Debug for Script {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Script")
+            .field("frame", &self.host.borrow().frame)
+            .finish()
+    }
+}
+
+/// An engine limited to the machine API of `host`.
+fn engine(host: &Rc<RefCell<Host>>) -> Engine {
+    let mut engine = Engine::new();
+    engine.set_max_operations(MAX_OPERATIONS);
+
+    let h = host.clone();
+    engine.on_print(move |text| {
+        let mut host = h.borrow_mut();
+        if host.hud.len() < MAX_HUD_LINES {
+            host.hud.push(text.into());
+        }
+    });
+    engine.on_debug(|_, _, _| ());
+
+    let h = host.clone();
+    engine.register_fn("v", move |x: INT| -> Result<INT> {
+        Ok(h.borrow().v_reg[index(x, NUM_REGS, "register")?].into())
+    });
+    let h = host.clone();
+    engine.register_fn("set_v", move |x: INT, value: INT| -> Result<()> {
+        let mut host = h.borrow_mut();
+        host.v_reg[index(x, NUM_REGS, "register")?] = byte(value)?;
+        Ok(())
+    });
+    let h = host.clone();
+    engine.register_fn("i", move || -> INT { h.borrow().i_reg.into() });
+    let h = host.clone();
+    engine.register_fn("set_i", move |value: INT| -> Result<()> {
+        h.borrow_mut().i_reg = index(value, RAM_SIZE, "address")? as u16;
+        Ok(())
+    });
+    let h = host.clone();
+    engine.register_fn("dt", move || -> INT { h.borrow().dt.into() });
+    let h = host.clone();
+    engine.register_fn("set_dt", move |value: INT| -> Result<()> {
+        h.borrow_mut().dt = byte(value)?;
+        Ok(())
+    });
+    let h = host.clone();
+    engine.register_fn("st", move || -> INT { h.borrow().st.into() });
+    let h = host.clone();
+    engine.register_fn("set_st", move |value: INT| -> Result<()> {
+        h.borrow_mut().st = byte(value)?;
+        Ok(())
+    });
+    let h = host.clone();
+    engine.register_fn("peek", move |addr: INT| -> Result<INT> {
+        Ok(h.borrow().ram[index(addr, RAM_SIZE, "address")?].into())
+    });
+    let h = host.clone();
+    engine.register_fn("poke", move |addr: INT, value: INT| -> Result<()> {
+        let mut host = h.borrow_mut();
+        host.ram[index(addr, RAM_SIZE, "address")?] = byte(value)?;
+        Ok(())
+    });
+    let h = host.clone();
+    engine.register_fn("pc", move || -> INT { h.borrow().pc.into() });
+    let h = host.clone();
+    engine.register_fn("pixel", move |x: INT, y: INT| -> Result<bool> {
+        let x = index(x, SCREEN_WIDTH, "column")?;
+        let y = index(y, SCREEN_HEIGHT, "row")?;
+        Ok(h.borrow().screen[y * SCREEN_WIDTH + x])
+    });
+    let h = host.clone();
+    engine.register_fn("held", move |key: INT| -> Result<bool> {
+        Ok(h.borrow().keys[index(key, NUM_KEYS, "key")?])
+    });
+    for (name, pressed) in [("press", true), ("release", false)] {
+        let h = host.clone();
+        engine.register_fn(name, move |key: INT| -> Result<()> {
+            let key = index(key, NUM_KEYS, "key")? as u8;
+            h.borrow_mut().events.push(InputEvent {
+                cycle: 0,
+                key,
+                pressed,
+            });
+            Ok(())
+        });
+    }
+    let h = host.clone();
+    engine.register_fn("frame", move || -> INT { h.borrow().frame as INT });
+    let h = host.clone();
+    engine.register_fn("announce", move |text: &str| {
+        let mut host = h.borrow_mut();
+        if host.announcements.len() < MAX_HUD_LINES {
+            host.announcements.push(text.into());
+        }
+    });
+
+    engine
+}
+
+/// `value` as an index below `len`.
+fn index(value: INT, len: usize, what: &str) -> Result<usize> {
+    usize::try_from(value)
+        .ok()
+        .filter(|&i| i < len)
+        .ok_or_else(|| format!("{what} out of range: {value}").into())
+}
+
+/// `value` as a byte.
+fn byte(value: INT) -> Result<u8> {
+    u8::try_from(value).map_err(|_| format!("byte out of range: {value}").into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn core() -> Oxid8 {
+        let mut emu = Oxid8::new();
+        // ADD V0, 1; JP 200
+        emu.load_rom_bytes(&[0x70, 0x01, 0x12, 0x00]).unwrap();
+        emu
+    }
+
+    #[test]
+    fn reads_and_writes_the_machine() {
+        let mut emu = core();
+        let mut script = Script::new(
+            "poke(0x300, v(0) + 1);
+            set_i(0x300);
+            set_dt(60);
+            print(`${pc()} ${peek(0x200)}`);",
+        )
+        .unwrap();
+        assert_eq!(script.run_frame(&mut emu), Ok(vec![]));
+        assert_eq!(script.hud(), ["512 112"]);
+
+        let state = emu.save_state();
+        assert_eq!(state.ram[0x300], 1);
+        assert_eq!(state.i_reg, 0x300);
+        assert_eq!(state.dt, 60);
+
+        // The top level only runs once.
+        emu.next_frame().unwrap();
+        script.run_frame(&mut emu).unwrap();
+        assert_eq!(emu.save_state().ram[0x300], 1);
+        assert!(script.hud().is_empty());
+    }
+
+    #[test]
+    fn on_frame_keeps_state_and_presses_keys() {
+        let mut emu = core();
+        let mut script = Script::new(
+            "fn on_frame() {
+                this.count = (this.count ?? 0) + 1;
+                if this.count == 2 { press(0xA) } else { release(0xA) }
+            }",
+        )
+        .unwrap();
+        let press = InputEvent {
+            cycle: 0,
+            key: 0xA,
+            pressed: true,
+        };
+        let frames: Vec<_> = (0..3)
+            .map(|_| script.run_frame(&mut emu).unwrap())
+            .collect();
+        assert_eq!(
+            frames,
+            [
+                vec![InputEvent {
+                    pressed: false,
+                    ..press
+                }],
+                vec![press],
+                vec![InputEvent {
+                    pressed: false,
+                    ..press
+                }],
+            ]
+        );
+        assert_eq!(format!("{script:?}"), "Script { frame: 3 }");
+    }
+
+    #[test]
+    fn errors_leave_the_core() {
+        let mut emu = core();
+        let before = emu.save_state();
+        for source in [
+            "set_v(0, 1); set_v(16, 0)",
+            "poke(0x1000, 0)",
+            "set_dt(256)",
+            "loop {}",
+        ] {
+            let mut script = Script::new(source).unwrap();
+            assert!(script.run_frame(&mut emu).is_err(), "{source}");
+            assert_eq!(emu.save_state(), before, "{source}");
+        }
+        assert!(Script::new("fn on_frame( {").is_err());
+    }
+}
@@ -0,0 +1,195 @@
+//! Skipping invalid opcodes instead of stopping.
+//!
+//! Some roms keep data where the interpreter can run into it, or were
+//! written for interpreters that ignored opcodes they didn't know. With
+//! [`Oxid8::set_skip_invalid`], invalid opcodes run as a two byte no-op
+//! and are counted in a [`SkipLog`] instead of stopping the game with
+//! an error. Each opcode and address is listed once, with how many
+//! times it was skipped, so frontends can report new ones without
+//! flooding the log, and show the total.
+//!
+//! ```
+//! use oxid8_core::Oxid8;
+//!
+//! let mut emu = Oxid8::new();
+//! // An invalid opcode; ADD V0, 1; JP 200
+//! emu.load_rom_bytes(&[0xFF, 0xFF, 0x70, 0x01, 0x12, 0x00]).unwrap();
+//! assert!(emu.run_cycle().is_err());
+//!
+//! emu.set_skip_invalid(true);
+//! for _ in 0..6 {
+//!     emu.run_cycle().unwrap();
+//! }
+//! let log = emu.skipped();
+//! assert_eq!(log.total(), 2);
+//! assert_eq!(log.entries()[0].to_string(), "FFFF at 200, 2 times");
+//! ```
+
+use std::fmt;
+
+use crate::{Oxid8, invalid_instruction};
+
+/// Most distinct opcodes listed, any more are only counted.
+pub const MAX_LISTED: usize = 64;
+
+/// An invalid opcode at an address, and how often it was skipped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Skipped {
+    pub pc: u16,
+    pub opcode: u16,
+    pub count: u32,
+}
+
+/// Formatted like "FFFF at 2A4, 3 times".
+impl fmt::Display for Skipped {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:04X} at {:03X}", self.opcode, self.pc)?;
+        match self.count {
+            1 => Ok(()),
+            n => write!(f, ", {n} times"),
+        }
+    }
+}
+
+/// Invalid opcodes skipped since the rom was loaded.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SkipLog {
+    entries: Vec<Skipped>,
+    total: u64,
+}
+
+impl SkipLog {
+    /// Counts `opcode` skipped at `pc`, returning true the first time
+    /// it is.
+    pub fn record(&mut self, pc: u16, opcode: u16) -> bool {
+        self.total += 1;
+        let listed = self.entries.len();
+        let entry = self
+            .entries
+            .iter_mut()
+            .find(|entry| entry.pc == pc && entry.opcode == opcode);
+        match entry {
+            Some(entry) => {
+                entry.count = entry.count.saturating_add(1);
+                false
+            }
+            None if listed < MAX_LISTED => {
+                self.entries.push(Skipped {
+                    pc,
+                    opcode,
+                    count: 1,
+                });
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Invalid opcodes skipped, counting repeats.
+    #[must_use]
+    pub fn total(&self) -> u64 {
+        self.total
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.total == 0
+    }
+
+    /// Each opcode and address skipped, in the order first skipped, up
+    /// to [`MAX_LISTED`].
+    #[must_use]
+    pub fn entries(&self) -> &[Skipped] {
+        &self.entries
+    }
+
+    pub fn clear(&mut self) {
+        *self = SkipLog::default();
+    }
+}
+
+impl Oxid8 {
+    /// Skip invalid opcodes instead of failing, see the
+    /// [module docs](self). Off by default.
+    pub fn set_skip_invalid(&mut self, skip: bool) {
+        self.skip_invalid = skip;
+    }
+
+    /// Returns true if invalid opcodes are skipped.
+    #[must_use]
+    pub fn skip_invalid(&self) -> bool {
+        self.skip_invalid
+    }
+
+    /// The invalid opcodes skipped since the rom was loaded.
+    #[must_use]
+    pub fn skipped(&self) -> &SkipLog {
+        &self.skipped
+    }
+
+    /// Forget the invalid opcodes skipped so far.
+    pub fn clear_skipped(&mut self) {
+        self.skipped.clear();
+    }
+
+    /// Skips `opcode` at `pc` if turned on, or fails.
+    #[cold]
+    pub(crate) fn invalid_opcode(&mut self, opcode: u16, pc: u16) -> Result<(), String> {
+        if !self.skip_invalid {
+            return Err(invalid_instruction(opcode, pc));
+        }
+        if self.skipped.record(pc, opcode) {
+            #[cfg(feature = "tracing")]
+            tracing::warn!(pc, opcode, "Skipped invalid opcode");
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dedup() {
+        let mut log = SkipLog::default();
+        assert!(log.is_empty());
+        assert!(log.record(0x200, 0xFFFF));
+        assert!(!log.record(0x200, 0xFFFF));
+        assert!(log.record(0x200, 0x0123));
+        assert!(log.record(0x2A4, 0xFFFF));
+        assert_eq!(log.total(), 4);
+        let counts: Vec<u32> = log.entries().iter().map(|e| e.count).collect();
+        assert_eq!(counts, [2, 1, 1]);
+        assert_eq!(log.entries()[1].to_string(), "0123 at 200");
+
+        // Only counted past the limit.
+        for pc in 0..MAX_LISTED as u16 * 2 {
+            log.record(pc, 0xE000);
+        }
+        assert_eq!(log.entries().len(), MAX_LISTED);
+        assert_eq!(log.total(), 4 + MAX_LISTED as u64 * 2);
+        log.clear();
+        assert!(log.entries().is_empty());
+    }
+
+    #[test]
+    fn skips_every_invalid_group() {
+        // 0NNN, 8XYF, EX00, FX00, then LD V0, 1
+        let rom = [0x01, 0x23, 0x80, 0x0F, 0xE0, 0x00, 0xF0, 0x00, 0x60, 0x01];
+        let mut emu = Oxid8::new();
+        emu.load_rom_bytes(&rom).unwrap();
+        emu.set_skip_invalid(true);
+        for _ in 0..5 {
+            emu.run_cycle().unwrap();
+        }
+        assert_eq!(emu.v_reg[0], 1);
+        let pcs: Vec<u16> = emu.skipped().entries().iter().map(|e| e.pc).collect();
+        assert_eq!(pcs, [0x200, 0x202, 0x204, 0x206]);
+
+        // Kept over a reset, the log isn't.
+        emu.reset();
+        assert!(emu.skip_invalid());
+        assert!(emu.skipped().is_empty());
+    }
+}
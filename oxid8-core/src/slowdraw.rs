@@ -0,0 +1,235 @@
+//! Dxyn drawn a bit at a time, for teaching how CHIP-8 draws sprites.
+//!
+//! [`Oxid8::next_frame_until_draw`] emulates a frame like
+//! [`Oxid8::next_frame_with_input`] but stops before a Dxyn and returns
+//! a [`SlowDraw`] of it. Frontends show [`SlowDraw::screen`] while
+//! calling [`SlowDraw::step`] at their own pace, the rest of emulation
+//! waiting, then run the instruction with [`Oxid8::run_cycle`] and carry
+//! on with the next frame.
+//!
+//! ```
+//! use oxid8_core::Oxid8;
+//!
+//! let mut emu = Oxid8::new();
+//! emu.load_font();
+//! // LD I, 050; DRW V0, V0, 5; JP 204
+//! emu.load_rom_bytes(&[0xA0, 0x50, 0xD0, 0x05, 0x12, 0x04]).unwrap();
+//!
+//! let mut draw = emu.next_frame_until_draw(&[]).unwrap().unwrap();
+//! assert!(draw.step());
+//! assert_eq!(draw.status(), "DRW V0, V0, 5: row 0 bit 0 at (0, 0), 1 XOR 0 = 1");
+//! while draw.step() {}
+//!
+//! emu.run_cycle().unwrap();
+//! assert_eq!(draw.screen(), emu.screen_ref());
+//! ```
+
+use crate::{Oxid8, SCREEN_AREA, SCREEN_WIDTH, disasm, input::InputEvent};
+
+/// A bit of a sprite and the pixel Dxyn XORs it onto.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SpriteBit {
+    /// Row of the sprite, from 0.
+    pub row: usize,
+    /// Bit of the row, from 0 for the most significant.
+    pub bit: usize,
+    /// Column of the pixel, after wrapping.
+    pub x: usize,
+    /// Row of the pixel, after wrapping.
+    pub y: usize,
+    /// The pixel flips.
+    pub set: bool,
+}
+
+/// A Dxyn part way through being drawn.
+#[derive(Debug, Clone)]
+pub struct SlowDraw {
+    /// Mnemonic of the instruction.
+    instruction: String,
+    bits: Vec<SpriteBit>,
+    screen: [bool; SCREEN_AREA],
+    drawn: usize,
+    /// The last bit drawn and the pixel under it before.
+    last: Option<(SpriteBit, bool)>,
+    collision: bool,
+}
+
+impl SlowDraw {
+    /// Animates the next instruction of `core`, if it is a Dxyn.
+    #[must_use]
+    pub fn new(core: &Oxid8) -> Option<Self> {
+        let opcode = core.next_opcode()?;
+        if opcode >> 12 != 0xD {
+            return None;
+        }
+        let [x, y, n] = [opcode >> 8 & 0xF, opcode >> 4 & 0xF, opcode & 0xF];
+        Some(SlowDraw {
+            instruction: disasm::mnemonic(opcode),
+            bits: core.sprite_bits(x as usize, y as usize, n as u8).collect(),
+            screen: *core.screen,
+            drawn: 0,
+            last: None,
+            collision: false,
+        })
+    }
+
+    /// XORs the next bit onto the screen. Returns false once every bit
+    /// has been drawn.
+    pub fn step(&mut self) -> bool {
+        let Some(&bit) = self.bits.get(self.drawn) else {
+            return false;
+        };
+        let pixel = &mut self.screen[bit.y * SCREEN_WIDTH + bit.x];
+        self.last = Some((bit, *pixel));
+        if bit.set {
+            self.collision |= *pixel;
+            *pixel ^= true;
+        }
+        self.drawn += 1;
+        true
+    }
+
+    /// The screen with the bits drawn so far.
+    #[must_use]
+    pub fn screen(&self) -> &[bool; SCREEN_AREA] {
+        &self.screen
+    }
+
+    /// The bit drawn last, if any.
+    #[must_use]
+    pub fn current(&self) -> Option<SpriteBit> {
+        self.last.map(|(bit, _)| bit)
+    }
+
+    /// Bits drawn so far and in total.
+    #[must_use]
+    pub fn progress(&self) -> (usize, usize) {
+        (self.drawn, self.bits.len())
+    }
+
+    /// A lit pixel has been turned off, VF will be 1.
+    #[must_use]
+    pub fn collision(&self) -> bool {
+        self.collision
+    }
+
+    /// Formatted like "DRW V0, V1, 5: row 2 bit 5 at (12, 7), 1 XOR 1 =
+    /// 0", the instruction alone before the first bit.
+    #[must_use]
+    pub fn status(&self) -> String {
+        let Some((bit, before)) = self.last else {
+            return self.instruction.clone();
+        };
+        let after = before ^ bit.set;
+        format!(
+            "{}: row {} bit {} at ({}, {}), {} XOR {} = {}",
+            self.instruction,
+            bit.row,
+            bit.bit,
+            bit.x,
+            bit.y,
+            bit.set as u8,
+            before as u8,
+            after as u8
+        )
+    }
+}
+
+impl Oxid8 {
+    /// Emulates a frame like `next_frame_with_input`, stopping before
+    /// a Dxyn instruction.
+    ///
+    /// Returns the draw to animate if stopped, the rest of the frame is
+    /// skipped like `next_frame_until`. Run the instruction with
+    /// `run_cycle` once drawn.
+    ///
+    /// # Errors
+    ///
+    /// See `next_frame`.
+    ///
+    /// # Panics
+    ///
+    /// See `next_frame_with_input`.
+    pub fn next_frame_until_draw(
+        &mut self,
+        events: &[InputEvent],
+    ) -> Result<Option<SlowDraw>, String> {
+        let stopped = self.run_frame(events, |core| {
+            core.next_opcode().is_some_and(|opcode| opcode >> 12 == 0xD)
+        })?;
+        Ok(stopped.and_then(|_| SlowDraw::new(self)))
+    }
+
+    /// The instruction at the program counter, none past the end of RAM.
+    fn next_opcode(&self) -> Option<u16> {
+        let bytes = self.ram.get(self.pc.range(2))?;
+        Some(u16::from_be_bytes([bytes[0], bytes[1]]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::quirks::Quirks;
+
+    fn core(rom: &[u8]) -> Oxid8 {
+        let mut emu = Oxid8::new();
+        emu.load_rom_bytes(rom).unwrap();
+        emu
+    }
+
+    #[test]
+    fn matches_dxyn() {
+        // LD V0, 3C; LD V1, 1E; LD I, 20E; DRW V0, V1, 4; DRW V0, V1, 4;
+        // JP 20A; 4 rows of FF
+        let rom = [
+            0x60, 0x3C, 0x61, 0x1E, 0xA2, 0x0E, 0xD0, 0x14, 0xD0, 0x14, 0x12, 0x0A, 0, 0, 0xFF,
+            0xFF, 0xFF, 0xFF,
+        ];
+        for wrap in [false, true] {
+            let mut emu = core(&rom);
+            emu.set_quirks(Quirks {
+                wrap_x: wrap,
+                wrap_y: wrap,
+                ..Quirks::default()
+            });
+            for collision in [false, true] {
+                let mut draw = emu.next_frame_until_draw(&[]).unwrap().unwrap();
+                // Clipped to 4x2 at the bottom right unless wrapped.
+                let bits = if wrap { 32 } else { 8 };
+                assert_eq!(draw.progress(), (0, bits));
+                while draw.step() {}
+                assert_eq!(draw.progress(), (bits, bits));
+                assert_eq!(draw.collision(), collision);
+
+                emu.run_cycle().unwrap();
+                assert_eq!(draw.screen(), emu.screen_ref());
+                assert_eq!(emu.v_reg[0xF], collision as u8);
+            }
+            // Runs on to the end of the frame without another draw.
+            assert!(emu.next_frame_until_draw(&[]).unwrap().is_none());
+        }
+    }
+
+    #[test]
+    fn steps_in_order() {
+        // LD I, 206; DRW V0, V0, 2; JP 204; 80 01
+        let mut emu = core(&[0xA2, 0x06, 0xD0, 0x02, 0x12, 0x04, 0x80, 0x01]);
+        let mut draw = emu.next_frame_until_draw(&[]).unwrap().unwrap();
+        assert_eq!(draw.status(), "DRW V0, V0, 2");
+        assert_eq!(draw.current(), None);
+
+        let mut lit = Vec::new();
+        while draw.step() {
+            let bit = draw.current().unwrap();
+            if bit.set {
+                lit.push((bit.row, bit.bit, bit.x, bit.y));
+            }
+        }
+        assert_eq!(lit, [(0, 0, 0, 0), (1, 7, 7, 1)]);
+        assert_eq!(
+            draw.status(),
+            "DRW V0, V0, 2: row 1 bit 7 at (7, 1), 1 XOR 0 = 1"
+        );
+    }
+}
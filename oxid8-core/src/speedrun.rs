@@ -0,0 +1,155 @@
+//! A frame-accurate timer for racing games.
+//!
+//! [`SpeedrunTimer`] counts the frames emulated since a rom was loaded
+//! or reset, so time spent paused, in a menu, or behind on a slow host
+//! doesn't count, and two runs of the same input time the same. Each
+//! frame counts as the frame time of the timer rate it ran at, which
+//! adds up exactly at 60Hz and 50Hz alike.
+//!
+//! Frontends [`sync`](SpeedrunTimer::sync) it to the core's
+//! [emulated time](crate::time) after each frame, so it agrees with
+//! movies and trace logs. Show the held keys next to it with
+//! [`held_keys`](crate::keylog::held_keys).
+//!
+//! ```
+//! use oxid8_core::{Oxid8, TimerRate, speedrun::SpeedrunTimer};
+//!
+//! let mut emu = Oxid8::new();
+//! // JP 200
+//! emu.load_rom_bytes(&[0x12, 0x00]).unwrap();
+//!
+//! let mut timer = SpeedrunTimer::new();
+//! for _ in 0..3723 {
+//!     emu.next_frame().unwrap();
+//!     timer.sync(&emu);
+//! }
+//! assert_eq!(timer.to_string(), "1:02.05");
+//! ```
+
+use std::{fmt, time::Duration};
+
+use crate::{Oxid8, TimerRate, time::EmuTime};
+
+/// Time emulated since the last reset.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SpeedrunTimer {
+    /// Emulated time at the last reset.
+    start: EmuTime,
+    /// Emulated time at the last frame.
+    now: EmuTime,
+}
+
+impl SpeedrunTimer {
+    /// A timer at zero.
+    #[must_use]
+    pub fn new() -> Self {
+        SpeedrunTimer::default()
+    }
+
+    /// Catches up with the emulated time of `core`, call after every
+    /// frame. Starts from zero again if `core` was reset.
+    pub fn sync(&mut self, core: &Oxid8) {
+        if core.time() < self.now {
+            self.start = EmuTime::default();
+        }
+        self.now = core.time();
+    }
+
+    /// Counts a frame emulated at `rate`, for timing frames without a
+    /// core.
+    pub fn tick(&mut self, rate: TimerRate) {
+        self.now.tick_frame(rate);
+    }
+
+    /// Goes back to zero, e.g. when the rom is reset.
+    pub fn reset(&mut self) {
+        self.start = self.now;
+    }
+
+    /// Time since the last reset.
+    #[must_use]
+    pub fn time(&self) -> EmuTime {
+        self.now.since(self.start)
+    }
+
+    /// Frames counted.
+    #[must_use]
+    pub fn frames(&self) -> u64 {
+        self.time().frames()
+    }
+
+    /// Time the frames counted take at their timer rates.
+    #[must_use]
+    pub fn elapsed(&self) -> Duration {
+        self.time().elapsed()
+    }
+}
+
+/// Formatted like `1:02.05`, or `1:00:00.00` from an hour, with the
+/// hundredths rounded down.
+impl fmt::Display for SpeedrunTimer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.time().fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mixed_rates() {
+        let mut timer = SpeedrunTimer::new();
+        for _ in 0..30 {
+            timer.tick(TimerRate::Ntsc);
+        }
+        for _ in 0..25 {
+            timer.tick(TimerRate::Pal);
+        }
+        assert_eq!(timer.frames(), 55);
+        assert_eq!(timer.elapsed(), Duration::from_secs(1));
+        assert_eq!(timer.to_string(), "0:01.00");
+
+        timer.tick(TimerRate::Ntsc);
+        assert_eq!(timer.elapsed(), Duration::from_nanos(1_016_666_666));
+        assert_eq!(timer.to_string(), "0:01.01");
+
+        timer.reset();
+        assert_eq!(timer.frames(), 0);
+        assert_eq!(timer.to_string(), "0:00.00");
+    }
+
+    #[test]
+    fn hours() {
+        let mut timer = SpeedrunTimer::new();
+        for _ in 0..60 * 60 * 60 + 61 * 60 {
+            timer.tick(TimerRate::Ntsc);
+        }
+        assert_eq!(timer.to_string(), "1:01:01.00");
+    }
+
+    #[test]
+    fn syncs_to_core() {
+        let mut emu = Oxid8::new();
+        emu.load_rom_bytes(&[0x12, 0x00]).unwrap();
+        let mut timer = SpeedrunTimer::new();
+        for _ in 0..10 {
+            emu.next_frame().unwrap();
+        }
+        timer.sync(&emu);
+        timer.reset();
+        // Frames run while not syncing still count.
+        for _ in 0..30 {
+            emu.next_frame().unwrap();
+        }
+        timer.sync(&emu);
+        assert_eq!(timer.frames(), 30);
+        assert_eq!(timer.to_string(), "0:00.50");
+
+        emu.reset();
+        emu.load_rom_bytes(&[0x12, 0x00]).unwrap();
+        emu.next_frame().unwrap();
+        timer.sync(&emu);
+        assert_eq!(timer.frames(), 1);
+    }
+}
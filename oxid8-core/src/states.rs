@@ -0,0 +1,321 @@
+//! Savestates kept by rom hash and slot, shared between frontends.
+//!
+//! A [`StateStore`] keeps each state under `<rom_hash>/<slot>`, in the
+//! [`SaveState::to_bytes`] format, with a `<rom_hash>/index` listing the
+//! slots in use, one `<slot> <saved>` line each, `saved` being seconds
+//! since the Unix epoch. Native frontends store them in files with
+//! [`DirStorage`] in the same directory, so a state saved in one loads
+//! in another. The web frontend implements [`Storage`] over
+//! `localStorage` instead.
+//!
+//! The core has no clock of its own, frontends pass in the time.
+//!
+//! ```
+//! use std::time::Duration;
+//! use oxid8_core::{Oxid8, states::{MemoryStorage, StateStore}};
+//!
+//! let mut emu = Oxid8::new();
+//! emu.load_rom_bytes(&[0x70, 0x01, 0x12, 0x00]).unwrap();
+//! emu.next_frame().unwrap();
+//!
+//! let mut store = StateStore::with_storage(MemoryStorage::default());
+//! store.save(3, &emu.save_state(), Duration::from_secs(1_700_000_000)).unwrap();
+//!
+//! let hash = emu.rom_hash().unwrap();
+//! assert_eq!(store.index(hash).unwrap()[0].slot, 3);
+//! let state = store.load(hash, 3).unwrap().unwrap();
+//! assert_eq!(state.to_bytes(), emu.save_state().to_bytes());
+//! ```
+
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use crate::savestate::SaveState;
+
+/// Where a [`StateStore`] keeps its bytes, by `/` separated key.
+pub trait Storage {
+    /// The bytes under `key`, or `None` if there are none.
+    ///
+    /// # Errors
+    ///
+    /// If the bytes exist but can't be read.
+    fn read(&self, key: &str) -> io::Result<Option<Vec<u8>>>;
+
+    /// Puts `bytes` under `key`, replacing any before.
+    ///
+    /// # Errors
+    ///
+    /// If the bytes can't be written.
+    fn write(&mut self, key: &str, bytes: &[u8]) -> io::Result<()>;
+
+    /// Removes the bytes under `key`, if any.
+    ///
+    /// # Errors
+    ///
+    /// If the bytes exist but can't be removed.
+    fn remove(&mut self, key: &str) -> io::Result<()>;
+}
+
+/// A file per key in a directory, created on first write.
+#[derive(Debug, Clone)]
+pub struct DirStorage {
+    dir: PathBuf,
+}
+
+impl DirStorage {
+    #[must_use]
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        DirStorage { dir: dir.into() }
+    }
+
+    /// The directory files are stored in.
+    #[must_use]
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+}
+
+impl Storage for DirStorage {
+    fn read(&self, key: &str) -> io::Result<Option<Vec<u8>>> {
+        match fs::read(self.dir.join(key)) {
+            Ok(bytes) => Ok(Some(bytes)),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
+    fn write(&mut self, key: &str, bytes: &[u8]) -> io::Result<()> {
+        let path = self.dir.join(key);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, bytes)
+    }
+
+    fn remove(&mut self, key: &str) -> io::Result<()> {
+        match fs::remove_file(self.dir.join(key)) {
+            Err(err) if err.kind() != io::ErrorKind::NotFound => Err(err),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Keys kept in memory, for tests and frontends without storage.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryStorage {
+    entries: BTreeMap<String, Vec<u8>>,
+}
+
+impl Storage for MemoryStorage {
+    fn read(&self, key: &str) -> io::Result<Option<Vec<u8>>> {
+        Ok(self.entries.get(key).cloned())
+    }
+
+    fn write(&mut self, key: &str, bytes: &[u8]) -> io::Result<()> {
+        self.entries.insert(key.to_owned(), bytes.to_vec());
+        Ok(())
+    }
+
+    fn remove(&mut self, key: &str) -> io::Result<()> {
+        self.entries.remove(key);
+        Ok(())
+    }
+}
+
+/// A slot in use, from the index.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Entry {
+    pub slot: u8,
+    /// When the state was saved, since the Unix epoch.
+    pub saved: Duration,
+}
+
+/// Savestates by rom hash and slot, see the [module docs](self).
+#[derive(Debug, Clone)]
+pub struct StateStore<S = DirStorage> {
+    storage: S,
+}
+
+impl StateStore<DirStorage> {
+    /// Store states in files in `dir`, which is created on first save.
+    #[must_use]
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        StateStore {
+            storage: DirStorage::new(dir),
+        }
+    }
+}
+
+impl<S: Storage> StateStore<S> {
+    /// Store states in `storage`.
+    #[must_use]
+    pub fn with_storage(storage: S) -> Self {
+        StateStore { storage }
+    }
+
+    /// Saves `state` in `slot` for the rom it was taken from, replacing
+    /// the state there. `now` is the time since the Unix epoch.
+    ///
+    /// # Errors
+    ///
+    /// If no rom was loaded when the state was taken, or the state or
+    /// the index can't be written.
+    pub fn save(&mut self, slot: u8, state: &SaveState, now: Duration) -> io::Result<()> {
+        let hash = state
+            .rom_hash
+            .as_deref()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "The state has no rom"))?;
+        self.storage
+            .write(&format!("{hash}/{slot}"), &state.to_bytes())?;
+
+        let mut index = self.index(hash)?;
+        index.retain(|entry| entry.slot != slot);
+        index.push(Entry {
+            slot,
+            saved: Duration::from_secs(now.as_secs()),
+        });
+        self.write_index(hash, index)
+    }
+
+    /// The state in `slot` for the rom with `hash`, if any.
+    ///
+    /// # Errors
+    ///
+    /// If the state can't be read, isn't a savestate, or is for
+    /// another rom.
+    pub fn load(&self, hash: &str, slot: u8) -> io::Result<Option<SaveState>> {
+        let Some(bytes) = self.storage.read(&format!("{hash}/{slot}"))? else {
+            return Ok(None);
+        };
+        let state = SaveState::from_bytes(&bytes)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        if state.rom_hash.as_deref() != Some(hash) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "The state is for another rom",
+            ));
+        }
+        Ok(Some(state))
+    }
+
+    /// Removes the state in `slot` for the rom with `hash`, if any.
+    ///
+    /// # Errors
+    ///
+    /// If the state or the index can't be written.
+    pub fn remove(&mut self, hash: &str, slot: u8) -> io::Result<()> {
+        self.storage.remove(&format!("{hash}/{slot}"))?;
+        let mut index = self.index(hash)?;
+        index.retain(|entry| entry.slot != slot);
+        self.write_index(hash, index)
+    }
+
+    /// The slots in use for the rom with `hash`, in order.
+    ///
+    /// # Errors
+    ///
+    /// If the index can't be read or isn't an index.
+    pub fn index(&self, hash: &str) -> io::Result<Vec<Entry>> {
+        let Some(bytes) = self.storage.read(&format!("{hash}/index"))? else {
+            return Ok(Vec::new());
+        };
+        String::from_utf8_lossy(&bytes)
+            .lines()
+            .map(|line| {
+                let (slot, saved) = line.split_once(' ')?;
+                Some(Entry {
+                    slot: slot.parse().ok()?,
+                    saved: Duration::from_secs(saved.parse().ok()?),
+                })
+            })
+            .collect::<Option<_>>()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "Invalid state index"))
+    }
+
+    fn write_index(&mut self, hash: &str, mut index: Vec<Entry>) -> io::Result<()> {
+        let key = format!("{hash}/index");
+        if index.is_empty() {
+            return self.storage.remove(&key);
+        }
+        index.sort_by_key(|entry| entry.slot);
+        let text: String = index
+            .iter()
+            .map(|entry| format!("{} {}\n", entry.slot, entry.saved.as_secs()))
+            .collect();
+        self.storage.write(&key, text.as_bytes())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Oxid8;
+
+    fn state(rom: &[u8]) -> SaveState {
+        let mut emu = Oxid8::new();
+        emu.load_rom_bytes(rom).unwrap();
+        emu.save_state()
+    }
+
+    fn roundtrip<S: Storage>(store: &mut StateStore<S>) {
+        let a = state(&[0x12, 0x00]);
+        let b = state(&[0x12, 0x02]);
+        let hash = a.rom_hash.clone().unwrap();
+
+        assert!(store.index(&hash).unwrap().is_empty());
+        assert!(store.load(&hash, 0).unwrap().is_none());
+        store.save(7, &a, Duration::from_millis(5_500)).unwrap();
+        store.save(2, &a, Duration::from_secs(9)).unwrap();
+        store.save(7, &a, Duration::from_secs(10)).unwrap();
+        store.save(1, &b, Duration::from_secs(11)).unwrap();
+
+        let slots = |index: Vec<Entry>| {
+            index
+                .iter()
+                .map(|e| (e.slot, e.saved.as_secs()))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(slots(store.index(&hash).unwrap()), [(2, 9), (7, 10)]);
+        let loaded = store.load(&hash, 7).unwrap().unwrap();
+        assert_eq!(loaded.to_bytes(), a.to_bytes());
+        assert!(store.load(&hash, 1).unwrap().is_none());
+
+        store.remove(&hash, 2).unwrap();
+        store.remove(&hash, 7).unwrap();
+        assert!(store.index(&hash).unwrap().is_empty());
+        assert!(store.load(&hash, 7).unwrap().is_none());
+
+        let mut none = SaveState::from_bytes(&a.to_bytes()).unwrap();
+        none.rom_hash = None;
+        assert!(store.save(0, &none, Duration::ZERO).is_err());
+    }
+
+    #[test]
+    fn memory() {
+        roundtrip(&mut StateStore::with_storage(MemoryStorage::default()));
+    }
+
+    #[test]
+    fn files() {
+        let dir = std::env::temp_dir().join(format!("oxid8-states-{}", std::process::id()));
+        let mut store = StateStore::new(&dir);
+        roundtrip(&mut store);
+
+        // A state copied into another rom's slot is rejected.
+        let a = state(&[0x12, 0x00]);
+        let b = state(&[0x12, 0x02]);
+        let (hash_a, hash_b) = (a.rom_hash.clone().unwrap(), b.rom_hash.clone().unwrap());
+        store.save(0, &a, Duration::ZERO).unwrap();
+        fs::create_dir_all(dir.join(&hash_b)).unwrap();
+        fs::copy(dir.join(&hash_a).join("0"), dir.join(&hash_b).join("0")).unwrap();
+        assert!(store.load(&hash_b, 0).is_err());
+
+        fs::write(dir.join(&hash_a).join("index"), "zz\n").unwrap();
+        assert!(store.index(&hash_a).is_err());
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,283 @@
+//! Running many independent cores at once.
+//!
+//! A [`Swarm`] owns a batch of cores, e.g. one rom under different
+//! quirks, or agents of a training run, and emulates frames of all of
+//! them on a pool of threads. Each core gets its own input, asked for
+//! by core and frame, and ends with an [`Outcome`]: the frames it ran,
+//! the error that stopped it if any, its registers, and the hash of its
+//! screen, the same hash `oxid-replay` prints.
+//!
+//! Cores are split evenly between scoped threads, so a swarm pays off
+//! from a few cores per thread up to thousands. With one thread, as on
+//! the web, cores run on the calling thread.
+//!
+//! ```
+//! use oxid8_core::{Oxid8, input::InputEvent, rng::Rng, swarm::Swarm};
+//!
+//! // RND V0, FF; JP 200
+//! let rom = [0xC0, 0xFF, 0x12, 0x00];
+//! let cores = (0..8)
+//!     .map(|seed| {
+//!         let mut emu = Oxid8::new();
+//!         emu.load_rom_bytes(&rom).unwrap();
+//!         emu.set_rng(Rng::from_seed(seed));
+//!         emu
+//!     })
+//!     .collect();
+//!
+//! let mut swarm = Swarm::new(cores);
+//! let outcomes = swarm.run(60, |_core, _frame| Vec::<InputEvent>::new());
+//! assert_eq!(outcomes.len(), 8);
+//! assert!(outcomes.iter().all(|outcome| outcome.frames == 60));
+//! ```
+
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+    num::NonZeroUsize,
+    thread,
+};
+
+use crate::{Oxid8, hash, input::InputEvent};
+
+/// Where a core ended up after [`Swarm::run`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Outcome {
+    /// Frames run in full, fewer than asked for if the core failed.
+    pub frames: usize,
+    /// The error that stopped the core.
+    pub error: Option<String>,
+    pub pc: u16,
+    pub i: u16,
+    pub v: [u8; 16],
+    /// SHA-1 of the packed screen, see [`Oxid8::pack_screen`].
+    pub screen_hash: String,
+    /// A quick hash of the screen after every frame run, if turned on
+    /// with [`Swarm::hash_frames`], for finding where cores diverge.
+    pub frame_hashes: Vec<u64>,
+}
+
+/// A batch of cores emulated in parallel, see the [module docs](self).
+#[derive(Debug)]
+pub struct Swarm {
+    cores: Vec<Oxid8>,
+    threads: usize,
+    hash_frames: bool,
+}
+
+impl Swarm {
+    /// Runs `cores` on as many threads as the machine has.
+    #[must_use]
+    pub fn new(cores: Vec<Oxid8>) -> Self {
+        Swarm {
+            cores,
+            threads: thread::available_parallelism().map_or(1, NonZeroUsize::get),
+            hash_frames: false,
+        }
+    }
+
+    /// Runs the cores on at most `threads` threads.
+    #[must_use]
+    pub fn with_threads(mut self, threads: usize) -> Self {
+        self.threads = threads.max(1);
+        self
+    }
+
+    /// Keeps a hash of every core's screen after every frame in its
+    /// [`Outcome::frame_hashes`].
+    #[must_use]
+    pub fn hash_frames(mut self, on: bool) -> Self {
+        self.hash_frames = on;
+        self
+    }
+
+    /// The cores, where the last run left them.
+    #[must_use]
+    pub fn cores(&self) -> &[Oxid8] {
+        &self.cores
+    }
+
+    /// Takes the cores back.
+    #[must_use]
+    pub fn into_cores(self) -> Vec<Oxid8> {
+        self.cores
+    }
+
+    /// Number of cores.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.cores.len()
+    }
+
+    /// Returns true if there are no cores.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.cores.is_empty()
+    }
+
+    /// Emulates `frames` frames of every core like
+    /// `next_frame_with_input`, with the events `input(core, frame)`
+    /// returns, and returns the outcome of each core in order. A core
+    /// that fails stops there, the others carry on.
+    ///
+    /// Cores continue from where they are, so runs can be chained.
+    ///
+    /// # Panics
+    ///
+    /// If a core panics, see `next_frame_with_input`.
+    pub fn run<F>(&mut self, frames: usize, input: F) -> Vec<Outcome>
+    where
+        F: Fn(usize, usize) -> Vec<InputEvent> + Sync,
+    {
+        let hash_frames = self.hash_frames;
+        if self.threads == 1 {
+            return self
+                .cores
+                .iter_mut()
+                .enumerate()
+                .map(|(i, core)| run_core(core, frames, hash_frames, |frame| input(i, frame)))
+                .collect();
+        }
+
+        let chunk = self.cores.len().div_ceil(self.threads).max(1);
+        let input = &input;
+        thread::scope(|scope| {
+            let handles: Vec<_> = self
+                .cores
+                .chunks_mut(chunk)
+                .enumerate()
+                .map(|(n, cores)| {
+                    scope.spawn(move || {
+                        cores
+                            .iter_mut()
+                            .enumerate()
+                            .map(|(i, core)| {
+                                run_core(core, frames, hash_frames, |frame| {
+                                    input(n * chunk + i, frame)
+                                })
+                            })
+                            .collect::<Vec<_>>()
+                    })
+                })
+                .collect();
+            handles
+                .into_iter()
+                .flat_map(|handle| match handle.join() {
+                    Ok(outcomes) => outcomes,
+                    Err(panic) => std::panic::resume_unwind(panic),
+                })
+                .collect()
+        })
+    }
+}
+
+/// Emulates `frames` frames of one core.
+fn run_core(
+    core: &mut Oxid8,
+    frames: usize,
+    hash_frames: bool,
+    input: impl Fn(usize) -> Vec<InputEvent>,
+) -> Outcome {
+    let mut frame_hashes = Vec::with_capacity(if hash_frames { frames } else { 0 });
+    let mut error = None;
+    let mut ran = 0;
+    for frame in 0..frames {
+        if let Err(err) = core.next_frame_with_input(&input(frame)) {
+            error = Some(err);
+            break;
+        }
+        ran += 1;
+        if hash_frames {
+            let mut hasher = DefaultHasher::new();
+            core.pack_screen().hash(&mut hasher);
+            frame_hashes.push(hasher.finish());
+        }
+    }
+    Outcome {
+        frames: ran,
+        error,
+        pc: core.pc.get(),
+        i: core.i_reg.get(),
+        v: core.v_reg,
+        screen_hash: hash::rom_hash(&core.pack_screen()),
+        frame_hashes,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// ADD V0, 1; SKP V1; JP 200; then an invalid instruction
+    const ROM: [u8; 8] = [0x70, 0x01, 0xE1, 0x9E, 0x12, 0x00, 0xFF, 0xFF];
+
+    fn cores(n: usize) -> Vec<Oxid8> {
+        (0..n)
+            .map(|_| {
+                let mut emu = Oxid8::new();
+                emu.load_rom_bytes(&ROM).unwrap();
+                emu
+            })
+            .collect()
+    }
+
+    /// Core `n` presses V1's key, which is zero, on frame `n`.
+    fn press(core: usize, frame: usize) -> Vec<InputEvent> {
+        match core == frame {
+            true => vec![InputEvent {
+                cycle: 0,
+                key: 0,
+                pressed: true,
+            }],
+            false => Vec::new(),
+        }
+    }
+
+    #[test]
+    fn per_core_input() {
+        let mut swarm = Swarm::new(cores(10)).with_threads(3).hash_frames(true);
+        let outcomes = swarm.run(8, press);
+        assert_eq!(outcomes.len(), 10);
+        for (n, outcome) in outcomes.iter().enumerate() {
+            match n < 8 {
+                true => {
+                    assert_eq!(outcome.frames, n);
+                    assert!(outcome.error.is_some());
+                    // Past the invalid instruction.
+                    assert_eq!(outcome.pc, 0x208);
+                }
+                false => {
+                    assert_eq!(outcome.frames, 8);
+                    assert_eq!(outcome.error, None);
+                }
+            }
+            assert_eq!(outcome.frame_hashes.len(), outcome.frames);
+        }
+        // The same as running one core alone.
+        let mut alone = cores(1).remove(0);
+        for frame in 0..8 {
+            alone.next_frame_with_input(&press(9, frame)).unwrap();
+        }
+        assert_eq!(outcomes[9].v, alone.v_reg);
+        assert_eq!(
+            outcomes[9].screen_hash,
+            hash::rom_hash(&alone.pack_screen())
+        );
+    }
+
+    #[test]
+    fn runs_chain() {
+        let mut swarm = Swarm::new(cores(2)).with_threads(8);
+        assert_eq!(
+            Swarm::new(cores(10)).with_threads(1).run(8, press),
+            Swarm::new(cores(10)).with_threads(4).run(8, press)
+        );
+        swarm.run(3, |_, _| Vec::new());
+        let outcomes = swarm.run(2, |_, _| Vec::new());
+        let cycles = Oxid8::new().cycles_per_frame() as u8;
+        // Every third instruction is the ADD, the first included.
+        assert_eq!(outcomes[0].v[0], 5 * cycles / 3 + 1);
+        assert_eq!(swarm.len(), 2);
+        assert!(Swarm::new(Vec::new()).run(1, |_, _| Vec::new()).is_empty());
+    }
+}
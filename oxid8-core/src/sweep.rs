@@ -0,0 +1,278 @@
+//! Finding which quirks a rom depends on.
+//!
+//! [`sweep`] runs a rom under several [`Candidate`] quirk settings at
+//! once on a [`Swarm`], with the same input and a seeded random number
+//! generator, and compares every screen with the first candidate's.
+//! Settings a rom never notices match it to the end, and the frame
+//! where the others first diverge shows when the quirk came into play,
+//! which helps pick a preset for an unknown rom.
+//!
+//! ```
+//! use oxid8_core::sweep::{Candidate, sweep};
+//!
+//! // LD V0, 3E; LD I, 20A; DRW V0, V1, 1; JP 206; then a one row sprite
+//! let rom = [0x60, 0x3E, 0xA2, 0x0A, 0xD0, 0x11, 0x12, 0x06, 0x00, 0x00, 0xFF];
+//!
+//! let mut candidates = Candidate::presets();
+//! candidates.extend(Candidate::toggles());
+//! let results = sweep(&rom, &candidates, 10, |_| Vec::new()).unwrap();
+//!
+//! // The sprite is cut off at the right edge unless it wraps.
+//! let wrap_x = results.iter().find(|r| r.name == "wrap_x=on").unwrap();
+//! assert_eq!(wrap_x.diverged, Some(0));
+//! let wrap_y = results.iter().find(|r| r.name == "wrap_y=on").unwrap();
+//! assert_eq!(wrap_y.diverged, None);
+//! ```
+
+use std::{fmt, io};
+
+use crate::{
+    Oxid8,
+    input::InputEvent,
+    quirks::{KeyWait, Quirks, RngMode, Shift},
+    rng::Rng,
+    swarm::{Outcome, Swarm},
+    variant::Variant,
+};
+
+/// Seed of the uniform random number generator of every core, so only
+/// the quirks differ.
+const SEED: u64 = 0;
+
+/// Quirk settings to try, with a name to report them by.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Candidate {
+    pub name: String,
+    pub quirks: Quirks,
+}
+
+impl Candidate {
+    /// The quirks of each [`Variant`], named after them. Variants with
+    /// the same quirks would share a candidate, e.g. "CHIP-8/SCHIP".
+    #[must_use]
+    pub fn presets() -> Vec<Candidate> {
+        let mut presets: Vec<Candidate> = Vec::new();
+        for variant in Variant::ALL {
+            let quirks = variant.quirks();
+            match presets.iter_mut().find(|preset| preset.quirks == quirks) {
+                Some(preset) => preset.name = format!("{}/{variant}", preset.name),
+                None => presets.push(Candidate {
+                    name: variant.to_string(),
+                    quirks,
+                }),
+            }
+        }
+        presets
+    }
+
+    /// The default quirks with one quirk changed, named like the
+    /// `:quirk` command, e.g. "wrap_x=on".
+    #[must_use]
+    pub fn toggles() -> Vec<Candidate> {
+        let default = Quirks::default();
+        [
+            (
+                "rng=lfsr",
+                Quirks {
+                    rng: RngMode::Lfsr,
+                    ..default
+                },
+            ),
+            (
+                "wrap_x=on",
+                Quirks {
+                    wrap_x: true,
+                    ..default
+                },
+            ),
+            (
+                "wrap_y=on",
+                Quirks {
+                    wrap_y: true,
+                    ..default
+                },
+            ),
+            (
+                "key_wait=press",
+                Quirks {
+                    key_wait: KeyWait::Press,
+                    ..default
+                },
+            ),
+            (
+                "i_overflow=on",
+                Quirks {
+                    i_overflow: true,
+                    ..default
+                },
+            ),
+            (
+                "jump_vx=on",
+                Quirks {
+                    jump_vx: true,
+                    ..default
+                },
+            ),
+            (
+                "shift=vy",
+                Quirks {
+                    shift: Shift::Vy,
+                    ..default
+                },
+            ),
+        ]
+        .into_iter()
+        .map(|(name, quirks)| Candidate {
+            name: name.into(),
+            quirks,
+        })
+        .collect()
+    }
+}
+
+/// How a rom ran under one [`Candidate`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SweepResult {
+    pub name: String,
+    pub quirks: Quirks,
+    pub outcome: Outcome,
+    /// The first frame whose screen differs from the first candidate's,
+    /// or where only one of them failed, or `None` if they match.
+    pub diverged: Option<usize>,
+}
+
+/// Formatted like "wrap_x=on: diverged at frame 12" or "rng=lfsr:
+/// same", followed by the error if it failed.
+impl fmt::Display for SweepResult {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.diverged {
+            Some(frame) => write!(f, "{}: diverged at frame {frame}", self.name)?,
+            None => write!(f, "{}: same", self.name)?,
+        }
+        if let Some(err) = &self.outcome.error {
+            write!(f, ", failed after {} frames: {err}", self.outcome.frames)?;
+        }
+        Ok(())
+    }
+}
+
+/// Runs `rom` for `frames` frames under each of `candidates`, with the
+/// events `input(frame)` returns, and compares each with the first.
+///
+/// # Errors
+///
+/// If the rom can't be loaded.
+pub fn sweep<F>(
+    rom: &[u8],
+    candidates: &[Candidate],
+    frames: usize,
+    input: F,
+) -> io::Result<Vec<SweepResult>>
+where
+    F: Fn(usize) -> Vec<InputEvent> + Sync,
+{
+    let mut cores = Vec::with_capacity(candidates.len());
+    for candidate in candidates {
+        let mut emu = Oxid8::new();
+        emu.load_font();
+        emu.load_rom_bytes(rom)?;
+        emu.set_quirks(candidate.quirks);
+        emu.set_rng(Rng::from_seed(SEED));
+        cores.push(emu);
+    }
+
+    let outcomes = Swarm::new(cores)
+        .hash_frames(true)
+        .run(frames, |_, frame| input(frame));
+    let baseline = outcomes.first().cloned();
+    Ok(candidates
+        .iter()
+        .zip(outcomes)
+        .map(|(candidate, outcome)| SweepResult {
+            name: candidate.name.clone(),
+            quirks: candidate.quirks,
+            diverged: baseline.as_ref().and_then(|b| divergence(b, &outcome)),
+            outcome,
+        })
+        .collect())
+}
+
+/// The first frame where `b` differs from `a`.
+fn divergence(a: &Outcome, b: &Outcome) -> Option<usize> {
+    let (a, b) = (&a.frame_hashes, &b.frame_hashes);
+    a.iter()
+        .zip(b)
+        .position(|(a, b)| a != b)
+        .or_else(|| (a.len() != b.len()).then(|| a.len().min(b.len())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn candidates() {
+        let presets = Candidate::presets();
+        let names: Vec<&str> = presets.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, ["CHIP-8", "SCHIP", "XO-CHIP"]);
+        assert_eq!(presets[0].quirks, Quirks::default());
+
+        let toggles = Candidate::toggles();
+        assert_eq!(toggles.len(), 7);
+        for toggle in toggles {
+            assert_ne!(toggle.quirks, Quirks::default());
+            // Named like the quirk's part of the profile.
+            assert!(toggle.quirks.to_string().contains(&toggle.name));
+        }
+    }
+
+    #[test]
+    fn seeded_rng() {
+        // RND V0, FF; LD F, V0; DRW V1, V1, 5; SE V0, 0; JP 200;
+        // then an invalid instruction
+        let rom = [
+            0xC0, 0xFF, 0xF0, 0x29, 0xD1, 0x15, 0x30, 0x00, 0x12, 0x00, 0xFF, 0xFF,
+        ];
+        let candidates = [
+            Candidate {
+                name: "uniform".into(),
+                quirks: Quirks::default(),
+            },
+            Candidate {
+                name: "same".into(),
+                quirks: Quirks::default(),
+            },
+            Candidate::toggles().remove(0),
+        ];
+        let results = sweep(&rom, &candidates, 30, |_| Vec::new()).unwrap();
+        // The seed is the same.
+        assert_eq!(results[1].diverged, None);
+        assert_eq!(results[1].outcome, results[0].outcome);
+        assert_eq!(results[1].to_string(), "same: same");
+        assert!(results[2].diverged.is_some());
+        assert!(
+            results[2]
+                .to_string()
+                .starts_with("rng=lfsr: diverged at frame ")
+        );
+
+        // Too big to load.
+        assert!(sweep(&[0; 4096], &candidates, 1, |_| Vec::new()).is_err());
+    }
+
+    #[test]
+    fn failure_is_divergence() {
+        let a = |frame_hashes: Vec<u64>| Outcome {
+            frames: frame_hashes.len(),
+            error: None,
+            pc: 0,
+            i: 0,
+            v: [0; 16],
+            screen_hash: String::new(),
+            frame_hashes,
+        };
+        assert_eq!(divergence(&a(vec![1, 2, 3]), &a(vec![1, 2])), Some(2));
+        assert_eq!(divergence(&a(vec![1, 2, 3]), &a(vec![1, 4, 3])), Some(1));
+        assert_eq!(divergence(&a(vec![1, 2]), &a(vec![1, 2])), None);
+    }
+}
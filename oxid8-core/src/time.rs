@@ -0,0 +1,152 @@
+//! Emulated time, counted by the core.
+//!
+//! [`Oxid8::time`] is the time emulated since the core was created or
+//! reset: the frames and cycles run, and what the frames add up to at
+//! the timer rates they ran at. It only moves when the core runs, so
+//! it stands still while paused and races ahead in fast-forward, and
+//! the speedrun timer, movie recordings and trace logs all agree on
+//! when something happened, whatever the host did in between. Loading
+//! a savestate or restarting the rom doesn't turn it back.
+//!
+//! ```
+//! use oxid8_core::Oxid8;
+//!
+//! let mut emu = Oxid8::new();
+//! // JP 200
+//! emu.load_rom_bytes(&[0x12, 0x00]).unwrap();
+//! let start = emu.time();
+//! for _ in 0..90 {
+//!     emu.next_frame().unwrap();
+//! }
+//! let time = emu.time().since(start);
+//! assert_eq!(time.frames(), 90);
+//! assert_eq!(time.cycles(), 900);
+//! assert_eq!(time.to_string(), "0:01.50");
+//! ```
+
+use std::{fmt, time::Duration};
+
+use crate::{Oxid8, TimerRate};
+
+/// Ticks in a second, a whole number of frames at every timer rate.
+const TICKS_PER_SEC: u64 = 300;
+
+/// A point in emulated time, or the time between two.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct EmuTime {
+    frames: u64,
+    cycles: u64,
+    ticks: u64,
+}
+
+impl EmuTime {
+    /// Frames run.
+    #[must_use]
+    pub fn frames(&self) -> u64 {
+        self.frames
+    }
+
+    /// Cycles run.
+    #[must_use]
+    pub fn cycles(&self) -> u64 {
+        self.cycles
+    }
+
+    /// Time the frames run take at their timer rates.
+    #[must_use]
+    pub fn elapsed(&self) -> Duration {
+        Duration::from_secs(self.ticks / TICKS_PER_SEC)
+            + Duration::from_nanos(self.ticks % TICKS_PER_SEC * 1_000_000_000 / TICKS_PER_SEC)
+    }
+
+    /// Time from `start` to here, zero if `start` is later.
+    #[must_use]
+    pub fn since(&self, start: EmuTime) -> EmuTime {
+        EmuTime {
+            frames: self.frames.saturating_sub(start.frames),
+            cycles: self.cycles.saturating_sub(start.cycles),
+            ticks: self.ticks.saturating_sub(start.ticks),
+        }
+    }
+
+    /// Counts a cycle.
+    pub(crate) fn tick_cycle(&mut self) {
+        self.cycles += 1;
+    }
+
+    /// Counts a frame run at `rate`.
+    pub(crate) fn tick_frame(&mut self, rate: TimerRate) {
+        self.frames += 1;
+        self.ticks += TICKS_PER_SEC / u64::from(rate.hz());
+    }
+}
+
+/// Formatted like `1:02.05`, or `1:00:00.00` from an hour, with the
+/// hundredths rounded down.
+impl fmt::Display for EmuTime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let hundredths = self.ticks * 100 / TICKS_PER_SEC;
+        let (secs, hundredths) = (hundredths / 100, hundredths % 100);
+        let (mins, secs) = (secs / 60, secs % 60);
+        match mins / 60 {
+            0 => write!(f, "{mins}:{secs:02}.{hundredths:02}"),
+            hours => write!(f, "{hours}:{:02}:{secs:02}.{hundredths:02}", mins % 60),
+        }
+    }
+}
+
+impl Oxid8 {
+    /// Time emulated since the core was created or reset, see the
+    /// [module docs](self).
+    #[must_use]
+    pub fn time(&self) -> EmuTime {
+        self.time
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mixed_rates() {
+        let mut time = EmuTime::default();
+        for _ in 0..30 {
+            time.tick_frame(TimerRate::Ntsc);
+        }
+        for _ in 0..25 {
+            time.tick_frame(TimerRate::Pal);
+        }
+        assert_eq!(time.frames(), 55);
+        assert_eq!(time.elapsed(), Duration::from_secs(1));
+        assert_eq!(time.to_string(), "0:01.00");
+
+        time.tick_frame(TimerRate::Ntsc);
+        assert_eq!(time.elapsed(), Duration::from_nanos(1_016_666_666));
+        assert_eq!(time.to_string(), "0:01.01");
+    }
+
+    #[test]
+    fn hours() {
+        let mut time = EmuTime::default();
+        for _ in 0..60 * 60 * 60 + 61 * 60 {
+            time.tick_frame(TimerRate::Ntsc);
+        }
+        assert_eq!(time.to_string(), "1:01:01.00");
+    }
+
+    #[test]
+    fn survives_savestates() {
+        let mut emu = Oxid8::new();
+        emu.load_rom_bytes(&[0x12, 0x00]).unwrap();
+        emu.next_frame().unwrap();
+        let state = emu.save_state();
+        emu.next_frame().unwrap();
+        emu.restore_state(&state);
+        assert_eq!(emu.time().frames(), 2);
+        assert_eq!(emu.time().since(emu.time()).frames(), 0);
+
+        emu.reset();
+        assert_eq!(emu.time(), EmuTime::default());
+    }
+}
@@ -0,0 +1,154 @@
+//! Handing frames from the emulation thread to the render thread.
+//!
+//! A triple buffer keeps three copies of a frame: one the [`Writer`]
+//! fills, one the [`Reader`] shows, and the latest one published
+//! between them. Publishing and reading swap boxes under a lock held
+//! only for the swap, so neither side waits on the other filling or
+//! drawing a frame. Frames published faster than they are read are
+//! dropped, the reader always gets the newest, so an emulator running
+//! ahead at turbo speeds never holds back the display and a slow
+//! display never holds back the emulator.
+//!
+//! Frontends hand off packed screens, see
+//! [`pack_screen`](crate::export::pack_screen), 256 bytes a frame.
+//!
+//! ```
+//! use oxid8_core::triple;
+//!
+//! let (mut writer, mut reader) = triple::buffer(0u32);
+//! *writer.back() = 1;
+//! writer.publish();
+//! *writer.back() = 2;
+//! writer.publish();
+//!
+//! // Frame 1 was overwritten before it was read.
+//! assert!(reader.update());
+//! assert_eq!(*reader.front(), 2);
+//! assert!(!reader.update());
+//! ```
+
+use std::sync::{Arc, Mutex, PoisonError};
+
+/// The latest frame published, and whether it is newer than the
+/// reader's.
+#[derive(Debug)]
+struct Middle<T> {
+    frame: Box<T>,
+    fresh: bool,
+}
+
+/// Publishes frames to a [`Reader`], see the [module docs](self).
+#[derive(Debug)]
+pub struct Writer<T> {
+    back: Box<T>,
+    middle: Arc<Mutex<Middle<T>>>,
+}
+
+/// Reads the newest frame of a [`Writer`], see the [module docs](self).
+#[derive(Debug)]
+pub struct Reader<T> {
+    front: Box<T>,
+    middle: Arc<Mutex<Middle<T>>>,
+}
+
+/// A writer and reader sharing three copies of `initial`.
+#[must_use]
+pub fn buffer<T: Clone>(initial: T) -> (Writer<T>, Reader<T>) {
+    let middle = Arc::new(Mutex::new(Middle {
+        frame: Box::new(initial.clone()),
+        fresh: false,
+    }));
+    let writer = Writer {
+        back: Box::new(initial.clone()),
+        middle: Arc::clone(&middle),
+    };
+    let reader = Reader {
+        front: Box::new(initial),
+        middle,
+    };
+    (writer, reader)
+}
+
+impl<T> Writer<T> {
+    /// The frame being filled, holding an older frame until it is
+    /// overwritten.
+    pub fn back(&mut self) -> &mut T {
+        &mut self.back
+    }
+
+    /// Hands the frame filled to the reader, replacing any it hasn't
+    /// read.
+    pub fn publish(&mut self) {
+        // A panic while swapping leaves whole frames, nothing to fix.
+        let mut middle = self.middle.lock().unwrap_or_else(PoisonError::into_inner);
+        std::mem::swap(&mut self.back, &mut middle.frame);
+        middle.fresh = true;
+    }
+}
+
+impl<T> Reader<T> {
+    /// Takes the newest frame published, returning true if there was
+    /// one since the last update.
+    pub fn update(&mut self) -> bool {
+        let mut middle = self.middle.lock().unwrap_or_else(PoisonError::into_inner);
+        if !middle.fresh {
+            return false;
+        }
+        std::mem::swap(&mut self.front, &mut middle.frame);
+        middle.fresh = false;
+        true
+    }
+
+    /// The frame taken by the last update.
+    #[must_use]
+    pub fn front(&self) -> &T {
+        &self.front
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn reads_the_newest_frame() {
+        let (mut writer, mut reader) = buffer(vec![0u8; 4]);
+        assert!(!reader.update());
+        assert_eq!(reader.front(), &[0; 4]);
+
+        writer.back().fill(1);
+        writer.publish();
+        assert!(reader.update());
+        assert_eq!(reader.front(), &[1; 4]);
+
+        // The back buffer is whichever the reader let go of, the
+        // initial frame here, never the one shown.
+        writer.back().fill(2);
+        writer.publish();
+        writer.back()[0] = 3;
+        writer.publish();
+        assert!(reader.update());
+        assert_eq!(reader.front(), &[3, 0, 0, 0]);
+    }
+
+    #[test]
+    fn hands_frames_across_threads() {
+        let (mut writer, mut reader) = buffer(0u64);
+        let emulator = thread::spawn(move || {
+            for frame in 1..=10_000 {
+                *writer.back() = frame;
+                writer.publish();
+            }
+        });
+        let mut last = 0;
+        while last < 10_000 {
+            if reader.update() {
+                // Frames are skipped but never go back.
+                assert!(*reader.front() > last);
+                last = *reader.front();
+            }
+        }
+        emulator.join().unwrap();
+    }
+}
@@ -0,0 +1,292 @@
+//! Interpreter variants and switching between them at runtime.
+//!
+//! SCHIP adds a 128x64 hi-res mode and scrolling to CHIP-8, and
+//! XO-CHIP adds more memory, bitplanes, and sample playback on top.
+//! The core only emulates the CHIP-8 instruction set and display, so
+//! selecting another variant fails and leaves the machine as it was.
+//! [`Variant::is_supported`] tells which can be picked.
+//!
+//! Switching variant keeps memory, registers, timers, and the display,
+//! like SCHIP keeps them when switching between lo-res and hi-res, and
+//! applies the quirks games for the variant expect. Quirks set to
+//! something other than the old variant's preset were picked by the
+//! player and are kept. Frontends can flip modes without reloading the
+//! ROM.
+//!
+//! [`Oxid8::profile`] sums up the variant with the quirks, timer rate,
+//! and speed, for frontends to show which configuration is running.
+//!
+//! ```
+//! use oxid8_core::{Oxid8, variant::Variant};
+//!
+//! let mut emu = Oxid8::new();
+//! assert!(emu.set_variant(Variant::Schip).is_err());
+//! assert_eq!(emu.variant(), Variant::Chip8);
+//! ```
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::{fmt, str::FromStr};
+
+use crate::{Oxid8, TimerRate, quirks::Quirks};
+
+/// A CHIP-8 dialect, see [`Oxid8::set_variant`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum Variant {
+    /// The original COSMAC VIP interpreter.
+    #[default]
+    Chip8,
+    /// SUPER-CHIP 1.1 for the HP48.
+    Schip,
+    /// Octo's XO-CHIP.
+    XoChip,
+}
+
+impl Variant {
+    /// Every variant, in order of release.
+    pub const ALL: [Variant; 3] = [Variant::Chip8, Variant::Schip, Variant::XoChip];
+
+    /// Returns true if the core emulates this variant.
+    #[must_use]
+    pub fn is_supported(self) -> bool {
+        self == Variant::Chip8
+    }
+
+    /// Quirks games written for this variant expect. SCHIP clips
+    /// sprites at the screen edges and jumps with `Bxnn`, XO-CHIP wraps
+    /// sprites around.
+    #[must_use]
+    pub fn quirks(self) -> Quirks {
+        match self {
+            Variant::Chip8 => Quirks::default(),
+            Variant::Schip => Quirks {
+                jump_vx: true,
+                ..Quirks::default()
+            },
+            Variant::XoChip => Quirks {
+                wrap_x: true,
+                wrap_y: true,
+                ..Quirks::default()
+            },
+        }
+    }
+}
+
+/// Formatted as "CHIP-8", "SCHIP", or "XO-CHIP".
+impl fmt::Display for Variant {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Variant::Chip8 => "CHIP-8",
+            Variant::Schip => "SCHIP",
+            Variant::XoChip => "XO-CHIP",
+        })
+    }
+}
+
+/// Parses names case insensitively, with or without the hyphen.
+impl FromStr for Variant {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().replace('-', "").as_str() {
+            "chip8" => Ok(Variant::Chip8),
+            "schip" | "superchip" => Ok(Variant::Schip),
+            "xochip" => Ok(Variant::XoChip),
+            _ => Err(format!("Invalid variant: {s}")),
+        }
+    }
+}
+
+/// What the machine is configured to emulate, see [`Oxid8::profile`].
+///
+/// Include it in bug reports: it is everything besides the ROM that
+/// changes how a game runs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Profile {
+    pub variant: Variant,
+    pub quirks: Quirks,
+    pub timer_rate: TimerRate,
+    /// See [`Oxid8::cycles_per_frame`].
+    pub cycles_per_frame: u32,
+}
+
+/// Formatted like "CHIP-8, 10 cycles/frame @ 60Hz, rng=uniform
+/// wrap_x=off wrap_y=off key_wait=release i_overflow=off jump_vx=off
+/// shift=vx", with the cycles of a frame at the timer rate, so 10 at
+/// 60Hz is 12 at 50Hz.
+impl fmt::Display for Profile {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let hz = self.timer_rate.hz();
+        let cycles = f64::from(self.cycles_per_frame.saturating_mul(60)) / f64::from(hz);
+        write!(
+            f,
+            "{}, {cycles} cycles/frame @ {hz}Hz, {}",
+            self.variant, self.quirks
+        )
+    }
+}
+
+impl Oxid8 {
+    /// The active variant, quirks, timer rate, and speed.
+    #[must_use]
+    pub fn profile(&self) -> Profile {
+        Profile {
+            variant: self.variant,
+            quirks: self.quirks,
+            timer_rate: self.timer_rate,
+            cycles_per_frame: self.cycles_per_frame,
+        }
+    }
+
+    /// The variant being emulated.
+    #[must_use]
+    pub fn variant(&self) -> Variant {
+        self.variant
+    }
+
+    /// Switches to `variant` at runtime, applying its quirks where they
+    /// are still the old variant's, see the [module docs](self).
+    ///
+    /// Memory, registers, timers, and the display are kept. Like
+    /// quirks, the variant is a setting and survives `reset`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error naming the variant if the core doesn't emulate
+    /// it, leaving the machine unchanged.
+    pub fn set_variant(&mut self, variant: Variant) -> Result<(), String> {
+        if !variant.is_supported() {
+            return Err(format!("{variant} is not supported yet"));
+        }
+        self.quirks = migrate(self.quirks, self.variant.quirks(), variant.quirks());
+        self.variant = variant;
+        Ok(())
+    }
+}
+
+/// `quirks` moved from the preset `from` to the preset `to`, keeping
+/// the quirks that differ from `from`.
+fn migrate(quirks: Quirks, from: Quirks, to: Quirks) -> Quirks {
+    fn pick<T: PartialEq>(quirk: T, from: T, to: T) -> T {
+        if quirk == from { to } else { quirk }
+    }
+    Quirks {
+        rng: pick(quirks.rng, from.rng, to.rng),
+        wrap_x: pick(quirks.wrap_x, from.wrap_x, to.wrap_x),
+        wrap_y: pick(quirks.wrap_y, from.wrap_y, to.wrap_y),
+        key_wait: pick(quirks.key_wait, from.key_wait, to.key_wait),
+        i_overflow: pick(quirks.i_overflow, from.i_overflow, to.i_overflow),
+        jump_vx: pick(quirks.jump_vx, from.jump_vx, to.jump_vx),
+        shift: pick(quirks.shift, from.shift, to.shift),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        for variant in Variant::ALL {
+            assert_eq!(variant.to_string().parse(), Ok(variant));
+        }
+        assert_eq!("xochip".parse(), Ok(Variant::XoChip));
+        assert_eq!("Super-Chip".parse(), Ok(Variant::Schip));
+        assert!("chip48".parse::<Variant>().is_err());
+    }
+
+    #[test]
+    fn switch_keeps_state() {
+        let mut emu = Oxid8::new();
+        // LD V0, 2A; JP 202
+        emu.load_rom_bytes(&[0x60, 0x2A, 0x12, 0x02]).unwrap();
+        emu.run_cycle().unwrap();
+        emu.set_quirks(Quirks {
+            wrap_x: true,
+            ..Quirks::default()
+        });
+        let state = emu.save_state();
+
+        assert_eq!(emu.set_variant(Variant::Chip8), Ok(()));
+        assert_eq!(emu.save_state(), state);
+        // Picked by the player, not the preset.
+        assert!(emu.quirks().wrap_x);
+
+        emu.reset();
+        assert_eq!(emu.variant(), Variant::Chip8);
+    }
+
+    #[test]
+    fn migrate_keeps_overrides() {
+        let custom = Quirks {
+            i_overflow: true,
+            ..Variant::Chip8.quirks()
+        };
+        let schip = migrate(custom, Variant::Chip8.quirks(), Variant::Schip.quirks());
+        assert!(schip.jump_vx && schip.i_overflow);
+
+        // Back again, the preset's own quirks are undone.
+        let xochip = Quirks {
+            i_overflow: true,
+            ..Variant::XoChip.quirks()
+        };
+        let chip8 = migrate(xochip, Variant::XoChip.quirks(), Variant::Chip8.quirks());
+        assert_eq!(
+            chip8,
+            Quirks {
+                i_overflow: true,
+                ..Quirks::default()
+            }
+        );
+    }
+
+    #[test]
+    fn profile() {
+        let mut emu = Oxid8::new();
+        assert_eq!(
+            emu.profile().to_string(),
+            "CHIP-8, 10 cycles/frame @ 60Hz, rng=uniform wrap_x=off wrap_y=off key_wait=release i_overflow=off jump_vx=off shift=vx"
+        );
+
+        emu.set_cycles_per_frame(15);
+        emu.set_timer_rate(TimerRate::Pal);
+        emu.set_quirks(Quirks {
+            rng: crate::quirks::RngMode::Lfsr,
+            wrap_x: true,
+            ..Quirks::default()
+        });
+        let profile = emu.profile();
+        assert_eq!(profile.cycles_per_frame, 15);
+        assert!(profile.quirks.wrap_x);
+        assert_eq!(
+            profile.to_string(),
+            "CHIP-8, 18 cycles/frame @ 50Hz, rng=lfsr wrap_x=on wrap_y=off key_wait=release i_overflow=off jump_vx=off shift=vx"
+        );
+        emu.set_cycles_per_frame(11);
+        assert!(
+            emu.profile()
+                .to_string()
+                .contains(" 13.2 cycles/frame @ 50Hz")
+        );
+    }
+
+    #[test]
+    fn unsupported() {
+        let mut emu = Oxid8::new();
+        emu.load_rom_bytes(&[0x60, 0x2A]).unwrap();
+        let state = emu.save_state();
+        let quirks = emu.quirks();
+
+        for variant in [Variant::Schip, Variant::XoChip] {
+            assert_eq!(
+                emu.set_variant(variant),
+                Err(format!("{variant} is not supported yet"))
+            );
+            assert_eq!(emu.variant(), Variant::Chip8);
+            assert_eq!(emu.save_state(), state);
+            assert_eq!(emu.quirks(), quirks);
+        }
+    }
+}
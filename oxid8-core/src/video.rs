@@ -0,0 +1,198 @@
+//! Display-only state for frontend effects.
+//!
+//! [`VideoState`] is everything needed to show the screen and nothing
+//! else, so frontends can preview or rewind the display, e.g. on a
+//! savestate picker, without touching the cpu, memory, or timers.
+//!
+//! [`ScreenChanges`] counts the pixels drawn between frames and where,
+//! so frontends can skip redrawing a static screen or redraw only the
+//! region that changed.
+//!
+//! The core only has the monochrome 64x32 display. Hi-res and extra
+//! planes will be added here when the core supports them.
+
+use std::mem;
+
+use crate::{Oxid8, SCREEN_AREA, SCREEN_WIDTH};
+
+/// A copy of the display.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct VideoState {
+    /// See [`Oxid8::screen_ref`].
+    pub screen: [bool; SCREEN_AREA],
+}
+
+/// A rectangle of the screen in pixels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Region {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+/// Pixels that changed since the last [`Oxid8::take_screen_changes`].
+///
+/// A pixel drawn twice counts twice, so the screen may look the same
+/// even if some changed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ScreenChanges {
+    /// Number of pixels flipped.
+    pub pixels: u32,
+    /// Bounding box of the flipped pixels, `None` if there are none.
+    pub region: Option<Region>,
+}
+
+impl ScreenChanges {
+    /// Returns true if no pixels changed.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.pixels == 0
+    }
+
+    /// Adds the changes in `other`, for frontends that draw less often
+    /// than frames run.
+    pub fn merge(&mut self, other: &ScreenChanges) {
+        self.pixels += other.pixels;
+        self.region = match (self.region, other.region) {
+            (Some(a), Some(b)) => Some(a.union(b)),
+            (a, b) => a.or(b),
+        };
+    }
+
+    /// Records a flip of the pixel at `x`, `y`.
+    pub(crate) fn mark(&mut self, x: usize, y: usize) {
+        self.merge(&ScreenChanges {
+            pixels: 1,
+            region: Some(Region {
+                x,
+                y,
+                width: 1,
+                height: 1,
+            }),
+        });
+    }
+}
+
+impl Region {
+    /// The smallest region covering both.
+    fn union(self, other: Region) -> Region {
+        let (left, top) = (self.x.min(other.x), self.y.min(other.y));
+        let right = (self.x + self.width).max(other.x + other.width);
+        let bottom = (self.y + self.height).max(other.y + other.height);
+        Region {
+            x: left,
+            y: top,
+            width: right - left,
+            height: bottom - top,
+        }
+    }
+}
+
+impl Oxid8 {
+    /// Returns the pixels that changed since the last call and starts
+    /// counting again.
+    pub fn take_screen_changes(&mut self) -> ScreenChanges {
+        mem::take(&mut self.screen_changes)
+    }
+
+    /// Replaces the whole screen, recording the pixels that differ.
+    pub(crate) fn set_screen(&mut self, screen: &[bool; SCREEN_AREA]) {
+        for (i, (old, new)) in self.screen.iter_mut().zip(screen).enumerate() {
+            if old != new {
+                *old = *new;
+                self.screen_changes.mark(i % SCREEN_WIDTH, i / SCREEN_WIDTH);
+            }
+        }
+    }
+
+    /// Copies the display state.
+    #[must_use]
+    pub fn video_state(&self) -> VideoState {
+        VideoState {
+            screen: *self.screen,
+        }
+    }
+
+    /// Restores the display state from [`Oxid8::video_state`], leaving
+    /// the rest of the machine untouched.
+    pub fn restore_video_state(&mut self, video: &VideoState) {
+        self.set_screen(&video.screen);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn restore_display_only() {
+        let mut emu = Oxid8::new();
+        emu.screen[0] = true;
+        let video = emu.video_state();
+
+        *emu.screen = [true; SCREEN_AREA];
+        emu.v_reg[0] = 7;
+        emu.pc += 2;
+        let pc = emu.pc;
+
+        emu.restore_video_state(&video);
+        assert_eq!(emu.video_state(), video);
+        assert!(emu.screen[0] && !emu.screen[1]);
+        assert_eq!((emu.v_reg[0], emu.pc), (7, pc));
+    }
+
+    #[test]
+    fn screen_changes() {
+        let mut emu = Oxid8::new();
+        emu.load_font();
+        assert!(emu.take_screen_changes().is_empty());
+
+        // LD I, 0x050 ("0"); LD V0, 3; LD V1, 2; DRW V0, V1, 5
+        emu.load_rom_bytes(&[0xA0, 0x50, 0x60, 0x03, 0x61, 0x02, 0xD0, 0x15])
+            .unwrap();
+        for _ in 0..4 {
+            emu.run_cycle().unwrap();
+        }
+        let changes = emu.take_screen_changes();
+        assert_eq!(changes.pixels, 14);
+        assert_eq!(
+            changes.region,
+            Some(Region {
+                x: 3,
+                y: 2,
+                width: 4,
+                height: 5
+            })
+        );
+        assert!(emu.take_screen_changes().is_empty());
+
+        // Restoring a blank screen changes the same pixels back.
+        emu.restore_video_state(&Oxid8::new().video_state());
+        assert_eq!(emu.take_screen_changes(), changes);
+
+        let mut merged = ScreenChanges::default();
+        merged.merge(&changes);
+        assert_eq!(merged, changes);
+        merged.merge(&ScreenChanges {
+            pixels: 1,
+            region: Some(Region {
+                x: 10,
+                y: 0,
+                width: 1,
+                height: 1,
+            }),
+        });
+        assert_eq!(merged.pixels, 15);
+        assert_eq!(
+            merged.region,
+            Some(Region {
+                x: 3,
+                y: 0,
+                width: 8,
+                height: 7
+            })
+        );
+    }
+}
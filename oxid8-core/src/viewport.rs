@@ -0,0 +1,230 @@
+//! Fitting the screen into small terminals.
+//!
+//! Text frontends draw the screen with characters that each stand for
+//! a block of pixels, at one of three [`Zoom`]s: Braille dots, 2x4
+//! pixels a cell, half blocks, 1x2 pixels a cell, or full blocks, two
+//! cells a pixel. A [`Viewport`] picks the zoom and the part of the
+//! screen shown in an area of cells. It fits by default, picking the
+//! largest zoom that shows the whole screen, so a 64x32 screen needs
+//! 64x16 cells in half blocks but only 32x8 in Braille. Players can
+//! cycle to larger zooms than fit and pan around the part shown.
+//!
+//! Only the 64x32 screen is emulated so far. Viewports take the
+//! screen's size, so SCHIP's 128x64 mode can use them when it lands.
+//!
+//! ```
+//! use oxid8_core::viewport::{Viewport, Zoom};
+//!
+//! let mut viewport = Viewport::default();
+//! // 40x12 cells are too small for half blocks.
+//! let view = viewport.view((64, 32), (40, 12));
+//! assert_eq!(view.zoom, Zoom::Braille);
+//! assert_eq!((view.width, view.height), (64, 32));
+//!
+//! viewport.cycle((64, 32), (40, 12));
+//! viewport.pan(100, 0, (64, 32), (40, 12));
+//! let view = viewport.view((64, 32), (40, 12));
+//! assert_eq!(view.zoom, Zoom::HalfBlock);
+//! // Panned as far right as the screen goes.
+//! assert_eq!((view.x, view.width), (24, 40));
+//! ```
+
+use std::fmt;
+
+/// How many pixels a cell shows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Zoom {
+    /// 2x4 pixels a cell, as Braille dots.
+    Braille,
+    /// 1x2 pixels a cell, as `▀` and `▄`.
+    HalfBlock,
+    /// Two cells a pixel, as `██`, for square pixels.
+    Block,
+}
+
+impl Zoom {
+    /// Every zoom, smallest first.
+    pub const ALL: [Zoom; 3] = [Zoom::Braille, Zoom::HalfBlock, Zoom::Block];
+
+    /// Cells needed to show `width` by `height` pixels, across and
+    /// down.
+    #[must_use]
+    pub fn cells(self, width: usize, height: usize) -> (usize, usize) {
+        match self {
+            Zoom::Braille => (width.div_ceil(2), height.div_ceil(4)),
+            Zoom::HalfBlock => (width, height.div_ceil(2)),
+            Zoom::Block => (width * 2, height),
+        }
+    }
+
+    /// Pixels shown in `cols` by `rows` cells, across and down.
+    #[must_use]
+    pub fn pixels(self, cols: usize, rows: usize) -> (usize, usize) {
+        match self {
+            Zoom::Braille => (cols * 2, rows * 4),
+            Zoom::HalfBlock => (cols, rows * 2),
+            Zoom::Block => (cols / 2, rows),
+        }
+    }
+}
+
+/// Formatted like "half blocks".
+impl fmt::Display for Zoom {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.pad(match self {
+            Zoom::Braille => "braille",
+            Zoom::HalfBlock => "half blocks",
+            Zoom::Block => "blocks",
+        })
+    }
+}
+
+/// The part of the screen to draw, in pixels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct View {
+    pub zoom: Zoom,
+    /// Left edge.
+    pub x: usize,
+    /// Top edge.
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl View {
+    /// Returns true if the whole screen is shown.
+    #[must_use]
+    pub fn is_whole(&self, screen: (usize, usize)) -> bool {
+        (self.width, self.height) == screen
+    }
+}
+
+/// The zoom and pan of the screen, see the [module docs](self). The
+/// default fits the screen.
+///
+/// Sizes are `(width, height)`: the screen's in pixels, as rotated, and
+/// the area's in cells.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Viewport {
+    /// `None` to fit.
+    zoom: Option<Zoom>,
+    x: usize,
+    y: usize,
+}
+
+impl Viewport {
+    /// The largest zoom showing all of `screen` in `area`, or Braille if
+    /// none does.
+    #[must_use]
+    pub fn fit(screen: (usize, usize), area: (usize, usize)) -> Zoom {
+        Zoom::ALL
+            .into_iter()
+            .rev()
+            .find(|zoom| {
+                let (cols, rows) = zoom.cells(screen.0, screen.1);
+                cols <= area.0 && rows <= area.1
+            })
+            .unwrap_or(Zoom::Braille)
+    }
+
+    /// The zoom picked, `None` when fitting.
+    #[must_use]
+    pub fn zoom(&self) -> Option<Zoom> {
+        self.zoom
+    }
+
+    /// The part of `screen` to draw in `area`, kept on the screen
+    /// however far it was panned.
+    #[must_use]
+    pub fn view(&self, screen: (usize, usize), area: (usize, usize)) -> View {
+        let zoom = self.zoom.unwrap_or_else(|| Self::fit(screen, area));
+        let (width, height) = zoom.pixels(area.0, area.1);
+        let (width, height) = (width.min(screen.0), height.min(screen.1));
+        View {
+            zoom,
+            x: self.x.min(screen.0 - width),
+            y: self.y.min(screen.1 - height),
+            width,
+            height,
+        }
+    }
+
+    /// Zooms in a step from the zoom shown, and from the largest back
+    /// to fitting.
+    pub fn cycle(&mut self, screen: (usize, usize), area: (usize, usize)) {
+        let shown = self.view(screen, area).zoom;
+        self.zoom = match (self.zoom, shown) {
+            (Some(Zoom::Block), _) => None,
+            (_, Zoom::Braille) => Some(Zoom::HalfBlock),
+            (_, Zoom::HalfBlock | Zoom::Block) => Some(Zoom::Block),
+        };
+        if self.zoom.is_none() {
+            (self.x, self.y) = (0, 0);
+        }
+    }
+
+    /// Moves the view `dx` pixels right and `dy` down, stopping at the
+    /// edges of the screen.
+    pub fn pan(&mut self, dx: isize, dy: isize, screen: (usize, usize), area: (usize, usize)) {
+        let view = self.view(screen, area);
+        let clamp =
+            |start: usize, delta: isize, max: usize| start.saturating_add_signed(delta).min(max);
+        self.x = clamp(view.x, dx, screen.0 - view.width);
+        self.y = clamp(view.y, dy, screen.1 - view.height);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCREEN: (usize, usize) = (64, 32);
+    const HIRES: (usize, usize) = (128, 64);
+
+    #[test]
+    fn fits() {
+        assert_eq!(Viewport::fit(SCREEN, (128, 32)), Zoom::Block);
+        assert_eq!(Viewport::fit(SCREEN, (80, 24)), Zoom::HalfBlock);
+        assert_eq!(Viewport::fit(SCREEN, (63, 24)), Zoom::Braille);
+        assert_eq!(Viewport::fit(HIRES, (80, 24)), Zoom::Braille);
+        assert_eq!(Viewport::fit(HIRES, (128, 32)), Zoom::HalfBlock);
+        // Too small for anything, the corner is shown.
+        assert_eq!(Viewport::fit(HIRES, (20, 5)), Zoom::Braille);
+        let view = Viewport::default().view(HIRES, (20, 5));
+        assert_eq!((view.x, view.y, view.width, view.height), (0, 0, 40, 20));
+        assert!(!view.is_whole(HIRES));
+    }
+
+    #[test]
+    fn cycles_and_pans() {
+        let area = (80, 24);
+        let mut viewport = Viewport::default();
+        assert!(viewport.view(SCREEN, area).is_whole(SCREEN));
+        viewport.cycle(SCREEN, area);
+        assert_eq!(viewport.zoom(), Some(Zoom::Block));
+        let view = viewport.view(SCREEN, area);
+        assert_eq!((view.width, view.height), (40, 24));
+
+        viewport.pan(-5, 3, SCREEN, area);
+        viewport.pan(30, 30, SCREEN, area);
+        let view = viewport.view(SCREEN, area);
+        assert_eq!((view.x, view.y), (24, 8));
+
+        // A larger area keeps the view on the screen.
+        let view = viewport.view(SCREEN, (100, 30));
+        assert_eq!((view.x, view.y, view.width), (14, 2, 50));
+
+        viewport.cycle(SCREEN, area);
+        assert_eq!(viewport.zoom(), None);
+        assert_eq!(viewport.view(SCREEN, area).x, 0);
+    }
+
+    #[test]
+    fn cells() {
+        for zoom in Zoom::ALL {
+            let (cols, rows) = zoom.cells(SCREEN.0, SCREEN.1);
+            assert_eq!(zoom.pixels(cols, rows), SCREEN, "{zoom}");
+        }
+        assert_eq!(Zoom::Braille.cells(5, 5), (3, 2));
+    }
+}
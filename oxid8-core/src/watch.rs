@@ -0,0 +1,477 @@
+//! Watch expressions for debuggers.
+//!
+//! A [`Watch`] is a small expression over the machine state, evaluated
+//! against the core after every step to follow game variables that
+//! fixed register views don't show, e.g. a score kept at `ram[I]`.
+//!
+//! | Syntax                  | Value                                   |
+//! |-------------------------|-----------------------------------------|
+//! | `42`, `0x2A`            | Numbers, decimal or hex                 |
+//! | `V0` to `VF`, `I`, `PC` | Registers, as in [`golden`] traces      |
+//! | `SP`, `DT`, `ST`        | Stack depth and timers                  |
+//! | `opcode`                | The instruction at `PC`                 |
+//! | `ram[a]`                | The byte at address `a`                 |
+//! | `ram[a..b]`             | Bytes from `a` up to `b`, at most 16    |
+//! | `+ - * / % & \| ^`      | 16-bit wrapping arithmetic and bits     |
+//! | `== != < <= > >=`       | Comparisons, 1 if true and 0 if not     |
+//! | `&& \|\|`                | Both or either nonzero, 1 or 0          |
+//!
+//! Operators bind like in C, `*` tighter than `+` tighter than `<`
+//! tighter than `==` tighter than `&` tighter than `&&`, and
+//! parentheses group. Names are not case sensitive.
+//!
+//! ```
+//! use oxid8_core::{Oxid8, watch::{Value, Watch}};
+//!
+//! let mut emu = Oxid8::new();
+//! // LD V3, 2; LD V4, 3; LD I, 200
+//! emu.load_rom_bytes(&[0x63, 0x02, 0x64, 0x03, 0xA2, 0x00]).unwrap();
+//! for _ in 0..3 {
+//!     emu.run_cycle().unwrap();
+//! }
+//!
+//! let watch: Watch = "V3 + V4 * 2".parse().unwrap();
+//! assert_eq!(watch.eval(&emu), Ok(Value::Number(8)));
+//! let watch: Watch = "ram[I..I + 2]".parse().unwrap();
+//! assert_eq!(watch.eval(&emu), Ok(Value::Bytes(vec![0x63, 0x02])));
+//! let watch: Watch = "V3 >= 2 && V4 != 0".parse().unwrap();
+//! assert_eq!(watch.is_true(&emu), Ok(true));
+//! ```
+//!
+//! [`golden`]: crate::golden
+
+use std::{fmt, str::FromStr};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::{Oxid8, golden::Field, layout::RAM_SIZE};
+
+/// Longest `ram[a..b]` range, so a watch fits on a line.
+pub const MAX_BYTES: usize = 16;
+
+/// A parsed watch expression.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Watch {
+    source: String,
+    expr: Expr,
+}
+
+/// The value of a [`Watch`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Value {
+    Number(u16),
+    /// A `ram[a..b]` range.
+    Bytes(Vec<u8>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Expr {
+    Number(u16),
+    Field(Field),
+    Ram(Box<Expr>),
+    RamRange(Box<Expr>, Box<Expr>),
+    Binary(Op, Box<Expr>, Box<Expr>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+    And,
+    Or,
+    Xor,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    LogicalAnd,
+    LogicalOr,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Number(u16),
+    Name(String),
+    Op(Op),
+    Open,
+    Close,
+    OpenBracket,
+    CloseBracket,
+    Range,
+}
+
+impl Op {
+    fn precedence(self) -> u8 {
+        match self {
+            Op::LogicalOr => 1,
+            Op::LogicalAnd => 2,
+            Op::Or => 3,
+            Op::Xor => 4,
+            Op::And => 5,
+            Op::Eq | Op::Ne => 6,
+            Op::Lt | Op::Le | Op::Gt | Op::Ge => 7,
+            Op::Add | Op::Sub => 8,
+            Op::Mul | Op::Div | Op::Rem => 9,
+        }
+    }
+
+    fn apply(self, a: u16, b: u16) -> Result<u16, String> {
+        Ok(match self {
+            Op::Add => a.wrapping_add(b),
+            Op::Sub => a.wrapping_sub(b),
+            Op::Mul => a.wrapping_mul(b),
+            Op::Div => a.checked_div(b).ok_or("Division by zero")?,
+            Op::Rem => a.checked_rem(b).ok_or("Division by zero")?,
+            Op::And => a & b,
+            Op::Or => a | b,
+            Op::Xor => a ^ b,
+            Op::Eq => u16::from(a == b),
+            Op::Ne => u16::from(a != b),
+            Op::Lt => u16::from(a < b),
+            Op::Le => u16::from(a <= b),
+            Op::Gt => u16::from(a > b),
+            Op::Ge => u16::from(a >= b),
+            Op::LogicalAnd => u16::from(a != 0 && b != 0),
+            Op::LogicalOr => u16::from(a != 0 || b != 0),
+        })
+    }
+}
+
+impl Watch {
+    /// The expression as it was typed, trimmed.
+    #[must_use]
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// Evaluates the expression against `core`.
+    ///
+    /// # Errors
+    ///
+    /// If it divides by zero, reads past the end of RAM, or uses a
+    /// `ram[a..b]` range as a number.
+    pub fn eval(&self, core: &Oxid8) -> Result<Value, String> {
+        match &self.expr {
+            Expr::RamRange(start, end) => {
+                let (start, end) = (number(start, core)?, number(end, core)?);
+                let len = end.saturating_sub(start) as usize;
+                if end <= start || len > MAX_BYTES {
+                    return Err(format!("ram[{start:#X}..{end:#X}] is not 1 to 16 bytes"));
+                }
+                if end as usize > RAM_SIZE {
+                    return Err(format!("ram[{end:#X}] is past the end of RAM"));
+                }
+                Ok(Value::Bytes(
+                    core.ram[start as usize..end as usize].to_vec(),
+                ))
+            }
+            expr => number(expr, core).map(Value::Number),
+        }
+    }
+
+    /// Evaluates the expression against `core` as a condition, true if
+    /// it isn't zero.
+    ///
+    /// # Errors
+    ///
+    /// See [`Watch::eval`], or if it is a `ram[a..b]` range.
+    pub fn is_true(&self, core: &Oxid8) -> Result<bool, String> {
+        number(&self.expr, core).map(|n| n != 0)
+    }
+}
+
+fn number(expr: &Expr, core: &Oxid8) -> Result<u16, String> {
+    match expr {
+        Expr::Number(n) => Ok(*n),
+        Expr::Field(field) => Ok(field.read(core)),
+        Expr::Ram(addr) => {
+            let addr = number(addr, core)?;
+            core.ram
+                .get(addr as usize)
+                .map(|&byte| byte as u16)
+                .ok_or_else(|| format!("ram[{addr:#X}] is past the end of RAM"))
+        }
+        Expr::RamRange(..) => Err("A ram range is not a number".into()),
+        Expr::Binary(op, a, b) => op.apply(number(a, core)?, number(b, core)?),
+    }
+}
+
+fn tokenize(s: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = s.char_indices().peekable();
+    while let Some((start, c)) = chars.next() {
+        let token = match c {
+            ' ' | '\t' => continue,
+            '(' => Token::Open,
+            ')' => Token::Close,
+            '[' => Token::OpenBracket,
+            ']' => Token::CloseBracket,
+            '+' => Token::Op(Op::Add),
+            '-' => Token::Op(Op::Sub),
+            '*' => Token::Op(Op::Mul),
+            '/' => Token::Op(Op::Div),
+            '%' => Token::Op(Op::Rem),
+            '&' if chars.next_if(|&(_, c)| c == '&').is_some() => Token::Op(Op::LogicalAnd),
+            '&' => Token::Op(Op::And),
+            '|' if chars.next_if(|&(_, c)| c == '|').is_some() => Token::Op(Op::LogicalOr),
+            '|' => Token::Op(Op::Or),
+            '^' => Token::Op(Op::Xor),
+            '=' if chars.next_if(|&(_, c)| c == '=').is_some() => Token::Op(Op::Eq),
+            '!' if chars.next_if(|&(_, c)| c == '=').is_some() => Token::Op(Op::Ne),
+            '<' if chars.next_if(|&(_, c)| c == '=').is_some() => Token::Op(Op::Le),
+            '<' => Token::Op(Op::Lt),
+            '>' if chars.next_if(|&(_, c)| c == '=').is_some() => Token::Op(Op::Ge),
+            '>' => Token::Op(Op::Gt),
+            '.' if chars.next_if(|&(_, c)| c == '.').is_some() => Token::Range,
+            c if c.is_ascii_alphanumeric() => {
+                let mut end = start + 1;
+                while let Some((i, _)) = chars.next_if(|&(_, c)| c.is_ascii_alphanumeric()) {
+                    end = i + 1;
+                }
+                let word = &s[start..end];
+                match c.is_ascii_digit() {
+                    true => Token::Number(parse_number(word)?),
+                    false => Token::Name(word.to_ascii_lowercase()),
+                }
+            }
+            c => return Err(format!("Unexpected {c:?}")),
+        };
+        tokens.push(token);
+    }
+    Ok(tokens)
+}
+
+fn parse_number(word: &str) -> Result<u16, String> {
+    let parsed = match word.strip_prefix("0x").or_else(|| word.strip_prefix("0X")) {
+        Some(hex) => u16::from_str_radix(hex, 16),
+        None => word.parse(),
+    };
+    parsed.map_err(|_| format!("Invalid number: {word}"))
+}
+
+/// Recursive descent over the tokens, by precedence climbing.
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn expect(&mut self, token: Token, what: &str) -> Result<(), String> {
+        match self.next() {
+            Some(next) if next == token => Ok(()),
+            _ => Err(format!("Expected {what}")),
+        }
+    }
+
+    fn expr(&mut self, min_precedence: u8) -> Result<Expr, String> {
+        let mut lhs = self.term()?;
+        while let Some(&Token::Op(op)) = self.peek() {
+            if op.precedence() < min_precedence {
+                break;
+            }
+            self.pos += 1;
+            let rhs = self.expr(op.precedence() + 1)?;
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(rhs));
+        }
+        Ok(lhs)
+    }
+
+    fn term(&mut self) -> Result<Expr, String> {
+        match self.next() {
+            Some(Token::Number(n)) => Ok(Expr::Number(n)),
+            Some(Token::Open) => {
+                let expr = self.expr(0)?;
+                self.expect(Token::Close, "')'")?;
+                Ok(expr)
+            }
+            Some(Token::Name(name)) if name == "ram" => {
+                self.expect(Token::OpenBracket, "'[' after ram")?;
+                let addr = self.expr(0)?;
+                let expr = match self.peek() {
+                    Some(Token::Range) => {
+                        self.pos += 1;
+                        Expr::RamRange(Box::new(addr), Box::new(self.expr(0)?))
+                    }
+                    _ => Expr::Ram(Box::new(addr)),
+                };
+                self.expect(Token::CloseBracket, "']'")?;
+                Ok(expr)
+            }
+            Some(Token::Name(name)) => Field::parse(&name)
+                .map(Expr::Field)
+                .ok_or_else(|| format!("Unknown name: {name}")),
+            _ => Err("Expected a number, register, or ram[...]".into()),
+        }
+    }
+}
+
+/// A ram range anywhere but the whole expression.
+fn nested_range(expr: &Expr) -> bool {
+    match expr {
+        Expr::Number(_) | Expr::Field(_) => false,
+        Expr::Ram(addr) => nested_range(addr) || matches!(**addr, Expr::RamRange(..)),
+        Expr::RamRange(a, b) | Expr::Binary(_, a, b) => {
+            nested_range(a)
+                || nested_range(b)
+                || matches!(**a, Expr::RamRange(..))
+                || matches!(**b, Expr::RamRange(..))
+        }
+    }
+}
+
+impl FromStr for Watch {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let source = s.trim();
+        let mut parser = Parser {
+            tokens: tokenize(source)?,
+            pos: 0,
+        };
+        let expr = parser.expr(0)?;
+        if parser.peek().is_some() {
+            return Err("Expected an operator".into());
+        }
+        if nested_range(&expr) {
+            return Err("A ram range is not a number".into());
+        }
+        Ok(Watch {
+            source: source.to_string(),
+            expr,
+        })
+    }
+}
+
+/// Formatted as typed, e.g. "V3 + V4".
+impl fmt::Display for Watch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+/// Serialized as typed.
+#[cfg(feature = "serde")]
+impl Serialize for Watch {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.source)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for Watch {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+/// Formatted like "42 (0x2A)", or hex bytes like "01 A0 FF".
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value::Number(n) => write!(f, "{n} ({n:#X})"),
+            Value::Bytes(bytes) => {
+                for (i, byte) in bytes.iter().enumerate() {
+                    let sep = if i == 0 { "" } else { " " };
+                    write!(f, "{sep}{byte:02X}")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn eval(source: &str, core: &Oxid8) -> Result<Value, String> {
+        source.parse::<Watch>()?.eval(core)
+    }
+
+    #[test]
+    fn precedence_and_registers() {
+        let mut emu = Oxid8::new();
+        emu.v_reg[3] = 5;
+        emu.v_reg[0xA] = 0xF0;
+        assert_eq!(eval("v3 + V3 * 2", &emu), Ok(Value::Number(15)));
+        assert_eq!(eval("(V3 + V3) * 2", &emu), Ok(Value::Number(20)));
+        assert_eq!(eval("VA & 0x3C | 1", &emu), Ok(Value::Number(0x31)));
+        assert_eq!(eval("0 - 1", &emu), Ok(Value::Number(u16::MAX)));
+        assert_eq!(eval("PC", &emu), Ok(Value::Number(0x200)));
+        assert_eq!(eval("V3 / 0", &emu), Err("Division by zero".into()));
+    }
+
+    #[test]
+    fn ram() {
+        let mut emu = Oxid8::new();
+        emu.load_rom_bytes(&[1, 2, 3, 4]).unwrap();
+        emu.i_reg = crate::layout::Addr::new(0x201);
+        assert_eq!(eval("ram[I]", &emu), Ok(Value::Number(2)));
+        assert_eq!(eval("ram[I] + ram[0x200]", &emu), Ok(Value::Number(3)));
+        let bytes = eval("ram[0x200..0x204]", &emu).unwrap();
+        assert_eq!(bytes, Value::Bytes(vec![1, 2, 3, 4]));
+        assert_eq!(bytes.to_string(), "01 02 03 04");
+        assert!(eval("ram[0x1000]", &emu).is_err());
+        assert!(eval("ram[0x200..0x300]", &emu).is_err());
+        assert!(eval("ram[0x204..0x200]", &emu).is_err());
+    }
+
+    #[test]
+    fn conditions() {
+        let mut emu = Oxid8::new();
+        emu.v_reg[3] = 5;
+        assert_eq!(eval("V3 >= 5", &emu), Ok(Value::Number(1)));
+        assert_eq!(eval("V3 > 5", &emu), Ok(Value::Number(0)));
+        assert_eq!(eval("V3 < 2 + 4", &emu), Ok(Value::Number(1)));
+        assert_eq!(eval("V3 <= 4 || V3 == 5", &emu), Ok(Value::Number(1)));
+        assert_eq!(eval("V3 != 5 && 1", &emu), Ok(Value::Number(0)));
+        // Like in C, & binds looser than ==.
+        assert_eq!(eval("V3 & 4 == 4", &emu), Ok(Value::Number(1)));
+        assert_eq!(eval("V3 & 1 | 2", &emu), Ok(Value::Number(3)));
+
+        let watch: Watch = "V3 == 5".parse().unwrap();
+        assert_eq!(watch.is_true(&emu), Ok(true));
+        let watch: Watch = "ram[0..2]".parse().unwrap();
+        assert!(watch.is_true(&emu).is_err());
+    }
+
+    #[test]
+    fn parse_errors() {
+        for source in [
+            "",
+            "V3 +",
+            "V3 V4",
+            "foo",
+            "ram[1",
+            "(1",
+            "ram[1..2] + 1",
+            "1 $ 2",
+            "V3 = 1",
+            "V3 ! 1",
+            "V3 >",
+        ] {
+            assert!(source.parse::<Watch>().is_err(), "{source}");
+        }
+        let watch: Watch = "  V3 + V4 ".parse().unwrap();
+        assert_eq!(watch.to_string(), "V3 + V4");
+        assert_eq!(Value::Number(42).to_string(), "42 (0x2A)");
+    }
+}
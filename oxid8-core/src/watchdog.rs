@@ -0,0 +1,202 @@
+//! Noticing programs stuck waiting on the delay timer.
+//!
+//! Games wait by spinning on the delay timer:
+//!
+//! ```text
+//! 2A4  LD V0, DT
+//! 2A6  SE V0, 0
+//! 2A8  JP 2A4
+//! ```
+//!
+//! While the frontend is paused and instructions are stepped, or if
+//! its timers aren't ticking, DT never changes and the game looks
+//! frozen. [`Oxid8::timer_wait`] recognizes the loop around the program
+//! counter so debuggers can say what the program is waiting for, and a
+//! [`Watchdog`] reports when a program has spun in one for a second's
+//! worth of instructions without DT changing.
+//!
+//! ```
+//! use oxid8_core::{Oxid8, watchdog::Watchdog};
+//!
+//! let mut emu = Oxid8::new();
+//! // LD V0, 3; LD DT, V0; LD V0, DT; SE V0, 0; JP 204
+//! emu.load_rom_bytes(&[0x60, 0x03, 0xF0, 0x15, 0xF0, 0x07, 0x30, 0x00, 0x12, 0x04])
+//!     .unwrap();
+//! let mut watchdog = Watchdog::default();
+//! // Running instructions without ticking the timers.
+//! let mut stalled = None;
+//! for _ in 0..1000 {
+//!     emu.run_cycle().unwrap();
+//!     stalled = stalled.or(watchdog.check(&emu, 1));
+//! }
+//! let wait = stalled.unwrap();
+//! assert_eq!(wait.pc, 0x204);
+//! assert_eq!(wait.to_string(), "Program is waiting on delay timer (DT 03)");
+//! ```
+
+use std::fmt;
+
+use crate::{Oxid8, layout::RAM_SIZE};
+
+/// Instructions spun in a wait with DT unchanged before a [`Watchdog`]
+/// reports it, a second at the default speed.
+pub const STALL_CYCLES: u32 = 600;
+
+/// A loop waiting for the delay timer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimerWait {
+    /// Address of the `Fx07` reading the timer.
+    pub pc: u16,
+    /// Register the timer is read into.
+    pub register: u8,
+    /// The delay timer.
+    pub dt: u8,
+}
+
+/// Formatted like "Program is waiting on delay timer (DT 1E)".
+impl fmt::Display for TimerWait {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Program is waiting on delay timer (DT {:02X})", self.dt)
+    }
+}
+
+impl Oxid8 {
+    /// The wait for the delay timer the program counter is in, if any,
+    /// see the [module docs](self).
+    ///
+    /// A loop of `Fx07`, then `3xkk` or `4xkk` on the same register,
+    /// then a jump back to the `Fx07` counts, as long as the timer
+    /// keeps it looping.
+    #[must_use]
+    pub fn timer_wait(&self) -> Option<TimerWait> {
+        let pc = self.pc();
+        (0..3)
+            .filter_map(|i| pc.checked_sub(i * 2))
+            .find_map(|start| self.timer_wait_at(start))
+    }
+
+    /// The wait starting at `start`, if there is one.
+    fn timer_wait_at(&self, start: u16) -> Option<TimerWait> {
+        let opcode = |offset: u16| {
+            let addr = (start + offset) as usize;
+            (addr + 1 < RAM_SIZE).then(|| u16::from_be_bytes([self.ram[addr], self.ram[addr + 1]]))
+        };
+        let (read, skip, jump) = (opcode(0)?, opcode(2)?, opcode(4)?);
+        let x = (read >> 8 & 0xF) as u8;
+        if read & 0xF0FF != 0xF007 || jump != 0x1000 | start || (skip >> 8 & 0xF) as u8 != x {
+            return None;
+        }
+        let kk = skip as u8;
+        // SE skips the jump once DT reaches kk, SNE once it leaves it.
+        let looping = match skip >> 12 {
+            0x3 => self.dt != kk,
+            0x4 => self.dt == kk && self.dt > 0,
+            _ => false,
+        };
+        looping.then_some(TimerWait {
+            pc: start,
+            register: x,
+            dt: self.dt,
+        })
+    }
+}
+
+/// Counts instructions spent in a [`TimerWait`] while DT stays the
+/// same.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Watchdog {
+    /// The wait last seen and instructions spent in it.
+    waiting: Option<(TimerWait, u32)>,
+    /// Whether the current wait was reported.
+    reported: bool,
+}
+
+impl Watchdog {
+    /// Adds `cycles` instructions run by `core` since the last check.
+    /// Returns the wait once, when the program has spun in it for
+    /// [`STALL_CYCLES`] without DT changing.
+    pub fn check(&mut self, core: &Oxid8, cycles: u32) -> Option<TimerWait> {
+        let Some(wait) = core.timer_wait() else {
+            *self = Watchdog::default();
+            return None;
+        };
+        let spun = match self.waiting {
+            Some((last, spun)) if last == wait => spun.saturating_add(cycles),
+            _ => {
+                self.reported = false;
+                0
+            }
+        };
+        self.waiting = Some((wait, spun));
+        if spun >= STALL_CYCLES && !self.reported {
+            self.reported = true;
+            return Some(wait);
+        }
+        None
+    }
+
+    /// The wait the program has spun in for [`STALL_CYCLES`] without DT
+    /// changing, as of the last check.
+    #[must_use]
+    pub fn stalled(&self) -> Option<TimerWait> {
+        self.waiting
+            .filter(|&(_, spun)| spun >= STALL_CYCLES)
+            .map(|(wait, _)| wait)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recognizes_waits() {
+        let mut emu = Oxid8::new();
+        // LD V3, DT; SNE V3, 2; JP 200
+        emu.load_rom_bytes(&[0xF3, 0x07, 0x43, 0x02, 0x12, 0x00])
+            .unwrap();
+        // Not looping with DT off 2.
+        assert_eq!(emu.timer_wait(), None);
+        emu.dt = 2;
+        for pc in [0x200, 0x202, 0x204] {
+            emu.pc = crate::Addr::new(pc);
+            let wait = emu.timer_wait().unwrap();
+            assert_eq!((wait.pc, wait.register), (0x200, 3));
+        }
+
+        // A jump elsewhere isn't a wait.
+        let mut emu = Oxid8::new();
+        // LD V0, DT; SE V0, 0; JP 202
+        emu.load_rom_bytes(&[0xF0, 0x07, 0x30, 0x00, 0x12, 0x02])
+            .unwrap();
+        emu.dt = 5;
+        assert_eq!(emu.timer_wait(), None);
+    }
+
+    #[test]
+    fn watchdog_needs_a_stall() {
+        let mut emu = Oxid8::new();
+        // LD V0, DT; SE V0, 0; JP 200
+        emu.load_rom_bytes(&[0xF0, 0x07, 0x30, 0x00, 0x12, 0x00])
+            .unwrap();
+        emu.dt = 120;
+        let mut watchdog = Watchdog::default();
+        // Ticking timers keep it quiet.
+        for _ in 0..100 {
+            emu.next_frame().unwrap();
+            assert_eq!(watchdog.check(&emu, emu.cycles_per_frame()), None);
+        }
+        // Reported once when they stop.
+        let reports = (0..STALL_CYCLES * 2)
+            .filter_map(|_| {
+                emu.run_cycle().unwrap();
+                watchdog.check(&emu, 1)
+            })
+            .count();
+        assert_eq!(reports, 1);
+        assert!(watchdog.stalled().is_some());
+        emu.next_frame().unwrap();
+        watchdog.check(&emu, emu.cycles_per_frame());
+        assert_eq!(watchdog.stalled(), None);
+    }
+}
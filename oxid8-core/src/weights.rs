@@ -0,0 +1,253 @@
+//! Weighting instructions against the speed budget.
+//!
+//! By default every instruction costs one of the `cycles_per_frame` a
+//! frame runs, however much work it does. On real hardware drawing a
+//! sprite or storing registers took many times longer than a jump,
+//! which games relied on to pace themselves. [`CycleWeights`] make some
+//! instructions cost more, e.g. `Dxyn` four cycles, so games that draw
+//! a lot slow down like they did without slowing down everything else.
+//!
+//! Instructions are named by their pattern in [`disasm::OPCODES`], in
+//! any case. An instruction that starts within a frame's budget
+//! finishes, what it runs over isn't carried to the next frame, so
+//! frames only depend on the machine state and the settings.
+//!
+//! ```
+//! use oxid8_core::{Oxid8, weights::CycleWeights};
+//!
+//! let mut emu = Oxid8::new();
+//! // DRW V0, V0, 1; JP 200
+//! emu.load_rom_bytes(&[0xD0, 0x01, 0x12, 0x00]).unwrap();
+//! let weights: CycleWeights = "dxyn=5".parse().unwrap();
+//! emu.set_cycle_weights(weights);
+//!
+//! // 10 cycles: DRW, JP, then the DRW from 6 runs over.
+//! emu.next_frame().unwrap();
+//! assert_eq!(emu.pc(), 0x202);
+//! assert_eq!(emu.cycle_weights().to_string(), "Dxyn=5");
+//! ```
+//!
+//! [`disasm::OPCODES`]: crate::disasm::OPCODES
+
+use std::{fmt, str::FromStr};
+
+#[cfg(feature = "serde")]
+use std::collections::BTreeMap;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    Oxid8,
+    disasm::{OPCODES, OpcodeInfo},
+};
+
+/// Heaviest weight an instruction can have.
+pub const MAX_WEIGHT: u8 = 100;
+
+/// Cycles instructions cost, 1 unless set.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(try_from = "BTreeMap<String, u8>", into = "BTreeMap<String, u8>")
+)]
+pub struct CycleWeights {
+    /// Instructions that don't cost 1, in the order of `OPCODES`.
+    weights: Vec<(&'static OpcodeInfo, u8)>,
+}
+
+impl CycleWeights {
+    /// Returns true if every instruction costs 1.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.weights.is_empty()
+    }
+
+    /// Makes the instruction with `pattern`, like "Dxyn", cost `weight`
+    /// cycles.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no instruction has `pattern`, or `weight` is
+    /// 0 or over [`MAX_WEIGHT`].
+    pub fn set(&mut self, pattern: &str, weight: u8) -> Result<(), String> {
+        let info = OPCODES
+            .iter()
+            .find(|info| info.pattern.eq_ignore_ascii_case(pattern))
+            .ok_or_else(|| format!("Unknown instruction: {pattern}"))?;
+        if !(1..=MAX_WEIGHT).contains(&weight) {
+            return Err(format!("Weights are 1 to {MAX_WEIGHT}: {weight}"));
+        }
+        self.weights
+            .retain(|(other, _)| other.pattern != info.pattern);
+        if weight > 1 {
+            self.weights.push((info, weight));
+            self.weights.sort_by_key(|(info, _)| {
+                OPCODES
+                    .iter()
+                    .position(|other| other.pattern == info.pattern)
+            });
+        }
+        Ok(())
+    }
+
+    /// Cycles `opcode` costs.
+    #[must_use]
+    pub fn weight(&self, opcode: u16) -> u32 {
+        self.weights
+            .iter()
+            .find(|(info, _)| info.matches(opcode))
+            .map_or(1, |&(_, weight)| weight as u32)
+    }
+
+    /// The instructions that don't cost 1, by pattern.
+    pub fn iter(&self) -> impl Iterator<Item = (&'static str, u8)> + '_ {
+        self.weights
+            .iter()
+            .map(|&(info, weight)| (info.pattern, weight))
+    }
+}
+
+/// Formatted like "Dxyn=4 Fx55=2".
+impl fmt::Display for CycleWeights {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, (pattern, weight)) in self.iter().enumerate() {
+            if i > 0 {
+                f.write_str(" ")?;
+            }
+            write!(f, "{pattern}={weight}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Parses weights like "dxyn=4 fx55=2", separated by spaces or commas.
+impl FromStr for CycleWeights {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut weights = CycleWeights::default();
+        for item in s.split([' ', ',']).filter(|item| !item.is_empty()) {
+            let (pattern, weight) = item
+                .split_once('=')
+                .ok_or_else(|| format!("Expected <instruction>=<cycles>: {item}"))?;
+            let weight = weight
+                .parse()
+                .map_err(|_| format!("Invalid weight: {weight}"))?;
+            weights.set(pattern, weight)?;
+        }
+        Ok(weights)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<BTreeMap<String, u8>> for CycleWeights {
+    type Error = String;
+
+    fn try_from(map: BTreeMap<String, u8>) -> Result<Self, Self::Error> {
+        let mut weights = CycleWeights::default();
+        for (pattern, weight) in map {
+            weights.set(&pattern, weight)?;
+        }
+        Ok(weights)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl From<CycleWeights> for BTreeMap<String, u8> {
+    fn from(weights: CycleWeights) -> Self {
+        weights
+            .iter()
+            .map(|(pattern, weight)| (pattern.to_string(), weight))
+            .collect()
+    }
+}
+
+impl Oxid8 {
+    /// Cycles each instruction costs, see the [module docs](self).
+    #[must_use]
+    pub fn cycle_weights(&self) -> &CycleWeights {
+        &self.cycle_weights
+    }
+
+    /// Sets the cycles each instruction costs. Like quirks, weights are
+    /// a setting and survive `reset`.
+    pub fn set_cycle_weights(&mut self, weights: CycleWeights) {
+        self.cycle_weights = weights;
+    }
+
+    /// Cycles the next instruction costs.
+    pub(crate) fn next_weight(&self) -> u32 {
+        if self.cycle_weights.is_empty() {
+            return 1;
+        }
+        let byte = |offset| self.ram.get(self.pc.index() + offset).copied();
+        match (byte(0), byte(1)) {
+            (Some(hi), Some(lo)) => self.cycle_weights.weight(u16::from_be_bytes([hi, lo])),
+            _ => 1,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        let weights: CycleWeights = "fx55=2, DXYN=4 00e0=3".parse().unwrap();
+        assert_eq!(weights.to_string(), "00E0=3 Dxyn=4 Fx55=2");
+        assert_eq!(weights.weight(0xD125), 4);
+        assert_eq!(weights.weight(0xF355), 2);
+        assert_eq!(weights.weight(0x00E0), 3);
+        assert_eq!(weights.weight(0x1200), 1);
+        assert_eq!("".parse(), Ok(CycleWeights::default()));
+
+        // Setting 1 goes back to the default.
+        let weights: CycleWeights = "dxyn=4 dxyn=1".parse().unwrap();
+        assert!(weights.is_empty());
+
+        assert!("dxyn".parse::<CycleWeights>().is_err());
+        assert!("dxyn=0".parse::<CycleWeights>().is_err());
+        assert!("dxyn=101".parse::<CycleWeights>().is_err());
+        assert!("8xy8=2".parse::<CycleWeights>().is_err());
+    }
+
+    #[test]
+    fn frames_run_fewer_heavy_instructions() {
+        // ADD V0, 1; DRW V1, V1, 0; JP 200
+        let rom = [0x70, 0x01, 0xD1, 0x10, 0x12, 0x00];
+        let mut flat = Oxid8::new();
+        flat.load_rom_bytes(&rom).unwrap();
+        let mut weighted = Oxid8::new();
+        weighted.load_rom_bytes(&rom).unwrap();
+        weighted.set_cycle_weights("dxyn=8".parse().unwrap());
+        for _ in 0..6 {
+            flat.next_frame().unwrap();
+            weighted.next_frame().unwrap();
+        }
+        // 60 instructions, or a loop a frame at 1 + 8 + 1 cycles.
+        assert_eq!(flat.save_state().v_reg[0], 20);
+        assert_eq!(weighted.save_state().v_reg[0], 6);
+
+        weighted.reset();
+        assert_eq!(weighted.cycle_weights().to_string(), "Dxyn=8");
+    }
+
+    #[cfg(feature = "config")]
+    #[test]
+    fn toml() {
+        use std::collections::BTreeMap;
+
+        #[derive(Debug, PartialEq, Deserialize, Serialize)]
+        struct Config {
+            cycle_weights: CycleWeights,
+        }
+        let config: Config = toml::from_str("[cycle_weights]\ndxyn = 4\nFx55 = 2\n").unwrap();
+        assert_eq!(config.cycle_weights.to_string(), "Dxyn=4 Fx55=2");
+        let map: BTreeMap<String, u8> = config.cycle_weights.into();
+        assert_eq!(map["Dxyn"], 4);
+        assert!(toml::from_str::<Config>("[cycle_weights]\nfoo = 2\n").is_err());
+    }
+}
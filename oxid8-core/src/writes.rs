@@ -0,0 +1,108 @@
+//! RAM written by the program.
+//!
+//! `FX33` and `FX55` store into RAM, which is how self-modifying roms
+//! rewrite their own code and how games keep data. [`RamWrites`] is
+//! the set of addresses written since the last
+//! [`Oxid8::take_ram_writes`], so debuggers can mark bytes that just
+//! changed, e.g. instructions in a disassembly that were rewritten.
+//!
+//! ```
+//! use oxid8_core::Oxid8;
+//!
+//! let mut emu = Oxid8::new();
+//! // LD I, 300; LD B, V0 (hundreds, tens, ones at I)
+//! emu.load_rom_bytes(&[0xA3, 0x00, 0xF0, 0x33]).unwrap();
+//! emu.run_cycle().unwrap();
+//! assert!(emu.take_ram_writes().is_empty());
+//! emu.run_cycle().unwrap();
+//!
+//! let writes = emu.take_ram_writes();
+//! assert!(writes.contains(0x301));
+//! assert_eq!(writes.iter().collect::<Vec<_>>(), [0x300, 0x301, 0x302]);
+//! assert!(emu.take_ram_writes().is_empty());
+//! ```
+
+use std::{mem, ops::Range};
+
+use crate::{Oxid8, layout::RAM_SIZE};
+
+const WORDS: usize = RAM_SIZE / 64;
+
+/// Addresses written since the last [`Oxid8::take_ram_writes`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RamWrites {
+    bits: [u64; WORDS],
+}
+
+impl Default for RamWrites {
+    fn default() -> Self {
+        Self { bits: [0; WORDS] }
+    }
+}
+
+impl RamWrites {
+    /// Returns true if nothing was written.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.bits.iter().all(|&word| word == 0)
+    }
+
+    /// Returns true if `addr` was written.
+    #[must_use]
+    pub fn contains(&self, addr: u16) -> bool {
+        let addr = addr as usize;
+        addr < RAM_SIZE && self.bits[addr / 64] & (1 << (addr % 64)) != 0
+    }
+
+    /// The addresses written, in order.
+    pub fn iter(&self) -> impl Iterator<Item = u16> + '_ {
+        (0..RAM_SIZE as u16).filter(|&addr| self.contains(addr))
+    }
+
+    /// Adds the addresses written in `other`.
+    pub fn merge(&mut self, other: &RamWrites) {
+        for (word, other) in self.bits.iter_mut().zip(other.bits) {
+            *word |= other;
+        }
+    }
+
+    /// Records writes to the addresses in `range`.
+    pub(crate) fn mark(&mut self, range: Range<usize>) {
+        for addr in range {
+            self.bits[addr / 64] |= 1 << (addr % 64);
+        }
+    }
+}
+
+impl Oxid8 {
+    /// Returns the addresses written since the last call and starts
+    /// recording again.
+    pub fn take_ram_writes(&mut self) -> RamWrites {
+        mem::take(&mut self.ram_writes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn store_registers() {
+        let mut emu = Oxid8::new();
+        // LD I, FFC; LD [I], V3
+        emu.load_rom_bytes(&[0xAF, 0xFC, 0xF3, 0x55]).unwrap();
+        emu.run_cycle().unwrap();
+        emu.run_cycle().unwrap();
+        let writes = emu.take_ram_writes();
+        assert_eq!(
+            writes.iter().collect::<Vec<_>>(),
+            [0xFFC, 0xFFD, 0xFFE, 0xFFF]
+        );
+        assert!(!writes.contains(0x1000));
+
+        let mut merged = RamWrites::default();
+        merged.mark(0x200..0x202);
+        merged.merge(&writes);
+        assert_eq!(merged.iter().count(), 6);
+    }
+}
@@ -0,0 +1,130 @@
+//! Steady-state emulation must not allocate, see "Allocations" in the
+//! crate docs.
+//!
+//! Its own test binary since it replaces the global allocator.
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    cell::Cell,
+};
+
+use oxid8_core::{Oxid8, input::InputEvent};
+
+/// Counts allocations made by the current thread, so tests running in
+/// parallel don't interfere.
+struct Counting;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+fn count() {
+    let _ = ALLOCATIONS.try_with(|n| n.set(n.get() + 1));
+}
+
+fn allocations() -> usize {
+    ALLOCATIONS.with(Cell::get)
+}
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        count();
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        count();
+        unsafe { System.alloc_zeroed(layout) }
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        count();
+        unsafe { System.realloc(ptr, layout, new_size) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+/// Draws, calls, reads keys, and uses the timers and memory in a loop.
+#[rustfmt::skip]
+const ROM: [u8; 42] = [
+    0x00, 0xE0, // 200: CLS
+    0xC0, 0x3F, // 202: RND V0, 3F
+    0xC1, 0x1F, // 204: RND V1, 1F
+    0xA0, 0x50, // 206: LD I, 050
+    0xD0, 0x15, // 208: DRW V0, V1, 5
+    0x22, 0x20, // 20A: CALL 220
+    0xE2, 0x9E, // 20C: SKP V2
+    0x72, 0x01, // 20E: ADD V2, 1
+    0x82, 0x06, // 210: SHR V2
+    0xF2, 0x15, // 212: LD DT, V2
+    0xF3, 0x18, // 214: LD ST, V3
+    0xF4, 0x07, // 216: LD V4, DT
+    0x12, 0x00, // 218: JP 200
+    0x00, 0x00, // 21A
+    0x00, 0x00, // 21C
+    0x00, 0x00, // 21E
+    0xA3, 0x00, // 220: LD I, 300
+    0xF0, 0x33, // 222: LD B, V0
+    0xF2, 0x65, // 224: LD V2, [I]
+    0xF1, 0x55, // 226: LD [I], V1
+    0x00, 0xEE, // 228: RET
+];
+
+fn core() -> Oxid8 {
+    let mut core = Oxid8::new();
+    core.load_font();
+    core.load_rom_bytes(&ROM).unwrap();
+    core
+}
+
+#[test]
+fn next_frame() {
+    let mut core = core();
+    core.set_cycles_per_frame(37);
+
+    let before = allocations();
+    for frame in 0..600 {
+        core.set_key(frame % 16, frame % 3 == 0);
+        core.next_frame().unwrap();
+        let _ = core.pack_screen();
+        let _ = core.sound();
+        core.clear_keys();
+    }
+    assert_eq!(allocations() - before, 0);
+}
+
+#[test]
+fn next_frame_with_input() {
+    let mut core = core();
+    let events = [
+        InputEvent {
+            cycle: 2,
+            key: 0,
+            pressed: true,
+        },
+        InputEvent {
+            cycle: 7,
+            key: 0,
+            pressed: false,
+        },
+    ];
+
+    let before = allocations();
+    for _ in 0..600 {
+        core.next_frame_with_input(&events).unwrap();
+    }
+    assert_eq!(allocations() - before, 0);
+}
+
+#[test]
+fn counts() {
+    let before = allocations();
+    drop(std::hint::black_box(vec![0u8; 16]));
+    assert_eq!(allocations() - before, 1);
+}
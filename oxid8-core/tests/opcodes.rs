@@ -0,0 +1,1090 @@
+//! One module per opcode, run through the public API: programs are
+//! loaded as ROMs, registers are set up and inspected through
+//! savestates.
+//!
+//! Quirk-sensitive behaviour is tested for the quirks of every
+//! [`Variant`] preset in `quirks`, which names every quirk so adding
+//! one doesn't compile until it has tests.
+
+use oxid8_core::{
+    Oxid8, SCREEN_HEIGHT, SCREEN_WIDTH,
+    quirks::{KeyWait, Quirks, RngMode, Shift},
+    rng::Rng,
+    savestate::SaveState,
+    variant::Variant,
+};
+
+const START: u16 = 0x200;
+const VF: usize = 0xF;
+
+/// Loads `program` at 0x200 with `quirks` and lets `setup` edit the
+/// state before the first instruction.
+fn machine_with(quirks: Quirks, program: &[u16], setup: impl FnOnce(&mut SaveState)) -> Oxid8 {
+    let rom: Vec<u8> = program.iter().flat_map(|op| op.to_be_bytes()).collect();
+    let mut emu = Oxid8::new();
+    emu.set_quirks(quirks);
+    emu.load_font();
+    emu.load_rom_bytes(&rom).unwrap();
+    let mut state = emu.save_state();
+    setup(&mut state);
+    emu.restore_state(&state);
+    emu
+}
+
+fn machine(program: &[u16], setup: impl FnOnce(&mut SaveState)) -> Oxid8 {
+    machine_with(Quirks::default(), program, setup)
+}
+
+/// Runs `cycles` instructions and returns the state after them.
+fn run(emu: &mut Oxid8, cycles: usize) -> SaveState {
+    for _ in 0..cycles {
+        emu.run_cycle().unwrap();
+    }
+    emu.save_state()
+}
+
+/// Runs a single instruction after `setup`.
+fn exec(opcode: u16, setup: impl FnOnce(&mut SaveState)) -> SaveState {
+    run(&mut machine(&[opcode], setup), 1)
+}
+
+fn pixel(state: &SaveState, x: usize, y: usize) -> bool {
+    state.screen[y * SCREEN_WIDTH + x]
+}
+
+/// Sets V0 and V1 for the `8xyn` arithmetic tests.
+fn v0_v1(v0: u8, v1: u8) -> impl FnOnce(&mut SaveState) {
+    move |s| {
+        s.v_reg[0] = v0;
+        s.v_reg[1] = v1;
+    }
+}
+
+mod cls_00e0 {
+    use super::*;
+
+    #[test]
+    fn clears_screen() {
+        let state = exec(0x00E0, |s| s.screen = [true; SCREEN_WIDTH * SCREEN_HEIGHT]);
+        assert!(!state.screen.contains(&true));
+        assert_eq!(state.pc, START + 2);
+    }
+
+    #[test]
+    fn keeps_vf() {
+        let state = exec(0x00E0, |s| s.v_reg[VF] = 1);
+        assert_eq!(state.v_reg[VF], 1);
+    }
+}
+
+mod ret_00ee {
+    use super::*;
+
+    #[test]
+    fn returns_after_call() {
+        // CALL 206; (skipped); (skipped); RET
+        let mut emu = machine(&[0x2206, 0x0000, 0x0000, 0x00EE], |_| {});
+        let state = run(&mut emu, 2);
+        assert_eq!(state.pc, START + 2);
+        assert_eq!(state.sp, 0);
+    }
+
+    #[test]
+    fn nested() {
+        // CALL 204; (skipped); CALL 208; RET; RET
+        let mut emu = machine(&[0x2204, 0x0000, 0x2208, 0x00EE, 0x00EE], |_| {});
+        assert_eq!(run(&mut emu, 2).sp, 2);
+        assert_eq!(run(&mut emu, 1).pc, START + 6);
+        let state = run(&mut emu, 1);
+        assert_eq!(state.pc, START + 2);
+        assert_eq!(state.sp, 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "Stack Underflow")]
+    fn underflow() {
+        exec(0x00EE, |_| {});
+    }
+}
+
+mod jp_1nnn {
+    use super::*;
+
+    #[test]
+    fn jumps() {
+        assert_eq!(exec(0x1ABC, |_| {}).pc, 0xABC);
+    }
+
+    #[test]
+    fn jump_to_self() {
+        let mut emu = machine(&[0x1200], |_| {});
+        assert_eq!(run(&mut emu, 10).pc, START);
+    }
+}
+
+mod call_2nnn {
+    use super::*;
+
+    #[test]
+    fn pushes_return_address() {
+        let state = exec(0x2300, |_| {});
+        assert_eq!(state.pc, 0x300);
+        assert_eq!(state.sp, 1);
+        assert_eq!(state.stack[0], START + 2);
+    }
+
+    #[test]
+    fn sixteen_levels() {
+        let state = run(&mut machine(&[0x2200], |_| {}), 16);
+        assert_eq!(state.sp, 16);
+        assert_eq!(state.stack, [START + 2; 16]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Stack Overflow")]
+    fn overflow() {
+        run(&mut machine(&[0x2200], |_| {}), 17);
+    }
+}
+
+/// Skips run one instruction and land past the next one, which must
+/// not execute.
+fn skips(opcode: u16, setup: impl FnOnce(&mut SaveState)) -> bool {
+    // op; ADD VE, 1
+    let mut emu = machine(&[opcode, 0x7E01], setup);
+    let state = run(&mut emu, 1);
+    let skipped = state.pc == START + 4;
+    if skipped {
+        assert_eq!(state.v_reg[0xE], 0, "skipped instruction ran");
+    } else {
+        assert_eq!(state.pc, START + 2);
+        assert_eq!(run(&mut emu, 1).v_reg[0xE], 1);
+    }
+    skipped
+}
+
+mod se_3xkk {
+    use super::*;
+
+    #[test]
+    fn skips_if_equal() {
+        assert!(skips(0x3342, |s| s.v_reg[3] = 0x42));
+        assert!(!skips(0x3342, |s| s.v_reg[3] = 0x43));
+    }
+
+    #[test]
+    fn zero() {
+        assert!(skips(0x3000, |_| {}));
+    }
+
+    #[test]
+    fn vf() {
+        assert!(skips(0x3F01, |s| s.v_reg[VF] = 1));
+    }
+}
+
+mod sne_4xkk {
+    use super::*;
+
+    #[test]
+    fn skips_if_not_equal() {
+        assert!(skips(0x4342, |s| s.v_reg[3] = 0x43));
+        assert!(!skips(0x4342, |s| s.v_reg[3] = 0x42));
+    }
+
+    #[test]
+    fn keeps_vf() {
+        let state = exec(0x4F00, |s| s.v_reg[VF] = 1);
+        assert_eq!(state.v_reg[VF], 1);
+    }
+}
+
+mod se_5xy0 {
+    use super::*;
+
+    #[test]
+    fn skips_if_equal() {
+        assert!(skips(0x5120, |s| {
+            s.v_reg[1] = 7;
+            s.v_reg[2] = 7;
+        }));
+        assert!(!skips(0x5120, |s| s.v_reg[1] = 7));
+    }
+
+    #[test]
+    fn same_register() {
+        assert!(skips(0x5550, |s| s.v_reg[5] = 9));
+    }
+}
+
+mod ld_6xkk {
+    use super::*;
+
+    #[test]
+    fn loads() {
+        assert_eq!(exec(0x6AFF, |_| {}).v_reg[0xA], 0xFF);
+    }
+
+    #[test]
+    fn vf() {
+        assert_eq!(exec(0x6F2A, |_| {}).v_reg[VF], 0x2A);
+    }
+}
+
+mod add_7xkk {
+    use super::*;
+
+    #[test]
+    fn adds() {
+        assert_eq!(exec(0x7105, |s| s.v_reg[1] = 10).v_reg[1], 15);
+    }
+
+    #[test]
+    fn wraps_without_carry() {
+        let state = exec(0x7102, |s| s.v_reg[1] = 0xFF);
+        assert_eq!(state.v_reg[1], 0x01);
+        assert_eq!(state.v_reg[VF], 0);
+    }
+
+    #[test]
+    fn keeps_vf() {
+        let state = exec(0x7101, |s| s.v_reg[VF] = 1);
+        assert_eq!(state.v_reg[VF], 1);
+    }
+}
+
+mod ld_8xy0 {
+    use super::*;
+
+    #[test]
+    fn copies() {
+        let state = exec(0x8010, v0_v1(1, 2));
+        assert_eq!(state.v_reg[..2], [2, 2]);
+    }
+
+    #[test]
+    fn into_vf() {
+        assert_eq!(exec(0x8F10, v0_v1(0, 9)).v_reg[VF], 9);
+    }
+}
+
+/// The logical ops leave VF alone.
+mod logic_8xy1_8xy2_8xy3 {
+    use super::*;
+
+    #[test]
+    fn or() {
+        let state = exec(0x8011, v0_v1(0b1100, 0b1010));
+        assert_eq!(state.v_reg[0], 0b1110);
+        assert_eq!(state.v_reg[1], 0b1010);
+    }
+
+    #[test]
+    fn and() {
+        assert_eq!(exec(0x8012, v0_v1(0b1100, 0b1010)).v_reg[0], 0b1000);
+    }
+
+    #[test]
+    fn xor() {
+        assert_eq!(exec(0x8013, v0_v1(0b1100, 0b1010)).v_reg[0], 0b0110);
+        assert_eq!(exec(0x8003, v0_v1(0xFF, 0)).v_reg[0], 0);
+    }
+
+    #[test]
+    fn keep_vf() {
+        for op in [0x8011, 0x8012, 0x8013] {
+            let state = exec(op, |s| s.v_reg[VF] = 1);
+            assert_eq!(state.v_reg[VF], 1, "{op:04X}");
+        }
+    }
+}
+
+mod add_8xy4 {
+    use super::*;
+
+    #[test]
+    fn no_carry() {
+        let state = exec(0x8014, v0_v1(0xFE, 0x01));
+        assert_eq!(state.v_reg[0], 0xFF);
+        assert_eq!(state.v_reg[VF], 0);
+    }
+
+    #[test]
+    fn carry() {
+        let state = exec(0x8014, v0_v1(0xFF, 0x01));
+        assert_eq!(state.v_reg[0], 0x00);
+        assert_eq!(state.v_reg[VF], 1);
+
+        let state = exec(0x8014, v0_v1(0xFF, 0xFF));
+        assert_eq!(state.v_reg[0], 0xFE);
+        assert_eq!(state.v_reg[VF], 1);
+    }
+
+    #[test]
+    fn clears_stale_carry() {
+        let state = exec(0x8014, |s| {
+            s.v_reg[VF] = 1;
+            s.v_reg[1] = 1;
+        });
+        assert_eq!(state.v_reg[VF], 0);
+    }
+
+    #[test]
+    fn flag_wins_over_vf_result() {
+        // VF = VF + V1 then VF = carry.
+        let state = exec(0x8F14, |s| {
+            s.v_reg[VF] = 0xFF;
+            s.v_reg[1] = 2;
+        });
+        assert_eq!(state.v_reg[VF], 1);
+        let state = exec(0x8F14, |s| {
+            s.v_reg[VF] = 0x10;
+            s.v_reg[1] = 2;
+        });
+        assert_eq!(state.v_reg[VF], 0);
+    }
+
+    #[test]
+    fn doubles_same_register() {
+        let state = exec(0x8114, |s| s.v_reg[1] = 0x81);
+        assert_eq!(state.v_reg[1], 0x02);
+        assert_eq!(state.v_reg[VF], 1);
+    }
+}
+
+mod sub_8xy5 {
+    use super::*;
+
+    #[test]
+    fn no_borrow() {
+        let state = exec(0x8015, v0_v1(5, 3));
+        assert_eq!(state.v_reg[0], 2);
+        assert_eq!(state.v_reg[VF], 1);
+    }
+
+    #[test]
+    fn equal_is_no_borrow() {
+        let state = exec(0x8015, v0_v1(5, 5));
+        assert_eq!(state.v_reg[0], 0);
+        assert_eq!(state.v_reg[VF], 1);
+    }
+
+    #[test]
+    fn borrow() {
+        let state = exec(0x8015, v0_v1(3, 5));
+        assert_eq!(state.v_reg[0], 0xFE);
+        assert_eq!(state.v_reg[VF], 0);
+    }
+
+    #[test]
+    fn flag_wins_over_vf_result() {
+        let state = exec(0x8F15, |s| {
+            s.v_reg[VF] = 0x10;
+            s.v_reg[1] = 1;
+        });
+        assert_eq!(state.v_reg[VF], 1);
+    }
+}
+
+mod shr_8xy6 {
+    use super::*;
+
+    #[test]
+    fn shifted_out_bit() {
+        let state = exec(0x8006, |s| s.v_reg[0] = 0b0000_0101);
+        assert_eq!(state.v_reg[0], 0b0000_0010);
+        assert_eq!(state.v_reg[VF], 1);
+
+        let state = exec(0x8006, |s| {
+            s.v_reg[0] = 0b1000_0100;
+            s.v_reg[VF] = 1;
+        });
+        assert_eq!(state.v_reg[0], 0b0100_0010);
+        assert_eq!(state.v_reg[VF], 0);
+    }
+
+    #[test]
+    fn shifts_vx_in_place() {
+        // Vy is ignored, like SCHIP.
+        let state = exec(0x8016, v0_v1(0x10, 0x03));
+        assert_eq!(state.v_reg[0], 0x08);
+        assert_eq!(state.v_reg[1], 0x03);
+        assert_eq!(state.v_reg[VF], 0);
+    }
+
+    /// Shifts V1 into V0 under `Shift::Vy`.
+    pub fn shift(quirks: Quirks) {
+        let mut emu = machine_with(quirks, &[0x8016], v0_v1(0x10, 0x03));
+        let state = run(&mut emu, 1);
+        let (v0, vf) = match quirks.shift {
+            Shift::Vx => (0x08, 0),
+            Shift::Vy => (0x01, 1),
+        };
+        assert_eq!((state.v_reg[0], state.v_reg[VF]), (v0, vf));
+        assert_eq!(state.v_reg[1], 0x03);
+    }
+
+    #[test]
+    fn flag_wins_over_vf_result() {
+        let state = exec(0x8F06, |s| s.v_reg[VF] = 0b11);
+        assert_eq!(state.v_reg[VF], 1);
+    }
+}
+
+mod subn_8xy7 {
+    use super::*;
+
+    #[test]
+    fn no_borrow() {
+        let state = exec(0x8017, v0_v1(3, 5));
+        assert_eq!(state.v_reg[0], 2);
+        assert_eq!(state.v_reg[VF], 1);
+    }
+
+    #[test]
+    fn equal_is_no_borrow() {
+        let state = exec(0x8017, v0_v1(5, 5));
+        assert_eq!(state.v_reg[0], 0);
+        assert_eq!(state.v_reg[VF], 1);
+    }
+
+    #[test]
+    fn borrow() {
+        let state = exec(0x8017, v0_v1(5, 3));
+        assert_eq!(state.v_reg[0], 0xFE);
+        assert_eq!(state.v_reg[VF], 0);
+    }
+
+    #[test]
+    fn flag_wins_over_vf_result() {
+        let state = exec(0x8F17, |s| {
+            s.v_reg[VF] = 1;
+            s.v_reg[1] = 0x10;
+        });
+        assert_eq!(state.v_reg[VF], 1);
+    }
+}
+
+mod shl_8xye {
+    use super::*;
+
+    #[test]
+    fn shifted_out_bit() {
+        let state = exec(0x800E, |s| s.v_reg[0] = 0b1000_0001);
+        assert_eq!(state.v_reg[0], 0b0000_0010);
+        assert_eq!(state.v_reg[VF], 1);
+
+        let state = exec(0x800E, |s| {
+            s.v_reg[0] = 0b0100_0000;
+            s.v_reg[VF] = 1;
+        });
+        assert_eq!(state.v_reg[0], 0b1000_0000);
+        assert_eq!(state.v_reg[VF], 0);
+    }
+
+    #[test]
+    fn shifts_vx_in_place() {
+        let state = exec(0x801E, v0_v1(0x01, 0x80));
+        assert_eq!(state.v_reg[0], 0x02);
+        assert_eq!(state.v_reg[1], 0x80);
+        assert_eq!(state.v_reg[VF], 0);
+    }
+
+    /// Shifts V1 into V0 under `Shift::Vy`.
+    pub fn shift(quirks: Quirks) {
+        let mut emu = machine_with(quirks, &[0x801E], v0_v1(0x01, 0x80));
+        let state = run(&mut emu, 1);
+        let (v0, vf) = match quirks.shift {
+            Shift::Vx => (0x02, 0),
+            Shift::Vy => (0x00, 1),
+        };
+        assert_eq!((state.v_reg[0], state.v_reg[VF]), (v0, vf));
+        assert_eq!(state.v_reg[1], 0x80);
+    }
+
+    #[test]
+    fn flag_wins_over_vf_result() {
+        let state = exec(0x8F0E, |s| s.v_reg[VF] = 0x80);
+        assert_eq!(state.v_reg[VF], 1);
+    }
+}
+
+mod sne_9xy0 {
+    use super::*;
+
+    #[test]
+    fn skips_if_not_equal() {
+        assert!(skips(0x9120, |s| s.v_reg[1] = 7));
+        assert!(!skips(0x9120, |s| {
+            s.v_reg[1] = 7;
+            s.v_reg[2] = 7;
+        }));
+    }
+
+    #[test]
+    fn same_register() {
+        assert!(!skips(0x9550, |s| s.v_reg[5] = 9));
+    }
+}
+
+mod ld_annn {
+    use super::*;
+
+    #[test]
+    fn loads() {
+        let state = exec(0xAFFF, |_| {});
+        assert_eq!(state.i_reg, 0xFFF);
+        assert_eq!(state.pc, START + 2);
+    }
+}
+
+mod jp_bnnn {
+    use super::*;
+
+    #[test]
+    fn adds_v0() {
+        assert_eq!(exec(0xB300, |s| s.v_reg[0] = 0x10).pc, 0x310);
+        assert_eq!(exec(0xB300, |s| s.v_reg[0] = 0xFF).pc, 0x3FF);
+    }
+
+    #[test]
+    fn ignores_vx() {
+        // SCHIP's BXNN jumps to XNN + VX, CHIP-8 always uses V0.
+        let state = exec(0xB300, |s| s.v_reg[3] = 0x10);
+        assert_eq!(state.pc, 0x300);
+    }
+
+    /// Adds V3 instead of V0 under `jump_vx`.
+    pub fn jump_vx(quirks: Quirks) {
+        let mut emu = machine_with(quirks, &[0xB320], |s| {
+            s.v_reg[0] = 0x01;
+            s.v_reg[3] = 0x10;
+        });
+        let expected = match quirks.jump_vx {
+            true => 0x330,
+            false => 0x321,
+        };
+        assert_eq!(run(&mut emu, 1).pc, expected);
+    }
+}
+
+mod rnd_cxkk {
+    use super::*;
+
+    #[test]
+    fn zero_mask() {
+        for _ in 0..16 {
+            assert_eq!(exec(0xC000, |s| s.v_reg[0] = 0xFF).v_reg[0], 0);
+        }
+    }
+
+    #[test]
+    fn masks() {
+        let mut emu = machine(&[0xC00F, 0x1200], |_| {});
+        for _ in 0..64 {
+            assert_eq!(run(&mut emu, 2).v_reg[0] & 0xF0, 0);
+        }
+    }
+
+    #[test]
+    fn seeded() {
+        let program = [0xC0FF, 0xC1FF, 0xC2FF, 0xC3FF];
+        let seeded = || {
+            let mut emu = machine(&program, |_| {});
+            emu.set_rng(Rng::from_seed(8));
+            run(&mut emu, program.len()).v_reg
+        };
+        assert_eq!(seeded(), seeded());
+    }
+}
+
+mod drw_dxyn {
+    use super::*;
+
+    /// Draws the byte at 0x300, repeated `n` times, at (V0, V1).
+    fn draw(
+        quirks: Quirks,
+        sprite: u8,
+        n: u8,
+        x: u8,
+        y: u8,
+        screen: &[(usize, usize)],
+    ) -> SaveState {
+        let mut emu = machine_with(quirks, &[0xD010 | n as u16], |s| {
+            s.i_reg = 0x300;
+            s.ram[0x300..0x300 + n as usize].fill(sprite);
+            s.v_reg[0] = x;
+            s.v_reg[1] = y;
+            for &(x, y) in screen {
+                s.screen[y * SCREEN_WIDTH + x] = true;
+            }
+        });
+        run(&mut emu, 1)
+    }
+
+    #[test]
+    fn draws_msb_first() {
+        let state = draw(Quirks::default(), 0b1000_0001, 1, 4, 2, &[]);
+        assert!(pixel(&state, 4, 2));
+        assert!(pixel(&state, 11, 2));
+        assert_eq!(state.screen.iter().filter(|&&p| p).count(), 2);
+        assert_eq!(state.v_reg[VF], 0);
+    }
+
+    #[test]
+    fn collision_erases() {
+        let state = draw(Quirks::default(), 0xFF, 1, 0, 0, &[(3, 0)]);
+        assert!(!pixel(&state, 3, 0));
+        assert!(pixel(&state, 4, 0));
+        assert_eq!(state.v_reg[VF], 1);
+    }
+
+    #[test]
+    fn blank_sprite_pixels_dont_collide() {
+        let state = draw(Quirks::default(), 0x0F, 1, 0, 0, &[(0, 0)]);
+        assert!(pixel(&state, 0, 0));
+        assert_eq!(state.v_reg[VF], 0);
+    }
+
+    #[test]
+    fn clears_stale_collision() {
+        let mut emu = machine(&[0xD011], |s| s.v_reg[VF] = 1);
+        assert_eq!(run(&mut emu, 1).v_reg[VF], 0);
+    }
+
+    #[test]
+    fn zero_rows() {
+        let state = draw(Quirks::default(), 0xFF, 0, 0, 0, &[]);
+        assert!(!state.screen.contains(&true));
+        assert_eq!(state.v_reg[VF], 0);
+    }
+
+    #[test]
+    fn start_position_wraps() {
+        let state = draw(Quirks::default(), 0x80, 1, 64 + 5, 32 + 3, &[]);
+        assert!(pixel(&state, 5, 3));
+    }
+
+    #[test]
+    fn clipped_pixels_dont_collide() {
+        // Would land on (0, 0) and (0, 31) if wrapped.
+        let state = draw(Quirks::default(), 0xFF, 2, 60, 31, &[(0, 0), (0, 31)]);
+        assert!(pixel(&state, 63, 31));
+        assert!(pixel(&state, 0, 0));
+        assert_eq!(state.v_reg[VF], 0);
+    }
+
+    #[test]
+    fn coordinates_read_before_vf() {
+        // DF01 with VF = 10 draws at x = 10 before clearing VF.
+        let mut emu = machine(&[0xDF01], |s| {
+            s.i_reg = 0x300;
+            s.ram[0x300] = 0x80;
+            s.v_reg[VF] = 10;
+        });
+        let state = run(&mut emu, 1);
+        assert!(pixel(&state, 10, 0));
+        assert_eq!(state.v_reg[VF], 0);
+    }
+
+    #[test]
+    fn font_digit() {
+        // LD F, V0; DRW V1, V1, 5
+        let mut emu = machine(&[0xF029, 0xD115], |s| s.v_reg[0] = 0x8);
+        let state = run(&mut emu, 2);
+        // 8 is F0 90 F0 90 F0.
+        for y in 0..5 {
+            assert!(pixel(&state, 0, y));
+            assert!(pixel(&state, 3, y));
+            assert_eq!(pixel(&state, 1, y), y % 2 == 0);
+        }
+    }
+
+    /// Wrapping at the right edge under `wrap_x`.
+    pub fn wrap_x(quirks: Quirks) {
+        let state = draw(quirks, 0xFF, 1, 60, 0, &[(1, 0)]);
+        assert!((60..64).all(|x| pixel(&state, x, 0)));
+        for x in 0..4 {
+            // (1, 0) is erased only if wrapped.
+            let lit = if quirks.wrap_x { x != 1 } else { x == 1 };
+            assert_eq!(pixel(&state, x, 0), lit);
+        }
+        assert_eq!(state.v_reg[VF], quirks.wrap_x as u8);
+    }
+
+    /// Wrapping at the bottom edge under `wrap_y`.
+    pub fn wrap_y(quirks: Quirks) {
+        let state = draw(quirks, 0x80, 3, 0, 30, &[(0, 0)]);
+        assert!(pixel(&state, 0, 30) && pixel(&state, 0, 31));
+        assert_eq!(pixel(&state, 0, 0), !quirks.wrap_y);
+        assert_eq!(state.v_reg[VF], quirks.wrap_y as u8);
+    }
+}
+
+mod skp_ex9e {
+    use super::*;
+
+    #[test]
+    fn skips_if_pressed() {
+        assert!(skips(0xE39E, |s| {
+            s.v_reg[3] = 0xA;
+            s.keys[0xA] = true;
+        }));
+        assert!(!skips(0xE39E, |s| {
+            s.v_reg[3] = 0xA;
+            s.keys[0xB] = true;
+        }));
+    }
+}
+
+mod sknp_exa1 {
+    use super::*;
+
+    #[test]
+    fn skips_if_not_pressed() {
+        assert!(skips(0xE3A1, |s| s.v_reg[3] = 0xA));
+        assert!(!skips(0xE3A1, |s| {
+            s.v_reg[3] = 0xA;
+            s.keys[0xA] = true;
+        }));
+    }
+}
+
+mod ld_fx07 {
+    use super::*;
+
+    #[test]
+    fn reads_delay_timer() {
+        assert_eq!(exec(0xF507, |s| s.dt = 42).v_reg[5], 42);
+    }
+
+    #[test]
+    fn counts_down() {
+        // LD DT, V0; LD V1, DT
+        let mut emu = machine(&[0xF015, 0xF107], |s| s.v_reg[0] = 3);
+        emu.run_cycle().unwrap();
+        emu.dec_timers();
+        assert_eq!(run(&mut emu, 1).v_reg[1], 2);
+    }
+}
+
+mod ld_fx0a {
+    use super::*;
+
+    #[test]
+    fn waits_for_press_and_release() {
+        let mut emu = machine(&[0xF30A], |_| {});
+        assert_eq!(run(&mut emu, 3).pc, START);
+
+        emu.set_key(0x7, true);
+        assert_eq!(run(&mut emu, 3).pc, START);
+
+        emu.set_key(0x7, false);
+        let state = run(&mut emu, 1);
+        assert_eq!(state.pc, START + 2);
+        assert_eq!(state.v_reg[3], 0x7);
+    }
+
+    #[test]
+    fn held_before_waiting() {
+        let mut emu = machine(&[0xF30A], |s| s.keys[0x2] = true);
+        assert_eq!(run(&mut emu, 1).pc, START);
+        emu.set_key(0x2, false);
+        assert_eq!(run(&mut emu, 1).v_reg[3], 0x2);
+    }
+
+    #[test]
+    fn tap_between_cycles() {
+        let mut emu = machine(&[0xF30A], |_| {});
+        emu.set_key(0xC, true);
+        emu.set_key(0xC, false);
+        run(&mut emu, 2);
+        assert_eq!(emu.save_state().v_reg[3], 0xC);
+    }
+
+    #[test]
+    fn press_with_tap_between_cycles() {
+        let quirks = Quirks {
+            key_wait: KeyWait::Press,
+            ..Quirks::default()
+        };
+        let mut emu = machine_with(quirks, &[0xF30A], |_| {});
+        emu.set_key(0xC, true);
+        emu.set_key(0xC, false);
+        let state = run(&mut emu, 1);
+        assert_eq!(state.pc, START + 2);
+        assert_eq!(state.v_reg[3], 0xC);
+    }
+
+    /// Resumes on press or only after the release under `key_wait`.
+    pub fn key_wait(quirks: Quirks) {
+        let mut emu = machine_with(quirks, &[0xF30A], |_| {});
+        assert_eq!(run(&mut emu, 3).pc, START);
+
+        emu.set_key(0x7, true);
+        let state = run(&mut emu, 1);
+        match quirks.key_wait {
+            KeyWait::Release => assert_eq!(state.pc, START),
+            KeyWait::Press => {
+                assert_eq!(state.pc, START + 2);
+                assert_eq!(state.v_reg[3], 0x7);
+            }
+        }
+    }
+}
+
+mod ld_fx15_fx18 {
+    use super::*;
+
+    #[test]
+    fn sets_delay_timer() {
+        assert_eq!(exec(0xF215, |s| s.v_reg[2] = 60).dt, 60);
+    }
+
+    #[test]
+    fn sets_sound_timer() {
+        let mut emu = machine(&[0xF218], |s| s.v_reg[2] = 2);
+        assert!(!emu.sound());
+        emu.run_cycle().unwrap();
+        assert!(emu.sound());
+        emu.dec_timers();
+        emu.dec_timers();
+        assert!(!emu.sound());
+    }
+}
+
+mod add_fx1e {
+    use super::*;
+
+    #[test]
+    fn adds() {
+        assert_eq!(
+            exec(0xF01E, |s| {
+                s.i_reg = 0x300;
+                s.v_reg[0] = 0xFF;
+            })
+            .i_reg,
+            0x3FF
+        );
+    }
+
+    #[test]
+    fn keeps_vf_past_0fff() {
+        // Amiga interpreters set VF here, the VIP didn't.
+        let state = exec(0xF01E, |s| {
+            s.i_reg = 0xFFF;
+            s.v_reg[0] = 0x01;
+        });
+        assert_eq!(state.i_reg, 0x1000);
+        assert_eq!(state.v_reg[VF], 0);
+    }
+
+    /// Sets VF on overflow, and clears it otherwise, under
+    /// `i_overflow`.
+    pub fn i_overflow(quirks: Quirks) {
+        let add = |i_reg, v0| {
+            let mut emu = machine_with(quirks, &[0xF01E], |s| {
+                s.i_reg = i_reg;
+                s.v_reg[0] = v0;
+                s.v_reg[VF] = 0x42;
+            });
+            run(&mut emu, 1)
+        };
+        let state = add(0xFFE, 0x02);
+        assert_eq!(state.i_reg, 0x1000);
+        let state_vf = |state: SaveState| state.v_reg[VF];
+        match quirks.i_overflow {
+            true => {
+                assert_eq!(state_vf(state), 1);
+                assert_eq!(state_vf(add(0xFFE, 0x01)), 0);
+            }
+            false => {
+                assert_eq!(state_vf(state), 0x42);
+                assert_eq!(state_vf(add(0xFFE, 0x01)), 0x42);
+            }
+        }
+    }
+}
+
+mod ld_fx29 {
+    use super::*;
+
+    #[test]
+    fn points_at_digit() {
+        for digit in 0..16u8 {
+            let state = exec(0xF029, |s| s.v_reg[0] = digit);
+            let i = state.i_reg as usize;
+            let zero = exec(0xF029, |_| {}).i_reg as usize;
+            assert_eq!(i, zero + digit as usize * 5);
+        }
+        // 0 is F0 90 90 90 F0.
+        let state = exec(0xF029, |_| {});
+        let i = state.i_reg as usize;
+        assert_eq!(state.ram[i..i + 5], [0xF0, 0x90, 0x90, 0x90, 0xF0]);
+    }
+}
+
+mod ld_fx33 {
+    use super::*;
+
+    fn bcd(v: u8) -> [u8; 3] {
+        let state = exec(0xF033, |s| {
+            s.i_reg = 0x300;
+            s.v_reg[0] = v;
+        });
+        state.ram[0x300..0x303].try_into().unwrap()
+    }
+
+    #[test]
+    fn digits() {
+        assert_eq!(bcd(0), [0, 0, 0]);
+        assert_eq!(bcd(7), [0, 0, 7]);
+        assert_eq!(bcd(40), [0, 4, 0]);
+        assert_eq!(bcd(100), [1, 0, 0]);
+        assert_eq!(bcd(255), [2, 5, 5]);
+    }
+
+    #[test]
+    fn keeps_i() {
+        assert_eq!(exec(0xF033, |s| s.i_reg = 0x300).i_reg, 0x300);
+    }
+}
+
+mod ld_fx55_fx65 {
+    use super::*;
+
+    #[test]
+    fn stores_through_vx() {
+        let state = exec(0xF355, |s| {
+            s.i_reg = 0x300;
+            s.v_reg = std::array::from_fn(|x| x as u8 + 1);
+        });
+        assert_eq!(state.ram[0x300..0x305], [1, 2, 3, 4, 0]);
+        // I is left alone, like SCHIP.
+        assert_eq!(state.i_reg, 0x300);
+    }
+
+    #[test]
+    fn loads_through_vx() {
+        let state = exec(0xF265, |s| {
+            s.i_reg = 0x300;
+            s.ram[0x300..0x304].copy_from_slice(&[9, 8, 7, 6]);
+        });
+        assert_eq!(state.v_reg[..4], [9, 8, 7, 0]);
+        assert_eq!(state.i_reg, 0x300);
+    }
+
+    #[test]
+    fn all_registers() {
+        // LD [I], VF; LD VF, [I] after clearing them
+        let mut emu = machine(&[0xFF55, 0x00E0, 0xFF65], |s| {
+            s.i_reg = 0x300;
+            s.v_reg = std::array::from_fn(|x| 0xF0 | x as u8);
+        });
+        let stored = run(&mut emu, 1);
+        let mut cleared = stored.clone();
+        cleared.v_reg = [0; 16];
+        emu.restore_state(&cleared);
+        assert_eq!(run(&mut emu, 2).v_reg, stored.v_reg);
+    }
+}
+
+mod invalid {
+    use super::*;
+
+    #[test]
+    fn reports_opcode_and_address() {
+        for op in [0x0123, 0x8008, 0xE000, 0xF0FF] {
+            let mut emu = machine(&[op], |_| {});
+            assert_eq!(
+                emu.run_cycle(),
+                Err(format!("Invalid Instruction: {op:04X} at {START}"))
+            );
+        }
+    }
+}
+
+/// Quirk-sensitive behaviour under every preset.
+mod quirks {
+    use super::*;
+
+    /// Checks every quirk of `quirks`. Destructured without `..`, so a
+    /// new quirk doesn't compile until it's checked here.
+    fn check(quirks: Quirks) {
+        let Quirks {
+            rng,
+            wrap_x: _,
+            wrap_y: _,
+            key_wait: _,
+            i_overflow: _,
+            jump_vx: _,
+            shift: _,
+        } = quirks;
+        rng_mode(quirks, rng);
+        drw_dxyn::wrap_x(quirks);
+        drw_dxyn::wrap_y(quirks);
+        ld_fx0a::key_wait(quirks);
+        add_fx1e::i_overflow(quirks);
+        jp_bnnn::jump_vx(quirks);
+        shr_8xy6::shift(quirks);
+        shl_8xye::shift(quirks);
+    }
+
+    fn rng_mode(quirks: Quirks, rng: RngMode) {
+        let bytes = |seed| {
+            let mut emu = machine_with(quirks, &[0xC0FF, 0xC1FF, 0xC2FF, 0xC3FF], |_| {});
+            emu.set_rng(Rng::from_seed(seed));
+            run(&mut emu, 4).v_reg
+        };
+        match rng {
+            // Seeds pick the sequence.
+            RngMode::Uniform => {
+                assert_eq!(bytes(1), bytes(1));
+                assert_ne!(bytes(1), bytes(2));
+            }
+            // The same sequence on every boot, whatever the seed.
+            RngMode::Lfsr => {
+                assert_eq!(bytes(1), bytes(2));
+            }
+        }
+    }
+
+    #[test]
+    fn presets() {
+        for variant in Variant::ALL {
+            check(variant.quirks());
+        }
+    }
+
+    #[test]
+    fn each_quirk_toggled() {
+        check(Quirks {
+            rng: RngMode::Lfsr,
+            ..Quirks::default()
+        });
+        check(Quirks {
+            wrap_x: true,
+            ..Quirks::default()
+        });
+        check(Quirks {
+            wrap_y: true,
+            ..Quirks::default()
+        });
+        check(Quirks {
+            key_wait: KeyWait::Press,
+            ..Quirks::default()
+        });
+        check(Quirks {
+            i_overflow: true,
+            ..Quirks::default()
+        });
+        check(Quirks {
+            jump_vx: true,
+            ..Quirks::default()
+        });
+        check(Quirks {
+            shift: Shift::Vy,
+            ..Quirks::default()
+        });
+    }
+}
@@ -0,0 +1,29 @@
+//! Replays every repro in `tests/repros`, see `oxid8_core::repro`.
+
+use oxid8_core::repro::Repro;
+use std::{fs, path::Path};
+
+#[test]
+fn repros() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/repros");
+    let mut paths: Vec<_> = fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "repro"))
+        .collect();
+    paths.sort();
+    assert!(!paths.is_empty());
+
+    let failures: Vec<String> = paths
+        .iter()
+        .filter_map(|path| {
+            let name = path.file_name().unwrap().to_string_lossy();
+            let result = fs::read_to_string(path)
+                .map_err(|e| e.to_string())
+                .and_then(|s| s.parse::<Repro>())
+                .and_then(|repro| repro.replay());
+            result.err().map(|e| format!("{name}: {e}"))
+        })
+        .collect();
+    assert!(failures.is_empty(), "{}", failures.join("\n"));
+}
@@ -0,0 +1,184 @@
+//! Headless Chip-8 interpreter streaming frames over WebSocket.
+//!
+//! Every client receives each frame as a binary message of
+//! [`PACKED_SIZE`] bytes in the packed screen format, followed by one
+//! byte that is 1 while the sound timer is active. Clients send key
+//! events as two byte binary messages: the key (0x0 - 0xF), then 1 for
+//! pressed or 0 for released. All clients share the same game, and the
+//! keys a client holds are released when it disconnects.
+
+use clap::Parser;
+use futures::{SinkExt, StreamExt, channel::mpsc, sink};
+use oxid8_core::{
+    Oxid8,
+    driver::{self, Frame, KeyEvent},
+    export::{PACKED_SIZE, pack_screen},
+};
+use std::{convert::Infallible, net::SocketAddr, path::PathBuf};
+use tokio::{
+    net::{TcpListener, TcpStream},
+    sync::broadcast,
+};
+use tokio_tungstenite::{accept_async, tungstenite::Message};
+
+#[derive(Parser, Debug)]
+#[command(version, about, long_about = None)]
+struct Args {
+    /// The rom to serve, `-` reads it from standard input.
+    #[arg(required = true)]
+    rom_path: PathBuf,
+    /// Address to listen on.
+    #[arg(long, default_value = "127.0.0.1:8008")]
+    addr: SocketAddr,
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    env_logger::init();
+    let args = Args::parse();
+
+    let mut core = Oxid8::new();
+    core.load_font();
+    core.load_rom(&args.rom_path)?;
+
+    // Encoded frames for every client. Lagging clients skip frames.
+    let (frame_tx, _) = broadcast::channel(4);
+    // Key events from every client.
+    let (key_tx, key_rx) = mpsc::unbounded();
+
+    let frames = sink::unfold(frame_tx.clone(), |tx, frame: Frame| async move {
+        // Having no clients is not an error.
+        let _ = tx.send(encode(&frame));
+        Ok::<_, Infallible>(tx)
+    });
+    let emulator =
+        tokio::spawn(async move { driver::run(&mut core, Box::pin(frames), key_rx).await });
+
+    let listener = TcpListener::bind(args.addr).await?;
+    log::info!("Listening on ws://{}", args.addr);
+    tokio::spawn(serve(listener, frame_tx, key_tx));
+
+    emulator.await?.map_err(anyhow::Error::msg)
+}
+
+/// Accepts clients until the process exits.
+async fn serve(
+    listener: TcpListener,
+    frames: broadcast::Sender<Vec<u8>>,
+    keys: mpsc::UnboundedSender<KeyEvent>,
+) {
+    loop {
+        match listener.accept().await {
+            Ok((stream, peer)) => {
+                tokio::spawn(client(stream, peer, frames.subscribe(), keys.clone()));
+            }
+            Err(err) => log::warn!("Failed to accept connection: {err}"),
+        }
+    }
+}
+
+/// Streams frames to a single client and forwards its key events.
+async fn client(
+    stream: TcpStream,
+    peer: SocketAddr,
+    mut frames: broadcast::Receiver<Vec<u8>>,
+    keys: mpsc::UnboundedSender<KeyEvent>,
+) {
+    let ws = match accept_async(stream).await {
+        Ok(ws) => ws,
+        Err(err) => {
+            log::warn!("{peer}: {err}");
+            return;
+        }
+    };
+    log::info!("{peer} connected");
+
+    let (mut tx, mut rx) = ws.split();
+    // Keys this client holds, released when it leaves.
+    let mut held = [false; 0x10];
+    loop {
+        tokio::select! {
+            frame = frames.recv() => match frame {
+                Ok(frame) => {
+                    if tx.send(Message::binary(frame)).await.is_err() {
+                        break;
+                    }
+                }
+                Err(broadcast::error::RecvError::Lagged(_)) => (),
+                Err(broadcast::error::RecvError::Closed) => break,
+            },
+            msg = rx.next() => match msg {
+                Some(Ok(Message::Binary(data))) => {
+                    if let Some(event) = decode(&data) {
+                        held[event.key as usize] = event.pressed;
+                        let _ = keys.unbounded_send(event);
+                    }
+                }
+                Some(Ok(Message::Close(_)) | Err(_)) | None => break,
+                Some(Ok(_)) => (),
+            },
+        }
+    }
+
+    for key in (0..0x10).filter(|&key| held[key as usize]) {
+        let _ = keys.unbounded_send(KeyEvent {
+            key,
+            pressed: false,
+        });
+    }
+    log::info!("{peer} disconnected");
+}
+
+/// Packed screen followed by the sound flag.
+fn encode(frame: &Frame) -> Vec<u8> {
+    let mut data = Vec::with_capacity(PACKED_SIZE + 1);
+    data.extend_from_slice(&pack_screen(&frame.screen));
+    data.push(frame.sound as u8);
+    data
+}
+
+/// `[key, pressed]`
+fn decode(data: &[u8]) -> Option<KeyEvent> {
+    match *data {
+        [key @ 0x0..=0xF, pressed @ (0 | 1)] => Some(KeyEvent {
+            key,
+            pressed: pressed == 1,
+        }),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use oxid8_core::{SCREEN_AREA, export::unpack_screen};
+
+    #[test]
+    fn frames_round_trip() {
+        let mut screen = [false; SCREEN_AREA];
+        for i in (0..SCREEN_AREA).step_by(7) {
+            screen[i] = true;
+        }
+        for sound in [false, true] {
+            let data = encode(&Frame { screen, sound });
+            assert_eq!(data.len(), PACKED_SIZE + 1);
+            let packed: &[u8; PACKED_SIZE] = data[..PACKED_SIZE].try_into().unwrap();
+            assert_eq!(unpack_screen(packed), screen);
+            assert_eq!(data[PACKED_SIZE], sound as u8);
+        }
+    }
+
+    #[test]
+    fn decodes_keys() {
+        for key in 0x0..=0xF {
+            for pressed in [false, true] {
+                let event = KeyEvent { key, pressed };
+                assert_eq!(decode(&[key, pressed as u8]), Some(event));
+            }
+        }
+        assert_eq!(decode(&[0x10, 1]), None);
+        assert_eq!(decode(&[0x1, 2]), None);
+        assert_eq!(decode(&[0x1]), None);
+        assert_eq!(decode(&[0x1, 1, 0]), None);
+    }
+}
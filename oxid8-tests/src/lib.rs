@@ -0,0 +1,28 @@
+//! Tests that run each frontend headlessly, to catch changes to the
+//! core that break how a frontend is wired to it:
+//!
+//! - `tests/tui.rs` loads a ROM into the terminal game screen, types
+//!   commands into it, and draws it to a `TestBackend` terminal.
+//! - `tests/kitty.rs` checks the kitty graphics images `oxid-cli`
+//!   draws with send the whole screen once, then only what changed.
+//! - `tests/wgpu.rs` fills the windowed frontend's screen texture, on
+//!   the CPU and in the compute pass, on wgpu's fallback adapter and
+//!   reads it back. Skipped without an adapter.
+//! - `tests/wasm.rs` runs the `oxid8-wasm` bindings under
+//!   `wasm-bindgen-test`, with
+//!   `wasm-pack test --node oxid8-tests`.
+//!
+//! Each runs [`ROM`] and checks [`DIGIT`] is drawn.
+
+/// LD V0, 0; LD F, V0; DRW V0, V0, 5; JP 206. Draws the font's 0 in the
+/// top left corner and loops.
+pub const ROM: [u8; 8] = [0x60, 0x00, 0xF0, 0x29, 0xD0, 0x05, 0x12, 0x06];
+
+/// Rows of the digit [`ROM`] draws, the leftmost pixel in the high bit.
+pub const DIGIT: [u8; 5] = [0xF0, 0x90, 0x90, 0x90, 0xF0];
+
+/// Returns true if the pixel at `x`, `y` is lit once [`ROM`] has drawn.
+#[must_use]
+pub fn lit(x: usize, y: usize) -> bool {
+    x < 8 && DIGIT.get(y).is_some_and(|row| row & (0x80 >> x) != 0)
+}
@@ -0,0 +1,51 @@
+//! The kitty graphics images `oxid-cli --kitty-graphics` draws the
+//! screen with, from the core's dirty tracking.
+
+use oxid8::kitty::KittyImage;
+use oxid8_core::Oxid8;
+use oxid8_tests::ROM;
+use ratatui::layout::Rect;
+
+const AREA: Rect = Rect::new(8, 2, 64, 16);
+
+/// The escape codes drawing `emu` now, as text.
+fn draw(kitty: &mut KittyImage, emu: &mut Oxid8, area: Rect) -> String {
+    let changes = emu.take_screen_changes();
+    let mut out = Vec::new();
+    let len = kitty
+        .draw(&mut out, emu.screen_ref(), &changes, area)
+        .unwrap();
+    assert_eq!(len, out.len());
+    String::from_utf8(out).unwrap()
+}
+
+#[test]
+fn sends_only_changes() {
+    let mut emu = Oxid8::new();
+    emu.load_font();
+    emu.load_rom_bytes(&ROM).unwrap();
+    let mut kitty = KittyImage::new(8);
+
+    // The whole image, 64x32 RGB in two chunks of base64.
+    let full = draw(&mut kitty, &mut emu, AREA);
+    assert!(full.starts_with("\x1b[3;9H\x1b_Ga=T,f=24,s=64,v=32,i=8,c=64,r=16,C=1,q=2,m=1;"));
+    assert!(full.contains("\x1b_Gm=0;"));
+    assert_eq!(full.matches("\x1b_G").count(), 2);
+    assert!(draw(&mut kitty, &mut emu, AREA).is_empty());
+
+    // The digit, 4x5 pixels of 3 bytes.
+    for _ in 0..3 {
+        emu.run_cycle().unwrap();
+    }
+    let edit = draw(&mut kitty, &mut emu, AREA);
+    let header = "\x1b_Ga=f,r=1,f=24,i=8,x=0,y=0,s=4,v=5,q=2,m=0;";
+    assert!(edit.starts_with(header), "{edit:?}");
+    assert_eq!(edit.len(), header.len() + 4 * 60 / 3 + 2);
+    assert!(edit.len() * 50 < full.len());
+
+    // Moved when the terminal is resized.
+    let moved = draw(&mut kitty, &mut emu, Rect { x: 0, ..AREA });
+    assert!(moved.starts_with("\x1b_Ga=d,d=I,i=8,q=2\x1b\\\x1b[3;1H\x1b_Ga=T"));
+    kitty.invalidate();
+    assert!(draw(&mut kitty, &mut emu, AREA).starts_with("\x1b[3;9H\x1b_Ga=T"));
+}
@@ -0,0 +1,422 @@
+//! The terminal game screen, driven with events and drawn to a
+//! `TestBackend` instead of a terminal.
+
+use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers};
+use oxid8::{
+    app::{App, AppState, RomSource},
+    command::{self, Command, Quirk, Timer},
+    keypad::{self, KeypadDiagram},
+    screens::{
+        Screen,
+        game::{Game, HOTKEYS},
+        splash::SplashScreen,
+    },
+};
+use oxid8_core::{
+    TimerRate,
+    builtin::SPLASH_FRAMES,
+    hotkeys::Hotkeys,
+    keymap::Keymap,
+    quirks::{KeyWait, RngMode, Shift},
+    variant::Variant,
+};
+use oxid8_tests::{DIGIT, ROM};
+use ratatui::{
+    Terminal,
+    backend::TestBackend,
+    buffer::Buffer,
+    layout::{Position, Rect},
+    style::{Color, Modifier},
+    widgets::Widget,
+};
+use std::{fs, path::PathBuf};
+
+/// The game screen with [`ROM`] loaded from a file, as if picked in the
+/// library.
+fn game(name: &str) -> (Game, AppState) {
+    let path: PathBuf = std::env::temp_dir().join(format!("oxid8-tests-{name}.ch8"));
+    fs::write(&path, ROM).unwrap();
+    let mut app_state = AppState {
+        screen: Screen::Game,
+        rom: Some(RomSource::File(path)),
+        hotkeys: Hotkeys::new(&HOTKEYS),
+        ..AppState::default()
+    };
+    let mut game = Game::default();
+    game.load_selected(&mut app_state);
+    assert_eq!(app_state.error, None);
+    (game, app_state)
+}
+
+/// Each row of the terminal drawn.
+fn draw(game: &mut Game) -> Vec<String> {
+    let mut terminal = Terminal::new(TestBackend::new(160, 30)).unwrap();
+    terminal.draw(|frame| game.draw(frame)).unwrap();
+    rows(terminal.backend().buffer())
+}
+
+fn rows(buf: &Buffer) -> Vec<String> {
+    (0..buf.area.height)
+        .map(|y| (0..buf.area.width).map(|x| buf[(x, y)].symbol()).collect())
+        .collect()
+}
+
+fn type_keys(game: &mut Game, app_state: &mut AppState, keys: &str) {
+    for c in keys.chars() {
+        let key = KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE);
+        game.handle_event(Event::Key(key), app_state).unwrap();
+    }
+}
+
+fn press(game: &mut Game, app_state: &mut AppState, code: KeyCode) {
+    let key = KeyEvent::new(code, KeyModifiers::NONE);
+    game.handle_event(Event::Key(key), app_state).unwrap();
+}
+
+fn enter(game: &mut Game, app_state: &mut AppState) {
+    press(game, app_state, KeyCode::Enter);
+}
+
+/// Color of the first full block drawn.
+fn lit_color(game: &mut Game) -> Color {
+    let mut terminal = Terminal::new(TestBackend::new(160, 30)).unwrap();
+    terminal.draw(|frame| game.draw(frame)).unwrap();
+    let buf = terminal.backend().buffer();
+    buf.content
+        .iter()
+        .find(|cell| cell.symbol() == "█")
+        .expect("something is drawn")
+        .fg
+}
+
+fn drawn(game: &mut Game) -> bool {
+    draw(game).iter().any(|row| row.contains('█'))
+}
+
+#[test]
+fn draws_rom() {
+    let (mut game, mut app_state) = game("draws");
+    assert!(!drawn(&mut game));
+
+    game.run_frames(1, &mut app_state);
+    // Half-blocks, two pixel rows a cell.
+    let expected: Vec<String> = DIGIT
+        .chunks(2)
+        .map(|pair| {
+            (0..8)
+                .map(|x| {
+                    let px = |row: Option<&u8>| row.is_some_and(|row| row & (0x80 >> x) != 0);
+                    match (px(pair.first()), px(pair.get(1))) {
+                        (true, true) => '█',
+                        (true, false) => '▀',
+                        (false, true) => '▄',
+                        (false, false) => ' ',
+                    }
+                })
+                .collect()
+        })
+        .collect();
+    let rows = draw(&mut game);
+    let top = rows
+        .iter()
+        .position(|row| row.contains(&expected[0]))
+        .expect("the digit is drawn");
+    let left = rows[top].find(&expected[0]).unwrap();
+    for (row, expected) in rows[top..].iter().zip(&expected) {
+        assert_eq!(&row[left..left + expected.len()], expected);
+    }
+}
+
+#[test]
+fn palette_commands() {
+    let (mut game, mut app_state) = game("palette");
+    type_keys(&mut game, &mut app_state, ":memory");
+    // Paused while typing.
+    game.run_frames(1, &mut app_state);
+    assert!(!drawn(&mut game));
+    assert!(draw(&mut game).last().unwrap().contains(":memory"));
+
+    enter(&mut game, &mut app_state);
+    game.run_frames(1, &mut app_state);
+    let rows = draw(&mut game);
+    assert!(
+        rows.iter()
+            .any(|row| row.contains("Memory (:memory to hide)"))
+    );
+    assert!(drawn(&mut game));
+
+    type_keys(&mut game, &mut app_state, ":weight foo=2");
+    enter(&mut game, &mut app_state);
+    assert!(
+        draw(&mut game)
+            .last()
+            .unwrap()
+            .contains("Unknown instruction: foo")
+    );
+}
+
+#[test]
+fn parses_commands() {
+    let parse = |s: &str| s.parse::<Command>();
+    assert_eq!(parse(":speed 1200"), Ok(Command::Speed(1200)));
+    assert_eq!(parse("save 3"), Ok(Command::Save(3)));
+    assert_eq!(parse(":break 0x230"), Ok(Command::Break(0x230)));
+    assert_eq!(parse(":break 2A0"), Ok(Command::Break(0x2A0)));
+    assert_eq!(parse(":rate pal"), Ok(Command::Rate(TimerRate::Pal)));
+    assert_eq!(
+        parse(":load roms/pong.ch8"),
+        Ok(Command::Load(PathBuf::from("roms/pong.ch8")))
+    );
+    assert_eq!(
+        parse(":weight dxyn=4"),
+        Ok(Command::Weight("dxyn".into(), 4))
+    );
+    assert_eq!(
+        parse(":variant xo-chip"),
+        Ok(Command::Variant(Variant::XoChip))
+    );
+    assert_eq!(parse(":unwatch all"), Ok(Command::Unwatch(None)));
+    assert_eq!(parse(":unwatch 2"), Ok(Command::Unwatch(Some(2))));
+    assert_eq!(parse(":timer reset"), Ok(Command::Timer(Timer::Reset)));
+    assert_eq!(parse(":skip on"), Ok(Command::Skip(true)));
+    assert_eq!(parse("  :continue "), Ok(Command::Continue));
+
+    assert_eq!(parse(":speed 0"), Err("Invalid speed: 0".into()));
+    assert_eq!(
+        parse(":break 0x1000"),
+        Err("Invalid address: 0x1000".into())
+    );
+    assert_eq!(parse(":unwatch 0"), Err("Invalid watch: 0".into()));
+    assert_eq!(parse(":save"), Err("Usage: save <slot>".into()));
+    assert_eq!(parse(":step 2"), Err("Usage: step".into()));
+    assert_eq!(parse(":bogus"), Err("Unknown command: bogus".into()));
+}
+
+#[test]
+fn parses_quirks() {
+    let quirk = |s: &str| s.parse::<Quirk>();
+    assert_eq!(quirk("shift=vy"), Ok(Quirk::Shift(Shift::Vy)));
+    assert_eq!(quirk("rng=lfsr"), Ok(Quirk::Rng(RngMode::Lfsr)));
+    assert_eq!(quirk("rng=vip"), Ok(Quirk::Rng(RngMode::Lfsr)));
+    assert_eq!(quirk("wrap_x=1"), Ok(Quirk::WrapX(true)));
+    assert_eq!(quirk("jump_vx=off"), Ok(Quirk::JumpVx(false)));
+    assert_eq!(quirk("key_wait=press"), Ok(Quirk::KeyWait(KeyWait::Press)));
+    assert_eq!(
+        quirk("shift"),
+        Err("Expected <name>=<value>, got shift".into())
+    );
+    assert_eq!(quirk("shift=vz"), Err("Expected vx or vy, got vz".into()));
+    assert_eq!(quirk("wrap=on"), Err("Unknown quirk: wrap".into()));
+    assert_eq!(
+        ":quirk shift=vy".parse(),
+        Ok(Command::Quirk(Quirk::Shift(Shift::Vy)))
+    );
+}
+
+#[test]
+fn completes_commands() {
+    assert_eq!(command::complete("sp"), ["speed "]);
+    assert_eq!(command::complete("con"), ["continue"]);
+    assert_eq!(command::complete("rate "), ["rate 50", "rate 60"]);
+    assert_eq!(command::complete("variant "), ["variant chip8"]);
+    assert_eq!(command::complete("quirk sh"), ["quirk shift="]);
+    assert_eq!(
+        command::complete("quirk shift="),
+        ["quirk shift=vx", "quirk shift=vy"]
+    );
+    assert!(command::complete("weight 8xy").contains(&"weight 8xy6=".to_string()));
+    assert!(command::complete("nothing").is_empty());
+
+    let completions = command::complete("s");
+    assert_eq!(command::common_prefix(&completions), Some("s"));
+    let completions = command::complete("re");
+    assert_eq!(completions, ["restore ", "record"]);
+    assert_eq!(command::common_prefix(&completions), Some("re"));
+    assert_eq!(command::common_prefix(&[]), None);
+}
+
+#[test]
+fn quirk_command_shows_quirks() {
+    let (mut game, mut app_state) = game("quirk");
+    type_keys(&mut game, &mut app_state, ":quirk shift=vy");
+    enter(&mut game, &mut app_state);
+    let status = draw(&mut game).last().unwrap().clone();
+    assert!(status.contains("jump_vx=off shift=vy"), "{status}");
+}
+
+#[test]
+fn reset_once_while_held() {
+    let (mut game, mut app_state) = game("reset");
+    game.run_frames(1, &mut app_state);
+    press(&mut game, &mut app_state, KeyCode::Backspace);
+    assert!(!drawn(&mut game));
+    assert!(draw(&mut game).last().unwrap().contains("Reset"));
+
+    // The terminal repeats the press while the key is held.
+    game.run_frames(1, &mut app_state);
+    press(&mut game, &mut app_state, KeyCode::Backspace);
+    assert!(drawn(&mut game));
+}
+
+#[test]
+fn color_editor_previews() {
+    let (mut game, mut app_state) = game("colors");
+    game.run_frames(1, &mut app_state);
+    assert_eq!(lit_color(&mut game), Color::White);
+
+    type_keys(&mut game, &mut app_state, ":colors");
+    enter(&mut game, &mut app_state);
+    assert!(
+        draw(&mut game)
+            .iter()
+            .any(|row| row.contains("Colors (Esc to cancel)"))
+    );
+    // The next preset after white on black, green phosphor.
+    press(&mut game, &mut app_state, KeyCode::Tab);
+    assert_eq!(lit_color(&mut game), Color::Rgb(0x33, 0xFF, 0x66));
+    press(&mut game, &mut app_state, KeyCode::Down);
+    press(&mut game, &mut app_state, KeyCode::PageUp);
+    assert!(
+        draw(&mut game)
+            .iter()
+            .any(|row| row.contains("background #201010"))
+    );
+
+    // Cancelling puts the colors back.
+    press(&mut game, &mut app_state, KeyCode::Esc);
+    assert_eq!(lit_color(&mut game), Color::White);
+    assert!(!draw(&mut game).iter().any(|row| row.contains("Colors")));
+}
+
+#[test]
+fn ansi_colors() {
+    let (_, mut app_state) = game("ansi");
+    app_state.ansi_colors = true;
+    let mut game = Game::default();
+    game.load_selected(&mut app_state);
+    game.run_frames(1, &mut app_state);
+    // Unset colors stay the terminal's own.
+    assert_eq!(lit_color(&mut game), Color::White);
+
+    type_keys(&mut game, &mut app_state, ":colors");
+    enter(&mut game, &mut app_state);
+    press(&mut game, &mut app_state, KeyCode::Tab);
+    // Green phosphor, in the 256 color cube.
+    assert_eq!(lit_color(&mut game), Color::Indexed(83));
+}
+
+#[test]
+fn filters() {
+    assert_eq!(
+        App::default().with_filters("bloom").err().as_deref(),
+        Some("Unknown filter: bloom")
+    );
+    let (_, mut app_state) = game("filters");
+    app_state.filters = "brightness=0.5".into();
+    let mut game = Game::default();
+    game.load_selected(&mut app_state);
+    game.run_frames(1, &mut app_state);
+    assert_eq!(lit_color(&mut game), Color::Rgb(128, 128, 128));
+}
+
+#[test]
+fn zooms_small_terminals() {
+    let (mut game, mut app_state) = game("zoom");
+    game.run_frames(1, &mut app_state);
+    let small = |game: &mut Game| {
+        let mut terminal = Terminal::new(TestBackend::new(60, 12)).unwrap();
+        terminal.draw(|frame| game.draw(frame)).unwrap();
+        rows(terminal.backend().buffer()).concat()
+    };
+    // Too narrow for half blocks, fit in Braille.
+    let drawn = small(&mut game);
+    assert!(drawn.contains(|c| ('\u{2801}'..='\u{28FF}').contains(&c)));
+    assert!(!drawn.contains('█'));
+
+    press(&mut game, &mut app_state, KeyCode::Tab);
+    assert!(small(&mut game).contains('█'));
+    // Panned away from the digit in the corner.
+    for _ in 0..3 {
+        press(&mut game, &mut app_state, KeyCode::Right);
+    }
+    assert!(!small(&mut game).contains('█'));
+    let footer = |game: &mut Game| draw(game).last().unwrap().clone();
+    assert!(footer(&mut game).contains("Zoom: half blocks, arrows pan"));
+
+    small(&mut game);
+    press(&mut game, &mut app_state, KeyCode::Tab);
+    press(&mut game, &mut app_state, KeyCode::Tab);
+    assert!(footer(&mut game).contains("Zoom: fit, braille"));
+}
+
+#[test]
+fn keypad_diagram() {
+    let mut held = [false; 16];
+    held[0x0] = true;
+    let diagram = KeypadDiagram::new(Keymap::TwoPlayer).held(held);
+    let area = Rect::new(0, 0, keypad::WIDTH, 5);
+    let mut buf = Buffer::empty(area);
+    diagram.render(area, &mut buf);
+    let rows = rows(&buf);
+    assert_eq!(rows[0].trim_end(), "1 2 │ 8 9");
+    assert_eq!(rows[3].trim_end(), "z x │ , .");
+    assert_eq!(rows[4].trim_end(), "P1   P2");
+    // Keypad 0 is typed with x.
+    assert!(buf[(2, 3)].modifier.contains(Modifier::REVERSED));
+    assert_eq!(buf[(8, 3)].fg, Color::Magenta);
+
+    assert_eq!(diagram.key_at(area, Position::new(2, 3)), Some(0x0));
+    assert_eq!(diagram.key_at(area, Position::new(6, 0)), Some(0x3));
+    assert_eq!(diagram.key_at(area, Position::new(8, 0)), Some(0xC));
+    assert_eq!(diagram.key_at(area, Position::new(4, 0)), None);
+}
+
+#[test]
+fn pastes_hex_roms() {
+    let (mut game, mut app_state) = game("paste");
+    // CLS; JP 202
+    game.handle_event(Event::Paste("0200: 00E0 1202".into()), &mut app_state)
+        .unwrap();
+    assert_eq!(app_state.rom, Some(RomSource::Pasted));
+    game.run_frames(1, &mut app_state);
+    assert!(!drawn(&mut game));
+
+    let hex: Vec<String> = ROM.iter().map(|byte| format!("0x{byte:02X}")).collect();
+    game.handle_event(Event::Paste(hex.join(", ")), &mut app_state)
+        .unwrap();
+    game.run_frames(1, &mut app_state);
+    assert!(drawn(&mut game));
+
+    game.handle_event(Event::Paste("not a rom".into()), &mut app_state)
+        .unwrap();
+    assert!(
+        draw(&mut game)
+            .iter()
+            .any(|row| row.contains("Pasted text: Not hex: not"))
+    );
+    assert!(drawn(&mut game));
+}
+
+#[test]
+fn splash_runs_before_the_first_screen() {
+    let mut app_state = AppState {
+        screen: Screen::Splash,
+        ..AppState::default()
+    };
+    let mut splash = SplashScreen::new(Screen::Library);
+    for _ in 0..SPLASH_FRAMES {
+        assert!(matches!(app_state.screen, Screen::Splash));
+        splash.tick(&mut app_state);
+    }
+    assert!(matches!(app_state.screen, Screen::Library));
+    assert_eq!(splash.error(), None);
+
+    let mut terminal = Terminal::new(TestBackend::new(80, 24)).unwrap();
+    terminal.draw(|frame| splash.draw(frame)).unwrap();
+    let rows = rows(terminal.backend().buffer());
+    assert!(
+        rows.iter()
+            .any(|row| row.contains('▀') || row.contains('█'))
+    );
+    assert!(rows.iter().any(|row| row.contains("Press any key to skip")));
+}
@@ -0,0 +1,27 @@
+//! The `oxid8-wasm` bindings, run with `wasm-pack test --node
+//! oxid8-tests`.
+
+#![cfg(target_arch = "wasm32")]
+
+use oxid8_tests::{ROM, lit};
+use oxid8_wasm::{Emu, Framebuffer};
+use wasm_bindgen_test::wasm_bindgen_test;
+
+#[wasm_bindgen_test]
+fn draws_rom() {
+    let mut emu = Emu::new();
+    emu.load_font();
+    emu.load_rom_bytes(&ROM);
+    for _ in 0..3 {
+        emu.run_cycle().unwrap();
+    }
+    emu.draw_frame();
+
+    let width = Framebuffer::width();
+    assert_eq!(Framebuffer::area(), width * Framebuffer::height());
+    // One byte a pixel, as JavaScript reads it from the pointer.
+    let pixels = unsafe { std::slice::from_raw_parts(emu.frame.as_ptr(), Framebuffer::area()) };
+    for (i, &pixel) in pixels.iter().enumerate() {
+        assert_eq!(pixel != 0, lit(i % width, i / width), "pixel {i}");
+    }
+}
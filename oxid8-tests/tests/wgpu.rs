@@ -0,0 +1,148 @@
+//! The windowed frontend's screen texture, filled on wgpu's fallback
+//! adapter and read back. Skipped if there is no adapter, and the
+//! compute path if it can't run compute shaders. Also the emulation
+//! thread of `--threaded`, which needs no adapter.
+
+use std::time::{Duration, Instant};
+
+use oxid8_core::{Oxid8, SCREEN_AREA, SCREEN_HEIGHT, SCREEN_WIDTH, clock::CatchUp, color::Rgba};
+use oxid8_tests::{ROM, lit};
+use oxid8_wgpu::{
+    texture::Texture,
+    threaded::{EmuThread, SharedEmu},
+    unpack::Unpack,
+};
+
+fn device() -> Option<(wgpu::Adapter, wgpu::Device, wgpu::Queue)> {
+    let instance = wgpu::Instance::default();
+    let adapter = pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
+        force_fallback_adapter: true,
+        ..Default::default()
+    }))
+    .ok()?;
+    let (device, queue) =
+        pollster::block_on(adapter.request_device(&wgpu::DeviceDescriptor::default())).ok()?;
+    Some((adapter, device, queue))
+}
+
+/// The screen after [`ROM`] has drawn.
+fn screen() -> [bool; SCREEN_AREA] {
+    let mut emu = Oxid8::new();
+    emu.load_font();
+    emu.load_rom_bytes(&ROM).unwrap();
+    emu.next_frame().unwrap();
+    *emu.screen_ref()
+}
+
+/// Copies the texels of `texture` out of it.
+fn read_back(device: &wgpu::Device, queue: &wgpu::Queue, texture: &Texture) -> Vec<u8> {
+    let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: None,
+        size: (4 * SCREEN_AREA) as u64,
+        usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    });
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+    // Rows are 256 bytes, as copies require.
+    encoder.copy_texture_to_buffer(
+        texture.texture.as_image_copy(),
+        wgpu::TexelCopyBufferInfo {
+            buffer: &buffer,
+            layout: wgpu::TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some(4 * SCREEN_WIDTH as u32),
+                rows_per_image: Some(SCREEN_HEIGHT as u32),
+            },
+        },
+        texture.texture.size(),
+    );
+    queue.submit([encoder.finish()]);
+    buffer
+        .slice(..)
+        .map_async(wgpu::MapMode::Read, |result| result.unwrap());
+    device.poll(wgpu::PollType::Wait).unwrap();
+    buffer.slice(..).get_mapped_range().to_vec()
+}
+
+fn check(device: &wgpu::Device, queue: &wgpu::Queue, mut texture: Texture) {
+    let red = Rgba::rgb(255, 0, 0);
+    texture.set_colors(Some(red), None);
+    texture.update(queue, &screen());
+    let texels = read_back(device, queue, &texture);
+    for (i, texel) in texels.chunks_exact(4).enumerate() {
+        let expected = match lit(i % SCREEN_WIDTH, i / SCREEN_WIDTH) {
+            true => red.to_bytes(),
+            false => Rgba::BLACK.to_bytes(),
+        };
+        assert_eq!(texel, expected, "texel {i}");
+    }
+}
+
+#[test]
+fn texture() {
+    let Some((adapter, device, queue)) = device() else {
+        eprintln!("No adapter, skipping");
+        return;
+    };
+    check(&device, &queue, Texture::new(&device, None).unwrap());
+    match Unpack::new(&device, &adapter) {
+        Some(unpack) => {
+            let texture = Texture::new(&device, Some(unpack)).unwrap();
+            assert!(texture.is_unpacked_on_gpu());
+            check(&device, &queue, texture);
+        }
+        None => eprintln!("No compute shaders, skipping the compute path"),
+    }
+}
+
+#[test]
+fn emulation_thread() {
+    let mut emu = Oxid8::boxed();
+    emu.load_font();
+    emu.load_rom_bytes(&ROM).unwrap();
+    let emu = SharedEmu::new(emu);
+    let mut runner = EmuThread::spawn(emu.clone(), CatchUp::Clamp(4)).unwrap();
+    assert_eq!(runner.screen(), [false; SCREEN_AREA]);
+
+    // A frame is due every 1/60th of a second.
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while !runner.update() {
+        assert!(Instant::now() < deadline, "no frame emulated");
+        std::thread::sleep(Duration::from_millis(1));
+    }
+    assert_eq!(runner.screen(), screen());
+
+    // Pausing waits out the frames being emulated, which are still
+    // handed over, and none are emulated after.
+    runner.set_paused(true);
+    let emulated = runner.emulated();
+    runner.update();
+    assert!(!runner.update());
+    assert_eq!(runner.emulated(), emulated);
+    // Dropping stops the thread, the machine stays usable.
+    drop(runner);
+    assert!(emu.lock().screen_ref().iter().any(|&lit| lit));
+}
+
+#[test]
+fn emulation_thread_taps() {
+    let mut emu = Oxid8::boxed();
+    // Wait for a key into V0, then loop.
+    emu.load_rom_bytes(&[0xF0, 0x0A, 0x12, 0x02]).unwrap();
+    let emu = SharedEmu::new(emu);
+    let runner = EmuThread::spawn(emu.clone(), CatchUp::Clamp(4)).unwrap();
+
+    // Pressed and released before the next frame, still seen by it.
+    runner.key(0x7, true);
+    runner.key(0x7, false);
+    let deadline = Instant::now() + Duration::from_secs(5);
+    loop {
+        let state = emu.lock().save_state();
+        if state.pc == 0x202 {
+            assert_eq!(state.v_reg[0], 0x7);
+            break;
+        }
+        assert!(Instant::now() < deadline, "tap not seen");
+        std::thread::sleep(Duration::from_millis(1));
+    }
+}
@@ -163,8 +163,7 @@ init().then((wasm) => {
     }
 
     loadROM_(rom_data) {
-      this.core.load_rom_as_bytes(rom_data);
-      console.log(rom_data);
+      this.core.load_rom_bytes(rom_data);
     }
 
     raf_() {
@@ -1,12 +1,6 @@
 use oxid8_core::{Oxid8, SCREEN_AREA, SCREEN_HEIGHT, SCREEN_WIDTH};
 use wasm_bindgen::prelude::*;
 
-#[wasm_bindgen]
-extern "C" {
-    #[wasm_bindgen(js_namespace = console)]
-    fn log(s: &str);
-}
-
 #[wasm_bindgen]
 #[derive(Clone, Copy)]
 pub struct Framebuffer {
@@ -52,7 +46,7 @@ impl Framebuffer {
 #[derive(Default)]
 pub struct Emu {
     pub frame: Framebuffer,
-    core: Oxid8,
+    core: Box<Oxid8>,
 }
 
 #[wasm_bindgen]
@@ -101,8 +95,7 @@ impl Emu {
 
     /// Instruct the interpreter to load a rom from filename.
     // WARN: ignoring `Result`
-    pub fn load_rom_as_bytes(&mut self, rom_data: &[u8]) {
-        let _ = self.core.load_rom_as_bytes(rom_data);
-        log(&format!("{:?}", rom_data));
+    pub fn load_rom_bytes(&mut self, rom_data: &[u8]) {
+        let _ = self.core.load_rom_bytes(rom_data);
     }
 }
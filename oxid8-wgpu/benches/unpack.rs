@@ -0,0 +1,58 @@
+//! Screen upload benchmarks.
+//!
+//! Compares filling the screen texture with RGBA from the CPU against
+//! uploading the packed screen and expanding it in a compute pass, see
+//! `unpack`. Each update waits for the GPU to finish, so the upload and
+//! the pass are both counted. Skipped without an adapter, and the
+//! compute path without compute shaders.
+//!
+//! Run with `cargo bench -p oxid8-wgpu --bench unpack`.
+
+use criterion::{Criterion, criterion_group, criterion_main};
+use oxid8_core::SCREEN_AREA;
+use oxid8_wgpu::{texture::Texture, unpack::Unpack};
+use std::hint::black_box;
+
+fn device() -> Option<(wgpu::Adapter, wgpu::Device, wgpu::Queue)> {
+    let instance = wgpu::Instance::default();
+    let adapter =
+        pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions::default()))
+            .ok()?;
+    let (device, queue) =
+        pollster::block_on(adapter.request_device(&wgpu::DeviceDescriptor::default())).ok()?;
+    Some((adapter, device, queue))
+}
+
+fn upload(c: &mut Criterion) {
+    let Some((adapter, device, queue)) = device() else {
+        eprintln!("No adapter, skipping");
+        return;
+    };
+    // Every other frame is a checkerboard, so every pixel changes.
+    let screens = [
+        [false; SCREEN_AREA],
+        std::array::from_fn(|i| (i + i / 64) % 2 == 0),
+    ];
+
+    let mut group = c.benchmark_group("upload");
+    let mut bench = |name, texture: Texture| {
+        let mut frame = 0;
+        group.bench_function(name, |b| {
+            b.iter(|| {
+                frame += 1;
+                texture.update(&queue, black_box(&screens[frame % 2]));
+                queue.submit([]);
+                device.poll(wgpu::PollType::Wait).unwrap();
+            })
+        });
+    };
+    bench("CPU", Texture::new(&device, None).unwrap());
+    match Unpack::new(&device, &adapter) {
+        Some(unpack) => bench("Compute", Texture::new(&device, Some(unpack)).unwrap()),
+        None => eprintln!("No compute shaders, skipping the compute path"),
+    }
+    group.finish();
+}
+
+criterion_group!(benches, upload);
+criterion_main!(benches);
@@ -6,13 +6,37 @@ use std::sync::Arc;
 
 #[cfg(not(target_arch = "wasm32"))]
 use crate::Config;
+#[cfg(all(feature = "debugger", not(target_arch = "wasm32")))]
+use crate::debugger::DebuggerWindow;
+#[cfg(target_arch = "wasm32")]
+use crate::web_keys;
 use crate::{
     event::{RomSource, UserEvent},
+    magnifier::Magnifier,
+    overlay::{FpsCounter, ProfileText},
+    slow_draw::SlowDrawMode,
+    states,
+    threaded::{EmuThread, SharedEmu},
     wgpu_context::WgpuContext,
 };
 
-use oxid8_core::Oxid8;
-use web_time::{Duration, Instant};
+use oxid8_core::{
+    Oxid8, TimerRate,
+    builtin::{self, Splash},
+    clock::{CatchUp, FrameClock, MAX_FRAMES_DUE},
+    config::GameConfig,
+    filter::FilterChain,
+    flash::FlashFilter,
+    frontend::KeyAssist,
+    hotkeys::{Action, Hotkeys, Key},
+    keylog::{KeyAction, KeyLog},
+    keymap::Keymap,
+    osd::{MESSAGE_FRAMES, Osd},
+    palette,
+    savestate::SaveState,
+    speedrun::SpeedrunTimer,
+};
+use web_time::{Instant, SystemTime, UNIX_EPOCH};
 use winit::{
     application::ApplicationHandler,
     event::*,
@@ -24,50 +48,725 @@ use winit::{
 #[cfg(target_arch = "wasm32")]
 use wasm_bindgen::prelude::*;
 
+/// Text field savestates are pasted into and copied from.
+#[cfg(target_arch = "wasm32")]
+const STATE_ID: &str = "state";
+
+/// Text field ROMs written as hex are pasted into.
+#[cfg(target_arch = "wasm32")]
+const HEX_ID: &str = "hex";
+
+/// Savestate slot Page Up and Page Down save to and load from.
+const QUICK_SLOT: u8 = 0;
+
+/// The actions and their default keys, in the order the help overlay
+/// lists them. Natively they can be rebound in `hotkeys.toml`, see
+/// `oxid8_core::hotkeys`.
+const HOTKEYS: &[(Key, Action)] = &[
+    (Key::Char('/'), Action::Help),
+    #[cfg(not(target_arch = "wasm32"))]
+    (Key::F(1), Action::Debugger),
+    (Key::Char('-'), Action::Slower),
+    (Key::Char('='), Action::Faster),
+    (Key::Char('p'), Action::Pause),
+    (Key::Backspace, Action::Reset),
+    (Key::PageUp, Action::SaveState),
+    (Key::PageDown, Action::LoadState),
+    #[cfg(not(target_arch = "wasm32"))]
+    (Key::F(2), Action::SaveSettings),
+    (Key::F(3), Action::TimerRate),
+    (Key::F(4), Action::Keymap),
+    (Key::F(5), Action::Rotate),
+    (Key::F(6), Action::Magnifier),
+    (Key::F(7), Action::Fps),
+    (Key::F(8), Action::Keypad),
+    (Key::F(9), Action::Profile),
+    #[cfg(target_arch = "wasm32")]
+    (Key::F(10), Action::CopyState),
+    #[cfg(not(target_arch = "wasm32"))]
+    (Key::F(10), Action::Colors),
+    (Key::Char('\\'), Action::CyclePalette),
+    (Key::F(11), Action::Timer),
+    #[cfg(not(target_arch = "wasm32"))]
+    (Key::F(12), Action::Screenshot),
+    (Key::Char('`'), Action::KeyLog),
+];
+
+/// Size of a CHIP-8 pixel in screenshots.
+#[cfg(not(target_arch = "wasm32"))]
+const SCREENSHOT_SCALE: usize = 8;
+
+/// Button that runs the built-in controls test.
+#[cfg(target_arch = "wasm32")]
+const CONTROLS_ID: &str = "controls";
+
+/// Browsers stop drawing hidden tabs, so don't fast forward when one
+/// is shown again.
+#[cfg(target_arch = "wasm32")]
+const CATCH_UP: CatchUp = CatchUp::Drop(MAX_FRAMES_DUE);
+#[cfg(not(target_arch = "wasm32"))]
+const CATCH_UP: CatchUp = CatchUp::Clamp(MAX_FRAMES_DUE);
+
 /// The app is initialized in `Suspended` state and when a rom is
 /// loaded, the app is swapped to `Resumed` state. The app will
 /// remain suspended at least until the Wgpu context is created.
 pub enum State {
     Suspended,
     Resumed {
-        emu: Oxid8,
-        last_frame: Option<Instant>,
+        /// Locked for each use, the emulation thread runs it between.
+        emu: SharedEmu,
+        /// Emulates on its own thread, with `--threaded`.
+        runner: Option<Box<EmuThread>>,
+        /// When the rom was loaded, the frame clock counts from it.
+        started: Instant,
+        clock: Box<FrameClock>,
+        /// Overrides saved for the loaded rom.
+        config: Box<GameConfig>,
+        magnifier: Option<Magnifier>,
+        /// Feedback for hotkeys drawn over the screen.
+        osd: Box<Osd>,
+        fps: Box<FpsCounter>,
+        show_fps: bool,
+        show_keypad: bool,
+        /// Frames left to show the keypad after switching keymaps.
+        legend: u32,
+        /// Time emulated since the rom was loaded.
+        timer: Box<SpeedrunTimer>,
+        show_timer: bool,
+        /// Keys for the keypad and hotkeys, from the keymap and hotkeys
+        /// in use.
+        show_help: bool,
+        /// Paused with the pause hotkey, frames due are skipped.
+        paused: bool,
+        /// The machine as the rom was loaded, for the reset hotkey.
+        boot: Box<SaveState>,
+        /// Goals written for the rom, announced with the OSD when met.
+        #[cfg(feature = "debugger")]
+        goals: Box<oxid8_core::goals::Goals>,
+        /// Configuration overlay, cached between frames.
+        profile: Option<Box<ProfileText>>,
+        /// Raw key events shown beside the keypad state.
+        key_log: Option<Box<KeyLog>>,
+        /// Sprites drawn a bit at a time, toggled from the debugger.
+        slow_draw: Option<Box<SlowDrawMode>>,
+        /// Sticky keys and minimum hold.
+        assist: Box<KeyAssist>,
+        /// Damps flashing, with `--reduce-flashes`.
+        flashes: Option<Box<FlashFilter>>,
+        /// Post-processing of the screen, with `--filter`.
+        filters: Box<FilterChain>,
+        /// What RAM holds, for the debugger's memory map.
+        #[cfg(not(target_arch = "wasm32"))]
+        memory: Box<oxid8_core::memmap::MemoryMap>,
+        /// Color editor, keys go to it while it is open.
+        #[cfg(not(target_arch = "wasm32"))]
+        colors: Option<Box<palette::PaletteEditor>>,
     },
 }
 
+/// The splash shown at startup, see `oxid8_core::builtin::Splash`.
+/// Any key skips it.
+struct SplashRun {
+    splash: Splash,
+    /// When the first frame was drawn.
+    started: Option<Instant>,
+    clock: FrameClock,
+}
+
+impl SplashRun {
+    fn new() -> Self {
+        Self {
+            splash: Splash::new(env!("CARGO_PKG_VERSION")),
+            started: None,
+            clock: FrameClock::default(),
+        }
+    }
+
+    /// Runs the frames due, returns true once done or failed.
+    fn frames(&mut self) -> bool {
+        let started = *self.started.get_or_insert_with(Instant::now);
+        for _ in 0..self.clock.tick(started.elapsed()).timers {
+            if let Err(err) = self.splash.frame() {
+                log::error!("Self test failed: {err}");
+                return true;
+            }
+        }
+        self.splash.is_done()
+    }
+}
+
+/// Whether to show the splash, unless `splash = false` is in
+/// `defaults.toml`.
+#[cfg(not(target_arch = "wasm32"))]
+fn show_splash() -> bool {
+    game_configs()
+        .and_then(|store| store.load_defaults().ok().flatten())
+        .and_then(|defaults| defaults.splash)
+        .unwrap_or(true)
+}
+
+/// Whether to show the splash, always on the web.
+#[cfg(target_arch = "wasm32")]
+fn show_splash() -> bool {
+    true
+}
+
+/// Per-game overrides shared with the terminal frontends.
+#[cfg(not(target_arch = "wasm32"))]
+fn game_configs() -> Option<oxid8_core::config::ConfigStore> {
+    dirs::config_dir()
+        .map(|dir| oxid8_core::config::ConfigStore::new(dir.join("oxid8").join("games")))
+}
+
 impl State {
-    /// Handle user input key.
-    pub fn handle_key(&mut self, key_code: KeyCode, val: bool) {
-        use KeyCode::*;
-
-        if let State::Resumed { emu, .. } = self {
-            /*
-             * 1 2 3 C
-             * 4 5 6 D
-             * 7 8 9 E
-             * A 0 B f
-             */
-            match key_code {
-                Digit1 => emu.set_key(0x1, val),
-                Digit2 => emu.set_key(0x2, val),
-                Digit3 => emu.set_key(0x3, val),
-                Digit4 => emu.set_key(0xC, val),
-                KeyQ => emu.set_key(0x4, val),
-                KeyW => emu.set_key(0x5, val),
-                KeyE => emu.set_key(0x6, val),
-                KeyR => emu.set_key(0xD, val),
-                KeyA => emu.set_key(0x7, val),
-                KeyS => emu.set_key(0x8, val),
-                KeyD => emu.set_key(0x9, val),
-                KeyF => emu.set_key(0xE, val),
-                KeyZ => emu.set_key(0xA, val),
-                KeyX => emu.set_key(0x0, val),
-                KeyC => emu.set_key(0xB, val),
-                KeyV => emu.set_key(0xF, val),
+    /// Run the action of a hotkey, see [`HOTKEYS`].
+    pub fn handle_hotkey(&mut self, action: Action) {
+        if let State::Resumed {
+            emu,
+            config,
+            magnifier,
+            osd,
+            show_fps,
+            show_keypad,
+            legend,
+            timer,
+            show_timer,
+            show_help,
+            paused,
+            boot,
+            #[cfg(feature = "debugger")]
+            goals,
+            profile,
+            key_log,
+            assist,
+            #[cfg(not(target_arch = "wasm32"))]
+            memory,
+            #[cfg(not(target_arch = "wasm32"))]
+            colors,
+            ..
+        } = self
+        {
+            match action {
+                Action::Help => *show_help = !*show_help,
+                Action::Slower => {
+                    let mut emu = emu.lock();
+                    let cycles = emu.cycles_per_frame().saturating_sub(1).max(1);
+                    emu.set_cycles_per_frame(cycles);
+                    osd.show(format!("Speed {}", emu.cycles_per_frame()));
+                }
+                Action::Faster => {
+                    let mut emu = emu.lock();
+                    let cycles = emu.cycles_per_frame() + 1;
+                    emu.set_cycles_per_frame(cycles);
+                    osd.show(format!("Speed {}", emu.cycles_per_frame()));
+                }
+                Action::Pause => {
+                    *paused = !*paused;
+                    osd.show(if *paused { "Paused" } else { "Resumed" });
+                }
+                Action::Reset => {
+                    // Settings changed since loading are kept.
+                    let mut emu = emu.lock();
+                    emu.restart(boot);
+                    **assist = KeyAssist::new(assist.assist());
+                    #[cfg(not(target_arch = "wasm32"))]
+                    {
+                        **memory = oxid8_core::memmap::MemoryMap::new(&emu);
+                    }
+                    timer.reset();
+                    #[cfg(feature = "debugger")]
+                    goals.reset();
+                    osd.show("Reset");
+                }
+                Action::TimerRate => {
+                    let mut emu = emu.lock();
+                    let rate = match emu.timer_rate() {
+                        TimerRate::Ntsc => TimerRate::Pal,
+                        TimerRate::Pal => TimerRate::Ntsc,
+                    };
+                    emu.set_timer_rate(rate);
+                    osd.show(format!("{}Hz", emu.timer_rate().hz()));
+                }
+                Action::Keymap => {
+                    let keymap = config.keymap.unwrap_or_default().toggle();
+                    log::info!("Keymap: {keymap:?}");
+                    config.keymap = Some(keymap);
+                    #[cfg(target_arch = "wasm32")]
+                    web_keys::set_keymap(keymap);
+                    emu.lock().clear_keys();
+                    osd.show(match keymap {
+                        Keymap::Standard => "1 player",
+                        Keymap::TwoPlayer => "2 players",
+                    });
+                    // The new keys are shown as long as the message.
+                    *legend = MESSAGE_FRAMES;
+                }
+                Action::Rotate => {
+                    let rotation = config.rotation.unwrap_or_default().next();
+                    config.rotation = Some(rotation);
+                    osd.show(format!("Rotate {rotation}"));
+                }
+                Action::CyclePalette => {
+                    let preset = palette::next_preset(config);
+                    preset.apply(config);
+                    osd.show(format!("Colors: {}", preset.name));
+                }
+                #[cfg(not(target_arch = "wasm32"))]
+                Action::Colors => {
+                    *colors = Some(Box::new(palette::PaletteEditor::new(config)));
+                    emu.lock().clear_keys();
+                }
+                Action::Magnifier => *magnifier = Magnifier::cycle(*magnifier),
+                Action::Fps => *show_fps = !*show_fps,
+                Action::Keypad => *show_keypad = !*show_keypad,
+                Action::Timer => *show_timer = !*show_timer,
+                Action::Profile => {
+                    *profile = match profile {
+                        Some(_) => None,
+                        None => Some(Box::default()),
+                    }
+                }
+                Action::KeyLog => {
+                    *key_log = match key_log {
+                        Some(_) => None,
+                        None => Some(Box::default()),
+                    }
+                }
+                Action::SaveState => self.save_slot(QUICK_SLOT),
+                Action::LoadState => self.load_slot(QUICK_SLOT),
+                #[cfg(not(target_arch = "wasm32"))]
+                Action::SaveSettings => self.save_config(),
+                #[cfg(not(target_arch = "wasm32"))]
+                Action::Screenshot => self.screenshot(),
+                #[cfg(target_arch = "wasm32")]
+                Action::CopyState => self.export_state(),
                 _ => (),
             }
         }
     }
+
+    /// Move the magnifier, if shown, with the arrow keys.
+    pub fn move_magnifier(&mut self, key_code: KeyCode) {
+        if let State::Resumed { magnifier, .. } = self {
+            let (dx, dy) = match key_code {
+                KeyCode::ArrowLeft => (-1, 0),
+                KeyCode::ArrowRight => (1, 0),
+                KeyCode::ArrowUp => (0, -1),
+                KeyCode::ArrowDown => (0, 1),
+                _ => return,
+            };
+            magnifier.iter_mut().for_each(|m| m.move_by(dx, dy));
+        }
+    }
+
+    /// Edit the colors if the editor is open: arrows pick a color and
+    /// channel, `+` and `-` change it, Page Up and Page Down by 16, Tab
+    /// cycles the presets, Enter saves for the rom and `D` for every
+    /// game. Returns true if the key went to the editor.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn colors_key(&mut self, key_code: KeyCode) -> bool {
+        let State::Resumed {
+            emu,
+            config,
+            osd,
+            colors: Some(editor),
+            ..
+        } = self
+        else {
+            return false;
+        };
+        let save = match key_code {
+            KeyCode::Escape => {
+                editor.cancel(config);
+                None
+            }
+            KeyCode::Enter => match emu.lock().rom_hash() {
+                Some(hash) => Some(Some(hash.to_owned())),
+                None => return true,
+            },
+            KeyCode::KeyD => Some(None),
+            _ => {
+                match key_code {
+                    KeyCode::ArrowUp => editor.select_slot(-1),
+                    KeyCode::ArrowDown => editor.select_slot(1),
+                    KeyCode::ArrowLeft => editor.select_channel(-1),
+                    KeyCode::ArrowRight => editor.select_channel(1),
+                    KeyCode::Equal => editor.adjust(config, 1),
+                    KeyCode::Minus => editor.adjust(config, -1),
+                    KeyCode::PageUp => editor.adjust(config, 16),
+                    KeyCode::PageDown => editor.adjust(config, -16),
+                    KeyCode::Tab => palette::next_preset(config).apply(config),
+                    _ => (),
+                }
+                return true;
+            }
+        };
+        if let (Some(hash), Some(store)) = (save, game_configs()) {
+            match store.save_colors(hash.as_deref(), config) {
+                Ok(()) => osd.show("Saved colors"),
+                Err(err) => {
+                    log::error!("Failed to save colors: {err}");
+                    osd.show("Save failed");
+                }
+            }
+        }
+        if let State::Resumed { colors, .. } = self {
+            *colors = None;
+        }
+        true
+    }
+
+    /// Save the current settings as overrides for the loaded rom.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn save_config(&mut self) {
+        if let State::Resumed {
+            emu, config, osd, ..
+        } = self
+        {
+            let emu = emu.lock();
+            **config = GameConfig {
+                foreground: config.foreground,
+                background: config.background,
+                border: config.border,
+                keymap: config.keymap,
+                rotation: config.rotation,
+                ..GameConfig::capture(&emu)
+            };
+            if let (Some(store), Some(hash)) = (game_configs(), emu.rom_hash()) {
+                match store.save(hash, config) {
+                    Ok(()) => {
+                        log::info!("Saved game settings to {:?}", store.path(hash));
+                        osd.show("Saved");
+                    }
+                    Err(err) => {
+                        log::error!("Failed to save game settings: {err}");
+                        osd.show("Save failed");
+                    }
+                }
+            }
+        }
+    }
+
+    /// Save the screen as a PNG.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn screenshot(&mut self) {
+        if let State::Resumed {
+            emu,
+            osd,
+            config,
+            filters,
+            ..
+        } = self
+        {
+            let emu = emu.lock();
+            let image = match filters.is_empty() {
+                true => {
+                    emu.render_colored(SCREENSHOT_SCALE, false, palette::screen_palette(config))
+                }
+                false => oxid8_core::export::render_frame(filters.frame(), SCREENSHOT_SCALE, false),
+            };
+            match save_screenshot(&emu, &image) {
+                Ok(path) => {
+                    log::info!("Saved screenshot to {path:?}");
+                    osd.show("Screenshot saved");
+                }
+                Err(err) => {
+                    log::error!("Failed to save screenshot: {err}");
+                    osd.show("Screenshot failed");
+                }
+            }
+        }
+    }
+
+    /// Save the machine state in `slot` for the loaded rom.
+    fn save_slot(&mut self, slot: u8) {
+        if let State::Resumed { emu, osd, .. } = self {
+            let emu = emu.lock();
+            let Some(mut store) = states::store() else {
+                osd.show("No savestate storage");
+                return;
+            };
+            let now = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default();
+            match store.save(slot, &emu.save_state(), now) {
+                Ok(()) => osd.show(format!("Saved state {slot}")),
+                Err(err) => {
+                    log::error!("Failed to save state {slot}: {err}");
+                    osd.show("Save failed");
+                }
+            }
+        }
+    }
+
+    /// Restore the machine state saved in `slot` for the loaded rom.
+    fn load_slot(&mut self, slot: u8) {
+        if let State::Resumed { emu, osd, .. } = self {
+            let mut emu = emu.lock();
+            let (Some(store), Some(hash)) = (states::store(), emu.rom_hash()) else {
+                osd.show("No savestate storage");
+                return;
+            };
+            match store.load(hash, slot) {
+                Ok(Some(state)) => {
+                    emu.restore_state(&state);
+                    osd.show(format!("Loaded state {slot}"));
+                }
+                Ok(None) => osd.show(format!("No state in slot {slot}")),
+                Err(err) => {
+                    log::warn!("Failed to load state {slot}: {err}");
+                    osd.show("Load failed");
+                }
+            }
+        }
+    }
+
+    /// Copy the machine state to the clipboard as base64 text, and put
+    /// it in the savestate field in case the clipboard is blocked.
+    #[cfg(target_arch = "wasm32")]
+    fn export_state(&mut self) {
+        use base64::{Engine, engine::general_purpose::STANDARD};
+        use wasm_bindgen::JsCast;
+
+        if let State::Resumed { emu, osd, .. } = self {
+            let text = STANDARD.encode(emu.lock().save_state().to_bytes());
+            let Some(window) = web_sys::window() else {
+                return;
+            };
+            if let Some(field) = window
+                .document()
+                .and_then(|document| document.get_element_by_id(STATE_ID))
+                .and_then(|element| element.dyn_into::<web_sys::HtmlInputElement>().ok())
+            {
+                field.set_value(&text);
+            }
+            // Missing outside secure contexts and rejected without
+            // permission, the field still has the text.
+            let clipboard = window.navigator().clipboard();
+            if !clipboard.is_undefined() {
+                let _ = clipboard.write_text(&text);
+            }
+            log::info!("Exported savestate: {} characters", text.len());
+            osd.show("State copied");
+        }
+    }
+
+    /// Restore a savestate pasted as text, see `export_state`. States
+    /// for another rom are rejected.
+    #[cfg(target_arch = "wasm32")]
+    pub fn import_state(&mut self, text: &str) {
+        use base64::{Engine, engine::general_purpose::STANDARD};
+        use oxid8_core::savestate::SaveState;
+
+        if let State::Resumed { emu, osd, .. } = self {
+            let mut emu = emu.lock();
+            let state = STANDARD
+                .decode(text.trim())
+                .map_err(|err| err.to_string())
+                .and_then(|bytes| SaveState::from_bytes(&bytes));
+            match state {
+                Ok(state) if state.rom_hash.as_deref() != emu.rom_hash() => {
+                    osd.show("State is for another rom");
+                }
+                Ok(state) => {
+                    emu.restore_state(&state);
+                    osd.show("State loaded");
+                }
+                Err(err) => {
+                    log::warn!("Failed to import savestate: {err}");
+                    osd.show("Not a savestate");
+                }
+            }
+        }
+    }
+
+    /// Handle user input key, mapped by the keymap of the loaded rom.
+    /// See `oxid8_core::keymap` for the layouts.
+    pub fn handle_key(&mut self, key_code: KeyCode, val: bool) {
+        if let State::Resumed {
+            emu,
+            config,
+            assist,
+            runner,
+            slow_draw,
+            ..
+        } = self
+            && let Some(k) =
+                key_char(key_code).and_then(|c| config.keymap.unwrap_or_default().key(c))
+        {
+            let change = match val {
+                true => assist.press(k as usize),
+                false => assist.release(k as usize),
+            };
+            // The thread takes keys as events of its next frame, unless
+            // slow draw has the render thread emulating.
+            match (change, runner.as_deref().filter(|_| slow_draw.is_none())) {
+                (Some(val), Some(runner)) => runner.key(k as usize, val),
+                (Some(val), None) => emu.lock().set_key(k as usize, val),
+                (None, _) => {}
+            }
+        }
+    }
+
+    /// Log a raw key event if the key log is shown.
+    pub fn log_key(&mut self, key_code: KeyCode, action: KeyAction) {
+        if let State::Resumed {
+            config,
+            key_log: Some(log),
+            ..
+        } = self
+        {
+            let mapped = key_char(key_code).and_then(|c| config.keymap.unwrap_or_default().key(c));
+            log.push(format!("{key_code:?}"), action, mapped);
+        }
+    }
+}
+
+/// When the frame being drawn is presented. On the web this is the
+/// requestAnimationFrame timestamp, shared by every callback in a frame
+/// and free of the jitter of when this one ran, so frames are emulated
+/// evenly on 60Hz and 120Hz displays alike. Natively it is now.
+fn present_time() -> Instant {
+    let now = Instant::now();
+    #[cfg(target_arch = "wasm32")]
+    if let Some(window) = web_sys::window()
+        && let (Some(performance), Some(document)) = (window.performance(), window.document())
+        && let Some(frame) = document.timeline().current_time()
+    {
+        // How far into the frame this callback runs.
+        let late = ((performance.now() - frame) / 1000.0).max(0.0);
+        return now
+            .checked_sub(std::time::Duration::from_secs_f64(late))
+            .unwrap_or(now);
+    }
+    now
+}
+
+/// Hotkeys rebound from `<config_dir>/oxid8/hotkeys.toml`, shared with
+/// the terminal frontend.
+#[cfg(not(target_arch = "wasm32"))]
+fn hotkeys() -> Hotkeys {
+    let Some(dir) = dirs::config_dir() else {
+        return Hotkeys::new(HOTKEYS);
+    };
+    Hotkeys::load(dir.join("oxid8").join("hotkeys.toml"), HOTKEYS).unwrap_or_else(|err| {
+        log::error!("Bad hotkeys.toml, using the defaults: {err}");
+        Hotkeys::new(HOTKEYS)
+    })
+}
+
+/// Saves the screen to `<data_dir>/oxid8/screenshots/<hash>-<time>.png`,
+/// named by the start of the rom hash and the seconds since the Unix
+/// epoch.
+#[cfg(not(target_arch = "wasm32"))]
+fn save_screenshot(
+    emu: &Oxid8,
+    image: &oxid8_core::export::RgbaImage,
+) -> std::io::Result<std::path::PathBuf> {
+    use std::{fs, io};
+
+    let dir = dirs::data_dir()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "No data directory"))?
+        .join("oxid8")
+        .join("screenshots");
+    fs::create_dir_all(&dir)?;
+    let hash = emu.rom_hash().map_or("oxid8", |hash| &hash[..8]);
+    let time = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |time| time.as_secs());
+    let path = dir.join(format!("{hash}-{time}.png"));
+
+    image.write_png(io::BufWriter::new(fs::File::create(&path)?))?;
+    Ok(path)
+}
+
+/// The hotkey for a key, by the character it types on a QWERTY
+/// keyboard or its name.
+fn hotkey(key_code: KeyCode) -> Option<Key> {
+    use KeyCode::*;
+
+    Some(match key_code {
+        F1 => Key::F(1),
+        F2 => Key::F(2),
+        F3 => Key::F(3),
+        F4 => Key::F(4),
+        F5 => Key::F(5),
+        F6 => Key::F(6),
+        F7 => Key::F(7),
+        F8 => Key::F(8),
+        F9 => Key::F(9),
+        F10 => Key::F(10),
+        F11 => Key::F(11),
+        F12 => Key::F(12),
+        Escape => Key::Esc,
+        Enter => Key::Enter,
+        Tab => Key::Tab,
+        Backspace => Key::Backspace,
+        Insert => Key::Insert,
+        Delete => Key::Delete,
+        Home => Key::Home,
+        End => Key::End,
+        PageUp => Key::PageUp,
+        PageDown => Key::PageDown,
+        Space => Key::Char(' '),
+        Minus => Key::Char('-'),
+        Equal => Key::Char('='),
+        Backquote => Key::Char('`'),
+        Slash => Key::Char('/'),
+        Backslash => Key::Char('\\'),
+        Semicolon => Key::Char(';'),
+        Quote => Key::Char('\''),
+        BracketLeft => Key::Char('['),
+        BracketRight => Key::Char(']'),
+        Digit0 => Key::Char('0'),
+        Digit5 => Key::Char('5'),
+        Digit6 => Key::Char('6'),
+        Digit7 => Key::Char('7'),
+        KeyB => Key::Char('b'),
+        KeyG => Key::Char('g'),
+        KeyH => Key::Char('h'),
+        KeyJ => Key::Char('j'),
+        KeyM => Key::Char('m'),
+        KeyN => Key::Char('n'),
+        KeyP => Key::Char('p'),
+        KeyT => Key::Char('t'),
+        KeyU => Key::Char('u'),
+        KeyY => Key::Char('y'),
+        _ => return key_char(key_code).map(Key::Char),
+    })
+}
+
+/// The character typed by a key on a QWERTY keyboard, used by keymaps.
+fn key_char(key_code: KeyCode) -> Option<char> {
+    use KeyCode::*;
+
+    Some(match key_code {
+        Digit1 => '1',
+        Digit2 => '2',
+        Digit3 => '3',
+        Digit4 => '4',
+        Digit8 => '8',
+        Digit9 => '9',
+        KeyQ => 'q',
+        KeyW => 'w',
+        KeyE => 'e',
+        KeyR => 'r',
+        KeyI => 'i',
+        KeyO => 'o',
+        KeyA => 'a',
+        KeyS => 's',
+        KeyD => 'd',
+        KeyF => 'f',
+        KeyK => 'k',
+        KeyL => 'l',
+        KeyZ => 'z',
+        KeyX => 'x',
+        KeyC => 'c',
+        KeyV => 'v',
+        Comma => ',',
+        Period => '.',
+        _ => return None,
+    })
 }
 
 /// The
@@ -82,6 +781,19 @@ pub struct App {
     /// Native configuration via command line arguments.
     #[cfg(not(target_arch = "wasm32"))]
     config: Config,
+    /// The rom at `config.rom_path`, read before the window opens.
+    #[cfg(not(target_arch = "wasm32"))]
+    rom: Option<Vec<u8>>,
+    /// Registers, disassembly, and memory in a second window, toggled
+    /// with its hotkey.
+    #[cfg(all(feature = "debugger", not(target_arch = "wasm32")))]
+    debugger: Option<DebuggerWindow>,
+    /// Keys for frontend actions, see [`HOTKEYS`].
+    hotkeys: Hotkeys,
+    /// Shown before the rom, dropped once done.
+    splash: Option<Box<SplashRun>>,
+    /// The rom picked while the splash is shown, loaded after it.
+    pending: Option<RomSource>,
     /// Store the html document for easy access.
     #[cfg(target_arch = "wasm32")]
     document: Option<web_sys::Document>,
@@ -92,6 +804,7 @@ impl App {
     pub fn new(
         event_loop: &EventLoop<UserEvent>,
         #[cfg(not(target_arch = "wasm32"))] config: Config,
+        #[cfg(not(target_arch = "wasm32"))] rom: Option<Vec<u8>>,
     ) -> Self {
         Self {
             proxy: event_loop.create_proxy(),
@@ -99,46 +812,247 @@ impl App {
             state: State::Suspended,
             #[cfg(not(target_arch = "wasm32"))]
             config,
+            #[cfg(not(target_arch = "wasm32"))]
+            rom,
+            #[cfg(all(feature = "debugger", not(target_arch = "wasm32")))]
+            debugger: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            hotkeys: hotkeys(),
+            #[cfg(target_arch = "wasm32")]
+            hotkeys: Hotkeys::new(HOTKEYS),
+            splash: show_splash().then(|| Box::new(SplashRun::new())),
+            pending: None,
             #[cfg(target_arch = "wasm32")]
             document: None,
         }
     }
 
+    /// Open the debugger window, or close it if open.
+    #[cfg(all(feature = "debugger", not(target_arch = "wasm32")))]
+    fn toggle_debugger(&mut self, event_loop: &ActiveEventLoop) {
+        if self.debugger.take().is_some() {
+            return;
+        }
+        let Some(ctx) = &self.ctx else {
+            return;
+        };
+        let attributes = Window::default_attributes()
+            .with_title("Oxid-8 Debugger")
+            .with_inner_size(crate::debugger::WINDOW_SIZE);
+        let debugger = event_loop
+            .create_window(attributes)
+            .map_err(anyhow::Error::from)
+            .and_then(|window| DebuggerWindow::new(ctx, Arc::new(window)));
+        match debugger {
+            Ok(debugger) => self.debugger = Some(debugger),
+            Err(err) => log::error!("Failed to open the debugger window: {err}"),
+        }
+    }
+
+    /// Handle an event for the debugger window. Its hotkey and Escape
+    /// close it,
+    /// the up and down arrows select an instruction to inspect, `D`
+    /// toggles slow sprite drawing, and `[` and `]` slow it down and
+    /// speed it up.
+    #[cfg(all(feature = "debugger", not(target_arch = "wasm32")))]
+    fn debugger_event(&mut self, event: WindowEvent) {
+        let Some(debugger) = &mut self.debugger else {
+            return;
+        };
+        let slow_draw = match &mut self.state {
+            State::Resumed { slow_draw, .. } => Some(slow_draw),
+            State::Suspended => None,
+        };
+        match event {
+            WindowEvent::CloseRequested => self.debugger = None,
+            WindowEvent::KeyboardInput {
+                event:
+                    KeyEvent {
+                        physical_key: PhysicalKey::Code(key_code),
+                        state: ElementState::Pressed,
+                        ..
+                    },
+                ..
+            } if key_code == KeyCode::Escape
+                || hotkey(key_code).and_then(|key| self.hotkeys.action(key))
+                    == Some(Action::Debugger) =>
+            {
+                self.debugger = None
+            }
+            WindowEvent::KeyboardInput {
+                event:
+                    KeyEvent {
+                        physical_key: PhysicalKey::Code(key_code),
+                        state: ElementState::Pressed,
+                        ..
+                    },
+                ..
+            } => match (key_code, slow_draw) {
+                (KeyCode::ArrowUp, _) => debugger.select_up(),
+                (KeyCode::ArrowDown, _) => debugger.select_down(),
+                (KeyCode::KeyD, Some(slow_draw)) => {
+                    *slow_draw = match slow_draw {
+                        Some(_) => None,
+                        None => Some(Box::default()),
+                    }
+                }
+                (KeyCode::BracketLeft, Some(Some(slow_draw))) => slow_draw.slower(),
+                (KeyCode::BracketRight, Some(Some(slow_draw))) => slow_draw.faster(),
+                _ => (),
+            },
+            WindowEvent::Resized(size) => debugger.resize(size),
+            WindowEvent::RedrawRequested => match &self.state {
+                State::Resumed {
+                    emu,
+                    slow_draw,
+                    memory,
+                    ..
+                } => debugger.render(Some(&**emu.lock()), Some(memory), slow_draw.as_deref()),
+                State::Suspended => debugger.render(None, None, None),
+            },
+            _ => (),
+        }
+    }
+
+    /// End the splash, loading the rom picked while it was shown.
+    fn end_splash(&mut self) {
+        self.splash = None;
+        if let Some(rom_source) = self.pending.take() {
+            let _ = self.proxy.send_event(UserEvent::RomSelected(rom_source));
+        }
+    }
+
     /// Resume the app when given a rom by creating a new emulator
     /// instance, loading the font, and loading the rom, then set
     /// the app state to Resumed.
     pub fn resume(&mut self, rom_source: RomSource) {
         // WARN: check this implementation
-        if let Some(ctx) = &self.ctx {
-            let mut emu = Oxid8::default();
-            ctx.texture.update(&ctx.queue, emu.screen_ref());
+        let Some(ctx) = &mut self.ctx else {
+            return;
+        };
 
-            emu.load_font();
+        let mut emu = Oxid8::boxed();
+        emu.load_font();
 
-            // WARN: what to do if this fails?
-            match rom_source {
-                // Native
-                #[cfg(not(target_arch = "wasm32"))]
-                RomSource::Path(path) => {
-                    if emu.load_rom(&path).is_ok() {
-                        self.state = State::Resumed {
-                            emu,
-                            last_frame: None,
-                        };
-                    }
-                }
-                // Wasm
-                #[cfg(target_arch = "wasm32")]
-                RomSource::Bytes(bytes) => {
-                    if emu.load_rom_bytes(&bytes).is_ok() {
-                        self.state = State::Resumed {
-                            emu,
-                            last_frame: None,
-                        };
-                        self.focus_canvas();
-                    }
+        // WARN: what to do if this fails?
+        let loaded = match rom_source {
+            RomSource::Bytes(bytes) => emu.load_rom_bytes(&bytes),
+            RomSource::ControlsTest => emu.load_rom_bytes(&builtin::controls_test()),
+        };
+        if loaded.is_err() {
+            return;
+        }
+
+        // Apply the overrides saved for this rom.
+        #[cfg(not(target_arch = "wasm32"))]
+        let config = game_configs()
+            .zip(emu.rom_hash())
+            .and_then(|(store, hash)| store.load_merged(hash).ok().flatten())
+            .unwrap_or_default();
+        #[cfg(target_arch = "wasm32")]
+        let config = GameConfig::default();
+
+        // Goals written for this rom.
+        #[cfg(all(feature = "debugger", not(target_arch = "wasm32")))]
+        let goals = game_configs()
+            .zip(emu.rom_hash())
+            .and_then(|(store, hash)| match store.load_goals(hash) {
+                Ok(goals) => goals,
+                Err(err) => {
+                    log::warn!("Bad goals: {err}");
+                    None
                 }
+            })
+            .unwrap_or_default();
+        #[cfg(all(feature = "debugger", target_arch = "wasm32"))]
+        let goals = oxid8_core::goals::Goals::default();
+
+        // Command line arguments take priority.
+        #[cfg(not(target_arch = "wasm32"))]
+        let config = GameConfig {
+            rotation: self.config.rotation.or(config.rotation),
+            skip_invalid: self
+                .config
+                .skip_invalid
+                .then_some(true)
+                .or(config.skip_invalid),
+            ..config
+        };
+
+        config.apply(&mut emu);
+        ctx.texture.set_colors(config.foreground, config.background);
+        ctx.set_border(config.border);
+        ctx.texture.update(&ctx.queue, emu.screen_ref());
+        let boot = Box::new(emu.save_state());
+        #[cfg(not(target_arch = "wasm32"))]
+        let memory = Box::new(oxid8_core::memmap::MemoryMap::new(&emu));
+
+        let mut clock = Box::new(FrameClock::default());
+        clock.set_catch_up(CATCH_UP);
+
+        let emu = SharedEmu::new(emu);
+        #[cfg(not(target_arch = "wasm32"))]
+        let runner = self
+            .config
+            .threaded
+            .then(|| EmuThread::spawn(emu.clone(), CATCH_UP))
+            .and_then(|spawned| {
+                spawned
+                    .inspect_err(|err| log::error!("Failed to start emulation thread: {err}"))
+                    .ok()
+            })
+            .map(Box::new);
+        #[cfg(target_arch = "wasm32")]
+        let runner = None;
+
+        self.state = State::Resumed {
+            emu,
+            runner,
+            started: Instant::now(),
+            clock,
+            config: Box::new(config),
+            magnifier: None,
+            osd: Box::default(),
+            fps: Box::default(),
+            show_fps: false,
+            show_keypad: false,
+            legend: 0,
+            timer: Box::default(),
+            show_timer: false,
+            show_help: false,
+            paused: false,
+            boot,
+            #[cfg(feature = "debugger")]
+            goals: Box::new(goals),
+            profile: None,
+            key_log: None,
+            slow_draw: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            assist: Box::new(KeyAssist::new(self.config.assist)),
+            #[cfg(target_arch = "wasm32")]
+            assist: Box::default(),
+            #[cfg(not(target_arch = "wasm32"))]
+            flashes: self.config.reduce_flashes.then(Box::default),
+            #[cfg(target_arch = "wasm32")]
+            flashes: None,
+            // Checked when the flag was parsed.
+            #[cfg(not(target_arch = "wasm32"))]
+            filters: Box::new(self.config.filters.parse().unwrap_or_default()),
+            #[cfg(target_arch = "wasm32")]
+            filters: Box::default(),
+            #[cfg(not(target_arch = "wasm32"))]
+            memory,
+            #[cfg(not(target_arch = "wasm32"))]
+            colors: None,
+        };
+
+        #[cfg(target_arch = "wasm32")]
+        {
+            web_keys::set_running(true);
+            if let State::Resumed { config, .. } = &self.state {
+                web_keys::set_keymap(config.keymap.unwrap_or_default());
             }
+            self.focus_canvas();
         }
     }
 
@@ -205,9 +1119,10 @@ impl ApplicationHandler<UserEvent> for App {
             assert!(
                 self.proxy
                     // send the rom path as the event contents
-                    .send_event(UserEvent::RomSelected(RomSource::Path(
-                        self.config.rom_path.clone()
-                    )))
+                    .send_event(UserEvent::RomSelected(match &self.rom {
+                        Some(rom) => RomSource::Bytes(rom.clone()),
+                        None => RomSource::ControlsTest,
+                    }))
                     .is_ok()
             );
 
@@ -238,9 +1153,20 @@ impl ApplicationHandler<UserEvent> for App {
     fn window_event(
         &mut self,
         event_loop: &ActiveEventLoop,
-        _window_id: WindowId,
+        window_id: WindowId,
         event: WindowEvent,
     ) {
+        #[cfg(all(feature = "debugger", not(target_arch = "wasm32")))]
+        if self
+            .debugger
+            .as_ref()
+            .is_some_and(|debugger| debugger.window.id() == window_id)
+        {
+            return self.debugger_event(event);
+        }
+        #[cfg(any(not(feature = "debugger"), target_arch = "wasm32"))]
+        let _ = window_id;
+
         let ctx = match self.ctx.as_mut() {
             Some(canvas) => canvas,
             None => return,
@@ -251,49 +1177,265 @@ impl ApplicationHandler<UserEvent> for App {
                 println!("The close button was pressed; stopping");
                 event_loop.exit();
             }
+            WindowEvent::RedrawRequested if self.splash.is_some() => {
+                let Some(splash) = &mut self.splash else {
+                    return;
+                };
+                let done = splash.frames();
+                ctx.texture.update(&ctx.queue, splash.splash.screen_ref());
+                ctx.overlay.clear();
+                ctx.render();
+                ctx.window.request_redraw();
+                if done {
+                    self.end_splash();
+                }
+            }
             WindowEvent::RedrawRequested => {
                 // Only enter the gameloop if the app is Resumed.
                 if let State::Resumed {
-                    emu, last_frame, ..
+                    emu,
+                    runner,
+                    started,
+                    clock,
+                    config,
+                    magnifier,
+                    osd,
+                    fps,
+                    show_fps,
+                    show_keypad,
+                    legend,
+                    timer,
+                    show_timer,
+                    show_help,
+                    paused,
+                    #[cfg(feature = "debugger")]
+                    goals,
+                    profile,
+                    key_log,
+                    slow_draw,
+                    assist,
+                    flashes,
+                    filters,
+                    #[cfg(not(target_arch = "wasm32"))]
+                    memory,
+                    #[cfg(not(target_arch = "wasm32"))]
+                    colors,
+                    ..
                 } = &mut self.state
                 {
-                    match last_frame {
-                        // 16ms frame time
-                        Some(last) if last.elapsed() >= Duration::from_millis(16) => {
-                            *last_frame = Some(Instant::now());
-                            if emu.next_frame().is_ok() {
-                                // Update texture
-                                ctx.texture.update(&ctx.queue, emu.screen_ref());
+                    ctx.set_rotation(config.rotation.unwrap_or_default());
+                    // Colors preview as they are cycled or edited.
+                    let recolored = ctx.texture.set_colors(config.foreground, config.background);
+                    ctx.set_border(config.border);
+                    ctx.set_magnifier(*magnifier);
+                    // Slow draw steps the machine itself, so the thread
+                    // waits while it is on.
+                    if let Some(runner) = runner.as_deref() {
+                        runner.set_paused(*paused || slow_draw.is_some());
+                    }
+                    let mut threaded = runner.as_deref_mut().filter(|_| slow_draw.is_none());
+                    // Frames at 60Hz or 50Hz whatever the display's rate,
+                    // timed by when this one is presented.
+                    let mut emu = emu.lock();
+                    clock.set_timer_hz(emu.timer_rate().hz());
+                    let mut ran_any = false;
+                    let elapsed = present_time().saturating_duration_since(*started);
+                    let due = clock.tick(elapsed).timers;
+                    // Frames due while paused are skipped. Threaded, the
+                    // newest frame emulated is shown and the clock only
+                    // times keys and messages.
+                    let due = if *paused { 0 } else { due };
+                    let frames = match &mut threaded {
+                        Some(runner) => u32::from(runner.update()),
+                        None => due,
+                    };
+                    for _ in 0..frames {
+                        let listed = match &threaded {
+                            Some(runner) => runner.listed,
+                            None => emu.skipped().entries().len(),
+                        };
+                        let ran = match (threaded.is_some(), slow_draw.as_deref_mut()) {
+                            (true, _) => Ok(()),
+                            (false, Some(slow_draw)) => slow_draw.frame(&mut emu),
+                            (false, None) => emu.next_frame(),
+                        };
+                        let entries = emu.skipped().entries();
+                        for skipped in entries.get(listed..).unwrap_or_default() {
+                            log::warn!("Skipped invalid opcode {skipped}");
+                            osd.show(format!("Skipped invalid opcode {:04X}", skipped.opcode));
+                        }
+                        if let Some(runner) = &mut threaded {
+                            runner.listed = entries.len();
+                        }
+                        if ran.is_ok() {
+                            let screen = match &threaded {
+                                Some(runner) => runner.screen(),
+                                None => *emu.screen_ref(),
+                            };
+                            timer.sync(&emu);
+                            #[cfg(feature = "debugger")]
+                            for goal in goals.check(&emu) {
+                                osd.show(format!("Goal met: {}", goal.name));
+                            }
+                            if let Some(filter) = flashes {
+                                let damping = filter.damping();
+                                filter.frame(&screen);
+                                if filter.damping() && !damping {
+                                    osd.show("Reducing flashes");
+                                }
                             }
+                            if !filters.is_empty() {
+                                filters.process(&screen, ctx.texture.palette());
+                            }
+                            ran_any = true;
+                        }
+                        if threaded.is_none() {
+                            for k in assist.tick() {
+                                emu.set_key(k, false);
+                            }
+                            osd.tick();
+                            *legend = legend.saturating_sub(1);
+                        }
+                    }
+                    if let Some(runner) = &threaded {
+                        for _ in 0..due {
+                            for k in assist.tick() {
+                                runner.key(k, false);
+                            }
+                            osd.tick();
+                            *legend = legend.saturating_sub(1);
                         }
-                        None => *last_frame = Some(Instant::now()),
-                        _ => (), // This case is necessary.
+                    }
+                    #[cfg(not(target_arch = "wasm32"))]
+                    {
+                        let writes = emu.take_ram_writes();
+                        memory.update(&emu, &writes);
+                        #[cfg(all(feature = "debugger", not(target_arch = "wasm32")))]
+                        if let Some(debugger) = &mut self.debugger {
+                            debugger.note_writes(&writes);
+                        }
+                    }
+                    let screen = match &threaded {
+                        Some(runner) => runner.screen(),
+                        None => *emu.screen_ref(),
+                    };
+                    if recolored && !ran_any && !filters.is_empty() {
+                        filters.process(&screen, ctx.texture.palette());
+                    }
+                    if ran_any || recolored {
+                        // Update texture
+                        let damped = flashes.as_ref().filter(|filter| filter.damping());
+                        match (slow_draw.as_ref(), damped) {
+                            (Some(slow_draw), _) => {
+                                ctx.texture.update(&ctx.queue, slow_draw.screen(&emu))
+                            }
+                            (None, Some(filter)) => {
+                                ctx.texture.update_levels(&ctx.queue, filter.levels())
+                            }
+                            (None, None) if !filters.is_empty() => {
+                                ctx.texture.update_frame(&ctx.queue, filters.frame())
+                            }
+                            (None, None) => ctx.texture.update(&ctx.queue, &screen),
+                        }
+                    }
+
+                    fps.tick();
+                    ctx.overlay.clear();
+                    if let Some(status) = slow_draw.as_ref().and_then(|s| s.status()) {
+                        ctx.overlay.message(&status);
+                    } else if let Some(message) = osd.message() {
+                        ctx.overlay.message(message);
+                    }
+                    if *show_fps {
+                        ctx.overlay.fps(fps.fps());
+                    }
+                    if *show_keypad || *legend > 0 {
+                        ctx.overlay
+                            .keypad(config.keymap.unwrap_or_default(), emu.keys_ref());
+                    }
+                    if *show_timer {
+                        ctx.overlay.timer(timer, emu.keys_ref());
+                    }
+                    if let Some(profile) = profile {
+                        ctx.overlay.profile(profile.get(emu.profile()));
+                    }
+                    if let Some(log) = key_log {
+                        ctx.overlay.key_log(log, emu.keys_ref());
+                    }
+                    #[cfg(not(target_arch = "wasm32"))]
+                    if let Some(editor) = colors {
+                        ctx.overlay.colors(editor, config);
+                    }
+                    if *show_help {
+                        ctx.overlay
+                            .help(config.keymap.unwrap_or_default(), &self.hotkeys);
                     }
                 }
                 ctx.render();
                 // Emits a new redraw requested event.
                 ctx.window.request_redraw();
+                #[cfg(all(feature = "debugger", not(target_arch = "wasm32")))]
+                if let Some(debugger) = &self.debugger {
+                    debugger.window.request_redraw();
+                }
             }
             WindowEvent::Resized(size) => {
                 // Reconfigures the size of the surface. We do not re-render
                 // here as this event is always followed up by redraw request.
                 ctx.resize(size);
             }
+            WindowEvent::KeyboardInput {
+                event:
+                    KeyEvent {
+                        state: ElementState::Pressed,
+                        ..
+                    },
+                ..
+            } if self.splash.is_some() => self.end_splash(),
             WindowEvent::KeyboardInput {
                 event:
                     KeyEvent {
                         physical_key: PhysicalKey::Code(key_code),
                         state,
+                        repeat,
                         ..
                     },
                 ..
             } => {
                 // Only care about user input if the app is Resumed.
                 if let State::Resumed { .. } = &mut self.state {
+                    self.state.log_key(
+                        key_code,
+                        match (state, repeat) {
+                            (ElementState::Pressed, false) => KeyAction::Press,
+                            (ElementState::Pressed, true) => KeyAction::Repeat,
+                            (ElementState::Released, _) => KeyAction::Release,
+                        },
+                    );
+                    #[cfg(not(target_arch = "wasm32"))]
+                    if state == ElementState::Pressed && self.state.colors_key(key_code) {
+                        return;
+                    }
                     // match key state
-                    match state {
-                        ElementState::Pressed => self.state.handle_key(key_code, true),
-                        ElementState::Released => self.state.handle_key(key_code, false),
+                    let action = hotkey(key_code).and_then(|key| self.hotkeys.action(key));
+                    match (state, action) {
+                        #[cfg(all(feature = "debugger", not(target_arch = "wasm32")))]
+                        (ElementState::Pressed, Some(Action::Debugger)) if !repeat => {
+                            self.toggle_debugger(event_loop)
+                        }
+                        // Holding reset restarts the game once.
+                        (ElementState::Pressed, Some(Action::Reset)) if repeat => (),
+                        // Keys bound to hotkeys never reach the keypad.
+                        (ElementState::Pressed, Some(action)) => self.state.handle_hotkey(action),
+                        // Auto-repeat would toggle sticky keys again.
+                        (ElementState::Pressed, None) if repeat => {
+                            self.state.move_magnifier(key_code)
+                        }
+                        (ElementState::Pressed, None) => {
+                            self.state.move_magnifier(key_code);
+                            self.state.handle_key(key_code, true)
+                        }
+                        (ElementState::Released, _) => self.state.handle_key(key_code, false),
                     }
                 }
             }
@@ -378,6 +1520,113 @@ impl ApplicationHandler<UserEvent> for App {
 
                 // WARN: Leaking memory in rust, but we want a global handler.
                 onchange.forget();
+
+                // Controls test button
+                let controls = if let Some(document) = &self.document {
+                    document.get_element_by_id(CONTROLS_ID).unwrap_throw()
+                } else {
+                    panic!("no document");
+                };
+                let onclick = Closure::<dyn FnMut(_)>::new({
+                    let proxy = self.proxy.clone();
+                    move |_event: web_sys::Event| {
+                        let _ = proxy.send_event(UserEvent::RomSelected(RomSource::ControlsTest));
+                    }
+                });
+
+                let _ = controls
+                    .add_event_listener_with_callback("click", onclick.as_ref().unchecked_ref());
+
+                // WARN: Leaking memory in rust, but we want a global handler.
+                onclick.forget();
+
+                // Savestates pasted into the state field
+                let state_field = if let Some(document) = &self.document {
+                    document.get_element_by_id(STATE_ID).unwrap_throw()
+                } else {
+                    panic!("no document");
+                };
+                let onpaste = Closure::<dyn FnMut(_)>::new({
+                    let proxy = self.proxy.clone();
+                    move |event: web_sys::ClipboardEvent| {
+                        if let Some(text) = event
+                            .clipboard_data()
+                            .and_then(|data| data.get_data("text").ok())
+                        {
+                            event.prevent_default();
+                            let _ = proxy.send_event(UserEvent::StatePasted(text));
+                        }
+                    }
+                });
+
+                let _ = state_field
+                    .add_event_listener_with_callback("paste", onpaste.as_ref().unchecked_ref());
+
+                // WARN: Leaking memory in rust, but we want a global handler.
+                onpaste.forget();
+
+                // ROMs pasted into the hex field
+                let hex_field = if let Some(document) = &self.document {
+                    document.get_element_by_id(HEX_ID).unwrap_throw()
+                } else {
+                    panic!("no document");
+                };
+                let onpaste = Closure::<dyn FnMut(_)>::new({
+                    let proxy = self.proxy.clone();
+                    move |event: web_sys::ClipboardEvent| {
+                        if let Some(text) = event
+                            .clipboard_data()
+                            .and_then(|data| data.get_data("text").ok())
+                        {
+                            event.prevent_default();
+                            let _ = proxy.send_event(UserEvent::HexPasted(text));
+                        }
+                    }
+                });
+
+                let _ = hex_field
+                    .add_event_listener_with_callback("paste", onpaste.as_ref().unchecked_ref());
+
+                // WARN: Leaking memory in rust, but we want a global handler.
+                onpaste.forget();
+
+                // Keys pressed while the canvas isn't focused
+                if let Some(document) = &self.document {
+                    web_keys::listen(document, self.proxy.clone());
+                }
+            }
+            #[cfg(target_arch = "wasm32")]
+            UserEvent::StatePasted(text) => {
+                self.state.import_state(&text);
+                self.focus_canvas();
+            }
+            #[cfg(target_arch = "wasm32")]
+            UserEvent::HexPasted(text) => {
+                match oxid8_core::rom::parse_hex(&text) {
+                    Ok(rom) => self.resume(RomSource::Bytes(rom)),
+                    Err(err) => {
+                        log::warn!("Failed to read pasted ROM: {err}");
+                        if let State::Resumed { osd, .. } = &mut self.state {
+                            osd.show(err);
+                        }
+                    }
+                }
+                self.focus_canvas();
+            }
+            #[cfg(target_arch = "wasm32")]
+            UserEvent::Key(key_code, pressed) => {
+                self.state.log_key(
+                    key_code,
+                    match pressed {
+                        true => KeyAction::Press,
+                        false => KeyAction::Release,
+                    },
+                );
+                self.state.handle_key(key_code, pressed);
+            }
+            // Loaded once the splash is done.
+            UserEvent::RomSelected(rom_source) if self.splash.is_some() => {
+                self.pending = Some(rom_source)
             }
             UserEvent::RomSelected(rom_source) => self.resume(rom_source),
         }
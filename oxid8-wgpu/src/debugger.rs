@@ -0,0 +1,415 @@
+//! The debugger in a second native window.
+//!
+//! The window shares the device and queue of the game's `WgpuContext`
+//! and draws its panels with its own `Overlay`. It has no state of its
+//! own: the app owns the one core and hands it to `render` on every
+//! redraw, so the panels always show the machine the game window is
+//! running. The disassembly is read from RAM on every redraw too, so
+//! code the game rewrites shows as it now is, and instructions and
+//! memory written in the last second are marked.
+
+use std::sync::Arc;
+
+use crate::{overlay::Overlay, slow_draw::SlowDrawMode, wgpu_context::WgpuContext};
+
+use anyhow::Result;
+use oxid8_core::{
+    Oxid8, disasm,
+    layout::{Addr, RAM_SIZE},
+    memmap::{MemoryMap, REGIONS, Region, STACK_DEPTH},
+    writes::RamWrites,
+};
+use winit::{dpi::LogicalSize, window::Window};
+
+/// Instructions shown around the program counter.
+const DISASM_LINES: usize = 12;
+
+/// Instructions shown before the program counter.
+const DISASM_BEFORE: usize = 2;
+
+/// Redraws of the game window that a write stays marked for, about a
+/// second at 60Hz.
+const WRITE_FADE: u8 = 60;
+
+/// Rows of 8 bytes shown from I.
+const MEMORY_ROWS: usize = 8;
+
+/// Most recent invalid opcodes listed.
+const SKIPPED_LINES: usize = 3;
+
+/// Characters in a line of an instruction's description.
+const DESCRIPTION_WIDTH: usize = 34;
+
+/// Cells in the memory map, 32 bytes each.
+const MEMORY_CELLS: usize = 128;
+
+/// Panels and the memory map fit at the default overlay scale.
+pub const WINDOW_SIZE: LogicalSize<u32> = LogicalSize::new(900, 620);
+
+const BACKGROUND: wgpu::Color = wgpu::Color {
+    r: 0.05,
+    g: 0.05,
+    b: 0.08,
+    a: 1.0,
+};
+const TEXT_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+const TITLE_COLOR: [f32; 4] = [1.0, 0.8, 0.0, 1.0];
+const PC_COLOR: [f32; 4] = [0.4, 0.9, 1.0, 1.0];
+const SELECTED_COLOR: [f32; 4] = [0.3, 0.3, 0.4, 1.0];
+const WRITTEN_COLOR: [f32; 4] = [1.0, 0.5, 0.3, 1.0];
+const FREE_COLOR: [f32; 4] = [0.2, 0.2, 0.25, 1.0];
+const INTERPRETER_COLOR: [f32; 4] = [0.5, 0.5, 0.55, 1.0];
+const FONT_COLOR: [f32; 4] = [0.9, 0.8, 0.2, 1.0];
+const ROM_COLOR: [f32; 4] = [0.3, 0.5, 1.0, 1.0];
+const DATA_COLOR: [f32; 4] = [1.0, 0.3, 0.3, 1.0];
+
+pub struct DebuggerWindow {
+    pub(crate) window: Arc<Window>,
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    surface: wgpu::Surface<'static>,
+    surface_config: wgpu::SurfaceConfiguration,
+    overlay: Overlay,
+    /// Line of the disassembly inspected, the program counter's at
+    /// first.
+    selected: usize,
+    /// Redraws left that each address is marked as written for.
+    written: Box<[u8; RAM_SIZE]>,
+}
+
+impl DebuggerWindow {
+    /// Create a surface for `window` on the game window's device.
+    pub fn new(ctx: &WgpuContext, window: Arc<Window>) -> Result<Self> {
+        let surface = ctx.instance.create_surface(window.clone())?;
+        let format = surface.get_capabilities(&ctx.adapter).formats[0];
+        let size = window.inner_size();
+        let surface_config = wgpu::SurfaceConfiguration {
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            format,
+            view_formats: vec![],
+            alpha_mode: wgpu::CompositeAlphaMode::Auto,
+            width: size.width.max(1),
+            height: size.height.max(1),
+            desired_maximum_frame_latency: 2,
+            present_mode: wgpu::PresentMode::Fifo,
+        };
+        surface.configure(&ctx.device, &surface_config);
+
+        let mut overlay = Overlay::new(&ctx.device, &ctx.queue, format);
+        overlay.resize(size);
+
+        Ok(DebuggerWindow {
+            window,
+            device: ctx.device.clone(),
+            queue: ctx.queue.clone(),
+            surface,
+            surface_config,
+            overlay,
+            selected: DISASM_BEFORE,
+            written: Box::new([0; RAM_SIZE]),
+        })
+    }
+
+    /// Mark the addresses in `writes`, written since the game window's
+    /// last redraw, and fade older marks.
+    pub fn note_writes(&mut self, writes: &RamWrites) {
+        for left in self.written.iter_mut() {
+            *left = left.saturating_sub(1);
+        }
+        for addr in writes.iter() {
+            self.written[addr as usize] = WRITE_FADE;
+        }
+    }
+
+    /// Returns true if any of the `len` bytes from `addr` were written
+    /// recently.
+    fn written(&self, addr: usize, len: usize) -> bool {
+        self.written
+            .get(addr..(addr + len).min(RAM_SIZE))
+            .is_some_and(|bytes| bytes.iter().any(|&left| left > 0))
+    }
+
+    /// Inspect the instruction above.
+    pub fn select_up(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    /// Inspect the instruction below.
+    pub fn select_down(&mut self) {
+        self.selected = (self.selected + 1).min(DISASM_LINES - 1);
+    }
+
+    pub fn resize(&mut self, size: winit::dpi::PhysicalSize<u32>) {
+        if size.width > 0 && size.height > 0 {
+            self.surface_config.width = size.width;
+            self.surface_config.height = size.height;
+            self.surface.configure(&self.device, &self.surface_config);
+            self.overlay.resize(size);
+        }
+    }
+
+    /// Draw the registers, disassembly, and memory panels for `emu`,
+    /// with the memory map along the bottom, or an empty window before
+    /// a rom is loaded.
+    pub fn render(
+        &mut self,
+        emu: Option<&Oxid8>,
+        memory: Option<&MemoryMap>,
+        slow_draw: Option<&SlowDrawMode>,
+    ) {
+        self.overlay.clear();
+        if let Some(emu) = emu {
+            self.panels(emu, slow_draw);
+        }
+        if let Some(memory) = memory {
+            self.memory_map(memory);
+        }
+
+        let Ok(surface_texture) = self.surface.get_current_texture() else {
+            return;
+        };
+        let view = surface_texture
+            .texture
+            .create_view(&wgpu::TextureViewDescriptor::default());
+        let mut encoder = self.device.create_command_encoder(&Default::default());
+        // Clears the window, the overlay pass loads what is there.
+        encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Debugger Clear Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: &view,
+                depth_slice: None,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(BACKGROUND),
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        self.overlay.render(&self.queue, &mut encoder, &view);
+        self.queue.submit([encoder.finish()]);
+        self.window.pre_present_notify();
+        surface_texture.present();
+    }
+
+    /// Queue the panels, registers and memory on the left and the
+    /// disassembly on the right with the selected instruction broken
+    /// down below it, and slow drawing and invalid opcodes skipped
+    /// under the memory.
+    fn panels(&mut self, emu: &Oxid8, slow_draw: Option<&SlowDrawMode>) {
+        let state = emu.save_state();
+        let line = self.overlay.line_height();
+        let margin = self.overlay.scale() * 2.0;
+
+        let mut left = vec![(String::from("Registers"), TITLE_COLOR)];
+        left.push((
+            format!(
+                "PC {}  I {}  SP {:X}",
+                Addr::new(state.pc),
+                Addr::new(state.i_reg),
+                state.sp
+            ),
+            TEXT_COLOR,
+        ));
+        left.push((
+            format!("DT {:02X}  ST {:02X}", state.dt, state.st),
+            TEXT_COLOR,
+        ));
+        for (row, values) in state.v_reg.chunks(4).enumerate() {
+            let text: Vec<String> = values
+                .iter()
+                .enumerate()
+                .map(|(i, v)| format!("V{:X} {v:02X}", row * 4 + i))
+                .collect();
+            left.push((text.join(" "), TEXT_COLOR));
+        }
+        let stack: Vec<String> = state.stack[..state.sp as usize]
+            .iter()
+            .map(|&addr| Addr::new(addr).to_string())
+            .collect();
+        left.push((format!("Stack {}", stack.join(" ")), TEXT_COLOR));
+        left.push((String::new(), TEXT_COLOR));
+
+        left.push((String::from("Memory at I"), TITLE_COLOR));
+        let i = state.i_reg as usize & !7;
+        for row in (i..state.ram.len()).step_by(8).take(MEMORY_ROWS) {
+            let bytes: Vec<String> = state.ram[row..row + 8]
+                .iter()
+                .map(|b| format!("{b:02X}"))
+                .collect();
+            let color = match self.written(row, 8) {
+                true => WRITTEN_COLOR,
+                false => TEXT_COLOR,
+            };
+            left.push((
+                format!("{} {}", Addr::new(row as u16), bytes.join(" ")),
+                color,
+            ));
+        }
+
+        let mut right = vec![(String::from("Disassembly (Up, Down)"), TITLE_COLOR)];
+        let pc = Addr::new(state.pc);
+        let lines = disasm::around(&state.ram, pc, DISASM_BEFORE, DISASM_LINES);
+        right.extend(lines.iter().map(|line| {
+            // Rewritten code is marked, the program counter's line stays
+            // the color of the program counter.
+            let written = self.written(line.addr.index(), 2);
+            match (line.addr == pc, written) {
+                (true, false) => (line.to_string(), PC_COLOR),
+                (true, true) => (format!("{line} *"), PC_COLOR),
+                (false, true) => (format!("{line} *"), WRITTEN_COLOR),
+                (false, false) => (line.to_string(), TEXT_COLOR),
+            }
+        }));
+        let selected_top = margin + (1 + self.selected) as f32 * line;
+        right.push((String::new(), TEXT_COLOR));
+        if let Some(selected) = lines.get(self.selected) {
+            right.extend(inspect(emu, selected.opcode));
+        }
+
+        // The status of a draw runs under the disassembly.
+        let column = left
+            .iter()
+            .map(|(text, _)| self.overlay.text_width(text))
+            .fold(0.0, f32::max);
+
+        left.push((String::new(), TEXT_COLOR));
+        left.push((String::from("Slow draw (D, [ ])"), TITLE_COLOR));
+        match slow_draw {
+            Some(slow_draw) => {
+                left.push((
+                    format!("On, {} frames per bit", slow_draw.frames_per_bit()),
+                    TEXT_COLOR,
+                ));
+                if let Some(status) = slow_draw.status() {
+                    left.push((status, PC_COLOR));
+                }
+            }
+            None => left.push((String::from("Off"), TEXT_COLOR)),
+        }
+
+        left.push((String::new(), TEXT_COLOR));
+        left.push((String::from("Invalid opcodes"), TITLE_COLOR));
+        let skipped = emu.skipped();
+        left.push((
+            match (emu.skip_invalid(), skipped.total()) {
+                (false, 0) => String::from("Stop the game"),
+                (true, 0) => String::from("Skipped, none yet"),
+                (_, total) => format!("Skipped {total}"),
+            },
+            TEXT_COLOR,
+        ));
+        let recent = skipped.entries().iter().rev().take(SKIPPED_LINES);
+        left.extend(recent.map(|skipped| (skipped.to_string(), PC_COLOR)));
+
+        let right_x = 2.0 * margin + column;
+        if self.selected < lines.len() {
+            let width = self.overlay.text_width(&right[1 + self.selected].0);
+            self.overlay.fill(
+                right_x - margin / 2.0,
+                selected_top,
+                width + margin,
+                line,
+                SELECTED_COLOR,
+            );
+        }
+        for (x, lines) in [(margin, left), (right_x, right)] {
+            for (i, (text, color)) in lines.iter().enumerate() {
+                self.overlay.text(x, margin + i as f32 * line, text, *color);
+            }
+        }
+    }
+
+    /// Queue the memory map along the bottom of the window, a bar of
+    /// RAM colored by region with the bytes in each below it.
+    fn memory_map(&mut self, memory: &MemoryMap) {
+        let scale = self.overlay.scale();
+        let line = self.overlay.line_height();
+        let margin = scale * 2.0;
+        let top = self.surface_config.height as f32 - margin - 3.0 * line;
+        let title = format!(
+            "Memory, stack {} deepest {} of {STACK_DEPTH}",
+            memory.stack(),
+            memory.deepest()
+        );
+        self.overlay.text(margin, top, &title, TITLE_COLOR);
+
+        let width = self.surface_config.width as f32 - 2.0 * margin;
+        let cell = width / MEMORY_CELLS as f32;
+        for (i, region) in memory.bar(MEMORY_CELLS).into_iter().enumerate() {
+            let x = margin + i as f32 * cell;
+            self.overlay
+                .fill(x, top + line, cell, line - scale, region_color(region));
+        }
+
+        let mut x = margin;
+        for region in REGIONS {
+            let y = top + 2.0 * line;
+            let swatch = line - 2.0 * scale;
+            self.overlay
+                .fill(x, y, swatch, swatch, region_color(region));
+            let text = format!("{region} {}", memory.bytes(region));
+            self.overlay.text(x + line, y, &text, TEXT_COLOR);
+            x += line + self.overlay.text_width(&text) + 2.0 * margin;
+        }
+    }
+}
+
+/// The color a region is drawn in on the memory map.
+fn region_color(region: Region) -> [f32; 4] {
+    match region {
+        Region::Free => FREE_COLOR,
+        Region::Interpreter => INTERPRETER_COLOR,
+        Region::Font => FONT_COLOR,
+        Region::Rom => ROM_COLOR,
+        Region::Data => DATA_COLOR,
+    }
+}
+
+/// The fields of `opcode`, the current values of the registers it
+/// uses, and what it does, from the core's opcode table.
+fn inspect(emu: &Oxid8, opcode: u16) -> Vec<(String, [f32; 4])> {
+    let Some(info) = disasm::info(opcode) else {
+        return vec![(format!("{opcode:04X} is not an instruction"), TEXT_COLOR)];
+    };
+    let mut lines = vec![(
+        format!("{opcode:04X} {}", info.pattern.to_uppercase()),
+        TITLE_COLOR,
+    )];
+    let fields: Vec<String> = info
+        .fields
+        .iter()
+        .map(|field| format!("{field} = {:X}", field.value(opcode)))
+        .collect();
+    if !fields.is_empty() {
+        lines.push((fields.join("  "), TEXT_COLOR));
+    }
+    let registers: Vec<String> = info
+        .registers(opcode)
+        .into_iter()
+        .map(|register| {
+            let name = register.to_string().to_uppercase();
+            format!("{name} = {:X}", register.read(emu))
+        })
+        .collect();
+    if !registers.is_empty() {
+        lines.push((registers.join("  "), TEXT_COLOR));
+    }
+    lines.extend(wrap(info.description, DESCRIPTION_WIDTH).map(|line| (line, TEXT_COLOR)));
+    lines
+}
+
+/// Splits `text` into lines of at most `width` characters at spaces.
+fn wrap(text: &str, width: usize) -> impl Iterator<Item = String> + '_ {
+    let mut words = text.split(' ').peekable();
+    std::iter::from_fn(move || {
+        let mut line = String::from(words.next()?);
+        while let Some(word) = words.next_if(|word| line.len() + 1 + word.len() <= width) {
+            line.push(' ');
+            line.push_str(word);
+        }
+        Some(line)
+    })
+}
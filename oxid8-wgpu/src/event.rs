@@ -1,20 +1,17 @@
 //! User events send via the winit event_loop proxy.
 
-#[cfg(not(target_arch = "wasm32"))]
-use std::path::PathBuf;
-
 #[cfg(target_arch = "wasm32")]
 use crate::wgpu_context::WgpuContext;
+#[cfg(target_arch = "wasm32")]
+use winit::keyboard::KeyCode;
 
 /// How to rom source is stored.
-/// An alternative to this would be to only use #[cfg].
 pub enum RomSource {
-    /// As a file for native use
-    #[cfg(not(target_arch = "wasm32"))]
-    Path(PathBuf),
-    /// As bytes for web use
-    #[cfg(target_arch = "wasm32")]
+    /// As bytes, read from a file or standard input natively, or
+    /// uploaded on the web
     Bytes(Vec<u8>),
+    /// The built-in controls test, see `oxid8_core::builtin`
+    ControlsTest,
 }
 
 /// User events sent by the winit event_loop proxy
@@ -28,5 +25,14 @@ pub enum UserEvent {
     ContextCreated(WgpuContext),
     /// User uploaded rom
     RomSelected(RomSource),
+    /// User pasted text into the savestate field
+    #[cfg(target_arch = "wasm32")]
+    StatePasted(String),
+    /// User pasted a ROM written as hex into the hex field
+    #[cfg(target_arch = "wasm32")]
+    HexPasted(String),
+    /// Keypad key pressed or released outside the canvas, see `web_keys`
+    #[cfg(target_arch = "wasm32")]
+    Key(KeyCode, bool),
     // TODO: Shader swap event
 }
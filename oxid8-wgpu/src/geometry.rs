@@ -1,13 +1,17 @@
 //! Fullscreen quad
 
+use oxid8_core::rotation::Rotation;
+
 #[repr(C)]
 #[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
 pub struct Vertex {
     position: [f32; 3],
+    tex_coords: [f32; 2],
 }
 
 impl Vertex {
-    const ATTRIBS: [wgpu::VertexAttribute; 1] = wgpu::vertex_attr_array![0 => Float32x3];
+    const ATTRIBS: [wgpu::VertexAttribute; 2] =
+        wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x2];
 
     pub fn desc() -> wgpu::VertexBufferLayout<'static> {
         wgpu::VertexBufferLayout {
@@ -19,12 +23,35 @@ impl Vertex {
 }
 
 #[rustfmt::skip]
-pub const VERTICES: &[Vertex] = &[
-    Vertex { position: [-1.0,  1.0,  0.0] },
-    Vertex { position: [-1.0, -3.0,  0.0] },
-    Vertex { position: [ 3.0,  1.0,  0.0] },
+const POSITIONS: [[f32; 3]; 3] = [
+    [-1.0,  1.0,  0.0],
+    [-1.0, -3.0,  0.0],
+    [ 3.0,  1.0,  0.0],
 ];
 
+/// The fullscreen triangle with texture coordinates rotated clockwise
+/// by `rotation`.
+pub fn vertices(rotation: Rotation) -> [Vertex; 3] {
+    POSITIONS.map(|position| {
+        // Convert clip space to [0, 1] and flip y
+        let u = position[0] / 2.0 + 0.5;
+        let v = 0.5 - position[1] / 2.0;
+
+        // Texture coordinates shown at (u, v)
+        let tex_coords = match rotation {
+            Rotation::Deg0 => [u, v],
+            Rotation::Deg90 => [v, 1.0 - u],
+            Rotation::Deg180 => [1.0 - u, 1.0 - v],
+            Rotation::Deg270 => [1.0 - v, u],
+        };
+
+        Vertex {
+            position,
+            tex_coords,
+        }
+    })
+}
+
 #[rustfmt::skip]
 pub const INDICES: &[u16] = &[
     0, 1, 2,
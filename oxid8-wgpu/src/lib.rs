@@ -12,31 +12,78 @@ use wasm_bindgen::prelude::*;
 use crate::{app::App, event::UserEvent};
 
 mod app;
+#[cfg(all(feature = "debugger", not(target_arch = "wasm32")))]
+mod debugger;
 mod event;
 mod geometry;
-mod texture;
+mod magnifier;
+mod overlay;
+mod slow_draw;
+mod states;
+#[doc(hidden)]
+pub mod threaded;
+// Public for the unpack benchmark.
+#[doc(hidden)]
+pub mod texture;
+#[doc(hidden)]
+pub mod unpack;
+#[cfg(target_arch = "wasm32")]
+mod web_keys;
 mod wgpu_context;
 
 #[cfg(not(target_arch = "wasm32"))]
 pub struct Config {
-    pub rom_path: PathBuf,
+    /// Runs the built-in controls test if none, `-` reads the rom from
+    /// standard input.
+    pub rom_path: Option<PathBuf>,
+    /// Overrides the rotation saved for the rom.
+    pub rotation: Option<oxid8_core::rotation::Rotation>,
+    /// Sticky keys and minimum hold for the keypad.
+    pub assist: oxid8_core::frontend::Assist,
+    /// Skip invalid opcodes instead of stopping, whatever is saved for
+    /// the rom.
+    pub skip_invalid: bool,
+    /// Damp rapid flashing, see `oxid8_core::flash`.
+    pub reduce_flashes: bool,
+    /// Post-processing of the screen as `--filter` takes it, see
+    /// `oxid8_core::filter`. Empty for none.
+    pub filters: String,
+    /// Emulate on a thread of its own, see `threaded`.
+    pub threaded: bool,
 }
 
 pub fn run(#[cfg(not(target_arch = "wasm32"))] config: Config) -> anyhow::Result<()> {
     cfg_if! {
         if #[cfg(target_arch = "wasm32")] {
             console_log::init_with_level(log::Level::Info).unwrap_throw();
+        } else if #[cfg(feature = "tracing")] {
+            // Also collects `log` records from wgpu and this crate.
+            tracing_subscriber::fmt()
+                .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+                .init();
         } else {
             env_logger::init();
         }
     }
 
+    // Read first, standard input can only be read once.
+    #[cfg(not(target_arch = "wasm32"))]
+    let rom = match &config.rom_path {
+        Some(path) => Some(
+            oxid8_core::rom::read(path)
+                .map_err(|err| anyhow::anyhow!("{}: {err}", path.display()))?,
+        ),
+        None => None,
+    };
+
     let event_loop = EventLoop::<UserEvent>::with_user_event().build()?;
     #[allow(unused_mut)]
     let mut app = App::new(
         &event_loop,
         #[cfg(not(target_arch = "wasm32"))]
         config,
+        #[cfg(not(target_arch = "wasm32"))]
+        rom,
     );
 
     cfg_if! {
@@ -54,6 +101,7 @@ pub fn run(#[cfg(not(target_arch = "wasm32"))] config: Config) -> anyhow::Result
 #[cfg(target_arch = "wasm32")]
 #[wasm_bindgen(start)]
 pub fn start() -> Result<(), wasm_bindgen::JsValue> {
+    #[cfg(feature = "panic-hook")]
     console_error_panic_hook::set_once();
     run().unwrap_throw();
 
@@ -0,0 +1,70 @@
+//! Region-of-interest magnifier overlay.
+//!
+//! Shows an 8x8 or 16x16 region of the screen under a movable cursor,
+//! magnified in the corner of the window with a grid between pixels.
+//! Useful for checking sprite alignment at the native resolution.
+
+use oxid8_core::{SCREEN_HEIGHT, SCREEN_WIDTH};
+
+/// Cursor over the magnified region in screen pixels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Magnifier {
+    x: usize,
+    y: usize,
+    size: usize,
+}
+
+impl Magnifier {
+    /// Off, then 8x8, then 16x16, then off again.
+    pub fn cycle(magnifier: Option<Self>) -> Option<Self> {
+        match magnifier {
+            None => Some(Magnifier {
+                x: 0,
+                y: 0,
+                size: 8,
+            }),
+            Some(m) if m.size == 8 => Some(Magnifier { size: 16, ..m }.clamped()),
+            Some(_) => None,
+        }
+    }
+
+    /// Move the cursor by one pixel, staying on screen.
+    pub fn move_by(&mut self, dx: isize, dy: isize) {
+        self.x = self.x.saturating_add_signed(dx);
+        self.y = self.y.saturating_add_signed(dy);
+        *self = self.clamped();
+    }
+
+    fn clamped(self) -> Self {
+        Magnifier {
+            x: self.x.min(SCREEN_WIDTH - self.size),
+            y: self.y.min(SCREEN_HEIGHT - self.size),
+            ..self
+        }
+    }
+
+    /// Shader uniform for the overlay, disabled for `None`.
+    pub fn uniform(magnifier: Option<Self>) -> MagnifierUniform {
+        match magnifier {
+            Some(m) => MagnifierUniform {
+                cursor: [m.x as f32, m.y as f32],
+                size: m.size as f32,
+                enabled: 1,
+            },
+            None => MagnifierUniform {
+                cursor: [0.0, 0.0],
+                size: 0.0,
+                enabled: 0,
+            },
+        }
+    }
+}
+
+/// Matches `Magnifier` in shader.wgsl.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct MagnifierUniform {
+    cursor: [f32; 2],
+    size: f32,
+    enabled: u32,
+}
@@ -1,18 +1,59 @@
 use clap::Parser;
+use oxid8_core::{filter::FilterChain, frontend::Assist, rotation::Rotation};
 use oxid8_wgpu::{Config, run};
 
 #[cfg(not(target_arch = "wasm32"))]
 #[derive(Parser, Debug)]
 #[command(version, about, long_about = None)]
 pub struct Args {
-    #[arg(required = true)]
-    rom_path: String,
+    /// Path to the rom, or - to pipe it in.
+    #[arg(required_unless_present = "controls_test")]
+    rom_path: Option<String>,
+    /// Run the built-in controls test to check input instead of a rom.
+    #[arg(long, conflicts_with = "rom_path")]
+    controls_test: bool,
+    /// Rotate the display clockwise by 0, 90, 180, or 270 degrees.
+    #[arg(long)]
+    rotate: Option<Rotation>,
+    /// Toggle keys with each press instead of holding them.
+    #[arg(long)]
+    sticky_keys: bool,
+    /// Keep keys down for at least this many frames after a press.
+    #[arg(long, value_name = "FRAMES", default_value_t = 0)]
+    min_hold: u8,
+    /// Skip invalid opcodes instead of stopping the game.
+    #[arg(long)]
+    skip_invalid: bool,
+    /// Damp rapid flashing of the screen, for photosensitive players.
+    #[arg(long)]
+    reduce_flashes: bool,
+    /// Post-process the screen, e.g. ghosting=0.5,flicker-blend,grayscale.
+    #[arg(long, value_name = "FILTERS", value_parser = parse_filters, default_value = "")]
+    filter: String,
+    /// Emulate on a thread of its own, for steady frames at high speeds.
+    #[arg(long)]
+    threaded: bool,
 }
 
-impl Into<Config> for Args {
-    fn into(self) -> Config {
+/// Checks `--filter` names a chain of filters.
+fn parse_filters(spec: &str) -> Result<String, String> {
+    spec.parse::<FilterChain>()?;
+    Ok(spec.into())
+}
+
+impl From<Args> for Config {
+    fn from(args: Args) -> Self {
         Config {
-            rom_path: self.rom_path.into(),
+            rom_path: args.rom_path.map(Into::into),
+            rotation: args.rotate,
+            assist: Assist {
+                sticky: args.sticky_keys,
+                min_hold: args.min_hold,
+            },
+            skip_invalid: args.skip_invalid,
+            reduce_flashes: args.reduce_flashes,
+            filters: args.filter,
+            threaded: args.threaded,
         }
     }
 }
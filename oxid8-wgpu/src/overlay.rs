@@ -0,0 +1,628 @@
+//! Text and boxes drawn over the screen in a second render pass.
+//!
+//! Glyphs from the core's OSD font are packed into a small atlas once,
+//! and every overlay quad of a frame is drawn in one instanced draw
+//! call. This keeps on-screen text crisp, unlike the OSD drawn into the
+//! emulated display, without pulling a GUI library into the web build.
+
+#[cfg(not(target_arch = "wasm32"))]
+use oxid8_core::{
+    config::GameConfig,
+    palette::{CHANNELS, PaletteEditor, SLOTS},
+};
+use oxid8_core::{
+    hotkeys::Hotkeys,
+    keylog::{self, KeyLog},
+    keymap::{self, Keymap},
+    osd::{GLYPH_ADVANCE, GLYPH_HEIGHT, glyph},
+    speedrun::SpeedrunTimer,
+    variant::Profile,
+};
+use wgpu::util::DeviceExt;
+use winit::dpi::PhysicalSize;
+
+/// Most quads drawn in a frame, any more are dropped.
+const MAX_QUADS: usize = 2048;
+
+/// Glyph width in pixels.
+const GLYPH_WIDTH: usize = 4;
+
+/// Cells in the atlas are a pixel larger than glyphs so neighbors never
+/// bleed into each other.
+const CELL_WIDTH: usize = GLYPH_WIDTH + 1;
+const CELL_HEIGHT: usize = GLYPH_HEIGHT + 1;
+const ATLAS_COLUMNS: usize = 16;
+const ATLAS_ROWS: usize = 6;
+const ATLAS_WIDTH: usize = ATLAS_COLUMNS * CELL_WIDTH;
+const ATLAS_HEIGHT: usize = ATLAS_ROWS * CELL_HEIGHT;
+
+/// Printable ASCII from `' '`, followed by a solid cell for boxes.
+const FIRST_CHAR: u8 = b' ';
+const SOLID: usize = (b'~' - FIRST_CHAR) as usize + 1;
+
+const TEXT_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+const BOX_COLOR: [f32; 4] = [0.0, 0.0, 0.0, 0.6];
+const KEY_COLOR: [f32; 4] = [0.2, 0.2, 0.2, 0.8];
+const HELD_COLOR: [f32; 4] = [1.0, 0.8, 0.0, 0.9];
+const MAPPED_COLOR: [f32; 4] = [0.4, 0.9, 1.0, 1.0];
+
+/// Keys of the color editor, see `State::colors_key`.
+#[cfg(not(target_arch = "wasm32"))]
+const COLOR_KEYS: [&str; 5] = [
+    "Up/Down color  Left/Right channel",
+    "+/- change  PgUp/PgDn by 16",
+    "Tab next preset",
+    "Enter save for game  D for all",
+    "Esc cancel",
+];
+
+/// Labels of player 1 and 2 keys in two player mode, like the
+/// terminal's cyan and magenta.
+const PLAYER_COLORS: [[f32; 4]; 2] = [[0.4, 0.9, 1.0, 1.0], [1.0, 0.5, 1.0, 1.0]];
+
+/// Matches `InstanceInput` in overlay.wgsl.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct Quad {
+    /// Left, top, width, and height in clip space.
+    rect: [f32; 4],
+    /// Top left and bottom right in the atlas.
+    uv: [f32; 4],
+    color: [f32; 4],
+}
+
+impl Quad {
+    const ATTRIBS: [wgpu::VertexAttribute; 3] =
+        wgpu::vertex_attr_array![0 => Float32x4, 1 => Float32x4, 2 => Float32x4];
+
+    fn desc() -> wgpu::VertexBufferLayout<'static> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<Quad>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: &Self::ATTRIBS,
+        }
+    }
+}
+
+/// Atlas pixels, a lit texel for each lit glyph pixel.
+fn atlas() -> Vec<u8> {
+    let mut texels = vec![0u8; 4 * ATLAS_WIDTH * ATLAS_HEIGHT];
+    let mut light = |cell: usize, x: usize, y: usize| {
+        let px = (cell % ATLAS_COLUMNS) * CELL_WIDTH + x;
+        let py = (cell / ATLAS_COLUMNS) * CELL_HEIGHT + y;
+        let i = 4 * (px + py * ATLAS_WIDTH);
+        texels[i..i + 4].fill(255);
+    };
+
+    for (cell, c) in (FIRST_CHAR..=b'~').enumerate() {
+        for (y, bits) in glyph(c as char).into_iter().enumerate() {
+            for x in 0..GLYPH_WIDTH {
+                if bits & (0x80 >> x) != 0 {
+                    light(cell, x, y);
+                }
+            }
+        }
+    }
+    for y in 0..GLYPH_HEIGHT {
+        for x in 0..GLYPH_WIDTH {
+            light(SOLID, x, y);
+        }
+    }
+    texels
+}
+
+/// Atlas coordinates of a cell.
+fn cell_uv(cell: usize) -> [f32; 4] {
+    let x = ((cell % ATLAS_COLUMNS) * CELL_WIDTH) as f32 / ATLAS_WIDTH as f32;
+    let y = ((cell / ATLAS_COLUMNS) * CELL_HEIGHT) as f32 / ATLAS_HEIGHT as f32;
+    [
+        x,
+        y,
+        x + GLYPH_WIDTH as f32 / ATLAS_WIDTH as f32,
+        y + GLYPH_HEIGHT as f32 / ATLAS_HEIGHT as f32,
+    ]
+}
+
+/// The atlas cell for `c`, `?` for anything it doesn't have.
+fn char_cell(c: char) -> usize {
+    match c {
+        ' '..='~' => (c as u8 - FIRST_CHAR) as usize,
+        _ => (b'?' - FIRST_CHAR) as usize,
+    }
+}
+
+pub struct Overlay {
+    pipeline: wgpu::RenderPipeline,
+    atlas_bind_group: wgpu::BindGroup,
+    quad_buffer: wgpu::Buffer,
+    quads: Vec<Quad>,
+    size: PhysicalSize<u32>,
+}
+
+impl Overlay {
+    /// Create the atlas and pipeline for drawing to `format`.
+    pub fn new(device: &wgpu::Device, queue: &wgpu::Queue, format: wgpu::TextureFormat) -> Self {
+        let atlas = device.create_texture_with_data(
+            queue,
+            &wgpu::TextureDescriptor {
+                label: Some("Overlay Atlas"),
+                size: wgpu::Extent3d {
+                    width: ATLAS_WIDTH as u32,
+                    height: ATLAS_HEIGHT as u32,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: wgpu::TextureFormat::Rgba8Unorm,
+                usage: wgpu::TextureUsages::TEXTURE_BINDING,
+                view_formats: &[],
+            },
+            wgpu::util::TextureDataOrder::LayerMajor,
+            &atlas(),
+        );
+        let view = atlas.create_view(&wgpu::TextureViewDescriptor::default());
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            mag_filter: wgpu::FilterMode::Nearest,
+            min_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        });
+
+        let atlas_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            multisampled: false,
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                        count: None,
+                    },
+                ],
+                label: Some("overlay_bind_group_layout"),
+            });
+
+        let atlas_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &atlas_bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&sampler),
+                },
+            ],
+            label: Some("overlay_bind_group"),
+        });
+
+        let shader = device.create_shader_module(wgpu::include_wgsl!("overlay.wgsl"));
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Overlay Pipeline Layout"),
+            bind_group_layouts: &[&atlas_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Overlay Pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: Some("vs_main"),
+                buffers: &[Quad::desc()],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: Some("fs_main"),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleStrip,
+                ..Default::default()
+            },
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+            cache: None,
+        });
+
+        let quad_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Overlay Buffer"),
+            size: (MAX_QUADS * std::mem::size_of::<Quad>()) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        Overlay {
+            pipeline,
+            atlas_bind_group,
+            quad_buffer,
+            quads: Vec::with_capacity(MAX_QUADS),
+            size: PhysicalSize::new(1, 1),
+        }
+    }
+
+    /// Set the window size that positions are given in.
+    pub fn resize(&mut self, size: PhysicalSize<u32>) {
+        self.size = size;
+    }
+
+    /// Remove everything drawn since the last frame.
+    pub fn clear(&mut self) {
+        self.quads.clear();
+    }
+
+    /// Size of a glyph pixel in window pixels, scaled with the window.
+    pub fn scale(&self) -> f32 {
+        (self.size.height as f32 / 160.0).floor().max(2.0)
+    }
+
+    /// Height of a line of text in window pixels, with a glyph pixel
+    /// of margin above and below.
+    pub fn line_height(&self) -> f32 {
+        (GLYPH_HEIGHT + 2) as f32 * self.scale()
+    }
+
+    /// Width of `text` in window pixels.
+    #[cfg(feature = "debugger")]
+    pub fn text_width(&self, text: &str) -> f32 {
+        (text.chars().count() * GLYPH_ADVANCE) as f32 * self.scale()
+    }
+
+    /// Queue a quad of atlas `cell` at `x`, `y` (top left) in window
+    /// pixels.
+    fn quad(&mut self, x: f32, y: f32, width: f32, height: f32, cell: usize, color: [f32; 4]) {
+        if self.quads.len() == MAX_QUADS {
+            return;
+        }
+        let (w, h) = (self.size.width as f32, self.size.height as f32);
+        self.quads.push(Quad {
+            rect: [
+                x / w * 2.0 - 1.0,
+                1.0 - y / h * 2.0,
+                width / w * 2.0,
+                height / h * 2.0,
+            ],
+            uv: cell_uv(cell),
+            color,
+        });
+    }
+
+    /// Queue a filled box in window pixels.
+    pub fn fill(&mut self, x: f32, y: f32, width: f32, height: f32, color: [f32; 4]) {
+        self.quad(x, y, width, height, SOLID, color);
+    }
+
+    /// Queue `text` with its top left at `x`, `y` in window pixels.
+    pub fn text(&mut self, x: f32, y: f32, text: &str, color: [f32; 4]) {
+        let scale = self.scale();
+        for (i, c) in text.chars().enumerate() {
+            let left = x + (i * GLYPH_ADVANCE) as f32 * scale;
+            self.quad(
+                left,
+                y,
+                GLYPH_WIDTH as f32 * scale,
+                GLYPH_HEIGHT as f32 * scale,
+                char_cell(c),
+                color,
+            );
+        }
+    }
+
+    /// Size of `text` in window pixels with a glyph pixel of margin.
+    fn text_box(&self, text: &str) -> (f32, f32) {
+        let scale = self.scale();
+        let chars = text.chars().count();
+        (
+            (chars * GLYPH_ADVANCE + 1) as f32 * scale,
+            (GLYPH_HEIGHT + 2) as f32 * scale,
+        )
+    }
+
+    /// Queue an OSD message in a box along the bottom left.
+    pub fn message(&mut self, message: &str) {
+        let scale = self.scale();
+        let (width, height) = self.text_box(message);
+        let top = self.size.height as f32 - height - scale;
+        self.fill(scale, top, width, height, BOX_COLOR);
+        self.text(2.0 * scale, top + scale, message, TEXT_COLOR);
+    }
+
+    /// Queue the frame rate in a box in the top left.
+    pub fn fps(&mut self, fps: u32) {
+        let scale = self.scale();
+        let mut buf = [0u8; 16];
+        let text = fps_text(fps, &mut buf);
+        let (width, height) = self.text_box(text);
+        self.fill(scale, scale, width, height, BOX_COLOR);
+        self.text(2.0 * scale, 2.0 * scale, text, TEXT_COLOR);
+    }
+
+    /// Queue the machine profile in a box in the top right.
+    pub fn profile(&mut self, text: &str) {
+        let scale = self.scale();
+        let (width, height) = self.text_box(text);
+        let left = self.size.width as f32 - width - scale;
+        self.fill(left, scale, width, height, BOX_COLOR);
+        self.text(left + scale, 2.0 * scale, text, TEXT_COLOR);
+    }
+
+    /// Queue the speedrun timer and the held keypad keys in a box in
+    /// the top centre.
+    pub fn timer(&mut self, timer: &SpeedrunTimer, keys: &[bool; 16]) {
+        let scale = self.scale();
+        let text = format!("{timer}  {}", keylog::held_keys(keys));
+        let (width, height) = self.text_box(&text);
+        let left = ((self.size.width as f32 - width) / 2.0).floor();
+        self.fill(left, scale, width, height, BOX_COLOR);
+        self.text(left + scale, 2.0 * scale, &text, TEXT_COLOR);
+    }
+
+    /// Queue the keypad in the bottom right, labeled with the keys of
+    /// `keymap`, with held keys lit.
+    pub fn keypad(&mut self, keymap: Keymap, keys: &[bool; 16]) {
+        let scale = self.scale();
+        let key = (GLYPH_HEIGHT + 2) as f32 * scale;
+        let gap = scale;
+        let size = 4.0 * key + 3.0 * gap;
+        let left = self.size.width as f32 - size - scale;
+        let top = self.size.height as f32 - size - scale;
+
+        let caps = keymap.diagram(keys);
+        for (i, cap) in caps.iter().flatten().enumerate() {
+            let x = left + (i % 4) as f32 * (key + gap);
+            let y = top + (i / 4) as f32 * (key + gap);
+            let held = cap.held;
+            self.fill(x, y, key, key, if held { HELD_COLOR } else { KEY_COLOR });
+
+            let color = match (held, cap.player) {
+                (true, _) => BOX_COLOR,
+                (false, Some(player)) => PLAYER_COLORS[player as usize - 1],
+                (false, None) => TEXT_COLOR,
+            };
+            let inset = ((GLYPH_HEIGHT + 2 - GLYPH_WIDTH) as f32 / 2.0).floor() * scale;
+            self.quad(
+                x + inset,
+                y + scale,
+                GLYPH_WIDTH as f32 * scale,
+                GLYPH_HEIGHT as f32 * scale,
+                char_cell(cap.label),
+                color,
+            );
+        }
+    }
+
+    /// Queue raw key events, newest first, with the held keypad keys in
+    /// a box along the left.
+    pub fn key_log(&mut self, log: &KeyLog, keys: &[bool; 16]) {
+        let scale = self.scale();
+        let line = (GLYPH_HEIGHT + 2) as f32 * scale;
+        let mut lines = vec![
+            (format!("Held: {}", keylog::held_keys(keys)), TEXT_COLOR),
+            (log.counts(), TEXT_COLOR),
+        ];
+        if let Some(warning) = log.warning() {
+            lines.push((warning.to_string(), HELD_COLOR));
+        }
+        lines.extend(log.records().map(|record| {
+            let color = if record.mapped.is_some() {
+                MAPPED_COLOR
+            } else {
+                TEXT_COLOR
+            };
+            (record.to_string(), color)
+        }));
+
+        let chars = lines.iter().map(|(text, _)| text.chars().count());
+        let width = (chars.max().unwrap_or(0) * GLYPH_ADVANCE + 1) as f32 * scale;
+        let height = lines.len() as f32 * line + scale;
+        let top = ((self.size.height as f32 - height) / 2.0).floor();
+        self.fill(scale, top, width, height, BOX_COLOR);
+        for (i, (text, color)) in lines.iter().enumerate() {
+            self.text(2.0 * scale, top + scale + i as f32 * line, text, *color);
+        }
+    }
+
+    /// Queue the colors being edited with swatches, the one being
+    /// changed lit, and the editor's keys, in a box along the left.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn colors(&mut self, editor: &PaletteEditor, config: &GameConfig) {
+        let scale = self.scale();
+        let line = self.line_height();
+        let mut lines: Vec<(String, [f32; 4])> = SLOTS
+            .iter()
+            .map(|&slot| {
+                let rgb = slot.color(config);
+                let text = format!("{slot:<11}{:02X}{:02X}{:02X}", rgb.0, rgb.1, rgb.2);
+                let color = if slot == editor.slot() {
+                    HELD_COLOR
+                } else {
+                    TEXT_COLOR
+                };
+                (text, color)
+            })
+            .collect();
+        lines.push((
+            format!("Editing {} {}", editor.slot(), CHANNELS[editor.channel()]),
+            MAPPED_COLOR,
+        ));
+        lines.extend(COLOR_KEYS.iter().map(|keys| (keys.to_string(), TEXT_COLOR)));
+
+        // Swatches are a line high and square, before the first lines.
+        let indent = line + scale;
+        let chars = lines.iter().map(|(text, _)| text.chars().count());
+        let width = (chars.max().unwrap_or(0) * GLYPH_ADVANCE + 1) as f32 * scale + indent;
+        let height = lines.len() as f32 * line + scale;
+        let top = ((self.size.height as f32 - height) / 2.0).floor();
+        self.fill(scale, top, width, height, BOX_COLOR);
+        for (i, (text, color)) in lines.iter().enumerate() {
+            let y = top + scale + i as f32 * line;
+            if let Some(slot) = SLOTS.get(i) {
+                let rgb = slot.color(config);
+                let swatch = [rgb.0, rgb.1, rgb.2].map(|c| c as f32 / 255.0);
+                let [r, g, b] = swatch;
+                self.fill(
+                    2.0 * scale,
+                    y - scale,
+                    line - scale,
+                    line - scale,
+                    [r, g, b, 1.0],
+                );
+            }
+            self.text(indent + scale, y, text, *color);
+        }
+    }
+
+    /// Queue the keyboard keys for the keypad with `keymap`, then the
+    /// bound `hotkeys` and what they do, in a box in the centre.
+    pub fn help(&mut self, keymap: Keymap, hotkeys: &Hotkeys) {
+        let scale = self.scale();
+        let line = self.line_height();
+        let mut lines: Vec<String> = keymap::KEYPAD
+            .iter()
+            .zip(keymap.rows())
+            .map(|(keys, labels)| {
+                let keys: Vec<String> = keys.iter().map(|k| format!("{k:X}")).collect();
+                let labels: Vec<String> = labels.iter().map(char::to_string).collect();
+                format!("{}   {}", keys.join(" "), labels.join(" "))
+            })
+            .collect();
+        lines.push(String::new());
+        let keys: Vec<(String, &str)> = hotkeys
+            .iter()
+            .map(|(key, action)| (key.to_string(), action.description()))
+            .collect();
+        let key_width = keys.iter().map(|(key, _)| key.len()).max().unwrap_or(0);
+        lines.extend(
+            keys.iter()
+                .map(|(key, what)| format!("{key:<key_width$}  {what}")),
+        );
+
+        let chars = lines.iter().map(|text| text.chars().count());
+        let width = (chars.max().unwrap_or(0) * GLYPH_ADVANCE + 1) as f32 * scale;
+        let height = lines.len() as f32 * line + scale;
+        let left = ((self.size.width as f32 - width) / 2.0).floor();
+        let top = ((self.size.height as f32 - height) / 2.0).max(0.0).floor();
+        self.fill(left, top, width, height, BOX_COLOR);
+        for (i, text) in lines.iter().enumerate() {
+            let color = if i < 4 { MAPPED_COLOR } else { TEXT_COLOR };
+            self.text(left + scale, top + scale + i as f32 * line, text, color);
+        }
+    }
+
+    /// Draw the queued quads over `view` in their own render pass.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "trace", skip_all))]
+    pub fn render(
+        &self,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        view: &wgpu::TextureView,
+    ) {
+        if self.quads.is_empty() {
+            return;
+        }
+        queue.write_buffer(&self.quad_buffer, 0, bytemuck::cast_slice(&self.quads));
+
+        let mut renderpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Overlay Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view,
+                depth_slice: None,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+
+        renderpass.set_pipeline(&self.pipeline);
+        renderpass.set_bind_group(0, &self.atlas_bind_group, &[]);
+        renderpass.set_vertex_buffer(0, self.quad_buffer.slice(..));
+        renderpass.draw(0..4, 0..self.quads.len() as u32);
+    }
+}
+
+/// Formats "<fps> FPS" into `buf` without allocating.
+fn fps_text(fps: u32, buf: &mut [u8; 16]) -> &str {
+    use std::io::Write;
+
+    let mut cursor = std::io::Cursor::new(&mut buf[..]);
+    let _ = write!(cursor, "{fps} FPS");
+    let len = cursor.position() as usize;
+    std::str::from_utf8(&buf[..len]).unwrap_or("")
+}
+
+/// A [`Profile`] as text, formatted again only when it changes.
+#[derive(Debug, Default)]
+pub struct ProfileText {
+    profile: Option<Profile>,
+    text: String,
+}
+
+impl ProfileText {
+    /// The text for `profile`.
+    pub fn get(&mut self, profile: Profile) -> &str {
+        if self.profile != Some(profile) {
+            self.profile = Some(profile);
+            self.text = profile.to_string();
+        }
+        &self.text
+    }
+}
+
+/// Presented frames per second, updated every second.
+#[derive(Debug, Clone, Copy)]
+pub struct FpsCounter {
+    since: web_time::Instant,
+    frames: u32,
+    fps: u32,
+}
+
+impl Default for FpsCounter {
+    fn default() -> Self {
+        Self {
+            since: web_time::Instant::now(),
+            frames: 0,
+            fps: 0,
+        }
+    }
+}
+
+impl FpsCounter {
+    /// Count a presented frame.
+    pub fn tick(&mut self) {
+        self.frames += 1;
+        let elapsed = self.since.elapsed();
+        if elapsed.as_secs_f32() >= 1.0 {
+            self.fps = (self.frames as f32 / elapsed.as_secs_f32()).round() as u32;
+            self.frames = 0;
+            self.since = web_time::Instant::now();
+        }
+    }
+
+    /// Frames presented in the last full second.
+    pub fn fps(&self) -> u32 {
+        self.fps
+    }
+}
@@ -0,0 +1,45 @@
+// Overlay pass, see overlay.rs
+
+// One quad per instance, drawn as a 4 vertex triangle strip.
+struct InstanceInput {
+    // Left, top, width, and height in clip space
+    @location(0) rect: vec4<f32>,
+    // Top left and bottom right in the atlas
+    @location(1) uv: vec4<f32>,
+    @location(2) color: vec4<f32>,
+};
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+    @location(1) color: vec4<f32>,
+};
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32, in: InstanceInput) -> VertexOutput {
+    var out: VertexOutput;
+
+    // (0, 0), (1, 0), (0, 1), (1, 1)
+    let corner: vec2<f32> = vec2<f32>(f32(index & 1u), f32(index >> 1u));
+    out.position = vec4<f32>(
+        in.rect.x + corner.x * in.rect.z,
+        in.rect.y - corner.y * in.rect.w,
+        0.0,
+        1.0
+    );
+    out.uv = mix(in.uv.xy, in.uv.zw, corner);
+    out.color = in.color;
+
+    return out;
+}
+
+@group(0) @binding(0)
+var t_atlas: texture_2d<f32>;
+@group(0) @binding(1)
+var s_atlas: sampler;
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let coverage: f32 = textureSample(t_atlas, s_atlas, in.uv).r;
+    return vec4<f32>(in.color.rgb, in.color.a * coverage);
+}
@@ -2,11 +2,13 @@
 
 struct VertexInput {
     @location(0) position: vec3<f32>,
+    @location(1) tex_coords: vec2<f32>,
 };
 
 struct VertexOutput {
     @builtin(position) position: vec4<f32>,
     @location(0) tex_coords: vec2<f32>,
+    @location(1) window_uv: vec2<f32>,
 };
 
 @vertex
@@ -16,9 +18,12 @@ fn vs_main(in: VertexInput) -> VertexOutput {
     // Position: as is
     out.position = vec4<f32>(in.position, 1.0);
 
-    // Texture: Convert clip space to [0, 1] and flip y
-    out.tex_coords = (in.position.xy / 2.0) + 0.5;
-    out.tex_coords.y = 1.0 - out.tex_coords.y;
+    // Texture: as is, rotated on the cpu
+    out.tex_coords = in.tex_coords;
+
+    // Window: Convert clip space to [0, 1] and flip y
+    out.window_uv = (in.position.xy / 2.0) + 0.5;
+    out.window_uv.y = 1.0 - out.window_uv.y;
 
     return out;
 }
@@ -32,6 +37,53 @@ var t_diffuse: texture_2d<f32>;
 @group(0) @binding(1)
 var s_diffuse: sampler;
 
+// Magnifier overlay, see magnifier.rs
+
+struct Magnifier {
+    // Top left of the region in screen pixels
+    cursor: vec2<f32>,
+    // Width and height of the region in screen pixels
+    size: f32,
+    enabled: u32,
+};
+
+@group(1) @binding(0)
+var<uniform> magnifier: Magnifier;
+
+// Overlay in the top right corner of the window, square on a 2:1 window
+const OVERLAY_MIN: vec2<f32> = vec2<f32>(0.68, 0.04);
+const OVERLAY_MAX: vec2<f32> = vec2<f32>(0.96, 0.60);
+const CURSOR_COLOR: vec3<f32> = vec3<f32>(1.0, 0.8, 0.0);
+const GRID_COLOR: vec3<f32> = vec3<f32>(0.2, 0.2, 0.2);
+
+// Returns true if `uv` is on the outline of the magnified region.
+fn on_cursor(uv: vec2<f32>) -> bool {
+    let texel: vec2<f32> = uv * vec2<f32>(64.0, 32.0);
+    let outer: bool = all(texel >= magnifier.cursor - 0.2)
+        && all(texel <= magnifier.cursor + magnifier.size + 0.2);
+    let inner: bool = all(texel > magnifier.cursor + 0.2)
+        && all(texel < magnifier.cursor + magnifier.size - 0.2);
+    return outer && !inner;
+}
+
+// Returns the magnified color at `window_uv` inside the overlay, or
+// `color` outside of it.
+fn magnify(window_uv: vec2<f32>, color: vec3<f32>) -> vec3<f32> {
+    let local: vec2<f32> = (window_uv - OVERLAY_MIN) / (OVERLAY_MAX - OVERLAY_MIN);
+    if any(local < vec2<f32>(0.0)) || any(local >= vec2<f32>(1.0)) {
+        return color;
+    }
+    if any(local < vec2<f32>(0.01)) || any(local > vec2<f32>(0.99)) {
+        return CURSOR_COLOR;
+    }
+
+    let texel: vec2<f32> = local * magnifier.size;
+    if any(fract(texel) < vec2<f32>(0.06)) {
+        return GRID_COLOR;
+    }
+    return textureLoad(t_diffuse, vec2<i32>(magnifier.cursor + floor(texel)), 0).rgb;
+}
+
 fn box_blur(uv: vec2<f32>) -> vec3<f32> {
     var boxBlurColor: vec3<f32> = vec3<f32>(0.0);
     let kernelSize: i32 = 3;
@@ -73,5 +125,12 @@ fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
         color = mix(color, vec3<f32>(0.05, 0.1, 0.55), 0.45);
     }
 
+    if magnifier.enabled == 1u {
+        if on_cursor(uv) {
+            color = CURSOR_COLOR;
+        }
+        color = magnify(in.window_uv, color);
+    }
+
     return vec4<f32>(color, 1.0);
 }
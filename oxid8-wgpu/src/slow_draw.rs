@@ -0,0 +1,86 @@
+//! Slow sprite drawing, toggled from the debugger window.
+//!
+//! Emulation stops before each Dxyn, which is then drawn onto the
+//! screen a bit at a time before it runs. See `oxid8_core::slowdraw`.
+
+use oxid8_core::{Oxid8, SCREEN_AREA, slowdraw::SlowDraw};
+
+/// Frames each bit is shown for at first, 15 bits a second at 60Hz.
+const DEFAULT_FRAMES_PER_BIT: u32 = 4;
+
+/// Slowest the animation goes, a bit every half second at 60Hz.
+#[cfg(feature = "debugger")]
+const MAX_FRAMES_PER_BIT: u32 = 30;
+
+#[derive(Debug)]
+pub struct SlowDrawMode {
+    frames_per_bit: u32,
+    /// The draw being animated and frames until its next bit.
+    draw: Option<(SlowDraw, u32)>,
+}
+
+impl Default for SlowDrawMode {
+    fn default() -> Self {
+        Self {
+            frames_per_bit: DEFAULT_FRAMES_PER_BIT,
+            draw: None,
+        }
+    }
+}
+
+impl SlowDrawMode {
+    /// Emulate a frame, or the next bit of a draw. A draw's instruction
+    /// runs once every bit has been shown.
+    pub fn frame(&mut self, emu: &mut Oxid8) -> Result<(), String> {
+        match &mut self.draw {
+            Some((_, wait)) if *wait > 1 => *wait -= 1,
+            Some((draw, wait)) => {
+                if draw.step() {
+                    *wait = self.frames_per_bit;
+                } else {
+                    self.draw = None;
+                    emu.run_cycle()?;
+                }
+            }
+            None => {
+                self.draw = emu
+                    .next_frame_until_draw(&[])?
+                    .map(|draw| (draw, self.frames_per_bit));
+            }
+        }
+        Ok(())
+    }
+
+    /// The screen to show, part way through a draw or the emulator's.
+    pub fn screen<'a>(&'a self, emu: &'a Oxid8) -> &'a [bool; SCREEN_AREA] {
+        match &self.draw {
+            Some((draw, _)) => draw.screen(),
+            None => emu.screen_ref(),
+        }
+    }
+
+    /// The bit being drawn, with the progress through the sprite.
+    pub fn status(&self) -> Option<String> {
+        let (draw, _) = self.draw.as_ref()?;
+        let (drawn, bits) = draw.progress();
+        let collision = if draw.collision() { ", VF = 1" } else { "" };
+        Some(format!("{} ({drawn}/{bits}){collision}", draw.status()))
+    }
+
+    #[cfg(feature = "debugger")]
+    pub fn frames_per_bit(&self) -> u32 {
+        self.frames_per_bit
+    }
+
+    /// Show each bit for more frames.
+    #[cfg(feature = "debugger")]
+    pub fn slower(&mut self) {
+        self.frames_per_bit = (self.frames_per_bit + 1).min(MAX_FRAMES_PER_BIT);
+    }
+
+    /// Show each bit for fewer frames.
+    #[cfg(feature = "debugger")]
+    pub fn faster(&mut self) {
+        self.frames_per_bit = self.frames_per_bit.saturating_sub(1).max(1);
+    }
+}
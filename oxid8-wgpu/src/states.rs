@@ -0,0 +1,66 @@
+//! Savestate slots, see `oxid8_core::states`. Natively they are files
+//! shared with the terminal frontends, on the web they are kept in
+//! `localStorage` as base64 text.
+
+use oxid8_core::states::StateStore;
+
+#[cfg(not(target_arch = "wasm32"))]
+pub type Store = StateStore;
+#[cfg(target_arch = "wasm32")]
+pub type Store = StateStore<LocalStorage>;
+
+/// The store in `<data_dir>/oxid8/states`.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn store() -> Option<Store> {
+    dirs::data_dir().map(|dir| StateStore::new(dir.join("oxid8").join("states")))
+}
+
+/// The store in the page's `localStorage`, if the browser allows it.
+#[cfg(target_arch = "wasm32")]
+pub fn store() -> Option<Store> {
+    let storage = web_sys::window()?.local_storage().ok()??;
+    Some(StateStore::with_storage(LocalStorage(storage)))
+}
+
+/// Keys under `oxid8/states/` in `localStorage`.
+#[cfg(target_arch = "wasm32")]
+pub struct LocalStorage(web_sys::Storage);
+
+#[cfg(target_arch = "wasm32")]
+impl LocalStorage {
+    const PREFIX: &str = "oxid8/states/";
+}
+
+#[cfg(target_arch = "wasm32")]
+impl oxid8_core::states::Storage for LocalStorage {
+    fn read(&self, key: &str) -> std::io::Result<Option<Vec<u8>>> {
+        use base64::{Engine, engine::general_purpose::STANDARD};
+
+        let text = self
+            .0
+            .get_item(&format!("{}{key}", Self::PREFIX))
+            .map_err(js_error)?;
+        text.map(|text| STANDARD.decode(text).map_err(std::io::Error::other))
+            .transpose()
+    }
+
+    fn write(&mut self, key: &str, bytes: &[u8]) -> std::io::Result<()> {
+        use base64::{Engine, engine::general_purpose::STANDARD};
+
+        // Fails when the quota is used up.
+        self.0
+            .set_item(&format!("{}{key}", Self::PREFIX), &STANDARD.encode(bytes))
+            .map_err(js_error)
+    }
+
+    fn remove(&mut self, key: &str) -> std::io::Result<()> {
+        self.0
+            .remove_item(&format!("{}{key}", Self::PREFIX))
+            .map_err(js_error)
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+fn js_error(err: wasm_bindgen::JsValue) -> std::io::Error {
+    std::io::Error::other(format!("{err:?}"))
+}
@@ -1,23 +1,31 @@
 //! The Chip-8 screen is drawn to a texture and rendered to a
-//! full screen quad.
+//! full screen quad. Where compute shaders are available the texture is
+//! filled on the GPU, see `unpack`.
 
 use anyhow::Result;
 
-use oxid8_core::{SCREEN_AREA, SCREEN_HEIGHT, SCREEN_WIDTH};
+use oxid8_core::{
+    SCREEN_AREA, SCREEN_HEIGHT, SCREEN_WIDTH,
+    color::{Palette, Rgba},
+    filter::Frame,
+};
 
-const WHITE: [u8; 4] = [255, 255, 255, 255];
-const BLACK: [u8; 4] = [0, 0, 0, 255];
+use crate::unpack::Unpack;
 
 pub struct Texture {
     pub texture: wgpu::Texture,
     pub view: wgpu::TextureView,
     pub sampler: wgpu::Sampler,
     size: wgpu::Extent3d,
+    palette: Palette,
+    /// Fills the texture on the GPU if there are compute shaders.
+    unpack: Option<Unpack>,
 }
 
 impl Texture {
-    /// Create a new empty texture for the render pipeline.
-    pub fn new(device: &wgpu::Device) -> Result<Self> {
+    /// Create a new empty texture for the render pipeline, filled by
+    /// `unpack` if given.
+    pub fn new(device: &wgpu::Device, unpack: Option<Unpack>) -> Result<Self> {
         let size = wgpu::Extent3d {
             width: SCREEN_WIDTH as u32,
             height: SCREEN_HEIGHT as u32,
@@ -31,8 +39,10 @@ impl Texture {
             sample_count: 1,
             dimension: wgpu::TextureDimension::D2,
             format: wgpu::TextureFormat::Rgba8UnormSrgb,
+            // Copied out by the headless tests in `oxid8-tests`.
             usage: wgpu::TextureUsages::TEXTURE_BINDING
-                | wgpu::TextureUsages::COPY_DST,
+                | wgpu::TextureUsages::COPY_DST
+                | wgpu::TextureUsages::COPY_SRC,
             view_formats: &[],
         });
 
@@ -52,26 +62,82 @@ impl Texture {
             view,
             sampler,
             size,
+            palette: Palette::default(),
+            unpack,
         };
 
         Ok(tx)
     }
 
+    /// Set the colors of lit and unlit pixels, defaulting to white
+    /// on black. Takes effect on the next update, returns true if they
+    /// changed.
+    pub fn set_colors(&mut self, foreground: Option<Rgba>, background: Option<Rgba>) -> bool {
+        let default = Palette::default();
+        let palette = Palette {
+            foreground: foreground.unwrap_or(default.foreground),
+            background: background.unwrap_or(default.background),
+        };
+        let changed = palette != self.palette;
+        self.palette = palette;
+        changed
+    }
+
+    /// The colors of lit and unlit pixels.
+    pub fn palette(&self) -> Palette {
+        self.palette
+    }
+
+    /// Returns true if the texture is filled on the GPU.
+    pub fn is_unpacked_on_gpu(&self) -> bool {
+        self.unpack.is_some()
+    }
+
     /// Given the emulator screen, update the texture.
-    pub fn update(&self, queue: &wgpu::Queue, screen: &[bool]) {
-        let mut tx: Vec<u8> = vec![];
-        tx.reserve(4 * SCREEN_AREA);
-
-        for y in 0..SCREEN_HEIGHT {
-            for x in 0..SCREEN_WIDTH {
-                tx.extend_from_slice(if screen[x + y * SCREEN_WIDTH] {
-                    &WHITE
-                } else {
-                    &BLACK
-                });
-            }
+    pub fn update(&self, queue: &wgpu::Queue, screen: &[bool; SCREEN_AREA]) {
+        if let Some(unpack) = &self.unpack {
+            unpack.update(queue, &self.texture, screen, self.palette);
+            return;
+        }
+
+        // On the stack, updates should not allocate.
+        let mut tx = [0u8; 4 * SCREEN_AREA];
+
+        let (foreground, background) = (
+            self.palette.foreground.to_bytes(),
+            self.palette.background.to_bytes(),
+        );
+        for (texel, &pixel) in tx.chunks_exact_mut(4).zip(screen) {
+            texel.copy_from_slice(if pixel { &foreground } else { &background });
+        }
+        self.write(queue, &tx);
+    }
+
+    /// Update the texture with pixels between unlit and lit, see
+    /// `oxid8_core::flash`. Always filled on the CPU.
+    pub fn update_levels(&self, queue: &wgpu::Queue, levels: &[u8; SCREEN_AREA]) {
+        let mut tx = [0u8; 4 * SCREEN_AREA];
+        for (texel, &level) in tx.chunks_exact_mut(4).zip(levels) {
+            let Palette {
+                foreground,
+                background,
+            } = self.palette;
+            texel.copy_from_slice(&foreground.mix(background, level).to_bytes());
         }
+        self.write(queue, &tx);
+    }
+
+    /// Update the texture with a frame in color, see
+    /// `oxid8_core::filter`. Always filled on the CPU.
+    pub fn update_frame(&self, queue: &wgpu::Queue, frame: &Frame) {
+        let mut tx = [0u8; 4 * SCREEN_AREA];
+        for (texel, color) in tx.chunks_exact_mut(4).zip(frame) {
+            texel.copy_from_slice(&color.to_bytes());
+        }
+        self.write(queue, &tx);
+    }
 
+    fn write(&self, queue: &wgpu::Queue, tx: &[u8; 4 * SCREEN_AREA]) {
         queue.write_texture(
             wgpu::TexelCopyTextureInfo {
                 aspect: wgpu::TextureAspect::All,
@@ -79,7 +145,7 @@ impl Texture {
                 mip_level: 0,
                 origin: wgpu::Origin3d::ZERO,
             },
-            &tx,
+            tx,
             wgpu::TexelCopyBufferLayout {
                 offset: 0,
                 bytes_per_row: Some(4 * SCREEN_WIDTH as u32),
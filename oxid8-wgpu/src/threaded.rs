@@ -0,0 +1,184 @@
+//! Emulating on a thread of its own, with `--threaded`.
+//!
+//! At high speeds a frame takes long enough to emulate that doing it
+//! on the render thread delays drawing and input. An [`EmuThread`]
+//! runs the machine on its own thread, paced by its own
+//! [`FrameClock`], and hands the packed screen of the last frame to the
+//! render thread through a triple buffer, see `oxid8_core::triple`, so
+//! drawing never waits on emulation and frames are presented evenly.
+//!
+//! The machine is a [`SharedEmu`] locked for a frame at a time, so
+//! hotkeys, savestates, and the debugger reach it between frames. Keys
+//! go through [`EmuThread::key`] instead, as input events at the start
+//! of the next frame, so a tap between two frames is seen by the game
+//! like it is on the render thread.
+//! The web has no threads and no `--threaded`, there the render thread
+//! always emulates, as it does natively without the flag.
+
+use std::{
+    io,
+    sync::{
+        Arc, Mutex, MutexGuard, PoisonError,
+        atomic::{AtomicBool, AtomicU64, Ordering},
+    },
+    thread::{self, JoinHandle},
+};
+
+use oxid8_core::{
+    Oxid8, SCREEN_AREA,
+    clock::{CatchUp, FrameClock},
+    export::{PACKED_SIZE, unpack_screen},
+    input::InputEvent,
+    triple::{self, Reader},
+};
+use web_time::Instant;
+
+/// The machine, shared with the emulation thread if there is one.
+#[derive(Clone)]
+pub struct SharedEmu(Arc<Mutex<Box<Oxid8>>>);
+
+impl SharedEmu {
+    pub fn new(emu: Box<Oxid8>) -> Self {
+        Self(Arc::new(Mutex::new(emu)))
+    }
+
+    /// Locks the machine, waiting for any frames being emulated.
+    pub fn lock(&self) -> MutexGuard<'_, Box<Oxid8>> {
+        // A panic mid-frame leaves a machine as good as an invalid
+        // opcode would.
+        self.0.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// Runs a [`SharedEmu`] on its own thread until dropped, see the
+/// [module docs](self).
+pub struct EmuThread {
+    frames: Reader<[u8; PACKED_SIZE]>,
+    paused: Arc<AtomicBool>,
+    stop: Arc<AtomicBool>,
+    /// Key events for the next frame.
+    input: Arc<Mutex<Vec<InputEvent>>>,
+    /// Held while emulating frames, so pausing can wait them out.
+    batch: Arc<Mutex<()>>,
+    emulated: Arc<AtomicU64>,
+    handle: Option<JoinHandle<()>>,
+    /// Skipped opcodes already reported.
+    pub listed: usize,
+}
+
+impl EmuThread {
+    /// Starts emulating `emu`, catching up on missed frames like
+    /// `catch_up`.
+    ///
+    /// # Errors
+    ///
+    /// If the thread can't be spawned.
+    pub fn spawn(emu: SharedEmu, catch_up: CatchUp) -> io::Result<Self> {
+        let (mut writer, frames) = triple::buffer(emu.lock().pack_screen());
+        let paused = Arc::new(AtomicBool::new(false));
+        let stop = Arc::new(AtomicBool::new(false));
+        let input = Arc::new(Mutex::new(Vec::new()));
+        let batch = Arc::new(Mutex::new(()));
+        let emulated = Arc::new(AtomicU64::new(0));
+        let listed = emu.lock().skipped().entries().len();
+        let handle = {
+            let (paused, stop) = (Arc::clone(&paused), Arc::clone(&stop));
+            let (input, batch) = (Arc::clone(&input), Arc::clone(&batch));
+            let emulated = Arc::clone(&emulated);
+            thread::Builder::new()
+                .name("oxid8-emu".into())
+                .spawn(move || {
+                    let started = Instant::now();
+                    let mut clock = FrameClock::default();
+                    clock.set_catch_up(catch_up);
+                    let mut events = Vec::new();
+                    while !stop.load(Ordering::Relaxed) {
+                        let now = started.elapsed();
+                        clock.set_timer_hz(emu.lock().timer_rate().hz());
+                        let due = clock.tick(now).timers;
+                        let running = batch.lock().unwrap_or_else(PoisonError::into_inner);
+                        // Frames due while paused are skipped, keys wait
+                        // for the next one run.
+                        if due > 0 && !paused.load(Ordering::Relaxed) {
+                            events
+                                .append(&mut input.lock().unwrap_or_else(PoisonError::into_inner));
+                            for _ in 0..due {
+                                // Stopped machines show what they drew,
+                                // as on the render thread.
+                                let _ = emu.lock().next_frame_with_input(&events);
+                                events.clear();
+                            }
+                            *writer.back() = emu.lock().pack_screen();
+                            writer.publish();
+                            emulated.fetch_add(u64::from(due), Ordering::Release);
+                        }
+                        drop(running);
+                        thread::sleep(clock.until_timer(now));
+                    }
+                })?
+        };
+        Ok(Self {
+            frames,
+            paused,
+            stop,
+            input,
+            batch,
+            emulated,
+            handle: Some(handle),
+            listed,
+        })
+    }
+
+    /// Presses or releases keypad key `k` at the start of the next
+    /// frame.
+    ///
+    /// # Panics
+    ///
+    /// If `k` is not on the keypad.
+    pub fn key(&self, k: usize, pressed: bool) {
+        assert!(k < 0x10, "Key {k:#X} is not on the keypad");
+        let event = InputEvent {
+            cycle: 0,
+            key: k as u8,
+            pressed,
+        };
+        self.input
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(event);
+    }
+
+    /// Pauses or resumes emulation. Pausing waits for the frames being
+    /// emulated, so none are emulated once it returns.
+    pub fn set_paused(&self, paused: bool) {
+        if self.paused.swap(paused, Ordering::Relaxed) == paused {
+            return;
+        }
+        drop(self.batch.lock().unwrap_or_else(PoisonError::into_inner));
+    }
+
+    /// Frames emulated so far.
+    pub fn emulated(&self) -> u64 {
+        self.emulated.load(Ordering::Acquire)
+    }
+
+    /// Takes the newest frame emulated, returning true if there was one
+    /// since the last update.
+    pub fn update(&mut self) -> bool {
+        self.frames.update()
+    }
+
+    /// The screen of the frame taken by the last update.
+    pub fn screen(&self) -> [bool; SCREEN_AREA] {
+        unpack_screen(self.frames.front())
+    }
+}
+
+impl Drop for EmuThread {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
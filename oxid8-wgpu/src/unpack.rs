@@ -0,0 +1,144 @@
+//! Expanding the packed screen to texels on the GPU.
+//!
+//! The screen is uploaded one bit per pixel, 256 bytes, with the two
+//! colors, instead of 8KB of RGBA. A compute pass writes a texel per
+//! pixel to a buffer, which is copied into the screen texture. WebGL
+//! has no compute shaders, so there the texture is filled on the CPU.
+
+use oxid8_core::{SCREEN_AREA, SCREEN_HEIGHT, SCREEN_WIDTH, color::Palette, export::PACKED_SIZE};
+
+/// Pixels per workgroup, as in unpack.wgsl.
+const WORKGROUP_SIZE: usize = 64;
+
+/// The two colors and the packed screen, as in unpack.wgsl.
+const INPUT_WORDS: usize = 2 + PACKED_SIZE / 4;
+
+pub struct Unpack {
+    device: wgpu::Device,
+    pipeline: wgpu::ComputePipeline,
+    bind_group: wgpu::BindGroup,
+    input: wgpu::Buffer,
+    texels: wgpu::Buffer,
+}
+
+impl Unpack {
+    /// The compute pass if the adapter can run compute shaders.
+    pub fn new(device: &wgpu::Device, adapter: &wgpu::Adapter) -> Option<Self> {
+        let flags = adapter.get_downlevel_capabilities().flags;
+        if !flags.contains(wgpu::DownlevelFlags::COMPUTE_SHADERS) {
+            return None;
+        }
+
+        let input = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Unpack Input"),
+            size: (4 * INPUT_WORDS) as u64,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let texels = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Unpack Texels"),
+            size: (4 * SCREEN_AREA) as u64,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+
+        let storage = |binding, read_only| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::COMPUTE,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Storage { read_only },
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[storage(0, true), storage(1, false)],
+            label: Some("unpack_bind_group_layout"),
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: input.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: texels.as_entire_binding(),
+                },
+            ],
+            label: Some("unpack_bind_group"),
+        });
+
+        let shader = device.create_shader_module(wgpu::include_wgsl!("unpack.wgsl"));
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Unpack Pipeline Layout"),
+            bind_group_layouts: &[&layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("Unpack Pipeline"),
+            layout: Some(&pipeline_layout),
+            module: &shader,
+            entry_point: Some("cs_main"),
+            compilation_options: Default::default(),
+            cache: None,
+        });
+
+        Some(Unpack {
+            device: device.clone(),
+            pipeline,
+            bind_group,
+            input,
+            texels,
+        })
+    }
+
+    /// Expand `screen` in the colors of `palette` into `texture`.
+    pub fn update(
+        &self,
+        queue: &wgpu::Queue,
+        texture: &wgpu::Texture,
+        screen: &[bool; SCREEN_AREA],
+        palette: Palette,
+    ) {
+        let mut input = [0u32; INPUT_WORDS];
+        input[0] = u32::from_le_bytes(palette.foreground.to_bytes());
+        input[1] = u32::from_le_bytes(palette.background.to_bytes());
+        let packed = oxid8_core::export::pack_screen(screen);
+        for (word, bytes) in input[2..].iter_mut().zip(packed.chunks_exact(4)) {
+            *word = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+        }
+        queue.write_buffer(&self.input, 0, bytemuck::cast_slice(&input));
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Unpack Encoder"),
+            });
+        {
+            let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: Some("Unpack Pass"),
+                timestamp_writes: None,
+            });
+            pass.set_pipeline(&self.pipeline);
+            pass.set_bind_group(0, &self.bind_group, &[]);
+            pass.dispatch_workgroups((SCREEN_AREA / WORKGROUP_SIZE) as u32, 1, 1);
+        }
+        // Rows are 256 bytes, as copies require.
+        encoder.copy_buffer_to_texture(
+            wgpu::TexelCopyBufferInfo {
+                buffer: &self.texels,
+                layout: wgpu::TexelCopyBufferLayout {
+                    offset: 0,
+                    bytes_per_row: Some(4 * SCREEN_WIDTH as u32),
+                    rows_per_image: Some(SCREEN_HEIGHT as u32),
+                },
+            },
+            texture.as_image_copy(),
+            texture.size(),
+        );
+        queue.submit([encoder.finish()]);
+    }
+}
@@ -0,0 +1,25 @@
+// Screen unpack pass, see unpack.rs
+
+struct Input {
+    // RGBA bytes of lit and unlit pixels, red in the low byte
+    foreground: u32,
+    background: u32,
+    // One bit per pixel, row-major, with the leftmost pixel in the high
+    // bit of each byte, read as little-endian words
+    packed: array<u32, 64>,
+};
+
+@group(0) @binding(0)
+var<storage, read> input: Input;
+// One RGBA texel per pixel, copied to the screen texture
+@group(0) @binding(1)
+var<storage, read_write> texels: array<u32>;
+
+@compute @workgroup_size(64)
+fn cs_main(@builtin(global_invocation_id) id: vec3<u32>) {
+    let i = id.x;
+    let byte = i / 8u;
+    let shift = (byte % 4u) * 8u + 7u - i % 8u;
+    let lit = (input.packed[byte / 4u] >> shift) & 1u;
+    texels[i] = select(input.background, input.foreground, lit == 1u);
+}
@@ -0,0 +1,178 @@
+//! Keyboard capture and key hints on the web.
+//!
+//! winit only sees keys while the canvas has focus. While a rom runs,
+//! keys pressed anywhere else on the page, other than in text fields,
+//! are captured at the document level and sent to the app as
+//! [`UserEvent::Key`], and Space, the arrows, and Page Up and Down
+//! don't scroll the page. `setKeyCapture(false)` turns this off for
+//! pages with their own keyboard controls.
+//!
+//! `keyHints()` lists the keyboard key for each keypad key of the keymap
+//! in use, for the page to draw a hint overlay. The document gets an
+//! `oxid8-keymap` event whenever the keymap changes.
+
+use std::cell::Cell;
+
+use oxid8_core::keymap::{KEYPAD, Keymap};
+use wasm_bindgen::{JsCast, prelude::*};
+use winit::{event_loop::EventLoopProxy, keyboard::KeyCode};
+
+use crate::event::UserEvent;
+
+/// Event dispatched on the document when the keymap changes.
+const KEYMAP_EVENT: &str = "oxid8-keymap";
+
+thread_local! {
+    static CAPTURE: Cell<bool> = const { Cell::new(true) };
+    static RUNNING: Cell<bool> = const { Cell::new(false) };
+    static KEYMAP: Cell<Keymap> = const { Cell::new(Keymap::Standard) };
+}
+
+/// Capture keys at the document level while a rom runs, on by default.
+#[wasm_bindgen(js_name = setKeyCapture)]
+pub fn set_key_capture(on: bool) {
+    CAPTURE.set(on);
+}
+
+/// Returns true if keys are captured at the document level.
+#[wasm_bindgen(js_name = keyCapture)]
+pub fn key_capture() -> bool {
+    CAPTURE.get()
+}
+
+/// A keyboard key and the keypad key it presses.
+#[wasm_bindgen(getter_with_clone)]
+pub struct KeyHint {
+    /// The character the key types on a QWERTY keyboard.
+    pub key: String,
+    pub keypad: u8,
+    /// Player 1 or 2 in two player mode.
+    pub player: Option<u8>,
+}
+
+/// The keymap in use, "standard" or "two-player".
+#[wasm_bindgen(js_name = activeKeymap)]
+pub fn active_keymap() -> String {
+    match KEYMAP.get() {
+        Keymap::Standard => "standard".into(),
+        Keymap::TwoPlayer => "two-player".into(),
+    }
+}
+
+/// The keys of the keymap in use, in keypad order row by row.
+#[wasm_bindgen(js_name = keyHints)]
+pub fn key_hints() -> Vec<KeyHint> {
+    let keymap = KEYMAP.get();
+    KEYPAD
+        .iter()
+        .flatten()
+        .filter_map(|&keypad| {
+            Some(KeyHint {
+                key: keymap.label(keypad)?.to_string(),
+                keypad,
+                player: keymap.player(keypad),
+            })
+        })
+        .collect()
+}
+
+/// Capture keys from now on, if turned on.
+pub fn set_running(running: bool) {
+    RUNNING.set(running);
+}
+
+/// Record the keymap in use and tell the page.
+pub fn set_keymap(keymap: Keymap) {
+    if KEYMAP.replace(keymap) == keymap {
+        return;
+    }
+    if let Some(document) = web_sys::window().and_then(|window| window.document())
+        && let Ok(event) = web_sys::Event::new(KEYMAP_EVENT)
+    {
+        let _ = document.dispatch_event(&event);
+    }
+}
+
+/// Listen for keys on `document` and send them through `proxy`.
+pub fn listen(document: &web_sys::Document, proxy: EventLoopProxy<UserEvent>) {
+    for (kind, pressed) in [("keydown", true), ("keyup", false)] {
+        let onkey = Closure::<dyn FnMut(_)>::new({
+            let proxy = proxy.clone();
+            move |event: web_sys::KeyboardEvent| {
+                if !CAPTURE.get() || !RUNNING.get() || handled_elsewhere(&event) {
+                    return;
+                }
+                let code = event.code();
+                if matches!(
+                    code.as_str(),
+                    "Space"
+                        | "ArrowUp"
+                        | "ArrowDown"
+                        | "ArrowLeft"
+                        | "ArrowRight"
+                        | "PageUp"
+                        | "PageDown"
+                ) {
+                    event.prevent_default();
+                }
+                if let Some(key_code) = key_code(&code)
+                    && !event.repeat()
+                {
+                    let _ = proxy.send_event(UserEvent::Key(key_code, pressed));
+                }
+            }
+        });
+
+        let _ = document.add_event_listener_with_callback(kind, onkey.as_ref().unchecked_ref());
+
+        // WARN: Leaking memory in rust, but we want a global handler.
+        onkey.forget();
+    }
+}
+
+/// Keys for the canvas are left to winit, and keys typed into fields
+/// to the fields.
+fn handled_elsewhere(event: &web_sys::KeyboardEvent) -> bool {
+    let canvas_focused = web_sys::window()
+        .and_then(|window| window.document())
+        .and_then(|document| document.active_element())
+        .is_some_and(|element| element.id() == "canvas");
+    let in_field = event
+        .target()
+        .and_then(|target| target.dyn_into::<web_sys::Element>().ok())
+        .is_some_and(|element| matches!(element.tag_name().as_str(), "INPUT" | "TEXTAREA"));
+    canvas_focused || in_field
+}
+
+/// The winit key for a `KeyboardEvent.code` of a keymap key.
+fn key_code(code: &str) -> Option<KeyCode> {
+    use KeyCode::*;
+
+    Some(match code {
+        "Digit1" => Digit1,
+        "Digit2" => Digit2,
+        "Digit3" => Digit3,
+        "Digit4" => Digit4,
+        "Digit8" => Digit8,
+        "Digit9" => Digit9,
+        "KeyQ" => KeyQ,
+        "KeyW" => KeyW,
+        "KeyE" => KeyE,
+        "KeyR" => KeyR,
+        "KeyI" => KeyI,
+        "KeyO" => KeyO,
+        "KeyA" => KeyA,
+        "KeyS" => KeyS,
+        "KeyD" => KeyD,
+        "KeyF" => KeyF,
+        "KeyK" => KeyK,
+        "KeyL" => KeyL,
+        "KeyZ" => KeyZ,
+        "KeyX" => KeyX,
+        "KeyC" => KeyC,
+        "KeyV" => KeyV,
+        "Comma" => Comma,
+        "Period" => Period,
+        _ => return None,
+    })
+}
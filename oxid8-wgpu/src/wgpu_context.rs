@@ -2,27 +2,46 @@
 
 use std::sync::Arc;
 
-use crate::{geometry::*, texture::Texture};
+use crate::{
+    geometry::*,
+    magnifier::{Magnifier, MagnifierUniform},
+    overlay::Overlay,
+    texture::Texture,
+    unpack::Unpack,
+};
 
 use anyhow::Result;
+use oxid8_core::{color::Rgba, rotation::Rotation};
 use wgpu::util::DeviceExt;
 use winit::window::Window;
 
 pub struct WgpuContext {
     pub(crate) window: Arc<Window>,
+    /// Shared with windows opened later, such as the debugger.
+    #[cfg(feature = "debugger")]
+    pub(crate) instance: wgpu::Instance,
+    #[cfg(feature = "debugger")]
+    pub(crate) adapter: wgpu::Adapter,
+    pub(crate) device: wgpu::Device,
     pub(crate) queue: wgpu::Queue,
     pub(crate) texture: Texture,
+    pub(crate) overlay: Overlay,
     pub(crate) is_surface_configured: bool,
 
-    device: wgpu::Device,
     size: winit::dpi::PhysicalSize<u32>,
     surface: wgpu::Surface<'static>,
     surface_format: wgpu::TextureFormat,
     render_pipeline: wgpu::RenderPipeline,
     vertex_buffer: wgpu::Buffer,
+    rotation: Rotation,
+    /// Clear color around the letterboxed screen.
+    border: wgpu::Color,
     index_buffer: wgpu::Buffer,
     num_indices: u32,
     texture_bind_group: wgpu::BindGroup,
+    magnifier: MagnifierUniform,
+    magnifier_buffer: wgpu::Buffer,
+    magnifier_bind_group: wgpu::BindGroup,
 }
 
 impl WgpuContext {
@@ -44,6 +63,7 @@ impl WgpuContext {
             })
             .await
             .unwrap();
+        log::info!("Adapter: {:?}", adapter.get_info());
         let (device, queue) = adapter
             .request_device(&wgpu::DeviceDescriptor {
                 required_features: wgpu::Features::empty(),
@@ -62,7 +82,13 @@ impl WgpuContext {
         let surface_caps = surface.get_capabilities(&adapter);
         let surface_format = surface_caps.formats[0];
 
-        let texture = Texture::new(&device).unwrap();
+        let texture = Texture::new(&device, Unpack::new(&device, &adapter)).unwrap();
+        log::info!(
+            "Screen unpacked on the GPU: {}",
+            texture.is_unpacked_on_gpu()
+        );
+        let mut overlay = Overlay::new(&device, &queue, surface_format);
+        overlay.resize(size);
 
         let texture_bind_group_layout =
             device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
@@ -102,12 +128,43 @@ impl WgpuContext {
             label: Some("diffuse_bind_group"),
         });
 
+        let magnifier = Magnifier::uniform(None);
+        let magnifier_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Magnifier Buffer"),
+            contents: bytemuck::bytes_of(&magnifier),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let magnifier_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                }],
+                label: Some("magnifier_bind_group_layout"),
+            });
+
+        let magnifier_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &magnifier_bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: magnifier_buffer.as_entire_binding(),
+            }],
+            label: Some("magnifier_bind_group"),
+        });
+
         let shader = device.create_shader_module(wgpu::include_wgsl!("shader.wgsl"));
 
         let render_pipeline_layout =
             device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
                 label: Some("Render Pipeline Layout"),
-                bind_group_layouts: &[&texture_bind_group_layout],
+                bind_group_layouts: &[&texture_bind_group_layout, &magnifier_bind_group_layout],
                 push_constant_ranges: &[],
             });
 
@@ -154,8 +211,8 @@ impl WgpuContext {
 
         let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
             label: Some("Vertex Buffer"),
-            contents: bytemuck::cast_slice(VERTICES),
-            usage: wgpu::BufferUsages::VERTEX,
+            contents: bytemuck::cast_slice(&vertices(Rotation::default())),
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
         });
         let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
             label: Some("Index Buffer"),
@@ -167,6 +224,10 @@ impl WgpuContext {
         #[allow(unused_mut)]
         let mut ctx = WgpuContext {
             window,
+            #[cfg(feature = "debugger")]
+            instance,
+            #[cfg(feature = "debugger")]
+            adapter,
             device,
             queue,
             size,
@@ -175,10 +236,16 @@ impl WgpuContext {
             is_surface_configured: false,
             render_pipeline,
             vertex_buffer,
+            rotation: Rotation::default(),
+            border: wgpu::Color::BLACK,
             index_buffer,
             num_indices,
             texture_bind_group,
+            magnifier,
+            magnifier_buffer,
+            magnifier_bind_group,
             texture,
+            overlay,
         };
 
         // Configure surface for the first time
@@ -202,17 +269,82 @@ impl WgpuContext {
         };
         self.surface.configure(&self.device, &surface_config);
         self.is_surface_configured = true;
+        log::debug!(
+            "Configured surface: {}x{} {:?}",
+            self.size.width,
+            self.size.height,
+            self.surface_format
+        );
     }
 
     pub fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
         if new_size.width > 0 && new_size.height > 0 {
             self.size = new_size;
+            self.overlay.resize(new_size);
 
             // reconfigure the surface
             self.configure_surface();
         }
     }
 
+    /// Rotate the displayed screen clockwise.
+    pub fn set_rotation(&mut self, rotation: Rotation) {
+        if rotation != self.rotation {
+            self.rotation = rotation;
+            self.queue.write_buffer(
+                &self.vertex_buffer,
+                0,
+                bytemuck::cast_slice(&vertices(rotation)),
+            );
+        }
+    }
+
+    /// Set the color around the screen, defaulting to black.
+    pub fn set_border(&mut self, border: Option<Rgba>) {
+        self.border = match border {
+            Some(Rgba(r, g, b, a)) => {
+                // Clear colors are linear on sRGB surfaces.
+                let channel = |c: u8| match self.surface_format.is_srgb() {
+                    true => srgb_to_linear(c),
+                    false => c as f64 / 255.0,
+                };
+                wgpu::Color {
+                    r: channel(r),
+                    g: channel(g),
+                    b: channel(b),
+                    a: a as f64 / 255.0,
+                }
+            }
+            None => wgpu::Color::BLACK,
+        };
+    }
+
+    /// The largest rectangle of the screen's aspect ratio centered in
+    /// the window, as x, y, width, and height.
+    fn viewport(&self) -> [f32; 4] {
+        let (w, h) = (self.size.width as f32, self.size.height as f32);
+        let aspect = match self.rotation {
+            Rotation::Deg0 | Rotation::Deg180 => 2.0,
+            Rotation::Deg90 | Rotation::Deg270 => 0.5,
+        };
+        let (width, height) = match w / h > aspect {
+            true => (h * aspect, h),
+            false => (w, w / aspect),
+        };
+        [(w - width) / 2.0, (h - height) / 2.0, width, height]
+    }
+
+    /// Show or hide the magnifier overlay.
+    pub fn set_magnifier(&mut self, magnifier: Option<Magnifier>) {
+        let uniform = Magnifier::uniform(magnifier);
+        if uniform != self.magnifier {
+            self.magnifier = uniform;
+            self.queue
+                .write_buffer(&self.magnifier_buffer, 0, bytemuck::bytes_of(&uniform));
+        }
+    }
+
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "trace", skip_all))]
     pub fn render(&mut self) {
         // We can't render unless the surface is configured
         if !self.is_surface_configured {
@@ -238,7 +370,7 @@ impl WgpuContext {
                     depth_slice: None,
                     resolve_target: None,
                     ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(wgpu::Color::BLUE),
+                        load: wgpu::LoadOp::Clear(self.border),
                         store: wgpu::StoreOp::Store,
                     },
                 })],
@@ -247,16 +379,32 @@ impl WgpuContext {
                 occlusion_query_set: None,
             });
 
+            let [x, y, width, height] = self.viewport();
+            renderpass.set_viewport(x, y, width, height, 0.0, 1.0);
             renderpass.set_pipeline(&self.render_pipeline);
             renderpass.set_bind_group(0, &self.texture_bind_group, &[]);
+            renderpass.set_bind_group(1, &self.magnifier_bind_group, &[]);
             renderpass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
             renderpass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
             renderpass.draw_indexed(0..self.num_indices, 0, 0..1);
         } // End the renderpass.
 
+        // Draw overlays on top in a second pass.
+        self.overlay
+            .render(&self.queue, &mut encoder, &texture_view);
+
         // Submit the command in the queue to execute
         self.queue.submit([encoder.finish()]);
         self.window.pre_present_notify();
         surface_texture.present();
     }
 }
+
+/// Converts an sRGB channel to linear light.
+fn srgb_to_linear(c: u8) -> f64 {
+    let c = c as f64 / 255.0;
+    match c <= 0.04045 {
+        true => c / 12.92,
+        false => ((c + 0.055) / 1.055).powf(2.4),
+    }
+}
@@ -0,0 +1,205 @@
+//! Terminal renderer benchmarks.
+//!
+//! Compares the renderers of [`oxid8::render`]: [`Pixels`] on the
+//! half-block and braille canvas markers, and writing half-block
+//! [`cells`] directly, for a full screen of noise and for a
+//! sprite moving over the screen, the usual case in games. Each frame is
+//! rendered into a buffer and diffed against the last like
+//! `Terminal::draw`, so finding the cells to send to the terminal is
+//! included. `Cells (changed rows)` only redraws the rows under
+//! `ScreenChanges::region`, and `static_screen` checks what skipping
+//! unchanged frames, as `oxid-cli` does, saves.
+//!
+//! Run with `cargo bench -p oxid8 --bench render`.
+
+use criterion::{BenchmarkId, Criterion, criterion_group, criterion_main};
+use oxid8::render::{Pixels, cells};
+use oxid8_core::{
+    Oxid8, SCREEN_AREA, SCREEN_HEIGHT, SCREEN_WIDTH, octo,
+    viewport::{View, Zoom},
+};
+use ratatui::{buffer::Buffer, layout::Rect, style::Color, widgets::Widget};
+use std::{hint::black_box, mem, ops::Range};
+
+type Screen = [bool; SCREEN_AREA];
+
+/// Moves a ball one pixel to the right every four cycles.
+const BALL: &str = "
+: main
+  i := ball
+  v0 := 0
+  v1 := 12
+  sprite v0 v1 8
+  loop
+    sprite v0 v1 8
+    v0 += 1
+    sprite v0 v1 8
+  again
+: ball
+  0x3C 0x7E 0xFF 0xFF 0xFF 0xFF 0x7E 0x3C
+";
+
+/// Cell rows of the half-block renderers.
+const ROWS: Range<usize> = 0..16;
+
+#[derive(Clone, Copy, Debug)]
+enum Renderer {
+    HalfBlock,
+    Braille,
+    Cells,
+}
+
+impl Renderer {
+    const ALL: [Renderer; 3] = [Renderer::HalfBlock, Renderer::Braille, Renderer::Cells];
+
+    /// The whole screen at the zoom drawn, cells draw half blocks.
+    fn view(self) -> View {
+        View {
+            zoom: match self {
+                Renderer::Braille => Zoom::Braille,
+                Renderer::HalfBlock | Renderer::Cells => Zoom::HalfBlock,
+            },
+            x: 0,
+            y: 0,
+            width: SCREEN_WIDTH,
+            height: SCREEN_HEIGHT,
+        }
+    }
+
+    /// Cells that fit the screen, braille packs 2x4 pixels into each.
+    fn area(self) -> Rect {
+        let (width, height) = self.view().zoom.cells(SCREEN_WIDTH, SCREEN_HEIGHT);
+        Rect::new(0, 0, width as u16, height as u16)
+    }
+
+    fn render(self, screen: &Screen, buf: &mut Buffer) {
+        if let Renderer::Cells = self {
+            return cells(screen, ROWS, buf);
+        }
+        let pixels = Pixels {
+            screen,
+            view: self.view(),
+            color: Color::White,
+        };
+        pixels.render(self.area(), buf);
+    }
+}
+
+/// Renders a frame from scratch and diffs it against the last, like
+/// `Terminal::draw`. Returns the number of cells that changed.
+fn frame(renderer: Renderer, screen: &Screen, last: &mut Buffer, next: &mut Buffer) -> usize {
+    next.reset();
+    renderer.render(screen, next);
+    let changed = last.diff(next).len();
+    mem::swap(last, next);
+    changed
+}
+
+/// A screen of noise from a simple LCG.
+fn noise(seed: u32) -> Screen {
+    let mut state = seed;
+    std::array::from_fn(|_| {
+        state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+        state >> 31 == 1
+    })
+}
+
+fn ball() -> Oxid8 {
+    let mut emu = Oxid8::new();
+    emu.load_rom_bytes(&octo::assemble(BALL).unwrap()).unwrap();
+    for _ in 0..5 {
+        emu.run_cycle().unwrap();
+    }
+    emu.take_screen_changes();
+    emu
+}
+
+/// Moves the ball one pixel.
+fn step(emu: &mut Oxid8) {
+    for _ in 0..4 {
+        emu.run_cycle().unwrap();
+    }
+}
+
+fn full_screen(c: &mut Criterion) {
+    let screens = [noise(1), noise(2)];
+    let mut group = c.benchmark_group("full_screen");
+    for renderer in Renderer::ALL {
+        let mut last = Buffer::empty(renderer.area());
+        let mut next = Buffer::empty(renderer.area());
+        let mut i = 0;
+        group.bench_function(BenchmarkId::from_parameter(format!("{renderer:?}")), |b| {
+            b.iter(|| {
+                i ^= 1;
+                black_box(frame(renderer, &screens[i], &mut last, &mut next))
+            })
+        });
+    }
+    group.finish();
+}
+
+fn sprite_delta(c: &mut Criterion) {
+    let mut group = c.benchmark_group("sprite_delta");
+    for renderer in Renderer::ALL {
+        let mut emu = ball();
+        let mut last = Buffer::empty(renderer.area());
+        let mut next = Buffer::empty(renderer.area());
+        group.bench_function(BenchmarkId::from_parameter(format!("{renderer:?}")), |b| {
+            b.iter(|| {
+                step(&mut emu);
+                black_box(frame(renderer, emu.screen_ref(), &mut last, &mut next))
+            })
+        });
+    }
+
+    // Both buffers are kept up to date so only the changed rows are
+    // written to either.
+    let mut emu = ball();
+    let area = Renderer::Cells.area();
+    let mut last = Buffer::empty(area);
+    let mut next = Buffer::empty(area);
+    group.bench_function(BenchmarkId::from_parameter("Cells (changed rows)"), |b| {
+        b.iter(|| {
+            step(&mut emu);
+            let Some(region) = emu.take_screen_changes().region else {
+                return 0;
+            };
+            let rows = region.y / 2..(region.y + region.height).div_ceil(2);
+            cells(emu.screen_ref(), rows.clone(), &mut next);
+            let changed = black_box(last.diff(&next).len());
+            cells(emu.screen_ref(), rows, &mut last);
+            changed
+        })
+    });
+    group.finish();
+}
+
+fn static_screen(c: &mut Criterion) {
+    let mut group = c.benchmark_group("static_screen");
+    let mut emu = ball();
+    let area = Renderer::Cells.area();
+    let mut last = Buffer::empty(area);
+    let mut next = Buffer::empty(area);
+    group.bench_function("redraw", |b| {
+        b.iter(|| {
+            black_box(frame(
+                Renderer::Cells,
+                emu.screen_ref(),
+                &mut last,
+                &mut next,
+            ))
+        })
+    });
+    group.bench_function("skipped", |b| {
+        b.iter(|| {
+            if emu.take_screen_changes().is_empty() {
+                return 0;
+            }
+            frame(Renderer::Cells, emu.screen_ref(), &mut last, &mut next)
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, full_screen, sprite_delta, static_screen);
+criterion_main!(benches);
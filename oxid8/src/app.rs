@@ -1,45 +1,291 @@
 use crate::screens::Screen;
-use crate::screens::{game::Game, menu::Menu};
+use crate::screens::{
+    debug::Debug, game::Game, help::Help, library::LibraryScreen, menu::Menu, splash::SplashScreen,
+};
 
+use crate::bandwidth::Bandwidth;
+use crate::library::Library;
+use crate::suspend::Resume;
+use crossterm::{
+    event::{
+        self, DisableBracketedPaste, DisableMouseCapture, EnableBracketedPaste, EnableMouseCapture,
+    },
+    terminal::EnterAlternateScreen,
+};
+use oxid8_core::{
+    builtin::CONTROLS_TEST_NAME, config::GameConfig, frontend::Assist, hotkeys::Hotkeys,
+    playlist::Playlist, rom, savestate::SaveState,
+};
 use ratatui::{DefaultTerminal, Frame};
-use std::io;
+use std::{
+    borrow::Cow,
+    fmt, io,
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+
+/// Where the selected ROM is loaded from.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RomSource {
+    File(PathBuf),
+    /// The controls test, see [`oxid8_core::builtin`].
+    Builtin,
+    /// [`AppState::pasted`].
+    Pasted,
+}
+
+impl RomSource {
+    /// The file stem, or the name shown for ROMs not read from disk.
+    pub fn stem(&self) -> Cow<'_, str> {
+        match self {
+            Self::File(path) => path
+                .file_stem()
+                .map_or("oxid8".into(), |stem| stem.to_string_lossy()),
+            Self::Builtin | Self::Pasted => self.to_string().into(),
+        }
+    }
+}
+
+impl fmt::Display for RomSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::File(path) => path.display().fmt(f),
+            Self::Builtin => f.write_str(CONTROLS_TEST_NAME),
+            Self::Pasted => f.write_str("Pasted ROM"),
+        }
+    }
+}
 
 #[derive(Default)]
 pub struct App {
     menu: Menu,
+    library: LibraryScreen,
     game: Game,
+    debug: Debug,
+    help: Help,
+    /// Shown at startup, dropped once done.
+    splash: Option<SplashScreen>,
     state: AppState,
+    bandwidth: Bandwidth,
 }
 
 #[derive(Default)]
 pub struct AppState {
     pub should_exit: bool,
     pub screen: Screen,
-    pub rom_path: Option<std::path::PathBuf>,
+    /// The selected ROM.
+    pub rom: Option<RomSource>,
+    /// The last ROM pasted as hex, see [`AppState::paste_rom`].
+    pub pasted: Vec<u8>,
+    /// Overrides for the selected ROM, e.g. from a playlist.
+    pub rom_config: GameConfig,
+    /// Savestates taken in game for the debugger, the last two are kept.
+    pub snapshots: Vec<SaveState>,
+    /// Why the selected ROM failed to load, shown in the library.
+    pub error: Option<String>,
+    /// Keep the last seconds of every game for `:dump`.
+    pub flight_recorder: bool,
+    /// Sticky keys and minimum hold for the keypad.
+    pub assist: Assist,
+    /// Damp flashing in every game, see [`oxid8_core::flash`].
+    pub reduce_flashes: bool,
+    /// Draw colors as the nearest of the 256 ANSI colors, see
+    /// [`color`](crate::color).
+    pub ansi_colors: bool,
+    /// Filters for every game, as given to `--filter`, see
+    /// [`oxid8_core::filter`].
+    pub filters: String,
+    /// Keys for actions in game, see [`HOTKEYS`].
+    ///
+    /// [`HOTKEYS`]: crate::screens::game::HOTKEYS
+    pub hotkeys: Hotkeys,
+    /// Runs before every frame of every game, in place of the scripts
+    /// written for each.
+    #[cfg(feature = "script")]
+    pub script: Option<oxid8_core::script::Script>,
+    /// Speaks what scripts announce, with `--speak`.
+    #[cfg(feature = "script")]
+    pub speech: Option<crate::speech::Speech>,
+}
+
+impl AppState {
+    /// Select a ROM pasted as hex bytes, e.g. `00E0 A22A`, see
+    /// [`rom::parse_hex`].
+    ///
+    /// # Errors
+    ///
+    /// If `text` isn't a ROM written as hex.
+    pub fn paste_rom(&mut self, text: &str) -> Result<(), String> {
+        self.pasted = rom::parse_hex(text)?;
+        self.rom = Some(RomSource::Pasted);
+        self.rom_config = GameConfig::default();
+        self.error = None;
+        self.screen = Screen::Game;
+        Ok(())
+    }
 }
 
 impl App {
+    /// Start in a library of the playlist's entries instead of the
+    /// scanned directories.
+    pub fn with_playlist(mut self, playlist: Playlist) -> Self {
+        self.library = LibraryScreen::new(Library::from_playlist(playlist));
+        self.state.screen = Screen::Library;
+        self
+    }
+
+    /// Show the splash before the first screen, see [`SplashScreen`].
+    pub fn with_splash(mut self) -> Self {
+        let next = std::mem::take(&mut self.state.screen);
+        self.splash = Some(SplashScreen::new(next));
+        self.state.screen = Screen::Splash;
+        self
+    }
+
+    /// Keep the last seconds of every game in memory, for `:dump`.
+    pub fn with_flight_recorder(mut self) -> Self {
+        self.state.flight_recorder = true;
+        self
+    }
+
+    /// Apply `assist` to keypad input in every game.
+    pub fn with_assist(mut self, assist: Assist) -> Self {
+        self.state.assist = assist;
+        self
+    }
+
+    /// Damp rapid flashing in every game.
+    pub fn with_flash_reduction(mut self) -> Self {
+        self.state.reduce_flashes = true;
+        self
+    }
+
+    /// Draw colors as the nearest ANSI colors, for terminals without
+    /// truecolor.
+    pub fn with_ansi_colors(mut self) -> Self {
+        self.state.ansi_colors = true;
+        self
+    }
+
+    /// Post-process the screen of every game with the filters in
+    /// `spec`, e.g. `ghosting=0.5,grayscale`.
+    ///
+    /// # Errors
+    ///
+    /// If `spec` isn't a chain of filters, see [`FilterChain`].
+    ///
+    /// [`FilterChain`]: oxid8_core::filter::FilterChain
+    pub fn with_filters(mut self, spec: &str) -> Result<Self, String> {
+        spec.parse::<oxid8_core::filter::FilterChain>()?;
+        self.state.filters = spec.into();
+        Ok(self)
+    }
+
+    /// Use `hotkeys` in game.
+    pub fn with_hotkeys(mut self, hotkeys: Hotkeys) -> Self {
+        self.state.hotkeys = hotkeys;
+        self
+    }
+
+    /// Send less to the terminal, see [`Bandwidth`].
+    pub fn with_bandwidth(mut self, bandwidth: Bandwidth) -> Self {
+        self.bandwidth = bandwidth;
+        self
+    }
+
+    #[must_use]
+    pub fn bandwidth(&self) -> Bandwidth {
+        self.bandwidth
+    }
+
+    /// Run `script` before every frame in game.
+    #[cfg(feature = "script")]
+    pub fn with_script(mut self, script: oxid8_core::script::Script) -> Self {
+        self.state.script = Some(script);
+        self
+    }
+
+    /// Speak what scripts announce with `speech`.
+    #[cfg(feature = "script")]
+    pub fn with_speech(mut self, speech: crate::speech::Speech) -> Self {
+        self.state.speech = Some(speech);
+        self
+    }
+
     pub fn run(mut self, terminal: &mut DefaultTerminal) -> io::Result<()> {
+        let resume = Resume::listen()?;
+        let mouse = self.bandwidth.mouse;
+        // Pasted text comes in whole, for ROMs pasted as hex.
+        crossterm::execute!(io::stdout(), EnableBracketedPaste)?;
+        if mouse {
+            crossterm::execute!(io::stdout(), EnableMouseCapture)?;
+        }
+        let mut last_draw = None;
         while !self.state.should_exit {
-            terminal.draw(|frame| self.draw(frame))?;
+            // Back from Ctrl-Z or a stop from outside
+            if resume.resumed() {
+                crossterm::terminal::enable_raw_mode()?;
+                crossterm::execute!(io::stdout(), EnterAlternateScreen, EnableBracketedPaste)?;
+                if mouse {
+                    crossterm::execute!(io::stdout(), EnableMouseCapture)?;
+                }
+                terminal.hide_cursor()?;
+                terminal.clear()?;
+                while event::poll(Duration::ZERO)? {
+                    event::read()?;
+                }
+                self.game.resume();
+                last_draw = None;
+            }
+            // Only the game is throttled, it wakes up every frame to
+            // draw what was held back. The other screens wait for keys.
+            let now = Instant::now();
+            let throttled = matches!(self.state.screen, Screen::Game)
+                && !self.bandwidth.redraw_due(last_draw, now);
+            if !throttled {
+                terminal.draw(|frame| self.draw(frame))?;
+                last_draw = Some(now);
+            }
             self.handle_events()?;
         }
+        if mouse {
+            crossterm::execute!(io::stdout(), DisableMouseCapture)?;
+        }
+        crossterm::execute!(io::stdout(), DisableBracketedPaste)?;
         Ok(())
     }
 
     fn draw(&mut self, frame: &mut Frame) {
         match self.state.screen {
-            Screen::Debug => (),
+            Screen::Debug => self.debug.draw(frame, &self.state),
             Screen::Menu => self.menu.draw(frame),
+            Screen::Library => self.library.draw(frame, &self.state),
             Screen::Game => self.game.draw(frame),
+            Screen::Help => self
+                .help
+                .draw(frame, self.game.keymap(), &self.state.hotkeys),
+            Screen::Splash => {
+                if let Some(splash) = &mut self.splash {
+                    splash.draw(frame);
+                }
+            }
         }
     }
 
     fn handle_events(&mut self) -> io::Result<()> {
         match self.state.screen {
-            Screen::Debug => (),
+            Screen::Debug => self.debug.handle_events(&mut self.state)?,
             Screen::Menu => self.menu.handle_events(&mut self.state)?,
+            Screen::Library => self.library.handle_events(&mut self.state)?,
             Screen::Game => self.game.handle_events(&mut self.state)?,
+            Screen::Help => self.help.handle_events(&mut self.state)?,
+            Screen::Splash => match &mut self.splash {
+                Some(splash) => splash.handle_events(&mut self.state)?,
+                None => self.state.screen = Screen::Menu,
+            },
+        }
+        if !matches!(self.state.screen, Screen::Splash) {
+            self.splash = None;
         }
         Ok(())
     }
@@ -0,0 +1,54 @@
+//! Playing over slow or high-latency connections with
+//! `--low-bandwidth`.
+//!
+//! Over SSH every cell redrawn, bell rung, and mouse move reported is
+//! sent down the connection. [`Bandwidth::LOW`] redraws at most ten
+//! times a second, so the frames in between reach the terminal as one
+//! update of the cells that changed, draws with cells even when
+//! terminal graphics are asked for, leaves out the bell, and doesn't
+//! capture the mouse, whose motion is reported on every move.
+
+use std::time::{Duration, Instant};
+
+/// What the terminal frontends send to the terminal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Bandwidth {
+    /// Shortest time between redraws, on top of the frontend's own.
+    pub redraw_interval: Duration,
+    /// Ring the terminal bell for sound.
+    pub bell: bool,
+    /// Draw the screen as an image when asked, e.g. with
+    /// [`kitty`](crate::kitty) graphics.
+    pub images: bool,
+    /// Capture the mouse.
+    pub mouse: bool,
+}
+
+impl Bandwidth {
+    pub const NORMAL: Bandwidth = Bandwidth {
+        redraw_interval: Duration::ZERO,
+        bell: true,
+        images: true,
+        mouse: true,
+    };
+
+    pub const LOW: Bandwidth = Bandwidth {
+        redraw_interval: Duration::from_millis(100),
+        bell: false,
+        images: false,
+        mouse: false,
+    };
+
+    /// Returns true if a redraw at `now` is due, `last` being the last
+    /// redraw.
+    #[must_use]
+    pub fn redraw_due(&self, last: Option<Instant>, now: Instant) -> bool {
+        last.is_none_or(|last| now.duration_since(last) >= self.redraw_interval)
+    }
+}
+
+impl Default for Bandwidth {
+    fn default() -> Self {
+        Bandwidth::NORMAL
+    }
+}
@@ -1,35 +1,62 @@
 use crossterm::{
     cursor,
     event::{
-        self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers, KeyboardEnhancementFlags,
+        self, Event, KeyCode, KeyEvent, KeyEventKind, KeyboardEnhancementFlags,
         PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags,
     },
     queue,
     terminal::{EnterAlternateScreen, LeaveAlternateScreen},
 };
-use oxid8_core::{CPU_TICK, Oxid8, SCREEN_HEIGHT, SCREEN_WIDTH, TIMER_TICK};
+use oxid8::{
+    bandwidth::Bandwidth,
+    kitty::KittyImage,
+    render::Pixels,
+    suspend::{self, Resume},
+};
+use oxid8_core::{
+    CPU_HZ, Oxid8, SCREEN_HEIGHT, SCREEN_WIDTH,
+    breakpoint::Breakpoints,
+    clock::{CatchUp, FrameClock, MAX_FRAMES_DUE},
+    disasm,
+    frontend::{Capabilities, KeyTimeout},
+    layout::Addr,
+    video::ScreenChanges,
+    viewport::{View, Viewport},
+    watchdog::Watchdog,
+};
 use ratatui::{
     backend::CrosstermBackend,
     layout::{Constraint, Flex, Layout, Rect},
     style::Color,
-    symbols::Marker,
-    widgets::canvas::{Canvas, Painter, Shape},
-};
-use signal_hook::{
-    consts::{SIGCONT, SIGTSTP},
-    iterator::Signals,
+    text::Line,
+    widgets::Paragraph,
 };
 use std::{
     env,
-    io::{self, Stdout},
-    process,
-    sync::mpsc,
-    thread,
+    io::{self, Stdout, Write},
+    mem, process,
     time::{Duration, Instant},
 };
 
+/// Shortest time between redraws, the screen is redrawn at most 30
+/// times per second and only when it changed.
+const REDRAW_INTERVAL: Duration = Duration::from_millis(1000 / 30);
+
+/// Lines of the register strip under the game.
+const STRIP_HEIGHT: u16 = 4;
+
+/// Pixels the arrow keys pan a zoomed screen by.
+const PAN_STEP: isize = 8;
+
+/// Id of the screen's image with `--kitty-graphics`.
+const IMAGE_ID: u32 = 8;
+
 struct Config {
+    /// `-` reads the rom from standard input.
     pub rom_path: String,
+    /// Draw the screen as an image with the kitty graphics protocol.
+    pub kitty_graphics: bool,
+    pub bandwidth: Bandwidth,
 }
 
 #[derive(Default)]
@@ -40,23 +67,66 @@ struct Emu {
 
 struct EmuState {
     should_exit: bool,
+    /// Cells the screen can take up, as of the last draw.
     area: Rect,
-    enhanced: bool,
+    /// Zoom and pan of the screen in `area`.
+    viewport: Viewport,
+    capabilities: Capabilities,
+    timeout: KeyTimeout,
+    /// The screen changed since it was last drawn.
+    dirty: bool,
+    last_draw: Option<Instant>,
+    /// The debugger was attached by stepping, the registers are shown
+    /// under the game from then on.
+    debugger: bool,
+    /// Stopped by stepping until `F5`.
+    paused: bool,
+    /// Where the last step stopped early, or why it failed.
+    debug_status: Option<String>,
+    /// Pixels changed since the screen was last drawn.
+    changes: ScreenChanges,
+    /// The screen as an image, with `--kitty-graphics`.
+    kitty: Option<KittyImage>,
+    /// Cells the screen was last drawn in.
+    screen_area: Rect,
+    /// Notices the game waiting on a delay timer that isn't ticking.
+    watchdog: Watchdog,
 }
 
 struct Terminal;
 
 impl Config {
+    /// `oxid-cli <rom>` or `oxid-cli --rom <rom>`, where `-` reads it
+    /// from standard input, with `--kitty-graphics` and
+    /// `--low-bandwidth` anywhere.
     pub fn build(args: &[String]) -> Result<Config, &'static str> {
-        if args.len() >= 2 {
-            Ok(Config {
-                rom_path: args[1].clone(),
-            })
+        const FLAGS: [&str; 2] = ["--kitty-graphics", "--low-bandwidth"];
+        let flag = |name: &str| args.iter().any(|arg| arg == name);
+        let kitty_graphics = flag("--kitty-graphics");
+        let bandwidth = match flag("--low-bandwidth") {
+            true => Bandwidth::LOW,
+            false => Bandwidth::NORMAL,
+        };
+        let args: Vec<&String> = args
+            .iter()
+            .filter(|arg| !FLAGS.contains(&arg.as_str()))
+            .collect();
+        let rom_path = if args.get(1).is_some_and(|arg| *arg == "--rom") {
+            args.get(2)
+                .ok_or("--rom requires a path, or - for stdin")?
+                .to_string()
+        } else if args.len() >= 2 {
+            args[1].clone()
         } else if let Ok(val) = env::var("OXID_ROM") {
-            Ok(Config { rom_path: val })
+            val
         } else {
-            Err("not enough arguments")
-        }
+            return Err("not enough arguments");
+        };
+        Ok(Config {
+            rom_path,
+            kitty_graphics,
+            bandwidth,
+        })
     }
 }
 
@@ -65,10 +135,27 @@ impl Default for EmuState {
         Self {
             should_exit: false,
             area: Rect::default(),
-            enhanced: matches!(
-                crossterm::terminal::supports_keyboard_enhancement(),
-                Ok(true)
-            ),
+            viewport: Viewport::default(),
+            capabilities: Capabilities {
+                // Only with the kitty keyboard protocol.
+                key_release: matches!(
+                    crossterm::terminal::supports_keyboard_enhancement(),
+                    Ok(true)
+                ),
+                // The terminal bell.
+                audio: true,
+                max_frame_rate: None,
+            },
+            timeout: KeyTimeout::default(),
+            dirty: true,
+            last_draw: None,
+            debugger: false,
+            paused: false,
+            debug_status: None,
+            changes: ScreenChanges::default(),
+            kitty: None,
+            screen_area: Rect::default(),
+            watchdog: Watchdog::default(),
         }
     }
 }
@@ -120,20 +207,12 @@ impl Terminal {
 
         Ok(())
     }
-
-    pub fn suspend() -> io::Result<()> {
-        Terminal::exit()?;
-        #[cfg(not(windows))]
-        signal_hook::low_level::raise(SIGTSTP)?;
-        Ok(())
-    }
-
-    pub fn resume() -> io::Result<()> {
-        Terminal::enter()
-    }
 }
 
 fn main() -> io::Result<()> {
+    #[cfg(feature = "tracing")]
+    oxid8::trace::init()?;
+
     let args: Vec<String> = env::args().collect();
     let config = Config::build(&args).unwrap_or_else(|err| {
         eprintln!("Error parsing arguments: {err}");
@@ -150,99 +229,158 @@ fn main() -> io::Result<()> {
 }
 
 fn run(config: Config) -> io::Result<()> {
-    // Install Signal Hooks
-    let (tx, rx) = mpsc::channel();
-    let mut signals = Signals::new([SIGCONT])?;
-    thread::spawn(move || {
-        for signal in signals.forever() {
-            if tx.send(signal).is_err() {
-                break; // Main thread terminated
-            }
-        }
-    });
+    // Emulator, before the terminal so a rom piped in is read first.
+    let mut emu = Emu::default();
+    emu.core.load_rom(&config.rom_path)?;
+    emu.core.load_font();
+    // Cells are cheaper to keep up to date than images on slow links.
+    if config.kitty_graphics && config.bandwidth.images {
+        emu.state.kitty = Some(KittyImage::new(IMAGE_ID));
+    }
+    emu.state.capabilities.audio = config.bandwidth.bell;
+    let redraw_interval = REDRAW_INTERVAL.max(config.bandwidth.redraw_interval);
+
+    let resume = Resume::listen()?;
 
     // Terminal
     let mut terminal = Terminal::init()?;
     terminal.clear()?;
 
-    // Emulator
-    let mut emu = Emu::default();
-    emu.core.load_rom(&config.rom_path)?;
-    emu.core.load_font();
-
-    let mut last_cpu_tick = Instant::now();
-    let mut last_timer_tick = Instant::now();
+    let start = Instant::now();
+    let mut clock = FrameClock::new(CPU_HZ, emu.core.timer_rate().hz());
+    // Hiccups are dropped rather than fast forwarded through.
+    clock.set_catch_up(CatchUp::Drop(MAX_FRAMES_DUE));
 
     while !emu.state.should_exit {
         let time = Instant::now();
 
-        // Poll Signals
-        if let Ok(signal) = rx.try_recv() {
-            match signal {
-                SIGCONT => {
-                    Terminal::resume()?;
-                    terminal.clear()?;
-                }
-                _ => (),
+        // Back from Ctrl-Z or a stop from outside
+        if resume.resumed() {
+            Terminal::enter()?;
+            terminal.clear()?;
+            emu.state.dirty = true;
+            if let Some(kitty) = &mut emu.state.kitty {
+                kitty.invalidate();
+            }
+            // Carry on where the game was suspended, with no time passed
+            // and the keys held then released.
+            clock.restart();
+            emu.core.clear_keys();
+            emu.state.timeout = KeyTimeout::default();
+            while event::poll(Duration::ZERO)? {
+                event::read()?;
             }
         }
 
-        // Emu Cycle
-        if time.duration_since(last_cpu_tick) >= CPU_TICK {
-            if event::poll(Duration::from_secs(0))? {
-                handle_events(&mut emu)?;
-            }
+        clock.set_timer_hz(emu.core.timer_rate().hz());
+        let ticks = clock.tick(time - start);
 
+        // Emu Cycles
+        if (ticks.cycles > 0 || emu.state.paused) && event::poll(Duration::from_secs(0))? {
+            handle_events(&mut emu)?;
+        }
+        // Time doesn't pass while paused.
+        let ticks = match emu.state.paused {
+            true => Default::default(),
+            false => ticks,
+        };
+        for _ in 0..ticks.cycles {
             if let Err(err) = emu.core.run_cycle() {
                 eprintln!("{err}");
             }
-
-            // To support more terminals
-            if !emu.state.enhanced {
-                emu.core.clear_keys();
-            }
-
-            last_cpu_tick += CPU_TICK;
+        }
+        if ticks.cycles > 0 && emu.state.watchdog.check(&emu.core, ticks.cycles).is_some() {
+            // Show why the game looks frozen.
+            emu.state.debugger = true;
+            emu.state.dirty = true;
         }
 
         // Decrement Timers
-        if time.duration_since(last_timer_tick) >= TIMER_TICK {
+        for _ in 0..ticks.timers {
             emu.core.dec_timers();
-            last_timer_tick += TIMER_TICK;
+            emu.core.clear_key_edges();
+            if !emu.state.capabilities.key_release {
+                emu.state.timeout.tick(&mut emu.core);
+            }
+        }
+        if ticks.timers > 0 {
+            let changes = emu.core.take_screen_changes();
+            if !changes.is_empty() {
+                emu.state.dirty = true;
+                emu.state.changes.merge(&changes);
+            }
+        }
+
+        // Redraw on change, at most every `REDRAW_INTERVAL`, or less
+        // often with `--low-bandwidth`
+        let due = emu
+            .state
+            .last_draw
+            .is_none_or(|last| time.duration_since(last) >= redraw_interval);
+        if emu.state.dirty && due {
+            emu.state.dirty = false;
+            emu.state.last_draw = Some(time);
 
             terminal.draw(|frame| {
-                // Clipping area
+                // Drawing area, with the registers below when debugging
+                let strip = if emu.state.debugger { STRIP_HEIGHT } else { 0 };
                 emu.state.area = frame.area();
+                emu.state.area.height = emu.state.area.height.saturating_sub(strip);
 
-                // Rendering half-blocks
-                let width = SCREEN_WIDTH;
-                let height = SCREEN_HEIGHT / 2;
+                // Zoomed to fit unless zoomed in
+                let pixels = Pixels {
+                    screen: emu.core.screen_ref(),
+                    view: emu.view(),
+                    color: Color::White,
+                };
+                let (width, height) = pixels.cells();
 
-                // Drawing area
                 let area = center(
                     frame.area(),
-                    Constraint::Length(width as u16),
-                    Constraint::Length(height as u16),
+                    Constraint::Length(width),
+                    Constraint::Length(height + strip),
                 );
+                let [area, strip_area] =
+                    Layout::vertical([Constraint::Length(height), Constraint::Length(strip)])
+                        .areas(area);
+                if emu.state.debugger {
+                    frame.render_widget(Paragraph::new(register_strip(&emu)), strip_area);
+                }
 
-                frame.render_widget(
-                    Canvas::default()
-                        .x_bounds([0.0, width as f64])
-                        .y_bounds([0.0, height as f64])
-                        .marker(Marker::HalfBlock)
-                        .paint(|ctx| {
-                            ctx.draw(&emu);
-                        }),
-                    area,
-                )
+                // The image is placed over the cells after drawing.
+                emu.state.screen_area = area;
+                if emu.state.kitty.is_some() {
+                    return;
+                }
+                frame.render_widget(&pixels, area)
             })?;
+
+            // Only the pixels changed since the last draw are sent.
+            let changes = mem::take(&mut emu.state.changes);
+            if let Some(kitty) = &mut emu.state.kitty {
+                let mut stdout = io::stdout();
+                kitty.draw(
+                    &mut stdout,
+                    emu.core.screen_ref(),
+                    &changes,
+                    emu.state.screen_area,
+                )?;
+                stdout.flush()?;
+            }
         }
 
-        if emu.core.sound() {
+        if emu.core.sound() && emu.state.capabilities.audio {
             print!("\x07");
         }
+
+        // Sleep until the next cycle or event instead of spinning
+        let now = start.elapsed();
+        event::poll(clock.until_cycle(now).min(clock.until_timer(now)))?;
     }
 
+    if let Some(kitty) = &mut emu.state.kitty {
+        kitty.delete(&mut io::stdout())?;
+    }
     Terminal::exit()
 }
 
@@ -255,15 +393,31 @@ fn center(area: Rect, horizontal: Constraint, vertical: Constraint) -> Rect {
 }
 
 fn handle_events(emu: &mut Emu) -> io::Result<()> {
-    match event::read()? {
+    let event = event::read()?;
+    #[cfg(feature = "tracing")]
+    tracing::debug!(?event, "Terminal event");
+    match event {
+        Event::Key(key_event) if suspend::is_suspend_key(&key_event) => {
+            return suspend::suspend(Terminal::exit);
+        }
+        Event::Resize(..) => emu.state.dirty = true,
+        Event::Key(KeyEvent {
+            code: code @ (KeyCode::F(5) | KeyCode::F(10) | KeyCode::F(11)),
+            kind: KeyEventKind::Press,
+            ..
+        }) => debug(emu, code),
         Event::Key(KeyEvent {
-            code: KeyCode::Char('z'),
-            modifiers: KeyModifiers::CONTROL,
+            code:
+                code @ (KeyCode::Tab | KeyCode::Left | KeyCode::Right | KeyCode::Up | KeyCode::Down),
+            kind: KeyEventKind::Press,
             ..
-        }) => return Terminal::suspend(),
+        }) => zoom(emu, code),
         Event::Key(key_event) if key_event.kind == KeyEventKind::Press => {
             if let Some(k) = handle_key_event(key_event, &mut emu.state) {
-                emu.core.set_key(k as usize, true);
+                match emu.state.capabilities.key_release {
+                    true => emu.core.set_key(k as usize, true),
+                    false => emu.state.timeout.press(&mut emu.core, k as usize),
+                }
             }
         }
         Event::Key(key_event) if key_event.kind == KeyEventKind::Release => {
@@ -276,6 +430,92 @@ fn handle_events(emu: &mut Emu) -> io::Result<()> {
     Ok(())
 }
 
+/// Debugger hotkeys: `F10` steps over a call, `F11` steps into it, and
+/// `F5` continues. Stepping pauses the game and attaches the debugger.
+fn debug(emu: &mut Emu, code: KeyCode) {
+    let state = &mut emu.state;
+    state.dirty = true;
+    if code == KeyCode::F(5) {
+        state.paused = false;
+        state.debug_status = None;
+        return;
+    }
+    if !state.paused {
+        // The first press only stops the game where it is.
+        state.debugger = true;
+        state.paused = true;
+        return;
+    }
+    // A second of cycles at most, in case the call never returns.
+    let result = match code {
+        KeyCode::F(10) => emu.core.step_over(&Breakpoints::default(), CPU_HZ),
+        _ => emu.core.run_cycle().map(|()| true),
+    };
+    state.debug_status = match result {
+        Ok(true) => None,
+        Ok(false) => Some("Call still running, stopped".into()),
+        Err(err) => Some(err),
+    };
+}
+
+/// `Tab` zooms in a step, or back to fitting the screen, and the arrow
+/// keys pan a zoomed screen.
+fn zoom(emu: &mut Emu, code: KeyCode) {
+    let state = &mut emu.state;
+    state.dirty = true;
+    let area = (state.area.width.into(), state.area.height.into());
+    let screen = (SCREEN_WIDTH, SCREEN_HEIGHT);
+    let (dx, dy) = match code {
+        KeyCode::Tab => return state.viewport.cycle(screen, area),
+        KeyCode::Left => (-1, 0),
+        KeyCode::Right => (1, 0),
+        KeyCode::Up => (0, -1),
+        _ => (0, 1),
+    };
+    state
+        .viewport
+        .pan(dx * PAN_STEP, dy * PAN_STEP, screen, area);
+}
+
+/// The registers, the next instruction, and the debugger hotkeys.
+fn register_strip(emu: &Emu) -> Vec<Line<'static>> {
+    let state = emu.core.save_state();
+    let next = disasm::disassemble(&state.ram, Addr::new(state.pc), 1)
+        .first()
+        .map(ToString::to_string)
+        .unwrap_or_default();
+    let v = |range: std::ops::Range<usize>| {
+        range
+            .map(|i| format!("V{i:X} {:02X}", state.v_reg[i]))
+            .collect::<Vec<_>>()
+            .join(" ")
+    };
+    let status = match (&emu.state.debug_status, emu.state.paused) {
+        (Some(status), _) => status.clone(),
+        (None, true) => match emu.core.timer_wait() {
+            // DT doesn't count down while stepping.
+            Some(wait) => format!("{wait}, F5 continue"),
+            None => "F10 over  F11 into  F5 continue".into(),
+        },
+        (None, false) => match emu.state.watchdog.stalled() {
+            Some(wait) => format!("{wait}, which isn't counting down"),
+            None => "Running, F10/F11 to pause".into(),
+        },
+    };
+    vec![
+        Line::from(format!(
+            "I {}  SP {:X}  DT {:02X}  ST {:02X}  {next}",
+            Addr::new(state.i_reg),
+            state.sp,
+            state.dt,
+            state.st
+        )),
+        Line::from(v(0..8)),
+        Line::from(v(8..16)),
+        Line::from(status),
+    ]
+}
+
 fn handle_key_event(key_event: KeyEvent, state: &mut EmuState) -> Option<u8> {
     match key_event.code {
         KeyCode::Esc => {
@@ -308,20 +548,13 @@ fn handle_key_event(key_event: KeyEvent, state: &mut EmuState) -> Option<u8> {
     }
 }
 
-impl Shape for Emu {
-    fn draw(&self, painter: &mut Painter) {
-        let screen_ref = self.core.screen_ref();
-        for y in 0..SCREEN_HEIGHT {
-            for x in 0..SCREEN_WIDTH {
-                if screen_ref[x + y * SCREEN_WIDTH]
-                    && x < self.state.area.width as usize
-                    && y < (self.state.area.height * 2) as usize
-                // WARN: ONLY for rendering half-blocks
-                {
-                    painter.paint(x, y, Color::White);
-                }
-            }
-        }
+impl Emu {
+    /// The part of the screen shown, see [`Viewport::view`].
+    fn view(&self) -> View {
+        let area = (self.state.area.width.into(), self.state.area.height.into());
+        self.state
+            .viewport
+            .view((SCREEN_WIDTH, SCREEN_HEIGHT), area)
     }
 
     /*********************************************************************
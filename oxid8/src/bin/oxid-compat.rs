@@ -0,0 +1,125 @@
+//! A compatibility report for a directory of roms.
+//!
+//! `oxid-compat <dir> [--frames <n>] [--out <dir>] [--format
+//! <markdown|html>]` runs every rom the library finds in `dir` under
+//! the default settings, in parallel, and writes `report.md` or
+//! `report.html` to the output directory, `compat` by default, with a
+//! thumbnail of each rom's final screen in `thumbs` beside it. The
+//! number of roms with each verdict is printed. See
+//! `oxid8_core::compat`.
+
+use oxid8::library::{self, Library};
+use oxid8_core::{
+    compat::{Entry, Report},
+    export,
+};
+use std::{
+    env,
+    fs::{self, File},
+    io::{self, BufWriter},
+    panic,
+    path::PathBuf,
+    process,
+};
+
+const USAGE: &str =
+    "usage: oxid-compat <dir> [--frames <n>] [--out <dir>] [--format <markdown|html>]";
+
+/// Ten seconds at 60Hz.
+const DEFAULT_FRAMES: usize = 600;
+
+/// Pixels per Chip-8 pixel in thumbnails.
+const THUMBNAIL_SCALE: usize = 2;
+
+struct Config {
+    pub rom_dir: PathBuf,
+    pub frames: usize,
+    pub out_dir: PathBuf,
+    pub html: bool,
+}
+
+impl Config {
+    pub fn build(args: &[String]) -> Result<Config, String> {
+        let mut paths = Vec::new();
+        let mut frames = DEFAULT_FRAMES;
+        let mut out_dir = PathBuf::from("compat");
+        let mut html = false;
+        let mut args = args.iter().skip(1);
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--frames" => {
+                    let n = args.next().ok_or("--frames requires a frame count")?;
+                    frames = n.parse().map_err(|_| format!("Invalid frame count: {n}"))?;
+                }
+                "--out" => out_dir = args.next().ok_or("--out requires a directory")?.into(),
+                "--format" => {
+                    html = match args.next().map(String::as_str) {
+                        Some("markdown") => false,
+                        Some("html") => true,
+                        _ => return Err("--format requires markdown or html".into()),
+                    }
+                }
+                _ => paths.push(arg.clone()),
+            }
+        }
+
+        let mut paths = paths.into_iter();
+        let (Some(rom_dir), None) = (paths.next(), paths.next()) else {
+            return Err(USAGE.into());
+        };
+        Ok(Config {
+            rom_dir: rom_dir.into(),
+            frames,
+            out_dir,
+            html,
+        })
+    }
+}
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    let config = Config::build(&args).unwrap_or_else(|err| {
+        eprintln!("Error parsing arguments: {err}");
+        process::exit(1);
+    });
+
+    if let Err(e) = run(config) {
+        eprintln!("Application error: {e}");
+        process::exit(1);
+    }
+}
+
+fn run(config: Config) -> io::Result<()> {
+    let mut library = Library::new(vec![config.rom_dir]);
+    library.scan();
+    let roms: Vec<(String, Vec<u8>)> = library
+        .entries()
+        .iter()
+        .filter_map(|entry| Some((entry.name.clone(), library::read_rom(&entry.path).ok()?)))
+        .collect();
+    if roms.is_empty() {
+        return Err(io::Error::new(io::ErrorKind::NotFound, "No roms found"));
+    }
+
+    // Roms that panic are reported, not printed.
+    panic::set_hook(Box::new(|_| {}));
+    let report = Report::run(roms, config.frames);
+    let _ = panic::take_hook();
+
+    let thumbs = config.out_dir.join("thumbs");
+    fs::create_dir_all(&thumbs)?;
+    for (i, entry) in report.entries.iter().enumerate() {
+        let file = BufWriter::new(File::create(thumbs.join(Entry::thumbnail(i)))?);
+        export::render_scaled(&entry.screen, THUMBNAIL_SCALE, false).write_png(file)?;
+    }
+    let (name, text) = match config.html {
+        true => ("report.html", report.html("thumbs")),
+        false => ("report.md", report.markdown("thumbs")),
+    };
+    let path = config.out_dir.join(name);
+    fs::write(&path, text)?;
+
+    println!("{} roms: {}", report.entries.len(), report.summary());
+    println!("Wrote {}", path.display());
+    Ok(())
+}
@@ -0,0 +1,225 @@
+//! Headless movie playback.
+//!
+//! `oxid-replay <rom> <movie> [--wav <out.wav>] [--repro <out.repro>] [--ansi]
+//! [--log-format <text|json>]` replays a movie recorded with `F7` as
+//! fast as possible, optionally rendering its audio, and prints the hash
+//! of the final screen so captures can be checked in CI. `--ansi` also
+//! prints the final screen itself as ANSI art. `--wav` needs the `audio`
+//! feature.
+//!
+//! `--log-format json` prints one JSON object a line for every frame,
+//! draw, sound starting or stopping, and error instead, ending with the
+//! hash, see `oxid8_core::events`.
+//!
+//! The movie can also be a demo, keys to press and release at given
+//! frames written by hand, played with the settings the rom loads with,
+//! see `oxid8_core::demo`.
+//!
+//! The movie can also be a directory dumped by the flight recorder with
+//! `:dump`, which replays from the savestate in it, see
+//! `oxid8_core::flight`.
+//!
+//! If the core errors or panics, `--repro` writes the state at the
+//! start of the failing frame as a regression fixture, see
+//! `oxid8_core::repro`. Input during that frame is not part of it.
+
+#[cfg(feature = "audio")]
+use oxid8_core::audio::{self, Beeper};
+use oxid8_core::{
+    Oxid8,
+    color::Palette,
+    demo::Demo,
+    events::{Event, EventLog},
+    flight::FlightDump,
+    hash,
+    movie::Movie,
+    repro::Repro,
+    savestate::SaveState,
+};
+use std::{
+    env, fs, io,
+    panic::{self, AssertUnwindSafe},
+    process,
+};
+
+struct Config {
+    pub rom_path: String,
+    pub movie_path: String,
+    #[cfg(feature = "audio")]
+    pub wav_path: Option<String>,
+    pub repro_path: Option<String>,
+    pub ansi: bool,
+    pub json: bool,
+}
+
+impl Config {
+    pub fn build(args: &[String]) -> Result<Config, &'static str> {
+        let mut paths = Vec::new();
+        #[cfg(feature = "audio")]
+        let mut wav_path = None;
+        let mut repro_path = None;
+        let mut ansi = false;
+        let mut json = false;
+        let mut args = args.iter().skip(1);
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                #[cfg(feature = "audio")]
+                "--wav" => wav_path = Some(args.next().ok_or("--wav requires a path")?.clone()),
+                #[cfg(not(feature = "audio"))]
+                "--wav" => return Err("--wav needs oxid8 built with the audio feature"),
+                "--repro" => {
+                    repro_path = Some(args.next().ok_or("--repro requires a path")?.clone())
+                }
+                "--ansi" => ansi = true,
+                "--log-format" => {
+                    json = match args.next().map(String::as_str) {
+                        Some("text") => false,
+                        Some("json") => true,
+                        _ => return Err("--log-format requires text or json"),
+                    }
+                }
+                _ => paths.push(arg.clone()),
+            }
+        }
+        if ansi && json {
+            return Err("--ansi can't be used with --log-format json");
+        }
+
+        match <[String; 2]>::try_from(paths) {
+            Ok([rom_path, movie_path]) => Ok(Config {
+                rom_path,
+                movie_path,
+                #[cfg(feature = "audio")]
+                wav_path,
+                repro_path,
+                ansi,
+                json,
+            }),
+            Err(_) => Err(
+                "usage: oxid-replay <rom> <movie> [--wav <out.wav>] [--repro <out.repro>] [--ansi] [--log-format <text|json>]",
+            ),
+        }
+    }
+}
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    let config = Config::build(&args).unwrap_or_else(|err| {
+        eprintln!("Error parsing arguments: {err}");
+        process::exit(1);
+    });
+
+    if let Err(e) = run(config) {
+        eprintln!("Application error: {e}");
+        process::exit(1);
+    }
+}
+
+fn run(config: Config) -> io::Result<()> {
+    let flight = match fs::metadata(&config.movie_path)?.is_dir() {
+        true => Some(FlightDump::load(&config.movie_path)?),
+        false => None,
+    };
+    let mut emu = Oxid8::new();
+    emu.load_font();
+    emu.load_rom(&config.rom_path)?;
+
+    let movie = match &flight {
+        Some(dump) => dump.movie.clone(),
+        None => load_movie(&config.movie_path, &mut emu)?,
+    };
+    if movie.rom_hash.is_some() && movie.rom_hash.as_deref() != emu.rom_hash() {
+        eprintln!("Warning: movie was recorded with a different rom");
+    }
+    match &flight {
+        Some(dump) => dump.prepare(&mut emu),
+        None => movie.prepare(&mut emu),
+    }
+
+    #[cfg(feature = "audio")]
+    let mut beeper = Beeper::default();
+    #[cfg(feature = "audio")]
+    let mut samples = Vec::new();
+    let mut log = EventLog::new(&mut emu);
+    let mut index = 0;
+    loop {
+        let before = config.repro_path.as_ref().map(|_| emu.save_state());
+        let played = panic::catch_unwind(AssertUnwindSafe(|| match &flight {
+            Some(dump) => dump.play_frame(&mut emu, index),
+            None => movie.play_frame(&mut emu, index),
+        }));
+        let failure = match played {
+            Ok(Ok(true)) => None,
+            Ok(Ok(false)) => break,
+            Ok(Err(e)) => Some(e),
+            Err(_) => Some(format!("Core panicked on frame {index}")),
+        };
+        if let Some(e) = failure {
+            if config.json {
+                println!("{}", log.error(e.as_str()));
+            }
+            if let (Some(path), Some(before)) = (&config.repro_path, before) {
+                write_repro(&mut emu, &before, path)?;
+            }
+            return Err(io::Error::other(e));
+        }
+        #[cfg(feature = "audio")]
+        beeper.render_frame(emu.sound(), emu.timer_rate(), &mut samples);
+        if config.json {
+            for event in log.frame(&mut emu) {
+                println!("{event}");
+            }
+        }
+        index += 1;
+    }
+
+    #[cfg(feature = "audio")]
+    if let Some(path) = &config.wav_path {
+        audio::write_wav(
+            io::BufWriter::new(fs::File::create(path)?),
+            audio::SAMPLE_RATE,
+            &samples,
+        )?;
+    }
+
+    if config.ansi {
+        print!("{}", emu.render_ansi(Palette::default()));
+    }
+    let screen = hash::rom_hash(&emu.pack_screen());
+    match config.json {
+        true => println!(
+            "{}",
+            Event::Done {
+                frames: index,
+                screen
+            }
+        ),
+        false => println!("{index} frames, screen {screen}"),
+    }
+    Ok(())
+}
+
+/// Loads the movie at `path`, or records one of the demo there with the
+/// settings of `emu`.
+fn load_movie(path: &str, emu: &mut Oxid8) -> io::Result<Movie> {
+    let text = fs::read_to_string(path)?;
+    let invalid = |err| io::Error::new(io::ErrorKind::InvalidData, err);
+    match text.starts_with("oxid8-movie") {
+        true => text.parse().map_err(invalid),
+        false => Ok(text.parse::<Demo>().map_err(invalid)?.movie(emu)),
+    }
+}
+
+/// Writes a repro of the frame that failed, starting from `before`.
+fn write_repro(emu: &mut Oxid8, before: &SaveState, path: &str) -> io::Result<()> {
+    emu.restore_state(before);
+    // Frames run more cycles at 50Hz.
+    match Repro::check(emu, 2 * emu.cycles_per_frame()) {
+        Some(repro) => {
+            fs::write(path, repro.to_string())?;
+            eprintln!("Wrote a repro to {path}");
+        }
+        None => eprintln!("The failure depends on input during the frame, no repro written"),
+    }
+    Ok(())
+}
@@ -0,0 +1,96 @@
+//! Finding the quirks a rom depends on.
+//!
+//! `oxid-sweep <rom>` runs the rom under the quirks of every variant
+//! for ten seconds of frames and prints, for each, whether its screen
+//! ever differs from CHIP-8's and on which frame it first did.
+//! `--toggles` also tries each quirk changed on its own, and
+//! `--frames <n>` runs for longer or shorter. See `oxid8_core::sweep`.
+//!
+//! No keys are pressed, so games that wait on a key are only compared
+//! up to there.
+
+use oxid8_core::{
+    rom,
+    sweep::{self, Candidate},
+};
+use std::{env, io, process};
+
+const USAGE: &str = "usage: oxid-sweep <rom> [--frames <n>] [--toggles]";
+
+/// Ten seconds at 60Hz.
+const DEFAULT_FRAMES: usize = 600;
+
+struct Config {
+    pub rom_path: String,
+    pub frames: usize,
+    pub toggles: bool,
+}
+
+impl Config {
+    pub fn build(args: &[String]) -> Result<Config, String> {
+        let mut paths = Vec::new();
+        let mut frames = DEFAULT_FRAMES;
+        let mut toggles = false;
+        let mut args = args.iter().skip(1);
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--frames" => {
+                    let n = args.next().ok_or("--frames requires a frame count")?;
+                    frames = n.parse().map_err(|_| format!("Invalid frame count: {n}"))?;
+                }
+                "--toggles" => toggles = true,
+                _ => paths.push(arg.clone()),
+            }
+        }
+
+        let mut paths = paths.into_iter();
+        let (Some(rom_path), None) = (paths.next(), paths.next()) else {
+            return Err(USAGE.into());
+        };
+        Ok(Config {
+            rom_path,
+            frames,
+            toggles,
+        })
+    }
+}
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    let config = Config::build(&args).unwrap_or_else(|err| {
+        eprintln!("Error parsing arguments: {err}");
+        process::exit(1);
+    });
+
+    if let Err(e) = run(config) {
+        eprintln!("Application error: {e}");
+        process::exit(1);
+    }
+}
+
+fn run(config: Config) -> io::Result<()> {
+    let rom = rom::read(&config.rom_path)?;
+    let mut candidates = Candidate::presets();
+    if config.toggles {
+        candidates.extend(Candidate::toggles());
+    }
+
+    let results = sweep::sweep(&rom, &candidates, config.frames, |_| Vec::new())?;
+    let Some((baseline, rest)) = results.split_first() else {
+        return Ok(());
+    };
+    println!(
+        "Compared with {} ({}) over {} frames",
+        baseline.name, baseline.quirks, config.frames
+    );
+    if let Some(err) = &baseline.outcome.error {
+        println!(
+            "{} failed after {} frames: {err}",
+            baseline.name, baseline.outcome.frames
+        );
+    }
+    for result in rest {
+        println!("{result}");
+    }
+    Ok(())
+}
@@ -0,0 +1,140 @@
+//! Golden-trace comparisons against a reference interpreter.
+//!
+//! `oxid-trace <rom> <trace.csv>` runs the rom in lockstep with a trace
+//! logged by another emulator and prints the first instruction where
+//! they disagree. `oxid-trace <rom> --record <steps>` prints a trace of
+//! our own in the same format. See `oxid8_core::golden` for the schema.
+//! `oxid-trace <rom> --profile <steps>` prints a callgrind profile of
+//! the steps instead, for KCachegrind, see `oxid8_core::profiler`.
+//!
+//! `--quirk <name>=<value>` sets a quirk first, like the `:quirk`
+//! command, so traces from interpreters with other quirks line up.
+
+use oxid8::command::Quirk;
+use oxid8_core::{
+    Oxid8,
+    golden::{Field, Trace},
+    profiler::Profiler,
+    quirks::Quirks,
+};
+use std::{env, fs, io, process};
+
+const USAGE: &str = "usage: oxid-trace <rom> (<trace.csv> | --record <steps> | --profile <steps>) [--quirk <name>=<value>]...";
+
+enum Mode {
+    Compare(String),
+    Record(usize),
+    Profile(usize),
+}
+
+struct Config {
+    pub rom_path: String,
+    pub mode: Mode,
+    pub quirks: Quirks,
+}
+
+impl Config {
+    pub fn build(args: &[String]) -> Result<Config, String> {
+        let mut paths = Vec::new();
+        let mut record = None;
+        let mut profile = None;
+        let mut quirks = Quirks::default();
+        let mut args = args.iter().skip(1);
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--record" => {
+                    let steps = args.next().ok_or("--record requires a step count")?;
+                    record = Some(
+                        steps
+                            .parse()
+                            .map_err(|_| format!("Invalid step count: {steps}"))?,
+                    );
+                }
+                "--profile" => {
+                    let steps = args.next().ok_or("--profile requires a step count")?;
+                    profile = Some(
+                        steps
+                            .parse()
+                            .map_err(|_| format!("Invalid step count: {steps}"))?,
+                    );
+                }
+                "--quirk" => args
+                    .next()
+                    .ok_or("--quirk requires <name>=<value>")?
+                    .parse::<Quirk>()?
+                    .apply(&mut quirks),
+                _ => paths.push(arg.clone()),
+            }
+        }
+
+        let mut paths = paths.into_iter();
+        let (Some(rom_path), trace_path, None) = (paths.next(), paths.next(), paths.next()) else {
+            return Err(USAGE.into());
+        };
+        let mode = match (trace_path, record, profile) {
+            (Some(path), None, None) => Mode::Compare(path),
+            (None, Some(steps), None) => Mode::Record(steps),
+            (None, None, Some(steps)) => Mode::Profile(steps),
+            _ => return Err(USAGE.into()),
+        };
+        Ok(Config {
+            rom_path,
+            mode,
+            quirks,
+        })
+    }
+}
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    let config = Config::build(&args).unwrap_or_else(|err| {
+        eprintln!("Error parsing arguments: {err}");
+        process::exit(1);
+    });
+
+    match run(config) {
+        Ok(true) => {}
+        Ok(false) => process::exit(1),
+        Err(e) => {
+            eprintln!("Application error: {e}");
+            process::exit(1);
+        }
+    }
+}
+
+/// Returns false if the rom diverged from the trace.
+fn run(config: Config) -> io::Result<bool> {
+    let mut emu = Oxid8::new();
+    emu.load_font();
+    emu.load_rom(&config.rom_path)?;
+    emu.set_quirks(config.quirks);
+
+    match config.mode {
+        Mode::Record(steps) => {
+            print!("{}", Trace::record(&mut emu, &Field::ALL, steps).to_csv());
+            Ok(true)
+        }
+        Mode::Profile(steps) => {
+            let mut profiler = Profiler::new();
+            for _ in 0..steps {
+                profiler.run_cycle(&mut emu).map_err(io::Error::other)?;
+            }
+            print!("{}", profiler.to_callgrind());
+            Ok(true)
+        }
+        Mode::Compare(path) => {
+            let trace = Trace::parse_csv(&fs::read_to_string(&path)?)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("{path}: {e}")))?;
+            match emu.compare_trace(&trace).map_err(io::Error::other)? {
+                None => {
+                    println!("{} steps match", trace.len());
+                    Ok(true)
+                }
+                Some(divergence) => {
+                    println!("Diverged at {divergence}");
+                    Ok(false)
+                }
+            }
+        }
+    }
+}
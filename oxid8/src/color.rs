@@ -0,0 +1,25 @@
+//! Drawing the core's colors in the terminal.
+//!
+//! Terminals that set `COLORTERM` to `truecolor` or `24bit` get colors
+//! as they are. Others get the nearest of the 256 ANSI colors, see
+//! [`Rgba::ansi256`], as does `--ansi-colors`.
+
+use std::env;
+
+use oxid8_core::color::Rgba;
+use ratatui::style::Color;
+
+/// Returns true if the terminal says it supports 24-bit color.
+#[must_use]
+pub fn truecolor() -> bool {
+    matches!(env::var("COLORTERM").as_deref(), Ok("truecolor" | "24bit"))
+}
+
+/// `color` as the terminal draws it, the nearest ANSI color if `ansi`.
+#[must_use]
+pub fn term_color(color: Rgba, ansi: bool) -> Color {
+    match ansi {
+        true => Color::Indexed(color.ansi256()),
+        false => Color::Rgb(color.0, color.1, color.2),
+    }
+}
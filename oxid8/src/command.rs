@@ -0,0 +1,370 @@
+//! Commands for the `:` palette in game.
+
+use oxid8_core::{
+    TimerRate, disasm,
+    quirks::{KeyWait, Quirks, RngMode, Shift},
+    variant::Variant,
+    watch::Watch,
+    weights::CycleWeights,
+};
+use std::{fs, path::PathBuf, str::FromStr};
+
+/// A parsed palette command.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    /// Load a rom from a path.
+    Load(PathBuf),
+    /// Set the speed in cycles per frame.
+    Speed(u32),
+    /// Set the timer rate.
+    Rate(TimerRate),
+    /// Set the cycles an instruction costs, by its pattern.
+    Weight(String, u8),
+    /// Change one quirk.
+    Quirk(Quirk),
+    /// Switch the interpreter variant.
+    Variant(Variant),
+    /// Take a savestate into a slot.
+    Save(u8),
+    /// Restore the savestate in a slot.
+    Restore(u8),
+    /// Toggle a breakpoint at an address.
+    Break(u16),
+    /// Resume from a breakpoint.
+    Continue,
+    /// Pause and run one instruction.
+    Step,
+    /// Pause and go back one instruction.
+    Back,
+    /// Show an expression's value beside the game.
+    Watch(Watch),
+    /// Remove the watch with this number, from 1, or every watch.
+    Unwatch(Option<usize>),
+    /// Start or stop recording a movie.
+    Record,
+    /// Write the flight recorder's last seconds to disk.
+    Dump,
+    /// Rotate the display 90 degrees clockwise.
+    Rotate,
+    /// Switch between the standard and two player keymaps.
+    Keymap,
+    /// Show or hide raw key events.
+    Keys,
+    /// Show, hide, or reset the speedrun timer.
+    Timer(Timer),
+    /// Save the current settings for the rom.
+    Settings,
+    /// Open the savestate diff in the debugger.
+    Diff,
+    /// Skip invalid opcodes instead of stopping.
+    Skip(bool),
+    /// Show or hide the memory map.
+    Memory,
+    /// Open the color editor.
+    Colors,
+}
+
+/// A single quirk setting, see [`Quirks`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Quirk {
+    Rng(RngMode),
+    WrapX(bool),
+    WrapY(bool),
+    KeyWait(KeyWait),
+    IOverflow(bool),
+    JumpVx(bool),
+    Shift(Shift),
+}
+
+/// What `:timer` does with the speedrun timer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Timer {
+    Show,
+    Hide,
+    Reset,
+}
+
+/// Command names and their arguments, in the order they are listed.
+pub const COMMANDS: [(&str, &str); 25] = [
+    ("load", "<path>"),
+    ("speed", "<cycles>"),
+    ("rate", "<50|60>"),
+    ("weight", "<instruction>=<cycles>"),
+    ("quirk", "<name>=<value>"),
+    ("variant", "<chip8|schip|xochip>"),
+    ("save", "<slot>"),
+    ("restore", "<slot>"),
+    ("break", "<addr>"),
+    ("continue", ""),
+    ("step", ""),
+    ("back", ""),
+    ("watch", "<expr>"),
+    ("unwatch", "<n|all>"),
+    ("record", ""),
+    ("dump", ""),
+    ("rotate", ""),
+    ("keymap", ""),
+    ("keys", ""),
+    ("timer", "<on|off|reset>"),
+    ("settings", ""),
+    ("diff", ""),
+    ("skip", "<on|off>"),
+    ("memory", ""),
+    ("colors", ""),
+];
+
+/// Quirk names and their values.
+const QUIRKS: [(&str, &[&str]); 7] = [
+    ("rng", &["uniform", "lfsr"]),
+    ("wrap_x", &["on", "off"]),
+    ("wrap_y", &["on", "off"]),
+    ("key_wait", &["release", "press"]),
+    ("i_overflow", &["on", "off"]),
+    ("jump_vx", &["on", "off"]),
+    ("shift", &["vx", "vy"]),
+];
+
+impl Quirk {
+    /// Changes this quirk in `quirks`.
+    pub fn apply(self, quirks: &mut Quirks) {
+        match self {
+            Quirk::Rng(mode) => quirks.rng = mode,
+            Quirk::WrapX(on) => quirks.wrap_x = on,
+            Quirk::WrapY(on) => quirks.wrap_y = on,
+            Quirk::KeyWait(wait) => quirks.key_wait = wait,
+            Quirk::IOverflow(on) => quirks.i_overflow = on,
+            Quirk::JumpVx(on) => quirks.jump_vx = on,
+            Quirk::Shift(shift) => quirks.shift = shift,
+        }
+    }
+}
+
+impl FromStr for Quirk {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, value) = s
+            .split_once('=')
+            .ok_or_else(|| format!("Expected <name>=<value>, got {s}"))?;
+        let on = || match value {
+            "on" | "true" | "1" => Ok(true),
+            "off" | "false" | "0" => Ok(false),
+            _ => Err(format!("Expected on or off, got {value}")),
+        };
+        match name {
+            "rng" => match value {
+                "uniform" => Ok(Quirk::Rng(RngMode::Uniform)),
+                "lfsr" | "vip" => Ok(Quirk::Rng(RngMode::Lfsr)),
+                _ => Err(format!("Expected uniform or lfsr, got {value}")),
+            },
+            "wrap_x" => on().map(Quirk::WrapX),
+            "wrap_y" => on().map(Quirk::WrapY),
+            "key_wait" => match value {
+                "release" => Ok(Quirk::KeyWait(KeyWait::Release)),
+                "press" => Ok(Quirk::KeyWait(KeyWait::Press)),
+                _ => Err(format!("Expected release or press, got {value}")),
+            },
+            "i_overflow" => on().map(Quirk::IOverflow),
+            "jump_vx" => on().map(Quirk::JumpVx),
+            "shift" => match value {
+                "vx" => Ok(Quirk::Shift(Shift::Vx)),
+                "vy" => Ok(Quirk::Shift(Shift::Vy)),
+                _ => Err(format!("Expected vx or vy, got {value}")),
+            },
+            _ => Err(format!("Unknown quirk: {name}")),
+        }
+    }
+}
+
+impl FromStr for Command {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim().trim_start_matches(':');
+        let (name, arg) = s.split_once(' ').unwrap_or((s, ""));
+        let arg = arg.trim();
+        let usage = COMMANDS.iter().find(|(command, _)| *command == name);
+        if let Some((name, args)) = usage
+            && arg.is_empty() != args.is_empty()
+        {
+            return Err(format!("Usage: {name} {args}").trim_end().to_string());
+        }
+
+        let command = match name {
+            "load" => Command::Load(expand(arg)),
+            "speed" => Command::Speed(
+                arg.parse()
+                    .ok()
+                    .filter(|&cycles| cycles > 0)
+                    .ok_or_else(|| format!("Invalid speed: {arg}"))?,
+            ),
+            "rate" => Command::Rate(match arg {
+                "60" | "ntsc" => TimerRate::Ntsc,
+                "50" | "pal" => TimerRate::Pal,
+                _ => return Err(format!("Invalid rate: {arg}")),
+            }),
+            "weight" => {
+                let (pattern, weight) = arg
+                    .split_once('=')
+                    .ok_or_else(|| "Usage: weight <instruction>=<cycles>".to_string())?;
+                let weight = weight
+                    .parse()
+                    .map_err(|_| format!("Invalid weight: {weight}"))?;
+                // Checked here so mistakes show while typing.
+                CycleWeights::default().set(pattern, weight)?;
+                Command::Weight(pattern.to_string(), weight)
+            }
+            "quirk" => Command::Quirk(arg.parse()?),
+            "variant" => Command::Variant(arg.parse()?),
+            "save" => Command::Save(slot(arg)?),
+            "restore" => Command::Restore(slot(arg)?),
+            "break" => Command::Break(addr(arg)?),
+            "continue" => Command::Continue,
+            "step" => Command::Step,
+            "back" => Command::Back,
+            "watch" => Command::Watch(arg.parse()?),
+            "unwatch" => Command::Unwatch(match arg {
+                "all" => None,
+                n => Some(
+                    n.parse()
+                        .ok()
+                        .filter(|&n| n > 0)
+                        .ok_or_else(|| format!("Invalid watch: {n}"))?,
+                ),
+            }),
+            "record" => Command::Record,
+            "dump" => Command::Dump,
+            "rotate" => Command::Rotate,
+            "keymap" => Command::Keymap,
+            "keys" => Command::Keys,
+            "timer" => Command::Timer(match arg {
+                "on" => Timer::Show,
+                "off" => Timer::Hide,
+                "reset" => Timer::Reset,
+                _ => return Err(format!("Invalid timer action: {arg}")),
+            }),
+            "settings" => Command::Settings,
+            "diff" => Command::Diff,
+            "memory" => Command::Memory,
+            "colors" => Command::Colors,
+            "skip" => Command::Skip(match arg {
+                "on" => true,
+                "off" => false,
+                _ => return Err(format!("Invalid skip: {arg}")),
+            }),
+            _ => return Err(format!("Unknown command: {name}")),
+        };
+        Ok(command)
+    }
+}
+
+fn slot(arg: &str) -> Result<u8, String> {
+    arg.parse().map_err(|_| format!("Invalid slot: {arg}"))
+}
+
+/// Hex address, with or without a `0x` prefix.
+fn addr(arg: &str) -> Result<u16, String> {
+    let hex = arg.trim_start_matches("0x").trim_start_matches("0X");
+    u16::from_str_radix(hex, 16)
+        .ok()
+        .filter(|&addr| addr < 0x1000)
+        .ok_or_else(|| format!("Invalid address: {arg}"))
+}
+
+/// Expands a leading `~` to the home directory.
+fn expand(path: &str) -> PathBuf {
+    match (path.strip_prefix("~/"), dirs::home_dir()) {
+        (Some(rest), Some(home)) => home.join(rest),
+        _ => PathBuf::from(path),
+    }
+}
+
+/// Completions for `input`, each a full replacement for it.
+///
+/// Completes command names, quirk names and values, instructions for
+/// `weight`, the arguments of `rate`, `variant`, `timer`, and `skip`, and paths for `load`.
+pub fn complete(input: &str) -> Vec<String> {
+    let Some((name, arg)) = input.split_once(' ') else {
+        return COMMANDS
+            .iter()
+            .filter(|(command, _)| command.starts_with(input))
+            .map(|(command, args)| match args.is_empty() {
+                true => command.to_string(),
+                false => format!("{command} "),
+            })
+            .collect();
+    };
+
+    let args: Vec<String> = match name {
+        "rate" => ["50", "60"].map(String::from).to_vec(),
+        // Only the ones that can be picked.
+        "variant" => Variant::ALL
+            .into_iter()
+            .filter(|variant| variant.is_supported())
+            .map(|variant| variant.to_string().to_lowercase().replace('-', ""))
+            .collect(),
+        "timer" => ["on", "off", "reset"].map(String::from).to_vec(),
+        "skip" => ["on", "off"].map(String::from).to_vec(),
+        "quirk" => match arg.split_once('=') {
+            Some((quirk, _)) => QUIRKS
+                .iter()
+                .filter(|(name, _)| *name == quirk)
+                .flat_map(|(name, values)| values.iter().map(move |v| format!("{name}={v}")))
+                .collect(),
+            None => QUIRKS.iter().map(|(name, _)| format!("{name}=")).collect(),
+        },
+        "weight" => disasm::OPCODES
+            .iter()
+            .map(|info| format!("{}=", info.pattern))
+            .collect(),
+        "load" => complete_path(arg),
+        _ => Vec::new(),
+    };
+    args.into_iter()
+        .filter(|candidate| candidate.starts_with(arg))
+        .map(|candidate| format!("{name} {candidate}"))
+        .collect()
+}
+
+/// Entries of the directory `arg` is in, directories ending in `/`.
+fn complete_path(arg: &str) -> Vec<String> {
+    let (dir, _) = arg.rsplit_once('/').unwrap_or(("", arg));
+    let prefix = match arg.rfind('/') {
+        Some(i) => &arg[..=i],
+        None => "",
+    };
+    let path = match dir {
+        "" if prefix.is_empty() => expand("."),
+        "" => PathBuf::from("/"),
+        dir => expand(dir),
+    };
+    let Ok(entries) = fs::read_dir(path) else {
+        return Vec::new();
+    };
+    let mut paths: Vec<String> = entries
+        .flatten()
+        .map(|entry| {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            match entry.file_type().is_ok_and(|kind| kind.is_dir()) {
+                true => format!("{prefix}{name}/"),
+                false => format!("{prefix}{name}"),
+            }
+        })
+        .collect();
+    paths.sort();
+    paths
+}
+
+/// The longest prefix shared by every completion.
+pub fn common_prefix(completions: &[String]) -> Option<&str> {
+    let (first, rest) = completions.split_first()?;
+    let len = rest.iter().fold(first.len(), |len, other| {
+        first
+            .char_indices()
+            .zip(other.chars())
+            .take_while(|((i, a), b)| *i < len && a == b)
+            .last()
+            .map_or(0, |((i, a), _)| i + a.len_utf8())
+    });
+    Some(&first[..len])
+}
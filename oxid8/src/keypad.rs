@@ -0,0 +1,107 @@
+//! The keypad drawn with the keyboard keys of the active keymap.
+//!
+//! [`KeypadDiagram`] draws [`Keymap::diagram`] as four rows of keys,
+//! colored by player in two player mode, with held keys reversed. The
+//! game screen shows it beside the screen, where keys can be clicked,
+//! and the help screen lists it next to the keypad's own digits.
+
+use oxid8_core::keymap::{KeyCap, Keymap};
+use ratatui::{
+    buffer::Buffer,
+    layout::{Position, Rect},
+    style::{Style, Stylize},
+    text::{Line, Span},
+    widgets::{Paragraph, Widget},
+};
+
+/// Cells a diagram is wide.
+pub const WIDTH: u16 = 11;
+
+/// The keypad labeled with the keys of a keymap.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeypadDiagram {
+    keymap: Keymap,
+    held: [bool; 16],
+}
+
+impl KeypadDiagram {
+    /// The keypad of `keymap` with no keys held.
+    #[must_use]
+    pub fn new(keymap: Keymap) -> Self {
+        Self {
+            keymap,
+            held: [false; 16],
+        }
+    }
+
+    /// Lights the keys in `held`.
+    #[must_use]
+    pub fn held(mut self, held: [bool; 16]) -> Self {
+        self.held = held;
+        self
+    }
+
+    /// A line for each row of keys, and a line naming the players in
+    /// two player mode.
+    #[must_use]
+    pub fn lines(&self) -> Vec<Line<'static>> {
+        let two_player = self.keymap == Keymap::TwoPlayer;
+        let mut lines: Vec<Line> = self
+            .keymap
+            .diagram(&self.held)
+            .iter()
+            .map(|row| {
+                let spans = row.iter().enumerate().flat_map(|(col, cap)| {
+                    // The players are split by ` │ `.
+                    let gap = if col == 2 && two_player { " │ " } else { " " };
+                    [
+                        Span::raw(gap),
+                        Span::styled(cap.label.to_string(), style(cap)),
+                    ]
+                });
+                Line::from(spans.skip(1).collect::<Vec<_>>())
+            })
+            .collect();
+        if two_player {
+            lines.push(Line::from(vec!["P1".cyan(), "   ".into(), "P2".magenta()]));
+        }
+        lines
+    }
+
+    /// The keypad key drawn at `position` of a diagram drawn over
+    /// `area`, or the space after it.
+    #[must_use]
+    pub fn key_at(&self, area: Rect, position: Position) -> Option<u8> {
+        if !area.contains(position) {
+            return None;
+        }
+        let diagram = self.keymap.diagram(&self.held);
+        let row = diagram.get((position.y - area.y) as usize)?;
+        let x = (position.x - area.x) as usize;
+        let col = match self.keymap {
+            // The players are split by ` │ `.
+            Keymap::TwoPlayer if x >= 4 => x.checked_sub(6)? / 2 + 2,
+            _ => x / 2,
+        };
+        row.get(col).map(|cap| cap.key)
+    }
+}
+
+impl Widget for &KeypadDiagram {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        Paragraph::new(self.lines()).render(area, buf);
+    }
+}
+
+/// The player's color, reversed if held.
+fn style(cap: &KeyCap) -> Style {
+    let style = match cap.player {
+        Some(1) => Style::new().cyan(),
+        Some(_) => Style::new().magenta(),
+        None => Style::new(),
+    };
+    match cap.held {
+        true => style.reversed(),
+        false => style,
+    }
+}
@@ -0,0 +1,134 @@
+//! Drawing the screen as an image with the kitty graphics protocol.
+//!
+//! Half-blocks redraw the screen as text, which adds up on slow SSH
+//! connections. [`KittyImage`] sends the screen once as a 64x32 RGB
+//! image placed over the cells it covers, then only the pixels in the
+//! region the core reports changed, see
+//! [`ScreenChanges`](oxid8_core::video::ScreenChanges), by editing the
+//! image's frame in place. The terminal scales the image to the cells.
+//!
+//! <https://sw.kovidgoyal.net/kitty/graphics-protocol/>
+
+use base64::{Engine, engine::general_purpose::STANDARD};
+use crossterm::{cursor::MoveTo, queue};
+use oxid8_core::{
+    SCREEN_AREA, SCREEN_HEIGHT, SCREEN_WIDTH,
+    color::Palette,
+    video::{Region, ScreenChanges},
+};
+use ratatui::layout::Rect;
+use std::io::{self, Write};
+
+/// Most base64 bytes in one escape code, longer data is split.
+const CHUNK_SIZE: usize = 4096;
+
+/// The whole screen.
+const FULL: Region = Region {
+    x: 0,
+    y: 0,
+    width: SCREEN_WIDTH,
+    height: SCREEN_HEIGHT,
+};
+
+/// The screen as an image in the terminal.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KittyImage {
+    /// Image id in the terminal.
+    id: u32,
+    /// Cells the image was placed over, `None` until it is sent.
+    placed: Option<Rect>,
+}
+
+impl KittyImage {
+    /// An image with `id`, not sent yet.
+    #[must_use]
+    pub fn new(id: u32) -> Self {
+        Self { id, placed: None }
+    }
+
+    /// Send the whole image on the next draw, e.g. after the terminal
+    /// was cleared.
+    pub fn invalidate(&mut self) {
+        self.placed = None;
+    }
+
+    /// Writes what the terminal needs to show `screen` over `area`: the
+    /// whole image the first time or when `area` changed, and after
+    /// that only the pixels in `changes`. Returns the bytes written.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if writing to `out` fails.
+    pub fn draw(
+        &mut self,
+        out: &mut impl Write,
+        screen: &[bool; SCREEN_AREA],
+        changes: &ScreenChanges,
+        area: Rect,
+    ) -> io::Result<usize> {
+        let mut buf = Vec::new();
+        if self.placed != Some(area) {
+            if self.placed.is_some() {
+                self.delete(&mut buf)?;
+            }
+            queue!(buf, MoveTo(area.x, area.y))?;
+            let control = format!(
+                "a=T,f=24,s={SCREEN_WIDTH},v={SCREEN_HEIGHT},i={},c={},r={},C=1,q=2",
+                self.id, area.width, area.height
+            );
+            Self::transmit(&mut buf, &control, screen, FULL)?;
+            self.placed = Some(area);
+        } else if let Some(region) = changes.region {
+            // Edits the root frame, which is the one shown.
+            let control = format!(
+                "a=f,r=1,f=24,i={},x={},y={},s={},v={},q=2",
+                self.id, region.x, region.y, region.width, region.height
+            );
+            Self::transmit(&mut buf, &control, screen, region)?;
+        }
+        out.write_all(&buf)?;
+        Ok(buf.len())
+    }
+
+    /// Removes the image from the terminal.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if writing to `out` fails.
+    pub fn delete(&mut self, out: &mut impl Write) -> io::Result<()> {
+        self.placed = None;
+        write!(out, "\x1b_Ga=d,d=I,i={},q=2\x1b\\", self.id)
+    }
+
+    /// Writes the pixels of `region` with `control`, split into chunks.
+    fn transmit(
+        out: &mut impl Write,
+        control: &str,
+        screen: &[bool; SCREEN_AREA],
+        region: Region,
+    ) -> io::Result<()> {
+        // White on black, like the half-blocks.
+        let palette = Palette::default();
+        let mut rgb = Vec::with_capacity(region.width * region.height * 3);
+        for y in region.y..region.y + region.height {
+            for x in region.x..region.x + region.width {
+                rgb.extend(match screen[x + y * SCREEN_WIDTH] {
+                    true => palette.foreground.to_rgb(),
+                    false => palette.background.to_rgb(),
+                });
+            }
+        }
+        let data = STANDARD.encode(rgb);
+        let chunks: Vec<&[u8]> = data.as_bytes().chunks(CHUNK_SIZE).collect();
+        for (i, chunk) in chunks.iter().enumerate() {
+            let more = (i + 1 < chunks.len()) as u8;
+            match i {
+                0 => write!(out, "\x1b_G{control},m={more};")?,
+                _ => write!(out, "\x1b_Gm={more};")?,
+            }
+            out.write_all(chunk)?;
+            out.write_all(b"\x1b\\")?;
+        }
+        Ok(())
+    }
+}
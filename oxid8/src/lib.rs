@@ -1,2 +1,15 @@
 pub mod app;
+pub mod bandwidth;
+pub mod color;
+pub mod command;
+pub mod keypad;
+pub mod kitty;
+pub mod library;
+pub mod render;
 pub mod screens;
+pub mod settings;
+#[cfg(feature = "script")]
+pub mod speech;
+pub mod suspend;
+#[cfg(feature = "tracing")]
+pub mod trace;
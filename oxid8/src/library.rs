@@ -0,0 +1,549 @@
+//! ROM library.
+//!
+//! Scans directories for ROMs, identifies each one by content hash,
+//! and resolves a display name from the metadata database, falling
+//! back to a prettified file name. Hashes are cached by path, size,
+//! and modification time so rescanning a large collection is cheap.
+//!
+//! Directories are read from `OXID_ROM_DIRS` (a `PATH`-style list),
+//! defaulting to the current directory. A library can instead list the
+//! entries of a playlist, in playlist order.
+//!
+//! The metadata database lives at `<config_dir>/oxid8/metadata.tsv`
+//! and the cache at `<cache_dir>/oxid8/library.tsv`. Both hold one
+//! tab-separated record per line; the database maps `sha1<TAB>name`.
+//! Tabs, newlines, and backslashes in a field are written `\t`, `\n`,
+//! and `\\`, so any path fits in a record.
+
+use crate::settings;
+use oxid8_core::{archive, config::GameConfig, hash::rom_hash, octo, playlist::Playlist};
+
+use std::{
+    collections::HashMap,
+    env, fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    time::UNIX_EPOCH,
+};
+
+/// File extensions recognized as ROMs.
+const ROM_EXTENSIONS: [&str; 5] = ["ch8", "c8", "rom", "zip", "o8"];
+
+/// Subdirectories deeper than this are not scanned.
+const MAX_DEPTH: usize = 4;
+
+/// A ROM found in the library.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RomEntry {
+    /// Path to the ROM file.
+    pub path: PathBuf,
+    /// Hex SHA-1 of the ROM contents.
+    pub hash: String,
+    /// Display name.
+    pub name: String,
+    /// Overrides from the playlist.
+    pub config: GameConfig,
+    size: u64,
+    modified: u64,
+}
+
+/// Searchable, sorted list of ROMs.
+#[derive(Debug, Default)]
+pub struct Library {
+    dirs: Vec<PathBuf>,
+    entries: Vec<RomEntry>,
+    metadata: HashMap<String, String>,
+    cache_path: Option<PathBuf>,
+    playlist: Option<Playlist>,
+}
+
+impl Library {
+    /// Create an empty library over `dirs`.
+    pub fn new(dirs: Vec<PathBuf>) -> Self {
+        Self {
+            dirs,
+            ..Default::default()
+        }
+    }
+
+    /// Create an empty library configured from the environment, using
+    /// the user's metadata database and cache if they exist.
+    pub fn from_env() -> Self {
+        let dirs = match env::var_os("OXID_ROM_DIRS") {
+            Some(paths) => env::split_paths(&paths).collect(),
+            None => vec![PathBuf::from(".")],
+        };
+
+        let mut library = Library::new(dirs);
+        library.cache_path = settings::cache_dir().map(|dir| dir.join("library.tsv"));
+        if let Some(path) = settings::config_dir().map(|dir| dir.join("metadata.tsv")) {
+            library.metadata = read_records(&path)
+                .into_iter()
+                .filter_map(|record| {
+                    let [hash, name] = <[String; 2]>::try_from(record).ok()?;
+                    Some((hash.to_ascii_lowercase(), name))
+                })
+                .collect();
+        }
+        library
+    }
+
+    /// Create an empty library over the entries of `playlist`, using
+    /// the user's metadata database and cache if they exist.
+    pub fn from_playlist(playlist: Playlist) -> Self {
+        Self {
+            playlist: Some(playlist),
+            ..Library::from_env()
+        }
+    }
+
+    /// Name of the playlist, if any.
+    pub fn title(&self) -> Option<&str> {
+        self.playlist.as_ref()?.name.as_deref()
+    }
+
+    /// The scanned ROMs sorted by name, or in playlist order.
+    pub fn entries(&self) -> &[RomEntry] {
+        &self.entries
+    }
+
+    /// Fuzzy searches entry names, best matches first.
+    ///
+    /// An empty query matches every entry in name order.
+    pub fn search(&self, query: &str) -> Vec<Match> {
+        let mut matches: Vec<Match> = self
+            .entries
+            .iter()
+            .enumerate()
+            .filter_map(|(index, entry)| {
+                let (score, positions) = fuzzy_match(query, &entry.name)?;
+                Some(Match {
+                    index,
+                    score,
+                    positions,
+                })
+            })
+            .collect();
+
+        // Stable sort keeps name order between equal scores.
+        matches.sort_by_key(|m| std::cmp::Reverse(m.score));
+        matches
+    }
+
+    /// Scans all configured directories or the playlist, replacing the
+    /// current entries.
+    ///
+    /// Unreadable files are skipped. Failing to write the cache is not
+    /// an error.
+    pub fn scan(&mut self) {
+        let cache = self.read_cache();
+
+        let sources: Vec<(PathBuf, Option<String>, GameConfig)> = match &self.playlist {
+            Some(playlist) => playlist
+                .entries
+                .iter()
+                .map(|entry| (entry.path.clone(), entry.name.clone(), entry.config.clone()))
+                .collect(),
+            None => {
+                let mut paths = Vec::new();
+                for dir in &self.dirs {
+                    collect_roms(dir, 0, &mut paths);
+                }
+                paths
+                    .into_iter()
+                    .map(|path| (path, None, GameConfig::default()))
+                    .collect()
+            }
+        };
+
+        self.entries = sources
+            .into_iter()
+            .filter_map(|(path, name, config)| {
+                let meta = fs::metadata(&path).ok()?;
+                let size = meta.len();
+                let modified = meta
+                    .modified()
+                    .ok()
+                    .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+                    .map_or(0, |time| time.as_secs());
+
+                let hash = match cache.get(&path) {
+                    Some(cached) if cached.size == size && cached.modified == modified => {
+                        cached.hash.clone()
+                    }
+                    _ => rom_hash(&read_rom(&path).ok()?),
+                };
+                let name = name
+                    .or_else(|| self.metadata.get(&hash).cloned())
+                    .unwrap_or_else(|| display_name(&path));
+
+                Some(RomEntry {
+                    path,
+                    hash,
+                    name,
+                    config,
+                    size,
+                    modified,
+                })
+            })
+            .collect();
+
+        if self.playlist.is_none() {
+            self.entries.sort_by(|a, b| {
+                a.name
+                    .to_lowercase()
+                    .cmp(&b.name.to_lowercase())
+                    .then_with(|| a.path.cmp(&b.path))
+            });
+        }
+
+        let _ = self.write_cache();
+    }
+
+    fn read_cache(&self) -> HashMap<PathBuf, RomEntry> {
+        let Some(path) = &self.cache_path else {
+            return HashMap::new();
+        };
+
+        read_records(path)
+            .into_iter()
+            .filter_map(|record| {
+                let [path, size, modified, hash] = <[String; 4]>::try_from(record).ok()?;
+                let entry = RomEntry {
+                    path: PathBuf::from(path),
+                    hash,
+                    name: String::new(),
+                    config: GameConfig::default(),
+                    size: size.parse().ok()?,
+                    modified: modified.parse().ok()?,
+                };
+                Some((entry.path.clone(), entry))
+            })
+            .collect()
+    }
+
+    fn write_cache(&self) -> io::Result<()> {
+        let Some(path) = &self.cache_path else {
+            return Ok(());
+        };
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let mut file = io::BufWriter::new(fs::File::create(path)?);
+        for entry in &self.entries {
+            writeln!(
+                file,
+                "{}\t{}\t{}\t{}",
+                escape(&entry.path.to_string_lossy()),
+                entry.size,
+                entry.modified,
+                entry.hash
+            )?;
+        }
+        file.flush()
+    }
+}
+
+/// A library entry matching a search query.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Match {
+    /// Index into [`Library::entries`].
+    pub index: usize,
+    /// Higher is better.
+    pub score: i32,
+    /// Char indices of the name that matched the query.
+    pub positions: Vec<usize>,
+}
+
+/// Matches `query` as a case-insensitive subsequence of `text`.
+///
+/// Returns a score and the matched char indices of `text`. Consecutive
+/// matches and matches at the start of words score higher, gaps score
+/// lower. Whitespace in the query is ignored.
+pub fn fuzzy_match(query: &str, text: &str) -> Option<(i32, Vec<usize>)> {
+    let text: Vec<char> = text.chars().collect();
+    let mut positions = Vec::new();
+    let mut score = 0;
+    let mut next = 0;
+
+    for q in query.chars().filter(|c| !c.is_whitespace()) {
+        let offset = text[next..]
+            .iter()
+            .position(|c| c.to_lowercase().eq(q.to_lowercase()))?;
+        let i = next + offset;
+
+        score += 1;
+        if positions.last().is_some_and(|&last| last + 1 == i) {
+            score += 5; // consecutive
+        }
+        if i == 0 || !text[i - 1].is_alphanumeric() {
+            score += 3; // word start
+        }
+        score -= offset.min(3) as i32; // gap
+
+        positions.push(i);
+        next = i + 1;
+    }
+
+    Some((score, positions))
+}
+
+/// Reads a ROM file, extracting it first if it is a zip archive or
+/// assembling it if it is Octo source.
+pub fn read_rom(path: &Path) -> io::Result<Vec<u8>> {
+    if path.extension().is_some_and(|ext| ext == "o8") {
+        let source = fs::read_to_string(path)?;
+        return octo::assemble(&source)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err));
+    }
+    let data = fs::read(path)?;
+    if archive::is_zip(&data) {
+        archive::extract_rom(&data)
+    } else {
+        Ok(data)
+    }
+}
+
+/// Recursively collects ROM paths under `dir`.
+fn collect_roms(dir: &Path, depth: usize, paths: &mut Vec<PathBuf>) {
+    let Ok(read_dir) = fs::read_dir(dir) else {
+        return;
+    };
+
+    for entry in read_dir.flatten() {
+        let path = entry.path();
+        let Ok(file_type) = entry.file_type() else {
+            continue;
+        };
+
+        if file_type.is_dir() && depth < MAX_DEPTH {
+            collect_roms(&path, depth + 1, paths);
+        } else if file_type.is_file() && is_rom(&path) {
+            paths.push(path);
+        }
+    }
+}
+
+fn is_rom(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| ROM_EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str()))
+}
+
+/// "space_invaders [David Winter].ch8" -> "Space Invaders [David Winter]"
+fn display_name(path: &Path) -> String {
+    let stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().replace('_', " "))
+        .unwrap_or_default();
+
+    stem.split(' ')
+        .map(|word| {
+            let mut chars = word.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars).collect(),
+                None => String::new(),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Reads a tab-separated file, ignoring blank lines and `#` comments.
+fn read_records(path: &Path) -> Vec<Vec<String>> {
+    let Ok(contents) = fs::read_to_string(path) else {
+        return Vec::new();
+    };
+
+    contents
+        .lines()
+        .filter(|line| !line.trim().is_empty() && !line.starts_with('#'))
+        .map(|line| line.split('\t').map(unescape).collect())
+        .collect()
+}
+
+/// Escapes a field for [`read_records`].
+fn escape(field: &str) -> String {
+    let mut escaped = String::with_capacity(field.len());
+    for c in field.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '\t' => escaped.push_str("\\t"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Reverses [`escape`]. Unknown escapes are kept as they are.
+fn unescape(field: &str) -> String {
+    let mut unescaped = String::with_capacity(field.len());
+    let mut chars = field.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('t') => unescaped.push('\t'),
+            Some('n') => unescaped.push('\n'),
+            Some('r') => unescaped.push('\r'),
+            Some('\\') => unescaped.push('\\'),
+            Some(other) => unescaped.extend(['\\', other]),
+            None => unescaped.push('\\'),
+        }
+    }
+    unescaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An empty directory of its own under the temp directory.
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("oxid8-library-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn escapes() {
+        for field in ["plain", "tab\there", "two\nlines\r", "back\\slash\\t", ""] {
+            assert_eq!(unescape(&escape(field)), field);
+            assert!(!escape(field).contains(['\t', '\n', '\r']));
+        }
+        assert_eq!(escape("a\tb\\"), "a\\tb\\\\");
+        assert_eq!(unescape("odd\\x\\"), "odd\\x\\");
+    }
+
+    #[test]
+    fn fuzzy_matches() {
+        let (_, positions) = fuzzy_match("spc inv", "Space Invaders").unwrap();
+        assert_eq!(positions, [0, 1, 3, 6, 7, 8]);
+        assert_eq!(fuzzy_match("PONG", "pong"), fuzzy_match("pong", "Pong"));
+        assert_eq!(fuzzy_match("", "Tetris"), Some((0, Vec::new())));
+        assert_eq!(fuzzy_match("xyz", "Tetris"), None);
+        // Out of order letters don't match.
+        assert_eq!(fuzzy_match("sirtet", "Tetris"), None);
+    }
+
+    #[test]
+    fn fuzzy_ranks() {
+        let score = |query, text| fuzzy_match(query, text).unwrap().0;
+        // Consecutive beats scattered.
+        assert!(score("tet", "Tetris") > score("tet", "The Extra Track"));
+        // Word starts beat the middle of words.
+        assert!(score("b", "Brix") > score("b", "Cube"));
+        // Small gaps beat large ones.
+        assert!(score("pg", "Pong") > score("pg", "Potato Peeling Game"));
+    }
+
+    /// A library of `names` without touching the filesystem.
+    fn library(names: &[&str]) -> Library {
+        let entries = names
+            .iter()
+            .map(|name| RomEntry {
+                path: PathBuf::from(format!("{name}.ch8")),
+                hash: String::new(),
+                name: name.to_string(),
+                config: GameConfig::default(),
+                size: 0,
+                modified: 0,
+            })
+            .collect();
+        Library {
+            entries,
+            ..Library::default()
+        }
+    }
+
+    #[test]
+    fn search_ranks_best_first() {
+        let library = library(&["Blinky", "Brix", "Space Invaders", "Tetris", "Tic-Tac-Toe"]);
+        let found = |query| -> Vec<&str> {
+            library
+                .search(query)
+                .iter()
+                .map(|m| library.entries()[m.index].name.as_str())
+                .collect()
+        };
+        assert_eq!(found("tet"), ["Tetris"]);
+        // Both letters start words in Tic-Tac-Toe.
+        assert_eq!(found("tt"), ["Tic-Tac-Toe", "Tetris"]);
+        assert_eq!(found("br"), ["Brix"]);
+        assert!(found("zzz").is_empty());
+        // Every entry, in name order.
+        assert_eq!(found("").len(), 5);
+        assert_eq!(found("")[0], "Blinky");
+
+        let best = &library.search("inv")[0];
+        assert_eq!(best.index, 2);
+        assert_eq!(best.positions, [6, 7, 8]);
+    }
+
+    #[test]
+    fn display_names() {
+        let name = |path: &str| display_name(Path::new(path));
+        assert_eq!(
+            name("roms/space_invaders [David Winter].ch8"),
+            "Space Invaders [David Winter]"
+        );
+        assert_eq!(name("pong.ch8"), "Pong");
+        assert!(is_rom(Path::new("GAME.CH8")));
+        assert!(!is_rom(Path::new("notes.txt")));
+    }
+
+    #[test]
+    fn scans_sorted_with_names() {
+        let dir = temp_dir("scan");
+        fs::create_dir_all(dir.join("nested")).unwrap();
+        fs::write(dir.join("zebra.ch8"), [0x12, 0x00]).unwrap();
+        fs::write(dir.join("nested/apple.c8"), [0x12, 0x02]).unwrap();
+        fs::write(dir.join("readme.txt"), "not a rom").unwrap();
+
+        let mut library = Library::new(vec![dir.clone()]);
+        let known = rom_hash(&[0x12, 0x00]);
+        library
+            .metadata
+            .insert(known.clone(), "A Known Game".into());
+        library.scan();
+
+        let names: Vec<&str> = library.entries().iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, ["A Known Game", "Apple"]);
+        assert_eq!(library.entries()[0].hash, known);
+        assert_eq!(library.entries()[1].path, dir.join("nested/apple.c8"));
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn cache_keeps_odd_paths() {
+        let dir = temp_dir("cache");
+        let odd = dir.join("tab\tand\nnewline.ch8");
+        fs::write(&odd, [0x12, 0x00]).unwrap();
+        fs::write(dir.join("plain.ch8"), [0x00, 0xE0]).unwrap();
+
+        let mut library = Library::new(vec![dir.clone()]);
+        library.cache_path = Some(dir.join("cache").join("library.tsv"));
+        library.scan();
+        assert_eq!(library.entries().len(), 2);
+
+        let cache = library.read_cache();
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache[&odd].hash, rom_hash(&[0x12, 0x00]));
+
+        // Hashes come from the cache while the file is unchanged.
+        let mut stale = cache[&odd].clone();
+        stale.hash = "cached".into();
+        library.entries = vec![stale];
+        library.write_cache().unwrap();
+        library.scan();
+        let entry = library.entries().iter().find(|e| e.path == odd).unwrap();
+        assert_eq!(entry.hash, "cached");
+        let _ = fs::remove_dir_all(dir);
+    }
+}
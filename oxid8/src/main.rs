@@ -1,12 +1,11 @@
-use oxid8::app::App;
-use oxid8_core::Oxid8;
+use oxid8::{app::App, bandwidth::Bandwidth, color, settings};
+use oxid8_core::{Oxid8, frontend::Assist, playlist::Playlist};
 use std::{
+    env,
     io::{self, Write, stdout},
-    time::Instant,
+    process,
 };
 
-const TICK_RATE: u64 = 1 / 700; // 700 instructions per second
-
 // NOTE: use bell character for a beep \X07
 // NOTE: use the left four columns of 1234 for the keypad
 
@@ -14,8 +13,27 @@ const TICK_RATE: u64 = 1 / 700; // 700 instructions per second
 // or draw two columns per pixel ██ 128 is pretty wide though (probably easier to do)
 
 fn main() -> io::Result<()> {
-    print!("\x07");
-    stdout().flush()?;
+    #[cfg(feature = "tracing")]
+    oxid8::trace::init()?;
+
+    let app = build_app(env::args().skip(1)).unwrap_or_else(|err| {
+        eprintln!("Error parsing arguments: {err}");
+        process::exit(1);
+    });
+    let hotkeys = settings::hotkeys().unwrap_or_else(|err| {
+        eprintln!("Error reading hotkeys.toml: {err}");
+        process::exit(1);
+    });
+    let app = app.with_hotkeys(hotkeys);
+    let app = match settings::splash() {
+        true => app.with_splash(),
+        false => app,
+    };
+
+    if app.bandwidth().bell {
+        print!("\x07");
+        stdout().flush()?;
+    }
 
     let mut emu = Oxid8::new();
     if let Err(err) = emu.load_rom("abc") {
@@ -24,23 +42,66 @@ fn main() -> io::Result<()> {
     emu.load_font();
 
     let mut terminal = ratatui::init();
-    let app_result = App::default().run(&mut terminal);
+    let app_result = app.run(&mut terminal);
     ratatui::restore();
 
-    loop {
-        let time = Instant::now();
-
-        // poll events (key press mainly)
-        // run cycle
-        if let Err(err) = emu.run_cycle() {
-            eprintln!("{err}");
-        }
-        // check draw flag
-        // check timers
-
-        while time.elapsed().as_secs() < TICK_RATE {} // spin
-        break; // WARN: temporary
+    // poll events (key press mainly)
+    // run cycle
+    if let Err(err) = emu.run_cycle() {
+        eprintln!("{err}");
     }
+    // check draw flag
+    // check timers
 
     app_result
 }
+
+/// `oxid8 [--playlist <file.json>] [--script <file.rhai>] [--speak] [--flight-recorder]
+/// [--sticky-keys] [--min-hold <frames>] [--low-bandwidth] [--reduce-flashes] [--ansi-colors]
+/// [--filter <filters>]`
+fn build_app(mut args: impl Iterator<Item = String>) -> Result<App, String> {
+    let mut app = App::default();
+    let mut assist = Assist::default();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--sticky-keys" => assist.sticky = true,
+            "--min-hold" => {
+                let frames = args
+                    .next()
+                    .ok_or("--min-hold requires a number of frames")?;
+                assist.min_hold = frames
+                    .parse()
+                    .map_err(|_| format!("Invalid --min-hold: {frames}"))?;
+            }
+            "--playlist" => {
+                let path = args.next().ok_or("--playlist requires a path")?;
+                let playlist = Playlist::load(&path).map_err(|err| format!("{path}: {err}"))?;
+                app = app.with_playlist(playlist);
+            }
+            "--flight-recorder" => app = app.with_flight_recorder(),
+            "--low-bandwidth" => app = app.with_bandwidth(Bandwidth::LOW),
+            "--reduce-flashes" => app = app.with_flash_reduction(),
+            "--ansi-colors" => app = app.with_ansi_colors(),
+            "--filter" => {
+                let spec = args.next().ok_or("--filter requires filters")?;
+                app = app
+                    .with_filters(&spec)
+                    .map_err(|err| format!("Invalid --filter: {err}"))?;
+            }
+            #[cfg(feature = "script")]
+            "--script" => {
+                let path = args.next().ok_or("--script requires a path")?;
+                let script = oxid8_core::script::Script::load(&path)
+                    .map_err(|err| format!("{path}: {err}"))?;
+                app = app.with_script(script);
+            }
+            #[cfg(feature = "script")]
+            "--speak" => app = app.with_speech(oxid8::speech::Speech::default()),
+            _ => return Err(format!("unexpected argument: {arg}")),
+        }
+    }
+    if !color::truecolor() {
+        app = app.with_ansi_colors();
+    }
+    Ok(app.with_assist(assist))
+}
@@ -0,0 +1,98 @@
+//! Drawing the screen into terminal cells.
+//!
+//! [`Pixels`] paints the part of the screen in a [`View`] on a ratatui
+//! canvas, with the [`marker`] for its zoom, as `oxid-cli` draws.
+//! [`cells`] writes half blocks straight into the buffer instead,
+//! skipping the canvas, for as many rows as changed. `cargo bench -p
+//! oxid8 --bench render` compares them.
+
+use oxid8_core::{
+    SCREEN_AREA, SCREEN_WIDTH,
+    viewport::{View, Zoom},
+};
+use ratatui::{
+    buffer::Buffer,
+    layout::Rect,
+    style::Color,
+    symbols::Marker,
+    widgets::{
+        Widget,
+        canvas::{Canvas, Painter, Shape},
+    },
+};
+use std::ops::Range;
+
+/// The lit pixels of `view`, in `color`.
+pub struct Pixels<'a> {
+    pub screen: &'a [bool; SCREEN_AREA],
+    pub view: View,
+    pub color: Color,
+}
+
+impl Pixels<'_> {
+    /// Cells the view takes up, across and down.
+    pub fn cells(&self) -> (u16, u16) {
+        let (width, height) = self.view.zoom.cells(self.view.width, self.view.height);
+        (width as u16, height as u16)
+    }
+}
+
+impl Shape for Pixels<'_> {
+    fn draw(&self, painter: &mut Painter) {
+        let view = self.view;
+        for y in 0..view.height {
+            for x in 0..view.width {
+                if !self.screen[view.x + x + (view.y + y) * SCREEN_WIDTH] {
+                    continue;
+                }
+                // Square pixels take two cells.
+                match view.zoom {
+                    Zoom::Block => {
+                        painter.paint(2 * x, y, self.color);
+                        painter.paint(2 * x + 1, y, self.color);
+                    }
+                    Zoom::Braille | Zoom::HalfBlock => painter.paint(x, y, self.color),
+                }
+            }
+        }
+    }
+}
+
+impl Widget for &Pixels<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let (width, height) = self.cells();
+        Canvas::default()
+            .x_bounds([0.0, width as f64])
+            .y_bounds([0.0, height as f64])
+            .marker(marker(self.view.zoom))
+            .paint(|ctx| ctx.draw(self))
+            .render(area, buf);
+    }
+}
+
+/// The canvas marker drawing `zoom`.
+pub fn marker(zoom: Zoom) -> Marker {
+    match zoom {
+        Zoom::Braille => Marker::Braille,
+        Zoom::HalfBlock => Marker::HalfBlock,
+        Zoom::Block => Marker::Block,
+    }
+}
+
+/// Writes `rows` of the whole screen as half-block cells from the top
+/// left of `buf`, two pixels each.
+pub fn cells(screen: &[bool; SCREEN_AREA], rows: Range<usize>, buf: &mut Buffer) {
+    for y in rows {
+        for x in 0..SCREEN_WIDTH {
+            let top = screen[x + 2 * y * SCREEN_WIDTH];
+            let bottom = screen[x + (2 * y + 1) * SCREEN_WIDTH];
+            let symbol = match (top, bottom) {
+                (true, true) => "█",
+                (true, false) => "▀",
+                (false, true) => "▄",
+                (false, false) => " ",
+            };
+            buf[(x as u16, y as u16)].set_symbol(symbol);
+        }
+    }
+}
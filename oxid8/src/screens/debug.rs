@@ -1 +1,199 @@
-pub struct Debug {}
+use crate::app::AppState;
+use crate::screens::Screen;
+use oxid8_core::{
+    SCREEN_HEIGHT, SCREEN_WIDTH,
+    hotkeys::{Action, Key},
+    savestate::{SaveState, StateDiff},
+};
+
+use crossterm::event::{
+    self, Event, KeyCode, KeyEvent, KeyEventKind, MouseButton, MouseEvent, MouseEventKind,
+};
+use ratatui::{
+    Frame,
+    buffer::Buffer,
+    layout::{Constraint, Flex, Layout, Rect},
+    style::{Color, Stylize},
+    symbols::Marker,
+    text::Line,
+    widgets::{
+        Block, Paragraph, Widget,
+        canvas::{Canvas, Painter, Shape},
+    },
+};
+use std::io;
+
+/// Diff between the last two savestates taken in game.
+#[derive(Default)]
+pub struct Debug {
+    scroll: u16,
+    /// Row the mouse was last dragged over.
+    drag: Option<u16>,
+}
+
+/// Pixels that differ between the two states, clipped to `area`.
+struct ScreenDiff<'a> {
+    diff: &'a StateDiff,
+    area: Rect,
+}
+
+impl Debug {
+    pub fn draw(&mut self, frame: &mut Frame, app_state: &AppState) {
+        let area = frame.area();
+        let diff = match app_state.snapshots.as_slice() {
+            [.., before, after] => Some(before.diff(after)),
+            _ => None,
+        };
+        let snapshot = app_state.hotkeys.key(Action::Snapshot);
+        self.render(diff.as_ref(), snapshot, area, frame.buffer_mut());
+    }
+
+    pub fn handle_events(&mut self, app_state: &mut AppState) -> io::Result<()> {
+        match event::read()? {
+            Event::Key(key_event) if key_event.kind == KeyEventKind::Press => {
+                self.handle_key_event(key_event, app_state)
+            }
+            Event::Mouse(mouse_event) => self.handle_mouse_event(mouse_event),
+            _ => (),
+        };
+        Ok(())
+    }
+
+    /// Dragging moves the table with the mouse, the wheel scrolls it.
+    fn handle_mouse_event(&mut self, mouse_event: MouseEvent) {
+        let row = mouse_event.row;
+        match mouse_event.kind {
+            MouseEventKind::Down(MouseButton::Left) => self.drag = Some(row),
+            MouseEventKind::Drag(MouseButton::Left) => {
+                if let Some(last) = self.drag.replace(row) {
+                    self.scroll = self.scroll.saturating_add(last).saturating_sub(row);
+                }
+            }
+            MouseEventKind::Up(_) => self.drag = None,
+            MouseEventKind::ScrollDown => self.scroll = self.scroll.saturating_add(1),
+            MouseEventKind::ScrollUp => self.scroll = self.scroll.saturating_sub(1),
+            _ => (),
+        }
+    }
+
+    fn handle_key_event(&mut self, key_event: KeyEvent, app_state: &mut AppState) {
+        match key_event.code {
+            KeyCode::Char('q') | KeyCode::Esc => {
+                self.scroll = 0;
+                app_state.screen = match app_state.rom {
+                    Some(_) => Screen::Game,
+                    None => Screen::Menu,
+                };
+            }
+            KeyCode::Char('j') | KeyCode::Down => self.scroll = self.scroll.saturating_add(1),
+            KeyCode::Char('k') | KeyCode::Up => self.scroll = self.scroll.saturating_sub(1),
+            KeyCode::Char('g') | KeyCode::Home => self.scroll = 0,
+            _ => (),
+        }
+    }
+}
+
+/// Rendering logic for the diff
+impl Debug {
+    fn render(
+        &self,
+        diff: Option<&StateDiff>,
+        snapshot: Option<Key>,
+        area: Rect,
+        buf: &mut Buffer,
+    ) {
+        let [body, footer] =
+            Layout::vertical([Constraint::Fill(1), Constraint::Length(1)]).areas(area);
+        Paragraph::new("Use ↓↑ or drag to scroll, g to go top, esc to go back.")
+            .centered()
+            .render(footer, buf);
+
+        let Some(diff) = diff else {
+            let [body] = Layout::vertical([Constraint::Length(1)])
+                .flex(Flex::Center)
+                .areas(body);
+            let text = match snapshot {
+                Some(key) => {
+                    format!("Press {key} twice in game to take two savestates to compare.")
+                }
+                None => "Bind a key to snapshot to take savestates to compare.".into(),
+            };
+            Paragraph::new(text).centered().render(body, buf);
+            return;
+        };
+
+        // Rendering half-blocks
+        let [screen, table] = Layout::horizontal([
+            Constraint::Length(SCREEN_WIDTH as u16 + 2),
+            Constraint::Fill(1),
+        ])
+        .spacing(1)
+        .areas(body);
+        let [screen] =
+            Layout::vertical([Constraint::Length(SCREEN_HEIGHT as u16 / 2 + 2)]).areas(screen);
+
+        let block = Block::bordered().title(Line::raw(format!("XOR: {} pixels", diff.pixels())));
+        let shape = ScreenDiff {
+            diff,
+            area: block.inner(screen),
+        };
+        Canvas::default()
+            .block(block)
+            .x_bounds([0.0, SCREEN_WIDTH as f64])
+            .y_bounds([0.0, SCREEN_HEIGHT as f64 / 2.0])
+            .marker(Marker::HalfBlock)
+            .paint(|ctx| ctx.draw(&shape))
+            .render(screen, buf);
+
+        let lines = diff_lines(diff);
+        Paragraph::new(lines)
+            .block(Block::bordered().title(Line::raw("Registers and RAM")))
+            .scroll((self.scroll, 0))
+            .render(table, buf);
+    }
+}
+
+/// One line per changed register and memory address, with the
+/// values before and after.
+fn diff_lines(diff: &StateDiff) -> Vec<Line<'static>> {
+    if diff.is_empty() {
+        return vec![Line::raw("The states are the same.")];
+    }
+    let registers = diff.registers.iter().map(|(register, change)| {
+        Line::from(vec![
+            format!("{register:<6}").cyan(),
+            format!("{:04X} -> {:04X}", change.before, change.after).into(),
+        ])
+    });
+    let ram = diff.ram.iter().map(|(addr, change)| {
+        Line::from(vec![
+            format!("{addr:03X}   ").magenta(),
+            format!("  {:02X} ->   {:02X}", change.before, change.after).into(),
+        ])
+    });
+    registers.chain(ram).collect()
+}
+
+impl Shape for ScreenDiff<'_> {
+    fn draw(&self, painter: &mut Painter) {
+        for y in 0..SCREEN_HEIGHT {
+            for x in 0..SCREEN_WIDTH {
+                if self.diff.screen[x + y * SCREEN_WIDTH]
+                    && x < self.area.width as usize
+                    && y < (self.area.height * 2) as usize
+                // WARN: ONLY for rendering half-blocks
+                {
+                    painter.paint(x, y, Color::Red);
+                }
+            }
+        }
+    }
+}
+
+/// Keep the last two savestates for [`Debug`].
+pub fn push_snapshot(snapshots: &mut Vec<SaveState>, state: SaveState) {
+    if snapshots.len() == 2 {
+        snapshots.remove(0);
+    }
+    snapshots.push(state);
+}
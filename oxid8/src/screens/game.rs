@@ -1,17 +1,103 @@
-use crate::app::AppState;
-use oxid8_core::{Oxid8, SCREEN_HEIGHT, SCREEN_WIDTH};
+use crate::app::{AppState, RomSource};
+use crate::color::term_color;
+use crate::command::{self, Command, Timer};
+use crate::keypad::{self, KeypadDiagram};
+use crate::render;
+use crate::screens::{Screen, debug};
+use crate::settings;
+use crate::suspend;
+use oxid8_core::{
+    Oxid8, SCREEN_WIDTH, TimerRate,
+    breakpoint::Breakpoints,
+    builtin,
+    clock::FrameClock,
+    color::Rgba,
+    config::{DebugConfig, GameConfig},
+    export::{self, RgbaImage},
+    filter::FilterChain,
+    flash::FlashFilter,
+    flight::FlightRecorder,
+    frontend::KeyAssist,
+    goals::Goals,
+    hotkeys::{Action, Debounce, Hotkeys, Key},
+    input::InputEvent,
+    keylog::{self, KeyAction, KeyLog},
+    keymap::Keymap,
+    memmap::{MemoryMap, REGIONS, Region, STACK_DEPTH},
+    minidump::Minidump,
+    movie::Movie,
+    palette::{self, CHANNELS, PaletteEditor, SLOTS},
+    rewind::Rewind,
+    rotation::Rotation,
+    savestate::SaveState,
+    speedrun::SpeedrunTimer,
+    time::EmuTime,
+    viewport::{View, Viewport, Zoom},
+    watch::Watch,
+};
 
-use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind};
+use crossterm::event::{
+    self, DisableMouseCapture, Event, KeyCode, KeyEvent, KeyEventKind, MouseButton, MouseEvent,
+    MouseEventKind,
+};
 use ratatui::{
     Frame,
     buffer::Buffer,
-    layout::Rect,
-    symbols::Marker,
-    widgets::{Widget, canvas::Canvas},
+    layout::{Constraint, Flex, Layout, Position, Rect},
+    style::{Color, Style, Stylize},
+    text::{Line, Span},
+    widgets::{
+        Paragraph, Widget, Wrap,
+        canvas::{Canvas, Painter, Shape},
+    },
+};
+use std::{
+    collections::BTreeMap,
+    fs::{self, File},
+    io::{self, BufWriter},
+    path::PathBuf,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
 };
-use std::io;
 
-const TICK_RATE: u64 = 1 / 700;
+/// The actions in game and their default keys, in the order the help
+/// screen lists them. They can be rebound in `hotkeys.toml`, see
+/// `oxid8_core::hotkeys`.
+pub const HOTKEYS: [(Key, Action); 21] = [
+    (Key::Esc, Action::Menu),
+    (Key::F(1), Action::Help),
+    (Key::Char('-'), Action::Slower),
+    (Key::Char('='), Action::Faster),
+    (Key::Char('p'), Action::Pause),
+    (Key::Backspace, Action::Reset),
+    (Key::PageUp, Action::SaveState),
+    (Key::PageDown, Action::LoadState),
+    (Key::F(2), Action::SaveSettings),
+    (Key::F(3), Action::TimerRate),
+    (Key::F(4), Action::Keymap),
+    (Key::F(5), Action::Rotate),
+    (Key::F(6), Action::KeyLog),
+    (Key::F(7), Action::Record),
+    (Key::F(8), Action::Snapshot),
+    (Key::F(9), Action::Diff),
+    (Key::F(10), Action::CyclePalette),
+    (Key::F(11), Action::Colors),
+    (Key::F(12), Action::Screenshot),
+    (Key::Char(':'), Action::Palette),
+    (Key::Tab, Action::Zoom),
+];
+
+/// Savestate slot of the quick save and load hotkeys, the same as Page
+/// Up and Page Down in the wgpu frontend.
+const QUICK_SLOT: u8 = 0;
+
+/// Pixels the arrow keys pan a zoomed screen by.
+const PAN_STEP: isize = 8;
+
+/// Size of a CHIP-8 pixel in screenshots.
+const SCREENSHOT_SCALE: usize = 8;
+
+/// Cells in the memory map, two rows across the side panel.
+const MEMORY_CELLS: usize = 64;
 
 #[derive(Default)]
 pub struct Game {
@@ -21,7 +107,101 @@ pub struct Game {
 
 #[derive(Default)]
 struct GameState {
-    redraw: bool,
+    rom: Option<RomSource>,
+    /// When the game was loaded, the frame clock counts from it.
+    started: Option<Instant>,
+    clock: FrameClock,
+    /// Cells the screen can take up, as of the last draw.
+    area: Rect,
+    /// Zoom and pan of the screen in `area`.
+    viewport: Viewport,
+    /// Where the keypad legend was last drawn, for mouse clicks.
+    legend: Rect,
+    /// Keypad key held down with the mouse.
+    clicked: Option<u8>,
+    /// Overrides saved for the loaded rom.
+    config: GameConfig,
+    /// Overrides from the app state, e.g. a playlist's, applied over
+    /// `loaded` but not saved with the settings.
+    overrides: GameConfig,
+    /// `config` as loaded, before `overrides`.
+    loaded: GameConfig,
+    /// Feedback shown in the footer.
+    status: Option<String>,
+    keymap: Keymap,
+    rotation: Rotation,
+    recording: Option<Recording>,
+    /// Key events for the next frame, timestamped as they arrive.
+    input: Vec<InputEvent>,
+    /// Command being typed, emulation is paused while it is open.
+    palette: Option<String>,
+    /// Paused with the pause hotkey.
+    paused: bool,
+    /// The app's hotkeys, kept for the hints in the footer.
+    hotkeys: Hotkeys,
+    /// Completions shown after tab.
+    completions: Vec<String>,
+    breakpoints: Breakpoints,
+    /// Address the game is paused at, by a breakpoint or stepping.
+    stopped: Option<u16>,
+    /// Frames run, for stepping back.
+    rewind: Rewind,
+    /// The last seconds of input, for `:dump`, if turned on.
+    flight: Option<FlightRecorder>,
+    /// Sticky keys and minimum hold.
+    assist: KeyAssist,
+    /// Savestates taken with `:save`, for when they can't be stored.
+    slots: BTreeMap<u8, SaveState>,
+    /// Raw key events shown beside the game, toggled with F6.
+    key_log: Option<KeyLog>,
+    /// Expressions shown beside the game, added with `:watch`.
+    watches: Vec<Watch>,
+    /// Goals written for the game, announced in the footer when met.
+    goals: Goals,
+    /// Time emulated since the game was loaded.
+    timer: SpeedrunTimer,
+    /// Show the timer and held keys beside the game, with `:timer on`.
+    show_timer: bool,
+    /// Invalid opcodes skipped that have been shown in the footer.
+    skips_shown: usize,
+    /// What RAM holds, updated every frame.
+    memory: MemoryMap,
+    /// Show the memory map beside the game, with `:memory`.
+    show_memory: bool,
+    /// The machine as the game was loaded, for the reset hotkey.
+    boot: Option<SaveState>,
+    /// Lets the reset hotkey through once while it is held.
+    reset_key: Debounce,
+    /// Color editor beside the game, opened with `:colors`.
+    colors: Option<PaletteEditor>,
+    /// Damps flashing, with `--reduce-flashes`.
+    flashes: Option<FlashFilter>,
+    /// Post-processing of the screen, with `--filter`.
+    filters: FilterChain,
+    /// Draw the nearest ANSI colors, for terminals without truecolor.
+    ansi_colors: bool,
+    /// The script written for the game, unless one was given with
+    /// `--script`.
+    #[cfg(feature = "script")]
+    script: Option<oxid8_core::script::Script>,
+}
+
+impl GameState {
+    /// Time since the game was loaded.
+    fn now(&mut self) -> Duration {
+        self.started.get_or_insert_with(Instant::now).elapsed()
+    }
+}
+
+/// Input movie and audio captured since recording started.
+struct Recording {
+    movie: Movie,
+    /// Emulated time when recording started.
+    start: EmuTime,
+    #[cfg(feature = "audio")]
+    beeper: oxid8_core::audio::Beeper,
+    #[cfg(feature = "audio")]
+    samples: Vec<i16>,
 }
 
 impl Game {
@@ -30,45 +210,1435 @@ impl Game {
     }
 
     pub fn handle_events(&mut self, app_state: &mut AppState) -> io::Result<()> {
-        match event::read()? {
+        self.load_selected(app_state);
+
+        let now = self.state.now();
+        self.state.clock.set_timer_hz(self.emu.timer_rate().hz());
+        if event::poll(self.state.clock.until_timer(now))? {
+            self.handle_event(event::read()?, app_state)?;
+        }
+
+        let now = self.state.now();
+        let frames = self.state.clock.tick(now).timers;
+        self.run_frames(frames, app_state);
+
+        Ok(())
+    }
+
+    /// Load the ROM selected in `app_state` unless it is loaded.
+    pub fn load_selected(&mut self, app_state: &mut AppState) {
+        if self.state.rom != app_state.rom {
+            self.load(app_state);
+        }
+    }
+
+    /// Handle a terminal event, without waiting for one.
+    pub fn handle_event(&mut self, event: Event, app_state: &mut AppState) -> io::Result<()> {
+        if let (Some(log), Event::Key(key_event)) = (&mut self.state.key_log, &event) {
+            let action = match key_event.kind {
+                KeyEventKind::Press => KeyAction::Press,
+                KeyEventKind::Repeat => KeyAction::Repeat,
+                KeyEventKind::Release => KeyAction::Release,
+            };
+            let mapped = match key_event.code {
+                KeyCode::Char(c) => self.state.keymap.key(c),
+                _ => None,
+            };
+            log.push(key_event.code.to_string(), action, mapped);
+        }
+        match event {
+            Event::Key(key_event) if suspend::is_suspend_key(&key_event) => {
+                suspend::suspend(|| {
+                    ratatui::restore();
+                    crossterm::execute!(io::stdout(), DisableMouseCapture, crossterm::cursor::Show)
+                })?;
+                self.resume();
+            }
             Event::Key(key_event) if key_event.kind == KeyEventKind::Press => {
-                self.handle_key_event(key_event, app_state)
+                self.handle_key_event(key_event, app_state, true)
+            }
+            Event::Key(key_event) if key_event.kind == KeyEventKind::Release => {
+                self.handle_key_event(key_event, app_state, false)
             }
+            Event::Mouse(mouse_event) => self.handle_mouse_event(mouse_event),
+            Event::Paste(text) => self.paste(&text, app_state),
             _ => (),
         };
         Ok(())
     }
 
-    fn handle_key_event(&mut self, key_event: KeyEvent, app_state: &mut AppState) {
+    /// Type pasted text into the command palette if it is open, or
+    /// load it as a ROM written as hex.
+    fn paste(&mut self, text: &str, app_state: &mut AppState) {
+        if let Some(input) = &mut self.state.palette {
+            input.extend(text.chars().filter(|c| !c.is_control()));
+            return;
+        }
+        match app_state.paste_rom(text) {
+            // Loaded again even if the last ROM was pasted too.
+            Ok(()) => self.load(app_state),
+            Err(err) => self.state.status = Some(format!("Pasted text: {err}")),
+        }
+    }
+
+    /// Run `frames` frames, or none while paused or away from the game.
+    /// Frames due while paused are skipped.
+    pub fn run_frames(&mut self, frames: u32, app_state: &mut AppState) {
+        for _ in 0..frames {
+            let paused =
+                self.state.paused || self.state.palette.is_some() || self.state.stopped.is_some();
+            if paused || !matches!(app_state.screen, Screen::Game) {
+                break;
+            }
+            self.run_frame(app_state);
+        }
+    }
+
+    fn run_frame(&mut self, app_state: &mut AppState) {
+        #[cfg(feature = "script")]
+        self.run_script(app_state);
+        let input = &mut self.state.input;
+        input.sort_by_key(|event| event.cycle);
+        if let Some(rec) = &mut self.state.recording {
+            rec.movie.push_frame(input);
+        }
+        // Movies replay with the rng seeded once, so the recorder,
+        // which reseeds it, sits out recordings.
+        if let Some(flight) = &mut self.state.flight {
+            match self.state.recording {
+                Some(_) => flight.clear(),
+                None => flight.push_frame(&mut self.emu, input),
+            }
+        }
+        let start = self.emu.save_state();
+        // Movies replay whole frames, so breakpoints sit out recordings.
+        let no_breakpoints = Breakpoints::default();
+        let breakpoints = match self.state.recording {
+            Some(_) => &no_breakpoints,
+            None => &self.state.breakpoints,
+        };
+        let ran = self
+            .state
+            .rewind
+            .next_frame(&mut self.emu, input, breakpoints);
+        match ran {
+            Ok(Some(addr)) => {
+                // The frame was cut short.
+                if let Some(flight) = &mut self.state.flight {
+                    flight.clear();
+                }
+                self.state.stopped = Some(addr);
+                self.state.status = Some(format!("Break at {addr:#05X}, :continue"));
+            }
+            Ok(None) => {
+                self.state.timer.sync(&self.emu);
+                if let Some(filter) = &mut self.state.flashes {
+                    let damping = filter.damping();
+                    filter.frame(self.emu.screen_ref());
+                    if filter.damping() && !damping {
+                        self.state.status = Some("Reducing flashes".into());
+                    }
+                }
+                if !self.state.filters.is_empty() {
+                    let palette = palette::screen_palette(&self.state.config);
+                    self.state.filters.process(self.emu.screen_ref(), palette);
+                }
+                let (total, met) = (self.state.goals.len(), self.state.goals.met());
+                for (i, goal) in self.state.goals.check(&self.emu).iter().enumerate() {
+                    let n = met + i + 1;
+                    self.state.status = Some(format!("Goal met: {} ({n}/{total})", goal.name));
+                }
+            }
+            Err(err) => {
+                let dump = Minidump::capture(&mut self.emu, &start, input, &err);
+                app_state.error = Some(match settings::crashes_dir().map(|dir| dump.write(dir)) {
+                    Some(Ok(path)) => format!("{err}, crash dump in {}", path.display()),
+                    Some(Err(dump_err)) => format!("{err}, failed to write crash dump: {dump_err}"),
+                    None => err,
+                });
+                app_state.screen = Screen::Library;
+            }
+        }
+        input.clear();
+        #[cfg(feature = "audio")]
+        if let Some(rec) = &mut self.state.recording {
+            let rate = self.emu.timer_rate();
+            rec.beeper
+                .render_frame(self.emu.sound(), rate, &mut rec.samples);
+        }
+        self.show_skipped();
+        let writes = self.emu.take_ram_writes();
+        self.state.memory.update(&self.emu, &writes);
+        self.release_keys();
+        for key in self.state.assist.tick() {
+            self.state.input.push(InputEvent {
+                cycle: 0,
+                key: key as u8,
+                pressed: false,
+            });
+        }
+    }
+
+    /// Run the script for the next frame, adding its key events to the
+    /// frame's input, showing what it printed and announced in the
+    /// footer, and speaking what it announced with `--speak`.
+    #[cfg(feature = "script")]
+    fn run_script(&mut self, app_state: &mut AppState) {
+        // Movies replay key presses only, not what a script writes to
+        // the registers and RAM, so scripts sit out recordings.
+        if self.state.recording.is_some() {
+            return;
+        }
+        let Some(script) = app_state.script.as_mut().or(self.state.script.as_mut()) else {
+            return;
+        };
+        match script.run_frame(&mut self.emu) {
+            Ok(events) => {
+                self.state.input.extend(events);
+                let hud = script.hud();
+                if !hud.is_empty() {
+                    self.state.status = Some(hud.join(" | "));
+                }
+                let announcements = script.announcements();
+                if !announcements.is_empty() {
+                    let text = announcements.join(". ");
+                    if let Some(speech) = &mut app_state.speech
+                        && let Err(err) = speech.say(&text)
+                    {
+                        self.state.status = Some(format!("Speech: {err}"));
+                        return;
+                    }
+                    self.state.status = Some(text);
+                }
+            }
+            Err(err) => self.state.status = Some(format!("Script: {err}")),
+        }
+    }
+
+    /// Reset the emulator and load the rom selected in the app state.
+    fn load(&mut self, app_state: &mut AppState) {
+        // Fresh settings, the previous game may have had overrides.
+        self.emu = Oxid8::new();
+        self.emu.load_font();
+        self.state = GameState::default();
+        self.state.hotkeys = app_state.hotkeys.clone();
+        if app_state.flight_recorder {
+            self.state.flight = Some(FlightRecorder::default());
+        }
+        self.state.assist = KeyAssist::new(app_state.assist);
+        self.state.flashes = app_state.reduce_flashes.then(FlashFilter::default);
+        // Checked when the flag was parsed.
+        self.state.filters = app_state.filters.parse().unwrap_or_default();
+        self.state.ansi_colors = app_state.ansi_colors;
+
+        if let Some(rom) = &app_state.rom {
+            let loaded = match rom {
+                RomSource::File(path) => self.emu.load_rom(path),
+                RomSource::Builtin => self.emu.load_rom_bytes(&builtin::controls_test()),
+                RomSource::Pasted => self.emu.load_rom_bytes(&app_state.pasted),
+            };
+            match loaded {
+                Ok(()) => {
+                    self.state.rom = Some(rom.clone());
+                    self.state.memory = MemoryMap::new(&self.emu);
+                    self.state.boot = Some(self.emu.save_state());
+                    self.load_config();
+                    self.load_debug();
+                    self.load_goals();
+                    #[cfg(feature = "script")]
+                    self.load_script();
+                    self.apply_overrides(&app_state.rom_config);
+                    return;
+                }
+                Err(err) => app_state.error = Some(format!("{rom}: {err}")),
+            }
+        }
+
+        app_state.rom = None;
+        app_state.screen = Screen::Library;
+    }
+
+    /// Apply the overrides saved for the loaded rom, if any.
+    fn load_config(&mut self) {
+        let (Some(store), Some(hash)) = (settings::game_configs(), self.emu.rom_hash()) else {
+            return;
+        };
+        match store.load_merged(hash) {
+            Ok(Some(config)) => {
+                config.apply(&mut self.emu);
+                self.state.keymap = config.keymap.unwrap_or_default();
+                self.state.rotation = config.rotation.unwrap_or_default();
+                self.state.config = config;
+                self.state.status = Some("Loaded game settings".into());
+            }
+            Ok(None) => (),
+            Err(err) => self.state.status = Some(format!("Bad game settings: {err}")),
+        }
+    }
+
+    /// Restore the breakpoints and watches saved for the loaded rom,
+    /// if any.
+    fn load_debug(&mut self) {
+        let (Some(store), Some(hash)) = (settings::game_configs(), self.emu.rom_hash()) else {
+            return;
+        };
+        match store.load_debug(hash) {
+            Ok(Some(debug)) => {
+                self.state.breakpoints = debug.breakpoints;
+                self.state.watches = debug.watches;
+            }
+            Ok(None) => (),
+            Err(err) => self.state.status = Some(format!("Bad breakpoints and watches: {err}")),
+        }
+    }
+
+    /// Load the goals written for the loaded rom, if any.
+    fn load_goals(&mut self) {
+        let (Some(store), Some(hash)) = (settings::game_configs(), self.emu.rom_hash()) else {
+            return;
+        };
+        match store.load_goals(hash) {
+            Ok(Some(goals)) => self.state.goals = goals,
+            Ok(None) => (),
+            Err(err) => self.state.status = Some(format!("Bad goals: {err}")),
+        }
+    }
+
+    /// Load the script written for the loaded rom, if any.
+    #[cfg(feature = "script")]
+    fn load_script(&mut self) {
+        let (Some(store), Some(hash)) = (settings::game_configs(), self.emu.rom_hash()) else {
+            return;
+        };
+        match store.load_script(hash) {
+            Ok(script) => self.state.script = script,
+            Err(err) => self.state.status = Some(format!("Bad script: {err}")),
+        }
+    }
+
+    /// Save the breakpoints and watches for the loaded rom, so they are
+    /// back the next time it is loaded.
+    fn save_debug(&mut self) {
+        let (Some(store), Some(hash)) = (settings::game_configs(), self.emu.rom_hash()) else {
+            return;
+        };
+        let debug = DebugConfig {
+            breakpoints: self.state.breakpoints.clone(),
+            watches: self.state.watches.clone(),
+        };
+        if let Err(err) = store.save_debug(hash, &debug) {
+            self.state.status = Some(format!("Failed to save breakpoints and watches: {err}"));
+        }
+    }
+
+    /// Apply overrides for this launch, e.g. from a playlist, on top of
+    /// the saved settings.
+    fn apply_overrides(&mut self, overrides: &GameConfig) {
+        if *overrides == GameConfig::default() {
+            return;
+        }
+        overrides.apply(&mut self.emu);
+        self.state.keymap = overrides.keymap.unwrap_or(self.state.keymap);
+        self.state.rotation = overrides.rotation.unwrap_or(self.state.rotation);
+        self.state.loaded = self.state.config.clone();
+        self.state.config = std::mem::take(&mut self.state.config).merge(overrides);
+        self.state.overrides = overrides.clone();
+    }
+
+    /// Save the current settings as overrides for the loaded rom,
+    /// leaving out those still as the app state's overrides set them.
+    fn save_config(&mut self) {
+        let (Some(store), Some(hash)) = (settings::game_configs(), self.emu.rom_hash()) else {
+            return;
+        };
+        let config = GameConfig {
+            foreground: self.state.config.foreground,
+            background: self.state.config.background,
+            border: self.state.config.border,
+            keymap: Some(self.state.keymap),
+            rotation: Some(self.state.rotation),
+            ..GameConfig::capture(&self.emu)
+        };
+        let saved = config
+            .clone()
+            .without(&self.state.overrides, &self.state.loaded);
+        self.state.status = Some(match store.save(hash, &saved) {
+            Ok(()) => "Saved game settings".into(),
+            Err(err) => format!("Failed to save game settings: {err}"),
+        });
+        self.state.config = config;
+    }
+
+    /// Change the speed by `delta` cycles per frame.
+    fn change_speed(&mut self, delta: i32) {
+        let cycles = self
+            .emu
+            .cycles_per_frame()
+            .saturating_add_signed(delta)
+            .max(1);
+        self.emu.set_cycles_per_frame(cycles);
+        self.clear_history();
+        self.state.status = None;
+    }
+
+    /// Switch between 60Hz and 50Hz timers.
+    fn toggle_timer_rate(&mut self) {
+        self.emu.set_timer_rate(match self.emu.timer_rate() {
+            TimerRate::Ntsc => TimerRate::Pal,
+            TimerRate::Pal => TimerRate::Ntsc,
+        });
+        self.clear_history();
+        self.state.status = None;
+    }
+
+    /// Forget the frames run, which replay with the current settings.
+    fn clear_history(&mut self) {
+        self.state.rewind.clear();
+        if let Some(flight) = &mut self.state.flight {
+            flight.clear();
+        }
+    }
+
+    /// Write the flight recorder's last seconds to the data directory.
+    fn dump_flight(&mut self) {
+        let Some(flight) = &self.state.flight else {
+            self.state.status = Some("The flight recorder is off, see --flight-recorder".into());
+            return;
+        };
+        let Some(dump) = flight.dump() else {
+            self.state.status = Some("Nothing recorded yet".into());
+            return;
+        };
+        let Some(dir) = settings::flights_dir() else {
+            self.state.status = Some("No data directory".into());
+            return;
+        };
+        self.state.status = Some(match dump.write(dir) {
+            Ok(path) => format!(
+                "Dumped {} frames to {}",
+                dump.movie.frames.len(),
+                path.display()
+            ),
+            Err(err) => format!("Failed to dump: {err}"),
+        });
+    }
+
+    /// Switch between the standard and two player keymaps.
+    fn toggle_keymap(&mut self) {
+        self.state.keymap = self.state.keymap.toggle();
+        self.release_keys();
+        self.state.status = None;
+    }
+
+    /// Rotate the display a further 90 degrees clockwise.
+    fn rotate(&mut self) {
+        self.state.rotation = self.state.rotation.next();
+        self.state.status = Some(format!("Rotation: {}°", self.state.rotation));
+    }
+
+    /// The part of the screen shown, see [`Viewport::view`].
+    fn view(&self) -> View {
+        let area = (self.state.area.width.into(), self.state.area.height.into());
+        self.state
+            .viewport
+            .view(self.state.rotation.dimensions(), area)
+    }
+
+    /// Zoom in a step, or back to fitting the screen.
+    fn zoom(&mut self) {
+        let area = (self.state.area.width.into(), self.state.area.height.into());
+        let screen = self.state.rotation.dimensions();
+        self.state.viewport.cycle(screen, area);
+        let zoom = self.view().zoom;
+        self.state.status = Some(match self.state.viewport.zoom() {
+            Some(_) => format!("Zoom: {zoom}, arrows pan"),
+            None => format!("Zoom: fit, {zoom}"),
+        });
+    }
+
+    /// Pan a zoomed screen `PAN_STEP` pixels across and down.
+    fn pan(&mut self, dx: isize, dy: isize) {
+        let area = (self.state.area.width.into(), self.state.area.height.into());
+        let screen = self.state.rotation.dimensions();
+        self.state
+            .viewport
+            .pan(dx * PAN_STEP, dy * PAN_STEP, screen, area);
+    }
+
+    /// Release every held key at the start of the next frame, except
+    /// one held with the mouse.
+    fn release_keys(&mut self) {
+        let clicked = self.state.clicked.map(usize::from);
+        let held = self
+            .emu
+            .keys_ref()
+            .iter()
+            .enumerate()
+            .filter(|&(key, &held)| held && Some(key) != clicked)
+            .map(|(key, _)| key)
+            .collect::<Vec<_>>();
+        for key in held {
+            // Sticky and briefly tapped keys stay down.
+            if self.state.assist.release(key) == Some(false) {
+                self.state.input.push(InputEvent {
+                    cycle: 0,
+                    key: key as u8,
+                    pressed: false,
+                });
+            }
+        }
+    }
+
+    /// The keymap in use.
+    pub fn keymap(&self) -> Keymap {
+        self.state.keymap
+    }
+
+    /// Carry on after the process was stopped, with no time passed and
+    /// the keys held then released.
+    pub fn resume(&mut self) {
+        self.state.clock.restart();
+        self.state.input.clear();
+        self.state.clicked = None;
+        self.release_keys();
+    }
+
+    /// Pause or resume the game. Frames due while paused are skipped.
+    fn toggle_pause(&mut self) {
+        self.state.paused = !self.state.paused;
+        self.state.status =
+            self.state
+                .paused
+                .then(|| match self.hint(Action::Pause, "to resume") {
+                    Some(hint) => format!("Paused, {hint}"),
+                    None => "Paused".into(),
+                });
+    }
+
+    /// Restart the game from when it was loaded, keeping the settings
+    /// changed since, see `oxid8_core::reset`.
+    fn reset(&mut self) {
+        if self.state.recording.is_some() {
+            self.state.status = Some("Not while recording".into());
+            return;
+        }
+        let Some(boot) = &self.state.boot else {
+            return;
+        };
+        self.emu.restart(boot);
+        self.state.memory = MemoryMap::new(&self.emu);
+        self.state.rewind.clear();
+        if let Some(flight) = &mut self.state.flight {
+            flight.clear();
+        }
+        self.state.timer.reset();
+        self.state.goals.reset();
+        self.state.skips_shown = 0;
+        self.state.stopped = None;
+        self.state.input.clear();
+        self.state.clicked = None;
+        self.state.assist = KeyAssist::new(self.state.assist.assist());
+        self.state.status = Some("Reset".into());
+    }
+
+    /// Save the screen as a PNG, in the game's colors and filters.
+    fn screenshot(&mut self) {
+        let image = match self.state.filters.is_empty() {
+            true => self.emu.render_colored(
+                SCREENSHOT_SCALE,
+                false,
+                palette::screen_palette(&self.state.config),
+            ),
+            false => export::render_frame(self.state.filters.frame(), SCREENSHOT_SCALE, false),
+        };
+        self.state.status = Some(match save_screenshot(self.state.rom.as_ref(), &image) {
+            Ok(path) => format!("Saved {}", path.display()),
+            Err(err) => format!("Failed to save screenshot: {err}"),
+        });
+    }
+
+    /// Show or hide the raw key events beside the game.
+    fn toggle_key_log(&mut self) {
+        self.state.key_log = match self.state.key_log {
+            Some(_) => None,
+            None => Some(KeyLog::default()),
+        };
+        self.state.status = None;
+    }
+
+    /// Take a savestate to compare in the debugger.
+    fn snapshot(&mut self, app_state: &mut AppState) {
+        debug::push_snapshot(&mut app_state.snapshots, self.emu.save_state());
+        let hint = match app_state.snapshots.len() {
+            1 => self.hint(Action::Snapshot, "again to compare"),
+            _ => self.hint(Action::Diff, "to diff"),
+        };
+        self.state.status = Some(match hint {
+            Some(hint) => format!("Took savestate, {hint}"),
+            None => "Took savestate".into(),
+        });
+    }
+
+    /// Restart the game and record it, or stop and save the recording.
+    fn toggle_recording(&mut self, app_state: &mut AppState) {
+        if let Some(rec) = self.state.recording.take() {
+            let length = self.emu.time().since(rec.start);
+            self.state.status = Some(match save_recording(self.state.rom.as_ref(), rec) {
+                Ok(path) => format!("Saved {}, {length} long", path.display()),
+                Err(err) => format!("Failed to save recording: {err}"),
+            });
+            return;
+        }
+
+        // Replays start from a fresh boot of the rom.
+        self.load(app_state);
+        if self.state.rom.is_none() {
+            return;
+        }
+        let seed = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |time| time.as_nanos() as u64);
+        self.state.recording = Some(Recording {
+            movie: Movie::record(&mut self.emu, seed),
+            start: self.emu.time(),
+            #[cfg(feature = "audio")]
+            beeper: Default::default(),
+            #[cfg(feature = "audio")]
+            samples: Vec::new(),
+        });
+        self.state.status = Some(match self.hint(Action::Record, "to stop") {
+            Some(hint) => format!("Recording, {hint}"),
+            None => "Recording".into(),
+        });
+    }
+
+    /// Edit the command palette, running the command on enter.
+    fn palette_key(&mut self, key_event: KeyEvent, app_state: &mut AppState) {
+        let Some(input) = &mut self.state.palette else {
+            return;
+        };
         match key_event.code {
-            KeyCode::Char('q') | KeyCode::Esc => app_state.should_exit = true,
+            KeyCode::Esc => self.state.palette = None,
+            KeyCode::Enter => {
+                let result = input.parse();
+                self.state.palette = None;
+                match result {
+                    Ok(command) => self.run_command(command, app_state),
+                    Err(err) => self.state.status = Some(err),
+                }
+            }
+            KeyCode::Backspace if input.is_empty() => self.state.palette = None,
+            KeyCode::Backspace => {
+                input.pop();
+            }
+            KeyCode::Tab => {
+                let completions = command::complete(input);
+                if let Some(prefix) = command::common_prefix(&completions) {
+                    *input = prefix.to_string();
+                }
+                self.state.completions = completions;
+                return;
+            }
+            KeyCode::Char(c) => input.push(c),
+            _ => (),
+        }
+        self.state.completions.clear();
+    }
+
+    /// Open the color editor, the game keeps running to preview the
+    /// colors but keys go to the editor.
+    fn open_colors(&mut self) {
+        self.state.colors = Some(PaletteEditor::new(&self.state.config));
+        self.state.clicked = None;
+        self.release_keys();
+        self.state.status = None;
+    }
+
+    /// Edit the colors: arrows pick a color and channel, `+` and `-`
+    /// change it, Page Up and Page Down by 16, Tab cycles the presets,
+    /// Enter saves for the rom and `d` for every game.
+    fn colors_key(&mut self, key_event: KeyEvent) {
+        let (Some(editor), config) = (&mut self.state.colors, &mut self.state.config) else {
+            return;
+        };
+        let save = match key_event.code {
+            KeyCode::Esc => {
+                editor.cancel(config);
+                self.state.colors = None;
+                return;
+            }
+            KeyCode::Up => return editor.select_slot(-1),
+            KeyCode::Down => return editor.select_slot(1),
+            KeyCode::Left => return editor.select_channel(-1),
+            KeyCode::Right => return editor.select_channel(1),
+            KeyCode::Char('+' | '=') => return editor.adjust(config, 1),
+            KeyCode::Char('-') => return editor.adjust(config, -1),
+            KeyCode::PageUp => return editor.adjust(config, 16),
+            KeyCode::PageDown => return editor.adjust(config, -16),
+            KeyCode::Tab => return palette::next_preset(config).apply(config),
+            KeyCode::Enter => match self.emu.rom_hash() {
+                Some(hash) => Some(hash.to_string()),
+                None => return,
+            },
+            KeyCode::Char('d') => None,
+            _ => return,
+        };
+        self.state.colors = None;
+        let Some(store) = settings::game_configs() else {
+            return;
+        };
+        self.state.status = Some(match store.save_colors(save.as_deref(), config) {
+            Ok(()) if save.is_some() => "Saved colors for this game".into(),
+            Ok(()) => "Saved colors for every game".into(),
+            Err(err) => format!("Failed to save colors: {err}"),
+        });
+    }
+
+    /// Run a palette command with the same actions as the hotkeys.
+    fn run_command(&mut self, command: Command, app_state: &mut AppState) {
+        // Movies only record the settings they started with.
+        let changes_replay = matches!(
+            command,
+            Command::Speed(_)
+                | Command::Rate(_)
+                | Command::Weight(..)
+                | Command::Quirk(_)
+                | Command::Variant(_)
+                | Command::Skip(_)
+                | Command::Restore(_)
+                | Command::Break(_)
+                | Command::Continue
+                | Command::Step
+                | Command::Back
+        );
+        // Frames are stepped back through with the current settings.
+        let changes_history = matches!(
+            command,
+            Command::Speed(_)
+                | Command::Rate(_)
+                | Command::Weight(..)
+                | Command::Quirk(_)
+                | Command::Variant(_)
+                | Command::Skip(_)
+                | Command::Restore(_)
+        );
+        if changes_history {
+            self.clear_history();
+        }
+        if changes_replay && self.state.recording.is_some() {
+            self.state.status = Some("Not while recording".into());
+            return;
+        }
+        // The flight recorder replays whole frames only.
+        if let (Command::Step | Command::Back | Command::Continue, Some(flight)) =
+            (&command, &mut self.state.flight)
+        {
+            flight.clear();
+        }
+
+        match command {
+            Command::Load(path) => {
+                app_state.rom = Some(RomSource::File(path));
+                app_state.rom_config = GameConfig::default();
+            }
+            Command::Speed(cycles) => {
+                self.emu.set_cycles_per_frame(cycles);
+                self.state.status = None;
+            }
+            Command::Rate(rate) => {
+                self.emu.set_timer_rate(rate);
+                self.state.status = None;
+            }
+            Command::Weight(pattern, weight) => {
+                let mut weights = self.emu.cycle_weights().clone();
+                self.state.status = Some(match weights.set(&pattern, weight) {
+                    Ok(()) if weights.is_empty() => "Every instruction costs 1 cycle".into(),
+                    Ok(()) => format!("Cycle weights {weights}"),
+                    Err(err) => err,
+                });
+                self.emu.set_cycle_weights(weights);
+            }
+            Command::Quirk(quirk) => {
+                let mut quirks = self.emu.quirks();
+                quirk.apply(&mut quirks);
+                self.emu.set_quirks(quirks);
+                self.state.status = Some(quirks.to_string());
+            }
+            Command::Variant(variant) => {
+                self.state.status = Some(match self.emu.set_variant(variant) {
+                    Ok(()) => format!("Switched to {variant}"),
+                    Err(err) => err,
+                });
+            }
+            Command::Save(slot) => {
+                let state = self.emu.save_state();
+                self.state.status = Some(match store_state(slot, &state) {
+                    Ok(()) => format!("Saved state {slot}"),
+                    Err(err) => format!("Saved state {slot} until exit: {err}"),
+                });
+                self.state.slots.insert(slot, state);
+            }
+            Command::Restore(slot) => {
+                let stored = match (settings::states(), self.emu.rom_hash()) {
+                    (Some(store), Some(hash)) => store.load(hash, slot),
+                    _ => Ok(None),
+                };
+                self.state.status = Some(match stored {
+                    Ok(state) => match state.or_else(|| self.state.slots.get(&slot).cloned()) {
+                        Some(state) => {
+                            self.emu.restore_state(&state);
+                            self.state.stopped = None;
+                            format!("Restored state {slot}")
+                        }
+                        None => format!("No state in slot {slot}"),
+                    },
+                    Err(err) => format!("Failed to restore state {slot}: {err}"),
+                });
+            }
+            Command::Break(addr) => {
+                let added = self.state.breakpoints.toggle(addr);
+                self.state.status = Some(match added {
+                    true => format!("Breakpoint at {addr:#05X}"),
+                    false => format!("Removed breakpoint at {addr:#05X}"),
+                });
+                self.save_debug();
+            }
+            Command::Watch(watch) => {
+                self.state.status = Some(format!("Watching {watch}"));
+                self.state.watches.push(watch);
+                self.save_debug();
+            }
+            Command::Unwatch(None) => {
+                self.state.watches.clear();
+                self.state.status = Some("Removed every watch".into());
+                self.save_debug();
+            }
+            Command::Unwatch(Some(n)) => {
+                self.state.status = Some(match n <= self.state.watches.len() {
+                    true => format!("Removed {}", self.state.watches.remove(n - 1)),
+                    false => format!("No watch {n}"),
+                });
+                self.save_debug();
+            }
+            Command::Continue => {
+                if self.state.stopped.take().is_none() {
+                    self.state.status = Some("Not stopped at a breakpoint".into());
+                    return;
+                }
+                // Step over the breakpoint.
+                self.state.status = self.state.rewind.step(&mut self.emu).err();
+            }
+            Command::Step => match self.state.rewind.step(&mut self.emu) {
+                Ok(()) => self.pause("Stepped to"),
+                Err(err) => self.state.status = Some(err),
+            },
+            Command::Back => match self.state.rewind.step_back(&mut self.emu) {
+                Ok(true) => self.pause("Back at"),
+                Ok(false) => self.state.status = Some("No history to step back through".into()),
+                Err(err) => self.state.status = Some(err),
+            },
+            Command::Record => self.toggle_recording(app_state),
+            Command::Dump => self.dump_flight(),
+            Command::Rotate => self.rotate(),
+            Command::Keymap => self.toggle_keymap(),
+            Command::Keys => self.toggle_key_log(),
+            Command::Timer(timer) => {
+                match timer {
+                    Timer::Show => self.state.show_timer = true,
+                    Timer::Hide => self.state.show_timer = false,
+                    Timer::Reset => self.state.timer.reset(),
+                }
+                self.state.status = None;
+            }
+            Command::Settings => self.save_config(),
+            Command::Diff => app_state.screen = Screen::Debug,
+            Command::Memory => {
+                self.state.show_memory = !self.state.show_memory;
+                self.state.status = None;
+            }
+            Command::Colors => self.open_colors(),
+            Command::Skip(skip) => {
+                self.emu.set_skip_invalid(skip);
+                self.state.status = Some(match skip {
+                    true => "Skipping invalid opcodes".into(),
+                    false => "Stopping at invalid opcodes".into(),
+                });
+            }
+        }
+    }
+
+    /// Tell the player about invalid opcodes skipped for the first time.
+    fn show_skipped(&mut self) {
+        let entries = self.emu.skipped().entries();
+        if let Some(new) = entries.get(self.state.skips_shown..).and_then(<[_]>::first) {
+            self.state.status = Some(format!("Skipped invalid opcode {new}"));
+        }
+        self.state.skips_shown = entries.len();
+    }
+
+    /// Pause at the program counter after stepping.
+    fn pause(&mut self, action: &str) {
+        let pc = self.emu.pc();
+        self.state.stopped = Some(pc);
+        self.state.status = Some(match self.emu.timer_wait() {
+            // DT doesn't count down while stepping.
+            Some(wait) => format!("{action} {pc:#05X}, {wait}, :continue"),
+            None => format!("{action} {pc:#05X}, :step, :back, :continue"),
+        });
+    }
+
+    fn handle_key_event(&mut self, key_event: KeyEvent, app_state: &mut AppState, val: bool) {
+        if self.state.palette.is_some() {
+            if val {
+                self.palette_key(key_event, app_state);
+            }
+            return;
+        }
+        if self.state.colors.is_some() {
+            if val {
+                self.colors_key(key_event);
+            }
+            return;
+        }
+        // Keys bound to hotkeys never reach the keypad.
+        if let Some(action) = hotkey(key_event.code).and_then(|key| self.state.hotkeys.action(key))
+        {
+            let now = self.state.now();
+            let run = match (action, val) {
+                // Terminals repeat the press of a held key.
+                (Action::Reset, true) => self.state.reset_key.press(now),
+                (Action::Reset, false) => {
+                    self.state.reset_key.release();
+                    false
+                }
+                _ => val,
+            };
+            if run {
+                self.run_hotkey(action, app_state);
+            }
+            return;
+        }
+        let (dx, dy) = match key_event.code {
+            KeyCode::Left => (-1, 0),
+            KeyCode::Right => (1, 0),
+            KeyCode::Up => (0, -1),
+            KeyCode::Down => (0, 1),
+            _ => (0, 0),
+        };
+        if (dx, dy) != (0, 0) {
+            if val {
+                self.pan(dx, dy);
+            }
+            return;
+        }
+        let k = match key_event.code {
+            // See `oxid8_core::keymap` for the layouts.
+            KeyCode::Char(c) => match self.state.keymap.key(c) {
+                Some(k) => k,
+                None => return,
+            },
+            _ => return,
+        };
+        #[cfg(feature = "tracing")]
+        tracing::debug!(?key_event.code, "Key event");
+        self.assist_key(k, val);
+    }
+
+    /// Run the action of a hotkey, see [`HOTKEYS`].
+    fn run_hotkey(&mut self, action: Action, app_state: &mut AppState) {
+        // Movies only record the settings they started with.
+        let changes_replay = matches!(action, Action::Slower | Action::Faster | Action::TimerRate);
+        if changes_replay && self.state.recording.is_some() {
+            return;
+        }
+        match action {
+            Action::Menu => app_state.screen = Screen::Menu,
+            Action::Help => app_state.screen = Screen::Help,
+            Action::Diff => app_state.screen = Screen::Debug,
+            Action::Slower => self.change_speed(-1),
+            Action::Faster => self.change_speed(1),
+            Action::Pause => self.toggle_pause(),
+            Action::Reset => self.reset(),
+            Action::SaveState => self.run_command(Command::Save(QUICK_SLOT), app_state),
+            Action::LoadState => self.run_command(Command::Restore(QUICK_SLOT), app_state),
+            Action::SaveSettings => self.save_config(),
+            Action::TimerRate => self.toggle_timer_rate(),
+            Action::Keymap => self.toggle_keymap(),
+            Action::Rotate => self.rotate(),
+            Action::Zoom => self.zoom(),
+            Action::KeyLog => self.toggle_key_log(),
+            Action::Record => self.toggle_recording(app_state),
+            Action::Snapshot => self.snapshot(app_state),
+            Action::Screenshot => self.screenshot(),
+            Action::Palette => {
+                self.state.palette = Some(String::new());
+                self.state.clicked = None;
+                self.release_keys();
+            }
+            Action::CyclePalette => {
+                let preset = palette::next_preset(&self.state.config);
+                preset.apply(&mut self.state.config);
+                self.state.status = Some(format!("Colors: {}", preset.name));
+            }
+            Action::Colors => self.open_colors(),
+            _ => (),
+        }
+    }
+
+    /// The key bound to `action` followed by `text`, e.g. "F7 to stop",
+    /// or `None` if it is unbound.
+    fn hint(&self, action: Action, text: &str) -> Option<String> {
+        let key = self.state.hotkeys.key(action)?;
+        Some(format!("{key} {text}"))
+    }
+
+    /// Clicking a key on the legend holds it until the button is
+    /// released.
+    fn handle_mouse_event(&mut self, mouse_event: MouseEvent) {
+        match mouse_event.kind {
+            MouseEventKind::Down(MouseButton::Left) if self.state.palette.is_none() => {
+                let Some(k) = self.legend_key(mouse_event.column, mouse_event.row) else {
+                    return;
+                };
+                self.state.clicked = Some(k);
+                self.assist_key(k, true);
+            }
+            MouseEventKind::Up(MouseButton::Left) => {
+                if let Some(k) = self.state.clicked.take() {
+                    self.assist_key(k, false);
+                }
+            }
             _ => (),
         }
     }
 
-    //fn canvas(&self) -> impl Widget + '_ {}
+    /// Queue a key press or release as changed by the sticky keys and
+    /// minimum hold settings.
+    fn assist_key(&mut self, key: u8, pressed: bool) {
+        let change = match pressed {
+            true => self.state.assist.press(key.into()),
+            false => self.state.assist.release(key.into()),
+        };
+        if let Some(pressed) = change {
+            self.push_key(key, pressed);
+        }
+    }
+
+    /// Queue a key event for the next frame, timestamped now.
+    fn push_key(&mut self, key: u8, pressed: bool) {
+        let now = self.state.now();
+        let elapsed = self.state.clock.since_timer(now);
+        let event = InputEvent {
+            cycle: self.emu.cycle_at(elapsed),
+            key,
+            pressed,
+        };
+        #[cfg(feature = "tracing")]
+        tracing::debug!(%event, "Keypad event");
+        self.state.input.push(event);
+    }
+
+    /// The keypad key drawn at a terminal cell of the legend, or the
+    /// space after it.
+    fn legend_key(&self, column: u16, row: u16) -> Option<u8> {
+        KeypadDiagram::new(self.state.keymap).key_at(self.state.legend, Position::new(column, row))
+    }
+}
+
+/// Save `rec` as `<name>-<time>.movie`, and `.wav` with the `audio`
+/// feature, in the movies directory, returning the movie path.
+fn save_recording(rom: Option<&RomSource>, rec: Recording) -> io::Result<PathBuf> {
+    let dir = settings::movies_dir()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "No data directory"))?;
+    fs::create_dir_all(&dir)?;
+    let path = dir.join(format!("{}.movie", file_name(rom)));
+
+    rec.movie.save(&path)?;
+    #[cfg(feature = "audio")]
+    {
+        use oxid8_core::audio;
+        let wav = BufWriter::new(File::create(path.with_extension("wav"))?);
+        audio::write_wav(wav, audio::SAMPLE_RATE, &rec.samples)?;
+    }
+    Ok(path)
+}
+
+/// Saves `image` to `<data_dir>/oxid8/screenshots/<rom>-<time>.png`.
+fn save_screenshot(rom: Option<&RomSource>, image: &RgbaImage) -> io::Result<PathBuf> {
+    let dir = settings::screenshots_dir()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "No data directory"))?;
+    fs::create_dir_all(&dir)?;
+    let path = dir.join(format!("{}.png", file_name(rom)));
+
+    image.write_png(BufWriter::new(File::create(&path)?))?;
+    Ok(path)
+}
+
+/// `<rom>-<time>`, the rom's file stem and the seconds since the Unix
+/// epoch.
+fn file_name(rom: Option<&RomSource>) -> String {
+    let name = rom.map_or("oxid8".into(), RomSource::stem);
+    let time = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |time| time.as_secs());
+    format!("{name}-{time}")
+}
+
+/// The hotkey for a key the terminal reports.
+fn hotkey(code: KeyCode) -> Option<Key> {
+    Some(match code {
+        KeyCode::Char(c) => Key::Char(c),
+        KeyCode::F(n) => Key::F(n),
+        KeyCode::Esc => Key::Esc,
+        KeyCode::Enter => Key::Enter,
+        KeyCode::Tab => Key::Tab,
+        KeyCode::Backspace => Key::Backspace,
+        KeyCode::Insert => Key::Insert,
+        KeyCode::Delete => Key::Delete,
+        KeyCode::Home => Key::Home,
+        KeyCode::End => Key::End,
+        KeyCode::PageUp => Key::PageUp,
+        KeyCode::PageDown => Key::PageDown,
+        _ => return None,
+    })
+}
+
+/// The color a region is drawn in on the memory map.
+fn region_color(region: Region) -> Color {
+    match region {
+        Region::Free => Color::DarkGray,
+        Region::Interpreter => Color::Gray,
+        Region::Font => Color::Yellow,
+        Region::Rom => Color::Blue,
+        Region::Data => Color::Red,
+    }
+}
+
+/// Stores `state` in `slot` for the frontends to share.
+fn store_state(slot: u8, state: &SaveState) -> io::Result<()> {
+    let mut store = settings::states()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "No data directory"))?;
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    store.save(slot, state, now)
+}
+
+impl Game {
+    fn foreground(&self) -> Color {
+        self.state
+            .config
+            .foreground
+            .map_or(Color::White, |color| self.term_color(color))
+    }
+
+    fn background(&self) -> Color {
+        self.state
+            .config
+            .background
+            .map_or(Color::Reset, |color| self.term_color(color))
+    }
+
+    /// The color of a pixel `level` of the way from unlit to lit, white
+    /// on black unless set.
+    fn damped_color(&self, level: u8) -> Color {
+        let palette = palette::screen_palette(&self.state.config);
+        self.term_color(palette.foreground.mix(palette.background, level))
+    }
+
+    /// `color` as the terminal can draw it.
+    fn term_color(&self, color: Rgba) -> Color {
+        term_color(color, self.state.ansi_colors)
+    }
+
+    /// Color around the screen, the terminal's own background unless
+    /// set.
+    fn border(&self) -> Option<Color> {
+        self.state.config.border.map(|color| self.term_color(color))
+    }
+
+    fn render_footer(&self, area: Rect, buf: &mut Buffer) {
+        if let Some(input) = &self.state.palette {
+            let completions = self.state.completions.join("  ");
+            Paragraph::new(format!(":{input}▏  {completions}")).render(area, buf);
+            return;
+        }
+        let profile = match self.emu.skipped().total() {
+            0 => self.emu.profile().to_string(),
+            1 => format!("{} | 1 invalid opcode skipped", self.emu.profile()),
+            n => format!("{} | {n} invalid opcodes skipped", self.emu.profile()),
+        };
+        let text = match &self.state.status {
+            Some(status) => format!("{profile} | {status}"),
+            None => {
+                let hints: Vec<String> = [
+                    (Action::Help, "for help"),
+                    (Action::Palette, "for commands"),
+                    (Action::Menu, "to go back"),
+                ]
+                .into_iter()
+                .filter_map(|(action, text)| self.hint(action, text))
+                .collect();
+                format!("{profile} | {}", hints.join(", "))
+            }
+        };
+        Paragraph::new(text).centered().render(area, buf);
+    }
+
+    /// Keyboard keys laid out like the keypad, colored by player.
+    /// Held keys, and keys tapped this frame, are highlighted. Keys can
+    /// be clicked to press them.
+    fn render_legend(&self, area: Rect, buf: &mut Buffer) {
+        let keys = self.emu.keys_ref();
+        let held = std::array::from_fn(|k| keys[k] || self.emu.just_pressed(k));
+        KeypadDiagram::new(self.state.keymap)
+            .held(held)
+            .render(area, buf);
+    }
+
+    /// The speedrun timer and the keys held on the keypad.
+    fn render_timer(&self, area: Rect, buf: &mut Buffer) {
+        let lines = vec![
+            Line::from(format!("Time {}", self.state.timer).bold()),
+            Line::from(format!("Held: {}", keylog::held_keys(self.emu.keys_ref()))),
+        ];
+        Paragraph::new(lines).render(area, buf);
+    }
+
+    /// Watch expressions, numbered for `:unwatch`, with their values
+    /// now.
+    fn render_watches(&self, area: Rect, buf: &mut Buffer) {
+        let mut lines = vec![Line::from("Watches (:unwatch <n>)".bold())];
+        lines.extend(self.state.watches.iter().enumerate().map(|(i, watch)| {
+            let n = i + 1;
+            match watch.eval(&self.emu) {
+                Ok(value) => Line::from(format!("{n} {watch} = {value}")),
+                Err(err) => Line::from(format!("{n} {watch}: {err}").red()),
+            }
+        }));
+        Paragraph::new(lines)
+            .wrap(Wrap { trim: true })
+            .render(area, buf);
+    }
+
+    /// RAM as a bar, a cell for every 64 bytes, with the bytes in each
+    /// region and the stack depth.
+    fn render_memory(&self, area: Rect, buf: &mut Buffer) {
+        let memory = &self.state.memory;
+        let cell = |region| Span::styled("█", region_color(region));
+        let mut lines = vec![Line::from("Memory (:memory to hide)".bold())];
+        lines.extend(
+            memory
+                .bar(MEMORY_CELLS)
+                .chunks(MEMORY_CELLS / 2)
+                .map(|row| Line::from(row.iter().map(|&region| cell(region)).collect::<Vec<_>>())),
+        );
+        lines.extend(REGIONS.iter().map(|&region| {
+            Line::from(vec![
+                cell(region),
+                format!(" {region:<12}{:>5} bytes", memory.bytes(region)).into(),
+            ])
+        }));
+        lines.push(Line::from(format!(
+            "Stack {}, deepest {} of {STACK_DEPTH}",
+            memory.stack(),
+            memory.deepest()
+        )));
+        Paragraph::new(lines).render(area, buf);
+    }
+
+    /// The colors being edited with swatches, the channel being
+    /// changed highlighted, and the keys.
+    fn render_colors(&self, editor: &PaletteEditor, area: Rect, buf: &mut Buffer) {
+        let config = &self.state.config;
+        let mut lines = vec![Line::from("Colors (Esc to cancel)".bold())];
+        lines.extend(SLOTS.iter().map(|&slot| {
+            let color = slot.color(config);
+            let mut spans = vec![
+                Span::styled("██", self.term_color(color)),
+                format!(" {slot:<11}#").into(),
+            ];
+            spans.extend(color.to_rgb().iter().enumerate().map(|(i, c)| {
+                let hex = Span::raw(format!("{c:02x}"));
+                match slot == editor.slot() && i == editor.channel() {
+                    true => hex.reversed(),
+                    false => hex,
+                }
+            }));
+            Line::from(spans)
+        }));
+        lines.push(Line::from(format!(
+            "Editing {} {}",
+            editor.slot(),
+            CHANNELS[editor.channel()]
+        )));
+        lines.push(Line::from("+/- PgUp/PgDn change, Tab preset"));
+        lines.push(Line::from("Enter save for game, d for all"));
+        Paragraph::new(lines).render(area, buf);
+    }
+
+    /// Raw key events, newest first, beside the keys held on the
+    /// keypad, for debugging terminals and keymaps.
+    fn render_key_log(&self, log: &KeyLog, area: Rect, buf: &mut Buffer) {
+        let mut lines = vec![
+            Line::from("Keys (F6 to hide)".bold()),
+            Line::from(format!("Held: {}", keylog::held_keys(self.emu.keys_ref()))),
+            Line::from(log.counts()),
+        ];
+        if let Some(warning) = log.warning() {
+            lines.push(Line::from(warning.yellow()));
+        }
+        lines.push(Line::default());
+        lines.extend(log.records().map(|record| match record.mapped {
+            Some(_) => Line::from(record.to_string().cyan()),
+            None => Line::from(record.to_string()),
+        }));
+        Paragraph::new(lines)
+            .wrap(Wrap { trim: true })
+            .render(area, buf);
+    }
+}
+
+impl Shape for Game {
+    fn draw(&self, painter: &mut Painter) {
+        let color = self.foreground();
+        let screen_ref = self.emu.screen_ref();
+        let rotation = self.state.rotation;
+        let view = self.view();
+        let damped = self
+            .state
+            .flashes
+            .as_ref()
+            .filter(|filter| filter.damping());
+        let filtered = (!self.state.filters.is_empty()).then(|| self.state.filters.frame());
+        let background = palette::screen_palette(&self.state.config).background;
+        for y in 0..view.height {
+            for x in 0..view.width {
+                let (px, py) = (view.x + x, view.y + y);
+                let color = if let Some(filter) = damped {
+                    let (sx, sy) = rotation.source(px, py);
+                    match filter.levels()[sx + sy * SCREEN_WIDTH] {
+                        0 => continue,
+                        level => self.damped_color(level),
+                    }
+                } else if let Some(frame) = filtered {
+                    let (sx, sy) = rotation.source(px, py);
+                    match frame[sx + sy * SCREEN_WIDTH] {
+                        // Left to the canvas, as unlit pixels are.
+                        color if color == background => continue,
+                        color => self.term_color(color),
+                    }
+                } else if rotation.pixel(screen_ref, px, py) {
+                    color
+                } else {
+                    continue;
+                };
+                // Square pixels take two cells.
+                match view.zoom {
+                    Zoom::Block => {
+                        painter.paint(2 * x, y, color);
+                        painter.paint(2 * x + 1, y, color);
+                    }
+                    Zoom::Braille | Zoom::HalfBlock => painter.paint(x, y, color),
+                }
+            }
+        }
+    }
 }
 
 impl Widget for &mut Game {
     fn render(self, area: Rect, buf: &mut Buffer) {
-        let width = SCREEN_WIDTH as f64;
-        let height = SCREEN_HEIGHT as f64;
+        let [area, footer] =
+            Layout::vertical([Constraint::Fill(1), Constraint::Length(1)]).areas(area);
+        self.render_footer(footer, buf);
+        let panel = self.state.show_timer
+            || self.state.show_memory
+            || self.state.colors.is_some()
+            || self.state.key_log.is_some()
+            || !self.state.watches.is_empty();
+        let area = if panel {
+            let [area, panel] = Layout::horizontal([Constraint::Fill(1), Constraint::Length(32)])
+                .spacing(1)
+                .areas(area);
+            let watches = match self.state.watches.len() {
+                0 => 0,
+                n => n as u16 + 2,
+            };
+            let timer = match self.state.show_timer {
+                true => 3,
+                false => 0,
+            };
+            let memory = match self.state.show_memory {
+                true => REGIONS.len() as u16 + 5,
+                false => 0,
+            };
+            let colors = match self.state.colors {
+                Some(_) => SLOTS.len() as u16 + 5,
+                None => 0,
+            };
+            let [
+                colors_panel,
+                timer_panel,
+                watch_panel,
+                memory_panel,
+                log_panel,
+            ] = Layout::vertical([
+                Constraint::Length(colors),
+                Constraint::Length(timer),
+                Constraint::Length(watches),
+                Constraint::Length(memory),
+                Constraint::Fill(1),
+            ])
+            .areas(panel);
+            if let Some(editor) = &self.state.colors {
+                self.render_colors(editor, colors_panel, buf);
+            }
+            if self.state.show_timer {
+                self.render_timer(timer_panel, buf);
+            }
+            self.render_watches(watch_panel, buf);
+            if self.state.show_memory {
+                self.render_memory(memory_panel, buf);
+            }
+            if let Some(log) = &self.state.key_log {
+                self.render_key_log(log, log_panel, buf);
+            }
+            area
+        } else {
+            area
+        };
+        if let Some(border) = self.border() {
+            buf.set_style(area, Style::new().bg(border));
+        }
+
+        // The screen gets what the legend leaves, zoomed to fit unless
+        // zoomed in.
+        self.state.area = Rect {
+            width: area.width.saturating_sub(keypad::WIDTH + 2),
+            ..area
+        };
+        let view = self.view();
+        let (width, height) = view.zoom.cells(view.width, view.height);
+        let (width, height) = (width as u16, height as u16);
+
+        let [area] = Layout::vertical([Constraint::Length(height)])
+            .flex(Flex::Center)
+            .areas(area);
+        let [area, legend] =
+            Layout::horizontal([Constraint::Length(width), Constraint::Length(keypad::WIDTH)])
+                .flex(Flex::Center)
+                .spacing(2)
+                .areas(area);
+        self.state.legend = legend;
+        self.render_legend(legend, buf);
 
         Widget::render(
             Canvas::default()
-                .x_bounds([-width / 2.0, width / 2.0])
-                .y_bounds([-height / 2.0, height / 2.0])
-                .marker(Marker::HalfBlock)
-                .paint(|ctx| {
-                    let screen_ref = self.emu.screen_ref();
-                    for y in 0..SCREEN_HEIGHT {
-                        for x in 0..SCREEN_WIDTH {
-                            if screen_ref[x + y * SCREEN_WIDTH] {
-                                ctx.print(x as f64, y as f64, "█");
-                            }
-                        }
-                    }
-                }),
+                .x_bounds([0.0, width as f64])
+                .y_bounds([0.0, height as f64])
+                .marker(render::marker(view.zoom))
+                .background_color(self.background())
+                .paint(|ctx| ctx.draw(&*self)),
             area,
             buf,
         );
@@ -0,0 +1,129 @@
+use crate::app::AppState;
+use crate::command::COMMANDS;
+use crate::keypad::KeypadDiagram;
+use crate::screens::Screen;
+use oxid8_core::{
+    hotkeys::Hotkeys,
+    keymap::{KEYPAD, Keymap},
+};
+
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, MouseEvent, MouseEventKind};
+use ratatui::{
+    Frame,
+    buffer::Buffer,
+    layout::{Constraint, Layout, Rect},
+    style::Stylize,
+    text::{Line, Span},
+    widgets::{Block, Paragraph, Widget},
+};
+use std::io;
+
+/// Keys and commands, from the keymap and hotkeys in use and the
+/// commands the palette parses, so they can't go out of date.
+#[derive(Default)]
+pub struct Help {
+    scroll: u16,
+}
+
+impl Help {
+    pub fn draw(&mut self, frame: &mut Frame, keymap: Keymap, hotkeys: &Hotkeys) {
+        let area = frame.area();
+        self.render(keymap, hotkeys, area, frame.buffer_mut());
+    }
+
+    pub fn handle_events(&mut self, app_state: &mut AppState) -> io::Result<()> {
+        match event::read()? {
+            Event::Key(key_event) if key_event.kind == KeyEventKind::Press => {
+                self.handle_key_event(key_event, app_state)
+            }
+            Event::Mouse(mouse_event) => self.handle_mouse_event(mouse_event),
+            _ => (),
+        };
+        Ok(())
+    }
+
+    fn handle_mouse_event(&mut self, mouse_event: MouseEvent) {
+        match mouse_event.kind {
+            MouseEventKind::ScrollDown => self.scroll = self.scroll.saturating_add(1),
+            MouseEventKind::ScrollUp => self.scroll = self.scroll.saturating_sub(1),
+            _ => (),
+        }
+    }
+
+    fn handle_key_event(&mut self, key_event: KeyEvent, app_state: &mut AppState) {
+        match key_event.code {
+            KeyCode::Char('q') | KeyCode::Esc | KeyCode::F(1) => {
+                self.scroll = 0;
+                app_state.screen = match app_state.rom {
+                    Some(_) => Screen::Game,
+                    None => Screen::Menu,
+                };
+            }
+            KeyCode::Char('j') | KeyCode::Down => self.scroll = self.scroll.saturating_add(1),
+            KeyCode::Char('k') | KeyCode::Up => self.scroll = self.scroll.saturating_sub(1),
+            KeyCode::Char('g') | KeyCode::Home => self.scroll = 0,
+            _ => (),
+        }
+    }
+}
+
+/// Rendering logic for the help
+impl Help {
+    fn render(&self, keymap: Keymap, hotkeys: &Hotkeys, area: Rect, buf: &mut Buffer) {
+        let [body, footer] =
+            Layout::vertical([Constraint::Fill(1), Constraint::Length(1)]).areas(area);
+        Paragraph::new("Use ↓↑ to scroll, g to go top, esc to go back.")
+            .centered()
+            .render(footer, buf);
+
+        let title = format!("Oxid8 {}", env!("CARGO_PKG_VERSION"));
+        Paragraph::new(help_lines(keymap, hotkeys))
+            .block(Block::bordered().title(Line::raw(title).centered()))
+            .scroll((self.scroll, 0))
+            .render(body, buf);
+    }
+}
+
+/// The keypad beside the keys typing it, then the hotkeys and commands.
+fn help_lines(keymap: Keymap, hotkeys: &Hotkeys) -> Vec<Line<'static>> {
+    let mut lines = vec![match keymap {
+        Keymap::Standard => Line::from("Keypad".bold()),
+        Keymap::TwoPlayer => Line::from(vec![
+            "Keypad, two players: ".bold(),
+            "P1".cyan(),
+            " ".into(),
+            "P2".magenta(),
+        ]),
+    }];
+    let diagram = KeypadDiagram::new(keymap).lines();
+    lines.extend(KEYPAD.iter().zip(diagram).map(|(keys, labels)| {
+        let keys = keys.iter().map(|k| format!("{k:X}")).collect::<Vec<_>>();
+        let mut spans = vec![Span::raw(format!("  {}    ", keys.join(" ")))];
+        spans.extend(labels.spans);
+        Line::from(spans)
+    }));
+
+    lines.push(Line::raw(""));
+    lines.push(Line::from("Hotkeys".bold()));
+    let hotkeys = hotkeys
+        .iter()
+        .map(|(key, action)| (key.to_string(), action.description()));
+    let others = [
+        ("Click", "Press a key on the legend"),
+        ("Ctrl-Z", "Suspend"),
+    ];
+    lines.extend(
+        hotkeys
+            .chain(others.map(|(key, what)| (key.to_string(), what)))
+            .map(|(key, what)| Line::from(vec![format!("  {key:<10}").cyan(), what.into()])),
+    );
+
+    lines.push(Line::raw(""));
+    lines.push(Line::from("Commands, after :".bold()));
+    lines.extend(
+        COMMANDS
+            .iter()
+            .map(|(name, args)| Line::from(vec![format!("  {name:<10}").cyan(), (*args).into()])),
+    );
+    lines
+}
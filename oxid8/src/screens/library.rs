@@ -0,0 +1,253 @@
+use crate::app::{AppState, RomSource};
+use crate::library::{Library, Match};
+use crate::screens::Screen;
+
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind};
+use ratatui::{
+    Frame,
+    buffer::Buffer,
+    layout::{Constraint, Layout, Rect},
+    style::{Modifier, Style, Stylize, palette::tailwind::SLATE},
+    text::{Line, Span},
+    widgets::{
+        Block,            //
+        HighlightSpacing, //
+        List,             //
+        ListItem,         //
+        ListState,        //
+        Paragraph,        //
+        StatefulWidget,   //
+        Widget,           //
+    },
+};
+use std::io;
+
+const SELECTED_STYLE: Style = Style::new().bg(SLATE.c800).add_modifier(Modifier::BOLD);
+const MATCH_STYLE: Style = Style::new()
+    .fg(SLATE.c300)
+    .add_modifier(Modifier::UNDERLINED);
+
+/// ROM picker listing the scanned library.
+///
+/// Press `/` to filter the list by fuzzy matching as you type.
+pub struct LibraryScreen {
+    library: Library,
+    state: ListState,
+    scanned: bool,
+    /// Is the search input focused.
+    searching: bool,
+    query: String,
+    /// Entries matching the query, in display order.
+    matches: Vec<Match>,
+    /// Why the last ROM failed to load.
+    error: Option<String>,
+}
+
+impl Default for LibraryScreen {
+    fn default() -> Self {
+        Self::new(Library::from_env())
+    }
+}
+
+impl LibraryScreen {
+    pub fn new(library: Library) -> Self {
+        Self {
+            library,
+            state: ListState::default(),
+            scanned: false,
+            searching: false,
+            query: String::new(),
+            matches: Vec::new(),
+            error: None,
+        }
+    }
+}
+
+impl LibraryScreen {
+    pub fn draw(&mut self, frame: &mut Frame, app_state: &AppState) {
+        if !self.scanned {
+            self.rescan();
+        }
+        self.error.clone_from(&app_state.error);
+        frame.render_widget(self, frame.area());
+    }
+
+    pub fn handle_events(&mut self, app_state: &mut AppState) -> io::Result<()> {
+        match event::read()? {
+            Event::Key(key_event) if key_event.kind == KeyEventKind::Press => {
+                self.handle_key_event(key_event, app_state)
+            }
+            Event::Paste(text) => {
+                if let Err(err) = app_state.paste_rom(&text) {
+                    app_state.error = Some(format!("Pasted text: {err}"));
+                }
+            }
+            _ => (),
+        };
+        Ok(())
+    }
+
+    fn handle_key_event(&mut self, key_event: KeyEvent, app_state: &mut AppState) {
+        if self.searching {
+            self.handle_search_key_event(key_event, app_state);
+            return;
+        }
+
+        match key_event.code {
+            KeyCode::Char('q') | KeyCode::Esc => app_state.should_exit = true,
+            KeyCode::Char('h') | KeyCode::Left => app_state.screen = Screen::Menu,
+            KeyCode::Char('j') | KeyCode::Down => self.state.select_next(),
+            KeyCode::Char('k') | KeyCode::Up => self.state.select_previous(),
+            KeyCode::Char('g') | KeyCode::Home => self.state.select_first(),
+            KeyCode::Char('G') | KeyCode::End => self.state.select_last(),
+            KeyCode::Char('r') => self.rescan(),
+            KeyCode::Char('/') => self.searching = true,
+            KeyCode::Backspace if !self.query.is_empty() => self.set_query(String::new()),
+            KeyCode::Char('l') | KeyCode::Right | KeyCode::Enter => self.select_rom(app_state),
+            _ => (),
+        }
+    }
+
+    /// Keys typed while the search input is focused edit the query.
+    fn handle_search_key_event(&mut self, key_event: KeyEvent, app_state: &mut AppState) {
+        match key_event.code {
+            KeyCode::Esc => {
+                self.searching = false;
+                self.set_query(String::new());
+            }
+            KeyCode::Enter => {
+                self.searching = false;
+                self.select_rom(app_state);
+            }
+            KeyCode::Down => self.state.select_next(),
+            KeyCode::Up => self.state.select_previous(),
+            KeyCode::Backspace => {
+                let mut query = std::mem::take(&mut self.query);
+                query.pop();
+                self.set_query(query);
+            }
+            KeyCode::Char(c) => {
+                let query = format!("{}{c}", self.query);
+                self.set_query(query);
+            }
+            _ => (),
+        }
+    }
+
+    fn rescan(&mut self) {
+        self.library.scan();
+        self.scanned = true;
+        let query = std::mem::take(&mut self.query);
+        self.set_query(query);
+    }
+
+    /// Refilter the list and select the best match.
+    fn set_query(&mut self, query: String) {
+        self.query = query;
+        self.matches = self.library.search(&self.query);
+        self.state.select((!self.matches.is_empty()).then_some(0));
+    }
+
+    fn select_rom(&mut self, app_state: &mut AppState) {
+        if let Some(entry) = self
+            .state
+            .selected()
+            .and_then(|i| self.matches.get(i))
+            .and_then(|m| self.library.entries().get(m.index))
+        {
+            app_state.rom = Some(RomSource::File(entry.path.clone()));
+            app_state.rom_config = entry.config.clone();
+            app_state.error = None;
+            app_state.screen = Screen::Game;
+        }
+    }
+}
+
+/// Rendering logic for the library
+impl LibraryScreen {
+    fn render_list(&mut self, area: Rect, buf: &mut Buffer) {
+        let title = self.library.title().unwrap_or("Library");
+        let block = Block::bordered().title(Line::raw(title).centered());
+
+        if self.library.entries().is_empty() {
+            Paragraph::new("No ROMs found. Set OXID_ROM_DIRS to your ROM directories.")
+                .block(block)
+                .centered()
+                .render(area, buf);
+            return;
+        }
+
+        let entries = self.library.entries();
+        let list = List::new(self.matches.iter().map(|m| {
+            // Highlight the characters matching the query.
+            let spans: Vec<Span> = entries[m.index]
+                .name
+                .chars()
+                .enumerate()
+                .map(|(i, c)| {
+                    if m.positions.contains(&i) {
+                        Span::styled(c.to_string(), MATCH_STYLE)
+                    } else {
+                        Span::raw(c.to_string())
+                    }
+                })
+                .collect();
+            ListItem::from(Line::from(spans))
+        }))
+        .block(block)
+        .highlight_style(SELECTED_STYLE)
+        .highlight_symbol(">")
+        .highlight_spacing(HighlightSpacing::Always);
+
+        StatefulWidget::render(list, area, buf, &mut self.state);
+    }
+
+    fn render_search(&self, area: Rect, buf: &mut Buffer) {
+        let line = if self.searching {
+            Line::from(vec![
+                Span::raw("/"),
+                Span::raw(&self.query),
+                "█".slow_blink(),
+            ])
+        } else if !self.query.is_empty() {
+            Line::from(vec![Span::raw("/"), Span::raw(&self.query)]).dim()
+        } else {
+            Line::default()
+        };
+        line.render(area, buf);
+    }
+
+    fn render_footer(&self, area: Rect, buf: &mut Buffer) {
+        if let Some(error) = &self.error
+            && !self.searching
+        {
+            Paragraph::new(error.as_str())
+                .red()
+                .centered()
+                .render(area, buf);
+            return;
+        }
+        Paragraph::new(if self.searching {
+            "Type to filter, ↓↑ to move, enter to play, esc to clear."
+        } else {
+            "Use ↓↑ to move, ← to go back, → to play, / to search, r to rescan, q to quit."
+        })
+        .centered()
+        .render(area, buf);
+    }
+}
+
+impl Widget for &mut LibraryScreen {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let [search, body, bottom] = Layout::vertical([
+            Constraint::Length(1),
+            Constraint::Fill(1),
+            Constraint::Length(1),
+        ])
+        .areas(area);
+
+        self.render_search(search, buf);
+        self.render_footer(bottom, buf);
+        self.render_list(body, buf);
+    }
+}
@@ -1,11 +1,14 @@
-use crate::app::AppState;
+use crate::app::{AppState, RomSource};
 use crate::screens::{Screen, widgets::title::Title};
+use oxid8_core::{builtin::CONTROLS_TEST_NAME, config::GameConfig};
 
-use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind};
+use crossterm::event::{
+    self, Event, KeyCode, KeyEvent, KeyEventKind, MouseButton, MouseEvent, MouseEventKind,
+};
 use ratatui::{
     Frame,
     buffer::Buffer,
-    layout::{Constraint, Flex, Layout, Rect},
+    layout::{Constraint, Flex, Layout, Position, Rect},
     style::{Modifier, Style, palette::tailwind::SLATE},
     text::Line,
     widgets::{
@@ -24,8 +27,12 @@ use std::io;
 #[derive(Default)]
 pub struct Menu {
     state: ListState,
+    /// Where the items were last drawn, for mouse clicks.
+    items: Rect,
 }
 
+const ITEMS: [&str; 5] = ["Play", "Load Rom", CONTROLS_TEST_NAME, "Debug", "Help"];
+
 const SELECTED_STYLE: Style = Style::new().bg(SLATE.c800).add_modifier(Modifier::BOLD);
 
 impl Menu {
@@ -38,11 +45,35 @@ impl Menu {
             Event::Key(key_event) if key_event.kind == KeyEventKind::Press => {
                 self.handle_key_event(key_event, app_state)
             }
+            Event::Mouse(mouse_event) => self.handle_mouse_event(mouse_event, app_state),
             _ => (),
         };
         Ok(())
     }
 
+    fn handle_mouse_event(&mut self, mouse_event: MouseEvent, app_state: &mut AppState) {
+        match mouse_event.kind {
+            MouseEventKind::Down(MouseButton::Left) => {
+                if let Some(i) = self.item_at(mouse_event.column, mouse_event.row) {
+                    self.state.select(Some(i));
+                    self.change_screen(app_state);
+                }
+            }
+            MouseEventKind::ScrollDown => self.select_next(),
+            MouseEventKind::ScrollUp => self.select_previous(),
+            _ => (),
+        }
+    }
+
+    /// The item drawn at a terminal cell.
+    fn item_at(&self, column: u16, row: u16) -> Option<usize> {
+        if !self.items.contains(Position::new(column, row)) {
+            return None;
+        }
+        let i = self.state.offset() + (row - self.items.y) as usize;
+        (i < ITEMS.len()).then_some(i)
+    }
+
     fn handle_key_event(&mut self, key_event: KeyEvent, app_state: &mut AppState) {
         match key_event.code {
             KeyCode::Char('q') | KeyCode::Esc => app_state.should_exit = true,
@@ -75,8 +106,16 @@ impl Menu {
     }
 
     fn change_screen(&mut self, app_state: &mut AppState) {
-        match self.state.selected().unwrap() {
-            0 => app_state.screen = Screen::Game,
+        match self.state.selected() {
+            Some(0) if app_state.rom.is_some() => app_state.screen = Screen::Game,
+            Some(0 | 1) => app_state.screen = Screen::Library,
+            Some(2) => {
+                app_state.rom = Some(RomSource::Builtin);
+                app_state.rom_config = GameConfig::default();
+                app_state.screen = Screen::Game;
+            }
+            Some(3) => app_state.screen = Screen::Debug,
+            Some(4) => app_state.screen = Screen::Help,
             _ => (),
         }
     }
@@ -98,22 +137,19 @@ impl Menu {
     fn render_menu(&mut self, area: Rect, buf: &mut Buffer) {
         let block = Block::new().title(Line::raw("Menu").centered());
 
-        let list = List::new([
-            ListItem::from("Play"),
-            ListItem::from("Load Rom"),
-            ListItem::from("Debug"),
-        ])
-        .block(block)
-        .highlight_style(SELECTED_STYLE)
-        .highlight_symbol(">")
-        .highlight_spacing(HighlightSpacing::Always);
+        self.items = block.inner(area);
+        let list = List::new(ITEMS.map(ListItem::from))
+            .block(block)
+            .highlight_style(SELECTED_STYLE)
+            .highlight_symbol(">")
+            .highlight_spacing(HighlightSpacing::Always);
 
         StatefulWidget::render(list, area, buf, &mut self.state);
     }
 
     fn render_footer(area: Rect, buf: &mut Buffer) {
         Paragraph::new(
-            "Use ↓↑ to move, ← to go back, → to select, g/G to go top/bottom, q to quit.",
+            "Use ↓↑ to move, ← to go back, → or click to select, g/G to go top/bottom, q to quit.",
         )
         .centered()
         .render(area, buf);
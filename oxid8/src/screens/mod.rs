@@ -4,19 +4,21 @@ use std::io;
 
 pub mod debug;
 pub mod game;
+pub mod help;
+pub mod library;
 pub mod menu;
+pub mod splash;
 pub mod widgets;
 
+#[derive(Default)]
 pub enum Screen {
     Debug,
+    #[default]
     Menu,
+    Library,
     Game,
-}
-
-impl Default for Screen {
-    fn default() -> Screen {
-        Screen::Menu
-    }
+    Help,
+    Splash,
 }
 
 pub trait ScreenTrait {
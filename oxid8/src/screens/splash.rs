@@ -0,0 +1,123 @@
+use crate::app::AppState;
+use crate::screens::Screen;
+use oxid8_core::{SCREEN_HEIGHT, SCREEN_WIDTH, builtin::Splash};
+
+use crossterm::event::{self, Event, KeyEventKind};
+use ratatui::{
+    Frame,
+    buffer::Buffer,
+    layout::{Constraint, Flex, Layout, Rect},
+    style::{Color, Stylize},
+    symbols::Marker,
+    widgets::{
+        Paragraph, Widget,
+        canvas::{Canvas, Painter, Shape},
+    },
+};
+use std::{io, time::Duration};
+
+/// A frame at 60Hz, as the splash is timed.
+const FRAME: Duration = Duration::from_nanos(1_000_000_000 / 60);
+
+/// The title and version drawn by the core at startup, see
+/// [`oxid8_core::builtin::Splash`]. Any key skips it, and the screen
+/// it was shown before follows.
+pub struct SplashScreen {
+    splash: Splash,
+    next: Screen,
+    /// Why the splash failed, shown until a key is pressed.
+    error: Option<String>,
+}
+
+impl SplashScreen {
+    /// Shows the splash, then `next`.
+    pub fn new(next: Screen) -> Self {
+        SplashScreen {
+            splash: Splash::new(env!("CARGO_PKG_VERSION")),
+            next,
+            error: None,
+        }
+    }
+
+    pub fn draw(&mut self, frame: &mut Frame) {
+        frame.render_widget(&*self, frame.area());
+    }
+
+    /// Runs a frame of the splash, waiting up to a frame for a key to
+    /// skip it.
+    pub fn handle_events(&mut self, app_state: &mut AppState) -> io::Result<()> {
+        if event::poll(FRAME)? {
+            if let Event::Key(key_event) = event::read()?
+                && key_event.kind == KeyEventKind::Press
+            {
+                self.finish(app_state);
+            }
+            return Ok(());
+        }
+        self.tick(app_state);
+        Ok(())
+    }
+
+    /// Runs a frame of the splash, moving on once it is done unless it
+    /// failed.
+    pub fn tick(&mut self, app_state: &mut AppState) {
+        if self.error.is_some() {
+            return;
+        }
+        match self.splash.frame() {
+            Ok(()) if self.splash.is_done() => self.finish(app_state),
+            Ok(()) => (),
+            Err(err) => self.error = Some(format!("Self test failed: {err}")),
+        }
+    }
+
+    /// Why the splash failed, if it did.
+    #[must_use]
+    pub fn error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+
+    fn finish(&mut self, app_state: &mut AppState) {
+        app_state.screen = std::mem::take(&mut self.next);
+    }
+}
+
+impl Shape for SplashScreen {
+    fn draw(&self, painter: &mut Painter) {
+        let screen = self.splash.screen_ref();
+        for y in 0..SCREEN_HEIGHT {
+            for x in 0..SCREEN_WIDTH {
+                if screen[x + y * SCREEN_WIDTH] {
+                    painter.paint(x, y, Color::White);
+                }
+            }
+        }
+    }
+}
+
+impl Widget for &SplashScreen {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let [screen, footer] = Layout::vertical([
+            Constraint::Length(SCREEN_HEIGHT as u16 / 2),
+            Constraint::Length(1),
+        ])
+        .spacing(1)
+        .flex(Flex::Center)
+        .areas(area);
+        let [screen] = Layout::horizontal([Constraint::Length(SCREEN_WIDTH as u16)])
+            .flex(Flex::Center)
+            .areas(screen);
+        Canvas::default()
+            .x_bounds([0.0, SCREEN_WIDTH as f64])
+            .y_bounds([0.0, SCREEN_HEIGHT as f64])
+            .marker(Marker::HalfBlock)
+            .paint(|ctx| ctx.draw(self))
+            .render(screen, buf);
+        match &self.error {
+            Some(err) => Paragraph::new(format!("{err}, press any key")).red(),
+            None => Paragraph::new("Press any key to skip").dark_gray(),
+        }
+        .centered()
+        .render(footer, buf);
+    }
+}
@@ -0,0 +1,64 @@
+//! Locations of user settings and caches.
+
+use oxid8_core::{config::ConfigStore, hotkeys::Hotkeys, states::StateStore};
+use std::{io, path::PathBuf};
+
+use crate::screens::game::HOTKEYS;
+
+/// `<config_dir>/oxid8`
+pub fn config_dir() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("oxid8"))
+}
+
+/// `<cache_dir>/oxid8`
+pub fn cache_dir() -> Option<PathBuf> {
+    dirs::cache_dir().map(|dir| dir.join("oxid8"))
+}
+
+/// `<data_dir>/oxid8/movies`
+pub fn movies_dir() -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join("oxid8").join("movies"))
+}
+
+/// `<data_dir>/oxid8/screenshots`
+pub fn screenshots_dir() -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join("oxid8").join("screenshots"))
+}
+
+/// `<data_dir>/oxid8/flights`
+pub fn flights_dir() -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join("oxid8").join("flights"))
+}
+
+/// `<data_dir>/oxid8/crashes`
+pub fn crashes_dir() -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join("oxid8").join("crashes"))
+}
+
+/// Savestates shared with the wgpu frontend in `<data_dir>/oxid8/states`.
+pub fn states() -> Option<StateStore> {
+    dirs::data_dir().map(|dir| StateStore::new(dir.join("oxid8").join("states")))
+}
+
+/// Per-game overrides in `<config_dir>/oxid8/games`.
+pub fn game_configs() -> Option<ConfigStore> {
+    config_dir().map(|dir| ConfigStore::new(dir.join("games")))
+}
+
+/// Whether to show the splash at startup, unless `splash = false` is
+/// in `defaults.toml`.
+pub fn splash() -> bool {
+    game_configs()
+        .and_then(|store| store.load_defaults().ok().flatten())
+        .and_then(|defaults| defaults.splash)
+        .unwrap_or(true)
+}
+
+/// The hotkeys in game, rebound from `<config_dir>/oxid8/hotkeys.toml`,
+/// which the wgpu frontend shares.
+pub fn hotkeys() -> io::Result<Hotkeys> {
+    match config_dir() {
+        Some(dir) => Hotkeys::load(dir.join("hotkeys.toml"), &HOTKEYS),
+        None => Ok(Hotkeys::new(&HOTKEYS)),
+    }
+}
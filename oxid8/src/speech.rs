@@ -0,0 +1,83 @@
+//! Speaking script announcements with `--speak` (experimental).
+//!
+//! Scripts describe what happens in a game with `announce`, see
+//! [`oxid8_core::script`]. With `--speak` each announcement is also
+//! passed to the system's text to speech command as its last argument:
+//! `say` on macOS and `spd-say` elsewhere, or the command in
+//! `OXID_TTS`, e.g. `OXID_TTS="espeak -s 200"`. A new announcement cuts
+//! off the one before so speech keeps up with the game.
+
+use std::{
+    env, io,
+    process::{Child, Command, Stdio},
+};
+
+/// The text to speech command used when `OXID_TTS` isn't set.
+#[cfg(target_os = "macos")]
+pub const DEFAULT_COMMAND: &str = "say";
+/// The text to speech command used when `OXID_TTS` isn't set.
+#[cfg(not(target_os = "macos"))]
+pub const DEFAULT_COMMAND: &str = "spd-say";
+
+/// Speaks text with a text to speech command.
+#[derive(Debug)]
+pub struct Speech {
+    /// The program and the arguments before the text.
+    command: Vec<String>,
+    /// The announcement being spoken.
+    speaking: Option<Child>,
+}
+
+impl Default for Speech {
+    fn default() -> Self {
+        let command = env::var("OXID_TTS").unwrap_or_else(|_| DEFAULT_COMMAND.into());
+        Speech::new(&command)
+    }
+}
+
+impl Speech {
+    /// Speaks with `command`, split on whitespace.
+    pub fn new(command: &str) -> Self {
+        Speech {
+            command: command.split_whitespace().map(String::from).collect(),
+            speaking: None,
+        }
+    }
+
+    /// Speaks `text`, cutting off anything still being spoken.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the command can't be run, e.g. when no text
+    /// to speech engine is installed.
+    pub fn say(&mut self, text: &str) -> io::Result<()> {
+        self.stop();
+        let Some((program, args)) = self.command.split_first() else {
+            return Err(io::Error::new(io::ErrorKind::NotFound, "No speech command"));
+        };
+        let child = Command::new(program)
+            .args(args)
+            .arg(text)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()?;
+        self.speaking = Some(child);
+        Ok(())
+    }
+
+    /// Stops speaking.
+    pub fn stop(&mut self) {
+        if let Some(mut child) = self.speaking.take() {
+            // Already done if it can't be killed.
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+    }
+}
+
+impl Drop for Speech {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
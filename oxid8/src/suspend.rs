@@ -0,0 +1,61 @@
+//! Suspending to the shell with `Ctrl-Z`.
+//!
+//! In raw mode the terminal sends `Ctrl-Z` as a key instead of
+//! stopping the process, so frontends restore the terminal and stop
+//! themselves with [`suspend`]. [`Resume`] listens for `SIGCONT` on a
+//! background thread, including after a stop from outside, e.g.
+//! `kill -STOP`, so the frontend can take the terminal back, redraw,
+//! and carry on from where it was without time accruing while stopped.
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use signal_hook::{
+    consts::{SIGCONT, SIGTSTP},
+    iterator::Signals,
+};
+use std::{
+    io,
+    sync::mpsc::{self, Receiver},
+    thread,
+};
+
+/// Returns true for `Ctrl-Z`.
+pub fn is_suspend_key(key_event: &KeyEvent) -> bool {
+    key_event.code == KeyCode::Char('z') && key_event.modifiers == KeyModifiers::CONTROL
+}
+
+/// Restores the terminal with `leave` and stops the process like the
+/// shell does for `Ctrl-Z`. Returns once the process is continued,
+/// which [`Resume::resumed`] then reports.
+pub fn suspend(leave: impl FnOnce() -> io::Result<()>) -> io::Result<()> {
+    leave()?;
+    #[cfg(not(windows))]
+    signal_hook::low_level::raise(SIGTSTP)?;
+    Ok(())
+}
+
+/// Reports the process being continued after a stop.
+pub struct Resume {
+    continued: Receiver<()>,
+}
+
+impl Resume {
+    /// Listens for `SIGCONT` on a background thread.
+    pub fn listen() -> io::Result<Self> {
+        let (tx, rx) = mpsc::channel();
+        let mut signals = Signals::new([SIGCONT])?;
+        thread::spawn(move || {
+            for _ in signals.forever() {
+                if tx.send(()).is_err() {
+                    break; // Receiver dropped
+                }
+            }
+        });
+        Ok(Resume { continued: rx })
+    }
+
+    /// Returns true if the process was continued since the last call.
+    pub fn resumed(&self) -> bool {
+        // Stops in quick succession resume once.
+        self.continued.try_iter().count() > 0
+    }
+}
@@ -0,0 +1,28 @@
+//! Tracing output for diagnosing timing and compatibility issues.
+//!
+//! The terminal is busy drawing the game, so spans and events are
+//! written to `<cache_dir>/oxid8/trace.log` instead, filtered by
+//! `RUST_LOG` (e.g. `RUST_LOG=oxid8_core=debug` for frame spans).
+
+use crate::settings;
+use std::{fs, io, sync::Mutex};
+use tracing_subscriber::EnvFilter;
+
+/// Install the global subscriber.
+///
+/// # Errors
+///
+/// Returns an error if the log file cannot be created.
+pub fn init() -> io::Result<()> {
+    let dir = settings::cache_dir()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "No cache directory"))?;
+    fs::create_dir_all(&dir)?;
+    let file = fs::File::create(dir.join("trace.log"))?;
+
+    tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .with_writer(Mutex::new(file))
+        .with_ansi(false)
+        .init();
+    Ok(())
+}
@@ -0,0 +1,60 @@
+#!/bin/sh
+# Fails if a release wasm artifact grows more than 5% past its budget.
+#
+# Usage: scripts/wasm-size.sh [--update]
+#
+# Builds the oxid8-wgpu and oxid8-wasm cdylibs for wasm32 with their
+# size optimized profiles, `release-wasm` and oxid8-wasm's `release`, and
+# compares their sizes with scripts/wasm-size.budget, one
+# "<name> <bytes>" line per artifact, lines starting with # ignored.
+# `--update` records the current sizes as the budget, commit it along
+# with changes that are expected to grow the binary. Without a budget
+# it fails rather than passing.
+
+set -eu
+cd "$(dirname "$0")/.."
+
+budget=scripts/wasm-size.budget
+target=wasm32-unknown-unknown
+
+cargo build --profile release-wasm --target "$target" -p oxid8-wgpu --lib
+cargo build --release --target "$target" --manifest-path oxid8-wasm/Cargo.toml
+
+sizes=$(
+    for wasm in \
+        "target/$target/release-wasm/oxid8_wgpu.wasm" \
+        "oxid8-wasm/target/$target/release/oxid8_wasm.wasm"; do
+        echo "$(basename "$wasm" .wasm) $(wc -c <"$wasm" | tr -d ' ')"
+    done
+)
+
+if [ "${1:-}" = "--update" ]; then
+    echo "$sizes" >"$budget"
+    echo "Recorded budget in $budget:"
+    cat "$budget"
+    exit 0
+fi
+
+if [ ! -f "$budget" ]; then
+    echo "No $budget, record one with --update and commit it"
+    exit 1
+fi
+
+status=0
+while read -r name size; do
+    limit=$(awk -v name="$name" '$1 == name { print $2 }' "$budget")
+    if [ -z "$limit" ]; then
+        echo "$name: $size bytes, no budget"
+        continue
+    fi
+    max=$((limit + limit / 20))
+    if [ "$size" -gt "$max" ]; then
+        echo "$name: $size bytes, over budget of $limit (+5%)"
+        status=1
+    else
+        echo "$name: $size bytes, budget $limit"
+    fi
+done <<EOF
+$sizes
+EOF
+exit $status